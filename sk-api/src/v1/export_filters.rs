@@ -18,6 +18,26 @@ pub struct ExportFilters {
     pub excluded_labels: Vec<metav1::LabelSelector>,
     #[serde(rename = "exclude_daemonsets")]
     pub exclude_daemonsets: bool,
+    #[serde(rename = "close_open_intervals", skip_serializing_if = "Option::is_none")]
+    pub close_open_intervals: Option<bool>,
+    #[serde(rename = "included_namespaces", skip_serializing_if = "Option::is_none")]
+    pub included_namespaces: Option<Vec<String>>,
+    #[serde(rename = "excluded_kinds", skip_serializing_if = "Option::is_none")]
+    pub excluded_kinds: Option<Vec<String>>,
+    #[serde(rename = "max_age", skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<i64>,
+    #[serde(rename = "dangling_refs", skip_serializing_if = "Option::is_none")]
+    pub dangling_refs: Option<DanglingRefPolicy>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub enum DanglingRefPolicy {
+    #[serde(rename = "drop")]
+    Drop,
+    #[serde(rename = "remove_reference")]
+    RemoveReference,
+    #[serde(rename = "include_dependency")]
+    IncludeDependency,
 }
 
 impl ExportFilters {
@@ -30,6 +50,11 @@ impl ExportFilters {
             excluded_namespaces,
             excluded_labels,
             exclude_daemonsets,
+            close_open_intervals: None,
+            included_namespaces: None,
+            excluded_kinds: None,
+            max_age: None,
+            dangling_refs: None,
         }
     }
 }
@@ -3,7 +3,10 @@ mod export_request;
 mod simulation_roots;
 mod simulations;
 
-pub use export_filters::ExportFilters;
+pub use export_filters::{
+    DanglingRefPolicy,
+    ExportFilters,
+};
 pub use export_request::ExportRequest;
 use k8s_openapi::apimachinery::pkg::apis::meta::v1 as metav1;
 use serde::{
@@ -81,6 +81,15 @@ pub struct SimulationSpec {
     pub duration: Option<String>,
     pub repetitions: Option<i32>,
     pub hooks: Option<SimulationHooksConfig>,
+
+    // Override the driver container's entrypoint; extra driver args are appended to the ones
+    // that the controller generates (these are required for the driver to run at all)
+    pub driver_command: Option<Vec<String>>,
+    pub driver_args: Option<Vec<String>>,
+
+    // Run the driver as this service account instead of the controller's; the service account has
+    // to exist in the driver namespace (unless the controller is creating the driver RBAC)
+    pub driver_service_account: Option<String>,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
@@ -0,0 +1,95 @@
+use sk_core::external_storage::{
+    ObjectStoreWrapper,
+    SkObjectStore,
+};
+use sk_core::prelude::*;
+use sk_store::TraceStore;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(long_help = "location of the input trace file")]
+    pub input: String,
+
+    #[arg(long_help = "location to save the compacted trace file")]
+    pub output: String,
+}
+
+pub async fn cmd(args: &Args) -> EmptyResult {
+    println!("Reading trace file: {}", args.input);
+    let data = SkObjectStore::new(&args.input)?.get().await?.to_vec();
+    let before = data.len();
+
+    println!("Compacting trace...");
+    let (compacted, dropped) = compact_trace(data)?;
+    println!("Dropped {dropped} object updates that didn't change anything");
+
+    println!("Writing compacted trace file: {}", args.output);
+    let after = compacted.len();
+    SkObjectStore::new(&args.output)?.put(compacted.into()).await?;
+
+    println!("Done! {before} bytes -> {after} bytes");
+    Ok(())
+}
+
+// See TraceStore::compact for what actually gets removed
+fn compact_trace(data: Vec<u8>) -> anyhow::Result<(Vec<u8>, usize)> {
+    let mut store = TraceStore::import(data, &None)?;
+    let dropped = store.compact();
+    Ok((store.export_all()?, dropped))
+}
+
+#[cfg(test)]
+mod test {
+    use kube::api::DynamicObject;
+    use rstest::*;
+    use sk_core::k8s::testutils::*;
+    use sk_store::{
+        TraceStorable,
+        TracerConfig,
+    };
+
+    use super::*;
+
+    // This is what objects looked like in traces from before we started stripping out all of the
+    // server-side metadata
+    fn noisy_deployment(name: &str) -> DynamicObject {
+        let mut depl = test_deployment(name);
+        depl.metadata.managed_fields = Some(vec![metav1::ManagedFieldsEntry {
+            manager: Some("kube-controller-manager".into()),
+            operation: Some("Update".into()),
+            ..Default::default()
+        }]);
+        depl.metadata.resource_version = Some("12345".into());
+        depl.metadata.uid = Some(format!("{name}-uid"));
+        depl.metadata.annotations =
+            Some([("kubectl.kubernetes.io/last-applied-configuration".into(), "{\"spec\":{}}".into())].into());
+        depl
+    }
+
+    #[rstest]
+    fn test_compact_trace() {
+        let mut store = TraceStore::new(TracerConfig::default());
+        store.create_or_update_obj(&noisy_deployment("depl1"), 0, None);
+        store.create_or_update_obj(&noisy_deployment("depl2"), 5, None);
+        store.delete_obj(&noisy_deployment("depl1"), 10);
+        let data = store.export_all().unwrap();
+
+        let (compacted, dropped) = compact_trace(data.clone()).unwrap();
+        assert_eq!(dropped, 0);
+        assert!(compacted.len() < data.len());
+
+        let compacted_store = TraceStore::import(compacted, &None).unwrap();
+        assert_eq!(compacted_store.time_bounds(), Some((0, 10)));
+        let objs: Vec<_> = compacted_store
+            .iter()
+            .flat_map(|(evt, _)| evt.applied_objs.iter().chain(evt.deleted_objs.iter()))
+            .collect();
+        assert_eq!(objs.len(), 3);
+        for obj in objs {
+            assert!(obj.metadata.managed_fields.is_none());
+            assert!(obj.metadata.resource_version.is_none());
+            assert!(obj.metadata.uid.is_none());
+            assert_eq!(obj.metadata.annotations, Some([].into()));
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use anyhow::bail;
+use clap::Subcommand;
+use serde_yaml::{
+    Mapping,
+    Value,
+};
+use sk_core::prelude::*;
+use sk_store::{
+    BackpressurePolicy,
+    OversizedObjectPolicy,
+    OwnersCachePolicy,
+    TracerConfig,
+};
+
+#[derive(Subcommand)]
+pub enum ConfigSubcommand {
+    #[command(about = "print the fully-parsed tracer config, with all defaults filled in")]
+    Show(ShowArgs),
+}
+
+#[derive(clap::Args)]
+pub struct ShowArgs {
+    #[arg(short, long, long_help = "tracer config file to load")]
+    pub config_file: String,
+}
+
+pub fn cmd(subcommand: &ConfigSubcommand) -> EmptyResult {
+    match subcommand {
+        ConfigSubcommand::Show(args) => {
+            let config = TracerConfig::load(&args.config_file)?;
+            print!("{}", effective_config_yaml(&config)?);
+        },
+    }
+    Ok(())
+}
+
+// The config is serialized into every trace, so fields that are set to their default values are
+// normally left out; here we want to see exactly what the tracer is going to do, so we put them
+// back in.  The output can be loaded as a config file and results in the same config.
+fn effective_config_yaml(config: &TracerConfig) -> anyhow::Result<String> {
+    let mut value = serde_yaml::to_value(config)?;
+    let Some(root) = value.as_mapping_mut() else {
+        bail!("tracer config did not serialize to a map");
+    };
+
+    if let Some(tracked_objects) = root.get_mut("trackedObjects").and_then(Value::as_mapping_mut) {
+        for obj_config in tracked_objects.values_mut().filter_map(Value::as_mapping_mut) {
+            fill_default(obj_config, "trackLifecycle", false.into());
+            fill_default(obj_config, "labelSelector", Value::Null);
+            fill_default(obj_config, "fieldSelector", Value::Null);
+        }
+    }
+    fill_default(root, "maxObjectsPerNamespace", Value::Null);
+    fill_default(root, "objectHistoryDepth", 0.into());
+    fill_default(root, "maxObjectSize", Value::Null);
+    fill_default(root, "oversizedObjectPolicy", serde_yaml::to_value(OversizedObjectPolicy::default())?);
+    fill_default(root, "preferredVersions", Value::Null);
+    fill_default(root, "storeChannelCapacity", Value::Null);
+    fill_default(root, "storeBackpressurePolicy", serde_yaml::to_value(BackpressurePolicy::default())?);
+    fill_default(root, "captureContainerEnv", false.into());
+    fill_default(root, "sensitiveEnvPattern", Value::Null);
+    fill_default(root, "ownersCachePolicy", serde_yaml::to_value(OwnersCachePolicy::default())?);
+    fill_default(root, "ownersCacheTtlSeconds", Value::Null);
+    fill_default(root, "heartbeatIntervalSeconds", Value::Null);
+
+    Ok(serde_yaml::to_string(&value)?)
+}
+
+fn fill_default(map: &mut Mapping, key: &str, default: Value) {
+    map.entry(key.into()).or_insert(default);
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::*;
+    use sk_core::k8s::GVK;
+
+    use super::*;
+
+    #[rstest]
+    #[case::minimal("trackedObjects:\n  apps/v1.Deployment:\n    podSpecTemplatePath: /spec/template\n")]
+    #[case::everything(
+        "
+trackedObjects:
+  apps/v1.Deployment:
+    podSpecTemplatePath: /spec/template
+    trackLifecycle: true
+    labelSelector: app=foo
+    fieldSelector: metadata.namespace!=kube-system
+maxObjectsPerNamespace: 10
+objectHistoryDepth: 3
+maxObjectSize: 4096
+oversizedObjectPolicy: skip
+preferredVersions:
+  example.com: v1
+storeChannelCapacity: 100
+storeBackpressurePolicy: drop
+ownersCachePolicy: ttl
+ownersCacheTtlSeconds: 300
+"
+    )]
+    fn test_effective_config_round_trips(#[case] contents: &str) {
+        let config: TracerConfig = serde_yaml::from_str(contents).unwrap();
+        let output = effective_config_yaml(&config).unwrap();
+        assert_eq!(serde_yaml::from_str::<TracerConfig>(&output).unwrap(), config);
+    }
+
+    #[rstest]
+    fn test_effective_config_includes_defaults() {
+        let config = TracerConfig {
+            tracked_objects: [(GVK::new("apps", "v1", "Deployment"), Default::default())].into(),
+            ..Default::default()
+        };
+        let output: Value = serde_yaml::from_str(&effective_config_yaml(&config).unwrap()).unwrap();
+
+        assert_eq!(output["objectHistoryDepth"], Value::from(0));
+        assert_eq!(output["oversizedObjectPolicy"], Value::from("truncate"));
+        assert_eq!(output["maxObjectsPerNamespace"], Value::Null);
+        assert_eq!(output["trackedObjects"]["apps/v1.Deployment"]["trackLifecycle"], Value::from(false));
+    }
+}
@@ -2,8 +2,35 @@ use kube::CustomResourceExt;
 use sk_core::prelude::*;
 
 pub fn cmd() -> EmptyResult {
-    print!("---\n{}", serde_yaml::to_string(&Simulation::crd())?);
-    print!("---\n{}", serde_yaml::to_string(&SimulationRoot::crd())?);
+    print!("{}", crd_yaml()?);
 
     Ok(())
 }
+
+fn crd_yaml() -> anyhow::Result<String> {
+    Ok(format!(
+        "---\n{}---\n{}",
+        serde_yaml::to_string(&Simulation::crd())?,
+        serde_yaml::to_string(&SimulationRoot::crd())?,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+    use rstest::*;
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[rstest]
+    fn test_crd_yaml() {
+        let crds: Vec<_> = serde_yaml::Deserializer::from_str(&crd_yaml().unwrap())
+            .map(|doc| CustomResourceDefinition::deserialize(doc).unwrap())
+            .collect();
+
+        let kinds: Vec<_> = crds.iter().map(|crd| crd.spec.names.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["Simulation", "SimulationRoot"]);
+        assert!(crds.iter().all(|crd| crd.spec.group == "simkube.io"));
+    }
+}
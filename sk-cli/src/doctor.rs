@@ -0,0 +1,408 @@
+use std::fmt;
+
+use anyhow::bail;
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use kube::api::ListParams;
+use kube::CustomResourceExt;
+use sk_core::k8s::{
+    metrics_server_available,
+    ApiSet,
+    GVK,
+};
+use sk_core::prelude::*;
+use sk_store::TracerConfig;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(
+        short,
+        long,
+        long_help = "tracer config file; if given, also check that we can list each of the tracked kinds"
+    )]
+    pub config_file: Option<String>,
+
+    #[arg(short, long, long_help = "only check permissions in this namespace")]
+    pub namespace: Option<String>,
+}
+
+// Failures mean that the tracer (or a simulation) isn't going to work at all; warnings are for
+// things that are optional, but that some features need
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug)]
+struct Check {
+    name: String,
+    status: CheckStatus,
+    detail: String,
+    hint: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &str, detail: impl Into<String>) -> Check {
+        Check {
+            name: name.into(),
+            status: CheckStatus::Pass,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn warn(name: &str, detail: impl Into<String>, hint: impl Into<String>) -> Check {
+        Check {
+            name: name.into(),
+            status: CheckStatus::Warn,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn fail(name: &str, detail: impl Into<String>, hint: impl Into<String>) -> Check {
+        Check {
+            name: name.into(),
+            status: CheckStatus::Fail,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = match self.status {
+            CheckStatus::Pass => "PASS",
+            CheckStatus::Warn => "WARN",
+            CheckStatus::Fail => "FAIL",
+        };
+        write!(f, "[{status}] {}: {}", self.name, self.detail)?;
+        if let Some(hint) = &self.hint {
+            write!(f, "\n       hint: {hint}")?;
+        }
+        Ok(())
+    }
+}
+
+pub async fn cmd(args: &Args) -> EmptyResult {
+    let config = args.config_file.as_deref().map(TracerConfig::load).transpose()?;
+    let checks = match kube::Client::try_default().await {
+        Ok(client) => run_checks(client, config.as_ref(), args.namespace.as_deref()).await,
+        Err(err) => vec![Check::fail(CLUSTER_CHECK, format!("could not create a client: {err}"), CLUSTER_HINT)],
+    };
+
+    for check in &checks {
+        println!("{check}");
+    }
+
+    let failed = checks.iter().filter(|c| c.status == CheckStatus::Fail).count();
+    if failed > 0 {
+        bail!("{failed} check(s) failed");
+    }
+    Ok(())
+}
+
+const CLUSTER_CHECK: &str = "cluster reachable";
+const CLUSTER_HINT: &str = "check that your kubeconfig points at the right cluster (try `kubectl cluster-info`)";
+
+// If we can't talk to the cluster at all, none of the other checks are going to tell us anything
+// useful, so we stop there
+async fn run_checks(client: kube::Client, config: Option<&TracerConfig>, namespace: Option<&str>) -> Vec<Check> {
+    let reachable = check_cluster_reachable(&client).await;
+    if reachable.status == CheckStatus::Fail {
+        return vec![reachable];
+    }
+
+    let mut checks = vec![reachable];
+    checks.extend(check_permissions(client.clone(), config, namespace).await);
+    checks.push(check_metrics_server(client.clone()).await);
+    checks.extend(check_crds(client).await);
+    checks
+}
+
+async fn check_cluster_reachable(client: &kube::Client) -> Check {
+    match client.apiserver_version().await {
+        Ok(info) => Check::pass(CLUSTER_CHECK, format!("connected to Kubernetes {}", info.git_version)),
+        Err(err) => Check::fail(CLUSTER_CHECK, format!("could not reach the apiserver: {err}"), CLUSTER_HINT),
+    }
+}
+
+// The pod watcher always needs to list pods, and the object watcher needs to be able to list every
+// tracked kind; we just try to list (at most one of) each of them to see if it works
+async fn check_permissions(client: kube::Client, config: Option<&TracerConfig>, namespace: Option<&str>) -> Vec<Check> {
+    let pod_api: kube::Api<corev1::Pod> = match namespace {
+        Some(ns) => kube::Api::namespaced(client.clone(), ns),
+        None => kube::Api::all(client.clone()),
+    };
+    let pod_gvk = GVK::new("", "v1", "Pod");
+    let mut checks = vec![list_check(&pod_gvk, pod_api.list(&ListParams::default().limit(1)).await.map(|_| ()))];
+
+    let Some(config) = config else { return checks };
+    let mut apiset = ApiSet::new(client);
+    if let Some(prefs) = &config.preferred_versions {
+        apiset = apiset.with_version_preferences(prefs.clone());
+    }
+
+    let mut gvks: Vec<_> = config.tracked_objects.keys().collect();
+    gvks.sort_by_key(|gvk| gvk.to_string());
+    for gvk in gvks {
+        let api = match namespace {
+            Some(ns) => apiset.namespaced_api_by_gvk(gvk, ns).await,
+            None => apiset.unnamespaced_api_by_gvk(gvk).await,
+        };
+        let check = match api {
+            Ok((api, _)) => list_check(gvk, api.list(&ListParams::default().limit(1)).await.map(|_| ())),
+            Err(err) => Check::fail(
+                &format!("can list {gvk}"),
+                format!("could not find the API for {gvk}: {err}"),
+                format!("make sure {gvk} is installed in the cluster, or remove it from trackedObjects"),
+            ),
+        };
+        checks.push(check);
+    }
+    checks
+}
+
+fn list_check(gvk: &GVK, res: Result<(), kube::Error>) -> Check {
+    let name = format!("can list {gvk}");
+    match res {
+        Ok(()) => Check::pass(&name, "ok"),
+        Err(kube::Error::Api(kube::core::ErrorResponse { code: 403, .. })) => Check::fail(
+            &name,
+            "forbidden",
+            format!("grant the current user (or the tracer's service account) list and watch permissions on {gvk}"),
+        ),
+        Err(err) => Check::fail(&name, format!("could not list {gvk}: {err}"), CLUSTER_HINT),
+    }
+}
+
+async fn check_metrics_server(client: kube::Client) -> Check {
+    const NAME: &str = "metrics-server installed";
+    const HINT: &str =
+        "install metrics-server to record pod resource usage (e.g., with `skctl snapshot --sample-usage`)";
+
+    match metrics_server_available(client).await {
+        Ok(true) => Check::pass(NAME, "metrics.k8s.io API is available"),
+        Ok(false) => Check::warn(NAME, "metrics.k8s.io API not found", HINT),
+        Err(err) => Check::warn(NAME, format!("could not query the metrics.k8s.io API: {err}"), HINT),
+    }
+}
+
+async fn check_crds(client: kube::Client) -> Vec<Check> {
+    const HINT: &str = "install the SimKube CRDs with `skctl crd | kubectl apply -f -`";
+
+    let crd_api: kube::Api<CustomResourceDefinition> = kube::Api::all(client);
+    let mut checks = vec![];
+    for (kind, crd_name) in [("Simulation", Simulation::crd_name()), ("SimulationRoot", SimulationRoot::crd_name())] {
+        let name = format!("{kind} CRD installed");
+        let check = match crd_api.get_opt(crd_name).await {
+            Ok(Some(_)) => Check::pass(&name, crd_name),
+            Ok(None) => Check::fail(&name, format!("{crd_name} not found"), HINT),
+            Err(err) => Check::fail(&name, format!("could not look up {crd_name}: {err}"), HINT),
+        };
+        checks.push(check);
+    }
+    checks
+}
+
+#[cfg(test)]
+mod test {
+    use httpmock::prelude::*;
+    use rstest::*;
+    use serde_json::json;
+    use sk_core::k8s::testutils::*;
+    use sk_store::TrackedObjectConfig;
+
+    use super::*;
+
+    fn empty_list() -> serde_json::Value {
+        json!({"metadata": {}, "items": []})
+    }
+
+    fn status_forbidden() -> serde_json::Value {
+        json!({
+            "kind": "Status",
+            "apiVersion": "v1",
+            "metadata": {},
+            "status": "Failure",
+            "reason": "Forbidden",
+            "code": 403
+        })
+    }
+
+    fn handle_version(fake_apiserver: &mut MockServerBuilder) {
+        fake_apiserver.handle(|when, then| {
+            when.method(GET).path("/version");
+            then.json_body(json!({
+                "major": "1",
+                "minor": "29",
+                "gitVersion": "v1.29.0",
+                "gitCommit": "",
+                "gitTreeState": "clean",
+                "buildDate": "",
+                "goVersion": "",
+                "compiler": "",
+                "platform": "linux/amd64",
+            }));
+        });
+    }
+
+    fn depl_config() -> TracerConfig {
+        TracerConfig {
+            tracked_objects: [(GVK::new("apps", "v1", "Deployment"), TrackedObjectConfig::default())].into(),
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_check_cluster_reachable() {
+        let (mut fake_apiserver, client) = make_fake_apiserver();
+        handle_version(&mut fake_apiserver);
+        fake_apiserver.build();
+
+        let check = check_cluster_reachable(&client).await;
+        assert_eq!(check.status, CheckStatus::Pass);
+        assert!(check.detail.contains("v1.29.0"));
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_run_checks_unreachable() {
+        let (mut fake_apiserver, client) = make_fake_apiserver();
+        fake_apiserver
+            .handle(|when, then| {
+                when.method(GET).path("/version");
+                then.status(500);
+            })
+            .build();
+
+        // Nothing else gets checked if we can't talk to the cluster
+        let checks = run_checks(client, None, None).await;
+        assert_eq!(checks.len(), 1);
+        assert_eq!(checks[0].status, CheckStatus::Fail);
+        assert!(checks[0].hint.is_some());
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_check_permissions() {
+        let (mut fake_apiserver, client) = make_fake_apiserver();
+        fake_apiserver
+            .handle(|when, then| {
+                when.method(GET).path("/api/v1/pods");
+                then.json_body(empty_list());
+            })
+            .handle(|when, then| {
+                when.method(GET).path("/apis/apps/v1");
+                then.json_body(apps_v1_discovery());
+            })
+            .handle(|when, then| {
+                when.method(GET).path("/apis/apps/v1/deployments");
+                then.json_body(empty_list());
+            })
+            .build();
+
+        let checks = check_permissions(client, Some(&depl_config()), None).await;
+        fake_apiserver.assert();
+        let names: Vec<_> = checks.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["can list v1.Pod", "can list apps/v1.Deployment"]);
+        assert!(checks.iter().all(|c| c.status == CheckStatus::Pass));
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_check_permissions_forbidden() {
+        let (mut fake_apiserver, client) = make_fake_apiserver();
+        fake_apiserver
+            .handle(|when, then| {
+                when.method(GET).path(format!("/api/v1/namespaces/{TEST_NAMESPACE}/pods"));
+                then.json_body(empty_list());
+            })
+            .handle(|when, then| {
+                when.method(GET).path("/apis/apps/v1");
+                then.json_body(apps_v1_discovery());
+            })
+            .handle(|when, then| {
+                when.method(GET)
+                    .path(format!("/apis/apps/v1/namespaces/{TEST_NAMESPACE}/deployments"));
+                then.status(403).json_body(status_forbidden());
+            })
+            .build();
+
+        let checks = check_permissions(client, Some(&depl_config()), Some(TEST_NAMESPACE)).await;
+        assert_eq!(checks[0].status, CheckStatus::Pass);
+        assert_eq!(checks[1].status, CheckStatus::Fail);
+        assert_eq!(checks[1].detail, "forbidden");
+        assert!(checks[1].hint.as_ref().unwrap().contains("apps/v1.Deployment"));
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_check_permissions_unknown_kind() {
+        let (mut fake_apiserver, client) = make_fake_apiserver();
+        fake_apiserver
+            .handle(|when, then| {
+                when.method(GET).path("/api/v1/pods");
+                then.json_body(empty_list());
+            })
+            .handle_not_found("/apis/apps/v1".into())
+            .build();
+
+        let checks = check_permissions(client, Some(&depl_config()), None).await;
+        assert_eq!(checks[1].status, CheckStatus::Fail);
+        assert!(checks[1].hint.as_ref().unwrap().contains("trackedObjects"));
+    }
+
+    #[rstest]
+    #[case::installed(200, CheckStatus::Pass)]
+    #[case::missing(404, CheckStatus::Warn)]
+    #[tokio::test]
+    async fn test_check_metrics_server(#[case] code: u16, #[case] expected: CheckStatus) {
+        let (mut fake_apiserver, client) = make_fake_apiserver();
+        fake_apiserver
+            .handle(move |when, then| {
+                when.method(GET).path("/apis/metrics.k8s.io/v1beta1/pods");
+                match code {
+                    200 => then.json_body(empty_list()),
+                    _ => then.status(code).json_body(status_not_found()),
+                };
+            })
+            .build();
+
+        assert_eq!(check_metrics_server(client).await.status, expected);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_check_crds() {
+        let (mut fake_apiserver, client) = make_fake_apiserver();
+        let sim_crd = Simulation::crd();
+        fake_apiserver
+            .handle(move |when, then| {
+                when.method(GET).path(format!(
+                    "/apis/apiextensions.k8s.io/v1/customresourcedefinitions/{}",
+                    Simulation::crd_name()
+                ));
+                then.json_body(serde_json::to_value(&sim_crd).unwrap());
+            })
+            .handle_not_found(format!(
+                "/apis/apiextensions.k8s.io/v1/customresourcedefinitions/{}",
+                SimulationRoot::crd_name()
+            ))
+            .build();
+
+        let checks = check_crds(client).await;
+        assert_eq!(checks[0].status, CheckStatus::Pass);
+        assert_eq!(checks[1].status, CheckStatus::Fail);
+        assert!(checks[1].hint.as_ref().unwrap().contains("skctl crd"));
+    }
+
+    #[rstest]
+    fn test_check_display() {
+        let check = Check::fail("can list v1.Pod", "forbidden", "grant permissions");
+        assert_eq!(check.to_string(), "[FAIL] can list v1.Pod: forbidden\n       hint: grant permissions");
+    }
+}
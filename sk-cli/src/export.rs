@@ -1,5 +1,7 @@
 use anyhow::bail;
+use clap::ValueEnum;
 use sk_api::v1::{
+    DanglingRefPolicy,
     ExportFilters,
     ExportRequest,
 };
@@ -8,7 +10,27 @@ use sk_core::external_storage::{
     SkObjectStore,
 };
 use sk_core::prelude::*;
-use sk_core::time::duration_to_ts;
+use sk_core::time::{
+    duration_to_secs,
+    duration_to_ts,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum DanglingRefs {
+    Drop,
+    RemoveReference,
+    IncludeDependency,
+}
+
+impl From<DanglingRefs> for DanglingRefPolicy {
+    fn from(d: DanglingRefs) -> DanglingRefPolicy {
+        match d {
+            DanglingRefs::Drop => DanglingRefPolicy::Drop,
+            DanglingRefs::RemoveReference => DanglingRefPolicy::RemoveReference,
+            DanglingRefs::IncludeDependency => DanglingRefPolicy::IncludeDependency,
+        }
+    }
+}
 
 #[derive(clap::Args)]
 pub struct Args {
@@ -43,6 +65,28 @@ pub struct Args {
     )]
     pub excluded_namespaces: Vec<String>,
 
+    #[arg(
+        long,
+        long_help = "mark pods that are still running at the end of the trace as finished at the end time"
+    )]
+    pub close_open_intervals: bool,
+
+    #[arg(
+        long,
+        long_help = "drop objects whose last recorded update (or deletion) happened longer than\n\
+            this long (e.g., 2h) before the end time",
+        value_parser = duration_to_secs
+    )]
+    pub max_age: Option<i64>,
+
+    #[arg(
+        long,
+        long_help = "what to do with objects that refer to (e.g., are owned by, or mount) something\n\
+            that was filtered out of the trace; by default they are exported as-is",
+        value_enum
+    )]
+    pub dangling_refs: Option<DanglingRefs>,
+
     #[arg(
         long,
         long_help = "sk-tracer server address",
@@ -60,13 +104,22 @@ pub struct Args {
 }
 
 pub async fn cmd(args: &Args) -> EmptyResult {
-    let filters = ExportFilters::new(args.excluded_namespaces.clone(), vec![], true);
+    let mut filters = ExportFilters::new(args.excluded_namespaces.clone(), vec![], true);
+    filters.close_open_intervals = Some(args.close_open_intervals);
+    filters.max_age = args.max_age;
+    filters.dangling_refs = args.dangling_refs.map(Into::into);
     let req = ExportRequest::new(args.start_time, args.end_time, args.output_path.clone(), filters);
     let endpoint = format!("{}/export", args.tracer_address);
 
     println!("exporting trace data");
     println!("start_ts = {}, end_ts = {}", args.start_time, args.end_time);
     println!("using filters:\n\texcluded_namespaces: {:?}\n\texcluded_labels: none", args.excluded_namespaces);
+    if let Some(max_age) = args.max_age {
+        println!("\tmax_age: {max_age}s");
+    }
+    if let Some(dangling_refs) = args.dangling_refs {
+        println!("\tdangling_refs: {dangling_refs:?}");
+    }
     println!("making request to {}", endpoint);
 
     let client = reqwest::Client::new();
@@ -0,0 +1,145 @@
+use std::fs;
+
+use anyhow::anyhow;
+use kube::api::DynamicObject;
+use sk_api::v1::ExportFilters;
+use sk_core::external_storage::{
+    ObjectStoreWrapper,
+    SkObjectStore,
+};
+use sk_core::jsonutils;
+use sk_core::k8s::{
+    sanitize_obj,
+    KubeResourceExt,
+};
+use sk_core::prelude::*;
+use sk_store::TraceStore;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(long_help = "location of the input trace file")]
+    pub input: String,
+
+    #[arg(short, long, long_help = "file to write the manifests to (defaults to stdout)")]
+    pub output: Option<String>,
+
+    #[arg(long, long_help = "namespaces to exclude from the output", value_delimiter = ',')]
+    pub excluded_namespaces: Vec<String>,
+
+    #[arg(long, long_help = "exclude objects owned by DaemonSets from the output")]
+    pub exclude_daemonsets: bool,
+}
+
+pub async fn cmd(args: &Args) -> EmptyResult {
+    let data = SkObjectStore::new(&args.input)?.get().await?.to_vec();
+    let store = TraceStore::import(data, &None)?;
+    let filters = ExportFilters::new(args.excluded_namespaces.clone(), vec![], args.exclude_daemonsets);
+
+    let yaml = manifests_yaml(&store, &filters)?;
+    match &args.output {
+        Some(path) => fs::write(path, yaml)?,
+        None => print!("{yaml}"),
+    }
+
+    Ok(())
+}
+
+// We only output the objects that exist at the end of the trace, since that's what you'd get if
+// you applied the whole trace in order.  Namespaces go first so the output can be applied as-is,
+// and everything else is sorted by kind and name so the output is stable from run to run.
+fn manifests_yaml(store: &TraceStore, filters: &ExportFilters) -> anyhow::Result<String> {
+    let mut objs = store
+        .objs_at_end(filters)
+        .into_iter()
+        .map(to_manifest)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    objs.sort_by_cached_key(|obj| {
+        let kind = obj.types.as_ref().map(|t| t.kind.clone()).unwrap_or_default();
+        (kind != "Namespace", kind, obj.namespaced_name())
+    });
+
+    let mut yaml = String::new();
+    for obj in objs {
+        yaml += "---\n";
+        yaml += &serde_yaml::to_string(&obj)?;
+    }
+    Ok(yaml)
+}
+
+// Strip out everything that the apiserver fills in, so that the objects can be re-created in a
+// different cluster.
+fn to_manifest(mut obj: DynamicObject) -> anyhow::Result<DynamicObject> {
+    let types = obj
+        .types
+        .clone()
+        .ok_or(anyhow!("object {} has no type information", obj.namespaced_name()))?;
+    sanitize_obj(&mut obj, &types.api_version, &types.kind);
+    if obj.data.is_object() {
+        jsonutils::patch_ext::remove("", "status", &mut obj.data)?;
+    }
+    Ok(obj)
+}
+
+#[cfg(test)]
+mod test {
+    use kube::api::TypeMeta;
+    use rstest::*;
+    use serde::Deserialize;
+    use serde_json::json;
+    use sk_store::{
+        TraceStorable,
+        TracerConfig,
+    };
+
+    use super::*;
+
+    fn test_obj(kind: &str, ns: Option<&str>, name: &str) -> DynamicObject {
+        DynamicObject {
+            types: Some(TypeMeta { api_version: "v1".into(), kind: kind.into() }),
+            metadata: metav1::ObjectMeta {
+                namespace: ns.map(|n| n.into()),
+                name: Some(name.into()),
+                resource_version: Some("1234".into()),
+                uid: Some("abcd".into()),
+                ..Default::default()
+            },
+            data: json!({"spec": {"name": name}, "status": {"ready": true}}),
+        }
+    }
+
+    #[rstest]
+    fn test_manifests_yaml() {
+        let mut store = TraceStore::new(TracerConfig::default());
+        store.create_or_update_obj(&test_obj("ServiceAccount", Some(TEST_NAMESPACE), "sa2"), 0, None);
+        store.create_or_update_obj(&test_obj("ConfigMap", Some(TEST_NAMESPACE), "cm"), 1, None);
+        store.create_or_update_obj(&test_obj("ServiceAccount", Some(TEST_NAMESPACE), "sa1"), 2, None);
+        store.create_or_update_obj(&test_obj("Namespace", None, TEST_NAMESPACE), 3, None);
+        store.create_or_update_obj(&test_obj("ConfigMap", Some("other-namespace"), "deleted"), 4, None);
+        store.delete_obj(&test_obj("ConfigMap", Some("other-namespace"), "deleted"), 5);
+
+        let yaml = manifests_yaml(&store, &Default::default()).unwrap();
+        let objs: Vec<_> = serde_yaml::Deserializer::from_str(&yaml)
+            .map(|doc| DynamicObject::deserialize(doc).unwrap())
+            .collect();
+
+        let names: Vec<_> = objs
+            .iter()
+            .map(|obj| format!("{}:{}", obj.types.as_ref().unwrap().kind, obj.namespaced_name()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                format!("Namespace:{TEST_NAMESPACE}"),
+                format!("ConfigMap:{TEST_NAMESPACE}/cm"),
+                format!("ServiceAccount:{TEST_NAMESPACE}/sa1"),
+                format!("ServiceAccount:{TEST_NAMESPACE}/sa2"),
+            ]
+        );
+        for obj in objs {
+            assert_eq!(obj.metadata.resource_version, None);
+            assert_eq!(obj.metadata.uid, None);
+            assert_eq!(obj.data.get("status"), None);
+            assert_eq!(obj.data["spec"]["name"], obj.metadata.name.unwrap());
+        }
+    }
+}
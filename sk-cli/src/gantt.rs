@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+
+use clap::ValueEnum;
+use sk_api::v1::ExportFilters;
+use sk_core::external_storage::{
+    ObjectStoreWrapper,
+    SkObjectStore,
+};
+use sk_core::prelude::*;
+use sk_store::{
+    PodLifecyclesMap,
+    TraceStorable,
+    TraceStore,
+};
+
+const SVG_ROW_HEIGHT: usize = 20;
+const SVG_CHAR_WIDTH: usize = 8;
+const SVG_PADDING: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum GanttFormat {
+    Svg,
+    Ascii,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(long_help = "location of the input trace file")]
+    pub input: String,
+
+    #[arg(short, long, long_help = "file to write the chart to (defaults to stdout)")]
+    pub out: Option<String>,
+
+    #[arg(short, long, long_help = "output format", value_enum, default_value_t = GanttFormat::Svg)]
+    pub format: GanttFormat,
+
+    #[arg(
+        short,
+        long,
+        long_help = "width of the timeline (in characters for ASCII output, or pixels for SVG output)",
+        default_value = "80",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub width: usize,
+
+    #[arg(long, long_help = "namespaces to exclude from the chart", value_delimiter = ',')]
+    pub excluded_namespaces: Vec<String>,
+
+    #[arg(long, long_help = "exclude pods owned by DaemonSets from the chart")]
+    pub exclude_daemonsets: bool,
+}
+
+// Each row in the chart is a single pod that ran at some point during the trace; we don't know the
+// names of the pods, just their owners, so the pods are labelled by owner name and launch order.
+#[derive(Clone, Debug, PartialEq)]
+struct GanttRow {
+    namespace: String,
+    label: String,
+    start_ts: i64,
+
+    // None means the pod was still running at the end of the trace
+    end_ts: Option<i64>,
+}
+
+pub async fn cmd(args: &Args) -> EmptyResult {
+    let data = SkObjectStore::new(&args.input)?.get().await?.to_vec();
+    let store = TraceStore::import(data, &None)?;
+    let filters = ExportFilters::new(args.excluded_namespaces.clone(), vec![], args.exclude_daemonsets);
+
+    let rows = build_rows(&store.pod_lifecycles(&filters));
+    let (start_ts, end_ts) = timeline_bounds(&rows, store.start_ts(), store.end_ts());
+    let chart = match args.format {
+        GanttFormat::Svg => render_svg(&rows, start_ts, end_ts, args.width),
+        GanttFormat::Ascii => render_ascii(&rows, start_ts, end_ts, args.width),
+    };
+
+    match &args.out {
+        Some(path) => fs::write(path, chart)?,
+        None => print!("{chart}"),
+    }
+
+    Ok(())
+}
+
+// Rows are grouped by namespace, and sorted by start time within each namespace; pods that never
+// started running don't have a run interval, so they don't show up in the chart.
+fn build_rows(lifecycles: &HashMap<String, PodLifecyclesMap>) -> Vec<GanttRow> {
+    let mut rows = vec![];
+    for (owner_ns_name, lifecycles_map) in lifecycles {
+        let (namespace, owner_name) = owner_ns_name.split_once('/').unwrap_or(("", owner_ns_name));
+
+        let mut intervals: Vec<_> = lifecycles_map
+            .values()
+            .flatten()
+            .filter_map(|l| Some((l.start_ts()?, l.end_ts())))
+            .collect();
+        intervals.sort();
+
+        for (i, (start_ts, end_ts)) in intervals.into_iter().enumerate() {
+            rows.push(GanttRow {
+                namespace: namespace.into(),
+                label: format!("{owner_name}-{i}"),
+                start_ts,
+                end_ts,
+            });
+        }
+    }
+
+    rows.sort_by(|r1, r2| (&r1.namespace, r1.start_ts, &r1.label).cmp(&(&r2.namespace, r2.start_ts, &r2.label)));
+    rows
+}
+
+// The chart covers the whole trace, but pods can start before the first event in the trace (or
+// end after the last one), so we widen the bounds to make sure that every bar fits.
+fn timeline_bounds(rows: &[GanttRow], trace_start_ts: Option<i64>, trace_end_ts: Option<i64>) -> (i64, i64) {
+    let start_ts = rows.iter().map(|r| r.start_ts).chain(trace_start_ts).min().unwrap_or_default();
+    let end_ts = rows
+        .iter()
+        .filter_map(|r| r.end_ts)
+        .chain(trace_end_ts)
+        .max()
+        .unwrap_or(start_ts);
+    (start_ts, end_ts)
+}
+
+// Convert a timestamp into a position along the timeline, which is `width` units wide
+fn scale(ts: i64, start_ts: i64, end_ts: i64, width: usize) -> usize {
+    if end_ts <= start_ts {
+        return 0;
+    }
+    ((ts - start_ts) as usize * width) / (end_ts - start_ts) as usize
+}
+
+fn render_ascii(rows: &[GanttRow], start_ts: i64, end_ts: i64, width: usize) -> String {
+    // The labels are indented under the namespace headings, and need a bit of space before the bars
+    let label_width = rows.iter().map(|r| r.label.len()).max().unwrap_or_default() + 4;
+
+    let mut chart = String::new();
+    let _ = writeln!(chart, "{:label_width$}|{start_ts:<w$}{end_ts:>w2$}|", "", w = width / 2, w2 = width - width / 2);
+
+    let mut current_ns = None;
+    for row in rows {
+        if current_ns != Some(&row.namespace) {
+            let _ = writeln!(chart, "{}", row.namespace);
+            current_ns = Some(&row.namespace);
+        }
+
+        // Every bar is at least one character wide so that short-lived pods are still visible;
+        // pods that are still running at the end of the trace get an arrow instead of an endpoint
+        let bar_start = scale(row.start_ts, start_ts, end_ts, width).min(width - 1);
+        let bar_end = scale(row.end_ts.unwrap_or(end_ts), start_ts, end_ts, width).clamp(bar_start + 1, width);
+        let bar_char = if row.end_ts.is_some() { "#" } else { "=" };
+        let mut bar = " ".repeat(bar_start) + &bar_char.repeat(bar_end - bar_start);
+        if row.end_ts.is_none() {
+            bar.replace_range(bar_end - 1.., ">");
+        }
+
+        let _ = writeln!(chart, "  {:w$}|{bar:width$}|", row.label, w = label_width - 2);
+    }
+    chart
+}
+
+fn render_svg(rows: &[GanttRow], start_ts: i64, end_ts: i64, width: usize) -> String {
+    let label_width = rows.iter().map(|r| r.label.len() + 4).max().unwrap_or_default() * SVG_CHAR_WIDTH;
+    let timeline_x = SVG_PADDING + label_width;
+
+    let mut body = String::new();
+    let mut y = SVG_PADDING;
+    let mut current_ns = None;
+    for row in rows {
+        if current_ns != Some(&row.namespace) {
+            y += SVG_ROW_HEIGHT;
+            let _ = writeln!(body, r#"  <text class="namespace" x="{SVG_PADDING}" y="{y}">{}</text>"#, row.namespace);
+            current_ns = Some(&row.namespace);
+        }
+
+        y += SVG_ROW_HEIGHT;
+        let x = timeline_x + scale(row.start_ts, start_ts, end_ts, width);
+        let bar_width = (timeline_x + scale(row.end_ts.unwrap_or(end_ts), start_ts, end_ts, width)).saturating_sub(x);
+        let (class, end) = match row.end_ts {
+            Some(ts) => ("finished", ts.to_string()),
+            None => ("running", "(still running)".into()),
+        };
+        let _ = writeln!(body, r#"  <text x="{}" y="{y}">{}</text>"#, SVG_PADDING + 2 * SVG_CHAR_WIDTH, row.label);
+        let _ = writeln!(
+            body,
+            r#"  <rect class="{class}" x="{x}" y="{}" width="{}" height="{}"><title>{}: {} - {end}</title></rect>"#,
+            y - SVG_ROW_HEIGHT * 3 / 4,
+            bar_width.max(1),
+            SVG_ROW_HEIGHT * 3 / 4,
+            row.label,
+            row.start_ts,
+        );
+    }
+
+    let total_width = timeline_x + width + SVG_PADDING;
+    let total_height = y + SVG_PADDING;
+    let mut chart = String::new();
+    let _ = writeln!(
+        chart,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{total_width}" height="{total_height}" font-family="monospace" font-size="12">"#
+    );
+    let _ = writeln!(chart, "  <style>.namespace {{ font-weight: bold; }} .finished {{ fill: steelblue; }} .running {{ fill: seagreen; }}</style>");
+    let _ =
+        writeln!(chart, r#"  <line x1="{timeline_x}" y1="{SVG_PADDING}" x2="{timeline_x}" y2="{y}" stroke="gray"/>"#);
+    chart += &body;
+    chart += "</svg>\n";
+    chart
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::*;
+    use sk_core::k8s::PodLifecycleData;
+
+    use super::*;
+
+    #[fixture]
+    fn lifecycles() -> HashMap<String, PodLifecyclesMap> {
+        HashMap::from([
+            (
+                "test/the-deployment".into(),
+                HashMap::from([
+                    (1, vec![PodLifecycleData::Finished(10, 60, None), PodLifecycleData::Running(50)]),
+                    (2, vec![PodLifecycleData::Finished(0, 20, Some("Completed".into()))]),
+                ]),
+            ),
+            (
+                "other/the-job".into(),
+                HashMap::from([(3, vec![PodLifecycleData::Pending(5), PodLifecycleData::Finished(40, 100, None)])]),
+            ),
+        ])
+    }
+
+    #[rstest]
+    fn test_build_rows(lifecycles: HashMap<String, PodLifecyclesMap>) {
+        let rows = build_rows(&lifecycles);
+        let summary: Vec<_> = rows
+            .iter()
+            .map(|r| (r.namespace.as_str(), r.label.as_str(), r.start_ts, r.end_ts))
+            .collect();
+
+        // The pending pod never ran, so it doesn't get a row
+        assert_eq!(
+            summary,
+            vec![
+                ("other", "the-job-0", 40, Some(100)),
+                ("test", "the-deployment-0", 0, Some(20)),
+                ("test", "the-deployment-1", 10, Some(60)),
+                ("test", "the-deployment-2", 50, None),
+            ]
+        );
+    }
+
+    #[rstest]
+    fn test_timeline_bounds(lifecycles: HashMap<String, PodLifecyclesMap>) {
+        let rows = build_rows(&lifecycles);
+        assert_eq!(timeline_bounds(&rows, Some(5), Some(80)), (0, 100));
+        assert_eq!(timeline_bounds(&[], Some(5), Some(80)), (5, 80));
+        assert_eq!(timeline_bounds(&[], None, None), (0, 0));
+    }
+
+    #[rstest]
+    fn test_render_ascii(lifecycles: HashMap<String, PodLifecyclesMap>) {
+        let rows = build_rows(&lifecycles);
+        let chart = render_ascii(&rows, 0, 100, 20);
+        let lines: Vec<_> = chart.lines().collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "                    |0                100|",
+                "other",
+                "  the-job-0         |        ############|",
+                "test",
+                "  the-deployment-0  |####                |",
+                "  the-deployment-1  |  ##########        |",
+                "  the-deployment-2  |          =========>|",
+            ]
+        );
+    }
+
+    #[rstest]
+    fn test_render_svg(lifecycles: HashMap<String, PodLifecyclesMap>) {
+        let rows = build_rows(&lifecycles);
+        let chart = render_svg(&rows, 0, 100, 200);
+
+        // One bar per pod, positioned relative to the start of the timeline (the label column is 20
+        // characters wide, plus padding)
+        let timeline_x = SVG_PADDING + 20 * SVG_CHAR_WIDTH;
+        for (class, start, width) in
+            [("finished", 80, 120), ("finished", 0, 40), ("finished", 20, 100), ("running", 100, 100)]
+        {
+            let rect = format!(r#"<rect class="{class}" x="{}" y="#, timeline_x + start);
+            assert!(chart.contains(&rect), "missing {rect} in\n{chart}");
+            assert!(chart.contains(&format!(r#"width="{width}""#)));
+        }
+        assert_eq!(chart.matches("<rect").count(), 4);
+        assert_eq!(chart.matches(r#"class="namespace""#).count(), 2);
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::bail;
+use clap::ValueEnum;
+use kube::api::DynamicObject;
+use serde::Serialize;
+use sk_core::external_storage::{
+    ObjectStoreWrapper,
+    SkObjectStore,
+};
+use sk_core::k8s::{
+    KubeResourceExt,
+    GVK,
+};
+use sk_core::prelude::*;
+use sk_store::{
+    PodLifecyclesMap,
+    TraceStorable,
+    TraceStore,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum InspectFormat {
+    Json,
+    Yaml,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(long_help = "location of the input trace file")]
+    pub input: String,
+
+    #[arg(
+        long_help = "namespaced name (<namespace>/<name>) of the object to inspect; cluster-scoped\n\
+        objects are just given by name"
+    )]
+    pub name: String,
+
+    #[arg(
+        short,
+        long,
+        long_help = "kind of the object to inspect (e.g., Deployment); only needed if there are\n\
+            multiple objects of different kinds with the same name"
+    )]
+    pub kind: Option<String>,
+
+    #[arg(short, long, long_help = "output format", value_enum, default_value_t = InspectFormat::Json)]
+    pub format: InspectFormat,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InspectOutput<'a> {
+    object: &'a DynamicObject,
+
+    // Lifecycle data for the pods owned by this object, if it has any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pod_lifecycles: Option<PodLifecyclesMap>,
+}
+
+pub async fn cmd(args: &Args) -> EmptyResult {
+    let data = SkObjectStore::new(&args.input)?.get().await?.to_vec();
+    let store = TraceStore::import(data, &None)?;
+
+    print!("{}", inspect(&store, &args.name, args.kind.as_deref(), args.format)?);
+    Ok(())
+}
+
+// We only look at the objects that exist at the end of the trace; kinds are matched
+// case-insensitively, since that's what kubectl does.
+fn inspect(store: &TraceStore, ns_name: &str, kind: Option<&str>, format: InspectFormat) -> anyhow::Result<String> {
+    let obj_kind = |obj: &DynamicObject| obj.types.as_ref().map(|t| t.kind.clone()).unwrap_or_default();
+
+    let matches: Vec<_> = store
+        .objects()
+        .filter(|obj| obj.namespaced_name() == ns_name)
+        .filter(|obj| match kind {
+            Some(k) => obj_kind(obj).eq_ignore_ascii_case(k),
+            None => true,
+        })
+        .collect();
+
+    let obj = match &matches[..] {
+        [] => bail!("no object named {ns_name} found in trace"),
+        [obj] => obj,
+        _ => {
+            let mut kinds: Vec<_> = matches.iter().map(|obj| obj_kind(obj)).collect();
+            kinds.sort();
+            bail!("multiple objects named {ns_name} found (kinds: {}); use --kind to pick one", kinds.join(", "));
+        },
+    };
+
+    // Pod lifecycle data is keyed by the owner's name, so we have to check that this is actually
+    // the kind of object whose pods we tracked, and not some other object with the same name
+    let tracks_lifecycle = GVK::from_dynamic_obj(obj).is_ok_and(|gvk| store.config().track_lifecycle_for(&gvk));
+    let output = InspectOutput {
+        object: obj,
+        pod_lifecycles: tracks_lifecycle
+            .then(|| store.pod_lifecycles(&Default::default()).remove(ns_name))
+            .flatten(),
+    };
+    Ok(match format {
+        InspectFormat::Json => serde_json::to_string_pretty(&output)? + "\n",
+        InspectFormat::Yaml => serde_yaml::to_string(&output)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use kube::api::TypeMeta;
+    use rstest::*;
+    use serde_json::json;
+    use sk_core::k8s::testutils::*;
+    use sk_core::k8s::PodLifecycleData;
+    use sk_store::{
+        TracerConfig,
+        TrackedObjectConfig,
+    };
+
+    use super::*;
+
+    #[fixture]
+    fn store(test_pod: corev1::Pod) -> TraceStore {
+        let config = TracerConfig {
+            tracked_objects: [(
+                GVK::new("apps", "v1", "Deployment"),
+                TrackedObjectConfig { track_lifecycle: true, ..Default::default() },
+            )]
+            .into(),
+            ..Default::default()
+        };
+        let mut store = TraceStore::new(config);
+
+        let mut depl = test_deployment(TEST_DEPLOYMENT);
+        depl.types = Some(TypeMeta {
+            api_version: "apps/v1".into(),
+            kind: "Deployment".into(),
+        });
+        depl.data = json!({"spec": {"replicas": 3}});
+        store.create_or_update_obj(&depl, 0, None);
+
+        // A ConfigMap with the same name as the deployment
+        let mut cm = test_deployment(TEST_DEPLOYMENT);
+        cm.types = Some(TypeMeta { api_version: "v1".into(), kind: "ConfigMap".into() });
+        cm.data = json!({"spec": {"foo": "bar"}});
+        store.create_or_update_obj(&cm, 1, None);
+
+        let owner = metav1::OwnerReference {
+            api_version: "apps/v1".into(),
+            kind: "Deployment".into(),
+            name: TEST_DEPLOYMENT.into(),
+            ..Default::default()
+        };
+        store
+            .record_pod_lifecycle(
+                &test_pod.namespaced_name(),
+                Some(test_pod.clone()),
+                vec![owner],
+                &PodLifecycleData::Finished(2, 5, None),
+            )
+            .unwrap();
+        store
+    }
+
+    #[rstest]
+    fn test_inspect_json(store: TraceStore) {
+        let ns_name = format!("{TEST_NAMESPACE}/{TEST_DEPLOYMENT}");
+        let output: serde_json::Value =
+            serde_json::from_str(&inspect(&store, &ns_name, Some("deployment"), InspectFormat::Json).unwrap()).unwrap();
+
+        assert_eq!(output["object"]["kind"], "Deployment");
+        assert_eq!(output["object"]["metadata"]["name"], TEST_DEPLOYMENT);
+        assert_eq!(output["object"]["spec"]["replicas"], 3);
+        let lifecycles = output["podLifecycles"].as_object().unwrap();
+        assert_eq!(lifecycles.len(), 1);
+        assert_eq!(lifecycles.values().next().unwrap(), &json!([{"Finished": [2, 5, null]}]));
+    }
+
+    #[rstest]
+    fn test_inspect_yaml(store: TraceStore) {
+        let ns_name = format!("{TEST_NAMESPACE}/{TEST_DEPLOYMENT}");
+        let output = inspect(&store, &ns_name, Some("ConfigMap"), InspectFormat::Yaml).unwrap();
+
+        assert!(output.contains("kind: ConfigMap"));
+        assert!(output.contains("foo: bar"));
+        assert!(!output.contains("podLifecycles"));
+    }
+
+    #[rstest]
+    fn test_inspect_ambiguous(store: TraceStore) {
+        let ns_name = format!("{TEST_NAMESPACE}/{TEST_DEPLOYMENT}");
+        let err = inspect(&store, &ns_name, None, InspectFormat::Json).unwrap_err();
+        assert!(err.to_string().contains("ConfigMap, Deployment"));
+    }
+
+    #[rstest]
+    #[case::missing_name("test-namespace/not-here", None)]
+    #[case::wrong_kind("test-namespace/the-deployment", Some("Service"))]
+    fn test_inspect_not_found(store: TraceStore, #[case] ns_name: &str, #[case] kind: Option<&str>) {
+        assert!(inspect(&store, ns_name, kind, InspectFormat::Json).is_err());
+    }
+}
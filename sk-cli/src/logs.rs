@@ -0,0 +1,198 @@
+use std::io::Write;
+use std::time::Duration;
+
+use anyhow::bail;
+use futures::{
+    AsyncBufReadExt,
+    TryStreamExt,
+};
+use kube::api::{
+    ListParams,
+    LogParams,
+};
+use kube::ResourceExt;
+use sk_core::k8s::{
+    driver_name,
+    is_terminal,
+};
+use sk_core::prelude::*;
+
+use crate::spinner::SPINNER_FRAMES;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(long_help = "name of the simulation to show driver logs for")]
+    pub name: String,
+}
+
+pub async fn cmd(args: &Args) -> EmptyResult {
+    let client = kube::Client::try_default().await?;
+    let sim_api = kube::Api::<Simulation>::all(client.clone());
+    let sim = sim_api.get(&args.name).await?;
+    let pod_api = kube::Api::<corev1::Pod>::namespaced(client, &sim.spec.driver.namespace);
+
+    let pod = wait_for_driver_pod(&sim_api, &pod_api, &args.name).await?;
+    let pod_name = pod.name_any();
+
+    // If the driver has already exited there's nothing to follow, so just dump whatever logs are
+    // left; otherwise stream them until the driver container exits (which closes the stream).
+    if is_finished(&pod) {
+        print!("{}", pod_api.logs(&pod_name, &Default::default()).await?);
+    } else {
+        let mut lines = pod_api
+            .log_stream(&pod_name, &LogParams { follow: true, ..Default::default() })
+            .await?
+            .lines();
+        while let Some(line) = lines.try_next().await? {
+            println!("{line}");
+        }
+    }
+
+    Ok(())
+}
+
+async fn wait_for_driver_pod(
+    sim_api: &kube::Api<Simulation>,
+    pod_api: &kube::Api<corev1::Pod>,
+    sim_name: &str,
+) -> anyhow::Result<corev1::Pod> {
+    let mut waited = false;
+    for frame in SPINNER_FRAMES.iter().cycle() {
+        // The pod can't serve logs until its containers have started, so we keep waiting while
+        // it's still pending (e.g., pulling the driver image)
+        if let Some(pod) = find_driver_pod(pod_api, sim_name).await? {
+            if !is_pending(&pod) {
+                if waited {
+                    eprintln!();
+                }
+                return Ok(pod);
+            }
+        } else if let Some(state) = sim_api.get(sim_name).await?.status.and_then(|s| s.state) {
+            // If the simulation is already done and there's no driver pod, it's not coming back
+            if is_terminal(&state) {
+                bail!("simulation {sim_name} is {state:?} and has no driver pod");
+            }
+        }
+
+        eprint!("\r{frame} waiting for driver pod for simulation {sim_name}...");
+        std::io::stderr().flush()?;
+        waited = true;
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+    unreachable!()
+}
+
+// The controller runs the driver as a Job named after the simulation, so the driver pods are the
+// ones labeled with that job name; if the simulation has multiple repetitions there will be one pod
+// per repetition, and we want the most recent one.
+async fn find_driver_pod(pod_api: &kube::Api<corev1::Pod>, sim_name: &str) -> anyhow::Result<Option<corev1::Pod>> {
+    let selector = format!("{}={}", JOB_NAME_LABEL_KEY, driver_name(sim_name));
+    let pods = pod_api.list(&ListParams::default().labels(&selector)).await?;
+    Ok(pods.items.into_iter().max_by_key(|pod| pod.creation_timestamp()))
+}
+
+fn pod_phase(pod: &corev1::Pod) -> Option<&str> {
+    pod.status.as_ref().and_then(|s| s.phase.as_deref())
+}
+
+fn is_pending(pod: &corev1::Pod) -> bool {
+    matches!(pod_phase(pod), None | Some("Pending" | "Unknown"))
+}
+
+fn is_finished(pod: &corev1::Pod) -> bool {
+    matches!(pod_phase(pod), Some("Succeeded" | "Failed"))
+}
+
+#[cfg(test)]
+mod test {
+    use clockabilly::DateTime;
+    use httpmock::prelude::*;
+    use rstest::*;
+    use serde_json::json;
+    use sk_api::v1::{
+        SimulationState,
+        SimulationStatus,
+    };
+    use sk_core::k8s::testutils::*;
+
+    use super::*;
+
+    fn driver_pod(name: &str, ts: i64, phase: &str) -> corev1::Pod {
+        let mut pod = test_pod(name.into());
+        pod.metadata.creation_timestamp = Some(metav1::Time(DateTime::from_timestamp(ts, 0).unwrap()));
+        pod.status = Some(corev1::PodStatus { phase: Some(phase.into()), ..Default::default() });
+        pod
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_find_driver_pod_latest() {
+        let (mut fake_apiserver, client) = make_fake_apiserver();
+        let pods = vec![driver_pod("pod-1", 1, "Succeeded"), driver_pod("pod-2", 2, "Running")];
+        fake_apiserver.handle(move |when, then| {
+            when.method(GET)
+                .path(format!("/api/v1/namespaces/{TEST_NAMESPACE}/pods"))
+                .query_param("labelSelector", format!("job-name=sk-{TEST_SIM_NAME}-driver"));
+            then.json_body(json!({
+                "kind": "PodList",
+                "metadata": {},
+                "items": pods,
+            }));
+        });
+        fake_apiserver.build();
+
+        let pod_api = kube::Api::<corev1::Pod>::namespaced(client, TEST_NAMESPACE);
+        let pod = find_driver_pod(&pod_api, TEST_SIM_NAME).await.unwrap().unwrap();
+
+        fake_apiserver.assert();
+        assert_eq!(pod.name_any(), "pod-2");
+        assert!(!is_pending(&pod));
+        assert!(!is_finished(&pod));
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_wait_for_driver_pod_sim_finished() {
+        let (mut fake_apiserver, client) = make_fake_apiserver();
+        let mut sim = test_sim();
+        sim.status = Some(SimulationStatus {
+            state: Some(SimulationState::Finished),
+            ..Default::default()
+        });
+        fake_apiserver
+            .handle(move |when, then| {
+                when.method(GET).path(format!("/api/v1/namespaces/{TEST_NAMESPACE}/pods"));
+                then.json_body(json!({
+                    "kind": "PodList",
+                    "metadata": {},
+                    "items": [],
+                }));
+            })
+            .handle(move |when, then| {
+                when.method(GET)
+                    .path(format!("/apis/simkube.io/v1/simulations/{TEST_SIM_NAME}"));
+                then.json_body_obj(&sim);
+            });
+        fake_apiserver.build();
+
+        let sim_api = kube::Api::<Simulation>::all(client.clone());
+        let pod_api = kube::Api::<corev1::Pod>::namespaced(client, TEST_NAMESPACE);
+        let res = wait_for_driver_pod(&sim_api, &pod_api, TEST_SIM_NAME).await;
+
+        fake_apiserver.assert();
+        assert!(res.is_err());
+    }
+
+    #[rstest]
+    #[case::pending("Pending", true, false)]
+    #[case::running("Running", false, false)]
+    #[case::succeeded("Succeeded", false, true)]
+    #[case::failed("Failed", false, true)]
+    fn test_pod_phase(#[case] phase: &str, #[case] pending: bool, #[case] finished: bool) {
+        let pod = driver_pod("pod", 1, phase);
+        assert_eq!(is_pending(&pod), pending);
+        assert_eq!(is_finished(&pod), finished);
+    }
+}
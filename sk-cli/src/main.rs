@@ -1,9 +1,22 @@
+mod compact;
 mod completions;
+mod config;
 mod crd;
 mod delete;
+mod doctor;
 mod export;
+mod export_yaml;
+mod gantt;
+mod inspect;
+mod logs;
+mod replay;
 mod run;
+mod scrub;
+mod sink;
 mod snapshot;
+mod spinner;
+mod split;
+mod split_by_namespace;
 mod validation;
 mod xray;
 
@@ -34,28 +47,61 @@ enum SkSubcommand {
     #[command(about = "generate shell completions for skctl")]
     Completions(completions::Args),
 
+    #[command(about = "shrink a trace by stripping out data that newer versions of SimKube don't record")]
+    Compact(compact::Args),
+
+    #[command(subcommand, about = "inspect tracer config files")]
+    Config(config::ConfigSubcommand),
+
     #[command(about = "print SimKube CRDs")]
     Crd,
 
     #[command(about = "delete a simulation")]
     Delete(delete::Args),
 
+    #[command(about = "check that the cluster is ready for tracing and running simulations")]
+    Doctor(doctor::Args),
+
     #[command(about = "export simulation trace data")]
     Export(export::Args),
 
+    #[command(about = "write the objects in a trace out as Kubernetes manifests")]
+    ExportYaml(export_yaml::Args),
+
+    #[command(about = "draw a Gantt chart of the pod lifecycles in a trace")]
+    Gantt(gantt::Args),
+
+    #[command(about = "print a single object (and its pod lifecycle data) from a trace")]
+    Inspect(inspect::Args),
+
+    #[command(about = "stream the driver logs for a simulation")]
+    Logs(logs::Args),
+
+    #[command(about = "replay a trace directly against the current cluster (does not require sk-ctrl to be running)")]
+    Replay(replay::Args),
+
     #[command(about = "run a simulation")]
     Run(run::Args),
 
+    #[command(about = "replace identifying information in a trace so it can be shared")]
+    Scrub(scrub::Args),
+
     #[command(about = "take a point-in-time snapshot of a cluster (does not require sk-tracer to be running)")]
     Snapshot(snapshot::Args),
 
+    #[command(about = "split a trace into multiple traces covering consecutive time windows")]
+    Split(split::Args),
+
+    #[command(about = "split a trace into one trace per namespace")]
+    SplitByNamespace(split_by_namespace::Args),
+
     #[command(subcommand)]
     Validate(ValidateSubcommand),
 
     #[command(about = "simkube version")]
     Version,
 
-    #[command(about = "explore or prepare trace data for simulation")]
+    #[command(about = "explore or prepare trace data for simulation", alias = "tui")]
     Xray(xray::Args),
 }
 
@@ -63,18 +109,36 @@ enum SkSubcommand {
 async fn main() -> EmptyResult {
     let args = SkCommandRoot::parse();
 
-    match &args.subcommand {
+    let res = match &args.subcommand {
         SkSubcommand::Completions(args) => completions::cmd(args, SkCommandRoot::command()),
+        SkSubcommand::Compact(args) => compact::cmd(args).await,
+        SkSubcommand::Config(subcommand) => config::cmd(subcommand),
         SkSubcommand::Crd => crd::cmd(),
+        SkSubcommand::Doctor(args) => doctor::cmd(args).await,
         SkSubcommand::Export(args) => export::cmd(args).await,
+        SkSubcommand::ExportYaml(args) => export_yaml::cmd(args).await,
         SkSubcommand::Delete(args) => delete::cmd(args).await,
+        SkSubcommand::Gantt(args) => gantt::cmd(args).await,
+        SkSubcommand::Inspect(args) => inspect::cmd(args).await,
+        SkSubcommand::Logs(args) => logs::cmd(args).await,
+        SkSubcommand::Replay(args) => replay::cmd(args).await,
         SkSubcommand::Run(args) => run::cmd(args).await,
+        SkSubcommand::Scrub(args) => scrub::cmd(args).await,
         SkSubcommand::Snapshot(args) => snapshot::cmd(args).await,
+        SkSubcommand::Split(args) => split::cmd(args).await,
+        SkSubcommand::SplitByNamespace(args) => split_by_namespace::cmd(args).await,
         SkSubcommand::Validate(subcommand) => validation::cmd(subcommand).await,
         SkSubcommand::Version => {
             println!("skctl {}", crate_version!());
             Ok(())
         },
         SkSubcommand::Xray(args) => xray::cmd(args).await,
+    };
+
+    // Some failures get their own exit status (and have already told the user what happened), so
+    // scripts can tell them apart from everything else
+    if let Some(err) = res.as_ref().err().and_then(|err| err.downcast_ref::<snapshot::SnapshotError>()) {
+        std::process::exit(err.exit_code());
     }
+    res
 }
@@ -0,0 +1,671 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::{
+    fs,
+    io,
+    thread,
+};
+
+use kube::api::DynamicObject;
+use sk_core::external_storage::{
+    ObjectStoreWrapper,
+    SkObjectStore,
+};
+use sk_core::k8s::build_client;
+use sk_core::prelude::*;
+use sk_core::time::duration_to_secs;
+use sk_store::replay::{
+    load_mutation_rules,
+    parse_remap,
+    replay_trace,
+    ApplyObserver,
+    ApplyRecord,
+    JsonlAuditSink,
+    MutationRule,
+    NamespaceMap,
+    ReplayOptions,
+    ReplayPacing,
+    ReplayTarget,
+};
+use sk_store::{
+    TraceStorable,
+    TraceStore,
+};
+use tokio::signal::unix::{
+    signal,
+    Signal,
+    SignalKind,
+};
+use tokio::sync::Notify;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(long_help = "location of the trace file to replay")]
+    pub trace_path: String,
+
+    #[arg(
+        short = 'D',
+        long,
+        long_help = "only replay this much of the trace",
+        allow_hyphen_values = true
+    )]
+    pub duration: Option<String>,
+
+    #[arg(short, long, long_help = "time multiplier for the replay", default_value = "1")]
+    pub speed: f64,
+
+    #[arg(
+        long,
+        long_help = "fire each event at the same offset from the start of the replay as it had in the\n\
+            trace, so the gaps in between events are reproduced exactly",
+        conflicts_with = "speed"
+    )]
+    pub preserve_gaps: bool,
+
+    #[arg(
+        long,
+        long_help = "never wait longer than this in between events, e.g., 5m; longer gaps in the\n\
+            trace (such as idle periods) are shortened to this",
+        value_parser = duration_to_secs
+    )]
+    pub max_sleep: Option<i64>,
+
+    #[arg(
+        long = "remap",
+        long_help = "replay objects from one namespace into another, e.g., --remap prod=sim; can be specified multiple times",
+        value_parser = parse_remap
+    )]
+    pub remaps: Vec<(String, String)>,
+
+    #[arg(
+        long,
+        long_help = "send all requests to the apiserver as dry runs; doesn't wait between events"
+    )]
+    pub dry_run: bool,
+
+    #[arg(
+        long,
+        long_help = "label every replayed object with simkube.io/simulation=<NAME>, so that everything\n\
+            the replay created can be cleaned up with, e.g., kubectl delete -l simkube.io/simulation=<NAME>"
+    )]
+    pub simulation_name: Option<String>,
+
+    #[arg(
+        long = "label",
+        long_help = "add this label to every replayed object, e.g., --label team=infra (overwriting any\n\
+            existing value); can be specified multiple times",
+        value_parser = parse_label
+    )]
+    pub labels: Vec<(String, String)>,
+
+    #[arg(
+        long,
+        long_help = "YAML file containing a list of JSON patch rules to apply to objects before they're\n\
+            created; each rule has a gvk, an optional regex to match against the object's namespaced\n\
+            name, and a list of RFC 6902 patch operations"
+    )]
+    pub mutations: Option<String>,
+
+    #[arg(
+        long,
+        long_help = "save the replay's progress to this file after every event; if the file already exists,\n\
+            resume the replay from where it left off"
+    )]
+    pub checkpoint: Option<PathBuf>,
+
+    #[arg(
+        long,
+        long_help = "schedule every replayed pod onto KWOK-managed nodes (i.e., nodes labelled type=virtual\n\
+            with the kwok-provider taint), so that they start \"running\" without any real containers"
+    )]
+    pub kwok: bool,
+
+    #[arg(
+        long,
+        long_help = "if there are fewer than this many KWOK nodes in the cluster, create stub nodes to make\n\
+            up the difference",
+        requires = "kwok",
+        default_value = "0"
+    )]
+    pub kwok_nodes: usize,
+
+    #[arg(
+        long,
+        long_help = "write a line of JSON to this file for every object the replay applies, with the time,\n\
+            GVK, namespaced name, and whether the object was created, updated, or failed to apply"
+    )]
+    pub audit_log: Option<PathBuf>,
+
+    #[arg(
+        long = "break-at",
+        long_help = "pause the replay when it gets this far into the trace, e.g., --break-at 10m, until you\n\
+            press Enter or send skctl a SIGUSR1; can be specified multiple times",
+        value_parser = duration_to_secs
+    )]
+    pub breakpoints: Vec<i64>,
+
+    #[arg(
+        long,
+        long_help = "path to the kubeconfig file for the cluster to replay the trace into\n\
+            (defaults to $KUBECONFIG or ~/.kube/config)"
+    )]
+    pub kubeconfig: Option<String>,
+
+    #[arg(long, long_help = "kubeconfig context to use (defaults to the current context)")]
+    pub context: Option<String>,
+}
+
+fn parse_label(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, val)) if !key.is_empty() => Ok((key.into(), val.into())),
+        _ => Err(format!("invalid label {s}; expected <key>=<value>")),
+    }
+}
+
+// Unlike the simulation driver, we don't move anything into virtual namespaces or hang it off a
+// SimulationRoot; the objects are applied to the cluster more-or-less as they were recorded, so
+// cleaning up afterwards is up to you.
+struct ClusterTarget {
+    ns_map: NamespaceMap,
+}
+
+impl ReplayTarget for ClusterTarget {
+    fn namespace_for(&self, ns: &str) -> String {
+        self.ns_map.map(ns)
+    }
+
+    fn build_namespace(&self, name: &str) -> corev1::Namespace {
+        corev1::Namespace {
+            metadata: metav1::ObjectMeta { name: Some(name.into()), ..Default::default() },
+            ..Default::default()
+        }
+    }
+
+    fn build_obj(&self, obj: &DynamicObject, ns: Option<&str>) -> anyhow::Result<DynamicObject> {
+        let mut vobj = obj.clone();
+        vobj.metadata.namespace = ns.map(|ns| ns.into());
+        self.ns_map.remap_references(&mut vobj);
+        Ok(vobj)
+    }
+}
+
+// Writes the audit log (if there is one), and lets you know when the replay is waiting on you
+struct ReplayStatus {
+    audit_log: Option<JsonlAuditSink>,
+    start_ts: i64,
+}
+
+impl ApplyObserver for ReplayStatus {
+    fn on_apply(&self, record: &ApplyRecord) {
+        if let Some(audit_log) = &self.audit_log {
+            audit_log.on_apply(record);
+        }
+    }
+
+    fn on_pause(&self, breakpoint: i64) {
+        println!("Paused {}s into the trace; press Enter or send SIGUSR1 to continue", breakpoint - self.start_ts);
+    }
+}
+
+async fn resume_on_signal(mut sigusr1: Signal, resume: Arc<Notify>) {
+    while sigusr1.recv().await.is_some() {
+        resume.notify_one();
+    }
+}
+
+// Reading from stdin blocks, so this gets its own thread; it doesn't stop the process from exiting
+// when the replay is done
+fn resume_on_enter(resume: Arc<Notify>) {
+    thread::spawn(move || {
+        for line in io::stdin().lines() {
+            if line.is_err() {
+                break;
+            }
+            resume.notify_one();
+        }
+    });
+}
+
+pub async fn cmd(args: &Args) -> EmptyResult {
+    let data = SkObjectStore::new(&args.trace_path)?.get().await?.to_vec();
+    let store = TraceStore::import(data, &args.duration)?;
+    let mutations = match &args.mutations {
+        Some(path) => load_mutation_rules(&fs::read_to_string(path)?)?,
+        None => vec![],
+    };
+    let client = build_client(args.kubeconfig.as_deref(), args.context.as_deref()).await?;
+    replay(client, &store, args, mutations).await
+}
+
+async fn replay(client: kube::Client, store: &TraceStore, args: &Args, mutations: Vec<MutationRule>) -> EmptyResult {
+    let target = ClusterTarget {
+        ns_map: NamespaceMap::new(None, args.remaps.clone()),
+    };
+    let mut labels: BTreeMap<_, _> = args.labels.iter().cloned().collect();
+    if let Some(sim_name) = &args.simulation_name {
+        labels.insert(SIMULATION_LABEL_KEY.into(), sim_name.clone());
+    }
+    let start_ts = store.start_ts().unwrap_or_default();
+    let observer = Arc::new(ReplayStatus {
+        audit_log: args.audit_log.as_deref().map(JsonlAuditSink::create).transpose()?,
+        start_ts,
+    });
+
+    let resume = Arc::new(Notify::new());
+    if !args.breakpoints.is_empty() {
+        tokio::spawn(resume_on_signal(signal(SignalKind::user_defined1())?, resume.clone()));
+        resume_on_enter(resume.clone());
+    }
+    let opts = ReplayOptions {
+        speed: args.speed,
+        pacing: if args.preserve_gaps { ReplayPacing::Recorded } else { ReplayPacing::Speed },
+        max_sleep: args.max_sleep,
+        dry_run: args.dry_run,
+        labels,
+        mutations,
+        checkpoint_path: args.checkpoint.clone(),
+        kwok: args.kwok,
+        kwok_nodes: args.kwok_nodes,
+        observer,
+        breakpoints: args.breakpoints.iter().map(|offset| start_ts + offset).collect(),
+        resume,
+    };
+    replay_trace(client, store, &target, &opts).await
+}
+
+#[cfg(test)]
+mod test {
+    use httpmock::Method::*;
+    use rstest::*;
+    use serde_json::json;
+    use sk_core::k8s::testutils::*;
+    use sk_core::k8s::{
+        sanitize_obj,
+        GVK,
+    };
+    use sk_store::replay::{
+        trace_hash,
+        ReplayCheckpoint,
+    };
+    use sk_store::{
+        TraceStorable,
+        TracerConfig,
+        TrackedObjectConfig,
+    };
+
+    use super::*;
+
+    fn args(remaps: Vec<(String, String)>, dry_run: bool) -> Args {
+        Args {
+            trace_path: "".into(),
+            duration: None,
+            speed: 1000.0,
+            preserve_gaps: false,
+            max_sleep: None,
+            remaps,
+            dry_run,
+            simulation_name: None,
+            labels: vec![],
+            mutations: None,
+            checkpoint: None,
+            kwok: false,
+            kwok_nodes: 0,
+            audit_log: None,
+            breakpoints: vec![],
+            kubeconfig: None,
+            context: None,
+        }
+    }
+
+    #[rstest]
+    #[case::valid("team=infra", Some(("team", "infra")))]
+    #[case::empty_value("team=", Some(("team", "")))]
+    #[case::no_key("=infra", None)]
+    #[case::no_equals("team", None)]
+    fn test_parse_label(#[case] s: &str, #[case] expected: Option<(&str, &str)>) {
+        assert_eq!(parse_label(s).ok(), expected.map(|(k, v)| (k.into(), v.into())));
+    }
+
+    #[rstest]
+    #[case::remapped(vec![(TEST_NAMESPACE.into(), "sim".into())], "sim", false)]
+    #[case::unmapped(vec![], TEST_NAMESPACE, false)]
+    #[case::dry_run(vec![], TEST_NAMESPACE, true)]
+    #[tokio::test]
+    async fn itest_replay(#[case] remaps: Vec<(String, String)>, #[case] ns: &'static str, #[case] dry_run: bool) {
+        let depl: DynamicObject = serde_json::from_value(json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": {"namespace": TEST_NAMESPACE, "name": TEST_DEPLOYMENT},
+        }))
+        .unwrap();
+        let mut store = TraceStore::new(TracerConfig::default());
+        store.create_or_update_obj(&depl, 1, None);
+        store.delete_obj(&depl, 2);
+
+        let (mut fake_apiserver, client) = make_fake_apiserver();
+        let dry_run_param = move |when: httpmock::When| {
+            if dry_run {
+                when.query_param("dryRun", "All")
+            } else {
+                when
+            }
+        };
+        fake_apiserver
+            .handle_not_found(format!("/api/v1/namespaces/{ns}"))
+            .handle(move |when, then| {
+                dry_run_param(when.method(POST).path("/api/v1/namespaces"));
+                then.json_body(json!({"kind": "Namespace", "metadata": {"name": ns}}));
+            })
+            .handle(|when, then| {
+                when.path("/apis/apps/v1");
+                then.json_body(apps_v1_discovery());
+            })
+            .handle(move |when, then| {
+                dry_run_param(
+                    when.method(PATCH)
+                        .path(format!("/apis/apps/v1/namespaces/{ns}/deployments/{TEST_DEPLOYMENT}")),
+                );
+                then.json_body(status_ok());
+            })
+            .handle(move |when, then| {
+                // kube-rs sends the delete params in the request body instead of the query string
+                let when = when
+                    .method(DELETE)
+                    .path(format!("/apis/apps/v1/namespaces/{ns}/deployments/{TEST_DEPLOYMENT}"));
+                if dry_run {
+                    when.body_contains("dryRun");
+                }
+                then.json_body(status_ok());
+            })
+            .build();
+
+        replay(client, &store, &args(remaps, dry_run), vec![]).await.unwrap();
+
+        // In a dry run the namespace doesn't really get created, so the deployment is skipped
+        let expected_hits = if dry_run { [1, 1, 0, 0, 0] } else { [1, 1, 1, 1, 1] };
+        for (i, hits) in expected_hits.into_iter().enumerate() {
+            fake_apiserver.assert_hits(i, hits);
+        }
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn itest_replay_resume_from_checkpoint() {
+        let depl: DynamicObject = serde_json::from_value(json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": {"namespace": TEST_NAMESPACE, "name": TEST_DEPLOYMENT},
+        }))
+        .unwrap();
+        let mut store = TraceStore::new(TracerConfig::default());
+        store.create_or_update_obj(&depl, 1, None);
+        store.delete_obj(&depl, 2);
+
+        // The first event (creating the deployment) was applied before the replay was interrupted,
+        // and the deployment got cleaned up out from under us in the meantime
+        let path = std::env::temp_dir().join(format!("sk-replay-checkpoint-test-{}", std::process::id()));
+        ReplayCheckpoint {
+            trace_hash: trace_hash(&store).unwrap(),
+            next_event: 1,
+            sim_ts: 2,
+        }
+        .save(&path)
+        .unwrap();
+
+        let (mut fake_apiserver, client) = make_fake_apiserver();
+        fake_apiserver
+            .handle(|when, then| {
+                when.path("/apis/apps/v1");
+                then.json_body(apps_v1_discovery());
+            })
+            .handle(|when, then| {
+                when.method(PATCH)
+                    .path(format!("/apis/apps/v1/namespaces/{TEST_NAMESPACE}/deployments/{TEST_DEPLOYMENT}"));
+                then.json_body(status_ok());
+            })
+            .handle(|when, then| {
+                when.method(DELETE)
+                    .path(format!("/apis/apps/v1/namespaces/{TEST_NAMESPACE}/deployments/{TEST_DEPLOYMENT}"));
+                then.status(404).json_body(status_not_found());
+            })
+            .build();
+
+        let mut args = args(vec![], false);
+        args.checkpoint = Some(path.clone());
+        replay(client, &store, &args, vec![]).await.unwrap();
+
+        fake_apiserver.assert_hits(1, 0);
+        fake_apiserver.assert_hits(2, 1);
+
+        // The replay finished, so there's nothing left to resume
+        assert!(!path.exists());
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn itest_replay_kwok() {
+        let depl: DynamicObject = serde_json::from_value(json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": {"namespace": TEST_NAMESPACE, "name": TEST_DEPLOYMENT},
+            "spec": {"template": {"spec": {
+                "containers": [{"name": "nginx"}],
+                "tolerations": [{"key": "dedicated", "operator": "Exists"}],
+            }}},
+        }))
+        .unwrap();
+        let mut store = TraceStore::new(TracerConfig {
+            tracked_objects: [(
+                GVK::new("apps", "v1", "Deployment"),
+                TrackedObjectConfig {
+                    pod_spec_template_path: Some("/spec/template".into()),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            ..Default::default()
+        });
+        store.create_or_update_obj(&depl, 1, None);
+
+        // There's already one KWOK node, so we only need to create one more
+        let (mut fake_apiserver, client) = make_fake_apiserver();
+        fake_apiserver
+            .handle(|when, then| {
+                when.method(GET)
+                    .path("/api/v1/nodes")
+                    .query_param("labelSelector", "type=virtual");
+                then.json_body(json!({
+                    "kind": "NodeList",
+                    "apiVersion": "v1",
+                    "metadata": {},
+                    "items": [{"metadata": {"name": "fake-node-1", "labels": {"type": "virtual"}}}],
+                }));
+            })
+            .handle(|when, then| {
+                when.method(POST).path("/api/v1/nodes").json_body_partial(
+                    json!({
+                        "metadata": {
+                            "name": "sk-kwok-node-1",
+                            "annotations": {"kwok.x-k8s.io/node": "fake"},
+                            "labels": {"type": "virtual"},
+                        },
+                        "spec": {"taints": [{"key": VIRTUAL_NODE_TOLERATION_KEY, "effect": "NoSchedule"}]},
+                    })
+                    .to_string(),
+                );
+                then.json_body(json!({"kind": "Node", "metadata": {"name": "sk-kwok-node-1"}}));
+            })
+            .handle(|when, then| {
+                when.path(format!("/api/v1/namespaces/{TEST_NAMESPACE}"));
+                then.json_body(json!({"kind": "Namespace", "metadata": {"name": TEST_NAMESPACE}}));
+            })
+            .handle(|when, then| {
+                when.path("/apis/apps/v1");
+                then.json_body(apps_v1_discovery());
+            })
+            .handle(|when, then| {
+                when.method(PATCH)
+                    .path(format!("/apis/apps/v1/namespaces/{TEST_NAMESPACE}/deployments/{TEST_DEPLOYMENT}"))
+                    .json_body_partial(
+                        json!({"spec": {"template": {"spec": {
+                            "nodeSelector": {"type": "virtual"},
+                            "tolerations": [
+                                {"key": "dedicated", "operator": "Exists"},
+                                {"key": VIRTUAL_NODE_TOLERATION_KEY, "operator": "Exists", "effect": "NoSchedule"},
+                            ],
+                        }}}})
+                        .to_string(),
+                    );
+                then.json_body(status_ok());
+            })
+            .build();
+
+        let mut args = args(vec![], false);
+        args.kwok = true;
+        args.kwok_nodes = 2;
+        replay(client, &store, &args, vec![]).await.unwrap();
+        fake_apiserver.assert();
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn itest_replay_labels() {
+        // The trace has a stale simulation label on it already, which should get overwritten
+        let depl: DynamicObject = serde_json::from_value(json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": {
+                "namespace": TEST_NAMESPACE,
+                "name": TEST_DEPLOYMENT,
+                "labels": {SIMULATION_LABEL_KEY: "old-sim", "app": "foo"},
+            },
+        }))
+        .unwrap();
+        let mut store = TraceStore::new(TracerConfig::default());
+        store.create_or_update_obj(&depl, 1, None);
+
+        let (mut fake_apiserver, client) = make_fake_apiserver();
+        fake_apiserver
+            .handle(|when, then| {
+                when.path(format!("/api/v1/namespaces/{TEST_NAMESPACE}"));
+                then.json_body(json!({"kind": "Namespace", "metadata": {"name": TEST_NAMESPACE}}));
+            })
+            .handle(|when, then| {
+                when.path("/apis/apps/v1");
+                then.json_body(apps_v1_discovery());
+            })
+            .handle(|when, then| {
+                when.method(PATCH)
+                    .path(format!("/apis/apps/v1/namespaces/{TEST_NAMESPACE}/deployments/{TEST_DEPLOYMENT}"))
+                    .json_body_partial(
+                        json!({"metadata": {"labels": {
+                            SIMULATION_LABEL_KEY: "my-sim",
+                            "team": "infra",
+                            "app": "foo",
+                        }}})
+                        .to_string(),
+                    );
+                then.json_body(status_ok());
+            })
+            .build();
+
+        let mut args = args(vec![], false);
+        args.simulation_name = Some("my-sim".into());
+        args.labels = vec![("team".into(), "infra".into())];
+        replay(client, &store, &args, vec![]).await.unwrap();
+        fake_apiserver.assert();
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn itest_replay_mutations() {
+        let depl: DynamicObject = serde_json::from_value(json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": {"namespace": TEST_NAMESPACE, "name": TEST_DEPLOYMENT},
+            "spec": {"replicas": 4},
+        }))
+        .unwrap();
+        let mut store = TraceStore::new(TracerConfig::default());
+        store.create_or_update_obj(&depl, 1, None);
+
+        let mutations = load_mutation_rules(&format!(
+            "
+- gvk: apps/v1.Deployment
+  name: {TEST_NAMESPACE}/.*
+  patch:
+    - op: replace
+      path: /spec/replicas
+      value: 2
+"
+        ))
+        .unwrap();
+
+        let (mut fake_apiserver, client) = make_fake_apiserver();
+        fake_apiserver
+            .handle(|when, then| {
+                when.path(format!("/api/v1/namespaces/{TEST_NAMESPACE}"));
+                then.json_body(json!({"kind": "Namespace", "metadata": {"name": TEST_NAMESPACE}}));
+            })
+            .handle(|when, then| {
+                when.path("/apis/apps/v1");
+                then.json_body(apps_v1_discovery());
+            })
+            .handle(|when, then| {
+                when.method(PATCH)
+                    .path(format!("/apis/apps/v1/namespaces/{TEST_NAMESPACE}/deployments/{TEST_DEPLOYMENT}"))
+                    .json_body_partial(json!({"spec": {"replicas": 2}}).to_string());
+                then.json_body(status_ok());
+            })
+            .build();
+
+        replay(client, &store, &args(vec![], false), mutations).await.unwrap();
+        fake_apiserver.assert();
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn itest_replay_sanitized_owner_refs() {
+        // The trace gets built the same way the watchers build it, so the UID and the owner
+        // reference are gone before the replay ever sees the object
+        let mut depl: DynamicObject = serde_json::from_value(json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": {
+                "namespace": TEST_NAMESPACE,
+                "name": TEST_DEPLOYMENT,
+                "uid": "old-depl-uid",
+                "ownerReferences": [{"apiVersion": "v1", "kind": "ConfigMap", "name": "owner", "uid": "old-owner-uid"}],
+            },
+        }))
+        .unwrap();
+        sanitize_obj(&mut depl, "apps/v1", "Deployment");
+        let mut store = TraceStore::new(TracerConfig::default());
+        store.create_or_update_obj(&depl, 1, None);
+
+        let (mut fake_apiserver, client) = make_fake_apiserver();
+        fake_apiserver
+            .handle(|when, then| {
+                when.path(format!("/api/v1/namespaces/{TEST_NAMESPACE}"));
+                then.json_body(json!({"kind": "Namespace", "metadata": {"name": TEST_NAMESPACE}}));
+            })
+            .handle(|when, then| {
+                when.path("/apis/apps/v1");
+                then.json_body(apps_v1_discovery());
+            })
+            .handle(|when, then| {
+                when.method(PATCH)
+                    .path(format!("/apis/apps/v1/namespaces/{TEST_NAMESPACE}/deployments/{TEST_DEPLOYMENT}"))
+                    .matches(|req| {
+                        let body = String::from_utf8_lossy(req.body.as_deref().unwrap_or_default());
+                        !body.contains("uid") && !body.contains("ownerReferences")
+                    });
+                then.json_body(status_ok());
+            })
+            .build();
+
+        replay(client, &store, &args(vec![], false), vec![]).await.unwrap();
+        fake_apiserver.assert();
+    }
+}
@@ -8,6 +8,7 @@ use sk_api::v1::{
     SimulationMetricsConfig,
     SimulationSpec,
 };
+use sk_core::k8s::build_client;
 use sk_core::prelude::*;
 
 const DRIVER_IMAGE: &str = "quay.io/appliedcomputing/sk-driver";
@@ -71,9 +72,26 @@ pub struct Args {
     #[arg(long, long_help = "namespace to launch sk-driver in", default_value = "simkube")]
     pub driver_namespace: String,
 
+    #[arg(
+        long,
+        long_help = "service account to run sk-driver as (defaults to the controller's service account)",
+        help_heading = "Driver"
+    )]
+    pub driver_service_account: Option<String>,
+
     #[arg(long, long_help = "name of file with simulation hooks")]
     pub hooks: Option<String>,
 
+    #[arg(
+        long,
+        long_help = "path to the kubeconfig file for the cluster to run the simulation in\n\
+            (defaults to $KUBECONFIG or ~/.kube/config)"
+    )]
+    pub kubeconfig: Option<String>,
+
+    #[arg(long, long_help = "kubeconfig context to use (defaults to the current context)")]
+    pub context: Option<String>,
+
     #[arg(
         long,
         long_help = "don't spawn Prometheus pod before running sim",
@@ -190,9 +208,12 @@ pub async fn cmd(args: &Args) -> EmptyResult {
             metrics: metrics_config,
             repetitions: Some(args.repetitions),
             hooks,
+            driver_command: None,
+            driver_args: None,
+            driver_service_account: args.driver_service_account.clone(),
         },
     );
-    let client = kube::Client::try_default().await?;
+    let client = build_client(args.kubeconfig.as_deref(), args.context.as_deref()).await?;
     let sim_api = kube::Api::<Simulation>::all(client.clone());
 
     sim_api.create(&Default::default(), &sim).await?;
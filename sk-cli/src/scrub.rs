@@ -0,0 +1,48 @@
+use regex::Regex;
+use sk_core::external_storage::{
+    ObjectStoreWrapper,
+    SkObjectStore,
+};
+use sk_core::prelude::*;
+use sk_store::{
+    TraceScrubber,
+    TraceStore,
+};
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(long_help = "location of the input trace file")]
+    pub input: String,
+
+    #[arg(long_help = "location to save the scrubbed trace file")]
+    pub output: String,
+
+    #[arg(
+        long,
+        long_help = "comma-separated list of regular expressions; annotations\n\
+            whose keys match any of these will be removed from the trace",
+        value_delimiter = ','
+    )]
+    pub strip_annotations: Vec<String>,
+}
+
+pub async fn cmd(args: &Args) -> EmptyResult {
+    let annotation_patterns = args
+        .strip_annotations
+        .iter()
+        .map(|p| Regex::new(p))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    println!("Reading trace file: {}", args.input);
+    let data = SkObjectStore::new(&args.input)?.get().await?.to_vec();
+    let mut store = TraceStore::import(data, &None)?;
+
+    println!("Scrubbing trace...");
+    store.scrub(&mut TraceScrubber::new(annotation_patterns))?;
+
+    println!("Writing scrubbed trace file: {}", args.output);
+    SkObjectStore::new(&args.output)?.put(store.export_all()?.into()).await?;
+
+    println!("Done!");
+    Ok(())
+}
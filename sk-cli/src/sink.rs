@@ -0,0 +1,133 @@
+use std::fs::File;
+use std::io::Write;
+
+use async_trait::async_trait;
+use object_store::ObjectStoreScheme;
+use sk_core::errors::*;
+use sk_core::external_storage::{
+    ObjectStoreWrapper,
+    SkObjectStore,
+};
+
+// A TraceSink is somewhere we can write trace data to; data can be written in multiple chunks, and
+// isn't guaranteed to be persisted until `finish` is called.  Which sink gets used is determined
+// by the scheme of the output location (see `sink_for`).
+#[async_trait]
+pub trait TraceSink: Send {
+    async fn write(&mut self, data: &[u8]) -> EmptyResult;
+    async fn finish(self: Box<Self>) -> EmptyResult;
+}
+
+pub struct FileSink {
+    file: File,
+}
+
+impl FileSink {
+    pub fn create(path: &str) -> anyhow::Result<FileSink> {
+        Ok(FileSink { file: File::create(path)? })
+    }
+}
+
+#[async_trait]
+impl TraceSink for FileSink {
+    async fn write(&mut self, data: &[u8]) -> EmptyResult {
+        Ok(self.file.write_all(data)?)
+    }
+
+    async fn finish(mut self: Box<Self>) -> EmptyResult {
+        self.file.flush()?;
+        Ok(self.file.sync_all()?)
+    }
+}
+
+// The object store API doesn't let us append to an object, so for cloud storage we buffer all of
+// the data in memory and upload it in one go when the sink is finished.
+pub struct ObjectStoreSink {
+    store: Box<dyn ObjectStoreWrapper + Send + Sync>,
+    buf: Vec<u8>,
+}
+
+impl ObjectStoreSink {
+    pub fn new(store: Box<dyn ObjectStoreWrapper + Send + Sync>) -> ObjectStoreSink {
+        ObjectStoreSink { store, buf: vec![] }
+    }
+}
+
+#[async_trait]
+impl TraceSink for ObjectStoreSink {
+    async fn write(&mut self, data: &[u8]) -> EmptyResult {
+        self.buf.extend_from_slice(data);
+        Ok(())
+    }
+
+    async fn finish(self: Box<Self>) -> EmptyResult {
+        self.store.put(self.buf.into()).await
+    }
+}
+
+// Bare paths and file:// URLs are written to the local filesystem; s3://, gs://, and azure:// URLs
+// are uploaded to the corresponding cloud provider (see external_storage.rs for how to set up
+// credentials).
+pub fn sink_for(output: &str) -> anyhow::Result<Box<dyn TraceSink>> {
+    let store = SkObjectStore::new(output)?;
+    match store.scheme() {
+        ObjectStoreScheme::Local => Ok(Box::new(FileSink::create(output.strip_prefix("file://").unwrap_or(output))?)),
+        ObjectStoreScheme::AmazonS3 | ObjectStoreScheme::GoogleCloudStorage | ObjectStoreScheme::MicrosoftAzure => {
+            Ok(Box::new(ObjectStoreSink::new(Box::new(store))))
+        },
+        scheme => bail!("unsupported output location {output} (scheme {scheme:?})"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use rstest::*;
+    use sk_core::external_storage::MockObjectStoreWrapper;
+
+    use super::*;
+
+    fn tmp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("sk-sink-test-{name}-{}", std::process::id()));
+        path.to_str().unwrap().into()
+    }
+
+    #[rstest]
+    #[case::bare_path(false)]
+    #[case::file_url(true)]
+    #[tokio::test]
+    async fn test_file_sink(#[case] use_url: bool) {
+        let path = tmp_path(&format!("file-{use_url}"));
+        let output = if use_url { format!("file://{path}") } else { path.clone() };
+
+        let mut sink = sink_for(&output).unwrap();
+        sink.write(b"foo").await.unwrap();
+        sink.write(b"bar").await.unwrap();
+        sink.finish().await.unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"foobar");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_object_store_sink() {
+        let mut store = MockObjectStoreWrapper::new();
+        let _ = store
+            .expect_put()
+            .withf(|data| data.as_ref() == b"foobar")
+            .returning(|_| Ok(()))
+            .once();
+
+        let mut sink = Box::new(ObjectStoreSink::new(Box::new(store)));
+        sink.write(b"foo").await.unwrap();
+        sink.write(b"bar").await.unwrap();
+        sink.finish().await.unwrap();
+    }
+
+    #[rstest]
+    fn test_sink_for_unsupported_scheme() {
+        assert!(sink_for("memory:///trace.out").is_err());
+    }
+}
@@ -1,25 +1,132 @@
-use std::fs::File;
-use std::io::Write;
+use std::cmp::min;
+use std::fmt::Display;
+use std::fs;
+use std::future::Future;
+use std::io::{
+    self,
+    IsTerminal,
+    Write,
+};
+use std::ops::Not;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::sync::mpsc::{
+    Receiver,
+    RecvTimeoutError,
+};
 use std::sync::{
     Arc,
     Mutex,
+    MutexGuard,
+};
+use std::time::{
+    Duration,
+    Instant,
 };
 
+use anyhow::bail;
+use chrono::DateTime;
+use clap::ValueEnum;
 use clockabilly::{
     Clockable,
     UtcClock,
 };
+use kube::config::Kubeconfig;
+use object_store::ObjectStoreScheme;
+use serde::Serialize;
 use sk_api::v1::ExportFilters;
-use sk_core::k8s::ApiSet;
+use sk_core::errors::*;
+use sk_core::external_storage::{
+    ObjectStoreWrapper,
+    SkObjectStore,
+};
+use sk_core::k8s::{
+    list_pod_events,
+    list_pod_usage,
+    ApiSet,
+    DiscoveryCache,
+    RateLimiter,
+    GVK,
+};
+use sk_core::logging;
 use sk_core::prelude::*;
+use sk_store::encryption::{
+    encrypt_trace,
+    TraceKey,
+};
 use sk_store::watchers::{
     DynObjWatcher,
     PodWatcher,
 };
 use sk_store::{
+    TraceFormat,
     TraceStore,
     TracerConfig,
 };
+use tokio::signal::unix::{
+    signal,
+    Signal,
+    SignalKind,
+};
+use tokio::time::{
+    sleep,
+    timeout,
+};
+
+use crate::sink::{
+    sink_for,
+    TraceSink,
+};
+use crate::spinner::SPINNER_FRAMES;
+
+// These namespaces are excluded from every snapshot unless --include-system-namespaces is set
+const SYSTEM_NAMESPACES: [&str; 3] = ["kube-node-lease", "kube-public", "kube-system"];
+
+const CONNECT_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const CONNECT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+// Exit status for a snapshot that hit --timeout; the (partial) trace is still written first
+const SNAPSHOT_TIMEOUT_EXIT_CODE: i32 = 3;
+
+err_impl! {SnapshotError,
+    #[error("snapshot timed out after {0}s")]
+    TimedOut(u64),
+}
+
+impl SnapshotError {
+    // A timed-out snapshot has already written its trace and printed a warning by the time this
+    // gets back to main, so main just needs to know what status to exit with
+    pub(crate) fn exit_code(&self) -> i32 {
+        match self {
+            SnapshotError::TimedOut(_) => SNAPSHOT_TIMEOUT_EXIT_CODE,
+        }
+    }
+}
+
+// How often the progress line gets redrawn while we wait for the watchers
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+// Upper bounds (in seconds) of the buckets in the export duration histogram
+const EXPORT_DURATION_BUCKETS: [f64; 7] = [0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0];
+
+// bincode isn't an option here, because it can't deserialize the arbitrary JSON that the objects
+// in the trace are stored as
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Serialization {
+    Msgpack,
+    Json,
+}
+
+impl From<Serialization> for TraceFormat {
+    fn from(s: Serialization) -> TraceFormat {
+        match s {
+            Serialization::Msgpack => TraceFormat::Msgpack,
+            Serialization::Json => TraceFormat::Json,
+        }
+    }
+}
 
 #[derive(clap::Args)]
 pub struct Args {
@@ -28,60 +135,1451 @@ pub struct Args {
 
     #[arg(
         long,
-        long_help = "namespaces to exclude from the snapshot",
+        long_help = "namespaces to exclude from the snapshot (in addition to the system namespaces)",
         value_delimiter = ',',
-        default_value = "cert-manager,kube-system,local-path-storage,monitoring,simkube"
+        default_value = "cert-manager,local-path-storage,monitoring,simkube"
     )]
     pub excluded_namespaces: Vec<String>,
 
     #[arg(
         short,
         long,
-        long_help = "location to save exported trace",
+        long_help = "only snapshot objects and pods in this namespace (cluster-scoped objects are still\n\
+            included); this only requires namespace-scoped permissions"
+    )]
+    pub namespace: Option<String>,
+
+    #[arg(
+        long,
+        long_help = "include the system namespaces (kube-node-lease, kube-public, kube-system) in the snapshot"
+    )]
+    pub include_system_namespaces: bool,
+
+    #[arg(
+        long,
+        long_help = "leave objects of this kind (e.g., events.k8s.io/v1.Event) out of the snapshot, even if\n\
+            it's in the config's trackedObjects; can be given multiple times"
+    )]
+    pub exclude_kinds: Vec<GVK>,
+
+    #[arg(
+        short,
+        long,
+        long_help = "location to save exported trace; can be a local path or a file://, s3://, gs://,\n\
+            or azure:// URL",
         default_value = "trace.out"
     )]
     pub output: String,
+
+    #[arg(
+        long,
+        long_help = "build the output location from this template instead of using --output, e.g.,\n\
+            {cluster}/{date}/trace.out; {cluster} is the cluster in the current kube context, {date}\n\
+            and {timestamp} are the (UTC) date and unix time that the snapshot started, and {seq} is the\n\
+            first number (counting from 0) that doesn't overwrite an existing file",
+        conflicts_with = "output"
+    )]
+    pub output_template: Option<String>,
+
+    #[arg(
+        long,
+        long_help = "encrypt the trace with the (hex-encoded, 256-bit) key in this file; if this isn't\n\
+            given, the key is read from the SK_TRACE_KEY environment variable (if set)"
+    )]
+    pub key_file: Option<String>,
+
+    #[arg(
+        long,
+        long_help = "record current pod CPU/memory usage from the metrics.k8s.io API (requires metrics-server)"
+    )]
+    pub sample_usage: bool,
+
+    #[arg(
+        long,
+        long_help = "record Kubernetes Events (e.g., scheduling, image pulls, backoffs) that\n\
+            reference tracked pods"
+    )]
+    pub capture_events: bool,
+
+    #[arg(
+        long,
+        long_help = "mark pods that are still running when the snapshot is taken as finished at the\n\
+            snapshot time"
+    )]
+    pub close_open_intervals: bool,
+
+    #[arg(
+        long,
+        long_help = "how long to wait (in seconds) for each attempt to connect to the cluster"
+    )]
+    pub connect_timeout: Option<u64>,
+
+    #[arg(
+        long,
+        long_help = "number of times to retry connecting to the cluster (with backoff) before giving up",
+        default_value = "0"
+    )]
+    pub connect_retries: u32,
+
+    #[arg(
+        long,
+        long_help = "maximum number of requests per second to make to the apiserver while\n\
+            looking up pod owners or listing events (unlimited by default)"
+    )]
+    pub max_qps: Option<f64>,
+
+    #[arg(
+        long,
+        long_help = "number of requests that can be made at once before --max-qps kicks in",
+        default_value = "10"
+    )]
+    pub burst: u32,
+
+    #[arg(
+        long,
+        long_help = "save API discovery results to disk and re-use them for this many seconds on later\n\
+            runs against the same cluster (off by default)",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub discovery_cache_ttl: Option<u64>,
+
+    #[arg(
+        long,
+        long_help = "ignore any cached API discovery results and look everything up again",
+        requires = "discovery_cache_ttl"
+    )]
+    pub refresh_discovery_cache: bool,
+
+    #[arg(
+        long,
+        long_help = "periodically save the state of the snapshot to this file, so that it can be\n\
+            picked up again with --resume if the snapshot is interrupted"
+    )]
+    pub checkpoint: Option<String>,
+
+    #[arg(
+        long,
+        long_help = "how often (in seconds) to write the checkpoint file",
+        default_value = "60",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub checkpoint_interval: u64,
+
+    #[arg(long, long_help = "resume an interrupted snapshot from a checkpoint file")]
+    pub resume: Option<String>,
+
+    #[arg(
+        long,
+        long_help = "give up on the snapshot after this many seconds (counting from when skctl starts);\n\
+            whatever has been collected so far is exported, and skctl exits with status 3.  If the\n\
+            timeout fires before the watchers start, there's nothing to export, so skctl just fails",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub timeout: Option<u64>,
+
+    #[arg(
+        long,
+        long_help = "stop waiting for each watcher's initial sync after this many seconds, and carry on\n\
+            with whatever it has collected so far (unlike --timeout, this doesn't count as a failure)",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub watch_timeout_secs: Option<u64>,
+
+    #[arg(
+        long,
+        long_help = "format to write the trace in; json traces are bigger, but can be read by other tools",
+        value_enum,
+        default_value = "msgpack"
+    )]
+    pub serialization: Serialization,
+
+    #[arg(short, long, long_help = "don't print any status messages")]
+    pub quiet: bool,
+
+    #[arg(
+        short,
+        long,
+        long_help = "log level (written to stderr, and not affected by --quiet); at the default level,\n\
+            this logs how long each export took and how big it was",
+        default_value = "info"
+    )]
+    pub verbosity: String,
+
+    #[arg(
+        long,
+        long_help = "print a JSON summary of the snapshot to stdout when done\n\
+            (status messages are printed to stderr instead)"
+    )]
+    pub json: bool,
+}
+
+// Status messages are for humans, so they can be turned off entirely; if we're printing a JSON
+// summary at the end, they go to stderr instead so that stdout only has the JSON on it.
+struct StatusPrinter {
+    quiet: bool,
+    to_stderr: bool,
+}
+
+impl StatusPrinter {
+    fn new(args: &Args) -> StatusPrinter {
+        StatusPrinter { quiet: args.quiet, to_stderr: args.json }
+    }
+
+    fn say(&self, msg: impl Display) {
+        if self.quiet {
+            return;
+        }
+
+        if self.to_stderr {
+            eprintln!("{msg}");
+        } else {
+            println!("{msg}");
+        }
+    }
+
+    // The progress line gets redrawn in place, which only makes sense if there's a person watching;
+    // if the output is going to a file or a pipe, it would just fill it up with junk.
+    fn is_interactive(&self) -> bool {
+        !self.quiet && if self.to_stderr { io::stderr().is_terminal() } else { io::stdout().is_terminal() }
+    }
+
+    // Overwrite the current line (without a newline at the end); an empty message clears it
+    fn redraw(&self, msg: impl Display) {
+        let line = format!("\r\x1b[2K{msg}");
+        // If we can't write to the terminal there's not much else we can do about it
+        let _ = if self.to_stderr {
+            io::stderr().write_all(line.as_bytes()).and_then(|_| io::stderr().flush())
+        } else {
+            io::stdout().write_all(line.as_bytes()).and_then(|_| io::stdout().flush())
+        };
+    }
+}
+
+// A point-in-time view of how much the watchers have collected so far
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct SnapshotProgress {
+    object_count: usize,
+    pod_count: usize,
+}
+
+impl SnapshotProgress {
+    // Both counts are read under the same lock, so they're always consistent with each other; the
+    // lock is released right away so we don't hold up the watchers.
+    fn read(store: &Mutex<TraceStore>) -> SnapshotProgress {
+        let store = lock_store(store);
+        SnapshotProgress {
+            object_count: store.object_count(),
+            pod_count: store.pod_count(),
+        }
+    }
+
+    // We don't know how long the watchers will take, but if there's a --timeout, we know they
+    // won't take longer than that, so that's what the ETA is based on.
+    fn render(&self, frame: usize, elapsed: Duration, maybe_timeout: Option<Duration>) -> String {
+        let spinner = SPINNER_FRAMES[frame % SPINNER_FRAMES.len()];
+        let timing = match maybe_timeout {
+            Some(t) => format!("{}s elapsed, at most {}s left", elapsed.as_secs(), t.saturating_sub(elapsed).as_secs()),
+            None => format!("{}s elapsed", elapsed.as_secs()),
+        };
+        format!(
+            "{spinner} Waiting for watchers: {} objects, {} pods collected so far ({timing})",
+            self.object_count, self.pod_count
+        )
+    }
+}
+
+// Runs until it gets aborted; the caller is responsible for clearing the line afterwards.  The
+// elapsed time is counted from when the command started, same as the timeout.
+async fn show_progress(
+    store: Arc<Mutex<TraceStore>>,
+    status: StatusPrinter,
+    started: Instant,
+    maybe_timeout: Option<Duration>,
+) {
+    for frame in 0.. {
+        let progress = SnapshotProgress::read(&store);
+        status.redraw(progress.render(frame, started.elapsed(), maybe_timeout));
+        sleep(PROGRESS_INTERVAL).await;
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SnapshotSummary {
+    output_path: String,
+    object_count: usize,
+    byte_size: usize,
+    duration_secs: f64,
+
+    #[serde(skip_serializing_if = "<&bool>::not")]
+    timed_out: bool,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    incomplete_watchers: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    export_metrics: Option<ExportMetrics>,
+}
+
+// How long exporting the store takes, and how big the exported trace is, for capacity planning of
+// the snapshot tooling.  skctl doesn't run long enough to have a metrics endpoint, so these are
+// reported in the JSON summary instead.  Every export counts, including intermediate ones that we
+// do on SIGUSR1.  Like a Prometheus histogram, the buckets are cumulative: each one counts all of
+// the exports that took at most that long, and anything slower than the last bucket is only
+// included in the total count.  The byte size is a gauge, i.e., the size of the latest export.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportMetrics {
+    duration_buckets: Vec<(f64, u64)>,
+    duration_sum_secs: f64,
+    count: u64,
+    byte_size: usize,
+}
+
+impl ExportMetrics {
+    fn new() -> ExportMetrics {
+        ExportMetrics {
+            duration_buckets: EXPORT_DURATION_BUCKETS.iter().map(|le| (*le, 0)).collect(),
+            duration_sum_secs: 0.0,
+            count: 0,
+            byte_size: 0,
+        }
+    }
+
+    fn record(&mut self, duration: Duration, byte_size: usize) {
+        let secs = duration.as_secs_f64();
+        for (le, count) in &mut self.duration_buckets {
+            if secs <= *le {
+                *count += 1;
+            }
+        }
+        self.duration_sum_secs += secs;
+        self.count += 1;
+        self.byte_size = byte_size;
+    }
+}
+
+// Everything we need to export the store, which happens once at the end of the snapshot, and
+// possibly a few more times in the middle (on SIGUSR1)
+struct SnapshotExporter {
+    filters: ExportFilters,
+    format: TraceFormat,
+    key: Option<TraceKey>,
+    metrics: Mutex<ExportMetrics>,
+}
+
+impl SnapshotExporter {
+    fn new(filters: ExportFilters, format: TraceFormat, key: Option<TraceKey>) -> SnapshotExporter {
+        SnapshotExporter {
+            filters,
+            format,
+            key,
+            metrics: Mutex::new(ExportMetrics::new()),
+        }
+    }
+
+    // Same as lock_store: a panic while the metrics were locked doesn't leave them in a state that
+    // we can't keep using, and they're not worth failing (or skipping) an export over
+    fn metrics(&self) -> MutexGuard<'_, ExportMetrics> {
+        self.metrics.lock().unwrap_or_else(|poisoned| {
+            self.metrics.clear_poison();
+            poisoned.into_inner()
+        })
+    }
 }
 
 pub async fn cmd(args: &Args) -> EmptyResult {
-    println!("Reading config from {}...", args.config_file);
-    let config = TracerConfig::load(&args.config_file)?;
+    if args.max_qps.is_some_and(|qps| qps <= 0.0) {
+        bail!("--max-qps must be positive");
+    }
+    logging::setup_stderr(&args.verbosity);
 
-    println!("Connecting to kubernetes cluster...");
-    let client = kube::Client::try_default().await?;
-    let mut apiset = ApiSet::new(client.clone());
+    // Load the key up front so that we don't spend a bunch of time on the snapshot if it's bad
+    let key = TraceKey::load(args.key_file.as_deref())?;
+
+    let started = Instant::now();
+    let deadline = args.timeout.map(|t| started + Duration::from_secs(t));
+    let status = StatusPrinter::new(args);
+    let output = match &args.output_template {
+        Some(template) => expand_output_template(template, &current_cluster_name(), UtcClock.now_ts())?,
+        None => args.output.clone(),
+    };
 
-    println!("Loading snapshot into store...");
-    let store = Arc::new(Mutex::new(TraceStore::new(config.clone())));
-    let (dyn_obj_watcher, do_ready_rx) =
-        DynObjWatcher::new(store.clone(), &mut apiset, &config.tracked_objects).await?;
-    let (pod_watcher, pod_ready_rx) = PodWatcher::new(client, store.clone(), apiset);
+    status.say(format!("Reading config from {}...", args.config_file));
+    let mut config = TracerConfig::load(&args.config_file)?;
+    for gvk in exclude_tracked_kinds(&mut config, &args.exclude_kinds) {
+        eprintln!("WARNING: {gvk} is not in trackedObjects, so excluding it does nothing");
+    }
+
+    status.say("Connecting to kubernetes cluster...");
+    let connecting = connect_with_retries(
+        connect,
+        args.connect_retries,
+        args.connect_timeout.map(Duration::from_secs),
+        CONNECT_INITIAL_BACKOFF,
+        &status,
+    );
+    let Some(client) = before_deadline(deadline, connecting).await else {
+        bail!(timed_out_before_start(args, "connecting to the cluster"));
+    };
+    let client = client?;
+    let rate_limiter = args.max_qps.map(|qps| RateLimiter::new(qps, args.burst));
+    let mut apiset = ApiSet::new_with_rate_limiter(client.clone(), rate_limiter);
+    if let Some(prefs) = &config.preferred_versions {
+        apiset = apiset.with_version_preferences(prefs.clone());
+    }
+    if let Some(ttl) = args.discovery_cache_ttl {
+        let cluster_url = kube::Config::infer().await?.cluster_url.to_string();
+        let cache = DiscoveryCache::new(discovery_cache_path(), &cluster_url, ttl as i64, args.refresh_discovery_cache);
+        apiset = apiset.with_discovery_cache(cache);
+    }
+
+    let store = match &args.resume {
+        Some(path) => {
+            status.say(format!("Resuming snapshot from checkpoint {path}..."));
+            TraceStore::from_checkpoint(&fs::read(path)?, config.clone())?
+        },
+        None => TraceStore::new(config.clone()),
+    };
+
+    status.say("Loading snapshot into store...");
+    let store = Arc::new(Mutex::new(store));
+    let exporter = Arc::new(SnapshotExporter::new(build_export_filters(args), args.serialization.into(), key));
+    let discovering =
+        DynObjWatcher::new(store.clone(), &mut apiset, &config.tracked_objects, args.namespace.as_deref());
+    let Some(watcher) = before_deadline(deadline, discovering).await else {
+        bail!(timed_out_before_start(args, "discovering the tracked resources"));
+    };
+    let (dyn_obj_watcher, do_ready_rx) = watcher?;
+    let (pod_watcher, pod_ready_rx) = PodWatcher::new(client.clone(), store.clone(), apiset, args.namespace.as_deref());
 
     let do_handle = tokio::spawn(dyn_obj_watcher.start());
     let pod_handle = tokio::spawn(pod_watcher.start());
+    let checkpoint_handle = args.checkpoint.clone().map(|path| {
+        let interval = Duration::from_secs(args.checkpoint_interval);
+        tokio::spawn(checkpoint_periodically(store.clone(), path, interval))
+    });
+
+    // The handler has to be installed before the watchers get going, otherwise a SIGUSR1 would
+    // just kill the process
+    let sigusr1 = signal(SignalKind::user_defined1())?;
+    let flush_handle = tokio::spawn(flush_on_signal(
+        sigusr1,
+        store.clone(),
+        output.clone(),
+        exporter.clone(),
+        StatusPrinter::new(args),
+    ));
+
+    let progress_handle = status.is_interactive().then(|| {
+        tokio::spawn(show_progress(
+            store.clone(),
+            StatusPrinter::new(args),
+            started,
+            args.timeout.map(Duration::from_secs),
+        ))
+    });
 
     // the receivers block until they get a message, so don't actually care about the value
-    let _ = do_ready_rx.recv();
-    let _ = pod_ready_rx.recv();
+    let ready = wait_until_ready(
+        &[("object", do_ready_rx), ("pod", pod_ready_rx)],
+        deadline,
+        args.watch_timeout_secs.map(Duration::from_secs),
+    );
+    let mut timed_out = ready.timed_out;
+    if let Some(handle) = progress_handle {
+        handle.abort();
+        let _ = handle.await;
+        status.redraw("");
+    }
+    for name in &ready.incomplete_watchers {
+        eprintln!(
+            "WARNING: {name} watcher did not finish its initial sync within {}s; the snapshot may be incomplete",
+            args.watch_timeout_secs.unwrap_or_default()
+        );
+    }
 
     do_handle.abort();
     pod_handle.abort();
+    if let Some(handle) = &checkpoint_handle {
+        handle.abort();
+    }
+    flush_handle.abort();
 
     // When I don't await the tasks, it seems like it hangs.  I'm not 100% this was actually
     // the issue though, it seemed a bit erratic.
     let _ = do_handle.await;
     let _ = pod_handle.await;
+    if let Some(handle) = checkpoint_handle {
+        let _ = handle.await;
+    }
+    let _ = flush_handle.await;
 
-    println!("Exporting snapshot data from store...");
-    let filters = ExportFilters::new(args.excluded_namespaces.clone(), vec![], true);
+    // Once we've hit the timeout, we skip the rest of the collection steps and go straight to the
+    // export; if one of them gets cut off partway, it hasn't written anything to the store yet
     let start_ts = UtcClock.now_ts();
-    let end_ts = start_ts + 1;
-    let data = store.lock().unwrap().export(start_ts, end_ts, &filters)?;
+    if args.sample_usage && !timed_out {
+        status.say("Sampling pod resource usage...");
+        match before_deadline(deadline, sample_pod_usage(client.clone(), &store, start_ts, &status)).await {
+            Some(res) => res?,
+            None => timed_out = true,
+        }
+    }
+
+    if args.capture_events && !timed_out {
+        status.say("Capturing pod events...");
+        // Events get their own rate limiter, so listing them doesn't eat into the owner lookups
+        let rate_limiter = args.max_qps.map(|qps| RateLimiter::new(qps, args.burst));
+        match before_deadline(deadline, capture_pod_events(client, &store, rate_limiter, &status)).await {
+            Some(res) => res?,
+            None => timed_out = true,
+        }
+    }
+
+    if timed_out {
+        // This is a warning, not a status message, so it isn't silenced by --quiet
+        eprintln!(
+            "WARNING: snapshot timed out after {}s; exporting the data collected so far",
+            args.timeout.unwrap_or_default()
+        );
+    }
+
+    status.say("Exporting snapshot data from store...");
+    let (data, object_count) = export_snapshot(&store, &exporter, start_ts)?;
+
+    status.say(format!("Writing trace file: {output}"));
+    write_trace(sink_for(&output)?, &data).await?;
+
+    status.say("Done!");
+    if args.json {
+        let summary = SnapshotSummary {
+            output_path: output,
+            object_count,
+            byte_size: data.len(),
+            duration_secs: started.elapsed().as_secs_f64(),
+            timed_out,
+            incomplete_watchers: ready.incomplete_watchers,
+            export_metrics: Some(exporter.metrics().clone()),
+        };
+        println!("{}", serde_json::to_string(&summary)?);
+    }
+
+    if timed_out {
+        return Err(SnapshotError::timed_out(&args.timeout.unwrap_or_default()));
+    }
+    Ok(())
+}
+
+// How waiting for the watchers went: whether we hit the overall --timeout, and which watchers we
+// gave up on because they took longer than --watch-timeout-secs.  Once the overall timeout fires we
+// stop waiting entirely, so any watchers after that aren't listed as incomplete.
+#[derive(Debug, Default, PartialEq)]
+struct ReadyState {
+    timed_out: bool,
+    incomplete_watchers: Vec<String>,
+}
+
+// The receivers block until the watchers have finished their initial list, so we don't actually
+// care about the value; if a watcher goes away without sending anything, there's nothing more to
+// wait for either.  Each watcher gets up to maybe_watch_timeout (starting from when we start
+// waiting on it), but the whole thing never goes past the overall deadline.
+fn wait_until_ready(
+    ready_rxs: &[(&str, Receiver<bool>)],
+    deadline: Option<Instant>,
+    maybe_watch_timeout: Option<Duration>,
+) -> ReadyState {
+    let mut state = ReadyState::default();
+    for (name, rx) in ready_rxs {
+        let watch_deadline = maybe_watch_timeout.map(|t| Instant::now() + t);
+        let res = match [deadline, watch_deadline].into_iter().flatten().min() {
+            Some(d) => rx.recv_timeout(d.saturating_duration_since(Instant::now())),
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        if let Err(RecvTimeoutError::Timeout) = res {
+            if deadline.is_some_and(|d| Instant::now() >= d) {
+                state.timed_out = true;
+                return state;
+            }
+            state.incomplete_watchers.push(name.to_string());
+        }
+    }
+    state
+}
+
+// Runs fut, unless the deadline passes first, in which case fut gets dropped and we return None
+async fn before_deadline<T>(deadline: Option<Instant>, fut: impl Future<Output = T>) -> Option<T> {
+    match deadline {
+        Some(d) => timeout(d.saturating_duration_since(Instant::now()), fut).await.ok(),
+        None => Some(fut.await),
+    }
+}
+
+fn timed_out_before_start(args: &Args, phase: &str) -> String {
+    format!(
+        "snapshot timed out after {}s while {phase}, before any data was collected",
+        args.timeout.unwrap_or_default()
+    )
+}
+
+// Everything in the store gets flattened into a single event at start_ts; returns the (possibly
+// encrypted) trace data along with the number of objects in it.  The export metrics include the
+// time spent encrypting, but not the time spent waiting for the store lock or counting the objects
+// for the summary.
+fn export_snapshot(
+    store: &Mutex<TraceStore>,
+    exporter: &SnapshotExporter,
+    start_ts: i64,
+) -> anyhow::Result<(Vec<u8>, usize)> {
+    let filters = &exporter.filters;
+    let (data, object_count, export_duration) = {
+        let store = lock_store(store);
+        let started = Instant::now();
+        let data = store.export_as(start_ts, start_ts + 1, filters, exporter.format)?;
+        let export_duration = started.elapsed();
+        (data, store.objs_at_end(filters).len(), export_duration)
+    };
+
+    let started = Instant::now();
+    let data = match &exporter.key {
+        Some(key) => encrypt_trace(&data, key)?,
+        None => data,
+    };
+    let duration = export_duration + started.elapsed();
+
+    exporter.metrics().record(duration, data.len());
+    info!(
+        duration_secs = duration.as_secs_f64(),
+        byte_size = data.len(),
+        object_count,
+        "exported snapshot data"
+    );
+    Ok((data, object_count))
+}
+
+// If one of the watchers panicked while it was holding the store lock, the mutex is poisoned; the
+// watcher was most likely partway through recording a single event when it died, and everything
+// before that is still fine, so we'd rather export what we have than throw the whole snapshot away.
+// Everything in here that touches the store should go through this, so that a dead watcher can't
+// take down the progress display, checkpoints, or the usage/event collection either.
+fn lock_store(store: &Mutex<TraceStore>) -> MutexGuard<'_, TraceStore> {
+    store.lock().unwrap_or_else(|poisoned| {
+        eprintln!("WARNING: a watcher panicked while updating the trace store; the snapshot may be incomplete");
+        store.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
+async fn write_trace(mut sink: Box<dyn TraceSink>, data: &[u8]) -> EmptyResult {
+    sink.write(data).await?;
+    sink.finish().await
+}
+
+// The store stays locked while we serialize it (so that we get a consistent view), but the actual
+// write happens after the lock is released.  We write to a temporary file and then move it into
+// place, so that if we crash halfway through a write the previous checkpoint is still intact.
+fn write_checkpoint(store: &Mutex<TraceStore>, path: &str) -> EmptyResult {
+    let data = lock_store(store).checkpoint()?;
+    let tmp_path = format!("{path}.tmp");
+    fs::write(&tmp_path, data)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+async fn checkpoint_periodically(store: Arc<Mutex<TraceStore>>, path: String, interval: Duration) {
+    loop {
+        sleep(interval).await;
+        // Like the usage warning, this isn't silenced by --quiet; a failed checkpoint isn't fatal,
+        // since the snapshot itself can still succeed
+        if let Err(err) = write_checkpoint(&store, &path) {
+            eprintln!("WARNING: could not write checkpoint to {path}: {err}");
+        }
+    }
+}
+
+// An intermediate export of whatever is in the store right now, written next to the final output
+// with the current timestamp in the name; the watchers keep running the whole time, they just have
+// to wait on the store lock while we export.  Returns the path that was written.
+async fn flush_trace(
+    store: &Mutex<TraceStore>,
+    output: &str,
+    exporter: &SnapshotExporter,
+    ts: i64,
+) -> anyhow::Result<String> {
+    let (data, _) = export_snapshot(store, exporter, ts)?;
+    let path = timestamped_path(output, ts);
+    write_trace(sink_for(&path)?, &data).await?;
+    Ok(path)
+}
+
+async fn flush_on_signal(
+    mut sigusr1: Signal,
+    store: Arc<Mutex<TraceStore>>,
+    output: String,
+    exporter: Arc<SnapshotExporter>,
+    status: StatusPrinter,
+) {
+    while sigusr1.recv().await.is_some() {
+        match flush_trace(&store, &output, &exporter, UtcClock.now_ts()).await {
+            Ok(path) => status.say(format!("Wrote intermediate trace file: {path}")),
+            // Like a failed checkpoint, this isn't fatal, and isn't silenced by --quiet
+            Err(err) => eprintln!("WARNING: could not write intermediate trace: {err}"),
+        }
+    }
+}
+
+// trace.out -> trace-<ts>.out; if the file name doesn't have an extension, the timestamp just goes
+// on the end.  This works the same way for cloud storage URLs.
+fn timestamped_path(output: &str, ts: i64) -> String {
+    let name_start = output.rfind('/').map_or(0, |i| i + 1);
+    match output[name_start..].rfind('.') {
+        Some(i) if i > 0 => {
+            let (base, ext) = output.split_at(name_start + i);
+            format!("{base}-{ts}{ext}")
+        },
+        _ => format!("{output}-{ts}"),
+    }
+}
+
+// When you're capturing lots of clusters, it's convenient to have the traces sorted into
+// directories automatically; missing parent directories are created for local paths (cloud storage
+// doesn't have directories, so there's nothing to do there).  We can't cheaply check what's already
+// in cloud storage, so {seq} only works for local paths.
+fn expand_output_template(template: &str, cluster: &str, ts: i64) -> anyhow::Result<String> {
+    let Some(date) = DateTime::from_timestamp(ts, 0) else {
+        bail!("invalid timestamp: {ts}");
+    };
+    let expand = |seq: usize| {
+        template
+            .replace("{cluster}", cluster)
+            .replace("{date}", &date.format("%Y-%m-%d").to_string())
+            .replace("{timestamp}", &ts.to_string())
+            .replace("{seq}", &seq.to_string())
+    };
+
+    let output = expand(0);
+    if let Some(start) = output.find('{') {
+        bail!("unknown placeholder in output template: {}", &output[start..]);
+    }
+
+    let is_local = SkObjectStore::new(&output)?.scheme() == ObjectStoreScheme::Local;
+    let output = match (template.contains("{seq}"), is_local) {
+        (false, _) => output,
+        (true, true) => (0..).map(expand).find(|path| !Path::new(local_path(path)).exists()).unwrap(),
+        (true, false) => bail!("{{seq}} is only supported for local output paths"),
+    };
+
+    if is_local {
+        if let Some(parent) = Path::new(local_path(&output)).parent() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    Ok(output)
+}
+
+fn local_path(output: &str) -> &str {
+    output.strip_prefix("file://").unwrap_or(output)
+}
+
+// Cluster names can have slashes in them (e.g., EKS cluster ARNs), which we don't want turning into
+// extra directories; if there's no kubeconfig (e.g., we're running in a pod), we can't tell what
+// the cluster is called.
+fn current_cluster_name() -> String {
+    let cluster = Kubeconfig::read().ok().and_then(|kc| {
+        let current = kc.current_context?;
+        kc.contexts
+            .into_iter()
+            .find(|ctx| ctx.name == current)?
+            .context
+            .map(|ctx| ctx.cluster)
+    });
+    cluster.unwrap_or_else(|| "unknown".into()).replace('/', "_")
+}
+
+fn discovery_cache_path() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or(PathBuf::from("."))
+        .join("simkube")
+        .join("discovery.json")
+}
+
+fn build_export_filters(args: &Args) -> ExportFilters {
+    let mut excluded_namespaces = args.excluded_namespaces.clone();
+    if !args.include_system_namespaces {
+        for ns in SYSTEM_NAMESPACES {
+            if !excluded_namespaces.iter().any(|n| n == ns) {
+                excluded_namespaces.push(ns.into());
+            }
+        }
+    }
+    let mut filters = ExportFilters::new(excluded_namespaces, vec![], true);
+    filters.close_open_intervals = Some(args.close_open_intervals);
+    if !args.exclude_kinds.is_empty() {
+        filters.excluded_kinds = Some(args.exclude_kinds.iter().map(|gvk| gvk.to_string()).collect());
+    }
+    filters
+}
 
-    println!("Writing trace file: {}", args.output);
-    let mut file = File::create(&args.output)?;
-    file.write_all(&data)?;
+// trackedObjects acts as the allowlist of kinds to snapshot, and --exclude-kinds is subtracted from
+// it, so that we don't bother watching the excluded kinds at all.  They're also filtered out at
+// export time, in case they were already recorded in a checkpoint we're resuming from.  Returns
+// the excluded kinds that weren't being tracked in the first place.
+fn exclude_tracked_kinds(config: &mut TracerConfig, excluded: &[GVK]) -> Vec<GVK> {
+    excluded
+        .iter()
+        .filter(|gvk| config.tracked_objects.remove(gvk).is_none())
+        .cloned()
+        .collect()
+}
+
+// Creating the client doesn't actually talk to the cluster, so we also make a (cheap) discovery
+// call to make sure the apiserver is up before we start the watchers.
+async fn connect() -> anyhow::Result<kube::Client> {
+    let client = kube::Client::try_default().await?;
+    client.apiserver_version().await?;
+    Ok(client)
+}
+
+// If we're snapshotting a cluster that just came up (e.g., in CI), the apiserver may not be ready
+// for a little while; here we retry the connection with exponential backoff.  By default we don't
+// retry at all, and just fail if the first attempt fails.
+async fn connect_with_retries<T, F, Fut>(
+    connect_fn: F,
+    retries: u32,
+    maybe_timeout: Option<Duration>,
+    initial_backoff: Duration,
+    status: &StatusPrinter,
+) -> anyhow::Result<T>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    let mut backoff = initial_backoff;
+    let mut attempt = 0;
+    loop {
+        let res = match maybe_timeout {
+            Some(t) => timeout(t, connect_fn())
+                .await
+                .unwrap_or_else(|_| Err(anyhow!("timed out after {}s", t.as_secs()))),
+            None => connect_fn().await,
+        };
+
+        match res {
+            Ok(val) => return Ok(val),
+            Err(err) if attempt >= retries => return Err(err),
+            Err(err) => {
+                attempt += 1;
+                status.say(format!(
+                    "Could not connect to cluster ({err}); retrying in {}s ({attempt}/{retries})...",
+                    backoff.as_secs()
+                ));
+                sleep(backoff).await;
+                backoff = min(backoff * 2, CONNECT_MAX_BACKOFF);
+            },
+        }
+    }
+}
+
+async fn sample_pod_usage(
+    client: kube::Client,
+    store: &Mutex<TraceStore>,
+    ts: i64,
+    status: &StatusPrinter,
+) -> EmptyResult {
+    let Some(usage) = list_pod_usage(client, ts).await? else {
+        // This is a warning, not a status message, so it isn't silenced by --quiet
+        eprintln!("WARNING: metrics.k8s.io API not available (is metrics-server installed?); skipping usage sampling");
+        return Ok(());
+    };
+
+    let mut store = lock_store(store);
+    let mut recorded = 0;
+    for (ns_name, pod_usage) in usage {
+        if store.record_pod_usage(&ns_name, pod_usage) {
+            recorded += 1;
+        }
+    }
+    status.say(format!("Recorded usage data for {recorded} pods"));
+    Ok(())
+}
+
+async fn capture_pod_events(
+    client: kube::Client,
+    store: &Mutex<TraceStore>,
+    rate_limiter: Option<RateLimiter>,
+    status: &StatusPrinter,
+) -> EmptyResult {
+    let pod_events = list_pod_events(client, rate_limiter).await?;
 
-    println!("Done!");
+    let mut store = lock_store(store);
+    let mut recorded = 0;
+    for (ns_name, events) in pod_events {
+        if store.record_pod_events(&ns_name, events) {
+            recorded += 1;
+        }
+    }
+    status.say(format!("Recorded events for {recorded} pods"));
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{
+        AtomicU32,
+        Ordering,
+    };
+
+    use assertables::*;
+    use clap::Parser;
+    use kube::api::TypeMeta;
+    use rstest::*;
+    use sk_core::k8s::testutils::test_deployment;
+    use sk_store::TraceStorable;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct TestCommand {
+        #[command(flatten)]
+        args: Args,
+    }
+
+    fn parse_args(argv: &[&str]) -> Args {
+        TestCommand::parse_from([&["snapshot", "-c", "config.yml"], argv].concat()).args
+    }
+
+    #[rstest]
+    fn test_build_export_filters_defaults() {
+        let filters = build_export_filters(&parse_args(&[]));
+        assert_eq!(
+            filters.excluded_namespaces,
+            vec![
+                "cert-manager",
+                "local-path-storage",
+                "monitoring",
+                "simkube",
+                "kube-node-lease",
+                "kube-public",
+                "kube-system"
+            ]
+        );
+    }
+
+    #[rstest]
+    fn test_build_export_filters_merges_user_namespaces() {
+        let filters = build_export_filters(&parse_args(&["--excluded-namespaces", "foo,kube-system"]));
+        assert_eq!(filters.excluded_namespaces, vec!["foo", "kube-system", "kube-node-lease", "kube-public"]);
+    }
+
+    #[rstest]
+    fn test_build_export_filters_include_system_namespaces() {
+        let filters =
+            build_export_filters(&parse_args(&["--excluded-namespaces", "foo", "--include-system-namespaces"]));
+        assert_eq!(filters.excluded_namespaces, vec!["foo"]);
+    }
+
+    #[rstest]
+    fn test_exclude_kinds() {
+        let args = parse_args(&["--exclude-kinds", "v1.ConfigMap", "--exclude-kinds", "batch/v1.Job"]);
+        let (depl_gvk, cm_gvk) = (GVK::new("apps", "v1", "Deployment"), GVK::new("", "v1", "ConfigMap"));
+        let mut config = TracerConfig {
+            tracked_objects: [(depl_gvk.clone(), Default::default()), (cm_gvk.clone(), Default::default())].into(),
+            ..Default::default()
+        };
+
+        let untracked = exclude_tracked_kinds(&mut config, &args.exclude_kinds);
+        assert_eq!(untracked, vec![GVK::new("batch", "v1", "Job")]);
+        assert_eq!(config.tracked_objects.keys().collect::<Vec<_>>(), vec![&depl_gvk]);
+
+        // Anything that's already in the store (e.g., from a checkpoint) is filtered out on export
+        let store = Mutex::new(TraceStore::new(config));
+        let mut depl = test_deployment("depl1");
+        depl.types = Some(TypeMeta {
+            api_version: "apps/v1".into(),
+            kind: "Deployment".into(),
+        });
+        let mut cm = test_deployment("cm1");
+        cm.types = Some(TypeMeta { api_version: "v1".into(), kind: "ConfigMap".into() });
+        store.lock().unwrap().create_or_update_obj(&depl, 0, None);
+        store.lock().unwrap().create_or_update_obj(&cm, 0, None);
+
+        let (data, object_count) = export_snapshot(
+            &store,
+            &SnapshotExporter::new(build_export_filters(&args), TraceFormat::Msgpack, None),
+            10,
+        )
+        .unwrap();
+        assert_eq!(object_count, 1);
+        let kinds: Vec<_> = TraceStore::import(data, &None)
+            .unwrap()
+            .objects()
+            .map(|obj| obj.types.as_ref().unwrap().kind.clone())
+            .collect();
+        assert_eq!(kinds, vec!["Deployment"]);
+    }
+
+    #[rstest]
+    fn test_exclude_kinds_invalid() {
+        assert!(TestCommand::try_parse_from(["snapshot", "-c", "config.yml", "--exclude-kinds", "a/b/c"]).is_err());
+    }
+
+    #[rstest]
+    fn test_parse_refresh_discovery_cache() {
+        let argv = ["snapshot", "-c", "config.yml", "--refresh-discovery-cache"];
+        assert!(TestCommand::try_parse_from(argv).is_err());
+
+        let args = parse_args(&["--discovery-cache-ttl", "3600", "--refresh-discovery-cache"]);
+        assert_eq!(args.discovery_cache_ttl, Some(3600));
+        assert!(args.refresh_discovery_cache);
+    }
+
+    async fn connect_after(attempts: &AtomicU32, succeed_on: u32) -> anyhow::Result<u32> {
+        let n = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+        if n < succeed_on {
+            bail!("connection refused");
+        }
+        Ok(n)
+    }
+
+    #[rstest]
+    #[case::first_try(0, 1, Some(1))]
+    #[case::no_retries(0, 2, None)]
+    #[case::eventually(3, 3, Some(3))]
+    #[case::exhausted(2, 4, None)]
+    #[tokio::test]
+    async fn test_connect_with_retries(#[case] retries: u32, #[case] succeed_on: u32, #[case] expected: Option<u32>) {
+        let attempts = AtomicU32::new(0);
+        let status = StatusPrinter { quiet: true, to_stderr: false };
+        let res =
+            connect_with_retries(|| connect_after(&attempts, succeed_on), retries, None, Duration::ZERO, &status).await;
+
+        assert_eq!(res.ok(), expected);
+        assert_eq!(attempts.load(Ordering::SeqCst), expected.unwrap_or(retries + 1));
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_connect_with_retries_timeout() {
+        let attempts = AtomicU32::new(0);
+        let res = connect_with_retries(
+            || async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                sleep(Duration::from_secs(10)).await;
+                Ok(())
+            },
+            1,
+            Some(Duration::from_millis(1)),
+            Duration::ZERO,
+            &StatusPrinter { quiet: true, to_stderr: false },
+        )
+        .await;
+
+        assert!(res.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_before_deadline() {
+        // The deadline covers all of the connection attempts together, not each one on its own
+        let deadline = Some(Instant::now() + Duration::from_millis(50));
+        let attempts = AtomicU32::new(0);
+        let connecting = connect_with_retries(
+            || async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                sleep(Duration::from_millis(30)).await;
+                Err::<(), _>(anyhow!("connection refused"))
+            },
+            10,
+            None,
+            Duration::ZERO,
+            &StatusPrinter { quiet: true, to_stderr: false },
+        );
+
+        let started = Instant::now();
+        assert!(before_deadline(deadline, connecting).await.is_none());
+        assert_lt!(started.elapsed(), Duration::from_secs(1));
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+        assert_eq!(before_deadline(None, async { 5 }).await, Some(5));
+    }
+
+    #[rstest]
+    fn test_export_metrics_recorded() {
+        let store = Mutex::new(TraceStore::new(TracerConfig::default()));
+        store.lock().unwrap().create_or_update_obj(&test_deployment("depl1"), 0, None);
+
+        let exporter = SnapshotExporter::new(ExportFilters::new(vec![], vec![], true), TraceFormat::Msgpack, None);
+        let (data1, _) = export_snapshot(&store, &exporter, 10).unwrap();
+        store.lock().unwrap().create_or_update_obj(&test_deployment("depl2"), 20, None);
+        let (data2, _) = export_snapshot(&store, &exporter, 30).unwrap();
+        assert_gt!(data2.len(), data1.len());
+
+        let metrics = exporter.metrics();
+        assert_eq!(metrics.count, 2);
+        assert_eq!(metrics.byte_size, data2.len());
+        assert_ge!(metrics.duration_sum_secs, 0.0);
+        // exporting a couple of objects definitely takes less than a minute
+        assert_eq!(metrics.duration_buckets.last(), Some(&(60.0, 2)));
+    }
+
+    #[rstest]
+    fn test_export_metrics_buckets() {
+        let mut metrics = ExportMetrics::new();
+        metrics.record(Duration::from_millis(300), 10);
+        metrics.record(Duration::from_secs(20), 20);
+        metrics.record(Duration::from_secs(120), 30);
+
+        let counts: Vec<_> = metrics.duration_buckets.iter().map(|(_, count)| *count).collect();
+        assert_eq!(counts, vec![0, 1, 1, 1, 1, 2, 2]);
+        assert_eq!(metrics.count, 3);
+        assert_lt!((metrics.duration_sum_secs - 140.3).abs(), 1e-9);
+        assert_eq!(metrics.byte_size, 30);
+    }
+
+    #[rstest]
+    fn test_snapshot_summary_json() {
+        let summary = SnapshotSummary {
+            output_path: "trace.out".into(),
+            object_count: 3,
+            byte_size: 1234,
+            duration_secs: 2.5,
+            timed_out: false,
+            incomplete_watchers: vec![],
+            export_metrics: None,
+        };
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "outputPath": "trace.out",
+                "objectCount": 3,
+                "byteSize": 1234,
+                "durationSecs": 2.5,
+            })
+        );
+    }
+
+    #[rstest]
+    fn test_write_checkpoint() {
+        let path = std::env::temp_dir().join(format!("sk-checkpoint-test-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        let config = TracerConfig {
+            max_objects_per_namespace: Some(5),
+            ..Default::default()
+        };
+        let store = Mutex::new(TraceStore::new(config.clone()));
+
+        write_checkpoint(&store, path).unwrap();
+        let new_store = TraceStore::from_checkpoint(&fs::read(path).unwrap(), config).unwrap();
+        assert_eq!(new_store.config().max_objects_per_namespace, Some(5));
+        assert!(!std::path::Path::new(&format!("{path}.tmp")).exists());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[rstest]
+    #[case::default(&[], None, 60)]
+    #[case::resume(&["--resume", "ckpt", "--checkpoint", "ckpt", "--checkpoint-interval", "5"], Some("ckpt"), 5)]
+    fn test_checkpoint_args(#[case] argv: &[&str], #[case] resume: Option<&str>, #[case] interval: u64) {
+        let args = parse_args(argv);
+        assert_eq!(args.resume.as_deref(), resume);
+        assert_eq!(args.checkpoint.as_deref(), resume);
+        assert_eq!(args.checkpoint_interval, interval);
+    }
+
+    #[rstest]
+    #[case::ext("trace.out", "trace-1234.out")]
+    #[case::no_ext("traces/trace", "traces/trace-1234")]
+    #[case::dot_in_dir("s3://my.bucket/trace", "s3://my.bucket/trace-1234")]
+    #[case::hidden("/tmp/.trace", "/tmp/.trace-1234")]
+    fn test_timestamped_path(#[case] output: &str, #[case] expected: &str) {
+        assert_eq!(timestamped_path(output, 1234), expected);
+    }
+
+    #[rstest]
+    fn test_expand_output_template() {
+        let dir = std::env::temp_dir().join(format!("sk-template-test-{}", std::process::id()));
+        let template = format!("{}/{{cluster}}/{{date}}/trace-{{timestamp}}-{{seq}}.out", dir.to_str().unwrap());
+
+        // 1700000000 is 2023-11-14 UTC
+        let path = expand_output_template(&template, "prod", 1700000000).unwrap();
+        assert_eq!(path, dir.join("prod/2023-11-14/trace-1700000000-0.out").to_str().unwrap());
+        assert!(dir.join("prod/2023-11-14").is_dir());
+
+        // The sequence number skips over files that already exist
+        fs::write(&path, "").unwrap();
+        let path = expand_output_template(&template, "prod", 1700000000).unwrap();
+        assert_eq!(path, dir.join("prod/2023-11-14/trace-1700000000-1.out").to_str().unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    #[case::unknown_placeholder("{cluster}/{hostname}/trace.out")]
+    #[case::remote_seq("s3://bucket/{cluster}/trace-{seq}.out")]
+    fn test_expand_output_template_invalid(#[case] template: &str) {
+        assert!(expand_output_template(template, "prod", 1700000000).is_err());
+    }
+
+    #[rstest]
+    #[case::default(&[], Serialization::Msgpack)]
+    #[case::json(&["--serialization", "json"], Serialization::Json)]
+    fn test_parse_serialization(#[case] argv: &[&str], #[case] expected: Serialization) {
+        assert_eq!(parse_args(argv).serialization, expected);
+        assert!(TestCommand::try_parse_from(["snapshot", "-c", "config.yml", "--serialization", "bincode"]).is_err());
+    }
+
+    #[rstest]
+    fn test_snapshot_json_serialization() {
+        let store = Mutex::new(TraceStore::new(TracerConfig::default()));
+        store.lock().unwrap().create_or_update_obj(&test_deployment("depl1"), 0, None);
+
+        let exporter = SnapshotExporter::new(ExportFilters::new(vec![], vec![], true), TraceFormat::Json, None);
+        let (data, _) = export_snapshot(&store, &exporter, 10).unwrap();
+        assert_eq!(data[0], b'{');
+        assert_eq!(TraceStore::import(data, &None).unwrap().objects().count(), 1);
+    }
+
+    #[rstest]
+    fn test_output_template_conflicts_with_output() {
+        let argv = ["snapshot", "-c", "config.yml", "-o", "a.out", "--output-template", "b.out"];
+        assert!(TestCommand::try_parse_from(argv).is_err());
+        assert_eq!(parse_args(&["--output-template", "b.out"]).output_template.as_deref(), Some("b.out"));
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_flush_trace() {
+        let dir = std::env::temp_dir().join(format!("sk-flush-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("trace.out");
+
+        let store = Mutex::new(TraceStore::new(TracerConfig::default()));
+        store.lock().unwrap().create_or_update_obj(&test_deployment("depl1"), 0, None);
+
+        let exporter = SnapshotExporter::new(ExportFilters::new(vec![], vec![], true), TraceFormat::Msgpack, None);
+        let path = flush_trace(&store, output.to_str().unwrap(), &exporter, 10).await.unwrap();
+        assert_eq!(path, dir.join("trace-10.out").to_str().unwrap());
+
+        let flushed = TraceStore::import(fs::read(&path).unwrap(), &None).unwrap();
+        assert_eq!(flushed.objects().count(), 1);
+
+        // The store is still usable afterwards, and keeps recording
+        store.lock().unwrap().create_or_update_obj(&test_deployment("depl2"), 20, None);
+        assert_eq!(store.lock().unwrap().objects().count(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    fn test_wait_until_ready() {
+        let (do_tx, do_rx) = std::sync::mpsc::channel();
+        let (pod_tx, pod_rx) = std::sync::mpsc::channel();
+        do_tx.send(true).unwrap();
+        pod_tx.send(true).unwrap();
+        assert_eq!(
+            wait_until_ready(
+                &[("object", do_rx), ("pod", pod_rx)],
+                Some(Instant::now() + Duration::from_secs(10)),
+                None
+            ),
+            ReadyState::default()
+        );
+    }
+
+    #[rstest]
+    fn test_wait_until_ready_watch_timeout() {
+        // The object watcher is slow, so we give up on it and move on to the pod watcher, which
+        // is already done
+        let (_do_tx, do_rx) = std::sync::mpsc::channel();
+        let (pod_tx, pod_rx) = std::sync::mpsc::channel();
+        pod_tx.send(true).unwrap();
+
+        let started = Instant::now();
+        let state = wait_until_ready(&[("object", do_rx), ("pod", pod_rx)], None, Some(Duration::from_millis(50)));
+        assert_ge!(started.elapsed(), Duration::from_millis(50));
+        assert_eq!(
+            state,
+            ReadyState {
+                timed_out: false,
+                incomplete_watchers: vec!["object".into()]
+            }
+        );
+    }
+
+    #[rstest]
+    fn test_wait_until_ready_overall_timeout_wins() {
+        let (_do_tx, do_rx) = std::sync::mpsc::channel();
+        let (_pod_tx, pod_rx) = std::sync::mpsc::channel();
+
+        let state = wait_until_ready(
+            &[("object", do_rx), ("pod", pod_rx)],
+            Some(Instant::now() + Duration::from_millis(50)),
+            Some(Duration::from_secs(10)),
+        );
+        assert_eq!(state, ReadyState { timed_out: true, incomplete_watchers: vec![] });
+    }
+
+    #[rstest]
+    fn test_snapshot_progress_reads_store_counts() {
+        const NUM_OBJS: usize = 200;
+
+        // The writer keeps the store busy while we read from it, the same way the watchers do while
+        // the progress line is being drawn; the counts we read can lag behind, but they should never
+        // go backwards, and once the writer is done, we should see everything it wrote
+        let store = Arc::new(Mutex::new(TraceStore::new(TracerConfig::default())));
+        let writer = {
+            let store = store.clone();
+            std::thread::spawn(move || {
+                for i in 0..NUM_OBJS {
+                    store
+                        .lock()
+                        .unwrap()
+                        .create_or_update_obj(&test_deployment(&format!("depl{i}")), i as i64, None);
+                }
+            })
+        };
+
+        let mut last = SnapshotProgress::default();
+        while !writer.is_finished() {
+            let progress = SnapshotProgress::read(&store);
+            assert_ge!(progress.object_count, last.object_count);
+            last = progress;
+        }
+        writer.join().unwrap();
+
+        assert_eq!(SnapshotProgress::read(&store), SnapshotProgress { object_count: NUM_OBJS, pod_count: 0 });
+    }
+
+    #[rstest]
+    #[case::unbounded(None, "⠙ Waiting for watchers: 3 objects, 2 pods collected so far (5s elapsed)")]
+    #[case::bounded(
+        Some(Duration::from_secs(30)),
+        "⠙ Waiting for watchers: 3 objects, 2 pods collected so far (5s elapsed, at most 25s left)"
+    )]
+    fn test_snapshot_progress_render(#[case] maybe_timeout: Option<Duration>, #[case] expected: &str) {
+        let progress = SnapshotProgress { object_count: 3, pod_count: 2 };
+        assert_eq!(progress.render(11, Duration::from_secs(5), maybe_timeout), expected);
+    }
+
+    #[rstest]
+    fn test_snapshot_timeout_exports_partial_data() {
+        // The pod watcher never becomes ready, but the dynamic object watcher already recorded
+        // something before we gave up on it
+        let (do_tx, do_rx) = std::sync::mpsc::channel();
+        let (_pod_tx, pod_rx) = std::sync::mpsc::channel();
+        do_tx.send(true).unwrap();
+
+        let store = Mutex::new(TraceStore::new(TracerConfig::default()));
+        store.lock().unwrap().create_or_update_obj(&test_deployment("depl1"), 0, None);
+
+        let started = Instant::now();
+        assert!(
+            wait_until_ready(
+                &[("object", do_rx), ("pod", pod_rx)],
+                Some(Instant::now() + Duration::from_millis(50)),
+                None
+            )
+            .timed_out
+        );
+        assert!(started.elapsed() >= Duration::from_millis(50));
+
+        let exporter = SnapshotExporter::new(ExportFilters::new(vec![], vec![], true), TraceFormat::Msgpack, None);
+        let (data, object_count) = export_snapshot(&store, &exporter, 10).unwrap();
+        assert_eq!(object_count, 1);
+        assert_eq!(TraceStore::import(data, &None).unwrap().objects().count(), 1);
+    }
+
+    #[rstest]
+    fn test_snapshot_timeout_exit_code() {
+        let err = SnapshotError::timed_out(&30);
+        assert_eq!(err.to_string(), "snapshot timed out after 30s");
+        assert_eq!(err.downcast_ref::<SnapshotError>().unwrap().exit_code(), SNAPSHOT_TIMEOUT_EXIT_CODE);
+    }
+
+    fn poison(store: &Arc<Mutex<TraceStore>>) {
+        let poisoner = store.clone();
+        std::thread::spawn(move || {
+            let _guard = poisoner.lock().unwrap();
+            panic!("watcher died");
+        })
+        .join()
+        .unwrap_err();
+        assert!(store.is_poisoned());
+    }
+
+    #[rstest]
+    fn test_export_snapshot_poisoned_store() {
+        let store = Arc::new(Mutex::new(TraceStore::new(TracerConfig::default())));
+        store.lock().unwrap().create_or_update_obj(&test_deployment("depl1"), 0, None);
+        poison(&store);
+
+        let exporter = SnapshotExporter::new(ExportFilters::new(vec![], vec![], true), TraceFormat::Msgpack, None);
+        let (data, object_count) = export_snapshot(&store, &exporter, 10).unwrap();
+        assert_eq!(object_count, 1);
+        assert_eq!(TraceStore::import(data, &None).unwrap().objects().count(), 1);
+        assert!(!store.is_poisoned());
+    }
+
+    #[rstest]
+    fn test_export_snapshot_poisoned_metrics() {
+        let store = Mutex::new(TraceStore::new(TracerConfig::default()));
+        let exporter = SnapshotExporter::new(ExportFilters::new(vec![], vec![], true), TraceFormat::Msgpack, None);
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                let _guard = exporter.metrics.lock().unwrap();
+                panic!("export died");
+            })
+            .join()
+            .unwrap_err();
+        });
+        assert!(exporter.metrics.is_poisoned());
+
+        export_snapshot(&store, &exporter, 10).unwrap();
+        assert_eq!(exporter.metrics().count, 1);
+        assert!(!exporter.metrics.is_poisoned());
+    }
+
+    #[rstest]
+    fn test_poisoned_store_progress_and_checkpoint() {
+        let path = std::env::temp_dir().join(format!("sk-poisoned-checkpoint-test-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        let store = Arc::new(Mutex::new(TraceStore::new(TracerConfig::default())));
+        store.lock().unwrap().create_or_update_obj(&test_deployment("depl1"), 0, None);
+        poison(&store);
+
+        assert_eq!(SnapshotProgress::read(&store), SnapshotProgress { object_count: 1, pod_count: 0 });
+        assert!(!store.is_poisoned());
+
+        poison(&store);
+        write_checkpoint(&store, path).unwrap();
+        let new_store = TraceStore::from_checkpoint(&fs::read(path).unwrap(), TracerConfig::default()).unwrap();
+        assert_eq!(new_store.objects().count(), 1);
+        assert!(!store.is_poisoned());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[rstest]
+    fn test_snapshot_summary_json_timed_out() {
+        let summary = SnapshotSummary {
+            output_path: "trace.out".into(),
+            object_count: 0,
+            byte_size: 0,
+            duration_secs: 1.0,
+            timed_out: true,
+            incomplete_watchers: vec![],
+            export_metrics: None,
+        };
+        assert_eq!(serde_json::to_value(&summary).unwrap()["timedOut"], true);
+    }
+
+    #[rstest]
+    fn test_quiet_and_json_compatible() {
+        let args = parse_args(&["--quiet", "--json"]);
+        let status = StatusPrinter::new(&args);
+        assert!(status.quiet);
+        assert!(status.to_stderr);
+    }
+}
@@ -0,0 +1,3 @@
+// Shared by the commands that show a spinner on stderr while they wait for something in the cluster
+// (e.g., `skctl snapshot` waiting for its watchers, or `skctl logs` waiting for the driver pod)
+pub(crate) const SPINNER_FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
@@ -0,0 +1,133 @@
+use anyhow::bail;
+use sk_api::v1::ExportFilters;
+use sk_core::external_storage::{
+    ObjectStoreWrapper,
+    SkObjectStore,
+};
+use sk_core::prelude::*;
+use sk_core::time::duration_to_secs;
+use sk_store::TraceStore;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(long_help = "location of the input trace file")]
+    pub input: String,
+
+    #[arg(
+        short,
+        long,
+        long_help = "length of each window, e.g., 5m or 1h",
+        value_parser = duration_to_secs
+    )]
+    pub window: i64,
+
+    #[arg(
+        long,
+        long_help = "prefix for the output files; the Nth window is written to <prefix>-N.out\n\
+            (can be a local path or a cloud storage URL)"
+    )]
+    pub out_prefix: String,
+}
+
+pub async fn cmd(args: &Args) -> EmptyResult {
+    println!("Reading trace file: {}", args.input);
+    let data = SkObjectStore::new(&args.input)?.get().await?.to_vec();
+    let store = TraceStore::import(data, &None)?;
+
+    for (i, (start_ts, end_ts, data)) in split_trace(&store, args.window)?.into_iter().enumerate() {
+        let path = format!("{}-{i}.out", args.out_prefix);
+        println!("Writing window {start_ts}-{end_ts} to {path}");
+        SkObjectStore::new(&path)?.put(data.into()).await?;
+    }
+
+    println!("Done!");
+    Ok(())
+}
+
+// Each window is exported just like a normal trace with different start and end times, which means
+// that anything that exists at the start of a window is flattened into that window's first event;
+// so objects that span a window boundary show up in every window that they overlap.  Windows are
+// end-exclusive, and the last one is extended (if needed) to include the final event in the trace.
+fn split_trace(store: &TraceStore, window: i64) -> anyhow::Result<Vec<(i64, i64, Vec<u8>)>> {
+    let Some((trace_start_ts, trace_end_ts)) = store.time_bounds() else {
+        bail!("trace is empty");
+    };
+
+    let filters = ExportFilters::default();
+    let mut windows = vec![];
+    let mut start_ts = trace_start_ts;
+    while start_ts <= trace_end_ts {
+        let end_ts = start_ts + window;
+        windows.push((start_ts, end_ts, store.export(start_ts, end_ts, &filters)?));
+        start_ts = end_ts;
+    }
+    Ok(windows)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use rstest::*;
+    use sk_core::k8s::testutils::*;
+    use sk_core::k8s::KubeResourceExt;
+    use sk_store::{
+        TraceStorable,
+        TracerConfig,
+    };
+
+    use super::*;
+
+    fn window_objs(data: Vec<u8>) -> BTreeSet<String> {
+        let store = TraceStore::import(data, &None).unwrap();
+        store
+            .iter()
+            .flat_map(|(evt, _)| evt.applied_objs.iter().map(|obj| obj.namespaced_name()))
+            .collect()
+    }
+
+    #[rstest]
+    #[case::no_plus("5m", 300)]
+    #[case::plus("+1h", 3600)]
+    fn test_parse_window(#[case] window: &str, #[case] expected: i64) {
+        assert_eq!(duration_to_secs(window).unwrap(), expected);
+    }
+
+    #[rstest]
+    fn test_parse_window_not_positive() {
+        assert!(duration_to_secs("-5m").is_err());
+    }
+
+    #[rstest]
+    fn test_split_trace() {
+        let mut store = TraceStore::new(TracerConfig::default());
+        store.create_or_update_obj(&test_deployment("depl1"), 0, None);
+        store.create_or_update_obj(&test_deployment("depl2"), 5, None);
+        store.delete_obj(&test_deployment("depl1"), 12);
+        store.create_or_update_obj(&test_deployment("depl3"), 15, None);
+
+        let windows = split_trace(&store, 10).unwrap();
+        let bounds: Vec<_> = windows.iter().map(|(start_ts, end_ts, _)| (*start_ts, *end_ts)).collect();
+        assert_eq!(bounds, vec![(0, 10), (10, 20)]);
+
+        // depl1 and depl2 both span the boundary, so they show up in both windows
+        let mut objs = windows.into_iter().map(|(_, _, data)| window_objs(data));
+        assert_eq!(
+            objs.next().unwrap(),
+            BTreeSet::from([format!("{TEST_NAMESPACE}/depl1"), format!("{TEST_NAMESPACE}/depl2")])
+        );
+        assert_eq!(
+            objs.next().unwrap(),
+            BTreeSet::from([
+                format!("{TEST_NAMESPACE}/depl1"),
+                format!("{TEST_NAMESPACE}/depl2"),
+                format!("{TEST_NAMESPACE}/depl3")
+            ])
+        );
+    }
+
+    #[rstest]
+    fn test_split_trace_empty() {
+        assert!(split_trace(&TraceStore::new(TracerConfig::default()), 10).is_err());
+    }
+}
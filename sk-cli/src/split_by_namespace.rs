@@ -0,0 +1,130 @@
+use anyhow::bail;
+use sk_api::v1::ExportFilters;
+use sk_core::external_storage::{
+    ObjectStoreWrapper,
+    SkObjectStore,
+};
+use sk_core::prelude::*;
+use sk_store::TraceStore;
+
+#[derive(clap::Args)]
+pub struct Args {
+    #[arg(long_help = "location of the input trace file")]
+    pub input: String,
+
+    #[arg(
+        long,
+        long_help = "directory for the output files; the trace for each namespace is written to\n\
+            <out-dir>/<namespace>.out (can be a local path or a cloud storage URL)"
+    )]
+    pub out_dir: String,
+}
+
+pub async fn cmd(args: &Args) -> EmptyResult {
+    println!("Reading trace file: {}", args.input);
+    let data = SkObjectStore::new(&args.input)?.get().await?.to_vec();
+    let store = TraceStore::import(data, &None)?;
+
+    for (ns, data) in split_by_namespace(&store)? {
+        let path = format!("{}/{ns}.out", args.out_dir.trim_end_matches('/'));
+        println!("Writing namespace {ns} to {path}");
+        SkObjectStore::new(&path)?.put(data.into()).await?;
+    }
+
+    println!("Done!");
+    Ok(())
+}
+
+// Each namespace is exported just like a normal trace covering the whole time range, but with only
+// that namespace included; cluster-scoped objects that something in the namespace depends on (e.g.,
+// PriorityClasses or ClusterRoles) are included too, so they end up in every file that needs them.
+fn split_by_namespace(store: &TraceStore) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+    let Some((start_ts, end_ts)) = store.time_bounds() else {
+        bail!("trace is empty");
+    };
+
+    let mut namespaces: Vec<_> = store.namespaces().into_iter().collect();
+    namespaces.sort();
+
+    namespaces
+        .into_iter()
+        .map(|ns| {
+            let filters = ExportFilters {
+                included_namespaces: Some(vec![ns.clone()]),
+                ..Default::default()
+            };
+
+            // Exports are end-exclusive, so we have to go one past the end to get the last event
+            let data = store.export(start_ts, end_ts + 1, &filters)?;
+            Ok((ns, data))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use kube::api::DynamicObject;
+    use rstest::*;
+    use serde_json::json;
+    use sk_core::k8s::KubeResourceExt;
+    use sk_store::{
+        TraceStorable,
+        TracerConfig,
+    };
+
+    use super::*;
+
+    fn obj(kind: &str, ns: Option<&str>, name: &str, spec: serde_json::Value) -> DynamicObject {
+        serde_json::from_value(json!({
+            "apiVersion": "v1",
+            "kind": kind,
+            "metadata": {"namespace": ns, "name": name},
+            "spec": spec,
+        }))
+        .unwrap()
+    }
+
+    fn trace_objs(data: Vec<u8>) -> BTreeSet<String> {
+        let store = TraceStore::import(data, &None).unwrap();
+        store
+            .iter()
+            .flat_map(|(evt, _)| evt.applied_objs.iter().map(|obj| obj.namespaced_name()))
+            .collect()
+    }
+
+    #[rstest]
+    fn test_split_by_namespace() {
+        let high_prio_spec = json!({"template": {"spec": {"priorityClassName": "high"}}});
+        let mut store = TraceStore::new(TracerConfig::default());
+        store.create_or_update_obj(&obj("PriorityClass", None, "high", json!({})), 0, None);
+        store.create_or_update_obj(&obj("PriorityClass", None, "unused", json!({})), 0, None);
+        store.create_or_update_obj(&obj("Deployment", Some("team-a"), "depl1", high_prio_spec.clone()), 1, None);
+        store.create_or_update_obj(&obj("Deployment", Some("team-a"), "depl2", json!({})), 2, None);
+        store.create_or_update_obj(&obj("Deployment", Some("team-b"), "depl3", high_prio_spec), 3, None);
+        store.create_or_update_obj(&obj("Deployment", Some("team-c"), "depl4", json!({})), 4, None);
+
+        let traces: Vec<_> = split_by_namespace(&store)
+            .unwrap()
+            .into_iter()
+            .map(|(ns, data)| (ns, trace_objs(data)))
+            .collect();
+
+        // The PriorityClass that's used in team-a and team-b ends up in both of their traces, and
+        // the one that isn't used anywhere doesn't show up at all
+        assert_eq!(
+            traces,
+            vec![
+                ("team-a".into(), BTreeSet::from(["high".into(), "team-a/depl1".into(), "team-a/depl2".into()])),
+                ("team-b".into(), BTreeSet::from(["high".into(), "team-b/depl3".into()])),
+                ("team-c".into(), BTreeSet::from(["team-c/depl4".into()])),
+            ]
+        );
+    }
+
+    #[rstest]
+    fn test_split_by_namespace_empty() {
+        assert!(split_by_namespace(&TraceStore::new(TracerConfig::default())).is_err());
+    }
+}
@@ -28,6 +28,10 @@ impl AnnotatedTrace {
     pub async fn new(trace_path: &str) -> anyhow::Result<AnnotatedTrace> {
         let object_store = SkObjectStore::new(trace_path)?;
         let trace_data = object_store.get().await?.to_vec();
+        AnnotatedTrace::from_trace_data(trace_path, trace_data)
+    }
+
+    pub fn from_trace_data(trace_path: &str, trace_data: Vec<u8>) -> anyhow::Result<AnnotatedTrace> {
         let base = TraceStore::import(trace_data, &None)?;
         let events = base
             .iter()
@@ -75,6 +75,12 @@ fn print_summary(trace: &AnnotatedTrace, validators: &ValidationStore) -> EmptyR
         let name = validators.lookup(code)?.name;
         println!("{name} ({code}): {count:.>30}");
     }
+
+    // These aren't rules (there's nothing to explain or fix up), they just mean that the trace
+    // doesn't hang together, usually because it was edited by hand
+    for issue in trace.base.validate() {
+        println!("inconsistent trace: {issue}");
+    }
     Ok(())
 }
 
@@ -1,40 +1,419 @@
+use std::collections::{
+    HashMap,
+    HashSet,
+};
+use std::hash::{
+    DefaultHasher,
+    Hash,
+    Hasher,
+};
+use std::os::unix::fs::MetadataExt;
+use std::time::{
+    Duration,
+    Instant,
+    SystemTime,
+};
+use std::{
+    fmt,
+    fs,
+};
+
+use clap::ValueEnum;
+use kube::api::DynamicObject;
+use kube::ResourceExt;
 use ratatui::widgets::ListState;
+use sk_core::external_storage::{
+    ObjectStoreWrapper,
+    SkObjectStore,
+};
+use sk_core::k8s::KubeResourceExt;
+use sk_core::prelude::*;
+use sk_store::encryption::{
+    decrypt_trace,
+    TraceKey,
+};
+use sk_store::TraceStorable;
 
+use super::compare::Comparison;
+use super::detail::DetailPane;
 use crate::validation::{
     AnnotatedTrace,
     ValidationStore,
 };
 
-#[derive(Debug, Default, Eq, PartialEq)]
+pub(super) const FOLLOW_REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+pub(super) const FOLLOW_RETRY_INTERVAL: Duration = Duration::from_millis(250);
+pub(super) const FOLLOW_MAX_RETRIES: usize = 8;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub(super) enum Mode {
     #[default]
     RootView,
     EventSelected,
     ObjectSelected,
+    Detail,
+}
+
+// How the applied and deleted objects for the selected event are ordered; the start time and
+// duration of an object come from the whole trace (when it was first applied and when it was
+// deleted), so objects that we don't have a timestamp for always sort last.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum SortMode {
+    #[default]
+    Name,
+    Namespace,
+    StartTime,
+    Duration,
+}
+
+impl SortMode {
+    pub(super) fn next(self) -> SortMode {
+        match self {
+            SortMode::Name => SortMode::Namespace,
+            SortMode::Namespace => SortMode::StartTime,
+            SortMode::StartTime => SortMode::Duration,
+            SortMode::Duration => SortMode::Name,
+        }
+    }
+}
+
+impl fmt::Display for SortMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            SortMode::Name => "name",
+            SortMode::Namespace => "namespace",
+            SortMode::StartTime => "start time",
+            SortMode::Duration => "duration",
+        };
+        write!(f, "{s}")
+    }
+}
+
+// Enough information about a local trace file to tell whether it's changed since we last looked at
+// it without having to read the whole thing; in particular, if the file gets replaced (e.g., when
+// a snapshot rotates its checkpoint file into place), the inode changes even if the size doesn't.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) struct FileStamp {
+    ino: u64,
+    len: u64,
+    mtime: Option<SystemTime>,
+}
+
+impl FileStamp {
+    // Returns None for anything that isn't a local file that we can stat (including cloud storage
+    // URLs), in which case we just have to read the data to see if it changed
+    pub(super) fn for_path(path: &str) -> Option<FileStamp> {
+        let md = fs::metadata(path.strip_prefix("file://").unwrap_or(path)).ok()?;
+        Some(FileStamp {
+            ino: md.ino(),
+            len: md.len(),
+            mtime: md.modified().ok(),
+        })
+    }
+}
+
+// What xray looks like when it first starts up, before the user has done anything
+#[derive(Debug, Default)]
+pub(super) struct InitialState {
+    pub(super) namespace: Option<String>,
+    pub(super) sort_mode: SortMode,
+    pub(super) read_only: bool,
 }
 
 #[derive(Default)]
 pub(super) struct App {
     pub(super) running: bool,
     pub(super) mode: Mode,
+    pub(super) sort_mode: SortMode,
 
     pub(super) trace: AnnotatedTrace,
+
+    // The number of distinct objects in the trace, for the footer; this is the whole trace (the
+    // view filters don't change it), so it's only recomputed when the trace changes, in set_trace
+    pub(super) object_count: usize,
+
     #[allow(dead_code)]
     pub(super) validation_store: ValidationStore,
 
     pub(super) event_list_state: ListState,
     pub(super) object_list_state: ListState,
     pub(super) object_contents_list_state: ListState,
+
+    // Only set in Mode::Detail; the pane keeps its own copy of the object, so it doesn't care if the
+    // trace changes underneath it
+    pub(super) detail: Option<DetailPane>,
+
+    // Objects the user has hidden from the object lists (see `hide_key`); this is purely a view
+    // filter, the trace itself is never modified.
+    pub(super) hidden: HashSet<String>,
+
+    // Like hiding objects, this only changes what's shown; it can only be set on the command line
+    pub(super) namespace_filter: Option<String>,
+
+    // In read-only mode the sort order and the set of hidden objects can't be changed, so that
+    // whatever the view started with is what you get
+    pub(super) read_only: bool,
+
+    // If colors are turned off, pod statuses are shown with plain ASCII markers instead
+    pub(super) no_color: bool,
+
+    // In follow mode we re-read the trace file every FOLLOW_REFRESH_INTERVAL; we keep a hash of
+    // the last data we loaded so that we don't rebuild (and reset) everything if nothing changed.
+    pub(super) follow: bool,
+    pub(super) next_refresh: Option<Instant>,
+    pub(super) refresh_error: Option<String>,
+    pub(super) trace_data_hash: Option<u64>,
+    pub(super) trace_stamp: Option<FileStamp>,
+    pub(super) refresh_retries: usize,
+
+    pub(super) key: Option<TraceKey>,
+
+    // Only set if we're comparing two traces; see compare.rs
+    pub(super) comparison: Option<Comparison>,
 }
 
 impl App {
-    pub(super) async fn new(trace_path: &str) -> anyhow::Result<App> {
-        Ok(App {
+    pub(super) async fn new(
+        trace_path: &str,
+        follow: bool,
+        key: Option<TraceKey>,
+        initial: InitialState,
+    ) -> anyhow::Result<App> {
+        let mut app = App {
             running: true,
-            trace: AnnotatedTrace::new(trace_path).await?,
+            sort_mode: initial.sort_mode,
+            trace: AnnotatedTrace { path: trace_path.into(), ..Default::default() },
             event_list_state: ListState::default().with_selected(Some(0)),
+            namespace_filter: initial.namespace,
+            read_only: initial.read_only,
+            follow,
+            key,
 
             ..Default::default()
-        })
+        };
+
+        // If we're following a trace that's being written, it's fine for the file to not exist
+        // yet (or to be garbage); we'll just start with an empty trace and pick it up later.
+        if follow {
+            app.refresh().await;
+        } else {
+            app.reload().await?;
+        }
+        Ok(app)
+    }
+
+    // The comparison trace is loaded once; comparison mode doesn't support following
+    pub(super) async fn load_comparison(&mut self, path: &str) -> EmptyResult {
+        let trace_data = SkObjectStore::new(path)?.get().await?.to_vec();
+        let trace_data = decrypt_trace(trace_data, self.key.as_ref())?;
+        let trace = AnnotatedTrace::from_trace_data(path, trace_data)?;
+        self.comparison = Some(Comparison::new(&self.trace, trace));
+        Ok(())
+    }
+
+    pub(super) fn refresh_timeout(&self) -> Duration {
+        self.next_refresh
+            .map_or(Duration::ZERO, |t| t.saturating_duration_since(Instant::now()))
+    }
+
+    // If the file is local, we can check whether it's changed (or been replaced) before we bother
+    // reading it.  The file might be missing or only partly written if we catch it in the middle of
+    // being rotated, so if the reload fails, or the file changes while we're reading it, we try
+    // again a few times in quick succession instead of waiting for the next regular refresh.
+    pub(super) async fn refresh(&mut self) {
+        let stamp = FileStamp::for_path(&self.trace.path);
+        if stamp.is_some() && stamp == self.trace_stamp {
+            self.next_refresh = Some(Instant::now() + FOLLOW_REFRESH_INTERVAL);
+            return;
+        }
+
+        let res = self.reload().await;
+        let stable = stamp == FileStamp::for_path(&self.trace.path);
+        match res {
+            Ok(()) if stable => {
+                self.trace_stamp = stamp;
+                self.refresh_error = None;
+                self.refresh_retries = 0;
+            },
+            Ok(()) => self.refresh_retries += 1,
+            Err(err) => {
+                self.refresh_error = Some(format!("{err}"));
+                self.refresh_retries += 1;
+            },
+        }
+
+        let interval = if self.refresh_retries > 0 && self.refresh_retries <= FOLLOW_MAX_RETRIES {
+            FOLLOW_RETRY_INTERVAL
+        } else {
+            FOLLOW_REFRESH_INTERVAL
+        };
+        self.next_refresh = Some(Instant::now() + interval);
+    }
+
+    async fn reload(&mut self) -> EmptyResult {
+        let object_store = SkObjectStore::new(&self.trace.path)?;
+        let trace_data = object_store.get().await?.to_vec();
+
+        let mut hasher = DefaultHasher::new();
+        trace_data.hash(&mut hasher);
+        let hash = hasher.finish();
+        if self.trace_data_hash == Some(hash) {
+            return Ok(());
+        }
+
+        let trace_data = decrypt_trace(trace_data, self.key.as_ref())?;
+        let trace = AnnotatedTrace::from_trace_data(&self.trace.path, trace_data)?;
+        self.set_trace(trace);
+        self.trace_data_hash = Some(hash);
+        Ok(())
+    }
+
+    pub(super) fn set_trace(&mut self, trace: AnnotatedTrace) {
+        let old_len = self.trace.events.len();
+        let selected = self.event_list_state.selected().unwrap_or(0);
+        self.trace = trace;
+        self.object_count = self
+            .trace
+            .events
+            .iter()
+            .flat_map(|evt| evt.data.applied_objs.iter().chain(evt.data.deleted_objs.iter()))
+            .map(|obj| obj.namespaced_name())
+            .collect::<HashSet<_>>()
+            .len();
+
+        // Like `tail -f`, if the user was sitting on the last event, keep them on the last event
+        // as new ones come in; otherwise we try to leave the selection alone, unless the trace got
+        // shorter out from under us (e.g., the file was overwritten), in which case we bail back
+        // out to the root view so we're not pointing at an event that doesn't exist.
+        let new_len = self.trace.events.len();
+        if new_len == 0 {
+            self.mode = Mode::RootView;
+            self.detail = None;
+            self.event_list_state.select(Some(0));
+        } else if selected >= new_len {
+            self.mode = Mode::RootView;
+            self.detail = None;
+            self.event_list_state.select(Some(new_len - 1));
+        } else if self.follow && self.mode == Mode::RootView && old_len > 0 && selected == old_len - 1 {
+            self.event_list_state.select(Some(new_len - 1));
+        }
     }
+
+    // Hides the selected object (in every event, not just the selected one); the selection stays at
+    // the same position in the list, which means it moves to the next object, unless we hid the last
+    // one, in which case it moves up.  If we were looking at the object's contents, we go back to
+    // the object list, since the object isn't there anymore.
+    pub(super) fn hide_selected(&mut self) {
+        if self.mode == Mode::RootView {
+            return;
+        }
+        let (Some(evt_idx), Some(obj_idx)) = (self.event_list_state.selected(), self.object_list_state.selected())
+        else {
+            return;
+        };
+        let Some((obj, _)) = self.sorted_objects(evt_idx).get(obj_idx).copied() else {
+            return;
+        };
+        self.hidden.insert(hide_key(obj));
+
+        let remaining = self.sorted_objects(evt_idx).len();
+        self.object_list_state.select(Some(obj_idx.min(remaining.saturating_sub(1))));
+        if self.mode == Mode::ObjectSelected {
+            self.mode = Mode::EventSelected;
+            self.object_contents_list_state.select(None);
+        }
+    }
+
+    pub(super) fn unhide_all(&mut self) {
+        self.hidden.clear();
+    }
+
+    // Opens the detail pane for the selected object; closing it goes back to wherever we were
+    pub(super) fn open_detail(&mut self) {
+        if !matches!(self.mode, Mode::EventSelected | Mode::ObjectSelected) {
+            return;
+        }
+        let (Some(evt_idx), Some(obj_idx)) = (self.event_list_state.selected(), self.object_list_state.selected())
+        else {
+            return;
+        };
+        let Some((obj, op)) = self.sorted_objects(evt_idx).get(obj_idx).copied() else {
+            return;
+        };
+
+        let lifecycles = self.trace.base.pod_lifecycles_for(&obj.namespaced_name());
+        let start_ts = self.trace.base.start_ts().unwrap_or(0);
+        let detail = DetailPane::new(obj, op, lifecycles, start_ts, self.no_color, self.mode);
+        self.detail = Some(detail);
+        self.mode = Mode::Detail;
+    }
+
+    pub(super) fn close_detail(&mut self) {
+        if let Some(detail) = self.detail.take() {
+            self.mode = detail.return_mode;
+        }
+    }
+
+    // The applied and deleted objects for an event, in the current sort order, tagged with "+" or
+    // "-" respectively; the object list in the view is indexed by position in this list.  The sort
+    // is stable, so objects that compare equal stay in trace order.
+    pub(super) fn sorted_objects(&self, evt_idx: usize) -> Vec<(&DynamicObject, &'static str)> {
+        let Some(evt) = self.trace.events.get(evt_idx) else {
+            return vec![];
+        };
+        let mut objs: Vec<_> = evt
+            .data
+            .applied_objs
+            .iter()
+            .map(|obj| (obj, "+"))
+            .chain(evt.data.deleted_objs.iter().map(|obj| (obj, "-")))
+            .filter(|(obj, _)| !self.hidden.contains(&hide_key(obj)))
+            .filter(|(obj, _)| {
+                self.namespace_filter.is_none() || obj.namespace().as_deref() == self.namespace_filter.as_deref()
+            })
+            .collect();
+
+        match self.sort_mode {
+            SortMode::Name => objs.sort_by_key(|(obj, _)| obj.name_any()),
+            SortMode::Namespace => objs.sort_by_key(|(obj, _)| obj.namespace().unwrap_or_default()),
+            SortMode::StartTime | SortMode::Duration => {
+                let lifetimes = self.object_lifetimes();
+                objs.sort_by_key(|(obj, _)| {
+                    let (start_ts, end_ts) = lifetimes.get(&obj.namespaced_name()).copied().unwrap_or_default();
+                    let key = match self.sort_mode {
+                        SortMode::StartTime => start_ts,
+                        _ => start_ts.zip(end_ts).map(|(start, end)| end - start),
+                    };
+                    (key.is_none(), key)
+                });
+            },
+        }
+        objs
+    }
+
+    // Objects that are never deleted don't have a duration; objects that get deleted and then
+    // re-created only count their first lifetime.
+    fn object_lifetimes(&self) -> HashMap<String, (Option<i64>, Option<i64>)> {
+        let mut lifetimes: HashMap<String, (Option<i64>, Option<i64>)> = HashMap::new();
+        for evt in &self.trace.events {
+            for obj in &evt.data.applied_objs {
+                let (start_ts, _) = lifetimes.entry(obj.namespaced_name()).or_default();
+                start_ts.get_or_insert(evt.data.ts);
+            }
+            for obj in &evt.data.deleted_objs {
+                let (start_ts, end_ts) = lifetimes.entry(obj.namespaced_name()).or_default();
+                if start_ts.is_some() {
+                    end_ts.get_or_insert(evt.data.ts);
+                }
+            }
+        }
+        lifetimes
+    }
+}
+
+// Objects are hidden by kind and namespaced name, so that hiding an object hides it everywhere it
+// shows up in the trace
+pub(super) fn hide_key(obj: &DynamicObject) -> String {
+    let kind = obj.types.as_ref().map_or("", |t| t.kind.as_str());
+    format!("{kind} {}", obj.namespaced_name())
 }
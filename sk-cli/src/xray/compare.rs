@@ -0,0 +1,80 @@
+use std::collections::BTreeMap;
+
+use kube::api::DynamicObject;
+use ratatui::widgets::ListState;
+use sk_core::k8s::KubeResourceExt;
+use sk_store::TraceStore;
+
+use crate::validation::AnnotatedTrace;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum DiffStatus {
+    Added,
+    Removed,
+    Changed,
+    Unchanged,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(super) struct ObjectDiff {
+    pub(super) kind: String,
+    pub(super) ns_name: String,
+    pub(super) status: DiffStatus,
+}
+
+// In comparison mode, the trace that xray was opened with is the "before" trace, and this holds the
+// "after" trace along with the differences between them.  Both panes in the view are rendered from
+// the same list of diffs (with blank rows for objects that are missing on one side), so a single
+// list state keeps them scrolled in sync.
+#[derive(Default)]
+pub(super) struct Comparison {
+    pub(super) trace: AnnotatedTrace,
+    pub(super) diffs: Vec<ObjectDiff>,
+    pub(super) list_state: ListState,
+}
+
+impl Comparison {
+    pub(super) fn new(before: &AnnotatedTrace, after: AnnotatedTrace) -> Comparison {
+        Comparison {
+            diffs: diff_objects(&before.base, &after.base),
+            trace: after,
+            list_state: ListState::default().with_selected(Some(0)),
+        }
+    }
+
+    pub(super) fn count(&self, status: DiffStatus) -> usize {
+        self.diffs.iter().filter(|d| d.status == status).count()
+    }
+}
+
+// We compare the objects that exist at the end of each trace, matched up by kind and namespaced
+// name; an object has changed if its body (spec, data, etc) or its labels or annotations are
+// different.  The rest of the metadata (uids, resource versions, ...) is expected to differ between
+// traces, and is mostly stripped out by the tracer anyways.  Results are sorted by namespaced name.
+pub(super) fn diff_objects(before: &TraceStore, after: &TraceStore) -> Vec<ObjectDiff> {
+    let key =
+        |obj: &DynamicObject| (obj.namespaced_name(), obj.types.as_ref().map(|t| t.kind.clone()).unwrap_or_default());
+    let before_objs: BTreeMap<_, _> = before.objects().map(|obj| (key(obj), obj)).collect();
+    let mut after_objs: BTreeMap<_, _> = after.objects().map(|obj| (key(obj), obj)).collect();
+
+    let mut diffs = vec![];
+    for ((ns_name, kind), before_obj) in before_objs {
+        let status = match after_objs.remove(&(ns_name.clone(), kind.clone())) {
+            None => DiffStatus::Removed,
+            Some(after_obj) if contents_differ(before_obj, after_obj) => DiffStatus::Changed,
+            Some(_) => DiffStatus::Unchanged,
+        };
+        diffs.push(ObjectDiff { kind, ns_name, status });
+    }
+    for (ns_name, kind) in after_objs.into_keys() {
+        diffs.push(ObjectDiff { kind, ns_name, status: DiffStatus::Added });
+    }
+    diffs.sort_by(|a, b| (&a.ns_name, &a.kind).cmp(&(&b.ns_name, &b.kind)));
+    diffs
+}
+
+fn contents_differ(before: &DynamicObject, after: &DynamicObject) -> bool {
+    before.data != after.data
+        || before.metadata.labels != after.metadata.labels
+        || before.metadata.annotations != after.metadata.annotations
+}
@@ -0,0 +1,197 @@
+use std::ops::Range;
+
+use chrono::TimeDelta;
+use kube::api::DynamicObject;
+use ratatui::prelude::*;
+use sk_core::k8s::{
+    KubeResourceExt,
+    PodLifecycleData,
+};
+use sk_store::PodLifecyclesMap;
+
+use super::app::Mode;
+use super::util::format_duration;
+use super::view::pod_status_indicator;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(super) enum DetailFormat {
+    #[default]
+    Yaml,
+    Json,
+}
+
+impl DetailFormat {
+    fn toggle(self) -> DetailFormat {
+        match self {
+            DetailFormat::Yaml => DetailFormat::Json,
+            DetailFormat::Json => DetailFormat::Yaml,
+        }
+    }
+}
+
+// The full contents of a single object, plus the lifecycles of its pods (if it has any).  Objects
+// can be huge (think ConfigMaps with a whole config file in them), so we serialize the object once
+// when the pane is opened, and only style the lines that are actually on the screen when we draw.
+pub(super) struct DetailPane {
+    pub(super) title: String,
+    pub(super) format: DetailFormat,
+    pub(super) lines: Vec<String>,
+    pub(super) lifecycle_lines: Vec<Line<'static>>,
+    pub(super) scroll: usize,
+
+    // The number of lines we had room for the last time we drew the pane, so we know how far to
+    // go for page up/down and where to stop scrolling
+    pub(super) height: usize,
+
+    // Where to go back to when the pane is closed
+    pub(super) return_mode: Mode,
+
+    obj: DynamicObject,
+}
+
+impl DetailPane {
+    pub(super) fn new(
+        obj: &DynamicObject,
+        op: &str,
+        lifecycles: Option<&PodLifecyclesMap>,
+        start_ts: i64,
+        no_color: bool,
+        return_mode: Mode,
+    ) -> DetailPane {
+        let kind = obj.types.as_ref().map_or("<unknown>", |t| t.kind.as_str());
+        let mut pane = DetailPane {
+            title: format!("{op} {kind} {}", obj.namespaced_name()),
+            format: DetailFormat::default(),
+            lines: vec![],
+            lifecycle_lines: lifecycles.map_or(vec![], |l| lifecycle_lines(l, start_ts, no_color)),
+            scroll: 0,
+            height: 0,
+            return_mode,
+            obj: obj.clone(),
+        };
+        pane.lines = serialize(&pane.obj, pane.format);
+        pane
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.lines.len() + self.lifecycle_lines.len()
+    }
+
+    pub(super) fn toggle_format(&mut self) {
+        self.format = self.format.toggle();
+        self.lines = serialize(&self.obj, self.format);
+        self.scroll = 0;
+    }
+
+    // We stop scrolling once the last line is at the bottom of the pane
+    pub(super) fn scroll_by(&mut self, delta: isize) {
+        let max_scroll = self.len().saturating_sub(self.height.max(1));
+        self.scroll = self.scroll.saturating_add_signed(delta).min(max_scroll);
+    }
+
+    pub(super) fn page(&self) -> isize {
+        self.height.max(1) as isize
+    }
+
+    pub(super) fn resize(&mut self, height: usize) {
+        self.height = height;
+        self.scroll_by(0);
+    }
+
+    pub(super) fn visible_range(&self) -> Range<usize> {
+        self.scroll..self.len().min(self.scroll + self.height)
+    }
+
+    pub(super) fn visible_lines(&self) -> Vec<Line<'_>> {
+        self.visible_range()
+            .map(|i| match self.lines.get(i) {
+                Some(line) => highlight(line, self.format),
+                None => self.lifecycle_lines[i - self.lines.len()].clone(),
+            })
+            .collect()
+    }
+}
+
+fn serialize(obj: &DynamicObject, format: DetailFormat) -> Vec<String> {
+    let res = match format {
+        DetailFormat::Yaml => serde_yaml::to_string(obj).map_err(anyhow::Error::from),
+        DetailFormat::Json => serde_json::to_string_pretty(obj).map_err(anyhow::Error::from),
+    };
+    match res {
+        Ok(s) => s.lines().map(String::from).collect(),
+        Err(err) => vec![format!("could not serialize object: {err}")],
+    }
+}
+
+// Pod hashes are shown in sorted order so that the pane doesn't shuffle around every time it's
+// opened; pod times are relative to the start of the trace, like the event list.
+fn lifecycle_lines(lifecycles: &PodLifecyclesMap, start_ts: i64, no_color: bool) -> Vec<Line<'static>> {
+    let ts_str = |ts: &i64| format_duration(TimeDelta::new(ts - start_ts, 0).unwrap_or_default());
+
+    let mut hashes: Vec<_> = lifecycles.keys().collect();
+    hashes.sort();
+
+    let mut lines = vec![];
+    for hash in hashes {
+        lines.push(Line::default());
+        lines.push(Line::styled(format!("pods with spec hash {hash}:"), Style::new().bold()));
+        for (i, lifecycle_data) in lifecycles[hash].iter().enumerate() {
+            let desc = match lifecycle_data {
+                PodLifecycleData::Empty => "no lifecycle data".into(),
+                PodLifecycleData::Pending(ts) => format!("pending since {}", ts_str(ts)),
+                PodLifecycleData::Running(ts) => format!("running since {}", ts_str(ts)),
+                PodLifecycleData::Finished(start, end, reason) => {
+                    let reason = reason.as_ref().map_or(String::new(), |r| format!(" ({r})"));
+                    format!("ran from {} to {}{reason}", ts_str(start), ts_str(end))
+                },
+            };
+            lines.push(Line::from(vec![
+                Span::raw("  "),
+                pod_status_indicator(lifecycle_data, no_color),
+                Span::raw(format!(" {i}: {desc}")),
+            ]));
+        }
+    }
+    lines
+}
+
+// This isn't a real parser, it just looks for something that looks like a key at the start of the
+// line; that's good enough for the output of serde_yaml and serde_json, which is all we ever show.
+pub(super) fn highlight(line: &str, format: DetailFormat) -> Line<'_> {
+    let mut body = line.trim_start_matches(' ');
+    if format == DetailFormat::Yaml {
+        while let Some(rest) = body.strip_prefix("- ") {
+            body = rest;
+        }
+    }
+    let prefix = &line[..line.len() - body.len()];
+
+    let key_end = match format {
+        DetailFormat::Yaml if body.starts_with(['\'', '"']) => None,
+        DetailFormat::Yaml => body.find(": ").or_else(|| body.ends_with(':').then(|| body.len() - 1)),
+        DetailFormat::Json if body.starts_with('"') => body.find("\": ").map(|i| i + 1),
+        DetailFormat::Json => None,
+    };
+
+    let mut spans = vec![Span::raw(prefix)];
+    let value = match key_end {
+        Some(i) => {
+            spans.push(Span::styled(&body[..i], Style::new().fg(Color::Cyan)));
+            spans.push(Span::raw(":"));
+            &body[i + 1..]
+        },
+        None => body,
+    };
+    spans.push(Span::styled(value, value_style(value)));
+    Line::from(spans)
+}
+
+fn value_style(value: &str) -> Style {
+    let v = value.trim().trim_end_matches(',');
+    match v {
+        "" | "{" | "}" | "[" | "]" | "{}" | "[]" | "|" | "|-" | ">" | ">-" => Style::new(),
+        "true" | "false" | "null" | "~" => Style::new().fg(Color::Yellow),
+        _ if v.parse::<f64>().is_ok() => Style::new().fg(Color::Yellow),
+        _ => Style::new().fg(Color::Green),
+    }
+}
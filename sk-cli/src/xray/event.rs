@@ -1,24 +1,40 @@
 use ratatui::crossterm::event::{
+    poll,
     read,
     Event,
     KeyCode,
     KeyEventKind,
 };
 
+use super::app::Mode;
 use super::{
     App,
     Message,
 };
 
-pub(super) fn handle_event(_app: &App) -> anyhow::Result<Message> {
+pub(super) fn handle_event(app: &App) -> anyhow::Result<Message> {
+    // In follow mode we can't block forever waiting for a keypress, because we need to wake up
+    // and check for new trace data periodically
+    if app.follow && !poll(app.refresh_timeout())? {
+        return Ok(Message::Refresh);
+    }
+
     if let Event::Key(key) = read()? {
         if key.kind == KeyEventKind::Press {
             return Ok(match key.code {
                 KeyCode::Char(' ') => Message::Select,
                 KeyCode::Down | KeyCode::Char('j') => Message::Down,
+                KeyCode::Enter if app.mode == Mode::Detail => Message::CloseDetail,
+                KeyCode::Enter => Message::OpenDetail,
                 KeyCode::Esc => Message::Deselect,
+                KeyCode::Char('f') => Message::ToggleFormat,
+                KeyCode::PageDown => Message::PageDown,
+                KeyCode::PageUp => Message::PageUp,
                 KeyCode::Up | KeyCode::Char('k') => Message::Up,
                 KeyCode::Char('q') => Message::Quit,
+                KeyCode::Char('s') => Message::CycleSort,
+                KeyCode::Char('x') => Message::HideSelected,
+                KeyCode::Char('X') => Message::UnhideAll,
                 _ => Message::Unknown,
             });
         }
@@ -1,4 +1,6 @@
 mod app;
+mod compare;
+mod detail;
 mod event;
 mod update;
 mod util;
@@ -7,8 +9,13 @@ mod view;
 use ratatui::backend::Backend;
 use ratatui::Terminal;
 use sk_core::prelude::*;
+use sk_store::encryption::TraceKey;
 
-use self::app::App;
+use self::app::{
+    App,
+    InitialState,
+    SortMode,
+};
 use self::event::handle_event;
 use self::update::{
     update,
@@ -16,25 +23,112 @@ use self::update::{
 };
 use self::view::view;
 
+// `skctl xray <trace>` is the same as `skctl xray view <trace>`, so that existing scripts (and
+// muscle memory) keep working
 #[derive(clap::Args)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct Args {
+    #[command(subcommand)]
+    pub subcommand: Option<XraySubcommand>,
+
+    #[command(flatten)]
+    pub view: Option<ViewArgs>,
+}
+
+#[derive(clap::Subcommand)]
+pub enum XraySubcommand {
+    #[command(about = "browse a trace interactively")]
+    View(ViewArgs),
+}
+
+#[derive(clap::Args)]
+pub struct ViewArgs {
     #[arg(long_help = "location of the input trace file")]
     pub trace_path: String,
+
+    #[arg(
+        short,
+        long,
+        long_help = "only show objects in this namespace (cluster-scoped objects are hidden too)"
+    )]
+    pub namespace: Option<String>,
+
+    #[arg(
+        long,
+        long_help = "how to sort the objects in each event",
+        value_enum,
+        default_value = "name"
+    )]
+    pub sort: SortMode,
+
+    #[arg(
+        long,
+        long_help = "don't allow the sort order or the hidden objects to be changed once xray starts"
+    )]
+    pub read_only: bool,
+
+    #[arg(
+        short,
+        long,
+        long_help = "keep watching the trace file and refresh the view as it changes (like `tail -f`)"
+    )]
+    pub follow: bool,
+
+    #[arg(
+        long,
+        long_help = "decrypt the trace with the (hex-encoded, 256-bit) key in this file; if this isn't\n\
+            given, the key is read from the SK_TRACE_KEY environment variable (if set)"
+    )]
+    pub key_file: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with = "follow",
+        long_help = "compare the trace against this one, showing the objects at the end of each trace\n\
+            side by side (added objects are green, removed objects are red, and changed objects are yellow)"
+    )]
+    pub compare: Option<String>,
+
+    #[arg(
+        long,
+        long_help = "don't use colors for the pod status markers (also turned off if NO_COLOR is set)"
+    )]
+    pub no_color: bool,
 }
 
 pub async fn cmd(args: &Args) -> EmptyResult {
-    let app = App::new(&args.trace_path).await?;
+    match (&args.subcommand, &args.view) {
+        (Some(XraySubcommand::View(args)), _) | (None, Some(args)) => view_cmd(args).await,
+        // clap won't let us get here, since the trace path is required if there's no subcommand
+        (None, None) => unreachable!(),
+    }
+}
+
+async fn view_cmd(args: &ViewArgs) -> EmptyResult {
+    let key = TraceKey::load(args.key_file.as_deref())?;
+    let initial = InitialState {
+        namespace: args.namespace.clone(),
+        sort_mode: args.sort,
+        read_only: args.read_only,
+    };
+    let mut app = App::new(&args.trace_path, args.follow, key, initial).await?;
+    app.no_color = args.no_color || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    if let Some(compare_path) = &args.compare {
+        app.load_comparison(compare_path).await?;
+    }
     let term = ratatui::init();
-    let res = run_loop(term, app);
+    let res = run_loop(term, app).await;
     ratatui::restore();
     res
 }
 
-fn run_loop<B: Backend>(mut term: Terminal<B>, mut app: App) -> EmptyResult {
+async fn run_loop<B: Backend>(mut term: Terminal<B>, mut app: App) -> EmptyResult {
     while app.running {
         term.draw(|frame| view(&mut app, frame))?;
-        let msg: Message = handle_event(&app)?;
-        update(&mut app, msg);
+        match handle_event(&app)? {
+            Message::Refresh => app.refresh().await,
+            msg => update(&mut app, msg),
+        }
     }
     Ok(())
 }
@@ -0,0 +1,397 @@
+use kube::api::DynamicObject;
+use kube::ResourceExt;
+use ratatui::widgets::ListState;
+use sk_core::k8s::testutils::test_deployment;
+use sk_store::encryption::{
+    encrypt_trace,
+    TraceKey,
+};
+use sk_store::{
+    TraceEvent,
+    TraceStorable,
+    TraceStore,
+};
+
+use super::*;
+use crate::validation::tests::annotated_trace;
+use crate::validation::AnnotatedTrace;
+
+#[fixture]
+fn follow_app() -> App {
+    App {
+        follow: true,
+        event_list_state: ListState::default().with_selected(Some(0)),
+        ..Default::default()
+    }
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_new_follow_missing_trace() {
+    let app = App::new("/tmp/this-trace-does-not-exist.out", true, None, Default::default())
+        .await
+        .unwrap();
+    assert!(app.trace.events.is_empty());
+    assert!(app.refresh_error.is_some());
+    assert!(app.next_refresh.is_some());
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_new_missing_trace() {
+    assert!(App::new("/tmp/this-trace-does-not-exist.out", false, None, Default::default())
+        .await
+        .is_err());
+}
+
+#[rstest]
+#[case::right_key("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f", true)]
+#[case::wrong_key("ffeeddccbbaa99887766554433221100ffeeddccbbaa99887766554433221100", false)]
+#[tokio::test]
+async fn test_new_encrypted_trace(#[case] key: &str, #[case] succeeds: bool) {
+    let mut store = TraceStore::new(Default::default());
+    store.create_or_update_obj(&test_deployment("the-deployment"), 0, None);
+    let trace_key = TraceKey::from_hex("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f").unwrap();
+    let data = encrypt_trace(&store.export(0, 1, &Default::default()).unwrap(), &trace_key).unwrap();
+
+    let path = std::env::temp_dir().join(format!("sk-xray-test-encrypted-{succeeds}-{}", std::process::id()));
+    std::fs::write(&path, data).unwrap();
+
+    let res = App::new(path.to_str().unwrap(), false, Some(TraceKey::from_hex(key).unwrap()), Default::default()).await;
+    std::fs::remove_file(&path).unwrap();
+    match res {
+        Ok(app) => {
+            assert!(succeeds);
+            assert!(app.trace.base.has_obj(&format!("{TEST_NAMESPACE}/the-deployment")));
+        },
+        Err(err) => {
+            assert!(!succeeds);
+            assert!(err.to_string().contains("key is wrong"));
+        },
+    }
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_new_initial_state() {
+    let mut store = TraceStore::new(Default::default());
+    let mut other = test_deployment("depl2");
+    other.metadata.namespace = Some("other-namespace".into());
+    for obj in [test_deployment("depl1"), other, test_deployment("depl3")] {
+        store.create_or_update_obj(&obj, 0, None);
+    }
+    let path = std::env::temp_dir().join(format!("sk-xray-test-initial-{}", std::process::id()));
+    std::fs::write(&path, store.export_all().unwrap()).unwrap();
+
+    let initial = InitialState {
+        namespace: Some(TEST_NAMESPACE.into()),
+        sort_mode: SortMode::Duration,
+        read_only: true,
+    };
+    let mut app = App::new(path.to_str().unwrap(), false, None, initial).await.unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(app.namespace_filter.as_deref(), Some(TEST_NAMESPACE));
+    assert_eq!(app.sort_mode, SortMode::Duration);
+    let names: Vec<_> = app.sorted_objects(0).iter().map(|(obj, _)| obj.name_any()).collect();
+    assert_eq!(names, vec!["depl1", "depl3"]);
+
+    // Read-only mode doesn't let you change anything
+    update(&mut app, Message::CycleSort);
+    assert_eq!(app.sort_mode, SortMode::Duration);
+}
+
+#[rstest]
+fn test_parse_view_args() {
+    #[derive(clap::Parser)]
+    struct TestCommand {
+        #[command(subcommand)]
+        xray: XrayCommand,
+    }
+
+    #[derive(clap::Subcommand)]
+    enum XrayCommand {
+        #[command(alias = "tui")]
+        Xray(Args),
+    }
+
+    let parse = |argv: &[&str]| {
+        let XrayCommand::Xray(args) = <TestCommand as clap::Parser>::parse_from(argv).xray;
+        args
+    };
+
+    // The old form, without a subcommand, still works
+    let args = parse(&["skctl", "xray", "trace.out"]);
+    assert!(args.subcommand.is_none());
+    assert_eq!(args.view.unwrap().trace_path, "trace.out");
+
+    let args = parse(&["skctl", "tui", "view", "trace.out", "-n", "foo", "--sort", "start-time", "--read-only"]);
+    let Some(XraySubcommand::View(view)) = args.subcommand else {
+        panic!("expected view subcommand")
+    };
+    assert_eq!(view.trace_path, "trace.out");
+    assert_eq!(view.namespace.as_deref(), Some("foo"));
+    assert_eq!(view.sort, SortMode::StartTime);
+    assert!(view.read_only);
+}
+
+fn trace_with(names: &[&str]) -> Vec<u8> {
+    let mut store = TraceStore::new(Default::default());
+    for (i, name) in names.iter().enumerate() {
+        store.create_or_update_obj(&test_deployment(name), i as i64, None);
+    }
+    store.export_all().unwrap()
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_refresh_file_replaced() {
+    let path = std::env::temp_dir().join(format!("sk-xray-test-replaced-{}", std::process::id()));
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&path, trace_with(&["depl1"])).unwrap();
+
+    let mut app = App::new(path.to_str().unwrap(), true, None, Default::default()).await.unwrap();
+    let stamp = app.trace_stamp;
+    assert!(stamp.is_some());
+    assert!(app.trace.base.has_obj(&format!("{TEST_NAMESPACE}/depl1")));
+
+    // Nothing changed, so nothing happens
+    app.refresh().await;
+    assert_eq!(app.trace_stamp, stamp);
+
+    // Move a new file into place, the way snapshot checkpoints get written
+    std::fs::write(&tmp_path, trace_with(&["depl2"])).unwrap();
+    std::fs::rename(&tmp_path, &path).unwrap();
+    app.refresh().await;
+    std::fs::remove_file(&path).unwrap();
+
+    assert_ne!(app.trace_stamp, stamp);
+    assert!(app.trace.base.has_obj(&format!("{TEST_NAMESPACE}/depl2")));
+    assert!(!app.trace.base.has_obj(&format!("{TEST_NAMESPACE}/depl1")));
+    assert_eq!(app.refresh_retries, 0);
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_refresh_retries_partial_file() {
+    let path = std::env::temp_dir().join(format!("sk-xray-test-partial-{}", std::process::id()));
+    let data = trace_with(&["depl1"]);
+    std::fs::write(&path, &data).unwrap();
+    let mut app = App::new(path.to_str().unwrap(), true, None, Default::default()).await.unwrap();
+
+    // A half-written file can't be loaded, so we keep the old trace and retry soon
+    std::fs::write(&path, &data[..data.len() / 2]).unwrap();
+    app.refresh().await;
+    assert!(app.refresh_error.is_some());
+    assert_eq!(app.refresh_retries, 1);
+    assert!(app.refresh_timeout() <= FOLLOW_RETRY_INTERVAL);
+    assert!(app.trace.base.has_obj(&format!("{TEST_NAMESPACE}/depl1")));
+
+    // Once the file is complete, everything goes back to normal
+    std::fs::write(&path, trace_with(&["depl1", "depl2"])).unwrap();
+    app.refresh().await;
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(app.refresh_error.is_none());
+    assert_eq!(app.refresh_retries, 0);
+    assert!(app.refresh_timeout() > FOLLOW_RETRY_INTERVAL);
+    assert!(app.trace.base.has_obj(&format!("{TEST_NAMESPACE}/depl2")));
+}
+
+#[rstest]
+fn test_set_trace_from_empty(mut follow_app: App, annotated_trace: AnnotatedTrace) {
+    follow_app.set_trace(annotated_trace);
+    assert_eq!(follow_app.trace.events.len(), 4);
+    assert_eq!(follow_app.event_list_state.selected(), Some(0));
+}
+
+#[rstest]
+fn test_set_trace_tail(mut follow_app: App, annotated_trace: AnnotatedTrace) {
+    let mut short_trace = AnnotatedTrace {
+        events: annotated_trace.events.clone(),
+        ..Default::default()
+    };
+    short_trace.events.truncate(2);
+    follow_app.set_trace(short_trace);
+    follow_app.event_list_state.select(Some(1));
+
+    follow_app.set_trace(annotated_trace);
+    assert_eq!(follow_app.event_list_state.selected(), Some(3));
+}
+
+#[rstest]
+fn test_set_trace_no_tail(mut follow_app: App, annotated_trace: AnnotatedTrace) {
+    let mut short_trace = AnnotatedTrace {
+        events: annotated_trace.events.clone(),
+        ..Default::default()
+    };
+    short_trace.events.truncate(2);
+    follow_app.set_trace(short_trace);
+    follow_app.event_list_state.select(Some(0));
+
+    follow_app.set_trace(annotated_trace);
+    assert_eq!(follow_app.event_list_state.selected(), Some(0));
+}
+
+#[rstest]
+fn test_set_trace_shrinks(mut follow_app: App, annotated_trace: AnnotatedTrace) {
+    let mut short_trace = AnnotatedTrace {
+        events: annotated_trace.events.clone(),
+        ..Default::default()
+    };
+    short_trace.events.truncate(2);
+    follow_app.set_trace(annotated_trace);
+    follow_app.mode = Mode::EventSelected;
+    follow_app.event_list_state.select(Some(3));
+    assert_eq!(follow_app.object_count, 2);
+
+    follow_app.set_trace(short_trace);
+    assert_eq!(follow_app.mode, Mode::RootView);
+    assert_eq!(follow_app.event_list_state.selected(), Some(1));
+    assert_eq!(follow_app.object_count, 1);
+}
+
+fn depl(name: &str, ns: &str) -> DynamicObject {
+    test_deployment(name).within(ns)
+}
+
+#[fixture]
+fn sort_app(annotated_trace: AnnotatedTrace) -> App {
+    let events = vec![
+        (0, vec![depl("zzz", "ns-b"), depl("aaa", "ns-c")], vec![]),
+        (5, vec![depl("mmm", "ns-a")], vec![]),
+        // "ghost" was never applied, so it doesn't have a start time or duration
+        (9, vec![], vec![depl("mmm", "ns-a"), depl("zzz", "ns-b"), depl("ghost", "ns-a")]),
+    ];
+    App {
+        trace: AnnotatedTrace {
+            events: events
+                .into_iter()
+                .map(|(ts, applied_objs, deleted_objs)| {
+                    let mut evt = annotated_trace.events[0].clone();
+                    evt.data = TraceEvent { ts, applied_objs, deleted_objs };
+                    evt
+                })
+                .collect(),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[rstest]
+#[case::name(SortMode::Name, vec!["ghost", "mmm", "zzz"])]
+#[case::namespace(SortMode::Namespace, vec!["mmm", "ghost", "zzz"])]
+#[case::start_time(SortMode::StartTime, vec!["zzz", "mmm", "ghost"])]
+#[case::duration(SortMode::Duration, vec!["mmm", "zzz", "ghost"])]
+fn test_sorted_objects(mut sort_app: App, #[case] sort_mode: SortMode, #[case] expected: Vec<&str>) {
+    sort_app.sort_mode = sort_mode;
+    let names: Vec<_> = sort_app
+        .sorted_objects(2)
+        .into_iter()
+        .map(|(obj, op)| {
+            assert_eq!(op, "-");
+            obj.metadata.name.as_deref().unwrap()
+        })
+        .collect();
+    assert_eq!(names, expected);
+}
+
+#[rstest]
+fn test_cycle_sort(mut sort_app: App) {
+    let mut seen = vec![sort_app.sort_mode];
+    for _ in 0..4 {
+        update(&mut sort_app, Message::CycleSort);
+        seen.push(sort_app.sort_mode);
+    }
+    assert_eq!(
+        seen,
+        vec![SortMode::Name, SortMode::Namespace, SortMode::StartTime, SortMode::Duration, SortMode::Name]
+    );
+}
+
+#[rstest]
+fn test_hide_selected(mut sort_app: App) {
+    update(&mut sort_app, Message::Select);
+    sort_app.event_list_state.select(Some(2));
+    sort_app.object_list_state.select(Some(2));
+    update(&mut sort_app, Message::Select);
+
+    // "zzz" is last, so the selection moves up; we also hid the object we were looking at
+    update(&mut sort_app, Message::HideSelected);
+    assert_eq!(sort_app.mode, Mode::EventSelected);
+    assert_eq!(sort_app.object_list_state.selected(), Some(1));
+
+    // Hidden objects are hidden in every event, not just the one we hid them from
+    let names: Vec<_> = sort_app.sorted_objects(0).into_iter().map(|(obj, _)| obj.name_any()).collect();
+    assert_eq!(names, vec!["aaa"]);
+
+    update(&mut sort_app, Message::HideSelected);
+    assert_eq!(sort_app.hidden.len(), 2);
+    assert_eq!(sort_app.sorted_objects(2).len(), 1);
+    assert_eq!(sort_app.trace.events[2].data.deleted_objs.len(), 3);
+
+    update(&mut sort_app, Message::UnhideAll);
+    assert!(sort_app.hidden.is_empty());
+    assert_eq!(sort_app.sorted_objects(2).len(), 3);
+}
+
+#[rstest]
+fn test_hide_selected_root_view(mut sort_app: App) {
+    sort_app.object_list_state.select(Some(0));
+    update(&mut sort_app, Message::HideSelected);
+    assert!(sort_app.hidden.is_empty());
+}
+
+#[rstest]
+fn test_open_detail(mut sort_app: App) {
+    // Nothing's selected yet, so there's nothing to show
+    update(&mut sort_app, Message::OpenDetail);
+    assert_eq!(sort_app.mode, Mode::RootView);
+    assert!(sort_app.detail.is_none());
+
+    sort_app.event_list_state.select(Some(2));
+    update(&mut sort_app, Message::Select);
+    update(&mut sort_app, Message::Down);
+    update(&mut sort_app, Message::OpenDetail);
+    assert_eq!(sort_app.mode, Mode::Detail);
+    let detail = sort_app.detail.as_ref().unwrap();
+    assert_eq!(detail.title, "- deployment ns-a/mmm");
+    assert!(detail.lines.contains(&"kind: deployment".to_string()));
+
+    // The object lists don't change while the pane is open
+    update(&mut sort_app, Message::Down);
+    update(&mut sort_app, Message::HideSelected);
+    assert_eq!(sort_app.object_list_state.selected(), Some(1));
+    assert!(sort_app.hidden.is_empty());
+
+    update(&mut sort_app, Message::CloseDetail);
+    assert_eq!(sort_app.mode, Mode::EventSelected);
+    assert!(sort_app.detail.is_none());
+    assert_eq!(sort_app.object_list_state.selected(), Some(1));
+
+    // Escape closes the pane too, and we go back to the object contents if that's where we were
+    update(&mut sort_app, Message::Select);
+    update(&mut sort_app, Message::OpenDetail);
+    assert_eq!(sort_app.mode, Mode::Detail);
+    update(&mut sort_app, Message::Deselect);
+    assert_eq!(sort_app.mode, Mode::ObjectSelected);
+}
+
+#[rstest]
+fn test_set_trace_shrinks_closes_detail(mut follow_app: App, annotated_trace: AnnotatedTrace) {
+    let mut short_trace = AnnotatedTrace {
+        events: annotated_trace.events.clone(),
+        ..Default::default()
+    };
+    short_trace.events.truncate(2);
+    follow_app.set_trace(annotated_trace);
+    follow_app.event_list_state.select(Some(2));
+    update(&mut follow_app, Message::Select);
+    update(&mut follow_app, Message::OpenDetail);
+    assert_eq!(follow_app.mode, Mode::Detail);
+
+    follow_app.set_trace(short_trace);
+    assert_eq!(follow_app.mode, Mode::RootView);
+    assert!(follow_app.detail.is_none());
+}
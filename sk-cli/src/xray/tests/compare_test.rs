@@ -0,0 +1,83 @@
+use kube::api::DynamicObject;
+use serde_json::json;
+use sk_core::k8s::testutils::test_deployment;
+use sk_store::{
+    TraceStorable,
+    TraceStore,
+    TracerConfig,
+};
+
+use super::compare::*;
+use super::*;
+use crate::validation::AnnotatedTrace;
+
+fn depl(name: &str, replicas: i64) -> DynamicObject {
+    let mut obj = test_deployment(name);
+    obj.data = json!({"spec": {"replicas": replicas}});
+    obj
+}
+
+fn store_with(objs: &[DynamicObject]) -> TraceStore {
+    let mut store = TraceStore::new(TracerConfig::default());
+    for obj in objs {
+        store.create_or_update_obj(obj, 0, None);
+    }
+    store
+}
+
+#[rstest]
+fn test_diff_objects() {
+    let mut relabeled = depl("relabeled", 1);
+    relabeled.metadata.labels = Some([("foo".into(), "bar".into())].into());
+
+    let before = store_with(&[depl("changed", 1), depl("removed", 1), depl("relabeled", 1), depl("same", 1)]);
+    let mut after = store_with(&[depl("added", 1), depl("changed", 2), depl("deleted", 1), relabeled, depl("same", 1)]);
+
+    // Objects that are deleted before the end of the trace don't count
+    after.delete_obj(&depl("deleted", 1), 1);
+
+    let diffs: Vec<_> = diff_objects(&before, &after)
+        .into_iter()
+        .map(|d| (d.ns_name.rsplit_once('/').unwrap().1.to_string(), d.status))
+        .collect();
+    assert_eq!(
+        diffs,
+        vec![
+            ("added".into(), DiffStatus::Added),
+            ("changed".into(), DiffStatus::Changed),
+            ("relabeled".into(), DiffStatus::Changed),
+            ("removed".into(), DiffStatus::Removed),
+            ("same".into(), DiffStatus::Unchanged),
+        ]
+    );
+}
+
+#[rstest]
+fn test_comparison_navigation() {
+    let before = AnnotatedTrace {
+        base: store_with(&[depl("a", 1), depl("b", 1)]),
+        ..Default::default()
+    };
+    let after = AnnotatedTrace {
+        base: store_with(&[depl("b", 2), depl("c", 1)]),
+        ..Default::default()
+    };
+    let mut app = App {
+        comparison: Some(Comparison::new(&before, after)),
+        trace: before,
+        ..Default::default()
+    };
+
+    update(&mut app, Message::Down);
+    update(&mut app, Message::Down);
+    update(&mut app, Message::Up);
+
+    let comparison = app.comparison.as_ref().unwrap();
+    assert_eq!(comparison.list_state.selected(), Some(1));
+    assert_eq!(comparison.diffs[1].status, DiffStatus::Changed);
+    assert_eq!(comparison.count(DiffStatus::Added), 1);
+    assert_eq!(comparison.count(DiffStatus::Removed), 1);
+
+    // The regular event list doesn't move in comparison mode
+    assert_eq!(app.event_list_state.selected(), None);
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use assertables::*;
+use ratatui::prelude::*;
+use ratatui::text::Span;
+use sk_core::k8s::testutils::test_deployment;
+use sk_core::k8s::PodLifecycleData;
+
+use super::detail::*;
+use super::*;
+
+fn pane(lifecycles: Option<&sk_store::PodLifecyclesMap>) -> DetailPane {
+    let mut depl = test_deployment("the-deployment");
+    depl.data = serde_json::json!({"spec": {"replicas": 3, "template": {"spec": {"containers": [
+        {"name": "nginx", "image": "nginx:latest", "args": ["--foo", "--bar", "--baz"]},
+    ]}}}});
+    DetailPane::new(&depl, "+", lifecycles, 100, true, Mode::EventSelected)
+}
+
+#[rstest]
+fn test_detail_pane_scroll() {
+    let mut detail = pane(None);
+    let len = detail.len();
+    assert_gt!(len, 10);
+
+    // Only the lines that fit get rendered, and we can't scroll past the end
+    detail.resize(5);
+    assert_eq!(detail.visible_lines().len(), 5);
+    detail.scroll_by(detail.page());
+    assert_eq!(detail.scroll, 5);
+    detail.scroll_by(1000);
+    assert_eq!(detail.scroll, len - 5);
+    assert_eq!(detail.visible_range(), len - 5..len);
+    detail.scroll_by(-1000);
+    assert_eq!(detail.scroll, 0);
+
+    // If the terminal gets bigger than the object, the whole thing fits
+    detail.scroll_by(1);
+    detail.resize(len + 10);
+    assert_eq!(detail.scroll, 0);
+    assert_eq!(detail.visible_lines().len(), len);
+}
+
+#[rstest]
+fn test_detail_pane_toggle_format() {
+    let mut detail = pane(None);
+    assert_eq!(detail.format, DetailFormat::Yaml);
+    detail.resize(3);
+    detail.scroll_by(1);
+
+    detail.toggle_format();
+    assert_eq!(detail.format, DetailFormat::Json);
+    assert_eq!(detail.scroll, 0);
+    assert_eq!(detail.lines[0], "{");
+    assert!(detail.lines.contains(&r#"  "kind": "deployment","#.to_string()));
+}
+
+#[rstest]
+fn test_detail_pane_lifecycles() {
+    let lifecycles = HashMap::from([
+        (2, vec![PodLifecycleData::Running(160)]),
+        (
+            1,
+            vec![PodLifecycleData::Finished(100, 400, Some("OOMKilled".into())), PodLifecycleData::Pending(500)],
+        ),
+    ]);
+    let detail = pane(Some(&lifecycles));
+    let lines: Vec<_> = detail.lifecycle_lines.iter().map(|l| l.to_string()).collect();
+    assert_eq!(
+        lines,
+        vec![
+            "",
+            "pods with spec hash 1:",
+            "  ! 0: ran from 00:00:00 to 00:05:00 (OOMKilled)",
+            "  ~ 1: pending since 00:06:40",
+            "",
+            "pods with spec hash 2:",
+            "  + 0: running since 00:01:00",
+        ]
+    );
+    assert_eq!(detail.len(), detail.lines.len() + 7);
+}
+
+#[rstest]
+#[case::yaml_key("  replicas: 3", DetailFormat::Yaml, vec![("  ", None), ("replicas", Some(Color::Cyan)), (":", None), (" 3", Some(Color::Yellow))])]
+#[case::yaml_list("  - name: foo", DetailFormat::Yaml, vec![("  - ", None), ("name", Some(Color::Cyan)), (":", None), (" foo", Some(Color::Green))])]
+#[case::yaml_map("spec:", DetailFormat::Yaml, vec![("", None), ("spec", Some(Color::Cyan)), (":", None), ("", None)])]
+#[case::yaml_quoted("- 'a: b'", DetailFormat::Yaml, vec![("- ", None), ("'a: b'", Some(Color::Green))])]
+#[case::json_key(r#"  "kind": "Deployment","#, DetailFormat::Json, vec![("  ", None), (r#""kind""#, Some(Color::Cyan)), (":", None), (r#" "Deployment","#, Some(Color::Green))])]
+#[case::json_value("    -1,", DetailFormat::Json, vec![("    ", None), ("-1,", Some(Color::Yellow))])]
+fn test_highlight(#[case] line: &str, #[case] format: DetailFormat, #[case] expected: Vec<(&str, Option<Color>)>) {
+    let expected: Vec<_> = expected
+        .into_iter()
+        .map(|(s, color)| match color {
+            Some(c) => Span::styled(s, Style::new().fg(c)),
+            None => Span::styled(s, Style::new()),
+        })
+        .collect();
+    assert_eq!(highlight(line, format).spans, expected);
+}
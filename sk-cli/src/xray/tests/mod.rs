@@ -1,3 +1,6 @@
+mod app_test;
+mod compare_test;
+mod detail_test;
 mod testutils;
 mod view_test;
 
@@ -1,19 +1,25 @@
 use insta::assert_debug_snapshot;
 use ratatui::backend::TestBackend;
 use ratatui::prelude::*;
+use ratatui::text::Span;
 use ratatui::widgets::ListState;
+use sk_core::k8s::testutils::test_deployment;
+use sk_core::k8s::PodLifecycleData;
+use sk_store::TraceStorable;
 
+use super::compare::Comparison;
 use super::*;
 use crate::validation::tests::annotated_trace;
 use crate::validation::AnnotatedTrace;
 
 #[fixture]
 fn test_app(annotated_trace: AnnotatedTrace) -> App {
-    App {
-        trace: annotated_trace,
+    let mut app = App {
         event_list_state: ListState::default().with_selected(Some(0)),
         ..Default::default()
-    }
+    };
+    app.set_trace(annotated_trace);
+    app
 }
 
 #[rstest]
@@ -40,3 +46,66 @@ fn itest_render_event_list_event_selected(mut test_app: App, #[case] index: usiz
     let cf = term.draw(|frame| view(&mut test_app, frame)).unwrap();
     assert_debug_snapshot!(cf);
 }
+
+#[rstest]
+fn itest_render_footer_narrow(mut test_app: App) {
+    test_app.mode = Mode::EventSelected;
+    test_app.event_list_state.select(Some(2));
+    test_app.object_list_state.select(Some(1));
+    let mut term = Terminal::new(TestBackend::new(40, 20)).unwrap();
+    let cf = term.draw(|frame| view(&mut test_app, frame)).unwrap();
+    assert_debug_snapshot!(cf);
+}
+
+#[rstest]
+fn itest_render_detail(mut test_app: App) {
+    test_app.mode = Mode::EventSelected;
+    test_app.event_list_state.select(Some(2));
+    test_app.object_list_state.select(Some(1));
+    update(&mut test_app, Message::OpenDetail);
+    let mut term = Terminal::new(TestBackend::new(80, 20)).unwrap();
+    let cf = term.draw(|frame| view(&mut test_app, frame)).unwrap();
+    assert_debug_snapshot!(cf);
+}
+
+#[rstest]
+fn itest_render_comparison(mut test_app: App) {
+    let mut changed = test_deployment("changed");
+    let mut before = AnnotatedTrace { path: "before.out".into(), ..Default::default() };
+    for obj in [test_deployment("removed"), changed.clone(), test_deployment("same")] {
+        before.base.create_or_update_obj(&obj, 0, None);
+    }
+
+    changed.data = serde_json::json!({"spec": {"replicas": 2}});
+    let mut after = AnnotatedTrace { path: "after.out".into(), ..Default::default() };
+    for obj in [test_deployment("added"), changed, test_deployment("same")] {
+        after.base.create_or_update_obj(&obj, 0, None);
+    }
+
+    test_app.comparison = Some(Comparison::new(&before, after));
+    test_app.set_trace(before);
+    let mut term = Terminal::new(TestBackend::new(80, 20)).unwrap();
+    let cf = term.draw(|frame| view(&mut test_app, frame)).unwrap();
+    assert_debug_snapshot!(cf);
+}
+
+#[rstest]
+#[case::empty(PodLifecycleData::Empty, "◌", Color::Yellow, "~")]
+#[case::pending(PodLifecycleData::Pending(1), "◌", Color::Yellow, "~")]
+#[case::running(PodLifecycleData::Running(1), "●", Color::Green, "+")]
+#[case::finished_no_reason(PodLifecycleData::Finished(1, 2, None), "○", Color::DarkGray, "-")]
+#[case::completed(PodLifecycleData::Finished(1, 2, Some("Completed".into())), "○", Color::DarkGray, "-")]
+#[case::oom_killed(PodLifecycleData::Finished(1, 2, Some("OOMKilled".into())), "✗", Color::Red, "!")]
+#[case::error(PodLifecycleData::Finished(1, 2, Some("Error".into())), "✗", Color::Red, "!")]
+fn test_pod_status_indicator(
+    #[case] lifecycle_data: PodLifecycleData,
+    #[case] glyph: &str,
+    #[case] color: Color,
+    #[case] ascii: &str,
+) {
+    assert_eq!(
+        view::pod_status_indicator(&lifecycle_data, false),
+        Span::styled(glyph.to_string(), Style::new().fg(color))
+    );
+    assert_eq!(view::pod_status_indicator(&lifecycle_data, true), Span::raw(ascii.to_string()));
+}
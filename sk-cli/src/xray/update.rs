@@ -4,16 +4,58 @@ use super::app::{
 };
 
 pub(super) enum Message {
+    CloseDetail,
+    CycleSort,
     Deselect,
     Down,
+    HideSelected,
+    OpenDetail,
+    PageDown,
+    PageUp,
     Quit,
+    Refresh,
     Select,
+    ToggleFormat,
+    UnhideAll,
     Unknown,
     Up,
 }
 
 pub(super) fn update(app: &mut App, msg: Message) {
+    // In comparison mode there's just the one (synced) object list to move around in
+    if let Some(comparison) = app.comparison.as_mut() {
+        match msg {
+            Message::Down => comparison.list_state.select_next(),
+            Message::Quit => app.running = false,
+            Message::Up => comparison.list_state.select_previous(),
+            _ => (),
+        }
+        return;
+    }
+
+    // The detail pane has its own set of keys; everything that would change the object lists is
+    // ignored until it's closed
+    if let Some(detail) = app.detail.as_mut() {
+        match msg {
+            Message::CloseDetail | Message::Deselect => app.close_detail(),
+            Message::Down => detail.scroll_by(1),
+            Message::PageDown => detail.scroll_by(detail.page()),
+            Message::PageUp => detail.scroll_by(-detail.page()),
+            Message::Quit => app.running = false,
+            Message::ToggleFormat => detail.toggle_format(),
+            Message::Up => detail.scroll_by(-1),
+            _ => (),
+        }
+        return;
+    }
+
+    if app.read_only && matches!(msg, Message::CycleSort | Message::HideSelected | Message::UnhideAll) {
+        return;
+    }
+
     match msg {
+        Message::CloseDetail | Message::PageDown | Message::PageUp | Message::ToggleFormat => (),
+        Message::CycleSort => app.sort_mode = app.sort_mode.next(),
         Message::Deselect => match app.mode {
             Mode::ObjectSelected => {
                 app.mode = Mode::EventSelected;
@@ -26,8 +68,14 @@ pub(super) fn update(app: &mut App, msg: Message) {
             Mode::ObjectSelected => app.object_contents_list_state.select_next(),
             Mode::EventSelected => app.object_list_state.select_next(),
             Mode::RootView => app.event_list_state.select_next(),
+            Mode::Detail => (),
         },
+        Message::HideSelected => app.hide_selected(),
+        Message::OpenDetail => app.open_detail(),
         Message::Quit => app.running = false,
+
+        // Refreshing requires reloading the trace, which is async, so it's handled in the run loop
+        Message::Refresh => (),
         Message::Select => match app.mode {
             Mode::EventSelected => {
                 app.mode = Mode::ObjectSelected;
@@ -39,11 +87,13 @@ pub(super) fn update(app: &mut App, msg: Message) {
             },
             _ => (),
         },
+        Message::UnhideAll => app.unhide_all(),
         Message::Unknown => (),
         Message::Up => match app.mode {
             Mode::ObjectSelected => app.object_contents_list_state.select_previous(),
             Mode::EventSelected => app.object_list_state.select_previous(),
             Mode::RootView => app.event_list_state.select_previous(),
+            Mode::Detail => (),
         },
     }
 }
@@ -8,3 +8,15 @@ pub(super) fn format_duration(d: TimeDelta) -> String {
 
     format!("{}{:02}:{:02}:{:02}", day_str, d.num_hours() % 24, d.num_minutes() % 60, d.num_seconds() % 60)
 }
+
+// Cut a string off at `width` characters, using an ellipsis to show that something's missing
+pub(super) fn truncate(s: &str, width: usize) -> String {
+    if s.chars().count() <= width {
+        return s.into();
+    }
+
+    match width {
+        0 => String::new(),
+        _ => s.chars().take(width - 1).chain(std::iter::once('…')).collect(),
+    }
+}
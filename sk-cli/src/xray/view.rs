@@ -1,6 +1,5 @@
-use std::iter::repeat;
-
 use chrono::TimeDelta;
+use kube::api::DynamicObject;
 use ratatui::prelude::*;
 use ratatui::widgets::{
     Block,
@@ -10,26 +9,49 @@ use ratatui::widgets::{
     Padding,
     Paragraph,
 };
-use sk_core::k8s::KubeResourceExt;
-use sk_store::TraceStorable;
+use sk_core::k8s::{
+    KubeResourceExt,
+    PodLifecycleData,
+};
+use sk_store::{
+    PodLifecyclesMap,
+    TraceStorable,
+};
 
 use super::app::{
     App,
     Mode,
 };
-use super::util::format_duration;
+use super::compare::{
+    Comparison,
+    DiffStatus,
+};
+use super::detail::{
+    DetailFormat,
+    DetailPane,
+};
+use super::util::{
+    format_duration,
+    truncate,
+};
 
 pub(super) fn view(app: &mut App, frame: &mut Frame) {
     let layout = Layout::default()
         .direction(Direction::Vertical)
-        .constraints(vec![Constraint::Percentage(100), Constraint::Min(5)])
+        .constraints(vec![Constraint::Percentage(100), Constraint::Min(5), Constraint::Length(1)])
         .split(frame.area());
-    let (top, bottom) = (layout[0], layout[1]);
+    let (top, bottom, footer) = (layout[0], layout[1], layout[2]);
 
-    let events_border = Block::bordered().title(app.trace.path.clone());
+    let events_border = Block::bordered()
+        .title(app.trace.path.clone())
+        .title_top(Line::from(format!("sort: {}", app.sort_mode)).right_aligned());
     let object_border = Block::bordered();
 
-    if top.width > 120 {
+    if let Some(comparison) = app.comparison.as_mut() {
+        render_comparison(comparison, &app.trace.path, frame, top);
+    } else if let Some(detail) = app.detail.as_mut() {
+        render_detail(detail, frame, top);
+    } else if top.width > 120 {
         let lr_layout = Layout::default()
             .direction(Direction::Horizontal)
             .constraints(vec![Constraint::Percentage(50), Constraint::Percentage(50)])
@@ -60,10 +82,23 @@ pub(super) fn view(app: &mut App, frame: &mut Frame) {
         }
     }
 
-    let greeting2 = Paragraph::new("Hello SimKube!\nUse arrows to navigate, space to select, 'q' to quit.")
-        .white()
-        .block(Block::new().borders(Borders::ALL));
+    let mut help_text = match (&app.comparison, &app.detail) {
+        (Some(_), _) => "Hello SimKube!\nUse arrows to navigate, 'q' to quit.".to_string(),
+        (_, Some(_)) => {
+            "Hello SimKube!\nUse arrows or PgUp/PgDn to scroll, 'f' for YAML/JSON, esc to close.".to_string()
+        },
+        _ => "Hello SimKube!\nUse arrows to navigate, space to select, 's' to sort, 'q' to quit.".to_string(),
+    };
+    if app.follow {
+        match &app.refresh_error {
+            Some(err) => help_text += &format!("\nFollowing {} (last refresh failed: {err})", app.trace.path),
+            None => help_text += &format!("\nFollowing {}", app.trace.path),
+        }
+    }
+    let greeting2 = Paragraph::new(help_text).white().block(Block::new().borders(Borders::ALL));
     frame.render_widget(greeting2, bottom);
+
+    render_footer(app, frame, footer);
 }
 
 fn render_event_list(app: &mut App, frame: &mut Frame, layout: Rect) {
@@ -73,13 +108,18 @@ fn render_event_list(app: &mut App, frame: &mut Frame, layout: Rect) {
     // then we nest in one level and display the applied and deleted objects, then we unnest and
     // display the rest of the events
     let num_events = app.trace.events.len();
+    if num_events == 0 && app.follow {
+        frame.render_widget(Paragraph::new("waiting for trace data...").italic(), layout);
+        return;
+    }
+
     let start_ts = app.trace.base.start_ts().unwrap_or(0);
 
     // Add one so the selected event is included on top
     let (sel_index_inclusive, sel_event) = match app.mode {
         Mode::EventSelected | Mode::ObjectSelected => {
             let sel_index = app.event_list_state.selected().unwrap();
-            (sel_index + 1, Some(&app.trace.events[sel_index]))
+            (sel_index + 1, Some(sel_index))
         },
         _ => (num_events, None),
     };
@@ -102,17 +142,22 @@ fn render_event_list(app: &mut App, frame: &mut Frame, layout: Rect) {
         }
     }
 
-    let sublist_items = sel_event.map_or(vec![], |evt| {
-        let mut items: Vec<_> = evt
-            .data
-            .applied_objs
-            .iter()
-            .zip(repeat("+"))
-            .chain(evt.data.deleted_objs.iter().zip(repeat("-")))
-            .map(|(obj, op)| format!("  {} {}", op, obj.namespaced_name()))
+    let sublist_items = sel_event.map_or(vec![], |evt_idx| {
+        let mut items: Vec<_> = app
+            .sorted_objects(evt_idx)
+            .into_iter()
+            .map(|(obj, op)| {
+                let ns_name = obj.namespaced_name();
+                let mut spans = vec![Span::raw(format!("  {op} {ns_name}"))];
+                if let Some(lifecycles_map) = app.trace.base.pod_lifecycles_for(&ns_name) {
+                    spans.push(Span::raw(" "));
+                    spans.extend(pod_status_indicators(lifecycles_map, app.no_color));
+                }
+                Line::from(spans)
+            })
             .collect();
         if items.is_empty() {
-            items.push(String::new());
+            items.push(Line::default());
         }
         items
     });
@@ -142,22 +187,148 @@ fn render_event_list(app: &mut App, frame: &mut Frame, layout: Rect) {
     frame.render_widget(list_part_two, nested_layout[2])
 }
 
-fn render_object(app: &mut App, frame: &mut Frame, layout: Rect) {
-    let evt_idx = app.event_list_state.selected().unwrap();
-    let obj_idx = app.object_list_state.selected().unwrap();
-    let applied_len = app.trace.events[evt_idx].data.applied_objs.len();
-    let deleted_len = app.trace.events[evt_idx].data.deleted_objs.len();
-
-    let obj = if obj_idx >= applied_len {
-        if obj_idx - applied_len > deleted_len {
-            return;
-        }
-        &app.trace.events[evt_idx].data.deleted_objs[obj_idx - applied_len]
-    } else {
-        &app.trace.events[evt_idx].data.applied_objs[obj_idx]
+// Objects that own pods get a marker for each of them, showing what state the pod was in at the end
+// of the trace; the markers are grouped by pod spec hash (in no particular order), and in launch
+// order within each group.
+fn pod_status_indicators(lifecycles_map: &PodLifecyclesMap, no_color: bool) -> Vec<Span<'static>> {
+    lifecycles_map
+        .values()
+        .flatten()
+        .map(|lifecycle_data| pod_status_indicator(lifecycle_data, no_color))
+        .collect()
+}
+
+// Pods that finished for any reason other than running to completion (e.g., OOMKilled or Error)
+// are shown as failed; older traces don't have a reason recorded, so those are just "finished".
+pub(super) fn pod_status_indicator(lifecycle_data: &PodLifecycleData, no_color: bool) -> Span<'static> {
+    let (glyph, ascii, color) = match lifecycle_data {
+        PodLifecycleData::Empty | PodLifecycleData::Pending(_) => ("◌", "~", Color::Yellow),
+        PodLifecycleData::Running(_) => ("●", "+", Color::Green),
+        PodLifecycleData::Finished(_, _, Some(reason)) if reason != "Completed" => ("✗", "!", Color::Red),
+        PodLifecycleData::Finished(..) => ("○", "-", Color::DarkGray),
+    };
+    match no_color {
+        true => Span::raw(ascii),
+        false => Span::styled(glyph, Style::new().fg(color)),
+    }
+}
+
+// The footer is recomputed from the app state on every draw, so it stays in sync with the selection
+// (and with the trace itself in follow mode); if the terminal is too narrow for everything, we just
+// cut it off at the end, which is why the selected object details (the longest part) come last.
+fn render_footer(app: &App, frame: &mut Frame, layout: Rect) {
+    let span = match (app.trace.events.first(), app.trace.events.last()) {
+        (Some(first), Some(last)) => TimeDelta::new(last.data.ts - first.data.ts, 0).unwrap_or_default(),
+        _ => TimeDelta::zero(),
+    };
+
+    let selected = match (&app.mode, selected_object(app)) {
+        (Mode::RootView, _) | (_, None) => match app.event_list_state.selected() {
+            Some(i) if !app.trace.events.is_empty() => format!("event {}/{}", i + 1, app.trace.events.len()),
+            _ => "none".into(),
+        },
+        (_, Some((obj, op))) => {
+            let kind = obj.types.as_ref().map_or("<unknown>", |t| t.kind.as_str());
+            format!("{op} {kind} {}", obj.namespaced_name())
+        },
+    };
+
+    let footer_text = match &app.comparison {
+        Some(comparison) => format!(
+            " {} added | {} removed | {} changed | {} unchanged",
+            comparison.count(DiffStatus::Added),
+            comparison.count(DiffStatus::Removed),
+            comparison.count(DiffStatus::Changed),
+            comparison.count(DiffStatus::Unchanged),
+        ),
+        None => {
+            let mut filters = vec![];
+            if let Some(ns) = &app.namespace_filter {
+                filters.push(format!("namespace {ns}"));
+            }
+            if !app.hidden.is_empty() {
+                filters.push(format!("{} hidden", app.hidden.len()));
+            }
+            let filter = match filters.is_empty() {
+                true => "none".to_string(),
+                false => filters.join(", "),
+            };
+            format!(
+                " {} objects | filter: {filter} | span: {} | selected: {selected}",
+                app.object_count,
+                format_duration(span)
+            )
+        },
     };
+    let footer = Paragraph::new(truncate(&footer_text, layout.width as usize)).reversed();
+    frame.render_widget(footer, layout);
+}
+
+fn selected_object(app: &App) -> Option<(&DynamicObject, &'static str)> {
+    let evt_idx = app.event_list_state.selected()?;
+    let obj_idx = app.object_list_state.selected()?;
+    app.sorted_objects(evt_idx).get(obj_idx).copied()
+}
+
+fn render_object(app: &mut App, frame: &mut Frame, layout: Rect) {
+    let Some((obj, _)) = selected_object(app) else { return };
 
     let obj_str = serde_json::to_string_pretty(obj).unwrap();
     let contents = List::new(obj_str.split('\n')).highlight_style(Style::new().bg(Color::Blue));
     frame.render_stateful_widget(contents, layout, &mut app.object_contents_list_state);
 }
+
+fn render_detail(detail: &mut DetailPane, frame: &mut Frame, layout: Rect) {
+    let format = match detail.format {
+        DetailFormat::Yaml => "yaml",
+        DetailFormat::Json => "json",
+    };
+    let border = Block::bordered().title(detail.title.clone());
+    let inner = border.inner(layout);
+    detail.resize(inner.height as usize);
+    let range = detail.visible_range();
+    let position = format!("{format} | {}-{}/{}", (range.start + 1).min(range.end), range.end, detail.len());
+
+    frame.render_widget(Paragraph::new(detail.visible_lines()), inner);
+    frame.render_widget(border.title_top(Line::from(position).right_aligned()), layout);
+}
+
+// Each row in the two panes is the same object, so objects that only exist in one of the traces
+// get a blank row on the other side; both lists are rendered with the same list state, which is
+// what keeps them scrolled together.
+fn render_comparison(comparison: &mut Comparison, before_path: &str, frame: &mut Frame, layout: Rect) {
+    let lr_layout = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(layout);
+
+    let mut before_items = Vec::with_capacity(comparison.diffs.len());
+    let mut after_items = Vec::with_capacity(comparison.diffs.len());
+    for diff in &comparison.diffs {
+        let item = format!("{} {}", diff.kind, diff.ns_name);
+        let style = match diff.status {
+            DiffStatus::Added => Style::new().green(),
+            DiffStatus::Removed => Style::new().red(),
+            DiffStatus::Changed => Style::new().yellow(),
+            DiffStatus::Unchanged => Style::new(),
+        };
+        let (before, after) = match diff.status {
+            DiffStatus::Added => (String::new(), item),
+            DiffStatus::Removed => (item, String::new()),
+            _ => (item.clone(), item),
+        };
+        before_items.push(Line::styled(before, style));
+        after_items.push(Line::styled(after, style));
+    }
+
+    for (items, path, pane) in [
+        (before_items, before_path, lr_layout[0]),
+        (after_items, comparison.trace.path.as_str(), lr_layout[1]),
+    ] {
+        let list = List::new(items)
+            .block(Block::bordered().title(path.to_string()))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol(">> ");
+        frame.render_stateful_widget(list, pane, &mut comparison.list_state);
+    }
+}
@@ -2,10 +2,18 @@
 pub const KUBERNETES_IO_METADATA_NAME_KEY: &str = "kubernetes.io/metadata.name";
 pub const APP_KUBERNETES_IO_NAME_KEY: &str = "app.kubernetes.io/name";
 pub const APP_KUBERNETES_IO_COMPONENT_KEY: &str = "app.kubernetes.io/component";
+pub const JOB_NAME_LABEL_KEY: &str = "job-name";
 
 // Common annotations and labels for SimKube
+pub const AFFECTED_PODS_ANNOTATION_KEY: &str = "simkube.io/affected-pods";
+pub const CRONJOB_STATE_ANNOTATION_KEY: &str = "simkube.io/cronjob-state";
+pub const DEBUG_ANNOTATION_KEY: &str = "simkube.io/debug";
+pub const HPA_STATE_ANNOTATION_KEY: &str = "simkube.io/hpa-state";
+pub const JOB_STATE_ANNOTATION_KEY: &str = "simkube.io/job-state";
 pub const LIFETIME_ANNOTATION_KEY: &str = "simkube.io/lifetime-seconds";
+pub const NODE_STATE_ANNOTATION_KEY: &str = "simkube.io/node-state";
 pub const ORIG_NAMESPACE_ANNOTATION_KEY: &str = "simkube.io/original-namespace";
+pub const CLEANUP_FINALIZER: &str = "simkube.io/cleanup";
 pub const SIMULATION_LABEL_KEY: &str = "simkube.io/simulation";
 pub const VIRTUAL_LABEL_KEY: &str = "simkube.io/virtual";
 pub const PROM2PARQUET_PREFIX_KEY: &str = "prom2parquet_prefix";
@@ -33,6 +41,10 @@ mod test_constants {
     pub const EMPTY_OBJ_HASH: u64 = 15130871412783076140;
     pub const EMPTY_POD_SPEC_HASH: u64 = 17506812802394981455;
     pub const TEST_DEPLOYMENT: &str = "the-deployment";
+    pub const TEST_CRONJOB: &str = "the-cronjob";
+    pub const TEST_HPA: &str = "the-hpa";
+    pub const TEST_JOB: &str = "the-job";
+    pub const TEST_NODE: &str = "the-node";
     pub const TEST_NAMESPACE: &str = "test-namespace";
     pub const TEST_SIM_NAME: &str = "test-sim";
     pub const TEST_SIM_ROOT_NAME: &str = "test-sim-root";
@@ -14,6 +14,46 @@ pub use thiserror::Error;
 
 pub type EmptyResult = anyhow::Result<()>;
 
+// Most of the library just returns anyhow errors, which is fine for the binaries but doesn't give
+// anyone embedding the library much to go on; so the failures that a caller might reasonably want
+// to handle (bad config, the apiserver saying no, data that can't be (de)serialized, and traces
+// that aren't valid) are reported as one of these, wrapped in the anyhow::Error.  Use
+// `err.downcast_ref::<SimKubeError>()` to get at it.  The constructors below all return the
+// wrapped anyhow::Error (same as the ones `err_impl!` generates), so they can be used directly in
+// a `map_err` or an `Err(...)`.
+#[derive(Debug, Error)]
+pub enum SimKubeError {
+    #[error("invalid config: {0}")]
+    Config(String),
+
+    #[error("kubernetes API error: {0}")]
+    Api(#[from] kube::Error),
+
+    #[error("serialization error: {0}")]
+    Serialization(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    #[error("invalid trace: {0}")]
+    TraceFormat(String),
+}
+
+impl SimKubeError {
+    pub fn config(msg: impl Into<String>) -> anyhow::Error {
+        SimKubeError::Config(msg.into()).into()
+    }
+
+    pub fn api(err: kube::Error) -> anyhow::Error {
+        SimKubeError::Api(err).into()
+    }
+
+    pub fn serialization(err: impl std::error::Error + Send + Sync + 'static) -> anyhow::Error {
+        SimKubeError::Serialization(Box::new(err)).into()
+    }
+
+    pub fn trace_format(msg: impl Into<String>) -> anyhow::Error {
+        SimKubeError::TraceFormat(msg.into()).into()
+    }
+}
+
 pub const BUILD_DIR: &str = "/.build/";
 pub const RUSTC_DIR: &str = "/rustc/";
 pub const GLIBC: &str = "glibc";
@@ -66,10 +106,7 @@ macro_rules! err_impl {
 macro_rules! skerr {
     (@hidden $err:ident, $msg:literal, $($args:expr),*) => {
         let bt = $err.backtrace().to_string();
-        let re = RegexBuilder::new(r"^\s+\d+(?s:.*?)(\s+at\s+.*:\d+)$")
-            .multi_line(true)
-            .build()
-            .unwrap();
+        let re = backtrace_frame_regex();
         let mut skipped_frames = 0;
         let mut filtered_bt = re.find_iter(&bt).fold(String::new(), |mut acc, frame| {
             let frame = frame.as_str();
@@ -104,6 +141,13 @@ macro_rules! skerr {
     };
 }
 
+pub fn backtrace_frame_regex() -> Regex {
+    RegexBuilder::new(r"^\s+\d+(?s:.*?)(\s+at\s+.*:\d+)$")
+        .multi_line(true)
+        .build()
+        .unwrap()
+}
+
 pub use {
     err_impl,
     skerr,
@@ -1,3 +1,5 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use serde_json::Value;
 
 use crate::errors::*;
@@ -34,6 +36,16 @@ err_impl! {JsonPatchError,
     UnexpectedType(String),
 }
 
+// Apply a regular (RFC 6902) JSON patch to anything that can be round-tripped through JSON, e.g., a
+// DynamicObject.  The patch is applied atomically, so if any operation fails (like a `replace` or
+// `remove` at a path that doesn't exist), `obj` is left unchanged.
+pub fn apply<T: Serialize + DeserializeOwned>(patch: &json_patch::Patch, obj: &mut T) -> EmptyResult {
+    let mut value = serde_json::to_value(&*obj)?;
+    json_patch::patch(&mut value, patch)?;
+    *obj = serde_json::from_value(value)?;
+    Ok(())
+}
+
 pub fn escape(path: &str) -> String {
     let path = path.replace('~', "~0");
     path.replace('/', "~1")
@@ -74,6 +86,12 @@ pub fn remove(path: &str, key: &str, obj: &mut Value) -> EmptyResult {
     Ok(())
 }
 
+// All of the values that `path` refers to; if some part of the path doesn't exist, there aren't any
+pub fn matches_mut<'a>(path: &str, obj: &'a mut Value) -> Vec<&'a mut Value> {
+    let parts: Vec<_> = path.split('*').collect();
+    patch_ext_helper(&parts, obj).unwrap_or_default()
+}
+
 // Given a list of "path parts", i.e., paths split by `*`, recursively walk through all the
 // possible "end" values that the path references; return a mutable reference so we can make
 // modifications at those points.  We assume that this function is never called with an empty
@@ -5,26 +5,81 @@ use kube::api::{
     ApiResource,
     DynamicObject,
 };
-use kube::discovery::ApiCapabilities;
+use kube::discovery::{
+    ApiCapabilities,
+    Scope,
+};
 
-use crate::k8s::GVK;
+use super::discovery_cache::VersionLookup;
+use crate::errors::*;
+use crate::k8s::{
+    DiscoveryCache,
+    RateLimiter,
+    GVK,
+};
+use crate::prelude::*;
 
 // An ApiSet object caches a list of ApiResources returned by the k8s server so that we don't have
-// to repeatedly make "discovery" calls against the apiserver.
+// to repeatedly make "discovery" calls against the apiserver.  It can optionally also throttle the
+// requests we make, so that we don't get rate-limited by the apiserver when there are a lot of
+// objects to look at (e.g., when a bunch of pods get created all at once); users of the APIs that
+// the ApiSet hands out should call `throttle` before each request they make.
 pub struct ApiSet {
     client: kube::Client,
+    rate_limiter: Option<RateLimiter>,
     resources: HashMap<GVK, (ApiResource, ApiCapabilities)>,
     apis: HashMap<GVK, kube::Api<DynamicObject>>,
     namespaced_apis: HashMap<(GVK, String), kube::Api<DynamicObject>>,
+
+    // By default we use exactly the version we're asked for; if version preferences are set, we
+    // instead look up which versions the apiserver serves for the kind and pick one of those (see
+    // `discover_preferred_kind`), and remember which one we picked.
+    version_preferences: Option<HashMap<String, String>>,
+    resolved_gvks: HashMap<GVK, GVK>,
+
+    // If set, discovery results are also saved to (and looked up from) disk; see discovery_cache.rs
+    discovery_cache: Option<DiscoveryCache>,
 }
 
 impl ApiSet {
     pub fn new(client: kube::Client) -> ApiSet {
+        ApiSet::new_with_rate_limiter(client, None)
+    }
+
+    pub fn new_with_rate_limiter(client: kube::Client, rate_limiter: Option<RateLimiter>) -> ApiSet {
         ApiSet {
             client,
+            rate_limiter,
             resources: HashMap::new(),
             apis: HashMap::new(),
             namespaced_apis: HashMap::new(),
+            version_preferences: None,
+            resolved_gvks: HashMap::new(),
+            discovery_cache: None,
+        }
+    }
+
+    // Preferences map an API group to the version we'd like to use for it; groups that aren't in
+    // the map use the apiserver's preferred version.
+    pub fn with_version_preferences(mut self, prefs: HashMap<String, String>) -> ApiSet {
+        self.version_preferences = Some(prefs);
+        self
+    }
+
+    pub fn with_discovery_cache(mut self, cache: DiscoveryCache) -> ApiSet {
+        self.discovery_cache = Some(cache);
+        self
+    }
+
+    // The GVK that we actually used for a requested GVK; this is only set once we've looked up the
+    // API for it, and only if version preferences are set.
+    pub fn resolved_gvk(&self, gvk: &GVK) -> Option<&GVK> {
+        self.resolved_gvks.get(gvk)
+    }
+
+    pub async fn throttle(&mut self) {
+        if let Some(rl) = self.rate_limiter.as_mut() {
+            rl.acquire().await;
         }
     }
 
@@ -42,6 +97,27 @@ impl ApiSet {
         }
     }
 
+    // Cluster-scoped resources don't live in a namespace, so for those we just hand back the
+    // unnamespaced api
+    pub async fn namespaced_api_by_gvk(
+        &mut self,
+        gvk: &GVK,
+        ns: &str,
+    ) -> anyhow::Result<(&kube::Api<DynamicObject>, ApiCapabilities)> {
+        let (ar, cap) = self.api_meta_for(gvk).await?.clone();
+        if matches!(cap.scope, Scope::Cluster) {
+            return self.unnamespaced_api_by_gvk(gvk).await;
+        }
+
+        match self.namespaced_apis.entry((gvk.clone(), ns.into())) {
+            Entry::Occupied(e) => Ok((e.into_mut(), cap)),
+            Entry::Vacant(e) => {
+                let api = kube::Api::namespaced_with(self.client.clone(), ns, &ar);
+                Ok((e.insert(api), cap))
+            },
+        }
+    }
+
     pub async fn api_for_obj(&mut self, obj: &DynamicObject) -> anyhow::Result<&kube::Api<DynamicObject>> {
         let gvk = GVK::from_dynamic_obj(obj)?;
         let ar = self.api_meta_for(&gvk).await?.0.clone();
@@ -61,9 +137,58 @@ impl ApiSet {
         match self.resources.entry(gvk.clone()) {
             Entry::Occupied(e) => Ok(e.into_mut()),
             Entry::Vacant(e) => {
-                let api_meta = kube::discovery::pinned_kind(&self.client, e.key()).await?;
+                let lookup = match &self.version_preferences {
+                    Some(prefs) => VersionLookup::Preferred(prefs.get(&gvk.group).cloned()),
+                    None => VersionLookup::Exact,
+                };
+                let cached = self.discovery_cache.as_ref().and_then(|c| c.get(gvk, &lookup));
+                let api_meta = match cached {
+                    Some(api_meta) => api_meta,
+                    None => {
+                        if let Some(rl) = self.rate_limiter.as_mut() {
+                            rl.acquire().await;
+                        }
+                        let api_meta = match &lookup {
+                            VersionLookup::Preferred(ver) => {
+                                discover_preferred_kind(&self.client, e.key(), ver.as_ref()).await?
+                            },
+                            VersionLookup::Exact => kube::discovery::pinned_kind(&self.client, e.key())
+                                .await
+                                .map_err(SimKubeError::api)?,
+                        };
+                        if let Some(cache) = self.discovery_cache.as_mut() {
+                            cache.put(gvk, lookup, &api_meta);
+                        }
+                        api_meta
+                    },
+                };
+
+                if self.version_preferences.is_some() {
+                    let resolved = GVK::new(&api_meta.0.group, &api_meta.0.version, &api_meta.0.kind);
+                    if &resolved != gvk {
+                        info!("using {resolved} for {gvk}");
+                    }
+                    self.resolved_gvks.insert(gvk.clone(), resolved);
+                }
                 Ok(e.insert(api_meta))
             },
         }
     }
 }
+
+// When a group serves a kind at multiple versions (e.g., a CRD with both v1beta1 and v1), the
+// version in an owner reference or a config file isn't necessarily the one we want to read the
+// objects at; so we use the configured version for the group if it serves the kind, and otherwise
+// the apiserver's preferred version (which is normally the storage version), falling back to the
+// most stable version that serves the kind.
+async fn discover_preferred_kind(
+    client: &kube::Client,
+    gvk: &GVK,
+    preferred_version: Option<&String>,
+) -> anyhow::Result<(ApiResource, ApiCapabilities)> {
+    let group = kube::discovery::group(client, &gvk.group).await.map_err(SimKubeError::api)?;
+    preferred_version
+        .and_then(|ver| group.versioned_resources(ver).into_iter().find(|(ar, _)| ar.kind == gvk.kind))
+        .or_else(|| group.recommended_kind(&gvk.kind))
+        .ok_or_else(|| anyhow!("no version of {gvk} is served by the apiserver"))
+}
@@ -0,0 +1,24 @@
+use kube::config::{
+    KubeConfigOptions,
+    Kubeconfig,
+};
+
+// By default we build a client the same way kubectl does (in-cluster config, then $KUBECONFIG,
+// then ~/.kube/config), but for local testing it's handy to be able to point at a specific
+// kubeconfig file and/or context without changing your default context.
+pub async fn build_client_config(kubeconfig: Option<&str>, context: Option<&str>) -> anyhow::Result<kube::Config> {
+    let options = KubeConfigOptions {
+        context: context.map(|c| c.into()),
+        ..Default::default()
+    };
+    let config = match (kubeconfig, context) {
+        (Some(path), _) => kube::Config::from_custom_kubeconfig(Kubeconfig::read_from(path)?, &options).await?,
+        (None, Some(_)) => kube::Config::from_kubeconfig(&options).await?,
+        (None, None) => kube::Config::infer().await?,
+    };
+    Ok(config)
+}
+
+pub async fn build_client(kubeconfig: Option<&str>, context: Option<&str>) -> anyhow::Result<kube::Client> {
+    Ok(kube::Client::try_from(build_client_config(kubeconfig, context).await?)?)
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use clockabilly::{
+    Clockable,
+    UtcClock,
+};
+use kube::api::ApiResource;
+use kube::discovery::{
+    ApiCapabilities,
+    Scope,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::errors::*;
+use crate::k8s::GVK;
+use crate::prelude::*;
+
+// Which version of a kind we end up using depends on whether there are version preferences set
+// (and what they are for the kind's group; see apiset.rs), so cached results only count if they
+// were looked up the same way
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum VersionLookup {
+    Exact,
+    Preferred(Option<String>),
+}
+
+// ApiCapabilities isn't serializable, so we just keep the parts of it that we need; we don't use
+// subresources anywhere, so those aren't cached.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedApiMeta {
+    resource: ApiResource,
+    namespaced: bool,
+    operations: Vec<String>,
+    lookup: VersionLookup,
+    ts: i64,
+}
+
+// Discovery results for each cluster we've talked to, keyed by apiserver URL
+type CacheContents = HashMap<String, HashMap<GVK, CachedApiMeta>>;
+
+// Clusters with lots of CRDs can take a while to do discovery against, and the results hardly ever
+// change, so an ApiSet can optionally save them to disk and re-use them on the next run (as long as
+// they're not older than the TTL).  If the cache can't be read or written, we log a warning and
+// carry on without it; the worst that happens is that we do discovery again.
+pub struct DiscoveryCache {
+    path: PathBuf,
+    cluster_url: String,
+    ttl: i64,
+    force_refresh: bool,
+    clock: Box<dyn Clockable + Send + Sync>,
+    contents: CacheContents,
+}
+
+impl DiscoveryCache {
+    pub fn new(path: PathBuf, cluster_url: &str, ttl: i64, force_refresh: bool) -> DiscoveryCache {
+        DiscoveryCache::new_with_clock(path, cluster_url, ttl, force_refresh, UtcClock::boxed())
+    }
+
+    pub fn new_with_clock(
+        path: PathBuf,
+        cluster_url: &str,
+        ttl: i64,
+        force_refresh: bool,
+        clock: Box<dyn Clockable + Send + Sync>,
+    ) -> DiscoveryCache {
+        let contents = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|err| {
+                warn!("could not parse discovery cache {}, ignoring: {err}", path.display());
+                CacheContents::new()
+            }),
+            Err(_) => CacheContents::new(),
+        };
+        DiscoveryCache {
+            path,
+            cluster_url: cluster_url.into(),
+            ttl,
+            force_refresh,
+            clock,
+            contents,
+        }
+    }
+
+    pub(crate) fn get(&self, gvk: &GVK, lookup: &VersionLookup) -> Option<(ApiResource, ApiCapabilities)> {
+        if self.force_refresh {
+            return None;
+        }
+
+        let meta = self.contents.get(&self.cluster_url)?.get(gvk)?;
+        if &meta.lookup != lookup || self.clock.now_ts() - meta.ts >= self.ttl {
+            return None;
+        }
+
+        debug!("using cached discovery results for {gvk}");
+        let cap = ApiCapabilities {
+            scope: if meta.namespaced { Scope::Namespaced } else { Scope::Cluster },
+            subresources: vec![],
+            operations: meta.operations.clone(),
+        };
+        Some((meta.resource.clone(), cap))
+    }
+
+    pub(crate) fn put(&mut self, gvk: &GVK, lookup: VersionLookup, api_meta: &(ApiResource, ApiCapabilities)) {
+        let meta = CachedApiMeta {
+            resource: api_meta.0.clone(),
+            namespaced: matches!(api_meta.1.scope, Scope::Namespaced),
+            operations: api_meta.1.operations.clone(),
+            lookup,
+            ts: self.clock.now_ts(),
+        };
+        self.contents
+            .entry(self.cluster_url.clone())
+            .or_default()
+            .insert(gvk.clone(), meta);
+
+        if let Err(err) = self.save() {
+            warn!("could not write discovery cache {}: {err}", self.path.display());
+        }
+    }
+
+    // There aren't that many kinds that we look up, so we just write the whole thing out every time
+    // something changes; we write to a temporary file and move it into place, so that if another
+    // process is reading the cache at the same time, it doesn't see a partly-written file
+    fn save(&self) -> EmptyResult {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec(&self.contents)?)?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
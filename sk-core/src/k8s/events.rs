@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use kube::api::ListParams;
+
+use crate::errors::SimKubeError;
+use crate::k8s::{
+    PodEvent,
+    RateLimiter,
+};
+use crate::prelude::*;
+
+const EVENT_PAGE_SIZE: u32 = 500;
+
+// Busy clusters can have a _lot_ of Events lying around, so we page through them instead of
+// listing them all at once, and (if a rate limiter is given) take a token for each page so that we
+// don't hammer the apiserver.  We only ask for Events about pods; see `correlate_pod_events` for
+// how they get matched up with the pods they reference.
+pub async fn list_pod_events(
+    client: kube::Client,
+    mut rate_limiter: Option<RateLimiter>,
+) -> anyhow::Result<HashMap<String, Vec<PodEvent>>> {
+    let events_api: kube::Api<corev1::Event> = kube::Api::all(client);
+    let mut params = ListParams::default().fields("involvedObject.kind=Pod").limit(EVENT_PAGE_SIZE);
+
+    let mut events = vec![];
+    loop {
+        if let Some(rl) = rate_limiter.as_mut() {
+            rl.acquire().await;
+        }
+
+        let page = events_api.list(&params).await.map_err(SimKubeError::api)?;
+        let continue_token = page.metadata.continue_.clone().filter(|t| !t.is_empty());
+        events.extend(page.items);
+
+        match continue_token {
+            Some(token) => params = params.continue_token(&token),
+            None => break,
+        }
+    }
+
+    Ok(correlate_pod_events(events))
+}
+
+// Join Events to the pods they're about via the involvedObject reference, keyed by the pod's
+// namespaced name; the events for each pod are sorted by timestamp.  Events that don't reference a
+// pod, or that don't have any timestamp on them, are skipped.
+pub fn correlate_pod_events(events: impl IntoIterator<Item = corev1::Event>) -> HashMap<String, Vec<PodEvent>> {
+    let mut pod_events: HashMap<String, Vec<PodEvent>> = HashMap::new();
+    for evt in events {
+        let obj_ref = &evt.involved_object;
+        if obj_ref.kind.as_deref() != Some("Pod") {
+            continue;
+        }
+        let (Some(ns), Some(name)) = (&obj_ref.namespace, &obj_ref.name) else {
+            continue;
+        };
+
+        // Newer-style events only have eventTime set, older ones only have first/lastTimestamp
+        let Some(ts) = evt
+            .last_timestamp
+            .as_ref()
+            .map(|t| t.0.timestamp())
+            .or(evt.event_time.as_ref().map(|t| t.0.timestamp()))
+            .or(evt.first_timestamp.as_ref().map(|t| t.0.timestamp()))
+        else {
+            continue;
+        };
+
+        pod_events.entry(format!("{ns}/{name}")).or_default().push(PodEvent {
+            ts,
+            event_type: evt.type_.unwrap_or_default(),
+            reason: evt.reason.unwrap_or_default(),
+            message: evt.message.unwrap_or_default(),
+            count: evt.count.unwrap_or(1),
+        });
+    }
+
+    for evts in pod_events.values_mut() {
+        evts.sort_by_key(|e| e.ts);
+    }
+    pod_events
+}
@@ -1,6 +1,7 @@
 use std::borrow::Cow;
 use std::fmt;
 use std::ops::Deref;
+use std::str::FromStr;
 
 use kube::api::{
     DynamicObject,
@@ -60,18 +61,43 @@ impl Deref for GVK {
     }
 }
 
+impl fmt::Display for GVK {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut group = Cow::from(&self.0.group);
+        if !group.is_empty() {
+            group.to_mut().push('/');
+        }
+
+        write!(f, "{group}{}.{}", self.0.version, self.0.kind)
+    }
+}
+
 impl Serialize for GVK {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        let mut group = Cow::from(&self.0.group);
-        if !group.is_empty() {
-            group.to_mut().push('/');
-        }
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl FromStr for GVK {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<GVK, String> {
+        let p1: Vec<_> = value.split('/').collect();
+        let (group, rest) = match p1.len() {
+            2 => (p1[0], p1[1]),
+            1 => ("", p1[0]),
+            _ => return Err(format!("invalid format for gvk: {value}")),
+        };
+        let p2: Vec<_> = rest.split('.').collect();
+        let (version, kind) = match p2.len() {
+            2 => (p2[0], p2[1]),
+            _ => return Err(format!("invalid format for gvk: {value}")),
+        };
 
-        let skey = format!("{group}{}.{}", self.0.version, self.0.kind);
-        serializer.serialize_str(&skey)
+        Ok(GVK(GroupVersionKind::gvk(group, version, kind)))
     }
 }
 
@@ -88,19 +114,7 @@ impl<'de> de::Visitor<'de> for GVKVisitor {
     where
         E: de::Error,
     {
-        let p1: Vec<_> = value.split('/').collect();
-        let (group, rest) = match p1.len() {
-            2 => (p1[0], p1[1]),
-            1 => ("", p1[0]),
-            _ => return Err(E::custom(format!("invalid format for gvk: {value}"))),
-        };
-        let p2: Vec<_> = rest.split('.').collect();
-        let (version, kind) = match p2.len() {
-            2 => (p2[0], p2[1]),
-            _ => return Err(E::custom(format!("invalid format for gvk: {value}"))),
-        };
-
-        Ok(GVK(GroupVersionKind::gvk(group, version, kind)))
+        value.parse().map_err(E::custom)
     }
 }
 
@@ -133,6 +147,12 @@ mod test {
         assert_eq!(serde_json::to_string(&GVK::new("", "v1", "bar")).unwrap(), "\"v1.bar\"");
     }
 
+    #[rstest]
+    fn test_display() {
+        assert_eq!(GVK::new("foo", "v1", "bar").to_string(), "foo/v1.bar");
+        assert_eq!(GVK::new("", "v1", "bar").to_string(), "v1.bar");
+    }
+
     #[rstest]
     fn test_deserialize() {
         let d1: StrDeserializer<SerdeError> = "foo/v1.bar".into_deserializer();
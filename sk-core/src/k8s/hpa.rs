@@ -0,0 +1,108 @@
+use k8s_openapi::api::autoscaling::{
+    v1 as autoscalingv1,
+    v2 as autoscalingv2,
+};
+use kube::api::DynamicObject;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::k8s::GVK;
+
+const HPA_GROUP: &str = "autoscaling";
+const HPA_KIND: &str = "HorizontalPodAutoscaler";
+
+// The state of a HorizontalPodAutoscaler at the time we saw it: its replica bounds, how many
+// replicas it has and wants, and what metrics it's targeting (along with their current values).
+// This is enough to seed the autoscaler in a simulation, or to figure out why it made the scaling
+// decision that it did.  autoscaling/v1 HPAs can only target CPU utilization; this gets converted
+// into the equivalent autoscaling/v2 metric, so that consumers only have to deal with one format.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HpaState {
+    pub min_replicas: Option<i32>,
+    pub max_replicas: i32,
+    pub current_replicas: Option<i32>,
+    pub desired_replicas: Option<i32>,
+    pub metrics: Vec<autoscalingv2::MetricSpec>,
+    pub current_metrics: Vec<autoscalingv2::MetricStatus>,
+}
+
+impl HpaState {
+    // Returns None if the object isn't an HPA (or is from some API version that we don't know about)
+    pub fn from_dynamic_obj(obj: &DynamicObject) -> anyhow::Result<Option<HpaState>> {
+        let Ok(gvk) = GVK::from_dynamic_obj(obj) else {
+            return Ok(None);
+        };
+        if gvk.group != HPA_GROUP || gvk.kind != HPA_KIND {
+            return Ok(None);
+        }
+
+        match gvk.version.as_str() {
+            "v1" => Ok(Some(obj.clone().try_parse::<autoscalingv1::HorizontalPodAutoscaler>()?.into())),
+            "v2" => Ok(Some(obj.clone().try_parse::<autoscalingv2::HorizontalPodAutoscaler>()?.into())),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl From<autoscalingv1::HorizontalPodAutoscaler> for HpaState {
+    fn from(hpa: autoscalingv1::HorizontalPodAutoscaler) -> HpaState {
+        let spec = hpa.spec.unwrap_or_default();
+        let status = hpa.status;
+
+        let metrics = spec.target_cpu_utilization_percentage.map(|pct| autoscalingv2::MetricSpec {
+            type_: "Resource".into(),
+            resource: Some(autoscalingv2::ResourceMetricSource {
+                name: "cpu".into(),
+                target: autoscalingv2::MetricTarget {
+                    type_: "Utilization".into(),
+                    average_utilization: Some(pct),
+                    ..Default::default()
+                },
+            }),
+            ..Default::default()
+        });
+        let current_metrics =
+            status
+                .as_ref()
+                .and_then(|s| s.current_cpu_utilization_percentage)
+                .map(|pct| autoscalingv2::MetricStatus {
+                    type_: "Resource".into(),
+                    resource: Some(autoscalingv2::ResourceMetricStatus {
+                        name: "cpu".into(),
+                        current: autoscalingv2::MetricValueStatus {
+                            average_utilization: Some(pct),
+                            ..Default::default()
+                        },
+                    }),
+                    ..Default::default()
+                });
+
+        HpaState {
+            min_replicas: spec.min_replicas,
+            max_replicas: spec.max_replicas,
+            current_replicas: status.as_ref().map(|s| s.current_replicas),
+            desired_replicas: status.as_ref().map(|s| s.desired_replicas),
+            metrics: metrics.into_iter().collect(),
+            current_metrics: current_metrics.into_iter().collect(),
+        }
+    }
+}
+
+impl From<autoscalingv2::HorizontalPodAutoscaler> for HpaState {
+    fn from(hpa: autoscalingv2::HorizontalPodAutoscaler) -> HpaState {
+        let spec = hpa.spec.unwrap_or_default();
+        let status = hpa.status;
+
+        HpaState {
+            min_replicas: spec.min_replicas,
+            max_replicas: spec.max_replicas,
+            current_replicas: status.as_ref().and_then(|s| s.current_replicas),
+            desired_replicas: status.as_ref().map(|s| s.desired_replicas),
+            metrics: spec.metrics.unwrap_or_default(),
+            current_metrics: status.and_then(|s| s.current_metrics).unwrap_or_default(),
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use k8s_openapi::api::batch::v1 as batchv1;
+use kube::api::DynamicObject;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::k8s::GVK;
+
+const BATCH_GROUP: &str = "batch";
+const JOB_KIND: &str = "Job";
+const CRONJOB_KIND: &str = "CronJob";
+
+// How far along a Job was when we saw it: how many pods it wants to complete (and how many it runs
+// at once), how many have succeeded or failed so far, and when it started and finished.  Like the
+// HPA state, this comes out of the Job's status, which doesn't survive a replay; timestamps are in
+// seconds since the epoch, same as everywhere else in the trace.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobState {
+    pub completions: Option<i32>,
+    pub parallelism: Option<i32>,
+    pub succeeded: Option<i32>,
+    pub failed: Option<i32>,
+    pub start_ts: Option<i64>,
+    pub completion_ts: Option<i64>,
+}
+
+impl JobState {
+    // Returns None if the object isn't a Job
+    pub fn from_dynamic_obj(obj: &DynamicObject) -> anyhow::Result<Option<JobState>> {
+        if !is_batch_kind(obj, JOB_KIND) {
+            return Ok(None);
+        }
+        Ok(Some(obj.clone().try_parse::<batchv1::Job>()?.into()))
+    }
+
+    // A Job has a completion time iff it finished successfully
+    pub fn is_complete(&self) -> bool {
+        self.completion_ts.is_some()
+    }
+
+    // How long each of the Job's pods should run for, so that the Job takes as long to complete in
+    // the simulation as it did in the trace.  With parallelism > 1, the pods run in "waves" of up to
+    // `parallelism` pods at a time, so each one gets an equal share of the total time; if the Job
+    // doesn't have a fixed completion count (i.e., it's a work queue), all of the pods run at once.
+    pub fn pod_lifetime(&self) -> Option<i64> {
+        let duration = self.completion_ts? - self.start_ts?;
+        let parallelism = self.parallelism.unwrap_or(1).max(1) as i64;
+        let waves = match self.completions {
+            Some(completions) => (completions.max(1) as i64 + parallelism - 1) / parallelism,
+            None => 1,
+        };
+        Some(duration / waves)
+    }
+}
+
+impl From<batchv1::Job> for JobState {
+    fn from(job: batchv1::Job) -> JobState {
+        let spec = job.spec.unwrap_or_default();
+        let status = job.status.unwrap_or_default();
+
+        JobState {
+            completions: spec.completions,
+            parallelism: spec.parallelism,
+            succeeded: status.succeeded,
+            failed: status.failed,
+            start_ts: status.start_time.map(|t| t.0.timestamp()),
+            completion_ts: status.completion_time.map(|t| t.0.timestamp()),
+        }
+    }
+}
+
+// The schedule of a CronJob, and when it last fired; the simulated cluster's CronJob controller
+// takes care of actually creating the Jobs on schedule, this is just so that you can tell where in
+// the schedule things were at when the trace was taken.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CronJobState {
+    pub schedule: String,
+    pub suspend: Option<bool>,
+    pub active: usize,
+    pub last_schedule_ts: Option<i64>,
+    pub last_successful_ts: Option<i64>,
+}
+
+impl CronJobState {
+    // Returns None if the object isn't a CronJob
+    pub fn from_dynamic_obj(obj: &DynamicObject) -> anyhow::Result<Option<CronJobState>> {
+        if !is_batch_kind(obj, CRONJOB_KIND) {
+            return Ok(None);
+        }
+        Ok(Some(obj.clone().try_parse::<batchv1::CronJob>()?.into()))
+    }
+}
+
+impl From<batchv1::CronJob> for CronJobState {
+    fn from(cj: batchv1::CronJob) -> CronJobState {
+        let spec = cj.spec.unwrap_or_default();
+        let status = cj.status.unwrap_or_default();
+
+        CronJobState {
+            schedule: spec.schedule,
+            suspend: spec.suspend,
+            active: status.active.map_or(0, |active| active.len()),
+            last_schedule_ts: status.last_schedule_time.map(|t| t.0.timestamp()),
+            last_successful_ts: status.last_successful_time.map(|t| t.0.timestamp()),
+        }
+    }
+}
+
+fn is_batch_kind(obj: &DynamicObject, kind: &str) -> bool {
+    GVK::from_dynamic_obj(obj).is_ok_and(|gvk| gvk.group == BATCH_GROUP && gvk.version == "v1" && gvk.kind == kind)
+}
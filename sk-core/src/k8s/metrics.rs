@@ -0,0 +1,77 @@
+use std::collections::{
+    BTreeMap,
+    HashMap,
+};
+
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use kube::api::{
+    ApiResource,
+    DynamicObject,
+    ListParams,
+};
+use serde::Deserialize;
+
+use crate::k8s::{
+    KubeResourceExt,
+    PodUsage,
+};
+
+const METRICS_GROUP: &str = "metrics.k8s.io";
+const METRICS_VERSION: &str = "v1beta1";
+
+// The metrics.k8s.io API isn't included in k8s-openapi, so we just define the bits of the
+// PodMetrics object that we care about here and parse them out of a DynamicObject.
+#[derive(Deserialize)]
+struct ContainerMetrics {
+    name: String,
+    usage: BTreeMap<String, Quantity>,
+}
+
+fn pod_metrics_api_resource() -> ApiResource {
+    ApiResource {
+        group: METRICS_GROUP.into(),
+        version: METRICS_VERSION.into(),
+        api_version: format!("{METRICS_GROUP}/{METRICS_VERSION}"),
+        kind: "PodMetrics".into(),
+        plural: "pods".into(),
+    }
+}
+
+// Check whether metrics-server is installed, without pulling down the usage for every pod
+pub async fn metrics_server_available(client: kube::Client) -> anyhow::Result<bool> {
+    let metrics_api: kube::Api<DynamicObject> = kube::Api::all_with(client, &pod_metrics_api_resource());
+    match metrics_api.list(&ListParams::default().limit(1)).await {
+        Ok(_) => Ok(true),
+        Err(kube::Error::Api(kube::core::ErrorResponse { code: 404 | 503, .. })) => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+// Query metrics-server for the current usage of every pod in the cluster, keyed by the pod's
+// namespaced name.  Lots of clusters don't have metrics-server installed, so if the API isn't
+// there we return None instead of an error and let the caller decide what to do about it.
+pub async fn list_pod_usage(client: kube::Client, ts: i64) -> anyhow::Result<Option<HashMap<String, PodUsage>>> {
+    let metrics_api: kube::Api<DynamicObject> = kube::Api::all_with(client, &pod_metrics_api_resource());
+    let pod_metrics = match metrics_api.list(&ListParams::default()).await {
+        Ok(list) => list,
+        Err(kube::Error::Api(kube::core::ErrorResponse { code: 404 | 503, .. })) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut usage = HashMap::new();
+    for pm in pod_metrics {
+        let containers: Vec<ContainerMetrics> = match pm.data.get("containers") {
+            Some(c) => serde_json::from_value(c.clone())?,
+            None => vec![],
+        };
+        usage.insert(
+            pm.namespaced_name(),
+            PodUsage {
+                ts,
+                containers: containers.into_iter().map(|c| (c.name, c.usage)).collect(),
+            },
+        );
+    }
+
+    Ok(Some(usage))
+}
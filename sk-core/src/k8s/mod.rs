@@ -1,17 +1,55 @@
 mod apiset;
+mod client;
 mod container_state;
+mod discovery_cache;
+mod events;
 mod gvk;
+mod hpa;
+mod job;
 mod lease;
+mod metrics;
+mod node;
+mod owner_chain_cache;
 mod owners;
 mod pod_ext;
 mod pod_lifecycle;
+mod rate_limiter;
 mod sim;
 mod util;
 
+use std::collections::BTreeMap;
+
 pub use apiset::*;
+pub use client::*;
+pub use discovery_cache::DiscoveryCache;
+pub use events::{
+    correlate_pod_events,
+    list_pod_events,
+};
 pub use gvk::*;
+pub use hpa::HpaState;
+pub use job::{
+    CronJobState,
+    JobState,
+};
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
 pub use lease::*;
-pub use owners::OwnersCache;
+pub use metrics::{
+    list_pod_usage,
+    metrics_server_available,
+};
+pub use node::NodeState;
+pub use owner_chain_cache::{
+    OwnerChainCache,
+    TtlOwnerChainCache,
+};
+pub use owners::{
+    OwnerChain,
+    OwnersCache,
+};
+pub use pod_ext::MASKED_ENV_VALUE;
+pub use rate_limiter::RateLimiter;
+use regex::Regex;
 use serde::{
     Deserialize,
     Serialize,
@@ -43,11 +81,90 @@ err_impl! {KubernetesError,
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum PodLifecycleData {
     Empty,
+    Pending(i64),
     Running(i64),
-    Finished(i64, i64),
+    // The last field is the (optional) reason the pod terminated, e.g., Completed or OOMKilled;
+    // older traces don't include this, so it gets defaulted to None
+    Finished(i64, i64, #[serde(default)] Option<String>),
 }
 partial_ord_eq_ref!(PodLifecycleData);
 
+// Resource usage (as reported by metrics-server) for each container in a pod at a particular time
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct PodUsage {
+    pub ts: i64,
+    pub containers: BTreeMap<String, BTreeMap<String, Quantity>>,
+}
+
+// When a pod started running and when it first became Ready; the difference between the two is the
+// readiness delay that we want to reproduce in simulations
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct PodReadiness {
+    pub start_ts: i64,
+    pub ready_ts: i64,
+}
+
+// A Kubernetes Event that references a pod (e.g., Scheduled, Pulling, BackOff); these give some
+// context about why a pod took as long as it did to start (or why it failed)
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct PodEvent {
+    pub ts: i64,
+    pub event_type: String,
+    pub reason: String,
+    pub message: String,
+    pub count: i32,
+}
+
+// StatefulSet pods have a stable identity: the pod with ordinal N is always named <statefulset>-N,
+// and it keeps that name if it gets re-created.  We keep track of the ordinal for each pod along
+// with its position in the owner's pod sequence (i.e., which lifecycle data belongs to it), so that
+// the pods can be recreated in the same order with the same names.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PodOrdinal {
+    pub ordinal: u32,
+    pub lifecycle_idx: usize,
+}
+
+// The environment variables set on each container in a pod (including init containers), keyed by
+// container name and then by variable name; anything sensitive has been masked out (see
+// PodExt::container_env)
+pub type PodEnv = BTreeMap<String, BTreeMap<String, String>>;
+
+// The scheduling constraints that a pod declared: its node affinity, pod affinity and
+// anti-affinity, and its topology spread constraints, which is what a scheduler needs to place the
+// pod the same way during a replay.  Pods that don't declare any of these just have None here.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PodScheduling {
+    pub affinity: Option<corev1::Affinity>,
+    pub topology_spread_constraints: Option<Vec<corev1::TopologySpreadConstraint>>,
+}
+
+// The timing settings for one of a container's probes; together with how long the probe takes to
+// start passing, these decide how long it takes a pod to become ready (or to get restarted).  We
+// don't care what the probe actually checks, since nothing real is running in the simulation.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProbeTiming {
+    pub initial_delay_seconds: Option<i32>,
+    pub period_seconds: Option<i32>,
+    pub timeout_seconds: Option<i32>,
+    pub success_threshold: Option<i32>,
+    pub failure_threshold: Option<i32>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerProbes {
+    pub readiness: Option<ProbeTiming>,
+    pub liveness: Option<ProbeTiming>,
+    pub startup: Option<ProbeTiming>,
+}
+
+// The probes on each of a pod's containers, keyed by container name; containers that don't have
+// any probes are left out
+pub type PodProbes = BTreeMap<String, ContainerProbes>;
+
 pub trait KubeResourceExt {
     fn namespaced_name(&self) -> String;
     fn matches(&self, sel: &metav1::LabelSelector) -> anyhow::Result<bool>;
@@ -57,6 +174,10 @@ pub trait PodExt {
     fn spec(&self) -> anyhow::Result<&corev1::PodSpec>;
     fn stable_spec(&self) -> anyhow::Result<corev1::PodSpec>;
     fn status(&self) -> anyhow::Result<&corev1::PodStatus>;
+    fn ready_ts(&self) -> Option<i64>;
+    fn container_env(&self, sensitive_keys: &Regex) -> anyhow::Result<PodEnv>;
+    fn scheduling(&self) -> anyhow::Result<PodScheduling>;
+    fn probes(&self) -> anyhow::Result<PodProbes>;
 }
 
 trait StartEndTimeable {
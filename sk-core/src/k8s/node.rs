@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use kube::api::DynamicObject;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::k8s::GVK;
+use crate::prelude::*;
+
+const NODE_READY_CONDITION: &str = "Ready";
+
+// What a node looked like to the scheduler when we saw it: how much room it has, and whether it was
+// healthy.  The labels and taints are part of the node object itself, so they don't need to be in
+// here; the capacity and conditions live in the status, which (like the HPA and Job state) doesn't
+// survive a replay.  The condition timestamps get bumped every time the kubelet checks in, so we
+// only keep the status of each condition; that way, a node that's just sitting there doesn't turn
+// into a new event in the trace every few seconds, but one that goes NotReady does.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeState {
+    pub allocatable: BTreeMap<String, Quantity>,
+    pub capacity: BTreeMap<String, Quantity>,
+    pub conditions: BTreeMap<String, String>,
+    pub unschedulable: bool,
+}
+
+impl NodeState {
+    // Returns None if the object isn't a Node
+    pub fn from_dynamic_obj(obj: &DynamicObject) -> anyhow::Result<Option<NodeState>> {
+        if !GVK::from_dynamic_obj(obj).is_ok_and(|gvk| gvk.group.is_empty() && gvk.kind == "Node") {
+            return Ok(None);
+        }
+        Ok(Some(obj.clone().try_parse::<corev1::Node>()?.into()))
+    }
+
+    // A node that hasn't reported a Ready condition at all isn't ready either
+    pub fn is_ready(&self) -> bool {
+        self.conditions.get(NODE_READY_CONDITION).is_some_and(|status| status == "True")
+    }
+}
+
+impl From<corev1::Node> for NodeState {
+    fn from(node: corev1::Node) -> NodeState {
+        let spec = node.spec.unwrap_or_default();
+        let status = node.status.unwrap_or_default();
+
+        NodeState {
+            allocatable: status.allocatable.unwrap_or_default(),
+            capacity: status.capacity.unwrap_or_default(),
+            conditions: status
+                .conditions
+                .unwrap_or_default()
+                .into_iter()
+                .map(|cond| (cond.type_, cond.status))
+                .collect(),
+            unschedulable: spec.unschedulable.unwrap_or_default(),
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+use clockabilly::{
+    Clockable,
+    UtcClock,
+};
+use tracing::*;
+
+use super::OwnerChain;
+
+// The OwnersCache remembers the owner chain for every object it's looked up, so that we don't have
+// to hit the apiserver again every time we see an update for the same pod.  By default, entries are
+// kept forever, but with a lot of pod churn that means the cache just keeps growing, so there are
+// other implementations that evict entries.
+pub trait OwnerChainCache: Send {
+    fn get(&mut self, ns_name: &str) -> Option<&OwnerChain>;
+    fn insert(&mut self, ns_name: String, owners: OwnerChain);
+}
+
+impl OwnerChainCache for HashMap<String, OwnerChain> {
+    fn get(&mut self, ns_name: &str) -> Option<&OwnerChain> {
+        HashMap::get(self, ns_name)
+    }
+
+    fn insert(&mut self, ns_name: String, owners: OwnerChain) {
+        HashMap::insert(self, ns_name, owners);
+    }
+}
+
+// Entries expire ttl seconds after they were computed, at which point they'll be recomputed the
+// next time they're needed.  Expired entries are removed when they're looked up, and we also sweep
+// out everything that's expired (at most) once per ttl, so that entries for pods we never see
+// again don't stick around forever.
+pub struct TtlOwnerChainCache {
+    ttl: i64,
+    entries: HashMap<String, (i64, OwnerChain)>,
+    last_sweep_ts: i64,
+    clock: Box<dyn Clockable + Send>,
+}
+
+impl TtlOwnerChainCache {
+    pub fn new(ttl: i64) -> TtlOwnerChainCache {
+        TtlOwnerChainCache::new_with_clock(ttl, UtcClock::boxed())
+    }
+
+    pub fn new_with_clock(ttl: i64, clock: Box<dyn Clockable + Send>) -> TtlOwnerChainCache {
+        TtlOwnerChainCache {
+            ttl,
+            entries: HashMap::new(),
+            last_sweep_ts: clock.now_ts(),
+            clock,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl OwnerChainCache for TtlOwnerChainCache {
+    fn get(&mut self, ns_name: &str) -> Option<&OwnerChain> {
+        let now = self.clock.now_ts();
+        if self.entries.get(ns_name).is_some_and(|(ts, _)| now - ts >= self.ttl) {
+            debug!("cached owners for {ns_name} have expired");
+            self.entries.remove(ns_name);
+        }
+        self.entries.get(ns_name).map(|(_, owners)| owners)
+    }
+
+    fn insert(&mut self, ns_name: String, owners: OwnerChain) {
+        let now = self.clock.now_ts();
+        if now - self.last_sweep_ts >= self.ttl {
+            let ttl = self.ttl;
+            self.entries.retain(|_, (ts, _)| now - *ts < ttl);
+            self.last_sweep_ts = now;
+        }
+        self.entries.insert(ns_name, (now, owners));
+    }
+}
@@ -1,7 +1,15 @@
 use std::collections::HashMap;
+use std::sync::Mutex;
 
 use async_recursion::async_recursion;
-use kube::api::ListParams;
+use futures::{
+    stream,
+    StreamExt,
+};
+use kube::api::{
+    DynamicObject,
+    ListParams,
+};
 use kube::discovery::{
     ApiCapabilities,
     Scope,
@@ -16,60 +24,154 @@ use super::*;
 use crate::k8s::ApiSet;
 use crate::prelude::*;
 
+// Objects can have more than one owner, but at most one of them is the "controller" (e.g., the
+// ReplicaSet that created a pod).  The primary chain follows the controller owner at each level (or
+// the first owner, if none of them are marked as the controller); any other owners we see along the
+// way are kept separately, but we don't walk up their ownership chains.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OwnerChain {
+    pub controllers: Vec<metav1::OwnerReference>,
+    pub others: Vec<metav1::OwnerReference>,
+}
+
+impl OwnerChain {
+    pub fn all(&self) -> Vec<metav1::OwnerReference> {
+        self.controllers.iter().chain(self.others.iter()).cloned().collect()
+    }
+}
+
+impl From<Vec<metav1::OwnerReference>> for OwnerChain {
+    fn from(controllers: Vec<metav1::OwnerReference>) -> OwnerChain {
+        OwnerChain { controllers, others: vec![] }
+    }
+}
+
+// Owner chains can be computed concurrently (see compute_owner_chains), so the apiset and the cache
+// both live behind locks; neither lock is held while we're waiting on the apiserver.
 pub struct OwnersCache {
-    apiset: ApiSet,
-    owners: HashMap<String, Vec<metav1::OwnerReference>>,
+    apiset: tokio::sync::Mutex<ApiSet>,
+    owners: Mutex<Box<dyn OwnerChainCache>>,
+
+    // If this is set, owners are looked up with namespace-scoped APIs, so that we don't need
+    // permission to list objects across the whole cluster
+    namespace: Option<String>,
 }
 
 impl OwnersCache {
     pub fn new(apiset: ApiSet) -> OwnersCache {
-        OwnersCache { apiset, owners: HashMap::new() }
+        OwnersCache {
+            apiset: tokio::sync::Mutex::new(apiset),
+            owners: Mutex::new(Box::new(HashMap::new())),
+            namespace: None,
+        }
     }
 
-    pub fn new_from_parts(apiset: ApiSet, owners: HashMap<String, Vec<metav1::OwnerReference>>) -> OwnersCache {
-        OwnersCache { apiset, owners }
+    pub fn new_namespaced(apiset: ApiSet, namespace: &str) -> OwnersCache {
+        OwnersCache {
+            apiset: tokio::sync::Mutex::new(apiset),
+            owners: Mutex::new(Box::new(HashMap::new())),
+            namespace: Some(namespace.into()),
+        }
     }
 
-    // Recursively look up all of the owning objects for a given Kubernetes object
+    pub fn new_from_parts(apiset: ApiSet, owners: HashMap<String, OwnerChain>) -> OwnersCache {
+        OwnersCache {
+            apiset: tokio::sync::Mutex::new(apiset),
+            owners: Mutex::new(Box::new(owners)),
+            namespace: None,
+        }
+    }
+
+    // Swap out the default (never-evicting) cache for a different one; see owner_chain_cache.rs
+    pub fn with_cache(mut self, cache: Box<dyn OwnerChainCache>) -> OwnersCache {
+        self.owners = Mutex::new(cache);
+        self
+    }
+
+    // Computes the owner chains for a batch of objects (in order), with at most `concurrency`
+    // lookups in flight at once.  Objects with the same owner can race each other, in which case
+    // the owner gets looked up more than once (and whichever lookup finishes last is what's
+    // cached); with the usual handful of owners with a bunch of pods each, that only happens for the
+    // first few pods of each owner.
+    pub async fn compute_owner_chains<'a, T: Resource + Sync + 'a>(
+        &self,
+        objs: impl IntoIterator<Item = &'a T>,
+        concurrency: usize,
+    ) -> Vec<anyhow::Result<OwnerChain>> {
+        // The futures don't do anything until they're polled, so it's fine to make them all up front
+        // (and if we make them inside the stream, the compiler gets confused about the lifetimes
+        // when this gets called from a spawned task)
+        let lookups: Vec<_> = objs.into_iter().map(|obj| self.compute_owner_chain(obj)).collect();
+        stream::iter(lookups).buffered(concurrency.max(1)).collect().await
+    }
+
+    // Recursively look up the owning objects for a given Kubernetes object (see OwnerChain)
     #[async_recursion]
-    pub async fn compute_owner_chain(
-        &mut self,
-        obj: &(impl Resource + Sync),
-    ) -> anyhow::Result<Vec<metav1::OwnerReference>> {
+    pub async fn compute_owner_chain(&self, obj: &(impl Resource + Sync)) -> anyhow::Result<OwnerChain> {
         let ns_name = obj.namespaced_name();
         debug!("computing owner references for {ns_name}");
 
-        if let Some(owners) = self.owners.get(&ns_name) {
+        if let Some(owners) = self.lookup(&ns_name) {
             debug!("found owners {owners:?} for {ns_name} in cache");
-            return Ok(owners.clone());
+            return Ok(owners);
         }
 
-        let mut owners = Vec::from(obj.owner_references());
+        let mut owners = OwnerChain::default();
+        let refs = obj.owner_references();
+        let primary = refs.iter().position(|rf| rf.controller == Some(true)).unwrap_or(0);
+        if let Some(rf) = refs.get(primary) {
+            owners.controllers.push(rf.clone());
+            owners
+                .others
+                .extend(refs.iter().enumerate().filter(|(i, _)| *i != primary).map(|(_, o)| o.clone()));
 
-        for rf in obj.owner_references() {
             let owner_gvk = GVK::from_owner_ref(rf)?;
-            let (api, cap) = self.apiset.unnamespaced_api_by_gvk(&owner_gvk).await?;
+            let (api, cap) = self.owner_api(&owner_gvk).await?;
             let sel = build_owner_selector(&rf.name, obj, cap);
-            let resp = api.list(&sel).await?;
-            if resp.items.len() != 1 {
-                bail!("could not find single owner for {}, found {:?}", obj.namespaced_name(), resp.items);
+            let resp = api.list(&sel).await.map_err(SimKubeError::api)?;
+            match &resp.items[..] {
+                [parent] => {
+                    let parent = self.compute_owner_chain(parent).await?;
+                    owners.controllers.extend(parent.controllers);
+                    owners.others.extend(parent.others);
+                },
+                // Leader-election leases come and go as leadership changes hands, so the things
+                // they own can outlive them; in that case the chain just ends at the lease.
+                [] if is_lease(&owner_gvk) => {
+                    warn!("lease {} (owner of {ns_name}) not found; ending owner chain there", rf.name);
+                },
+                items => bail!("could not find single owner for {}, found {:?}", obj.namespaced_name(), items),
             }
-
-            let owner = &resp.items[0];
-            owners.extend(self.compute_owner_chain(owner).await?);
         }
 
-        self.owners.insert(ns_name.clone(), owners.clone());
+        self.owners.lock().unwrap().insert(ns_name.clone(), owners.clone());
 
         debug!("computed owners {owners:?} for {ns_name}");
         Ok(owners)
     }
 
-    pub fn lookup(&mut self, ns_name: &str) -> Option<&Vec<metav1::OwnerReference>> {
-        self.owners.get(ns_name)
+    pub fn lookup(&self, ns_name: &str) -> Option<OwnerChain> {
+        self.owners.lock().unwrap().get(ns_name).cloned()
+    }
+
+    // We hang on to the apiset just long enough to wait for the rate limiter and get a handle to the
+    // api; the list call happens after the lock is released, so concurrent lookups don't have to
+    // wait for each other's requests.
+    async fn owner_api(&self, gvk: &GVK) -> anyhow::Result<(kube::Api<DynamicObject>, ApiCapabilities)> {
+        let mut apiset = self.apiset.lock().await;
+        apiset.throttle().await;
+        let (api, cap) = match &self.namespace {
+            Some(ns) => apiset.namespaced_api_by_gvk(gvk, ns).await?,
+            None => apiset.unnamespaced_api_by_gvk(gvk).await?,
+        };
+        Ok((api.clone(), cap))
     }
 }
 
+fn is_lease(gvk: &GVK) -> bool {
+    gvk.group == "coordination.k8s.io" && gvk.kind == "Lease"
+}
+
 fn build_owner_selector(owner_name: &str, obj: &(impl Resource + Sync), owner_cap: ApiCapabilities) -> ListParams {
     let sel = match owner_cap.scope {
         Scope::Cluster => Some(format!("metadata.name={owner_name}")),
@@ -2,6 +2,7 @@ use super::*;
 use crate::prelude::*;
 
 const KUBE_SVC_ACCOUNT_VOLUME_NAME_PREFIX: &str = "kube-api-access";
+pub const MASKED_ENV_VALUE: &str = "***";
 
 macro_rules! filter_volumes {
     ($vols:expr) => {
@@ -62,4 +63,99 @@ impl PodExt for corev1::Pod {
             Some(ps) => Ok(ps),
         }
     }
+
+    // The last time the pod's Ready condition transitioned to True; if the pod isn't ready (or
+    // never has been), this returns None
+    fn ready_ts(&self) -> Option<i64> {
+        self.status
+            .as_ref()?
+            .conditions
+            .as_ref()?
+            .iter()
+            .find(|c| c.type_ == "Ready" && c.status == "True")?
+            .last_transition_time
+            .as_ref()
+            .map(|t| t.0.timestamp())
+    }
+
+    // Values that come from a Secret, or whose names match the sensitive_keys pattern (e.g.,
+    // `DB_PASSWORD`), are replaced with `***`; values that come from somewhere else (a ConfigMap,
+    // the downward API, etc) are recorded as a description of where they come from, since we
+    // can't see the actual value from the pod spec.  Variables pulled in wholesale via envFrom
+    // don't show up here at all, since we don't know what their names are.
+    fn container_env(&self, sensitive_keys: &Regex) -> anyhow::Result<PodEnv> {
+        let spec = self.spec()?;
+        let containers = spec.init_containers.iter().flatten().chain(spec.containers.iter());
+
+        let mut pod_env = PodEnv::new();
+        for container in containers {
+            let mut env = BTreeMap::new();
+            for var in container.env.iter().flatten() {
+                env.insert(var.name.clone(), env_value(var, sensitive_keys));
+            }
+            pod_env.insert(container.name.clone(), env);
+        }
+        Ok(pod_env)
+    }
+
+    // An empty affinity (or list of spread constraints) doesn't constrain anything, so we store
+    // those as None too
+    fn scheduling(&self) -> anyhow::Result<PodScheduling> {
+        let spec = self.spec()?;
+        Ok(PodScheduling {
+            affinity: spec.affinity.clone().filter(|a| *a != corev1::Affinity::default()),
+            topology_spread_constraints: spec.topology_spread_constraints.clone().filter(|tscs| !tscs.is_empty()),
+        })
+    }
+
+    // Init containers can only have probes if they're sidecars (i.e., restartPolicy = Always), but
+    // those hold up the pod's readiness the same as any other container, so we look at them too
+    fn probes(&self) -> anyhow::Result<PodProbes> {
+        let spec = self.spec()?;
+        let mut pod_probes = PodProbes::new();
+        for container in spec.init_containers.iter().flatten().chain(spec.containers.iter()) {
+            let probes = ContainerProbes {
+                readiness: container.readiness_probe.as_ref().map(probe_timing),
+                liveness: container.liveness_probe.as_ref().map(probe_timing),
+                startup: container.startup_probe.as_ref().map(probe_timing),
+            };
+            if probes != ContainerProbes::default() {
+                pod_probes.insert(container.name.clone(), probes);
+            }
+        }
+        Ok(pod_probes)
+    }
+}
+
+fn probe_timing(probe: &corev1::Probe) -> ProbeTiming {
+    ProbeTiming {
+        initial_delay_seconds: probe.initial_delay_seconds,
+        period_seconds: probe.period_seconds,
+        timeout_seconds: probe.timeout_seconds,
+        success_threshold: probe.success_threshold,
+        failure_threshold: probe.failure_threshold,
+    }
+}
+
+fn env_value(var: &corev1::EnvVar, sensitive_keys: &Regex) -> String {
+    if sensitive_keys.is_match(&var.name) {
+        return MASKED_ENV_VALUE.into();
+    }
+
+    match &var.value_from {
+        None => var.value.clone().unwrap_or_default(),
+        Some(src) => {
+            if src.secret_key_ref.is_some() {
+                MASKED_ENV_VALUE.into()
+            } else if let Some(cm_ref) = &src.config_map_key_ref {
+                format!("<configMap {}/{}>", cm_ref.name.as_deref().unwrap_or_default(), cm_ref.key)
+            } else if let Some(field_ref) = &src.field_ref {
+                format!("<field {}>", field_ref.field_path)
+            } else if let Some(res_ref) = &src.resource_field_ref {
+                format!("<resource {}>", res_ref.resource)
+            } else {
+                "<unknown>".into()
+            }
+        },
+    }
 }
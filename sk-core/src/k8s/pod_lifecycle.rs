@@ -18,9 +18,12 @@ use crate::jsonutils;
 use crate::prelude::*;
 
 // A PodLifecycleData object is how we track the length of time a pod was running in a cluster.  It
-// has three states, Empty, Running, and Finished.  For each state, we track the timestamps that
-// are relevant for that state, e.g., Running only has a start time, and Finished has both a start
-// and end time.
+// has four states, Empty, Pending, Running, and Finished.  For each state, we track the timestamps
+// that are relevant for that state, e.g., Running only has a start time, and Finished has both a
+// start and end time (plus, if we know it, the reason that the pod terminated).  Pending is for
+// pods that exist but haven't started any containers yet (e.g., they're unschedulable or stuck
+// pulling an image); we record the pod's creation timestamp there so that pods which _never_ start
+// still show up in the trace.
 //
 // We compute this by tracking the earliest container start time and the latest container end time
 // among all the containers in the pod (we don't want to use the pod's creation timestamp field,
@@ -42,11 +45,16 @@ use crate::prelude::*;
 // just fill in the finished timestamp with `Utc::now()`.
 
 impl PodLifecycleData {
-    fn new(start_ts: Option<i64>, end_ts: Option<i64>) -> PodLifecycleData {
+    fn new(
+        created_ts: Option<i64>,
+        start_ts: Option<i64>,
+        end_ts: Option<i64>,
+        reason: Option<String>,
+    ) -> PodLifecycleData {
         match (start_ts, end_ts) {
-            (None, _) => PodLifecycleData::Empty,
+            (None, _) => created_ts.map_or(PodLifecycleData::Empty, PodLifecycleData::Pending),
             (Some(ts), None) => PodLifecycleData::Running(ts),
-            (Some(start), Some(end)) => PodLifecycleData::Finished(start, end),
+            (Some(start), Some(end)) => PodLifecycleData::Finished(start, end, reason),
         }
     }
 
@@ -83,12 +91,14 @@ impl PodLifecycleData {
         if terminated_container_count != pod.spec()?.containers.len() {
             latest_end_ts = None;
         }
-        Ok(PodLifecycleData::new(earliest_start_ts, latest_end_ts))
+        let created_ts = pod.creation_timestamp().map(|t| t.0.timestamp());
+        let reason = pod_status.container_statuses.as_deref().and_then(dominant_termination_reason);
+        Ok(PodLifecycleData::new(created_ts, earliest_start_ts, latest_end_ts, reason))
     }
 
     pub fn end_ts(&self) -> Option<i64> {
         match self {
-            &PodLifecycleData::Finished(_, ts) => Some(ts),
+            &PodLifecycleData::Finished(_, ts, _) => Some(ts),
             _ => None,
         }
     }
@@ -96,7 +106,7 @@ impl PodLifecycleData {
     pub fn start_ts(&self) -> Option<i64> {
         match *self {
             PodLifecycleData::Running(ts) => Some(ts),
-            PodLifecycleData::Finished(ts, _) => Some(ts),
+            PodLifecycleData::Finished(ts, ..) => Some(ts),
             _ => None,
         }
     }
@@ -106,8 +116,8 @@ impl PodLifecycleData {
         // if the pod is still running at the end of the given time window, it counts as
         // overlapping the time window.
         match *self {
-            PodLifecycleData::Running(ts) => ts < end_ts,
-            PodLifecycleData::Finished(s, e) => (start_ts <= s && s < end_ts) || (start_ts <= e && e < end_ts),
+            PodLifecycleData::Pending(ts) | PodLifecycleData::Running(ts) => ts < end_ts,
+            PodLifecycleData::Finished(s, e, _) => (start_ts <= s && s < end_ts) || (start_ts <= e && e < end_ts),
             _ => false,
         }
     }
@@ -122,8 +132,11 @@ impl PodLifecycleData {
 
         match new_lifecycle_data {
             PodLifecycleData::Finished(..) => Ok(new_lifecycle_data),
-            PodLifecycleData::Running(start_ts) => Ok(PodLifecycleData::Finished(start_ts, now)),
-            PodLifecycleData::Empty => {
+            PodLifecycleData::Running(start_ts) => Ok(PodLifecycleData::Finished(start_ts, now, None)),
+            // If the pod never got out of pending, it never ran, so there's no "finished" state for
+            // it; the best we can say is that it was pending when it got deleted.
+            PodLifecycleData::Pending(_) if current_lifecycle_data.start_ts().is_none() => Ok(new_lifecycle_data),
+            PodLifecycleData::Pending(_) | PodLifecycleData::Empty => {
                 let start_ts = if let Some(ts) = current_lifecycle_data.start_ts() {
                     ts
                 } else if let Some(t) = pod.creation_timestamp() {
@@ -131,15 +144,38 @@ impl PodLifecycleData {
                 } else {
                     bail!("could not determine final pod lifecycle for {}", pod.namespaced_name());
                 };
-                Ok(PodLifecycleData::Finished(start_ts, now))
+                Ok(PodLifecycleData::Finished(start_ts, now, None))
             },
         }
     }
 
+    // Move all of the timestamps by the same amount; a pod that's still running stays open-ended
+    pub fn shift(&mut self, delta: i64) {
+        match self {
+            PodLifecycleData::Pending(ts) | PodLifecycleData::Running(ts) => *ts += delta,
+            PodLifecycleData::Finished(start, end, _) => {
+                *start += delta;
+                *end += delta;
+            },
+            PodLifecycleData::Empty => (),
+        }
+    }
+
+    pub fn termination_reason(&self) -> Option<&str> {
+        match self {
+            PodLifecycleData::Finished(_, _, reason) => reason.as_deref(),
+            _ => None,
+        }
+    }
+
     pub fn empty(&self) -> bool {
         self == PodLifecycleData::Empty
     }
 
+    pub fn pending(&self) -> bool {
+        matches!(self, PodLifecycleData::Pending(_))
+    }
+
     pub fn running(&self) -> bool {
         matches!(self, PodLifecycleData::Running(_))
     }
@@ -150,8 +186,8 @@ impl PodLifecycleData {
 
     pub fn to_annotation_patch(&self) -> Option<PatchOperation> {
         match self {
-            PodLifecycleData::Empty | PodLifecycleData::Running(_) => None,
-            PodLifecycleData::Finished(start_ts, end_ts) => Some(PatchOperation::Add(AddOperation {
+            PodLifecycleData::Empty | PodLifecycleData::Pending(_) | PodLifecycleData::Running(_) => None,
+            PodLifecycleData::Finished(start_ts, end_ts, _) => Some(PatchOperation::Add(AddOperation {
                 path: format!("/metadata/annotations/{}", jsonutils::escape(LIFETIME_ANNOTATION_KEY)),
                 value: Value::String(format!("{}", end_ts - start_ts)),
             })),
@@ -168,9 +204,13 @@ impl PodLifecycleData {
 //
 // With this in mind, we implemnt a partial order over PodLifecycleData, as follows:
 //   - Empty < X, \forall X
+//   - Pending(created) < X, \forall X not Empty or Pending
+//   - Pending(created1) <> Pending(created2), \forall created1 != created2
 //   - Running(start) < Finished(start, end), \forall Running, Finished, start, end
 //   - Running(start1) <> Finished(start2, end), \forall start1 != start2
 //   - Finished(start1, end1) <> Finished(start2, end2) \forall (start1 != start2 || end1 != end2)
+//   - Finished(start, end, None) < Finished(start, end, Some(reason)), \forall reason
+//   - Finished(start, end, Some(r1)) <> Finished(start, end, Some(r2)), \forall r1 != r2
 //
 // This allows us to concisely check for _valid_ updates to pod lifecycle data with an expression
 // like if pld1 > pld2 { do update };  if pld1 and pld2 aren't comparable, no update will occur.
@@ -184,8 +224,19 @@ impl PartialOrd for PodLifecycleData {
                     Some(Ordering::Equal)
                 }
             },
-            PodLifecycleData::Running(ts) => match other {
+            PodLifecycleData::Pending(ts) => match other {
                 PodLifecycleData::Empty => Some(Ordering::Greater),
+                PodLifecycleData::Pending(other_ts) => {
+                    if ts == other_ts {
+                        Some(Ordering::Equal)
+                    } else {
+                        None
+                    }
+                },
+                PodLifecycleData::Running(_) | PodLifecycleData::Finished(..) => Some(Ordering::Less),
+            },
+            PodLifecycleData::Running(ts) => match other {
+                PodLifecycleData::Empty | PodLifecycleData::Pending(_) => Some(Ordering::Greater),
                 PodLifecycleData::Running(other_ts) => {
                     if ts == other_ts {
                         Some(Ordering::Equal)
@@ -195,8 +246,8 @@ impl PartialOrd for PodLifecycleData {
                 },
                 PodLifecycleData::Finished(..) => Some(Ordering::Less),
             },
-            PodLifecycleData::Finished(sts, ets) => match other {
-                PodLifecycleData::Empty => Some(Ordering::Greater),
+            PodLifecycleData::Finished(sts, ets, reason) => match other {
+                PodLifecycleData::Empty | PodLifecycleData::Pending(_) => Some(Ordering::Greater),
                 PodLifecycleData::Running(other_ts) => {
                     if sts == other_ts {
                         Some(Ordering::Greater)
@@ -204,11 +255,17 @@ impl PartialOrd for PodLifecycleData {
                         None
                     }
                 },
-                PodLifecycleData::Finished(other_sts, other_ets) => {
-                    if sts == other_sts && ets == other_ets {
-                        Some(Ordering::Equal)
-                    } else {
+                PodLifecycleData::Finished(other_sts, other_ets, other_reason) => {
+                    if sts != other_sts || ets != other_ets {
                         None
+                    } else {
+                        match (reason, other_reason) {
+                            (None, None) => Some(Ordering::Equal),
+                            (None, Some(_)) => Some(Ordering::Less),
+                            (Some(_), None) => Some(Ordering::Greater),
+                            (Some(r1), Some(r2)) if r1 == r2 => Some(Ordering::Equal),
+                            _ => None,
+                        }
                     }
                 },
             },
@@ -229,6 +286,19 @@ fn get_start_end_ts(pod: &corev1::Pod, container: &str, state: &corev1::Containe
     (start_ts, end_ts)
 }
 
+// If the pod has multiple containers, they may have terminated for different reasons; we want the
+// reason that best explains why the pod ended, so any failure (Error, OOMKilled, etc.) takes
+// precedence over Completed, and among the failures we pick the one that happened first, since
+// that's most likely the root cause.
+fn dominant_termination_reason(cstats: &[corev1::ContainerStatus]) -> Option<String> {
+    cstats
+        .iter()
+        .filter_map(|s| s.state.as_ref()?.terminated.as_ref())
+        .filter_map(|t| Some((t.reason.as_deref()?, t.finished_at.as_ref().map(|ts| ts.0.timestamp()))))
+        .min_by_key(|&(reason, finished_ts)| (reason == "Completed", finished_ts))
+        .map(|(reason, _)| reason.into())
+}
+
 impl PartialEq<Option<&PodLifecycleData>> for PodLifecycleData {
     fn eq(&self, other: &Option<&PodLifecycleData>) -> bool {
         match self {
@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use clockabilly::{
+    Clockable,
+    DateTime,
+    Utc,
+    UtcClock,
+};
+
+// A simple token bucket for throttling requests to the apiserver: the bucket holds up to `burst`
+// tokens and refills at `qps` tokens per second; each request takes a token, and if there aren't
+// any left, the request has to wait until one is available.
+pub struct RateLimiter {
+    qps: f64,
+    burst: f64,
+    tokens: f64,
+    last_refill: DateTime<Utc>,
+    clock: Box<dyn Clockable + Send>,
+}
+
+impl RateLimiter {
+    pub fn new(qps: f64, burst: u32) -> RateLimiter {
+        RateLimiter::new_with_clock(qps, burst, UtcClock::boxed())
+    }
+
+    pub fn new_with_clock(qps: f64, burst: u32, clock: Box<dyn Clockable + Send>) -> RateLimiter {
+        // A burst of zero would mean we could never make any requests at all
+        let burst = burst.max(1) as f64;
+        RateLimiter {
+            qps,
+            burst,
+            tokens: burst,
+            last_refill: clock.now(),
+            clock,
+        }
+    }
+
+    pub async fn acquire(&mut self) {
+        loop {
+            match self.try_acquire() {
+                Some(wait) => tokio::time::sleep(wait).await,
+                None => return,
+            }
+        }
+    }
+
+    // Take a token if one is available; otherwise, return how long we have to wait until there
+    // will be one (without taking it).
+    pub fn try_acquire(&mut self) -> Option<Duration> {
+        let now = self.clock.now();
+        let elapsed = (now - self.last_refill).to_std().unwrap_or_default().as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.qps).min(self.burst);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - self.tokens) / self.qps))
+        }
+    }
+}
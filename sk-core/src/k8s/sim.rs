@@ -24,6 +24,12 @@ pub fn metrics_svc_account(sim: &Simulation) -> String {
     }
 }
 
+// The driver is a Job, so its pods get labeled with the job name by the Job controller; both the
+// controller and skctl use this to find the driver (and its pods) for a simulation
+pub fn driver_name(sim_name: &str) -> String {
+    format!("sk-{sim_name}-driver")
+}
+
 pub fn is_terminal(sim_state: &SimulationState) -> bool {
     matches!(sim_state, SimulationState::Finished | SimulationState::Failed)
 }
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use clockabilly::mock::MockUtcClock;
+use serde_json::json;
+
+use super::*;
+
+fn foo_resources(version: &str) -> serde_json::Value {
+    json!({
+        "kind": "APIResourceList",
+        "apiVersion": "v1",
+        "groupVersion": format!("example.com/{version}"),
+        "resources": [{
+            "name": "foos",
+            "singularName": "foo",
+            "namespaced": true,
+            "kind": "Foo",
+            "verbs": ["get", "list", "watch"],
+        }],
+    })
+}
+
+// The example.com group serves Foo at both v1 and v1beta1, and prefers v1
+fn multi_version_apiserver() -> (MockServerBuilder, kube::Client) {
+    let (mut fake_apiserver, client) = make_fake_apiserver();
+    fake_apiserver
+        .handle(|when, then| {
+            when.path("/apis");
+            then.json_body(json!({
+                "kind": "APIGroupList",
+                "apiVersion": "v1",
+                "groups": [{
+                    "name": "example.com",
+                    "versions": [
+                        {"groupVersion": "example.com/v1", "version": "v1"},
+                        {"groupVersion": "example.com/v1beta1", "version": "v1beta1"},
+                    ],
+                    "preferredVersion": {"groupVersion": "example.com/v1", "version": "v1"},
+                }],
+            }));
+        })
+        .handle(|when, then| {
+            when.path("/apis/example.com/v1");
+            then.json_body(foo_resources("v1"));
+        })
+        .handle(|when, then| {
+            when.path("/apis/example.com/v1beta1");
+            then.json_body(foo_resources("v1beta1"));
+        });
+    (fake_apiserver, client)
+}
+
+#[rstest]
+#[case::apiserver_preferred(HashMap::new(), "v1beta1", "v1")]
+#[case::configured(HashMap::from([("example.com".into(), "v1beta1".into())]), "v1", "v1beta1")]
+#[case::configured_not_served(HashMap::from([("example.com".into(), "v2".into())]), "v1beta1", "v1")]
+#[tokio::test]
+async fn test_api_by_gvk_version_preferences(
+    #[case] prefs: HashMap<String, String>,
+    #[case] requested: &str,
+    #[case] expected: &str,
+) {
+    let (mut fake_apiserver, client) = multi_version_apiserver();
+    fake_apiserver.build();
+
+    let gvk = GVK::new("example.com", requested, "Foo");
+    let mut apiset = ApiSet::new(client).with_version_preferences(prefs);
+    let (api, _) = apiset.unnamespaced_api_by_gvk(&gvk).await.unwrap();
+
+    assert_eq!(api.resource_url(), format!("/apis/example.com/{expected}/foos"));
+    assert_eq!(apiset.resolved_gvk(&gvk), Some(&GVK::new("example.com", expected, "Foo")));
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_api_by_gvk_no_version_preferences() {
+    let (mut fake_apiserver, client) = multi_version_apiserver();
+    fake_apiserver.build();
+
+    let gvk = GVK::new("example.com", "v1beta1", "Foo");
+    let mut apiset = ApiSet::new(client);
+    let (api, _) = apiset.unnamespaced_api_by_gvk(&gvk).await.unwrap();
+
+    assert_eq!(api.resource_url(), "/apis/example.com/v1beta1/foos");
+    assert_eq!(apiset.resolved_gvk(&gvk), None);
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_api_by_gvk_api_error() {
+    let (mut fake_apiserver, client) = make_fake_apiserver();
+    fake_apiserver.handle_not_found("/apis/example.com/v1/foos".into()).build();
+
+    let gvk = GVK::new("example.com", "v1", "Foo");
+    let err = ApiSet::new(client).unnamespaced_api_by_gvk(&gvk).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<SimKubeError>(), Some(SimKubeError::Api(_))));
+}
+
+#[rstest]
+#[case::cached(false, 0, 1)]
+#[case::force_refresh(true, 0, 2)]
+#[case::expired(false, 600, 2)]
+#[tokio::test]
+async fn test_api_by_gvk_discovery_cache(#[case] force_refresh: bool, #[case] elapsed: i64, #[case] hits: usize) {
+    let (mut fake_apiserver, client) = multi_version_apiserver();
+    fake_apiserver.build();
+
+    let path = std::env::temp_dir()
+        .join(format!("sk-discovery-cache-test-{force_refresh}-{elapsed}-{}", std::process::id()))
+        .join("discovery.json");
+    let url = fake_apiserver.url().to_string();
+    let gvk = GVK::new("example.com", "v1beta1", "Foo");
+
+    let mut clock = MockUtcClock::new(0);
+    let cache = DiscoveryCache::new_with_clock(path.clone(), &url, 300, false, Box::new(clock.clone()));
+    let mut apiset = ApiSet::new(client.clone()).with_discovery_cache(cache);
+    apiset.unnamespaced_api_by_gvk(&gvk).await.unwrap();
+
+    // A new ApiSet (and cache) is what we'd get on the next run of skctl
+    clock.advance(elapsed);
+    let cache = DiscoveryCache::new_with_clock(path.clone(), &url, 300, force_refresh, Box::new(clock));
+    let mut apiset = ApiSet::new(client).with_discovery_cache(cache);
+    let (api, _) = apiset.unnamespaced_api_by_gvk(&gvk).await.unwrap();
+
+    assert_eq!(api.resource_url(), "/apis/example.com/v1beta1/foos");
+    fake_apiserver.assert_hits(2, hits);
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_api_by_gvk_discovery_cache_different_preferences() {
+    let (mut fake_apiserver, client) = multi_version_apiserver();
+    fake_apiserver.build();
+
+    let path = std::env::temp_dir()
+        .join(format!("sk-discovery-cache-test-prefs-{}", std::process::id()))
+        .join("discovery.json");
+    let url = fake_apiserver.url().to_string();
+    let gvk = GVK::new("example.com", "v1beta1", "Foo");
+
+    let cache = DiscoveryCache::new(path.clone(), &url, 300, false);
+    let mut apiset = ApiSet::new(client.clone()).with_discovery_cache(cache);
+    apiset.unnamespaced_api_by_gvk(&gvk).await.unwrap();
+
+    // The cached entry was looked up without any preferences, so it doesn't count here
+    let cache = DiscoveryCache::new(path.clone(), &url, 300, false);
+    let mut apiset = ApiSet::new(client)
+        .with_version_preferences(HashMap::new())
+        .with_discovery_cache(cache);
+    let (api, _) = apiset.unnamespaced_api_by_gvk(&gvk).await.unwrap();
+
+    assert_eq!(api.resource_url(), "/apis/example.com/v1/foos");
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
@@ -0,0 +1,25 @@
+use super::*;
+
+const TEST_KUBECONFIG: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/k8s/tests/data/kubeconfig.yml");
+
+#[rstest]
+#[tokio::test]
+async fn test_build_client_config_explicit_kubeconfig() {
+    let config = build_client_config(Some(TEST_KUBECONFIG), None).await.unwrap();
+    assert_eq!(config.cluster_url, "https://prod.example.com:6443/");
+    assert_eq!(config.default_namespace, "default");
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_build_client_config_explicit_context() {
+    let config = build_client_config(Some(TEST_KUBECONFIG), Some("kind-test")).await.unwrap();
+    assert_eq!(config.cluster_url, "https://127.0.0.1:34567/");
+    assert_eq!(config.default_namespace, "simkube");
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_build_client_config_missing_context() {
+    assert!(build_client_config(Some(TEST_KUBECONFIG), Some("nope")).await.is_err());
+}
@@ -0,0 +1,81 @@
+use httpmock::Method::*;
+use serde_json::json;
+
+use super::*;
+
+fn event(kind: &str, name: &str, reason: &str, ts: &str) -> serde_json::Value {
+    json!({
+        "metadata": {"name": format!("{name}.{reason}"), "namespace": TEST_NAMESPACE},
+        "involvedObject": {"kind": kind, "namespace": TEST_NAMESPACE, "name": name},
+        "reason": reason,
+        "message": format!("{reason} happened"),
+        "type": "Normal",
+        "lastTimestamp": ts,
+    })
+}
+
+#[rstest]
+fn test_correlate_pod_events() {
+    let events: Vec<corev1::Event> = serde_json::from_value(json!([
+        event("Pod", "the-pod", "Pulling", "2024-10-01T00:00:10Z"),
+        event("Pod", "the-pod", "Scheduled", "2024-10-01T00:00:00Z"),
+        event("Pod", "other-pod", "BackOff", "2024-10-01T00:00:05Z"),
+        event("Deployment", "the-deployment", "ScalingReplicaSet", "2024-10-01T00:00:00Z"),
+    ]))
+    .unwrap();
+
+    let pod_events = correlate_pod_events(events);
+    assert_eq!(pod_events.len(), 2);
+
+    let reasons: Vec<_> = pod_events[&format!("{TEST_NAMESPACE}/the-pod")]
+        .iter()
+        .map(|e| (e.ts, e.reason.as_str()))
+        .collect();
+    assert_eq!(reasons, vec![(1727740800, "Scheduled"), (1727740810, "Pulling")]);
+    assert_eq!(pod_events[&format!("{TEST_NAMESPACE}/other-pod")][0].count, 1);
+}
+
+#[rstest]
+#[traced_test]
+#[tokio::test]
+async fn test_list_pod_events_paginated() {
+    let (mut fake_apiserver, client) = make_fake_apiserver();
+    fake_apiserver
+        .handle(|when, then| {
+            when.method(GET)
+                .path("/api/v1/events")
+                .query_param("fieldSelector", "involvedObject.kind=Pod")
+                .query_param("continue", "page2");
+            then.json_body(json!({
+                "kind": "EventList",
+                "apiVersion": "v1",
+                "metadata": {},
+                "items": [event("Pod", "the-pod", "Started", "2024-10-01T00:00:20Z")],
+            }));
+        })
+        .handle(|when, then| {
+            when.method(GET)
+                .path("/api/v1/events")
+                .query_param("fieldSelector", "involvedObject.kind=Pod")
+                .matches(|req| {
+                    let params = req.query_params.as_deref().unwrap_or_default();
+                    !params.iter().any(|(k, _)| k == "continue")
+                });
+            then.json_body(json!({
+                "kind": "EventList",
+                "apiVersion": "v1",
+                "metadata": {"continue": "page2"},
+                "items": [event("Pod", "the-pod", "Scheduled", "2024-10-01T00:00:00Z")],
+            }));
+        })
+        .build();
+
+    let pod_events = list_pod_events(client, Some(RateLimiter::new(1000.0, 10))).await.unwrap();
+    fake_apiserver.assert();
+
+    let reasons: Vec<_> = pod_events[&format!("{TEST_NAMESPACE}/the-pod")]
+        .iter()
+        .map(|e| e.reason.as_str())
+        .collect();
+    assert_eq!(reasons, vec!["Scheduled", "Started"]);
+}
@@ -0,0 +1,77 @@
+use k8s_openapi::api::autoscaling::v2 as autoscalingv2;
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use kube::api::DynamicObject;
+use serde_json::json;
+
+use super::*;
+
+#[rstest]
+fn test_hpa_state_v1(test_hpa_v1: DynamicObject) {
+    let state = HpaState::from_dynamic_obj(&test_hpa_v1).unwrap().unwrap();
+    assert_eq!(
+        state,
+        HpaState {
+            min_replicas: Some(2),
+            max_replicas: 10,
+            current_replicas: Some(3),
+            desired_replicas: Some(5),
+            metrics: vec![autoscalingv2::MetricSpec {
+                type_: "Resource".into(),
+                resource: Some(autoscalingv2::ResourceMetricSource {
+                    name: "cpu".into(),
+                    target: autoscalingv2::MetricTarget {
+                        type_: "Utilization".into(),
+                        average_utilization: Some(50),
+                        ..Default::default()
+                    },
+                }),
+                ..Default::default()
+            }],
+            current_metrics: vec![autoscalingv2::MetricStatus {
+                type_: "Resource".into(),
+                resource: Some(autoscalingv2::ResourceMetricStatus {
+                    name: "cpu".into(),
+                    current: autoscalingv2::MetricValueStatus {
+                        average_utilization: Some(80),
+                        ..Default::default()
+                    },
+                }),
+                ..Default::default()
+            }],
+        }
+    );
+}
+
+#[rstest]
+fn test_hpa_state_v2(test_hpa_v2: DynamicObject) {
+    let state = HpaState::from_dynamic_obj(&test_hpa_v2).unwrap().unwrap();
+    assert_eq!(state.min_replicas, Some(2));
+    assert_eq!(state.max_replicas, 10);
+    assert_eq!(state.current_replicas, Some(3));
+    assert_eq!(state.desired_replicas, Some(4));
+    assert_eq!(state.metrics[0].resource.as_ref().unwrap().target.average_value, Some(Quantity("500Mi".into())));
+    assert_eq!(
+        state.current_metrics[0].resource.as_ref().unwrap().current.average_value,
+        Some(Quantity("650Mi".into()))
+    );
+}
+
+#[rstest]
+fn test_hpa_state_no_status(mut test_hpa_v2: DynamicObject) {
+    test_hpa_v2.data.as_object_mut().unwrap().remove("status");
+    let state = HpaState::from_dynamic_obj(&test_hpa_v2).unwrap().unwrap();
+    assert_eq!(state.current_replicas, None);
+    assert_eq!(state.desired_replicas, None);
+    assert!(state.current_metrics.is_empty());
+}
+
+#[rstest]
+fn test_hpa_state_not_hpa() {
+    let depl: DynamicObject = serde_json::from_value(json!({
+        "apiVersion": "apps/v1",
+        "kind": "Deployment",
+        "metadata": {"namespace": TEST_NAMESPACE, "name": TEST_DEPLOYMENT},
+    }))
+    .unwrap();
+    assert_eq!(HpaState::from_dynamic_obj(&depl).unwrap(), None);
+}
@@ -0,0 +1,68 @@
+use kube::api::DynamicObject;
+
+use super::*;
+
+const JAN_1_2024: i64 = 1704067200;
+
+#[rstest]
+fn test_job_state(test_completed_job: DynamicObject) {
+    let state = JobState::from_dynamic_obj(&test_completed_job).unwrap().unwrap();
+    assert_eq!(
+        state,
+        JobState {
+            completions: Some(6),
+            parallelism: Some(3),
+            succeeded: Some(6),
+            failed: Some(1),
+            start_ts: Some(JAN_1_2024),
+            completion_ts: Some(JAN_1_2024 + 600),
+        }
+    );
+    assert!(state.is_complete());
+}
+
+#[rstest]
+#[case::serial(Some(3), None, 200)]
+#[case::parallel(Some(6), Some(3), 300)]
+#[case::uneven_waves(Some(5), Some(2), 200)]
+#[case::more_parallel_than_completions(Some(2), Some(5), 600)]
+#[case::work_queue(None, Some(4), 600)]
+fn test_job_pod_lifetime(#[case] completions: Option<i32>, #[case] parallelism: Option<i32>, #[case] expected: i64) {
+    let state = JobState {
+        completions,
+        parallelism,
+        start_ts: Some(0),
+        completion_ts: Some(600),
+        ..Default::default()
+    };
+    assert_eq!(state.pod_lifetime(), Some(expected));
+}
+
+#[rstest]
+fn test_job_pod_lifetime_incomplete() {
+    let state = JobState { start_ts: Some(0), ..Default::default() };
+    assert!(!state.is_complete());
+    assert_eq!(state.pod_lifetime(), None);
+}
+
+#[rstest]
+fn test_cronjob_state(test_cronjob: DynamicObject) {
+    let state = CronJobState::from_dynamic_obj(&test_cronjob).unwrap().unwrap();
+    assert_eq!(
+        state,
+        CronJobState {
+            schedule: "*/5 * * * *".into(),
+            suspend: None,
+            active: 1,
+            last_schedule_ts: Some(JAN_1_2024 + 300),
+            last_successful_ts: Some(JAN_1_2024 + 60),
+        }
+    );
+}
+
+#[rstest]
+fn test_job_state_other_kinds(test_deployment: DynamicObject, test_cronjob: DynamicObject) {
+    assert_eq!(JobState::from_dynamic_obj(&test_deployment).unwrap(), None);
+    assert_eq!(JobState::from_dynamic_obj(&test_cronjob).unwrap(), None);
+    assert_eq!(CronJobState::from_dynamic_obj(&test_deployment).unwrap(), None);
+}
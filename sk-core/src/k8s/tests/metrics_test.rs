@@ -0,0 +1,54 @@
+use httpmock::Method::*;
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use serde_json::json;
+
+use super::*;
+
+#[rstest]
+#[traced_test]
+#[tokio::test]
+async fn test_list_pod_usage() {
+    let (mut fake_apiserver, client) = make_fake_apiserver();
+    fake_apiserver
+        .handle(|when, then| {
+            when.method(GET).path("/apis/metrics.k8s.io/v1beta1/pods");
+            then.json_body(json!({
+                "kind": "PodMetricsList",
+                "apiVersion": "metrics.k8s.io/v1beta1",
+                "metadata": {},
+                "items": [{
+                    "metadata": {"name": "the-pod", "namespace": TEST_NAMESPACE},
+                    "timestamp": "2024-10-01T00:00:00Z",
+                    "window": "15s",
+                    "containers": [
+                        {"name": "nginx", "usage": {"cpu": "25m", "memory": "12Mi"}},
+                        {"name": "sidecar", "usage": {"cpu": "1m", "memory": "1Mi"}},
+                    ],
+                }],
+            }));
+        })
+        .build();
+
+    let usage = list_pod_usage(client, 10).await.unwrap().unwrap();
+    fake_apiserver.assert();
+
+    let pod_usage = &usage[&format!("{TEST_NAMESPACE}/the-pod")];
+    assert_eq!(pod_usage.ts, 10);
+    assert_eq!(pod_usage.containers.len(), 2);
+    assert_eq!(pod_usage.containers["nginx"]["cpu"], Quantity("25m".into()));
+    assert_eq!(pod_usage.containers["sidecar"]["memory"], Quantity("1Mi".into()));
+}
+
+#[rstest]
+#[traced_test]
+#[tokio::test]
+async fn test_list_pod_usage_no_metrics_server() {
+    let (mut fake_apiserver, client) = make_fake_apiserver();
+    fake_apiserver
+        .handle_not_found("/apis/metrics.k8s.io/v1beta1/pods".into())
+        .build();
+
+    let usage = list_pod_usage(client, 10).await.unwrap();
+    fake_apiserver.assert();
+    assert_eq!(usage, None);
+}
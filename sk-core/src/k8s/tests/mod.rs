@@ -1,7 +1,16 @@
+mod apiset_test;
+mod client_test;
 mod container_state_test;
+mod events_test;
+mod hpa_test;
+mod job_test;
 mod lease_test;
+mod metrics_test;
+mod node_test;
 mod owners_test;
+mod pod_ext_test;
 mod pod_lifecycle_test;
+mod rate_limiter_test;
 mod util_test;
 
 use rstest::*;
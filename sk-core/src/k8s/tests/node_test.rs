@@ -0,0 +1,26 @@
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use kube::api::DynamicObject;
+
+use super::*;
+
+#[rstest]
+#[case::ready(true)]
+#[case::not_ready(false)]
+fn test_node_state(#[case] ready: bool) {
+    let state = NodeState::from_dynamic_obj(&test_node(ready)).unwrap().unwrap();
+    assert_eq!(state.allocatable["cpu"], Quantity("3920m".into()));
+    assert_eq!(state.capacity["memory"], Quantity("16Gi".into()));
+    assert_eq!(state.conditions["MemoryPressure"], "False");
+    assert_eq!(state.is_ready(), ready);
+    assert!(!state.unschedulable);
+}
+
+#[rstest]
+fn test_node_state_not_a_node(test_completed_job: DynamicObject) {
+    assert_eq!(NodeState::from_dynamic_obj(&test_completed_job).unwrap(), None);
+}
+
+#[rstest]
+fn test_node_state_no_conditions() {
+    assert!(!NodeState::default().is_ready());
+}
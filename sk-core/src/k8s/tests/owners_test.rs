@@ -1,5 +1,10 @@
 use std::collections::HashMap;
+use std::time::{
+    Duration,
+    Instant,
+};
 
+use clockabilly::mock::MockUtcClock;
 use kube::ResourceExt;
 use serde_json::json;
 
@@ -28,11 +33,11 @@ async fn test_compute_owner_chain_cached(mut test_pod: corev1::Pod) {
     let expected_owners = vec![rsref, deplref];
 
     let (_, client) = make_fake_apiserver();
-    let owners = HashMap::from([(test_pod.namespaced_name(), expected_owners.clone())]);
-    let mut cache = OwnersCache::new_from_parts(ApiSet::new(client), owners);
+    let owners = HashMap::from([(test_pod.namespaced_name(), expected_owners.clone().into())]);
+    let cache = OwnersCache::new_from_parts(ApiSet::new(client), owners);
 
     let res = cache.compute_owner_chain(&test_pod).await.unwrap();
-    assert_eq!(res, expected_owners);
+    assert_eq!(res.all(), expected_owners);
 }
 
 #[rstest]
@@ -93,10 +98,407 @@ async fn test_compute_owner_chain(mut test_pod: corev1::Pod) {
     });
     fake_apiserver.build();
 
-    let mut cache = OwnersCache::new(ApiSet::new(client));
+    let cache = OwnersCache::new(ApiSet::new(client));
 
     test_pod.owner_references_mut().push(rsref.clone());
     let res = cache.compute_owner_chain(&test_pod).await.unwrap();
 
-    assert_eq!(res, vec![rsref, deplref]);
+    assert_eq!(res.controllers, vec![rsref, deplref]);
+    assert!(res.others.is_empty());
+}
+
+#[rstest]
+#[traced_test]
+#[tokio::test]
+async fn test_compute_owner_chain_discovery_cached(mut test_pod: corev1::Pod) {
+    let rsref = metav1::OwnerReference {
+        api_version: "apps/v1".into(),
+        kind: "ReplicaSet".into(),
+        name: "test-rs".into(),
+        uid: "asdfasdf".into(),
+        ..Default::default()
+    };
+
+    let (mut fake_apiserver, client) = make_fake_apiserver();
+    fake_apiserver
+        .handle(|when, then| {
+            when.path("/apis/apps/v1");
+            then.json_body(apps_v1_discovery());
+        })
+        .handle(|when, then| {
+            when.path("/apis/apps/v1/replicasets");
+            then.json_body(json!({
+                "metadata": {},
+                "items": [{"metadata": {"namespace": TEST_NAMESPACE, "name": "test-rs"}}],
+            }));
+        })
+        .build();
+
+    // Two different pods, so the second one doesn't just come out of the owner chain cache; the
+    // owner still has to be listed both times, but the ReplicaSet resource only gets discovered once
+    let cache = OwnersCache::new(ApiSet::new(client));
+    test_pod.owner_references_mut().push(rsref.clone());
+    let mut other_pod = test_pod.clone();
+    other_pod.metadata.name = Some("other-pod".into());
+
+    cache.compute_owner_chain(&test_pod).await.unwrap();
+    let res = cache.compute_owner_chain(&other_pod).await.unwrap();
+
+    assert_eq!(res.controllers, vec![rsref]);
+    fake_apiserver.assert_hits(0, 1);
+    fake_apiserver.assert_hits(1, 2);
+}
+
+#[rstest]
+#[traced_test]
+#[tokio::test]
+async fn test_compute_owner_chain_prefers_controller(mut test_pod: corev1::Pod) {
+    let otherref = metav1::OwnerReference {
+        api_version: "v1".into(),
+        kind: "ConfigMap".into(),
+        name: "test-cm".into(),
+        uid: "qwerqwer".into(),
+        ..Default::default()
+    };
+    let rsref = metav1::OwnerReference {
+        api_version: "apps/v1".into(),
+        kind: "ReplicaSet".into(),
+        name: "test-rs".into(),
+        uid: "asdfasdf".into(),
+        controller: Some(true),
+        ..Default::default()
+    };
+
+    // The ConfigMap is listed first, but since it's not the controller we don't look it up
+    let (mut fake_apiserver, client) = make_fake_apiserver();
+    fake_apiserver
+        .handle(|when, then| {
+            when.path("/apis/apps/v1");
+            then.json_body(apps_v1_discovery());
+        })
+        .handle(|when, then| {
+            when.path("/apis/apps/v1/replicasets");
+            then.json_body(json!({
+                "metadata": {},
+                "items": [{"metadata": {"namespace": TEST_NAMESPACE, "name": "test-rs"}}],
+            }));
+        })
+        .build();
+
+    let cache = OwnersCache::new(ApiSet::new(client));
+
+    test_pod.owner_references_mut().extend([otherref.clone(), rsref.clone()]);
+    let res = cache.compute_owner_chain(&test_pod).await.unwrap();
+
+    fake_apiserver.assert();
+    assert_eq!(res.controllers, vec![rsref.clone()]);
+    assert_eq!(res.others, vec![otherref.clone()]);
+    assert_eq!(res.all(), vec![rsref, otherref]);
+}
+
+#[rstest]
+#[traced_test]
+#[tokio::test]
+async fn test_compute_owner_chain_namespaced(mut test_pod: corev1::Pod) {
+    let rsref = metav1::OwnerReference {
+        api_version: "apps/v1".into(),
+        kind: "ReplicaSet".into(),
+        name: "test-rs".into(),
+        uid: "asdfasdf".into(),
+        ..Default::default()
+    };
+
+    // The owner lookup should go through the namespaced endpoint, not the cluster-wide one
+    let (mut fake_apiserver, client) = make_fake_apiserver();
+    fake_apiserver
+        .handle(|when, then| {
+            when.path("/apis/apps/v1");
+            then.json_body(apps_v1_discovery());
+        })
+        .handle(|when, then| {
+            when.path(format!("/apis/apps/v1/namespaces/{TEST_NAMESPACE}/replicasets"));
+            then.json_body(json!({
+                "metadata": {},
+                "items": [{"metadata": {"namespace": TEST_NAMESPACE, "name": "test-rs"}}],
+            }));
+        })
+        .build();
+
+    let cache = OwnersCache::new_namespaced(ApiSet::new(client), TEST_NAMESPACE);
+
+    test_pod.owner_references_mut().push(rsref.clone());
+    let res = cache.compute_owner_chain(&test_pod).await.unwrap();
+
+    fake_apiserver.assert();
+    assert_eq!(res.controllers, vec![rsref]);
+}
+
+#[rstest]
+#[case::lease_present(true)]
+#[case::lease_absent(false)]
+#[traced_test]
+#[tokio::test]
+async fn test_compute_owner_chain_lease(mut test_pod: corev1::Pod, #[case] lease_present: bool) {
+    let rsref = metav1::OwnerReference {
+        api_version: "apps/v1".into(),
+        kind: "ReplicaSet".into(),
+        name: "test-rs".into(),
+        uid: "asdfasdf".into(),
+        ..Default::default()
+    };
+    let leaseref = metav1::OwnerReference {
+        api_version: "coordination.k8s.io/v1".into(),
+        kind: "Lease".into(),
+        name: "test-lease".into(),
+        uid: "qwerqwer".into(),
+        ..Default::default()
+    };
+    let deplref = metav1::OwnerReference {
+        api_version: "apps/v1".into(),
+        kind: "Deployment".into(),
+        name: "test-depl".into(),
+        uid: "yuioyoiuy".into(),
+        ..Default::default()
+    };
+
+    // The pod is owned by a replicaset, which is owned by a (leader-election) lease, which is in
+    // turn owned by a deployment
+    let (mut fake_apiserver, client) = make_fake_apiserver();
+    let (rs_owner, lease_owner) = (leaseref.clone(), deplref.clone());
+    fake_apiserver
+        .handle(|when, then| {
+            when.path("/apis/apps/v1");
+            then.json_body(apps_v1_discovery());
+        })
+        .handle(|when, then| {
+            when.path("/apis/coordination.k8s.io/v1");
+            then.json_body(coordination_v1_discovery());
+        })
+        .handle(move |when, then| {
+            when.path("/apis/apps/v1/replicasets");
+            then.json_body(json!({
+                "metadata": {},
+                "items": [{"metadata": {"namespace": TEST_NAMESPACE, "name": "test-rs", "ownerReferences": [rs_owner]}}],
+            }));
+        })
+        .handle(move |when, then| {
+            when.path("/apis/coordination.k8s.io/v1/leases");
+            let items = if lease_present {
+                vec![json!({"metadata": {"namespace": TEST_NAMESPACE, "name": "test-lease", "ownerReferences": [lease_owner]}})]
+            } else {
+                vec![]
+            };
+            then.json_body(json!({"metadata": {}, "items": items}));
+        })
+        .handle(|when, then| {
+            when.path("/apis/apps/v1/deployments");
+            then.json_body(json!({
+                "metadata": {},
+                "items": [{"metadata": {"namespace": TEST_NAMESPACE, "name": "test-depl"}}],
+            }));
+        })
+        .build();
+
+    let cache = OwnersCache::new(ApiSet::new(client));
+    test_pod.owner_references_mut().push(rsref.clone());
+    let res = cache.compute_owner_chain(&test_pod).await.unwrap();
+
+    // If the lease is gone, the chain stops there instead of failing
+    let expected = if lease_present { vec![rsref, leaseref, deplref] } else { vec![rsref, leaseref] };
+    assert_eq!(res.controllers, expected);
+    fake_apiserver.assert_hits(4, if lease_present { 1 } else { 0 });
+}
+
+#[rstest]
+#[traced_test]
+#[tokio::test]
+async fn test_compute_owner_chain_owner_absent(mut test_pod: corev1::Pod) {
+    let rsref = metav1::OwnerReference {
+        api_version: "apps/v1".into(),
+        kind: "ReplicaSet".into(),
+        name: "test-rs".into(),
+        uid: "asdfasdf".into(),
+        ..Default::default()
+    };
+
+    // Other kinds of owners still have to exist
+    let (mut fake_apiserver, client) = make_fake_apiserver();
+    fake_apiserver
+        .handle(|when, then| {
+            when.path("/apis/apps/v1");
+            then.json_body(apps_v1_discovery());
+        })
+        .handle(|when, then| {
+            when.path("/apis/apps/v1/replicasets");
+            then.json_body(json!({"metadata": {}, "items": []}));
+        })
+        .build();
+
+    let cache = OwnersCache::new(ApiSet::new(client));
+    test_pod.owner_references_mut().push(rsref);
+    assert!(cache.compute_owner_chain(&test_pod).await.is_err());
+}
+
+#[rstest]
+#[traced_test]
+#[tokio::test]
+async fn test_compute_owner_chain_list_error(mut test_pod: corev1::Pod) {
+    let rsref = metav1::OwnerReference {
+        api_version: "apps/v1".into(),
+        kind: "ReplicaSet".into(),
+        name: "test-rs".into(),
+        uid: "asdfasdf".into(),
+        ..Default::default()
+    };
+
+    let (mut fake_apiserver, client) = make_fake_apiserver();
+    fake_apiserver
+        .handle(|when, then| {
+            when.path("/apis/apps/v1");
+            then.json_body(apps_v1_discovery());
+        })
+        .handle(|when, then| {
+            when.path("/apis/apps/v1/replicasets");
+            then.status(403).json_body(json!({
+                "kind": "Status",
+                "apiVersion": "v1",
+                "metadata": {},
+                "status": "Failure",
+                "message": "replicasets.apps is forbidden",
+                "reason": "Forbidden",
+                "code": 403,
+            }));
+        })
+        .build();
+
+    let cache = OwnersCache::new(ApiSet::new(client));
+    test_pod.owner_references_mut().push(rsref);
+    let err = cache.compute_owner_chain(&test_pod).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<SimKubeError>(), Some(SimKubeError::Api(_))));
+}
+
+#[rstest]
+#[traced_test]
+#[tokio::test]
+async fn test_compute_owner_chain_ttl_expired(mut test_pod: corev1::Pod) {
+    let rsref = metav1::OwnerReference {
+        api_version: "apps/v1".into(),
+        kind: "ReplicaSet".into(),
+        name: "test-rs".into(),
+        uid: "asdfasdf".into(),
+        ..Default::default()
+    };
+
+    let (mut fake_apiserver, client) = make_fake_apiserver();
+    fake_apiserver
+        .handle(|when, then| {
+            when.path("/apis/apps/v1");
+            then.json_body(apps_v1_discovery());
+        })
+        .handle(|when, then| {
+            when.path("/apis/apps/v1/replicasets");
+            then.json_body(json!({
+                "metadata": {},
+                "items": [{"metadata": {"namespace": TEST_NAMESPACE, "name": "test-rs"}}],
+            }));
+        })
+        .build();
+
+    let mut clock = MockUtcClock::new(0);
+    let ttl_cache = TtlOwnerChainCache::new_with_clock(10, Box::new(clock.clone()));
+    let cache = OwnersCache::new(ApiSet::new(client)).with_cache(Box::new(ttl_cache));
+    test_pod.owner_references_mut().push(rsref.clone());
+    let ns_name = test_pod.namespaced_name();
+
+    // Before the ttl is up, the chain comes from the cache
+    cache.compute_owner_chain(&test_pod).await.unwrap();
+    clock.advance(5);
+    assert!(cache.lookup(&ns_name).is_some());
+    cache.compute_owner_chain(&test_pod).await.unwrap();
+    fake_apiserver.assert_hits(1, 1);
+
+    // Afterwards, it's gone from the cache and has to be looked up again
+    clock.advance(5);
+    assert!(cache.lookup(&ns_name).is_none());
+    let res = cache.compute_owner_chain(&test_pod).await.unwrap();
+    fake_apiserver.assert_hits(1, 2);
+    assert_eq!(res.controllers, vec![rsref]);
+    assert!(cache.lookup(&ns_name).is_some());
+}
+
+#[rstest]
+#[traced_test]
+#[tokio::test]
+async fn test_compute_owner_chains_concurrency(test_pod: corev1::Pod) {
+    const LOOKUP_DELAY: Duration = Duration::from_millis(200);
+
+    let (mut fake_apiserver, client) = make_fake_apiserver();
+    fake_apiserver
+        .handle(|when, then| {
+            when.path("/apis/apps/v1");
+            then.json_body(apps_v1_discovery());
+        })
+        .handle(|when, then| {
+            when.path("/apis/apps/v1/replicasets");
+            then.delay(LOOKUP_DELAY).json_body(json!({
+                "metadata": {},
+                "items": [{"metadata": {"namespace": TEST_NAMESPACE, "name": "the-rs"}}],
+            }));
+        })
+        .build();
+
+    let rsrefs: Vec<_> = (0..6)
+        .map(|i| metav1::OwnerReference {
+            api_version: "apps/v1".into(),
+            kind: "ReplicaSet".into(),
+            name: format!("rs-{i}"),
+            ..Default::default()
+        })
+        .collect();
+    let pods: Vec<_> = rsrefs
+        .iter()
+        .enumerate()
+        .map(|(i, rsref)| {
+            let mut pod = test_pod.clone();
+            pod.metadata.name = Some(format!("pod-{i}"));
+            pod.owner_references_mut().push(rsref.clone());
+            pod
+        })
+        .collect();
+
+    let cache = OwnersCache::new(ApiSet::new(client));
+    let start = Instant::now();
+    let res = cache.compute_owner_chains(&pods, 2).await;
+    let elapsed = start.elapsed();
+
+    for (chain, rsref) in res.into_iter().zip(rsrefs) {
+        assert_eq!(chain.unwrap().controllers, vec![rsref]);
+    }
+    for pod in &pods {
+        assert!(cache.lookup(&pod.namespaced_name()).is_some());
+    }
+    fake_apiserver.assert_hits(1, 6);
+
+    // Six lookups two at a time takes three rounds; with no limit it'd be one round, and doing them
+    // one at a time would take six
+    assert!(elapsed >= 3 * LOOKUP_DELAY, "{elapsed:?}");
+    assert!(elapsed < 6 * LOOKUP_DELAY, "{elapsed:?}");
+}
+
+#[rstest]
+fn test_ttl_cache_sweeps_expired_entries() {
+    let mut clock = MockUtcClock::new(0);
+    let mut cache = TtlOwnerChainCache::new_with_clock(10, Box::new(clock.clone()));
+    cache.insert("ns/pod-a".into(), OwnerChain::default());
+    clock.advance(5);
+    cache.insert("ns/pod-b".into(), OwnerChain::default());
+    assert_eq!(cache.len(), 2);
+
+    // pod-a has expired by the time we insert pod-c, so it gets swept out even though nobody asked
+    // for it again
+    clock.advance(7);
+    cache.insert("ns/pod-c".into(), OwnerChain::default());
+    assert_eq!(cache.len(), 2);
+    assert!(cache.get("ns/pod-a").is_none());
+    assert!(cache.get("ns/pod-b").is_some());
 }
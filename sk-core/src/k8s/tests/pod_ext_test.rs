@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+
+use super::*;
+
+#[rstest]
+fn test_container_env(test_pod_with_env: corev1::Pod) {
+    let env = test_pod_with_env.container_env(&Regex::new("(?i)password").unwrap()).unwrap();
+
+    assert_eq!(env["init"], BTreeMap::from([("MODE".into(), "setup".into())]));
+    assert_eq!(
+        env["app"],
+        BTreeMap::from([
+            ("LOG_LEVEL".into(), "debug".into()),
+            ("DB_PASSWORD".into(), MASKED_ENV_VALUE.into()),
+            ("API_CREDS".into(), MASKED_ENV_VALUE.into()),
+            ("CONFIG_PATH".into(), "<configMap cfg/path>".into()),
+            ("POD_NAME".into(), "<field metadata.name>".into()),
+        ])
+    );
+}
+
+#[rstest]
+fn test_container_env_no_containers(test_pod: corev1::Pod) {
+    assert!(test_pod.container_env(&Regex::new("(?i)password").unwrap()).unwrap().is_empty());
+}
+
+#[rstest]
+fn test_scheduling(test_pod_with_affinity: corev1::Pod) {
+    let scheduling = test_pod_with_affinity.scheduling().unwrap();
+
+    let anti_affinity = scheduling.affinity.unwrap().pod_anti_affinity.unwrap();
+    let required = anti_affinity.required_during_scheduling_ignored_during_execution.unwrap();
+    assert_eq!(required.len(), 1);
+    assert_eq!(required[0].topology_key, "kubernetes.io/hostname");
+    assert_eq!(required[0].label_selector.as_ref().unwrap().match_labels, klabel!("foo" => "bar"));
+    let preferred = anti_affinity.preferred_during_scheduling_ignored_during_execution.unwrap();
+    assert_eq!(preferred[0].weight, 50);
+    assert_eq!(preferred[0].pod_affinity_term.topology_key, "topology.kubernetes.io/zone");
+
+    let tscs = scheduling.topology_spread_constraints.unwrap();
+    assert_eq!(tscs.len(), 1);
+    assert_eq!(tscs[0].max_skew, 1);
+    assert_eq!(tscs[0].when_unsatisfiable, "DoNotSchedule");
+}
+
+#[rstest]
+#[case::missing(None, None)]
+#[case::empty(Some(corev1::Affinity::default()), Some(vec![]))]
+fn test_scheduling_none(
+    mut test_pod: corev1::Pod,
+    #[case] affinity: Option<corev1::Affinity>,
+    #[case] tscs: Option<Vec<corev1::TopologySpreadConstraint>>,
+) {
+    let spec = test_pod.spec.as_mut().unwrap();
+    spec.affinity = affinity;
+    spec.topology_spread_constraints = tscs;
+    assert_eq!(test_pod.scheduling().unwrap(), PodScheduling::default());
+}
+
+#[rstest]
+fn test_probes(test_pod_with_probes: corev1::Pod) {
+    let probes = test_pod_with_probes.probes().unwrap();
+
+    assert_eq!(
+        probes,
+        PodProbes::from([(
+            "app".into(),
+            ContainerProbes {
+                readiness: Some(ProbeTiming {
+                    initial_delay_seconds: Some(15),
+                    period_seconds: Some(5),
+                    failure_threshold: Some(3),
+                    ..Default::default()
+                }),
+                liveness: Some(ProbeTiming { period_seconds: Some(10), ..Default::default() }),
+                startup: None,
+            }
+        )])
+    );
+}
+
+#[rstest]
+fn test_probes_none(test_pod_with_env: corev1::Pod) {
+    assert!(test_pod_with_env.probes().unwrap().is_empty());
+}
@@ -1,5 +1,7 @@
 use std::cmp::Ordering;
 
+use clockabilly::DateTime;
+
 use super::*;
 
 const START_TS: i64 = 1234;
@@ -10,6 +12,14 @@ fn test_pod_lifecycle_data_for_empty(test_pod: corev1::Pod) {
     assert_eq!(res, PodLifecycleData::Empty);
 }
 
+#[rstest]
+fn test_pod_lifecycle_data_for_pending(mut test_pod: corev1::Pod) {
+    test_pod.metadata.creation_timestamp = Some(metav1::Time(DateTime::from_timestamp(START_TS, 0).unwrap()));
+
+    let res = PodLifecycleData::new_for(&test_pod).unwrap();
+    assert_eq!(res, PodLifecycleData::Pending(START_TS));
+}
+
 #[rstest]
 #[case::with_init_container(true)]
 #[case::without_init_container(false)]
@@ -48,68 +58,121 @@ fn test_pod_lifecycle_data_for_with_end_times(mut test_pod: corev1::Pod) {
     add_finished_container(&mut test_pod, tmid, end_ts);
 
     let res = PodLifecycleData::new_for(&test_pod).unwrap();
-    assert_eq!(res, PodLifecycleData::Finished(START_TS, end_ts));
+    assert_eq!(res, PodLifecycleData::Finished(START_TS, end_ts, None));
+}
+
+#[rstest]
+fn test_pod_lifecycle_data_for_termination_reason(mut test_pod: corev1::Pod) {
+    let end_ts = START_TS + 10;
+
+    add_terminated_container(&mut test_pod, START_TS, end_ts, "Completed");
+    add_terminated_container(&mut test_pod, START_TS, START_TS + 5, "OOMKilled");
+    add_terminated_container(&mut test_pod, START_TS, START_TS + 7, "Error");
+
+    let res = PodLifecycleData::new_for(&test_pod).unwrap();
+    assert_eq!(res, PodLifecycleData::Finished(START_TS, end_ts, Some("OOMKilled".into())));
+    assert_eq!(res.termination_reason(), Some("OOMKilled"));
+}
+
+#[rstest]
+fn test_pod_lifecycle_data_deserialize_without_reason() {
+    let res: PodLifecycleData = serde_json::from_str(r#"{"Finished": [1, 2]}"#).unwrap();
+    assert_eq!(res, PodLifecycleData::Finished(1, 2, None));
 }
 
 #[test]
 fn test_partial_eq() {
     assert_eq!(PodLifecycleData::Empty, None);
     assert_eq!(PodLifecycleData::Empty, Some(&PodLifecycleData::Empty));
+    assert_eq!(PodLifecycleData::Pending(1), Some(&PodLifecycleData::Pending(1)));
     assert_eq!(PodLifecycleData::Running(1), Some(&PodLifecycleData::Running(1)));
-    assert_eq!(PodLifecycleData::Finished(1, 2), Some(&PodLifecycleData::Finished(1, 2)));
+    assert_eq!(PodLifecycleData::Finished(1, 2, None), Some(&PodLifecycleData::Finished(1, 2, None)));
 
+    assert_ne!(PodLifecycleData::Empty, Some(&PodLifecycleData::Pending(1)));
     assert_ne!(PodLifecycleData::Empty, Some(&PodLifecycleData::Running(1)));
-    assert_ne!(PodLifecycleData::Empty, Some(&PodLifecycleData::Finished(1, 2)));
+    assert_ne!(PodLifecycleData::Empty, Some(&PodLifecycleData::Finished(1, 2, None)));
+    assert_ne!(PodLifecycleData::Pending(1), None);
+    assert_ne!(PodLifecycleData::Pending(1), Some(&PodLifecycleData::Pending(2)));
+    assert_ne!(PodLifecycleData::Pending(1), Some(&PodLifecycleData::Running(1)));
     assert_ne!(PodLifecycleData::Running(1), None);
     assert_ne!(PodLifecycleData::Running(1), Some(&PodLifecycleData::Empty));
     assert_ne!(PodLifecycleData::Running(1), Some(&PodLifecycleData::Running(2)));
-    assert_ne!(PodLifecycleData::Running(1), Some(&PodLifecycleData::Finished(1, 2)));
-    assert_ne!(PodLifecycleData::Finished(1, 2), None);
-    assert_ne!(PodLifecycleData::Finished(1, 2), Some(&PodLifecycleData::Empty));
-    assert_ne!(PodLifecycleData::Finished(1, 2), Some(&PodLifecycleData::Running(2)));
-    assert_ne!(PodLifecycleData::Finished(1, 2), Some(&PodLifecycleData::Finished(1, 3)));
+    assert_ne!(PodLifecycleData::Running(1), Some(&PodLifecycleData::Finished(1, 2, None)));
+    assert_ne!(PodLifecycleData::Finished(1, 2, None), None);
+    assert_ne!(PodLifecycleData::Finished(1, 2, None), Some(&PodLifecycleData::Empty));
+    assert_ne!(PodLifecycleData::Finished(1, 2, None), Some(&PodLifecycleData::Running(2)));
+    assert_ne!(PodLifecycleData::Finished(1, 2, None), Some(&PodLifecycleData::Finished(1, 3, None)));
 }
 
 #[test]
+// The negated comparisons below are intentional; we want to check that these are incomparable
+#[allow(clippy::neg_cmp_op_on_partial_ord)]
 fn test_partial_ord() {
     for cmp in [
         PodLifecycleData::Empty.partial_cmp(&None),
         PodLifecycleData::Empty.partial_cmp(&Some(&PodLifecycleData::Empty)),
+        PodLifecycleData::Pending(1).partial_cmp(&Some(&PodLifecycleData::Pending(1))),
         PodLifecycleData::Running(1).partial_cmp(&Some(&PodLifecycleData::Running(1))),
-        PodLifecycleData::Finished(1, 2).partial_cmp(&Some(&PodLifecycleData::Finished(1, 2))),
+        PodLifecycleData::Finished(1, 2, None).partial_cmp(&Some(&PodLifecycleData::Finished(1, 2, None))),
     ] {
         assert_eq!(cmp, Some(Ordering::Equal));
     }
 
     for cmp in [
+        PodLifecycleData::Empty.partial_cmp(&Some(&PodLifecycleData::Pending(1))),
         PodLifecycleData::Empty.partial_cmp(&Some(&PodLifecycleData::Running(1))),
-        PodLifecycleData::Empty.partial_cmp(&Some(&PodLifecycleData::Finished(1, 2))),
+        PodLifecycleData::Empty.partial_cmp(&Some(&PodLifecycleData::Finished(1, 2, None))),
+        PodLifecycleData::Pending(1).partial_cmp(&None),
+        PodLifecycleData::Pending(1).partial_cmp(&Some(&PodLifecycleData::Pending(2))),
+        PodLifecycleData::Pending(1).partial_cmp(&Some(&PodLifecycleData::Running(1))),
         PodLifecycleData::Running(1).partial_cmp(&None),
         PodLifecycleData::Running(1).partial_cmp(&Some(&PodLifecycleData::Empty)),
         PodLifecycleData::Running(1).partial_cmp(&Some(&PodLifecycleData::Running(2))),
-        PodLifecycleData::Running(1).partial_cmp(&Some(&PodLifecycleData::Finished(1, 2))),
-        PodLifecycleData::Finished(1, 2).partial_cmp(&None),
-        PodLifecycleData::Finished(1, 2).partial_cmp(&Some(&PodLifecycleData::Empty)),
-        PodLifecycleData::Finished(1, 2).partial_cmp(&Some(&PodLifecycleData::Running(2))),
-        PodLifecycleData::Finished(1, 2).partial_cmp(&Some(&PodLifecycleData::Finished(1, 3))),
+        PodLifecycleData::Running(1).partial_cmp(&Some(&PodLifecycleData::Finished(1, 2, None))),
+        PodLifecycleData::Finished(1, 2, None).partial_cmp(&None),
+        PodLifecycleData::Finished(1, 2, None).partial_cmp(&Some(&PodLifecycleData::Empty)),
+        PodLifecycleData::Finished(1, 2, None).partial_cmp(&Some(&PodLifecycleData::Running(2))),
+        PodLifecycleData::Finished(1, 2, None).partial_cmp(&Some(&PodLifecycleData::Finished(1, 3, None))),
     ] {
         assert_ne!(cmp, Some(Ordering::Equal));
     }
 
     assert!(PodLifecycleData::Empty < Some(&PodLifecycleData::Running(1)));
-    assert!(PodLifecycleData::Empty < Some(&PodLifecycleData::Finished(1, 2)));
-    assert!(PodLifecycleData::Running(1) < Some(&PodLifecycleData::Finished(1, 2)));
+    assert!(PodLifecycleData::Empty < Some(&PodLifecycleData::Finished(1, 2, None)));
+    assert!(PodLifecycleData::Running(1) < Some(&PodLifecycleData::Finished(1, 2, None)));
+    assert!(PodLifecycleData::Empty < Some(&PodLifecycleData::Pending(1)));
+    assert!(PodLifecycleData::Pending(1) < Some(&PodLifecycleData::Running(5)));
+    assert!(PodLifecycleData::Pending(1) < Some(&PodLifecycleData::Finished(5, 10, None)));
 
     assert!(PodLifecycleData::Running(1) > None);
+    assert!(PodLifecycleData::Pending(1) > None);
     assert!(PodLifecycleData::Running(1) > Some(&PodLifecycleData::Empty));
-    assert!(PodLifecycleData::Finished(1, 2) > None);
-    assert!(PodLifecycleData::Finished(1, 2) > Some(&PodLifecycleData::Empty));
-    assert!(PodLifecycleData::Finished(1, 2) > Some(&PodLifecycleData::Running(1)));
-
-    assert!(!(PodLifecycleData::Finished(1, 2) > Some(&PodLifecycleData::Running(0))));
-    assert!(!(PodLifecycleData::Finished(1, 2) < Some(&PodLifecycleData::Running(0))));
-    assert!(!(PodLifecycleData::Finished(1, 2) > Some(&PodLifecycleData::Finished(1, 3))));
-    assert!(!(PodLifecycleData::Finished(1, 2) < Some(&PodLifecycleData::Finished(1, 3))));
+    assert!(PodLifecycleData::Running(5) > Some(&PodLifecycleData::Pending(1)));
+    assert!(PodLifecycleData::Finished(1, 2, None) > None);
+    assert!(PodLifecycleData::Finished(1, 2, None) > Some(&PodLifecycleData::Empty));
+    assert!(PodLifecycleData::Finished(1, 2, None) > Some(&PodLifecycleData::Running(1)));
+
+    assert!(!(PodLifecycleData::Finished(1, 2, None) > Some(&PodLifecycleData::Running(0))));
+    assert!(!(PodLifecycleData::Finished(1, 2, None) < Some(&PodLifecycleData::Running(0))));
+    assert!(!(PodLifecycleData::Finished(1, 2, None) > Some(&PodLifecycleData::Finished(1, 3, None))));
+    assert!(!(PodLifecycleData::Finished(1, 2, None) < Some(&PodLifecycleData::Finished(1, 3, None))));
+
+    let oom = PodLifecycleData::Finished(1, 2, Some("OOMKilled".into()));
+    let err = PodLifecycleData::Finished(1, 2, Some("Error".into()));
+    assert!(oom > Some(&PodLifecycleData::Finished(1, 2, None)));
+    assert!(!(oom > Some(&err)));
+    assert!(!(oom < Some(&err)));
     assert!(!(PodLifecycleData::Running(1) < Some(&PodLifecycleData::Running(2))));
     assert!(!(PodLifecycleData::Running(1) > Some(&PodLifecycleData::Running(2))));
+    assert!(!(PodLifecycleData::Pending(1) < Some(&PodLifecycleData::Pending(2))));
+    assert!(!(PodLifecycleData::Pending(1) > Some(&PodLifecycleData::Pending(2))));
+}
+
+#[rstest]
+#[case::pending(PodLifecycleData::Pending(1), true)]
+#[case::pending_too_late(PodLifecycleData::Pending(20), false)]
+#[case::running(PodLifecycleData::Running(1), true)]
+#[case::empty(PodLifecycleData::Empty, false)]
+fn test_overlaps(#[case] data: PodLifecycleData, #[case] expected: bool) {
+    assert_eq!(data.overlaps(0, 10), expected);
 }
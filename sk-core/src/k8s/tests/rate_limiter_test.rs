@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+use clockabilly::mock::MockUtcClock;
+
+use super::*;
+
+#[rstest]
+fn test_rate_limiter_burst_exhausted() {
+    let mut clock = MockUtcClock::new(0);
+    let mut limiter = RateLimiter::new_with_clock(0.5, 2, Box::new(clock.clone()));
+
+    assert_eq!(limiter.try_acquire(), None);
+    assert_eq!(limiter.try_acquire(), None);
+
+    // The burst is used up, so the next request has to wait for a token to refill
+    assert_eq!(limiter.try_acquire(), Some(Duration::from_secs(2)));
+
+    clock.advance(1);
+    assert_eq!(limiter.try_acquire(), Some(Duration::from_secs(1)));
+
+    clock.advance(1);
+    assert_eq!(limiter.try_acquire(), None);
+    assert!(limiter.try_acquire().is_some());
+}
+
+#[rstest]
+fn test_rate_limiter_refill_capped_at_burst() {
+    let mut clock = MockUtcClock::new(0);
+    let mut limiter = RateLimiter::new_with_clock(10.0, 1, Box::new(clock.clone()));
+
+    assert_eq!(limiter.try_acquire(), None);
+    clock.advance(100);
+    assert_eq!(limiter.try_acquire(), None);
+    assert!(limiter.try_acquire().is_some());
+}
@@ -54,10 +54,7 @@ fn build_label_sel(key: &str, op: &str, value: Option<&str>) -> metav1::LabelSel
         match_expressions: Some(vec![metav1::LabelSelectorRequirement {
             key: key.into(),
             operator: op.into(),
-            values: match value {
-                Some(s) => Some(vec![s.into()]),
-                _ => None,
-            },
+            values: value.map(|s| vec![s.into()]),
         }]),
         ..Default::default()
     }
@@ -128,3 +125,31 @@ fn test_label_match(test_pod: corev1::Pod, #[case] label_key: String) {
     let res = test_pod.matches(&sel).unwrap();
     assert_eq!(res, &label_key == "foo");
 }
+
+#[rstest]
+#[case::named(Some("the-pod"), Some("abcd"), "test-namespace/the-pod")]
+#[case::pending(None, Some("abcd"), "test-namespace/abcd")]
+#[case::no_uid(None, None, "test-namespace/the-pod-")]
+fn test_namespaced_name(
+    mut test_pod: corev1::Pod,
+    #[case] name: Option<&str>,
+    #[case] uid: Option<&str>,
+    #[case] expected: &str,
+) {
+    test_pod.metadata.name = name.map(|n| n.into());
+    test_pod.metadata.generate_name = Some("the-pod-".into());
+    test_pod.metadata.uid = uid.map(|u| u.into());
+    assert_eq!(test_pod.namespaced_name(), expected);
+}
+
+#[rstest]
+#[case::ordinal("web-3", Some(3))]
+#[case::zero("web-0", Some(0))]
+#[case::dashes_in_name("web-frontend-12", None)]
+#[case::no_ordinal("web-", None)]
+#[case::other_sts("webapp-1", None)]
+#[case::not_a_number("web-abc", None)]
+#[case::sign("web-+1", None)]
+fn test_statefulset_pod_ordinal(#[case] pod_name: &str, #[case] expected: Option<u32>) {
+    assert_eq!(statefulset_pod_ordinal(pod_name, "web"), expected);
+}
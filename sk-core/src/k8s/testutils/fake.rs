@@ -18,6 +18,12 @@ fn print_req(req: &HttpMockRequest) -> bool {
     true
 }
 
+impl Default for MockServerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl MockServerBuilder {
     pub fn new() -> MockServerBuilder {
         MockServerBuilder {
@@ -29,11 +35,16 @@ impl MockServerBuilder {
 
     pub fn assert(&self) {
         for id in &self.mock_ids {
-            print!("checking assertions for mock {id}\n");
+            println!("checking assertions for mock {id}");
             Mock::new(*id, &self.server).assert()
         }
     }
 
+    // Check that the idx'th handler was hit exactly the given number of times
+    pub fn assert_hits(&self, idx: usize, hits: usize) {
+        Mock::new(self.mock_ids[idx], &self.server).assert_hits(hits)
+    }
+
     pub fn handle<F: Fn(When, Then) + 'static>(&mut self, f: F) -> &mut Self {
         self.handlers.push(Box::new(move |w, t| {
             let w = w.matches(print_req);
@@ -219,3 +230,21 @@ pub fn apps_v1_discovery() -> serde_json::Value {
         ],
     })
 }
+
+pub fn coordination_v1_discovery() -> serde_json::Value {
+    json!({
+        "kind":"APIResourceList",
+        "apiVersion":"v1",
+        "groupVersion":"coordination.k8s.io/v1",
+        "resources":[
+            {
+                "name":"leases",
+                "singularName":"lease",
+                "namespaced":true,
+                "kind":"Lease",
+                "verbs":["create","delete","deletecollection","get","list","patch","update","watch"],
+                "storageVersionHash":"gqkMMb/YqFM=",
+            },
+        ],
+    })
+}
@@ -4,14 +4,112 @@ use kube::api::{
 };
 use kube::discovery::ApiResource;
 use rstest::*;
+use serde_json::json;
 
 use crate::prelude::*;
 
 #[fixture]
 pub fn test_deployment(#[default(TEST_DEPLOYMENT)] name: &str) -> DynamicObject {
-    DynamicObject::new(
-        &name,
-        &ApiResource::from_gvk(&GroupVersionKind::gvk("core".into(), "v1".into(), "deployment".into())),
-    )
-    .within(TEST_NAMESPACE)
+    DynamicObject::new(name, &ApiResource::from_gvk(&GroupVersionKind::gvk("core", "v1", "deployment")))
+        .within(TEST_NAMESPACE)
+}
+
+#[fixture]
+pub fn test_hpa_v1() -> DynamicObject {
+    serde_json::from_value(json!({
+        "apiVersion": "autoscaling/v1",
+        "kind": "HorizontalPodAutoscaler",
+        "metadata": {"namespace": TEST_NAMESPACE, "name": TEST_HPA},
+        "spec": {
+            "scaleTargetRef": {"apiVersion": "apps/v1", "kind": "Deployment", "name": TEST_DEPLOYMENT},
+            "minReplicas": 2,
+            "maxReplicas": 10,
+            "targetCPUUtilizationPercentage": 50,
+        },
+        "status": {"currentReplicas": 3, "desiredReplicas": 5, "currentCPUUtilizationPercentage": 80},
+    }))
+    .unwrap()
+}
+
+#[fixture]
+pub fn test_hpa_v2() -> DynamicObject {
+    serde_json::from_value(json!({
+        "apiVersion": "autoscaling/v2",
+        "kind": "HorizontalPodAutoscaler",
+        "metadata": {"namespace": TEST_NAMESPACE, "name": TEST_HPA},
+        "spec": {
+            "scaleTargetRef": {"apiVersion": "apps/v1", "kind": "Deployment", "name": TEST_DEPLOYMENT},
+            "minReplicas": 2,
+            "maxReplicas": 10,
+            "metrics": [{
+                "type": "Resource",
+                "resource": {"name": "memory", "target": {"type": "AverageValue", "averageValue": "500Mi"}},
+            }],
+        },
+        "status": {
+            "currentReplicas": 3,
+            "desiredReplicas": 4,
+            "currentMetrics": [{
+                "type": "Resource",
+                "resource": {"name": "memory", "current": {"averageValue": "650Mi"}},
+            }],
+        },
+    }))
+    .unwrap()
+}
+
+#[fixture]
+pub fn test_completed_job() -> DynamicObject {
+    serde_json::from_value(json!({
+        "apiVersion": "batch/v1",
+        "kind": "Job",
+        "metadata": {"namespace": TEST_NAMESPACE, "name": TEST_JOB},
+        "spec": {"completions": 6, "parallelism": 3, "template": {"spec": {"containers": []}}},
+        "status": {
+            "succeeded": 6,
+            "failed": 1,
+            "startTime": "2024-01-01T00:00:00Z",
+            "completionTime": "2024-01-01T00:10:00Z",
+        },
+    }))
+    .unwrap()
+}
+
+#[fixture]
+pub fn test_node(#[default(true)] ready: bool) -> DynamicObject {
+    serde_json::from_value(json!({
+        "apiVersion": "v1",
+        "kind": "Node",
+        "metadata": {"name": TEST_NODE, "labels": {"topology.kubernetes.io/zone": "us-west-2a"}},
+        "spec": {"taints": [{"key": "dedicated", "value": "gpu", "effect": "NoSchedule"}]},
+        "status": {
+            "allocatable": {"cpu": "3920m", "memory": "14Gi", "pods": "110"},
+            "capacity": {"cpu": "4", "memory": "16Gi", "pods": "110"},
+            "conditions": [
+                {"type": "MemoryPressure", "status": "False", "lastHeartbeatTime": "2024-01-01T00:00:00Z"},
+                {
+                    "type": "Ready",
+                    "status": if ready { "True" } else { "False" },
+                    "lastHeartbeatTime": "2024-01-01T00:00:00Z",
+                },
+            ],
+        },
+    }))
+    .unwrap()
+}
+
+#[fixture]
+pub fn test_cronjob() -> DynamicObject {
+    serde_json::from_value(json!({
+        "apiVersion": "batch/v1",
+        "kind": "CronJob",
+        "metadata": {"namespace": TEST_NAMESPACE, "name": TEST_CRONJOB},
+        "spec": {"schedule": "*/5 * * * *", "jobTemplate": {"spec": {"template": {"spec": {"containers": []}}}}},
+        "status": {
+            "active": [{"namespace": TEST_NAMESPACE, "name": "the-cronjob-28400000"}],
+            "lastScheduleTime": "2024-01-01T00:05:00Z",
+            "lastSuccessfulTime": "2024-01-01T00:01:00Z",
+        },
+    }))
+    .unwrap()
 }
@@ -21,6 +21,90 @@ pub fn test_pod(#[default("the-pod".into())] name: String) -> corev1::Pod {
     }
 }
 
+// A pod with a mix of plain, secret-sourced, and sensitive-looking environment variables
+#[fixture]
+pub fn test_pod_with_env(test_pod: corev1::Pod) -> corev1::Pod {
+    let mut pod = test_pod;
+    pod.spec = Some(
+        serde_json::from_value(serde_json::json!({
+            "initContainers": [{"name": "init", "env": [{"name": "MODE", "value": "setup"}]}],
+            "containers": [{
+                "name": "app",
+                "env": [
+                    {"name": "LOG_LEVEL", "value": "debug"},
+                    {"name": "DB_PASSWORD", "value": "hunter2"},
+                    {"name": "API_CREDS", "valueFrom": {"secretKeyRef": {"name": "creds", "key": "api"}}},
+                    {"name": "CONFIG_PATH", "valueFrom": {"configMapKeyRef": {"name": "cfg", "key": "path"}}},
+                    {"name": "POD_NAME", "valueFrom": {"fieldRef": {"fieldPath": "metadata.name"}}},
+                ],
+            }],
+        }))
+        .unwrap(),
+    );
+    pod
+}
+
+// A pod that doesn't want to share a node with any other pod from the same app, and spreads itself
+// out across zones
+#[fixture]
+pub fn test_pod_with_affinity(test_pod: corev1::Pod) -> corev1::Pod {
+    let mut pod = test_pod;
+    pod.spec = Some(
+        serde_json::from_value(serde_json::json!({
+            "containers": [{"name": "app"}],
+            "affinity": {
+                "podAntiAffinity": {
+                    "requiredDuringSchedulingIgnoredDuringExecution": [{
+                        "labelSelector": {"matchLabels": {"foo": "bar"}},
+                        "topologyKey": "kubernetes.io/hostname",
+                    }],
+                    "preferredDuringSchedulingIgnoredDuringExecution": [{
+                        "weight": 50,
+                        "podAffinityTerm": {
+                            "labelSelector": {"matchExpressions": [{"key": "tier", "operator": "In", "values": ["db"]}]},
+                            "topologyKey": "topology.kubernetes.io/zone",
+                        },
+                    }],
+                },
+            },
+            "topologySpreadConstraints": [{
+                "maxSkew": 1,
+                "topologyKey": "topology.kubernetes.io/zone",
+                "whenUnsatisfiable": "DoNotSchedule",
+                "labelSelector": {"matchLabels": {"foo": "bar"}},
+            }],
+        }))
+        .unwrap(),
+    );
+    pod
+}
+
+// A pod whose app container has to pass a readiness probe before the pod is ready, next to a
+// sidecar that doesn't have any probes at all
+#[fixture]
+pub fn test_pod_with_probes(test_pod: corev1::Pod) -> corev1::Pod {
+    let mut pod = test_pod;
+    pod.spec = Some(
+        serde_json::from_value(serde_json::json!({
+            "containers": [
+                {
+                    "name": "app",
+                    "readinessProbe": {
+                        "httpGet": {"path": "/healthz", "port": 8080},
+                        "initialDelaySeconds": 15,
+                        "periodSeconds": 5,
+                        "failureThreshold": 3,
+                    },
+                    "livenessProbe": {"tcpSocket": {"port": 8080}, "periodSeconds": 10},
+                },
+                {"name": "sidecar"},
+            ],
+        }))
+        .unwrap(),
+    );
+    pod
+}
+
 pub fn add_running_init_container(pod: &mut corev1::Pod, t: i64) {
     add_container_with_status(pod, build_container_state_running(t), true);
 }
@@ -37,6 +121,24 @@ pub fn add_finished_container(pod: &mut corev1::Pod, t1: i64, t2: i64) {
     add_container_with_status(pod, build_container_state_finished(t1, t2), false);
 }
 
+pub fn add_terminated_container(pod: &mut corev1::Pod, t1: i64, t2: i64, reason: &str) {
+    let mut state = build_container_state_finished(t1, t2);
+    if let Some(t) = state.as_mut().and_then(|s| s.terminated.as_mut()) {
+        t.reason = Some(reason.into());
+    }
+    add_container_with_status(pod, state, false);
+}
+
+pub fn add_ready_condition(pod: &mut corev1::Pod, t: i64) {
+    let status = pod.status.get_or_insert(Default::default());
+    status.conditions.get_or_insert(vec![]).push(corev1::PodCondition {
+        type_: "Ready".into(),
+        status: "True".into(),
+        last_transition_time: Some(metav1::Time(DateTime::from_timestamp(t, 0).unwrap())),
+        ..Default::default()
+    });
+}
+
 fn build_container_state_running(t: i64) -> Option<corev1::ContainerState> {
     Some(corev1::ContainerState {
         running: Some(corev1::ContainerStateRunning {
@@ -102,11 +102,32 @@ pub fn split_namespaced_name(name: &str) -> (String, String) {
     }
 }
 
+// Pods owned by a StatefulSet are named <statefulset>-<ordinal>; returns None if the pod name
+// doesn't look like that (e.g., the pod doesn't actually belong to this StatefulSet).
+pub fn statefulset_pod_ordinal(pod_name: &str, sts_name: &str) -> Option<u32> {
+    let suffix = pod_name.strip_prefix(sts_name)?.strip_prefix('-')?;
+    if suffix.is_empty() || !suffix.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    suffix.parse().ok()
+}
+
+pub fn is_statefulset(gvk: &GVK) -> bool {
+    gvk.group == "apps" && gvk.kind == "StatefulSet"
+}
+
 impl<T: Resource> KubeResourceExt for T {
+    // Objects that we see in the middle of being created might only have a generateName, which
+    // isn't unique; these are keyed on their UID instead (if they have one), until we see them
+    // again with their real name.
     fn namespaced_name(&self) -> String {
+        let name = match (&self.meta().name, &self.meta().uid) {
+            (None, Some(uid)) => uid.clone(),
+            _ => self.name_any(),
+        };
         match self.namespace() {
-            Some(ns) => format!("{}/{}", ns, self.name_any()),
-            None => self.name_any().clone(),
+            Some(ns) => format!("{ns}/{name}"),
+            None => name,
         }
     }
 
@@ -10,3 +10,17 @@ pub fn setup(env_filter: &str) {
         .compact()
         .init();
 }
+
+// For CLI commands whose actual output goes to stdout (e.g., `skctl snapshot --json`), so the
+// logs don't get mixed in with it
+pub fn setup_stderr(env_filter: &str) {
+    tracing_subscriber::fmt()
+        .with_file(true)
+        .with_line_number(true)
+        .with_span_events(FmtSpan::NEW)
+        .with_target(false)
+        .with_env_filter(env_filter)
+        .with_writer(std::io::stderr)
+        .compact()
+        .init();
+}
@@ -1,3 +1,4 @@
+use anyhow::bail;
 use clockabilly::{
     DateTime,
     Local,
@@ -15,3 +16,14 @@ pub fn duration_to_ts_from(start_ts: i64, tstr: &str) -> anyhow::Result<i64> {
     let local_time = DateTime::from_timestamp(start_ts, 0).unwrap().with_timezone(&Local);
     Ok(parse_datetime_at_date(local_time, tstr)?.timestamp())
 }
+
+// Durations like "5m" or "+1h" are turned into a number of seconds by computing them relative to
+// the epoch; only positive durations are allowed
+pub fn duration_to_secs(dstr: &str) -> anyhow::Result<i64> {
+    let dstr = if dstr.starts_with('+') { dstr.into() } else { format!("+{dstr}") };
+    let secs = duration_to_ts_from(0, &dstr)?;
+    if secs <= 0 {
+        bail!("duration must be positive");
+    }
+    Ok(secs)
+}
@@ -1,7 +1,16 @@
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{
+    Arc,
+    Mutex,
+};
 
 use kube::ResourceExt;
 use sk_api::v1::Simulation;
+use sk_core::k8s::driver_name;
+use tokio::sync::{
+    Semaphore,
+    SemaphorePermit,
+};
 
 use crate::Options;
 
@@ -14,13 +23,23 @@ pub struct SimulationContext {
     pub metaroot_name: String,
     pub driver_name: String,
     pub driver_svc: String,
+    pub driver_rbac_name: String,
     pub prometheus_name: String,
     pub prometheus_svc: String,
     pub webhook_name: String,
+
+    // Number of consecutive failed reconciles for each simulation, used to compute the backoff in
+    // error_policy; this is shared between all the per-simulation copies of the context
+    error_counts: Arc<Mutex<HashMap<String, u32>>>,
+
+    // Limits the number of reconciles that can run at once (if --max-concurrent-reconciles is set);
+    // also shared between all the copies of the context
+    reconcile_permits: Option<Arc<Semaphore>>,
 }
 
 impl SimulationContext {
     pub fn new(client: kube::Client, opts: Options) -> SimulationContext {
+        let reconcile_permits = opts.max_concurrent_reconciles.map(|n| Arc::new(Semaphore::new(n)));
         SimulationContext {
             client,
             opts,
@@ -29,9 +48,13 @@ impl SimulationContext {
             metaroot_name: String::new(),
             driver_name: String::new(),
             driver_svc: String::new(),
+            driver_rbac_name: String::new(),
             prometheus_name: String::new(),
             prometheus_svc: String::new(),
             webhook_name: String::new(),
+
+            error_counts: Default::default(),
+            reconcile_permits,
         }
     }
 
@@ -39,12 +62,33 @@ impl SimulationContext {
         let mut new = (*self).clone();
         new.name = sim.name_any();
         new.metaroot_name = format!("sk-{}-metaroot", new.name);
-        new.driver_name = format!("sk-{}-driver", new.name);
+        new.driver_name = driver_name(&new.name);
         new.driver_svc = format!("sk-{}-driver-svc", new.name);
+        new.driver_rbac_name = format!("sk-{}-driver", new.name);
         new.prometheus_name = format!("sk-{}-prom", new.name);
         new.prometheus_svc = format!("sk-{}-prom-svc", new.name);
         new.webhook_name = format!("sk-{}-mutatepods", new.name);
 
         new
     }
+
+    pub fn record_failure(&self, sim_name: &str) -> u32 {
+        let mut error_counts = self.error_counts.lock().unwrap();
+        let count = error_counts.entry(sim_name.into()).or_default();
+        *count += 1;
+        *count
+    }
+
+    pub fn reset_failures(&self, sim_name: &str) {
+        self.error_counts.lock().unwrap().remove(sim_name);
+    }
+
+    // Waits until there's room for another reconcile to run; the returned permit should be held
+    // for the duration of the reconcile.  If there's no limit configured, this returns immediately.
+    pub async fn acquire_reconcile_permit(&self) -> anyhow::Result<Option<SemaphorePermit<'_>>> {
+        match &self.reconcile_permits {
+            Some(permits) => Ok(Some(permits.acquire().await?)),
+            None => Ok(None),
+        }
+    }
 }
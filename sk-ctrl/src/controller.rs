@@ -11,14 +11,21 @@ use clockabilly::{
     Utc,
 };
 use either::Either;
+use json_patch::{
+    PatchOperation,
+    RemoveOperation,
+    TestOperation,
+};
 use k8s_openapi::api::admissionregistration::v1 as admissionv1;
 use k8s_openapi::api::batch::v1 as batchv1;
+use k8s_openapi::api::rbac::v1 as rbacv1;
 use kube::api::{
     ListParams,
     Patch,
 };
 use kube::runtime::controller::Action;
 use kube::ResourceExt;
+use rand::Rng;
 use serde_json::json;
 use sk_api::prometheus::*;
 use sk_api::v1::{
@@ -49,6 +56,7 @@ use crate::objects::*;
 
 pub const REQUEUE_DURATION: Duration = Duration::from_secs(RETRY_DELAY_SECONDS);
 pub const REQUEUE_ERROR_DURATION: Duration = Duration::from_secs(ERROR_RETRY_DELAY_SECONDS);
+pub const MAX_REQUEUE_ERROR_DURATION: Duration = Duration::from_secs(10 * 60);
 pub const JOB_STATUS_CONDITION_COMPLETE: &str = "Complete";
 pub const JOB_STATUS_CONDITION_FAILED: &str = "Failed";
 
@@ -114,6 +122,37 @@ pub async fn fetch_driver_state(
     Ok(DriverState::Left((state, start_time, end_time)))
 }
 
+// Everything here is owned by the metaroot, so it gets cleaned up at the end of the simulation
+pub async fn create_driver_rbac_if_not_present(
+    ctx: &SimulationContext,
+    sim: &Simulation,
+    metaroot: &SimulationRoot,
+    svc_account: &str,
+) -> EmptyResult {
+    let sa_api = kube::Api::<corev1::ServiceAccount>::namespaced(ctx.client.clone(), &sim.spec.driver.namespace);
+    if sa_api.get_opt(svc_account).await?.is_none() {
+        info!("creating driver service account {svc_account}");
+        let obj = build_driver_service_account(ctx, sim, metaroot, svc_account);
+        sa_api.create(&Default::default(), &obj).await?;
+    }
+
+    let role_api = kube::Api::<rbacv1::ClusterRole>::all(ctx.client.clone());
+    if role_api.get_opt(&ctx.driver_rbac_name).await?.is_none() {
+        info!("creating driver cluster role {}", ctx.driver_rbac_name);
+        let obj = build_driver_cluster_role(ctx, metaroot);
+        role_api.create(&Default::default(), &obj).await?;
+    }
+
+    let binding_api = kube::Api::<rbacv1::ClusterRoleBinding>::all(ctx.client.clone());
+    if binding_api.get_opt(&ctx.driver_rbac_name).await?.is_none() {
+        info!("creating driver cluster role binding {}", ctx.driver_rbac_name);
+        let obj = build_driver_cluster_role_binding(ctx, sim, metaroot, svc_account);
+        binding_api.create(&Default::default(), &obj).await?;
+    }
+
+    Ok(())
+}
+
 pub async fn setup_simulation(
     ctx: &SimulationContext,
     sim: &Simulation,
@@ -202,6 +241,10 @@ pub async fn setup_simulation(
         webhook_api.create(&Default::default(), &obj).await?;
     };
 
+    if let (true, Some(svc_account)) = (ctx.opts.create_driver_rbac, &sim.spec.driver_service_account) {
+        create_driver_rbac_if_not_present(ctx, sim, metaroot, svc_account).await?;
+    }
+
     // Create the actual driver
     let jobs_api = kube::Api::<batchv1::Job>::namespaced(ctx.client.clone(), &sim.spec.driver.namespace);
     if jobs_api.get_opt(&ctx.driver_name).await?.is_none() {
@@ -226,15 +269,97 @@ pub async fn cleanup_simulation(ctx: &SimulationContext, sim: &Simulation) {
     }
 }
 
-#[instrument(parent=None, skip_all, fields(simulation=sim.name_any()))]
+// Simulations get a finalizer so that we have a chance to clean up after them if they're deleted
+// while they're still running (if they've already finished, we cleaned up then).  The finalizer
+// name comes from the controller options; we only ever add or remove our own finalizer, so other
+// controllers (or anything else) can have their own finalizers on the same simulation.
+async fn add_finalizer(ctx: &SimulationContext, sim: &Simulation) -> EmptyResult {
+    if sim.finalizers().contains(&ctx.opts.finalizer) {
+        return Ok(());
+    }
+
+    // If there aren't any finalizers yet, the "test" makes sure nobody else has added one in the
+    // meantime; otherwise we'd overwrite it
+    let patch = match &sim.metadata.finalizers {
+        None => json!([
+            {"op": "test", "path": "/metadata/finalizers", "value": null},
+            {"op": "add", "path": "/metadata/finalizers", "value": [ctx.opts.finalizer]},
+        ]),
+        Some(_) => json!([{"op": "add", "path": "/metadata/finalizers/-", "value": ctx.opts.finalizer}]),
+    };
+    let sim_api: kube::Api<Simulation> = kube::Api::all(ctx.client.clone());
+    sim_api
+        .patch(&sim.name_any(), &Default::default(), &Patch::Json::<()>(serde_json::from_value(patch)?))
+        .await?;
+    Ok(())
+}
+
+async fn finalize_simulation(ctx: &SimulationContext, sim: &Simulation) -> anyhow::Result<Action> {
+    let Some(patch) = remove_finalizer_patch(sim, &ctx.opts.finalizer) else {
+        return Ok(Action::await_change());
+    };
+
+    let state = sim.status.as_ref().and_then(|status| status.state.as_ref());
+    if !state.is_some_and(is_terminal) {
+        cleanup_simulation(ctx, sim).await;
+    }
+
+    info!("removing finalizer {} from simulation {}", ctx.opts.finalizer, ctx.name);
+    let sim_api: kube::Api<Simulation> = kube::Api::all(ctx.client.clone());
+    sim_api
+        .patch(&sim.name_any(), &Default::default(), &Patch::Json::<()>(patch))
+        .await?;
+    Ok(Action::await_change())
+}
+
+// We remove the finalizer by index, so the "test" makes sure that the list hasn't changed out from
+// under us (e.g., because another controller removed its finalizer first); if it has, the patch
+// fails and we'll try again on the next reconcile.
+pub(crate) fn remove_finalizer_patch(sim: &Simulation, finalizer: &str) -> Option<json_patch::Patch> {
+    let idx = sim.finalizers().iter().position(|f| f == finalizer)?;
+    let path = format!("/metadata/finalizers/{idx}");
+    Some(json_patch::Patch(vec![
+        PatchOperation::Test(TestOperation { path: path.clone(), value: finalizer.into() }),
+        PatchOperation::Remove(RemoveOperation { path }),
+    ]))
+}
+
+// Putting `simkube.io/debug: "true"` on a Simulation turns on debug logging for everything that
+// happens while reconciling it (and only it), regardless of the global verbosity; this works by
+// recording the annotation on the reconcile span, and adding a directive to the log filter that
+// enables debug logs inside any reconcile span with debug=true.
+pub fn log_filter(verbosity: &str) -> String {
+    format!("{verbosity},[reconcile{{debug=true}}]=debug")
+}
+
+fn debug_enabled(sim: &Simulation) -> bool {
+    sim.annotations().get(DEBUG_ANNOTATION_KEY).is_some_and(|v| v == "true")
+}
+
+#[instrument(parent=None, skip_all, fields(simulation=sim.name_any(), debug=debug_enabled(&sim)))]
 pub async fn reconcile(sim: Arc<Simulation>, ctx: Arc<SimulationContext>) -> Result<Action, AnyhowError> {
     let sim = sim.deref();
+    debug!("reconciling simulation {}", sim.name_any());
     let ctx = ctx.with_sim(sim);
+    let _permit = ctx.acquire_reconcile_permit().await?;
+    let res = reconcile_simulation(&ctx, sim).await;
+    if res.is_ok() {
+        ctx.reset_failures(&ctx.name);
+    }
+    res
+}
+
+async fn reconcile_simulation(ctx: &SimulationContext, sim: &Simulation) -> Result<Action, AnyhowError> {
+    if sim.metadata.deletion_timestamp.is_some() {
+        return finalize_simulation(ctx, sim).await.map_err(|e| e.into());
+    }
+    add_finalizer(ctx, sim).await?;
+
     let ctrl_ns = env::var(CTRL_NS_ENV_VAR).map_err(|e| anyhow!(e))?;
 
-    let metaroot = setup_sim_metaroot(&ctx, sim).await?;
+    let metaroot = setup_sim_metaroot(ctx, sim).await?;
     let (simulation_state, start_time, end_time, blocked_duration) =
-        match fetch_driver_state(&ctx, sim, &metaroot, &ctrl_ns).await? {
+        match fetch_driver_state(ctx, sim, &metaroot, &ctrl_ns).await? {
             DriverState::Left((state, st, et)) => (state, st, et, 0),
             DriverState::Right((state, t)) => (state, None, None, t),
         };
@@ -256,7 +381,7 @@ pub async fn reconcile(sim: Arc<Simulation>, ctx: Arc<SimulationContext>) -> Res
         .map_err(|e| anyhow!(e))?;
 
     match simulation_state {
-        SimulationState::Initializing => setup_simulation(&ctx, sim, &metaroot, &ctrl_ns).await.map_err(|e| e.into()),
+        SimulationState::Initializing => setup_simulation(ctx, sim, &metaroot, &ctrl_ns).await.map_err(|e| e.into()),
         SimulationState::Blocked => {
             info!("simulation blocked; sleeping for {blocked_duration} seconds");
             Ok(Action::requeue(Duration::from_secs(blocked_duration)))
@@ -265,7 +390,7 @@ pub async fn reconcile(sim: Arc<Simulation>, ctx: Arc<SimulationContext>) -> Res
         SimulationState::Finished | SimulationState::Failed => {
             // This action should never return an error, we want to try cleaning up once and if it
             // doesn't work, just abort (may revisit this in the future)
-            cleanup_simulation(&ctx, sim).await;
+            cleanup_simulation(ctx, sim).await;
             Ok(Action::await_change())
         },
 
@@ -285,7 +410,8 @@ pub fn error_policy(sim: Arc<Simulation>, err: &AnyhowError, ctx: Arc<Simulation
     let (action, state) = if err.is::<SkControllerError>() {
         (Action::await_change(), SimulationState::Failed)
     } else {
-        (Action::requeue(REQUEUE_ERROR_DURATION), SimulationState::Retrying)
+        let failures = ctx.record_failure(&sim.name_any());
+        (Action::requeue(error_backoff(failures)), SimulationState::Retrying)
     };
 
     let sim_api: kube::Api<Simulation> = kube::Api::all(ctx.client.clone());
@@ -304,3 +430,13 @@ pub fn error_policy(sim: Arc<Simulation>, err: &AnyhowError, ctx: Arc<Simulation
 
     action
 }
+
+// If a bunch of simulations fail at the same time for the same reason (e.g., the trace store is
+// unreachable), we don't want them all to retry in lockstep, so we back off exponentially with the
+// number of consecutive failures and add some jitter; the delay is chosen uniformly from the upper
+// half of the backoff window, so it always grows with the number of failures (until the cap).
+pub(crate) fn error_backoff(failures: u32) -> Duration {
+    let exp = failures.saturating_sub(1).min(31);
+    let delay = REQUEUE_ERROR_DURATION.saturating_mul(1 << exp).min(MAX_REQUEUE_ERROR_DURATION);
+    delay.mul_f64(rand::thread_rng().gen_range(0.5..1.0))
+}
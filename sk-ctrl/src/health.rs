@@ -0,0 +1,44 @@
+use std::sync::atomic::{
+    AtomicBool,
+    Ordering,
+};
+use std::sync::Arc;
+
+use rocket::http::Status;
+
+// Shared state for the liveness and readiness probes; the controller is "ready" once the
+// Simulation watcher has established its watch and finished the initial list of objects.
+#[derive(Clone, Default)]
+pub struct HealthState {
+    ready: Arc<AtomicBool>,
+}
+
+impl HealthState {
+    pub fn set_ready(&self) {
+        self.ready.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Relaxed)
+    }
+}
+
+#[rocket::get("/healthz")]
+pub fn healthz() -> Status {
+    Status::Ok
+}
+
+#[rocket::get("/readyz")]
+pub fn readyz(state: &rocket::State<HealthState>) -> Status {
+    if state.is_ready() {
+        Status::Ok
+    } else {
+        Status::ServiceUnavailable
+    }
+}
+
+pub fn build_server(config: &rocket::Config, state: HealthState) -> rocket::Rocket<rocket::Build> {
+    rocket::custom(config)
+        .mount("/", rocket::routes![healthz, readyz])
+        .manage(state)
+}
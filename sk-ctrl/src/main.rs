@@ -2,8 +2,13 @@ mod cert_manager;
 mod context;
 mod controller;
 mod errors;
+mod health;
 mod objects;
 
+use std::net::{
+    IpAddr,
+    Ipv4Addr,
+};
 use std::sync::Arc;
 
 use clap::Parser;
@@ -25,8 +30,13 @@ use sk_core::prelude::*;
 use crate::context::SimulationContext;
 use crate::controller::{
     error_policy,
+    log_filter,
     reconcile,
 };
+use crate::health::{
+    build_server,
+    HealthState,
+};
 
 #[derive(Clone, Debug, Parser)]
 struct Options {
@@ -40,6 +50,25 @@ struct Options {
     #[arg(long, default_value = "")]
     cert_manager_issuer: String,
 
+    #[arg(long, default_value = "8080")]
+    health_port: u16,
+
+    // If a simulation specifies a driverServiceAccount, create the service account along with a
+    // ClusterRole and ClusterRoleBinding giving it the permissions the driver needs
+    #[arg(long)]
+    create_driver_rbac: bool,
+
+    // If this isn't set, there's no limit on the number of simulations that can be reconciled at
+    // the same time (reconciles for the same simulation are always serialized, though)
+    #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    max_concurrent_reconciles: Option<usize>,
+
+    // Each controller only adds and removes its own finalizer on simulations, so if more than one
+    // controller is running at the same time (e.g., during a blue/green upgrade), give them
+    // different finalizer names so they don't step on each other
+    #[arg(long, default_value = CLEANUP_FINALIZER)]
+    finalizer: String,
+
     #[arg(short, long, default_value = "info")]
     verbosity: String,
 }
@@ -51,6 +80,24 @@ async fn run(opts: Options) -> EmptyResult {
     let job_api = kube::Api::<batchv1::Job>::all(client.clone());
 
     let (reader, writer) = reflector::store();
+
+    // The /healthz and /readyz endpoints are for the k8s liveness and readiness probes; we're
+    // ready as soon as the reflector store has been populated with the initial list of simulations
+    let health = HealthState::default();
+    let rkt_config = rocket::Config {
+        address: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        port: opts.health_port,
+        ..Default::default()
+    };
+    let server = build_server(&rkt_config, health.clone());
+    let ready_reader = reader.clone();
+    let ready_task = async move {
+        if ready_reader.wait_until_ready().await.is_ok() {
+            info!("initial simulation list complete, controller is ready");
+            health.set_ready();
+        }
+    };
+
     let sim_stream = watcher(sim_api, Default::default())
         .default_backoff()
         .reflect(writer)
@@ -75,14 +122,20 @@ async fn run(opts: Options) -> EmptyResult {
         .run(reconcile, error_policy, Arc::new(SimulationContext::new(client, opts)))
         .for_each(|_| future::ready(()));
 
-    ctrl.await;
-    Ok(())
+    tokio::spawn(ready_task);
+    tokio::select! {
+        _ = ctrl => Ok(()),
+        res = tokio::spawn(server.launch()) => match res {
+            Ok(r) => r.map(|_| ()).map_err(|err| err.into()),
+            Err(err) => Err(err.into()),
+        },
+    }
 }
 
 #[tokio::main]
 async fn main() -> EmptyResult {
     let args = Options::parse();
-    logging::setup(&args.verbosity);
+    logging::setup(&log_filter(&args.verbosity));
     run(args).await
 }
 
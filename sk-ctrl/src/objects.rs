@@ -4,6 +4,7 @@ use std::path::PathBuf;
 use anyhow::anyhow;
 use k8s_openapi::api::admissionregistration::v1 as admissionv1;
 use k8s_openapi::api::batch::v1 as batchv1;
+use k8s_openapi::api::rbac::v1 as rbacv1;
 use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
 use kube::ResourceExt;
 use object_store::ObjectStoreScheme;
@@ -171,7 +172,7 @@ pub fn build_driver_service(ctx: &SimulationContext, sim: &Simulation, metaroot:
                 target_port: Some(IntOrString::Int(sim.spec.driver.port)),
                 ..Default::default()
             }]),
-            selector: klabel!("job-name" => ctx.driver_name),
+            selector: klabel!(JOB_NAME_LABEL_KEY => ctx.driver_name),
             ..Default::default()
         }),
         ..Default::default()
@@ -196,7 +197,12 @@ pub fn build_driver_job(
         },
         None => sim.spec.driver.trace_path.clone(),
     };
-    let service_account = Some(env::var(POD_SVC_ACCOUNT_ENV_VAR)?);
+    // If the user didn't give us a service account for the driver, it runs with the same
+    // permissions as the controller
+    let service_account = match &sim.spec.driver_service_account {
+        Some(sa) => Some(sa.clone()),
+        None => Some(env::var(POD_SVC_ACCOUNT_ENV_VAR)?),
+    };
 
     let driver_secret_refs = driver_secrets.as_ref().map(|secrets_list| {
         secrets_list
@@ -216,8 +222,8 @@ pub fn build_driver_job(
                 spec: Some(corev1::PodSpec {
                     containers: vec![corev1::Container {
                         name: "driver".into(),
-                        command: Some(vec!["/sk-driver".into()]),
-                        args: Some(build_driver_args(ctx, cert_mount_path, trace_path, ctrl_ns.into())),
+                        command: Some(sim.spec.driver_command.clone().unwrap_or_else(|| vec!["/sk-driver".into()])),
+                        args: Some(build_driver_args(ctx, sim, cert_mount_path, trace_path, ctrl_ns.into())),
                         image: Some(sim.spec.driver.image.clone()),
                         env_from: driver_secret_refs,
                         env: Some(vec![
@@ -256,13 +262,77 @@ pub fn build_driver_job(
     })
 }
 
+pub fn build_driver_service_account(
+    ctx: &SimulationContext,
+    sim: &Simulation,
+    metaroot: &SimulationRoot,
+    svc_account: &str,
+) -> corev1::ServiceAccount {
+    let owner = metaroot;
+    corev1::ServiceAccount {
+        metadata: build_object_meta(&sim.spec.driver.namespace, svc_account, &ctx.name, owner),
+        ..Default::default()
+    }
+}
+
+// These are the permissions the driver needs to run a simulation: it has to read the Simulation,
+// manage the SimulationRoot and the lease, create the virtual namespaces, and apply (or delete)
+// whatever objects are in the trace; since we don't know ahead of time what kinds of objects those
+// are, that last rule has to cover everything.  Namespaces are cluster-scoped, so this has to be a
+// ClusterRole instead of a Role.
+pub fn build_driver_cluster_role(ctx: &SimulationContext, metaroot: &SimulationRoot) -> rbacv1::ClusterRole {
+    let rule = |api_group: &str, resources: &[&str], verbs: &[&str]| rbacv1::PolicyRule {
+        api_groups: Some(vec![api_group.into()]),
+        resources: Some(resources.iter().map(|r| r.to_string()).collect()),
+        verbs: verbs.iter().map(|v| v.to_string()).collect(),
+        ..Default::default()
+    };
+
+    let owner = metaroot;
+    rbacv1::ClusterRole {
+        metadata: build_global_object_meta(&ctx.driver_rbac_name, &ctx.name, owner),
+        rules: Some(vec![
+            rule("simkube.io", &["simulations"], &["get", "list", "watch"]),
+            rule("simkube.io", &["simulationroots"], &["get", "create", "delete"]),
+            rule("coordination.k8s.io", &["leases"], &["get", "update", "patch"]),
+            rule("", &["namespaces"], &["get", "list", "create", "delete"]),
+            rule("*", &["*"], &["get", "list", "watch", "create", "update", "patch", "delete"]),
+        ]),
+        ..Default::default()
+    }
+}
+
+pub fn build_driver_cluster_role_binding(
+    ctx: &SimulationContext,
+    sim: &Simulation,
+    metaroot: &SimulationRoot,
+    svc_account: &str,
+) -> rbacv1::ClusterRoleBinding {
+    let owner = metaroot;
+    rbacv1::ClusterRoleBinding {
+        metadata: build_global_object_meta(&ctx.driver_rbac_name, &ctx.name, owner),
+        role_ref: rbacv1::RoleRef {
+            api_group: "rbac.authorization.k8s.io".into(),
+            kind: "ClusterRole".into(),
+            name: ctx.driver_rbac_name.clone(),
+        },
+        subjects: Some(vec![rbacv1::Subject {
+            kind: "ServiceAccount".into(),
+            name: svc_account.into(),
+            namespace: Some(sim.spec.driver.namespace.clone()),
+            ..Default::default()
+        }]),
+    }
+}
+
 fn build_driver_args(
     ctx: &SimulationContext,
+    sim: &Simulation,
     cert_mount_path: String,
     trace_path: String,
     ctrl_ns: String,
 ) -> Vec<String> {
-    vec![
+    let mut args: Vec<String> = vec![
         "--cert-path".into(),
         format!("{cert_mount_path}/tls.crt"),
         "--key-path".into(),
@@ -277,7 +347,9 @@ fn build_driver_args(
         ctx.opts.verbosity.clone(),
         "--controller-ns".into(),
         ctrl_ns,
-    ]
+    ];
+    args.extend(sim.spec.driver_args.iter().flatten().cloned());
+    args
 }
 
 fn build_certificate_volumes(cert_secret_name: &str) -> VolumeInfo {
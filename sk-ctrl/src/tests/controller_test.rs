@@ -1,15 +1,23 @@
 use std::env;
+use std::time::Duration;
 
+use clap::Parser;
 use clockabilly::{
     Clockable,
     UtcClock,
 };
 use either::for_both;
+use futures::FutureExt;
 use httpmock::prelude::*;
+use k8s_openapi::api::rbac::v1 as rbacv1;
 use kube::runtime::controller::Action;
+use kube::ResourceExt;
 use serde_json::json;
 use sk_api::prometheus::*;
-use sk_api::v1::SimulationState;
+use sk_api::v1::{
+    SimulationState,
+    SimulationStatus,
+};
 use sk_core::k8s::build_lease;
 
 use super::*;
@@ -23,6 +31,10 @@ fn opts() -> Options {
         driver_secrets: None,
         use_cert_manager: false,
         cert_manager_issuer: "".into(),
+        health_port: 8080,
+        create_driver_rbac: false,
+        max_concurrent_reconciles: None,
+        finalizer: CLEANUP_FINALIZER.into(),
         verbosity: "info".into(),
     }
 }
@@ -265,9 +277,11 @@ async fn test_setup_simulation_create_prom(test_sim: Simulation, test_sim_root:
 }
 
 #[rstest]
-#[case::ready(true, false)]
-#[case::not_ready(false, false)]
-#[case::disabled(true, true)]
+#[case::ready(true, false, false, false)]
+#[case::not_ready(false, false, false, false)]
+#[case::disabled(true, true, false, false)]
+#[case::driver_overrides(true, false, true, false)]
+#[case::driver_service_account(true, false, false, true)]
 #[traced_test]
 #[tokio::test]
 async fn test_setup_simulation_wait_prom(
@@ -276,8 +290,17 @@ async fn test_setup_simulation_wait_prom(
     opts: Options,
     #[case] ready: bool,
     #[case] disabled: bool,
+    #[case] driver_overrides: bool,
+    #[case] driver_service_account: bool,
 ) {
     env::set_var("POD_SVC_ACCOUNT", "asdf");
+    if driver_overrides {
+        test_sim.spec.driver_command = Some(vec!["/custom-driver".into()]);
+        test_sim.spec.driver_args = Some(vec!["--speed".into(), "10".into()]);
+    }
+    if driver_service_account {
+        test_sim.spec.driver_service_account = Some("driver-sa".into());
+    }
     let (mut fake_apiserver, client) = make_fake_apiserver();
     let ctx = Arc::new(SimulationContext::new(client, opts)).with_sim(&test_sim);
 
@@ -289,7 +312,7 @@ async fn test_setup_simulation_wait_prom(
     let driver_ns_obj = build_driver_namespace(&ctx, &test_sim);
     let driver_svc_obj = build_driver_service(&ctx, &test_sim, &test_sim_root);
     let webhook_obj = build_mutating_webhook(&ctx, &test_sim, &test_sim_root);
-    let driver_obj = build_driver_job(&ctx, &test_sim, None, "".into(), TEST_CTRL_NAMESPACE).unwrap();
+    let driver_obj = build_driver_job(&ctx, &test_sim, None, "", TEST_CTRL_NAMESPACE).unwrap();
 
     fake_apiserver
         .handle(|when, then| {
@@ -346,8 +369,26 @@ async fn test_setup_simulation_wait_prom(
             })
             .handle_not_found(format!("/apis/batch/v1/namespaces/{TEST_NAMESPACE}/jobs/{driver_name}"))
             .handle(move |when, then| {
-                when.method(POST)
+                let when = when
+                    .method(POST)
                     .path(format!("/apis/batch/v1/namespaces/{TEST_NAMESPACE}/jobs"));
+                // The controller's service account is "asdf", so that's what the driver runs as
+                // unless the simulation says otherwise
+                let when = when.matches(if driver_service_account {
+                    |req: &HttpMockRequest| driver_job_service_account(req) == Some("driver-sa".into())
+                } else {
+                    |req: &HttpMockRequest| driver_job_service_account(req) == Some("asdf".into())
+                });
+                if driver_overrides {
+                    when.matches(|req: &HttpMockRequest| {
+                        let job: batchv1::Job = serde_json::from_slice(req.body.as_ref().unwrap()).unwrap();
+                        let container = &job.spec.unwrap().template.spec.unwrap().containers[0];
+                        let args = container.args.as_ref().unwrap();
+                        container.command == Some(vec!["/custom-driver".into()])
+                            && args.starts_with(&["--cert-path".into()])
+                            && args.ends_with(&["--speed".into(), "10".into()])
+                    });
+                }
                 then.json_body_obj(&driver_obj);
             });
     }
@@ -363,6 +404,55 @@ async fn test_setup_simulation_wait_prom(
     fake_apiserver.assert();
 }
 
+fn driver_job_service_account(req: &HttpMockRequest) -> Option<String> {
+    let job: batchv1::Job = serde_json::from_slice(req.body.as_ref().unwrap()).unwrap();
+    job.spec.unwrap().template.spec.unwrap().service_account
+}
+
+#[rstest]
+#[traced_test]
+#[tokio::test]
+async fn test_create_driver_rbac(test_sim: Simulation, test_sim_root: SimulationRoot, mut opts: Options) {
+    opts.create_driver_rbac = true;
+    let (mut fake_apiserver, client) = make_fake_apiserver();
+    let ctx = Arc::new(SimulationContext::new(client, opts)).with_sim(&test_sim);
+
+    let sa_obj = build_driver_service_account(&ctx, &test_sim, &test_sim_root, "driver-sa");
+    let role_obj = build_driver_cluster_role(&ctx, &test_sim_root);
+    let binding_obj = build_driver_cluster_role_binding(&ctx, &test_sim, &test_sim_root, "driver-sa");
+    let rbac_name = ctx.driver_rbac_name.clone();
+
+    fake_apiserver
+        .handle_not_found(format!("/api/v1/namespaces/{TEST_NAMESPACE}/serviceaccounts/driver-sa"))
+        .handle(move |when, then| {
+            when.method(POST)
+                .path(format!("/api/v1/namespaces/{TEST_NAMESPACE}/serviceaccounts"));
+            then.json_body_obj(&sa_obj);
+        })
+        .handle_not_found(format!("/apis/rbac.authorization.k8s.io/v1/clusterroles/{rbac_name}"))
+        .handle(move |when, then| {
+            when.method(POST).path("/apis/rbac.authorization.k8s.io/v1/clusterroles");
+            then.json_body_obj(&role_obj);
+        })
+        .handle_not_found(format!("/apis/rbac.authorization.k8s.io/v1/clusterrolebindings/{rbac_name}"))
+        .handle(move |when, then| {
+            when.method(POST)
+                .path("/apis/rbac.authorization.k8s.io/v1/clusterrolebindings")
+                .matches(|req: &HttpMockRequest| {
+                    let binding: rbacv1::ClusterRoleBinding =
+                        serde_json::from_slice(req.body.as_ref().unwrap()).unwrap();
+                    let subject = &binding.subjects.unwrap()[0];
+                    subject.name == "driver-sa" && subject.namespace.as_deref() == Some(TEST_NAMESPACE)
+                });
+            then.json_body_obj(&binding_obj);
+        })
+        .build();
+
+    create_driver_rbac_if_not_present(&ctx, &test_sim, &test_sim_root, "driver-sa")
+        .await
+        .unwrap();
+    fake_apiserver.assert();
+}
 
 #[rstest]
 #[traced_test]
@@ -384,3 +474,173 @@ async fn test_cleanup_simulation(test_sim: Simulation, opts: Options) {
     assert!(!logs_contain("ERROR"));
     fake_apiserver.assert();
 }
+
+#[rstest]
+#[tokio::test]
+async fn test_error_backoff(opts: Options) {
+    let (_, client) = make_fake_apiserver();
+    let ctx = SimulationContext::new(client, opts);
+
+    let mut last = Duration::ZERO;
+    for i in 0..3 {
+        let delay = error_backoff(ctx.record_failure(TEST_SIM_NAME));
+        assert!(delay > last);
+        assert!(delay < REQUEUE_ERROR_DURATION * (1 << i));
+        last = delay;
+    }
+
+    // Failures for other simulations don't count towards this one
+    assert_eq!(ctx.record_failure("other-sim"), 1);
+
+    ctx.reset_failures(TEST_SIM_NAME);
+    assert_eq!(ctx.record_failure(TEST_SIM_NAME), 1);
+    assert!(error_backoff(1) < REQUEUE_ERROR_DURATION);
+    assert_eq!(error_backoff(100).max(MAX_REQUEUE_ERROR_DURATION), MAX_REQUEUE_ERROR_DURATION);
+}
+
+#[rstest]
+#[case::unlimited(&[], Some(None))]
+#[case::limited(&["--max-concurrent-reconciles", "2"], Some(Some(2)))]
+#[case::zero(&["--max-concurrent-reconciles", "0"], None)]
+fn test_max_concurrent_reconciles_option(#[case] args: &[&str], #[case] expected: Option<Option<usize>>) {
+    let res = Options::try_parse_from(["sk-ctrl"].iter().chain(args));
+    assert_eq!(res.ok().map(|opts| opts.max_concurrent_reconciles), expected);
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_acquire_reconcile_permit(mut opts: Options) {
+    let (_, client) = make_fake_apiserver();
+    opts.max_concurrent_reconciles = Some(1);
+    let ctx = Arc::new(SimulationContext::new(client, opts));
+    let sim_ctx = ctx.clone().with_sim(&test_sim());
+
+    // The permits are shared between all the per-simulation copies of the context
+    let permit = sim_ctx.acquire_reconcile_permit().await.unwrap();
+    assert!(permit.is_some());
+    assert!(ctx.acquire_reconcile_permit().now_or_never().is_none());
+
+    drop(permit);
+    assert!(ctx.acquire_reconcile_permit().now_or_never().is_some());
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_acquire_reconcile_permit_unlimited(opts: Options) {
+    let (_, client) = make_fake_apiserver();
+    let ctx = SimulationContext::new(client, opts);
+    assert!(ctx.acquire_reconcile_permit().await.unwrap().is_none());
+}
+
+// Log lines get written here instead of stdout, so that we can check what the filter let through
+#[derive(Clone, Default)]
+struct LogBuffer(Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl std::io::Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[rstest]
+#[case::debug_annotation(Some("true"), true)]
+#[case::debug_disabled(Some("false"), false)]
+#[case::no_annotation(None, false)]
+#[tokio::test]
+async fn test_reconcile_debug_annotation(
+    mut test_sim: Simulation,
+    opts: Options,
+    #[case] annotation: Option<&str>,
+    #[case] expect_debug: bool,
+) {
+    use tracing::instrument::WithSubscriber;
+
+    if let Some(val) = annotation {
+        test_sim.annotations_mut().insert(DEBUG_ANNOTATION_KEY.into(), val.into());
+    }
+
+    let buf = LogBuffer::default();
+    let writer = buf.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(log_filter("info"))
+        .with_writer(move || writer.clone())
+        .finish();
+
+    // There's nothing behind the fake apiserver, so the reconcile fails; we only care about what
+    // got logged on the way there
+    let (_, client) = make_fake_apiserver();
+    let ctx = Arc::new(SimulationContext::new(client, opts));
+    let _ = reconcile(Arc::new(test_sim), ctx).with_subscriber(subscriber).await;
+
+    let logs = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+    assert_eq!(logs.contains("reconciling simulation"), expect_debug);
+}
+
+fn deleted_sim(mut sim: Simulation, finalizers: &[&str]) -> Simulation {
+    sim.metadata.deletion_timestamp = Some(metav1::Time(UtcClock.now()));
+    sim.metadata.finalizers = Some(finalizers.iter().map(|f| f.to_string()).collect());
+    sim.status = Some(SimulationStatus {
+        state: Some(SimulationState::Finished),
+        ..Default::default()
+    });
+    sim
+}
+
+#[rstest]
+fn test_remove_finalizer_patch(test_sim: Simulation) {
+    let sim = deleted_sim(test_sim.clone(), &["other.io/cleanup", CLEANUP_FINALIZER, "another.io/cleanup"]);
+    let patch = remove_finalizer_patch(&sim, CLEANUP_FINALIZER).unwrap();
+
+    let mut sim_json = serde_json::to_value(&sim).unwrap();
+    json_patch::patch(&mut sim_json, &patch).unwrap();
+    assert_eq!(sim_json["metadata"]["finalizers"], json!(["other.io/cleanup", "another.io/cleanup"]));
+
+    // If somebody else changed the list in the meantime, the patch shouldn't apply
+    let mut sim_json = serde_json::to_value(deleted_sim(test_sim, &[CLEANUP_FINALIZER, "other.io/cleanup"])).unwrap();
+    assert!(json_patch::patch(&mut sim_json, &patch).is_err());
+}
+
+#[rstest]
+fn test_remove_finalizer_patch_not_owned(test_sim: Simulation) {
+    let sim = deleted_sim(test_sim, &["other.io/cleanup"]);
+    assert!(remove_finalizer_patch(&sim, CLEANUP_FINALIZER).is_none());
+}
+
+#[rstest]
+#[case::owned(&["other.io/cleanup", CLEANUP_FINALIZER], true)]
+#[case::foreign_only(&["other.io/cleanup"], false)]
+#[tokio::test]
+async fn test_reconcile_deleted_simulation(
+    test_sim: Simulation,
+    opts: Options,
+    #[case] finalizers: &[&str],
+    #[case] expect_patch: bool,
+) {
+    let (mut fake_apiserver, client) = make_fake_apiserver();
+    let sim = deleted_sim(test_sim, finalizers);
+    if expect_patch {
+        let sim_json = serde_json::to_value(&sim).unwrap();
+        fake_apiserver.handle(move |when, then| {
+            when.method(httpmock::Method::PATCH)
+                .path(format!("/apis/simkube.io/v1/simulations/{TEST_SIM_NAME}"))
+                .json_body(json!([
+                    {"op": "test", "path": "/metadata/finalizers/1", "value": CLEANUP_FINALIZER},
+                    {"op": "remove", "path": "/metadata/finalizers/1"},
+                ]));
+            then.json_body(sim_json.clone());
+        });
+    }
+    fake_apiserver.build();
+
+    // The simulation already finished, so there's nothing to clean up; all we do is remove our
+    // finalizer (if it's there), and we don't touch anyone else's
+    let ctx = Arc::new(SimulationContext::new(client, opts));
+    let action = reconcile(Arc::new(sim), ctx).await.unwrap();
+
+    assert_eq!(action, Action::await_change());
+    fake_apiserver.assert();
+}
@@ -0,0 +1,21 @@
+use rocket::http::Status;
+use rocket::local::asynchronous::Client;
+
+use super::*;
+use crate::health::*;
+
+#[rstest]
+#[tokio::test]
+async fn test_health_endpoints() {
+    let state = HealthState::default();
+    let client = Client::untracked(build_server(&rocket::Config::debug_default(), state.clone()))
+        .await
+        .unwrap();
+
+    assert_eq!(client.get("/healthz").dispatch().await.status(), Status::Ok);
+    assert_eq!(client.get("/readyz").dispatch().await.status(), Status::ServiceUnavailable);
+
+    state.set_ready();
+    assert_eq!(client.get("/healthz").dispatch().await.status(), Status::Ok);
+    assert_eq!(client.get("/readyz").dispatch().await.status(), Status::Ok);
+}
@@ -1,4 +1,5 @@
 mod controller_test;
+mod health_test;
 
 use rstest::*;
 use sk_core::k8s::testutils::*;
@@ -6,6 +6,7 @@ use std::net::{
     IpAddr,
     Ipv4Addr,
 };
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -17,8 +18,8 @@ use sk_core::external_storage::{
     SkObjectStore,
 };
 use sk_core::k8s::{
+    build_client,
     ApiSet,
-    KubeResourceExt,
     OwnersCache,
 };
 use sk_core::prelude::*;
@@ -26,6 +27,10 @@ use sk_core::{
     hooks,
     logging,
 };
+use sk_store::replay::{
+    parse_remap,
+    NamespaceMap,
+};
 use sk_store::{
     TraceStorable,
     TraceStore,
@@ -47,6 +52,11 @@ struct Options {
     #[arg(long)]
     virtual_ns_prefix: String,
 
+    // Replay objects from one namespace in the trace into a specific namespace instead of the
+    // default virtual namespace, e.g., `--remap prod=sim`; can be specified multiple times
+    #[arg(long = "remap", value_parser = parse_remap)]
+    remaps: Vec<(String, String)>,
+
     #[arg(long, default_value = DRIVER_ADMISSION_WEBHOOK_PORT)]
     admission_webhook_port: u16,
 
@@ -65,6 +75,19 @@ struct Options {
     #[arg(long)]
     trace_path: String,
 
+    // If the driver pod gets restarted partway through a simulation, it picks up where it left off
+    // from the checkpoint stored here; this needs to be on a volume that outlives the pod
+    #[arg(long)]
+    checkpoint_path: Option<PathBuf>,
+
+    // These are mostly useful for running the driver locally against a test cluster; in the
+    // "normal" case the driver uses its in-cluster config
+    #[arg(long)]
+    kubeconfig: Option<String>,
+
+    #[arg(long)]
+    context: Option<String>,
+
     #[arg(short, long, default_value = "info")]
     verbosity: String,
 }
@@ -75,16 +98,17 @@ pub struct DriverContext {
     root_name: String,
     sim: Simulation,
     ctrl_ns: String,
-    virtual_ns_prefix: String,
+    ns_map: NamespaceMap,
     owners_cache: Arc<Mutex<OwnersCache>>,
     store: Arc<dyn TraceStorable + Send + Sync>,
+    checkpoint_path: Option<PathBuf>,
 }
 
 #[instrument(ret, err)]
 async fn run(opts: Options) -> EmptyResult {
     let name = env::var(DRIVER_NAME_ENV_VAR)?;
 
-    let client = kube::Client::try_default().await?;
+    let client = build_client(opts.kubeconfig.as_deref(), opts.context.as_deref()).await?;
     let sim_api: kube::Api<Simulation> = kube::Api::all(client.clone());
     let sim = sim_api.get(&opts.sim_name).await?;
 
@@ -102,9 +126,10 @@ async fn run(opts: Options) -> EmptyResult {
         root_name,
         sim,
         ctrl_ns: opts.controller_ns.clone(),
-        virtual_ns_prefix: opts.virtual_ns_prefix.clone(),
+        ns_map: NamespaceMap::new(Some(&opts.virtual_ns_prefix), opts.remaps.clone()),
         owners_cache,
         store,
+        checkpoint_path: opts.checkpoint_path.clone(),
     };
 
     let rkt_config = rocket::Config {
@@ -79,8 +79,8 @@ pub async fn mutate_pod(
 ) -> anyhow::Result<AdmissionResponse> {
     // enclose in a block so we release the mutex when we're done
     let owners = {
-        let mut owners_cache = ctx.owners_cache.lock().await;
-        owners_cache.compute_owner_chain(pod).await?
+        let owners_cache = ctx.owners_cache.lock().await;
+        owners_cache.compute_owner_chain(pod).await?.all()
     };
 
     if !owners.iter().any(|o| o.name == ctx.root_name) {
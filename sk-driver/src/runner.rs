@@ -1,4 +1,3 @@
-use std::cmp::max;
 use std::time::Duration;
 
 use anyhow::{
@@ -13,8 +12,6 @@ use either::Either;
 use kube::api::{
     DeleteParams,
     DynamicObject,
-    Patch,
-    PatchParams,
     PropagationPolicy,
 };
 use kube::ResourceExt;
@@ -26,11 +23,15 @@ use sk_core::k8s::{
     build_global_object_meta,
     build_simulation_root,
     try_update_lease,
-    ApiSet,
     GVK,
 };
 use sk_core::macros::*;
 use sk_core::prelude::*;
+use sk_store::replay::{
+    replay_trace,
+    ReplayOptions,
+    ReplayTarget,
+};
 use tokio::time::sleep;
 
 use super::*;
@@ -59,15 +60,15 @@ pub fn build_virtual_ns(ctx: &DriverContext, root: &SimulationRoot, namespace: &
 pub fn build_virtual_obj(
     ctx: &DriverContext,
     root: &SimulationRoot,
-    original_ns: &str,
-    virtual_ns: &str,
+    virtual_ns: Option<&str>,
     obj: &DynamicObject,
     maybe_pod_spec_template_path: Option<&str>,
 ) -> anyhow::Result<DynamicObject> {
     let owner = root;
     let mut vobj = obj.clone();
     add_common_metadata(&ctx.name, owner, &mut vobj.metadata);
-    vobj.metadata.namespace = Some(virtual_ns.into());
+    vobj.metadata.namespace = virtual_ns.map(|ns| ns.into());
+    ctx.ns_map.remap_references(&mut vobj);
     klabel_insert!(vobj, VIRTUAL_LABEL_KEY => "true");
 
     if let Some(pod_spec_template_path) = maybe_pod_spec_template_path {
@@ -79,13 +80,15 @@ pub fn build_virtual_obj(
             &mut vobj.data,
             false,
         )?;
-        jsonutils::patch_ext::add(
-            &format!("{}/metadata/annotations", pod_spec_template_path),
-            ORIG_NAMESPACE_ANNOTATION_KEY,
-            &json!(original_ns),
-            &mut vobj.data,
-            true,
-        )?;
+        if let Some(original_ns) = obj.namespace() {
+            jsonutils::patch_ext::add(
+                &format!("{}/metadata/annotations", pod_spec_template_path),
+                ORIG_NAMESPACE_ANNOTATION_KEY,
+                &json!(original_ns),
+                &mut vobj.data,
+                true,
+            )?;
+        }
         jsonutils::patch_ext::remove("", "status", &mut vobj.data)?;
 
         // We remove all container ports from the pod specification just before applying, because it is
@@ -104,11 +107,32 @@ pub fn build_virtual_obj(
     Ok(vobj)
 }
 
+// Namespaced objects in the trace get moved into a "virtual" namespace that's owned by the
+// simulation root, so that everything gets cleaned up when the simulation is over
+struct DriverTarget<'a> {
+    ctx: &'a DriverContext,
+    root: &'a SimulationRoot,
+}
+
+impl<'a> ReplayTarget for DriverTarget<'a> {
+    fn namespace_for(&self, ns: &str) -> String {
+        self.ctx.ns_map.map(ns)
+    }
+
+    fn build_namespace(&self, name: &str) -> corev1::Namespace {
+        build_virtual_ns(self.ctx, self.root, name)
+    }
+
+    fn build_obj(&self, obj: &DynamicObject, ns: Option<&str>) -> anyhow::Result<DynamicObject> {
+        let gvk = GVK::from_dynamic_obj(obj)?;
+        let pod_spec_template_path = self.ctx.store.config().pod_spec_template_path(&gvk);
+        build_virtual_obj(self.ctx, self.root, ns, obj, pod_spec_template_path)
+    }
+}
+
 #[instrument(parent=None, skip_all, fields(simulation=ctx.name))]
 pub async fn run_trace(ctx: DriverContext, client: kube::Client) -> EmptyResult {
     let roots_api: kube::Api<SimulationRoot> = kube::Api::all(client.clone());
-    let ns_api: kube::Api<corev1::Namespace> = kube::Api::all(client.clone());
-    let mut apiset = ApiSet::new(client.clone());
 
     let root_obj = if let Some(root) = roots_api.get_opt(&ctx.root_name).await? {
         warn!("Driver root {} already exists; continuing...", ctx.root_name);
@@ -118,62 +142,20 @@ pub async fn run_trace(ctx: DriverContext, client: kube::Client) -> EmptyResult
         roots_api.create(&Default::default(), &root_obj).await?
     };
 
-    let speed = ctx.sim.spec.driver.speed;
-
-    let mut sim_ts = ctx.store.start_ts().ok_or(anyhow!("no trace data"))?;
+    let sim_ts = ctx.store.start_ts().ok_or(anyhow!("no trace data"))?;
     let sim_end_ts = ctx.store.end_ts().ok_or(anyhow!("no trace data"))?;
     let sim_duration = sim_end_ts - sim_ts;
 
     try_update_lease(client.clone(), &ctx.sim, &ctx.ctrl_ns, sim_duration).await?;
 
-    for (evt, maybe_next_ts) in ctx.store.iter() {
-        // We're currently assuming that all tracked objects are namespace-scoped,
-        // this will panic/fail if that is not true.
-        for obj in &evt.applied_objs {
-            let gvk = GVK::from_dynamic_obj(obj)?;
-            let original_ns = obj.namespace().unwrap();
-            let virtual_ns = format!("{}-{}", ctx.virtual_ns_prefix, original_ns);
-
-            if ns_api.get_opt(&virtual_ns).await?.is_none() {
-                info!("creating virtual namespace: {virtual_ns}");
-                let vns = build_virtual_ns(&ctx, &root_obj, &virtual_ns);
-                ns_api.create(&Default::default(), &vns).await?;
-            }
-
-            let pod_spec_template_path = ctx.store.config().pod_spec_template_path(&gvk);
-            let vobj = build_virtual_obj(&ctx, &root_obj, &original_ns, &virtual_ns, obj, pod_spec_template_path)?;
-
-            info!("applying object {}", vobj.namespaced_name());
-            apiset
-                .api_for_obj(&vobj)
-                .await?
-                .patch(&vobj.name_any(), &PatchParams::apply("simkube"), &Patch::Apply(&vobj))
-                .await?;
-        }
-
-        for obj in &evt.deleted_objs {
-            info!("deleting object {}", obj.namespaced_name());
-            let virtual_ns = format!("{}-{}", ctx.virtual_ns_prefix, obj.namespace().unwrap());
-            let mut vobj = obj.clone();
-            vobj.metadata.namespace = Some(virtual_ns);
-            apiset
-                .api_for_obj(&vobj)
-                .await?
-                .delete(&obj.name_any(), &Default::default())
-                .await?;
-        }
-
-        if let Some(next_ts) = maybe_next_ts {
-            let simulation_normal_step_duration = max(0, next_ts - sim_ts) as f64;
-            let sleep_duration = (simulation_normal_step_duration / speed) as u64;
-
-            info!("next event happens in {sleep_duration} seconds, sleeping");
-            debug!("current sim ts = {sim_ts}, next sim ts = {next_ts}");
-
-            sim_ts = next_ts;
-            sleep(Duration::from_secs(sleep_duration as u64)).await;
-        }
-    }
+    let target = DriverTarget { ctx: &ctx, root: &root_obj };
+    let opts = ReplayOptions {
+        speed: ctx.sim.spec.driver.speed,
+        labels: [(SIMULATION_LABEL_KEY.into(), ctx.name.clone())].into(),
+        checkpoint_path: ctx.checkpoint_path.clone(),
+        ..Default::default()
+    };
+    replay_trace(client, ctx.store.as_ref(), &target, &opts).await?;
 
     let clock = UtcClock::boxed();
     let timeout = clock.now_ts() + DRIVER_CLEANUP_TIMEOUT_SECONDS;
@@ -47,8 +47,9 @@ pub fn build_driver_context(
         root_name: TEST_DRIVER_ROOT_NAME.into(),
         sim: Simulation::new(TEST_SIM_NAME, Default::default()),
         ctrl_ns: TEST_CTRL_NAMESPACE.into(),
-        virtual_ns_prefix: TEST_VIRT_NS_PREFIX.into(),
+        ns_map: NamespaceMap::new(Some(TEST_VIRT_NS_PREFIX), []),
         owners_cache,
         store,
+        checkpoint_path: None,
     }
 }
@@ -18,7 +18,10 @@ use kube::core::{
 use kube::ResourceExt;
 use mockall::predicate;
 use rocket::serde::json::Json;
-use sk_core::k8s::PodLifecycleData;
+use sk_core::k8s::{
+    KubeResourceExt,
+    PodLifecycleData,
+};
 use sk_store::mock::MockTraceStore;
 
 use super::helpers::build_driver_context;
@@ -32,15 +35,15 @@ fn ctx(
 ) -> DriverContext {
     let (_, client) = make_fake_apiserver();
     let mut owners = HashMap::new();
-    owners.insert(test_pod.namespaced_name(), pod_owners);
+    owners.insert(test_pod.namespaced_name(), pod_owners.into());
     let cache = OwnersCache::new_from_parts(ApiSet::new(client), owners);
     build_driver_context(Arc::new(Mutex::new(cache)), Arc::new(store))
 }
 
 #[fixture]
 fn adm_req(test_pod: corev1::Pod) -> AdmissionRequest<corev1::Pod> {
-    let gvr = GroupVersionResource::gvr("".into(), "v1".into(), "pods".into());
-    let gvk = GroupVersionKind::gvk("".into(), "v1".into(), "Pod".into());
+    let gvr = GroupVersionResource::gvr("", "v1", "pods");
+    let gvk = GroupVersionKind::gvk("", "v1", "Pod");
     AdmissionRequest {
         types: TypeMeta { api_version: "v1".into(), kind: "Pod".into() },
         uid: "12345-12345".into(),
@@ -132,7 +135,7 @@ async fn test_mutate_pod(mut test_pod: corev1::Pod, mut adm_resp: AdmissionRespo
     let _ = store
         .expect_lookup_pod_lifecycle()
         .with(predicate::always(), predicate::eq(EMPTY_POD_SPEC_HASH), predicate::eq(0))
-        .returning(|_, _, _| PodLifecycleData::Finished(1, 2))
+        .returning(|_, _, _| PodLifecycleData::Finished(1, 2, None))
         .once();
     let _ = store.expect_has_obj().returning(move |o| o == owner_ns_name);
 
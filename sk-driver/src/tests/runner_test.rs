@@ -4,6 +4,8 @@ use clockabilly::{
     UtcClock,
 };
 use httpmock::Method::*;
+use kube::api::DynamicObject;
+use serde_json::json;
 use sk_api::v1::SimulationRootSpec;
 use sk_core::k8s::build_lease;
 
@@ -14,6 +16,7 @@ use super::helpers::{
 use super::*;
 use crate::runner::{
     build_virtual_ns,
+    build_virtual_obj,
     cleanup_trace,
 };
 
@@ -167,3 +170,36 @@ async fn itest_run(#[case] has_start_marker: bool) {
     run_trace(ctx, client).await.unwrap();
     fake_apiserver.assert();
 }
+
+#[rstest]
+#[tokio::test]
+async fn test_build_virtual_obj_remapped() {
+    let (_, client) = make_fake_apiserver();
+    let cache = Arc::new(Mutex::new(OwnersCache::new(ApiSet::new(client))));
+    let mut ctx = build_driver_context(cache, Arc::new(TraceStore::new(Default::default())));
+    ctx.ns_map = NamespaceMap::new(Some(TEST_VIRT_NS_PREFIX), [("prod".to_string(), "sim".to_string())]);
+
+    let root = SimulationRoot {
+        metadata: metav1::ObjectMeta {
+            name: Some(TEST_DRIVER_ROOT_NAME.into()),
+            uid: Some("puwern5t".into()),
+            ..Default::default()
+        },
+        spec: SimulationRootSpec {},
+    };
+    let obj: DynamicObject = serde_json::from_value(json!({
+        "apiVersion": "rbac.authorization.k8s.io/v1",
+        "kind": "RoleBinding",
+        "metadata": {"namespace": "prod", "name": "the-binding"},
+        "roleRef": {"apiGroup": "rbac.authorization.k8s.io", "kind": "Role", "name": "the-role"},
+        "subjects": [{"kind": "ServiceAccount", "name": "the-sa", "namespace": "prod"}],
+    }))
+    .unwrap();
+
+    let virtual_ns = ctx.ns_map.map("prod");
+    let vobj = build_virtual_obj(&ctx, &root, Some(&virtual_ns), &obj, None).unwrap();
+
+    // The binding and the service account it refers to have to end up in the same namespace
+    assert_eq!(vobj.metadata.namespace, Some("sim".into()));
+    assert_eq!(vobj.data["subjects"][0]["namespace"], "sim");
+}
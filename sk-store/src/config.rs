@@ -2,37 +2,205 @@ use std::collections::HashMap;
 use std::fs::File;
 use std::ops::Not;
 
+use regex::Regex;
 use serde::{
     Deserialize,
     Serialize,
 };
+use sk_core::errors::*;
 use sk_core::k8s::GVK;
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+use crate::transforms::{
+    FieldTransform,
+    ObjectTransformer,
+};
+
+// Env var names that look like they hold credentials; used if captureContainerEnv is set but no
+// sensitiveEnvPattern is given
+const DEFAULT_SENSITIVE_ENV_PATTERN: &str = "(?i)passw(or)?d|secret|token|credential|api_?key|private_?key";
+
+// Owner lookups still go through the apiset's rate limiter (if it has one), so this mostly just
+// bounds how many requests we have outstanding at once
+pub(crate) const DEFAULT_OWNER_LOOKUP_CONCURRENCY: usize = 8;
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TrackedObjectConfig {
     pub pod_spec_template_path: Option<String>,
 
     #[serde(default, skip_serializing_if = "<&bool>::not")]
     pub track_lifecycle: bool,
+
+    // These are passed through to the apiserver on the watch call for this kind, so that we only
+    // get sent the objects we actually care about
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label_selector: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field_selector: Option<String>,
+
+    // Applied (in order) to every object of this kind before it's stored; see transforms.rs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transforms: Vec<FieldTransform>,
+}
+
+// What to do with objects that are bigger than maxObjectSize: either replace their largest fields
+// with a placeholder until they fit, or just don't record them at all
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OversizedObjectPolicy {
+    #[default]
+    Truncate,
+    Skip,
 }
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+// What the pod watcher should do when the channel to the store is full: either wait for the store
+// to catch up (which means we stop reading pod events in the meantime), or throw the update away
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BackpressurePolicy {
+    #[default]
+    Block,
+    Drop,
+}
+
+// How the pod watcher's cache of pod owner chains evicts entries: either never (which is what we've
+// always done), or after they've been around for ownersCacheTtlSeconds
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OwnersCachePolicy {
+    #[default]
+    Unbounded,
+    Ttl,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TracerConfig {
     pub tracked_objects: HashMap<GVK, TrackedObjectConfig>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_objects_per_namespace: Option<usize>,
+
+    // How many past versions of each object to keep around (along with the time they were
+    // recorded); 0 means we only keep the current version, like we've always done
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub object_history_depth: usize,
+
+    // Maximum size (in bytes, when serialized as JSON) of a single recorded object
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_object_size: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub oversized_object_policy: OversizedObjectPolicy,
+
+    // Maps API groups to the version that should be used for them, for groups that serve the same
+    // kind at multiple versions; if this is set at all (even to an empty map), groups that aren't
+    // listed use the apiserver's preferred version instead of whatever version we were given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preferred_versions: Option<HashMap<String, String>>,
+
+    // If this is set, the pod watcher hands its updates off to the store through a bounded channel
+    // with this many slots, instead of writing to the store directly
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub store_channel_capacity: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub store_backpressure_policy: BackpressurePolicy,
+
+    // If this is set, the pod watcher records the environment variables for each tracked pod's
+    // containers; values from Secrets, or whose names match sensitiveEnvPattern, are masked out
+    #[serde(default, skip_serializing_if = "<&bool>::not")]
+    pub capture_container_env: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sensitive_env_pattern: Option<String>,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub owners_cache_policy: OwnersCachePolicy,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owners_cache_ttl_seconds: Option<i64>,
+
+    // How many owner chains the pod watcher looks up at once when the pod watch (re)starts and it
+    // gets every pod in the cluster in one go; defaults to DEFAULT_OWNER_LOOKUP_CONCURRENCY
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner_lookup_concurrency: Option<usize>,
+
+    // If this is set, the watchers re-establish their watch streams at least this often, even if
+    // nothing's come in on them, so that idle connections don't get dropped by proxies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heartbeat_interval_seconds: Option<i64>,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+fn is_default<T: Default + PartialEq>(t: &T) -> bool {
+    *t == T::default()
 }
 
 impl TracerConfig {
     pub fn load(filename: &str) -> anyhow::Result<TracerConfig> {
-        Ok(serde_yaml::from_reader(File::open(filename)?)?)
+        let config: TracerConfig =
+            serde_yaml::from_reader(File::open(filename)?).map_err(SimKubeError::serialization)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    // Pods are always watched separately by the PodWatcher, which records their lifecycle data
+    // against their owners; if pods were also in tracked_objects they'd get recorded a second time
+    // as regular objects, which the driver would then try to create directly during a simulation.
+    pub fn validate(&self) -> EmptyResult {
+        if self.tracked_objects.contains_key(&GVK::new("", "v1", "Pod")) {
+            return Err(SimKubeError::config(
+                "v1.Pod cannot be listed in trackedObjects: pods are handled by the pod watcher; \
+                 to record pod lifecycle data, set trackLifecycle on the pods' owner instead",
+            ));
+        }
+        if self.store_channel_capacity == Some(0) {
+            return Err(SimKubeError::config("storeChannelCapacity must be at least 1"));
+        }
+        self.sensitive_env_regex()?;
+        for obj_config in self.tracked_objects.values() {
+            ObjectTransformer::new(&obj_config.transforms)?;
+        }
+        if self.owners_cache_policy == OwnersCachePolicy::Ttl {
+            match self.owners_cache_ttl_seconds {
+                Some(ttl) if ttl > 0 => (),
+                _ => {
+                    return Err(SimKubeError::config(
+                        "ownersCacheTtlSeconds must be set to a positive number for the ttl owners cache policy",
+                    ))
+                },
+            }
+        }
+        if self.owner_lookup_concurrency == Some(0) {
+            return Err(SimKubeError::config("ownerLookupConcurrency must be at least 1"));
+        }
+        if self.heartbeat_interval_seconds.is_some_and(|i| i <= 0) {
+            return Err(SimKubeError::config("heartbeatIntervalSeconds must be positive"));
+        }
+        Ok(())
     }
 
     pub fn pod_spec_template_path(&self, gvk: &GVK) -> Option<&str> {
         self.tracked_objects.get(gvk)?.pod_spec_template_path.as_deref()
     }
 
+    pub fn owner_lookup_concurrency(&self) -> usize {
+        self.owner_lookup_concurrency.unwrap_or(DEFAULT_OWNER_LOOKUP_CONCURRENCY)
+    }
+
     pub fn track_lifecycle_for(&self, gvk: &GVK) -> bool {
         self.tracked_objects.get(gvk).is_some_and(|obj| obj.track_lifecycle)
     }
+
+    // Env var names are matched anywhere in the name (so "password" matches "DB_PASSWORD"); if
+    // you want to match the whole name, you have to anchor the pattern yourself
+    pub fn sensitive_env_regex(&self) -> anyhow::Result<Regex> {
+        let pattern = self.sensitive_env_pattern.as_deref().unwrap_or(DEFAULT_SENSITIVE_ENV_PATTERN);
+        Regex::new(pattern).map_err(|err| SimKubeError::config(format!("invalid sensitiveEnvPattern {pattern}: {err}")))
+    }
 }
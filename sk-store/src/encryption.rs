@@ -0,0 +1,116 @@
+use std::{
+    env,
+    fs,
+};
+
+use ring::aead::{
+    Aad,
+    LessSafeKey,
+    Nonce,
+    UnboundKey,
+    AES_256_GCM,
+    NONCE_LEN,
+};
+use ring::rand::{
+    SecureRandom,
+    SystemRandom,
+};
+use sk_core::errors::*;
+
+pub const TRACE_KEY_ENV_VAR: &str = "SK_TRACE_KEY";
+
+// Encrypted traces start with this header, followed by the nonce and then the ciphertext (which
+// includes the GCM authentication tag at the end); the header lets us tell encrypted traces apart
+// from regular ones.  Both msgpack and JSON traces can be encrypted (the format is detected again
+// once they're decrypted), and neither one can start with these bytes: msgpack traces start with a
+// map or an array marker, and JSON traces start with a `{`.
+const ENCRYPTED_TRACE_HEADER: &[u8] = b"SKENC1";
+const KEY_LEN: usize = 32;
+
+// A TraceKey is a 256-bit AES-GCM key; keys are given as hex strings (e.g., the output of
+// `openssl rand -hex 32`), either in a file or in the SK_TRACE_KEY environment variable.
+#[derive(Clone)]
+pub struct TraceKey([u8; KEY_LEN]);
+
+impl TraceKey {
+    pub fn from_hex(hex: &str) -> anyhow::Result<TraceKey> {
+        let hex = hex.trim();
+        if hex.len() != KEY_LEN * 2 || !hex.is_ascii() {
+            bail!("trace key must be {} hex characters", KEY_LEN * 2);
+        }
+
+        let mut key = [0; KEY_LEN];
+        for (i, b) in key.iter_mut().enumerate() {
+            *b = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| anyhow!("trace key is not valid hex"))?;
+        }
+        Ok(TraceKey(key))
+    }
+
+    // The key file takes precedence over the environment variable; if neither is set, there's no
+    // key, and traces are read and written unencrypted.
+    pub fn load(key_file: Option<&str>) -> anyhow::Result<Option<TraceKey>> {
+        if let Some(path) = key_file {
+            return Ok(Some(TraceKey::from_hex(&fs::read_to_string(path)?)?));
+        }
+
+        match env::var(TRACE_KEY_ENV_VAR) {
+            Ok(hex) => Ok(Some(TraceKey::from_hex(&hex)?)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    fn aead_key(&self) -> LessSafeKey {
+        // This can only fail if the key is the wrong length, which we've already checked
+        LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &self.0).expect("invalid AES-256-GCM key"))
+    }
+}
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(ENCRYPTED_TRACE_HEADER)
+}
+
+pub fn encrypt_trace(data: &[u8], key: &TraceKey) -> anyhow::Result<Vec<u8>> {
+    let mut nonce = [0; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| anyhow!("could not generate nonce for trace encryption"))?;
+
+    let mut ciphertext = data.to_vec();
+    key.aead_key()
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(ENCRYPTED_TRACE_HEADER),
+            &mut ciphertext,
+        )
+        .map_err(|_| anyhow!("could not encrypt trace"))?;
+
+    Ok([ENCRYPTED_TRACE_HEADER, &nonce, &ciphertext].concat())
+}
+
+// Unencrypted traces are passed through as-is, so callers can just always call this on whatever
+// trace data they loaded; if the trace is encrypted, we need a key to read it.
+pub fn decrypt_trace(data: Vec<u8>, key: Option<&TraceKey>) -> anyhow::Result<Vec<u8>> {
+    if !is_encrypted(&data) {
+        return Ok(data);
+    }
+
+    let Some(key) = key else {
+        bail!("trace is encrypted; a key must be provided (via --key-file or {TRACE_KEY_ENV_VAR}) to read it");
+    };
+
+    let rest = &data[ENCRYPTED_TRACE_HEADER.len()..];
+    if rest.len() < NONCE_LEN {
+        bail!("encrypted trace is truncated");
+    }
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| anyhow!("invalid nonce in encrypted trace"))?;
+
+    let mut plaintext = ciphertext.to_vec();
+    let len = key
+        .aead_key()
+        .open_in_place(nonce, Aad::from(ENCRYPTED_TRACE_HEADER), &mut plaintext)
+        .map_err(|_| anyhow!("could not decrypt trace; the key is wrong or the trace is corrupted"))?
+        .len();
+    plaintext.truncate(len);
+    Ok(plaintext)
+}
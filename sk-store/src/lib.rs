@@ -1,7 +1,14 @@
 mod config;
+pub mod encryption;
 mod pod_owners_map;
+pub mod replay;
+mod scrub;
+mod self_check;
 mod trace_filter;
+mod trace_header;
+mod trace_schema;
 mod trace_store;
+mod transforms;
 pub mod watchers;
 
 use std::collections::VecDeque;
@@ -12,14 +19,34 @@ use serde::{
     Serialize,
 };
 use sk_core::errors::*;
-use sk_core::k8s::PodLifecycleData;
+use sk_core::k8s::{
+    PodEnv,
+    PodLifecycleData,
+    PodProbes,
+    PodReadiness,
+    PodScheduling,
+};
 use sk_core::prelude::*;
 
 pub use crate::config::{
+    BackpressurePolicy,
+    OversizedObjectPolicy,
+    OwnersCachePolicy,
     TracerConfig,
     TrackedObjectConfig,
 };
+pub use crate::pod_owners_map::PodLifecyclesMap;
+pub use crate::scrub::TraceScrubber;
+pub use crate::self_check::ValidationIssue;
+pub use crate::trace_header::{
+    TraceFormat,
+    TraceHeader,
+};
 pub use crate::trace_store::TraceStore;
+pub use crate::transforms::{
+    FieldTransform,
+    TransformOp,
+};
 
 #[cfg(test)]
 mod tests;
@@ -36,6 +63,52 @@ pub struct TraceEvent {
     pub deleted_objs: Vec<DynamicObject>,
 }
 
+// A single recorded version of an object, used for the (optional) per-object history
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ObjectVersion {
+    pub ts: i64,
+    pub obj: DynamicObject,
+}
+
+// A single thing that happened during the trace, as returned by TraceStore::iter_events; pod events
+// are identified by their owner, pod spec hash, and launch order (see pod_owners_map.rs), since
+// that's how we store them.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TimelineEvent<'a> {
+    ObjectApplied {
+        ts: i64,
+        obj: &'a DynamicObject,
+    },
+    ObjectDeleted {
+        ts: i64,
+        obj: &'a DynamicObject,
+    },
+    PodStarted {
+        ts: i64,
+        owner_ns_name: &'a str,
+        pod_hash: u64,
+        seq: usize,
+    },
+    PodFinished {
+        ts: i64,
+        owner_ns_name: &'a str,
+        pod_hash: u64,
+        seq: usize,
+        reason: Option<&'a str>,
+    },
+}
+
+impl<'a> TimelineEvent<'a> {
+    pub fn ts(&self) -> i64 {
+        match self {
+            TimelineEvent::ObjectApplied { ts, .. }
+            | TimelineEvent::ObjectDeleted { ts, .. }
+            | TimelineEvent::PodStarted { ts, .. }
+            | TimelineEvent::PodFinished { ts, .. } => *ts,
+        }
+    }
+}
+
 pub struct TraceIterator<'a> {
     events: &'a VecDeque<TraceEvent>,
     idx: usize,
@@ -53,6 +126,10 @@ pub trait TraceStorable {
         owners: Vec<metav1::OwnerReference>,
         lifecycle_data: &PodLifecycleData,
     ) -> EmptyResult;
+    fn record_pod_readiness(&mut self, ns_name: &str, readiness: PodReadiness) -> bool;
+    fn record_pod_env(&mut self, ns_name: &str, env: PodEnv) -> bool;
+    fn record_pod_scheduling(&mut self, ns_name: &str, scheduling: PodScheduling) -> bool;
+    fn record_pod_probes(&mut self, ns_name: &str, probes: PodProbes) -> bool;
     fn config(&self) -> &TracerConfig;
     fn has_obj(&self, ns_name: &str) -> bool;
     fn start_ts(&self) -> Option<i64>;
@@ -81,6 +158,10 @@ pub mod mock {
                 owners: Vec<metav1::OwnerReference>,
                 lifecycle_data: &PodLifecycleData,
             ) -> EmptyResult;
+            fn record_pod_readiness(&mut self, ns_name: &str, readiness: PodReadiness) -> bool;
+            fn record_pod_env(&mut self, ns_name: &str, env: PodEnv) -> bool;
+            fn record_pod_scheduling(&mut self, ns_name: &str, scheduling: PodScheduling) -> bool;
+            fn record_pod_probes(&mut self, ns_name: &str, probes: PodProbes) -> bool;
             fn config(&self) -> &TracerConfig;
             fn has_obj(&self, ns_name: &str) -> bool;
             fn start_ts(&self) -> Option<i64>;
@@ -89,6 +170,3 @@ pub mod mock {
         }
     }
 }
-
-#[cfg(feature = "testutils")]
-pub use crate::pod_owners_map::PodLifecyclesMap;
@@ -1,8 +1,22 @@
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::mem::take;
 
+use serde::{
+    Deserialize,
+    Serialize,
+};
 use sk_core::errors::*;
-use sk_core::k8s::PodLifecycleData;
+use sk_core::k8s::{
+    PodEnv,
+    PodEvent,
+    PodLifecycleData,
+    PodOrdinal,
+    PodProbes,
+    PodReadiness,
+    PodScheduling,
+    PodUsage,
+};
 use sk_core::prelude::*;
 
 // The PodOwnersMap tracks lifecycle data for all pods that are owned by some object that we care
@@ -43,8 +57,15 @@ use sk_core::prelude::*;
 // order.  I am not sure if there's any actual improvements to be had here, though.
 
 pub type PodLifecyclesMap = HashMap<u64, Vec<PodLifecycleData>>;
-
-#[derive(Default)]
+pub type PodUsageMap = HashMap<u64, Vec<PodUsage>>;
+pub type PodReadinessMap = HashMap<u64, Vec<PodReadiness>>;
+pub type PodEventsMap = HashMap<u64, Vec<PodEvent>>;
+pub type PodEnvMap = HashMap<u64, PodEnv>;
+pub type PodOrdinalsMap = HashMap<u64, Vec<PodOrdinal>>;
+pub type PodSchedulingMap = HashMap<u64, PodScheduling>;
+pub type PodProbesMap = HashMap<u64, PodProbes>;
+
+#[derive(Default, Deserialize, Serialize)]
 pub(crate) struct PodOwnersMap {
     m: HashMap<String, PodLifecyclesMap>,
     index: HashMap<String, (String, u64, usize)>,
@@ -62,6 +83,36 @@ impl PodOwnersMap {
         self.index.contains_key(ns_name)
     }
 
+    pub(crate) fn pod_owner_meta(&self, ns_name: &str) -> Option<&(String, u64, usize)> {
+        self.index.get(ns_name)
+    }
+
+    pub(crate) fn lifecycles(&self) -> &HashMap<String, PodLifecyclesMap> {
+        &self.m
+    }
+
+    // Every pod gets exactly one entry in the lifecycle data; the index would be cheaper to count,
+    // but it doesn't get exported, so it's empty for imported traces.
+    pub(crate) fn pod_count(&self) -> usize {
+        self.m.values().flat_map(|l| l.values()).map(|v| v.len()).sum()
+    }
+
+    // Rename all of the pods and owning objects in the map (e.g., when scrubbing a trace); the
+    // rename function needs to be consistent so that the index still points at the right owners.
+    pub(crate) fn rename(&mut self, mut rename_fn: impl FnMut(&str) -> String) {
+        self.m = take(&mut self.m).into_iter().map(|(owner, l)| (rename_fn(&owner), l)).collect();
+        self.index = take(&mut self.index)
+            .into_iter()
+            .map(|(pod, (owner, hash, idx))| (rename_fn(&pod), (rename_fn(&owner), hash, idx)))
+            .collect();
+    }
+
+    pub(crate) fn shift_timestamps(&mut self, delta: i64) {
+        for lifecycle in self.m.values_mut().flat_map(|l| l.values_mut()).flatten() {
+            lifecycle.shift(delta);
+        }
+    }
+
     pub(crate) fn lifecycle_data_for<'a>(
         &'a self,
         owner_ns_name: &str,
@@ -167,9 +218,12 @@ pub(crate) fn filter_lifecycles_map(
     Some(filtered_map)
 }
 
-#[cfg(test)]
-impl PodOwnersMap {
-    pub(crate) fn pod_owner_meta(&self, ns_name: &str) -> Option<&(String, u64, usize)> {
-        self.index.get(ns_name)
+// Pods that are still running at the end of the export window don't have an end timestamp; if the
+// caller wants a fixed horizon, we mark them as finished at the end of the window instead.
+pub(crate) fn close_open_intervals(lifecycle_data: &mut HashMap<String, PodLifecyclesMap>, end_ts: i64) {
+    for lifecycle in lifecycle_data.values_mut().flat_map(|m| m.values_mut()).flatten() {
+        if let PodLifecycleData::Running(start_ts) = *lifecycle {
+            *lifecycle = PodLifecycleData::Finished(start_ts, end_ts, None);
+        }
     }
 }
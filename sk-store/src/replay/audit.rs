@@ -0,0 +1,98 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{
+    BufWriter,
+    Write,
+};
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sk_core::k8s::GVK;
+use sk_core::prelude::*;
+
+// Server-side apply doesn't tell us whether the object was new, so "created" means this is the
+// first time the replay has applied the object (or the first time since it was deleted); objects
+// that were already in the cluster before the replay started are also reported as created.
+// "Skipped" only happens in a dry run, for objects in a namespace that doesn't actually exist (see
+// replay_trace).
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApplyResult {
+    Created,
+    Updated,
+    Failed,
+    Skipped,
+}
+
+// One object getting applied during a replay; `ts` is the (wall-clock) time of the apply, and
+// `trace_ts` is the timestamp of the event in the trace that it came from.  The name is the
+// namespaced name of the object that was actually applied, i.e., after namespace remapping.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplyRecord {
+    pub ts: i64,
+    pub trace_ts: i64,
+    pub gvk: GVK,
+    pub name: String,
+    pub result: ApplyResult,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// Gets called once for every object that the replay applies, whether or not the apply succeeded;
+// observers can't affect the replay, so anything that goes wrong in here should just be logged.
+pub trait ApplyObserver: Send + Sync {
+    fn on_apply(&self, record: &ApplyRecord);
+
+    // The replay stopped at a breakpoint (see pacing.rs), and is waiting to be resumed
+    fn on_pause(&self, _breakpoint: i64) {}
+}
+
+// So that ReplayOptions can still derive Debug
+impl fmt::Debug for dyn ApplyObserver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ApplyObserver")
+    }
+}
+
+pub struct NoopObserver;
+
+impl ApplyObserver for NoopObserver {
+    fn on_apply(&self, _: &ApplyRecord) {}
+}
+
+// Writes one JSON object per line for every apply; each line is flushed as soon as it's written,
+// so the log can be tailed (and lines up with what's happening in the cluster) while the replay
+// is running.
+pub struct JsonlAuditSink {
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl JsonlAuditSink {
+    pub fn create(path: &Path) -> anyhow::Result<JsonlAuditSink> {
+        Ok(JsonlAuditSink {
+            writer: Mutex::new(BufWriter::new(File::create(path)?)),
+        })
+    }
+
+    fn write_record(&self, record: &ApplyRecord) -> EmptyResult {
+        let mut writer = self.writer.lock().unwrap();
+        serde_json::to_writer(&mut *writer, record)?;
+        writeln!(writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl ApplyObserver for JsonlAuditSink {
+    fn on_apply(&self, record: &ApplyRecord) {
+        if let Err(err) = self.write_record(record) {
+            warn!("could not write audit record for {}: {err}", record.name);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::Hasher;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sk_core::errors::*;
+
+use crate::TraceStorable;
+
+// Where a replay has gotten to: the index and trace timestamp of the next event to apply.  This
+// gets written out after every event, so if the replay is interrupted, it can pick up where it left
+// off instead of starting over.  The event index only means something for the trace that the
+// checkpoint was written for, so we also keep a hash of that trace (see trace_hash) and refuse to
+// resume a different one.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayCheckpoint {
+    pub trace_hash: u64,
+    pub next_event: usize,
+    pub sim_ts: i64,
+}
+
+impl ReplayCheckpoint {
+    // Returns None if there's no checkpoint at the path yet, i.e., this is a fresh replay
+    pub fn load(path: &Path) -> anyhow::Result<Option<ReplayCheckpoint>> {
+        match fs::read(path) {
+            Ok(data) => Ok(Some(serde_json::from_slice(&data).map_err(SimKubeError::serialization)?)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    // Write to a temp file and then move it into place, so that if we crash partway through a
+    // write, the previous checkpoint is still there
+    pub fn save(&self, path: &Path) -> EmptyResult {
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec(self)?)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    // Once the replay is done, there's nothing left to resume; if we left the checkpoint around,
+    // running the same command again would skip the whole trace
+    pub fn remove(path: &Path) -> EmptyResult {
+        match fs::remove_file(path) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+// This covers everything that gets replayed (i.e., after the trace has been cut down to --duration,
+// if it was), so if anything about the events changes, the hash does too
+pub fn trace_hash(store: &(dyn TraceStorable + Send + Sync)) -> anyhow::Result<u64> {
+    let mut hasher = DefaultHasher::new();
+    for (evt, _) in store.iter() {
+        hasher.write(&serde_json::to_vec(evt)?);
+    }
+    Ok(hasher.finish())
+}
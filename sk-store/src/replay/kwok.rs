@@ -0,0 +1,101 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use kube::api::{
+    DynamicObject,
+    ListParams,
+    PostParams,
+};
+use serde_json::{
+    json,
+    Value,
+};
+use sk_core::errors::SimKubeError;
+use sk_core::jsonutils;
+use sk_core::prelude::*;
+
+const KWOK_NODE_ANNOTATION_KEY: &str = "kwok.x-k8s.io/node";
+const KWOK_NODE_PREFIX: &str = "sk-kwok-node";
+const VIRTUAL_NODE_SELECTOR: &str = "type=virtual";
+
+// KWOK pretends to be the kubelet for any node that it manages, so pods that get scheduled onto
+// those nodes go straight to Running without anything actually being started.  This is the same
+// node selector and toleration that the simulation driver's mutating webhook adds to every
+// simulated pod; here we put them directly on the pod template of each object we replay, since
+// there isn't a webhook in between.
+pub fn target_kwok_nodes(vobj: &mut DynamicObject, pod_spec_template_path: &str) -> EmptyResult {
+    let spec_path = format!("{pod_spec_template_path}/spec");
+    jsonutils::patch_ext::add(&spec_path, "nodeSelector", &json!({}), &mut vobj.data, false)?;
+    jsonutils::patch_ext::add(&format!("{spec_path}/nodeSelector"), "type", &json!("virtual"), &mut vobj.data, true)?;
+    jsonutils::patch_ext::add(&spec_path, "tolerations", &json!([]), &mut vobj.data, false)?;
+
+    let toleration = json!({"key": VIRTUAL_NODE_TOLERATION_KEY, "operator": "Exists", "effect": "NoSchedule"});
+    if let Some(tolerations) = vobj
+        .data
+        .pointer_mut(&format!("{spec_path}/tolerations"))
+        .and_then(Value::as_array_mut)
+    {
+        if !tolerations.contains(&toleration) {
+            tolerations.push(toleration);
+        }
+    }
+    Ok(())
+}
+
+// If there aren't (at least) `count` KWOK-managed nodes in the cluster already, create stubs to
+// make up the difference; otherwise all of the replayed pods would just sit in Pending.  The stubs
+// are big enough that you don't need very many of them, but they aren't meant to look like any
+// real instance type, so if you care about bin-packing, create your own nodes instead.
+pub async fn ensure_kwok_nodes(
+    node_api: &kube::Api<corev1::Node>,
+    count: usize,
+    labels: &BTreeMap<String, String>,
+    post_params: &PostParams,
+) -> EmptyResult {
+    let existing = node_api
+        .list(&ListParams::default().labels(VIRTUAL_NODE_SELECTOR))
+        .await
+        .map_err(SimKubeError::api)?
+        .items
+        .len();
+    for i in existing..count {
+        let node = build_kwok_node(&format!("{KWOK_NODE_PREFIX}-{i}"), labels);
+        info!("creating KWOK node {}", node.metadata.name.as_deref().unwrap_or_default());
+        node_api.create(post_params, &node).await.map_err(SimKubeError::api)?;
+    }
+    Ok(())
+}
+
+pub fn build_kwok_node(name: &str, labels: &BTreeMap<String, String>) -> corev1::Node {
+    let mut node_labels = labels.clone();
+    node_labels.insert("type".into(), "virtual".into());
+    let resources: BTreeMap<_, _> = [
+        ("cpu".into(), Quantity("64".into())),
+        ("memory".into(), Quantity("256Gi".into())),
+        ("pods".into(), Quantity("250".into())),
+    ]
+    .into();
+
+    corev1::Node {
+        metadata: metav1::ObjectMeta {
+            name: Some(name.into()),
+            annotations: Some([(KWOK_NODE_ANNOTATION_KEY.into(), "fake".into())].into()),
+            labels: Some(node_labels),
+            ..Default::default()
+        },
+        spec: Some(corev1::NodeSpec {
+            taints: Some(vec![corev1::Taint {
+                key: VIRTUAL_NODE_TOLERATION_KEY.into(),
+                value: Some("true".into()),
+                effect: "NoSchedule".into(),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        }),
+        status: Some(corev1::NodeStatus {
+            allocatable: Some(resources.clone()),
+            capacity: Some(resources),
+            ..Default::default()
+        }),
+    }
+}
@@ -0,0 +1,350 @@
+mod audit;
+mod checkpoint;
+mod kwok;
+mod mutation;
+mod ordering;
+mod pacing;
+mod remap;
+
+use std::collections::{
+    BTreeMap,
+    HashSet,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{
+    anyhow,
+    bail,
+};
+use clockabilly::{
+    Clockable,
+    UtcClock,
+};
+use kube::api::{
+    DeleteParams,
+    DynamicObject,
+    Patch,
+    PatchParams,
+    PostParams,
+};
+use kube::ResourceExt;
+use sk_core::errors::SimKubeError;
+use sk_core::k8s::{
+    ApiSet,
+    KubeResourceExt,
+    GVK,
+};
+use sk_core::prelude::*;
+use tokio::sync::Notify;
+use tokio::time::sleep;
+
+pub use self::audit::{
+    ApplyObserver,
+    ApplyRecord,
+    ApplyResult,
+    JsonlAuditSink,
+    NoopObserver,
+};
+pub use self::checkpoint::{
+    trace_hash,
+    ReplayCheckpoint,
+};
+pub use self::kwok::{
+    build_kwok_node,
+    ensure_kwok_nodes,
+    target_kwok_nodes,
+};
+pub use self::mutation::{
+    apply_mutations,
+    load_mutation_rules,
+    MutationRule,
+};
+pub use self::ordering::{
+    build_apply_plan,
+    find_dependencies,
+};
+pub(crate) use self::ordering::{
+    obj_key,
+    remove_dependency,
+};
+pub use self::pacing::ReplayPacing;
+use self::pacing::{
+    Breakpoints,
+    Pacer,
+};
+pub use self::remap::{
+    parse_remap,
+    NamespaceMap,
+};
+use crate::TraceStorable;
+
+// A ReplayTarget decides where (and how) the objects in a trace get created: the simulation driver
+// moves everything into virtual namespaces that are owned by the simulation root, whereas `skctl
+// replay` just applies the objects to the cluster (modulo any namespace remapping).
+pub trait ReplayTarget {
+    // The namespace that objects from `ns` in the trace should be created in
+    fn namespace_for(&self, ns: &str) -> String;
+
+    fn build_namespace(&self, name: &str) -> corev1::Namespace;
+
+    fn build_obj(&self, obj: &DynamicObject, ns: Option<&str>) -> anyhow::Result<DynamicObject>;
+}
+
+#[derive(Clone, Debug)]
+pub struct ReplayOptions {
+    pub speed: f64,
+    pub pacing: ReplayPacing,
+
+    // The longest we'll wait in between two events, in seconds (see pacing.rs)
+    pub max_sleep: Option<i64>,
+    pub dry_run: bool,
+
+    // Added to every object that gets applied (overwriting any existing value), so that everything
+    // created by the replay can be found (and cleaned up) afterwards with a label selector
+    pub labels: BTreeMap<String, String>,
+
+    // JSON patches that are applied to (matching) objects right before they're created
+    pub mutations: Vec<MutationRule>,
+
+    // If set, progress is saved here after every event, and if there's already a checkpoint here
+    // when the replay starts, we resume from it (see checkpoint.rs)
+    pub checkpoint_path: Option<PathBuf>,
+
+    // Schedule all the replayed pods onto KWOK-managed nodes (see kwok.rs), and make sure there are
+    // at least `kwok_nodes` of them
+    pub kwok: bool,
+    pub kwok_nodes: usize,
+
+    // Told about every object that gets applied, e.g., to write an audit log (see audit.rs)
+    pub observer: Arc<dyn ApplyObserver>,
+
+    // Trace timestamps to pause the replay at (see pacing.rs); the replay waits at each one until
+    // `resume` is notified.  A notification that comes in before the replay gets to a breakpoint
+    // isn't lost, it just means the replay won't stop there.
+    pub breakpoints: Vec<i64>,
+    pub resume: Arc<Notify>,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> ReplayOptions {
+        ReplayOptions {
+            speed: 1.0,
+            pacing: ReplayPacing::Speed,
+            max_sleep: None,
+            dry_run: false,
+            labels: BTreeMap::new(),
+            mutations: vec![],
+            checkpoint_path: None,
+            kwok: false,
+            kwok_nodes: 0,
+            observer: Arc::new(NoopObserver),
+            breakpoints: vec![],
+            resume: Arc::new(Notify::new()),
+        }
+    }
+}
+
+// Replay all of the events in the trace against the cluster, waiting in between events so that
+// the replay takes (trace duration / speed) seconds, or so that the events fire with the same gaps
+// in between them as they had in the trace, depending on the pacing.  In a dry run, all of the
+// requests are sent with dryRun set, and we don't bother waiting in between events (or saving
+// checkpoints), though we do still stop at breakpoints.
+//
+// When resuming from a checkpoint, the events before the checkpoint are skipped; the replay may
+// have been interrupted partway through the next event, so some of its objects might already exist
+// (in which case the apply just adopts them) or already be deleted (which we ignore).
+pub async fn replay_trace(
+    client: kube::Client,
+    store: &(dyn TraceStorable + Send + Sync),
+    target: &(impl ReplayTarget + Sync),
+    opts: &ReplayOptions,
+) -> EmptyResult {
+    let ns_api: kube::Api<corev1::Namespace> = kube::Api::all(client.clone());
+    let node_api: kube::Api<corev1::Node> = kube::Api::all(client.clone());
+    let mut apiset = ApiSet::new(client);
+
+    let mut sim_ts = store.start_ts().ok_or(anyhow!("no trace data"))?;
+
+    let post_params = PostParams { dry_run: opts.dry_run, ..Default::default() };
+    let mut patch_params = PatchParams::apply("simkube");
+    patch_params.dry_run = opts.dry_run;
+    let delete_params = DeleteParams { dry_run: opts.dry_run, ..Default::default() };
+
+    // Work out the creation order up front, so that objects are created after the things they
+    // depend on (see ordering.rs for details)
+    let apply_plan = build_apply_plan(store.iter().map(|(evt, _)| evt));
+    let mut pacer = Pacer::new(opts.pacing, opts.speed, opts.max_sleep, UtcClock::boxed());
+    let mut breakpoints = Breakpoints::new(&opts.breakpoints);
+    let clock = UtcClock;
+
+    // Everything we've applied (and not deleted since), so we can tell the observer whether an
+    // apply created or updated the object
+    let mut live = HashSet::new();
+
+    // In a dry run, namespaces that don't exist yet are only created as a dry run too, so the
+    // apiserver would reject everything we tried to put in them; we skip those objects instead of
+    // reporting a bunch of bogus failures
+    let mut dry_run_namespaces = HashSet::new();
+
+    if opts.kwok_nodes > 0 {
+        ensure_kwok_nodes(&node_api, opts.kwok_nodes, &opts.labels, &post_params).await?;
+    }
+
+    let checkpoint_path = opts.checkpoint_path.as_deref().filter(|_| !opts.dry_run);
+    let hash = checkpoint_path.map(|_| trace_hash(store)).transpose()?.unwrap_or_default();
+    let mut start_event = 0;
+    if let Some(path) = checkpoint_path {
+        if let Some(checkpoint) = ReplayCheckpoint::load(path)? {
+            if checkpoint.trace_hash != hash {
+                bail!(
+                    "checkpoint {} was written for a different trace; delete it (or use a different checkpoint file) \
+                    to replay this trace from the start",
+                    path.display()
+                );
+            }
+            info!("resuming replay from event {} (sim ts = {})", checkpoint.next_event, checkpoint.sim_ts);
+            start_event = checkpoint.next_event;
+            sim_ts = checkpoint.sim_ts;
+        }
+    }
+
+    let events = store.iter().zip(apply_plan).enumerate().skip(start_event);
+    for (i, ((evt, maybe_next_ts), applied_objs)) in events {
+        let resumed = i == start_event && start_event > 0;
+
+        if let Some(breakpoint) = breakpoints.hit(evt.ts) {
+            info!("paused at breakpoint (sim ts = {breakpoint}, next event at {})", evt.ts);
+            opts.observer.on_pause(breakpoint);
+            let paused_ts = clock.now_ts();
+            opts.resume.notified().await;
+            info!("resuming replay");
+            pacer.paused(clock.now_ts() - paused_ts);
+        }
+
+        // Cluster-scoped objects (e.g., ClusterRoles or PriorityClasses) don't have a namespace,
+        // so they don't get moved anywhere.
+        for obj in &applied_objs {
+            let ns = obj.namespace().map(|ns| target.namespace_for(&ns));
+
+            if let Some(ns) = &ns {
+                if !dry_run_namespaces.contains(ns) && ns_api.get_opt(ns).await.map_err(SimKubeError::api)?.is_none() {
+                    info!("creating namespace: {ns}");
+                    ns_api
+                        .create(&post_params, &target.build_namespace(ns))
+                        .await
+                        .map_err(SimKubeError::api)?;
+                    if opts.dry_run {
+                        dry_run_namespaces.insert(ns.clone());
+                    }
+                }
+            }
+
+            let gvk = GVK::from_dynamic_obj(obj)?;
+            let mut vobj = target.build_obj(obj, ns.as_deref())?;
+            vobj.labels_mut().extend(opts.labels.clone());
+            if opts.kwok {
+                if let Some(pod_spec_template_path) = store.config().pod_spec_template_path(&gvk) {
+                    target_kwok_nodes(&mut vobj, pod_spec_template_path)?;
+                }
+            }
+            apply_mutations(&opts.mutations, obj, &mut vobj)?;
+
+            let ns_name = vobj.namespaced_name();
+            if ns.as_ref().is_some_and(|ns| dry_run_namespaces.contains(ns)) {
+                info!("skipping object {ns_name}, its namespace was only created as a dry run");
+                opts.observer.on_apply(&ApplyRecord {
+                    ts: clock.now_ts(),
+                    trace_ts: evt.ts,
+                    gvk,
+                    name: ns_name,
+                    result: ApplyResult::Skipped,
+                    error: None,
+                });
+                continue;
+            }
+
+            info!("applying object {ns_name}");
+            let res = match apiset.api_for_obj(&vobj).await {
+                Ok(api) => api
+                    .patch(&vobj.name_any(), &patch_params, &Patch::Apply(&vobj))
+                    .await
+                    .map_err(SimKubeError::api),
+                Err(err) => Err(err),
+            };
+
+            let result = match &res {
+                Ok(_) if live.insert((gvk.clone(), ns_name.clone())) => ApplyResult::Created,
+                Ok(_) => ApplyResult::Updated,
+                Err(_) => ApplyResult::Failed,
+            };
+            opts.observer.on_apply(&ApplyRecord {
+                ts: clock.now_ts(),
+                trace_ts: evt.ts,
+                gvk,
+                name: ns_name,
+                result,
+                error: res.as_ref().err().map(|err| err.to_string()),
+            });
+            res?;
+        }
+
+        for obj in &evt.deleted_objs {
+            let mut vobj = obj.clone();
+            vobj.metadata.namespace = obj.namespace().map(|ns| target.namespace_for(&ns));
+            if vobj
+                .metadata
+                .namespace
+                .as_ref()
+                .is_some_and(|ns| dry_run_namespaces.contains(ns))
+            {
+                info!("skipping delete of {}, its namespace was only created as a dry run", obj.namespaced_name());
+                continue;
+            }
+
+            info!("deleting object {}", obj.namespaced_name());
+            if let Ok(gvk) = GVK::from_dynamic_obj(obj) {
+                live.remove(&(gvk, vobj.namespaced_name()));
+            }
+            match apiset.api_for_obj(&vobj).await?.delete(&obj.name_any(), &delete_params).await {
+                Err(kube::Error::Api(kube::core::ErrorResponse { code: 404, .. })) if resumed => {
+                    info!("object {} was already deleted", obj.namespaced_name());
+                },
+                res => {
+                    res.map_err(SimKubeError::api)?;
+                },
+            }
+        }
+
+        if let Some(path) = checkpoint_path {
+            let checkpoint = ReplayCheckpoint {
+                trace_hash: hash,
+                next_event: i + 1,
+                sim_ts: maybe_next_ts.unwrap_or(evt.ts),
+            };
+            checkpoint.save(path)?;
+        }
+
+        if let Some(next_ts) = maybe_next_ts {
+            if opts.dry_run {
+                continue;
+            }
+
+            let sleep_duration = pacer.delay(sim_ts, next_ts);
+
+            info!("next event happens in {} seconds, sleeping", sleep_duration.as_secs());
+            debug!("current sim ts = {sim_ts}, next sim ts = {next_ts}");
+
+            sim_ts = next_ts;
+            sleep(sleep_duration).await;
+        }
+    }
+
+    if let Some(path) = checkpoint_path {
+        ReplayCheckpoint::remove(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
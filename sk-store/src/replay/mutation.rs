@@ -0,0 +1,80 @@
+use anyhow::anyhow;
+use kube::api::DynamicObject;
+use regex::Regex;
+use serde::Deserialize;
+use sk_core::jsonutils::patch_ext;
+use sk_core::k8s::{
+    KubeResourceExt,
+    GVK,
+};
+use sk_core::prelude::*;
+
+// A MutationRule transforms objects as they're replayed, e.g., to scale a Deployment down or to
+// strip resource limits off of its pods.  Rules are scoped to a single GVK and (optionally) to the
+// objects whose namespaced name in the trace matches a regex, and the patch is a normal RFC 6902
+// JSON patch, which is applied to the whole object (so paths look like `/spec/replicas`).
+//
+// Rules are usually loaded from a YAML file:
+//
+// ```yaml
+// - gvk: apps/v1.Deployment
+//   name: prod/.*
+//   patch:
+//     - op: replace
+//       path: /spec/replicas
+//       value: 1
+// ```
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "MutationRuleSpec")]
+pub struct MutationRule {
+    pub gvk: GVK,
+    pub name: Option<Regex>,
+    pub patch: json_patch::Patch,
+}
+
+#[derive(Deserialize)]
+struct MutationRuleSpec {
+    gvk: GVK,
+    name: Option<String>,
+    patch: json_patch::Patch,
+}
+
+impl TryFrom<MutationRuleSpec> for MutationRule {
+    type Error = regex::Error;
+
+    // The name pattern has to match the whole namespaced name, not just some part of it
+    fn try_from(spec: MutationRuleSpec) -> Result<MutationRule, regex::Error> {
+        let name = spec.name.map(|n| Regex::new(&format!("^(?:{n})$"))).transpose()?;
+        Ok(MutationRule { gvk: spec.gvk, name, patch: spec.patch })
+    }
+}
+
+impl MutationRule {
+    pub fn matches(&self, obj: &DynamicObject) -> bool {
+        if !GVK::from_dynamic_obj(obj).is_ok_and(|gvk| gvk == self.gvk) {
+            return false;
+        }
+
+        match &self.name {
+            Some(re) => re.is_match(&obj.namespaced_name()),
+            None => true,
+        }
+    }
+}
+
+pub fn load_mutation_rules(contents: &str) -> anyhow::Result<Vec<MutationRule>> {
+    serde_yaml::from_str(contents).map_err(|err| anyhow!("invalid mutation rules: {err}"))
+}
+
+// Rules are matched against the object as it was recorded in the trace (i.e., before it's been
+// moved into a different namespace), and are applied to the object we're about to create in the
+// order they're given.  A patch that doesn't apply cleanly (e.g., because it references a path that
+// doesn't exist in the object) is an error, rather than being silently skipped.
+pub fn apply_mutations(rules: &[MutationRule], obj: &DynamicObject, vobj: &mut DynamicObject) -> EmptyResult {
+    for (i, rule) in rules.iter().enumerate().filter(|(_, rule)| rule.matches(obj)) {
+        patch_ext::apply(&rule.patch, vobj).map_err(|err| {
+            anyhow!("could not apply mutation rule {i} ({}) to {}: {err}", rule.gvk, obj.namespaced_name())
+        })?;
+    }
+    Ok(())
+}
@@ -0,0 +1,298 @@
+use std::cmp::Reverse;
+use std::collections::{
+    BinaryHeap,
+    HashMap,
+    HashSet,
+};
+
+use kube::api::DynamicObject;
+use kube::ResourceExt;
+use serde_json::Value;
+use sk_core::prelude::*;
+
+use crate::TraceEvent;
+
+// Fields in an object spec that reference some other object in the same namespace by name; the
+// value is the kind of the referenced object and the key inside the field that holds the name.
+const NAMED_REFS: [(&str, &str, &str); 6] = [
+    ("configMap", "ConfigMap", "name"),
+    ("configMapRef", "ConfigMap", "name"),
+    ("configMapKeyRef", "ConfigMap", "name"),
+    ("secret", "Secret", "secretName"),
+    ("secretRef", "Secret", "name"),
+    ("secretKeyRef", "Secret", "name"),
+];
+
+// Fields that reference a cluster-scoped object by name
+const CLUSTER_NAMED_REFS: [(&str, &str); 4] = [
+    ("priorityClassName", "PriorityClass"),
+    ("runtimeClassName", "RuntimeClass"),
+    ("storageClassName", "StorageClass"),
+    ("ingressClassName", "IngressClass"),
+];
+
+// Namespace-wide admission policies: if a pod gets created before these are in place, it's not
+// subject to the quota (or doesn't pick up the default requests/limits), so they need to go first.
+const NAMESPACE_POLICY_KINDS: [&str; 2] = ["ResourceQuota", "LimitRange"];
+
+const DEFAULT_SERVICE_ACCOUNT: &str = "default";
+
+fn dep_key(kind: &str, ns: Option<&str>, name: &str) -> String {
+    match ns {
+        Some(ns) => format!("{kind}:{ns}/{name}"),
+        None => format!("{kind}:{name}"),
+    }
+}
+
+pub(crate) fn obj_key(obj: &DynamicObject) -> String {
+    let kind = obj.types.as_ref().map(|t| t.kind.as_str()).unwrap_or_default();
+    dep_key(kind, obj.namespace().as_deref(), &obj.name_any())
+}
+
+fn collect_refs(val: &Value, ns: Option<&str>, refs: &mut Vec<String>) {
+    match val {
+        Value::Object(map) => {
+            for (field, child) in map {
+                if let Some((_, kind, name_field)) = NAMED_REFS.iter().find(|(f, ..)| f == field) {
+                    if let Some(name) = child.get(name_field).and_then(Value::as_str) {
+                        refs.push(dep_key(kind, ns, name));
+                    }
+                } else if let Some((_, kind)) = CLUSTER_NAMED_REFS.iter().find(|(f, _)| f == field) {
+                    if let Some(name) = child.as_str() {
+                        refs.push(dep_key(kind, None, name));
+                    }
+                } else if field == "roleRef" {
+                    // RoleBindings can point at either a Role (in the same namespace) or a ClusterRole
+                    let kind = child.get("kind").and_then(Value::as_str);
+                    if let (Some(kind), Some(name)) = (kind, child.get("name").and_then(Value::as_str)) {
+                        refs.push(dep_key(kind, if kind == "ClusterRole" { None } else { ns }, name));
+                    }
+                } else if field == "serviceAccountName" {
+                    if let Some(name) = child.as_str().filter(|n| *n != DEFAULT_SERVICE_ACCOUNT) {
+                        refs.push(dep_key("ServiceAccount", ns, name));
+                    }
+                } else if field == "imagePullSecrets" {
+                    for name in child.as_array().into_iter().flatten().filter_map(|s| s.get("name")) {
+                        if let Some(name) = name.as_str() {
+                            refs.push(dep_key("Secret", ns, name));
+                        }
+                    }
+                }
+                collect_refs(child, ns, refs);
+            }
+        },
+        Value::Array(items) => items.iter().for_each(|item| collect_refs(item, ns, refs)),
+        _ => (),
+    }
+}
+
+// Return the keys of all the objects that this object depends on, i.e., things that need to exist
+// before it's created: its owners, plus any ConfigMaps, Secrets, or ServiceAccounts it mentions,
+// and cluster-scoped things like PriorityClasses or (via a roleRef) ClusterRoles.
+pub fn find_dependencies(obj: &DynamicObject) -> Vec<String> {
+    let ns = obj.namespace();
+    let mut deps: Vec<_> = obj
+        .owner_references()
+        .iter()
+        .map(|owner| dep_key(&owner.kind, ns.as_deref(), &owner.name))
+        .collect();
+    collect_refs(&obj.data, ns.as_deref(), &mut deps);
+
+    let mut seen = HashSet::new();
+    deps.retain(|d| seen.insert(d.clone()));
+    deps
+}
+
+// The reverse of find_dependencies: get rid of any references from this object to `dep`.  Owner
+// references and the cluster-scoped/ServiceAccount/image pull secret fields can just be deleted,
+// but ConfigMap and Secret references are usually buried in a volume or an env var that something
+// else refers to, so instead we mark them optional, and the pod will start without them.  A
+// RoleBinding is useless without its roleRef (and the apiserver won't accept one anyways), so if
+// that's what points at `dep`, we return false and leave it alone.
+pub(crate) fn remove_dependency(obj: &mut DynamicObject, dep: &str) -> bool {
+    let ns = obj.namespace();
+    if let Some(owners) = obj.metadata.owner_references.as_mut() {
+        owners.retain(|owner| dep_key(&owner.kind, ns.as_deref(), &owner.name) != dep);
+        if owners.is_empty() {
+            obj.metadata.owner_references = None;
+        }
+    }
+    remove_refs(&mut obj.data, ns.as_deref(), dep)
+}
+
+fn remove_refs(val: &mut Value, ns: Option<&str>, dep: &str) -> bool {
+    let mut removable = true;
+    match val {
+        Value::Object(map) => {
+            let mut to_remove = vec![];
+            for (field, child) in map.iter_mut() {
+                if let Some((_, kind, name_field)) = NAMED_REFS.iter().find(|(f, ..)| f == field) {
+                    let name = child.get(name_field).and_then(Value::as_str);
+                    if name.is_some_and(|name| dep_key(kind, ns, name) == dep) {
+                        child["optional"] = Value::Bool(true);
+                    }
+                } else if let Some((_, kind)) = CLUSTER_NAMED_REFS.iter().find(|(f, _)| f == field) {
+                    if child.as_str().is_some_and(|name| dep_key(kind, None, name) == dep) {
+                        to_remove.push(field.clone());
+                    }
+                } else if field == "roleRef" {
+                    let kind = child.get("kind").and_then(Value::as_str);
+                    if let (Some(kind), Some(name)) = (kind, child.get("name").and_then(Value::as_str)) {
+                        removable &= dep_key(kind, if kind == "ClusterRole" { None } else { ns }, name) != dep;
+                    }
+                } else if field == "serviceAccountName" {
+                    if child.as_str().is_some_and(|name| dep_key("ServiceAccount", ns, name) == dep) {
+                        to_remove.push(field.clone());
+                    }
+                } else if field == "imagePullSecrets" {
+                    if let Some(secrets) = child.as_array_mut() {
+                        secrets.retain(|s| {
+                            let name = s.get("name").and_then(Value::as_str);
+                            name.map(|name| dep_key("Secret", ns, name)).as_deref() != Some(dep)
+                        });
+                    }
+                }
+                removable &= remove_refs(child, ns, dep);
+            }
+            for field in to_remove {
+                map.remove(&field);
+            }
+        },
+        Value::Array(items) => {
+            for item in items {
+                removable &= remove_refs(item, ns, dep);
+            }
+        },
+        _ => (),
+    }
+    removable
+}
+
+// Compute the list of objects to apply for each event in the trace.  Within an event, objects are
+// sorted so that dependencies get created before the things that depend on them; if a dependency
+// doesn't show up until a later event, we pull it forward into the event that needs it (the later
+// apply is left alone, since applying the same object twice is harmless).  Anything we can't sort
+// out (cycles, or references to objects that aren't in the trace at all) falls back to the order
+// it was recorded in, which is the timestamp order.
+pub fn build_apply_plan<'a>(events: impl IntoIterator<Item = &'a TraceEvent>) -> Vec<Vec<DynamicObject>> {
+    let events: Vec<_> = events.into_iter().collect();
+
+    let mut first_applied: HashMap<String, Vec<(usize, &DynamicObject)>> = HashMap::new();
+    for (i, evt) in events.iter().enumerate() {
+        for obj in &evt.applied_objs {
+            first_applied.entry(obj_key(obj)).or_default().push((i, obj));
+        }
+    }
+
+    let mut existing = HashSet::new();
+    let mut unresolved = HashSet::new();
+    let mut plan = Vec::with_capacity(events.len());
+    for (i, evt) in events.iter().enumerate() {
+        let mut objs = evt.applied_objs.clone();
+        existing.extend(objs.iter().map(obj_key));
+
+        // objs can grow as we hoist things, and the hoisted objects might have dependencies of their
+        // own, so we have to iterate by index here
+        let mut j = 0;
+        while j < objs.len() {
+            for dep in find_dependencies(&objs[j]) {
+                if existing.contains(&dep) {
+                    continue;
+                }
+
+                let later = first_applied
+                    .get(&dep)
+                    .and_then(|versions| versions.iter().find(|(k, _)| *k > i));
+                if let Some((k, dep_obj)) = later {
+                    info!("moving {dep} from event {k} to event {i} because {} depends on it", obj_key(&objs[j]));
+                    objs.push((*dep_obj).clone());
+                    existing.insert(dep);
+                } else if unresolved.insert(dep.clone()) {
+                    warn!(
+                        "{} depends on {dep}, which is not in the trace; applying in timestamp order",
+                        obj_key(&objs[j])
+                    );
+                }
+            }
+            j += 1;
+        }
+
+        plan.push(sort_by_dependencies(objs));
+
+        for obj in &evt.deleted_objs {
+            existing.remove(&obj_key(obj));
+        }
+    }
+
+    plan
+}
+
+fn is_namespace_policy(obj: &DynamicObject) -> bool {
+    obj.types
+        .as_ref()
+        .is_some_and(|t| NAMESPACE_POLICY_KINDS.contains(&t.kind.as_str()))
+}
+
+// Kahn's algorithm, except we always pick the earliest ready object so that unrelated objects stay
+// in their original order.  Everything in a namespace also implicitly depends on the namespace
+// policies in the same event; we don't hoist policies from later events like we do for named refs,
+// though, since a quota that showed up partway through the trace _should_ only apply from then on.
+fn sort_by_dependencies(objs: Vec<DynamicObject>) -> Vec<DynamicObject> {
+    let keys: Vec<_> = objs.iter().map(obj_key).collect();
+    let mut key_index = HashMap::new();
+    let mut policies: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, (key, obj)) in keys.iter().zip(&objs).enumerate() {
+        key_index.entry(key.as_str()).or_insert(i);
+        if let Some(ns) = obj.namespace().filter(|_| is_namespace_policy(obj)) {
+            policies.entry(ns).or_default().push(i);
+        }
+    }
+
+    let mut in_degree = vec![0; objs.len()];
+    let mut dependents = vec![vec![]; objs.len()];
+    for (i, obj) in objs.iter().enumerate() {
+        let ns_policies = (!is_namespace_policy(obj))
+            .then(|| obj.namespace())
+            .flatten()
+            .and_then(|ns| policies.get(&ns));
+        let mut deps: Vec<_> = find_dependencies(obj)
+            .iter()
+            .filter_map(|d| key_index.get(d.as_str()).copied())
+            .chain(ns_policies.into_iter().flatten().copied())
+            .filter(|j| *j != i)
+            .collect();
+        deps.sort_unstable();
+        deps.dedup();
+
+        in_degree[i] = deps.len();
+        for j in deps {
+            dependents[j].push(i);
+        }
+    }
+
+    let mut ready: BinaryHeap<_> = (0..objs.len()).filter(|i| in_degree[*i] == 0).map(Reverse).collect();
+    let mut done = vec![false; objs.len()];
+    let mut order = Vec::with_capacity(objs.len());
+    while let Some(Reverse(i)) = ready.pop() {
+        done[i] = true;
+        order.push(i);
+        for &k in &dependents[i] {
+            in_degree[k] -= 1;
+            if in_degree[k] == 0 {
+                ready.push(Reverse(k));
+            }
+        }
+    }
+
+    if order.len() < objs.len() {
+        let remaining: Vec<_> = (0..objs.len()).filter(|i| !done[*i]).collect();
+        warn!(
+            "dependency cycle between {}; applying in timestamp order",
+            remaining.iter().map(|i| keys[*i].as_str()).collect::<Vec<_>>().join(", ")
+        );
+        order.extend(remaining);
+    }
+
+    let mut objs: Vec<_> = objs.into_iter().map(Some).collect();
+    order.into_iter().filter_map(|i| objs[i].take()).collect()
+}
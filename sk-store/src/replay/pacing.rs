@@ -0,0 +1,98 @@
+use std::cmp::{
+    max,
+    min,
+};
+use std::time::Duration;
+
+use clockabilly::Clockable;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ReplayPacing {
+    // After each event, sleep for the gap to the next event divided by the speed multiplier
+    #[default]
+    Speed,
+
+    // Each event fires at the same offset from the start of the replay as it had from the start of
+    // the trace, so the time it takes to apply an event comes out of the gap that follows it,
+    // instead of pushing everything after it back; the speed multiplier is ignored
+    Recorded,
+}
+
+// The Pacer decides how long to wait in between events.  If there's a max sleep, gaps in the trace
+// that are longer than that are shortened to the max, so that we don't sit around for hours during
+// idle periods; for recorded pacing, everything after a shortened gap is moved up by the same
+// amount, so the rest of the gaps are still reproduced exactly.
+pub(crate) struct Pacer {
+    pacing: ReplayPacing,
+    speed: f64,
+    max_sleep: Option<i64>,
+
+    clock: Box<dyn Clockable + Send + Sync>,
+    next_fire_ts: i64,
+}
+
+impl Pacer {
+    pub(crate) fn new(
+        pacing: ReplayPacing,
+        speed: f64,
+        max_sleep: Option<i64>,
+        clock: Box<dyn Clockable + Send + Sync>,
+    ) -> Pacer {
+        let next_fire_ts = clock.now_ts();
+        Pacer { pacing, speed, max_sleep, clock, next_fire_ts }
+    }
+
+    // How long to wait before firing the next event, given the trace timestamps of the event that
+    // just fired and the next one
+    pub(crate) fn delay(&mut self, sim_ts: i64, next_ts: i64) -> Duration {
+        let gap = max(0, next_ts - sim_ts);
+        let secs = match self.pacing {
+            ReplayPacing::Speed => (gap as f64 / self.speed) as i64,
+            ReplayPacing::Recorded => {
+                self.next_fire_ts += self.capped(gap);
+                self.next_fire_ts - self.clock.now_ts()
+            },
+        };
+        Duration::from_secs(self.capped(secs).max(0) as u64)
+    }
+
+    // The replay sat at a breakpoint for this long; for recorded pacing, everything after the
+    // breakpoint gets pushed back by the same amount, otherwise we'd try to "catch up" on resume
+    pub(crate) fn paused(&mut self, secs: i64) {
+        self.next_fire_ts += max(0, secs);
+    }
+
+    fn capped(&self, secs: i64) -> i64 {
+        self.max_sleep.map_or(secs, |max_sleep| min(secs, max_sleep))
+    }
+}
+
+// Trace timestamps at which the replay stops and waits to be told to continue; the replay halts
+// right before applying the first event at (or after) each breakpoint.  If several breakpoints fall
+// in the same gap between events, the replay only stops once.
+#[derive(Debug, Default)]
+pub(crate) struct Breakpoints {
+    remaining: Vec<i64>,
+}
+
+impl Breakpoints {
+    pub(crate) fn new(breakpoints: &[i64]) -> Breakpoints {
+        // Stored in reverse so we can pop them off the end as they go by
+        let mut remaining = breakpoints.to_vec();
+        remaining.sort_unstable_by(|a, b| b.cmp(a));
+        remaining.dedup();
+        Breakpoints { remaining }
+    }
+
+    // Returns the (last) breakpoint that's been reached by the time of the next event, if any
+    pub(crate) fn hit(&mut self, next_ts: i64) -> Option<i64> {
+        let mut hit = None;
+        while let Some(&ts) = self.remaining.last() {
+            if ts > next_ts {
+                break;
+            }
+            hit = self.remaining.pop();
+        }
+        hit
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use kube::api::DynamicObject;
+use serde_json::Value;
+use sk_core::prelude::*;
+
+// Maps namespaces from the trace to the namespaces we actually create objects in during a replay.
+// Individual namespaces can be sent somewhere specific with `--remap ns=other`; if a prefix is set,
+// everything else gets moved into a "virtual" namespace, i.e., `ns` becomes `<prefix>-ns`, and
+// otherwise unmapped namespaces are left alone.
+#[derive(Clone, Debug, Default)]
+pub struct NamespaceMap {
+    prefix: Option<String>,
+    remaps: HashMap<String, String>,
+}
+
+// clap value parser for `--remap from=to`
+pub fn parse_remap(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((from, to)) if !from.is_empty() && !to.is_empty() => Ok((from.into(), to.into())),
+        _ => Err(format!("invalid namespace remap {s}; expected <from>=<to>")),
+    }
+}
+
+impl NamespaceMap {
+    pub fn new(prefix: Option<&str>, remaps: impl IntoIterator<Item = (String, String)>) -> NamespaceMap {
+        NamespaceMap {
+            prefix: prefix.map(|p| p.into()),
+            remaps: remaps.into_iter().collect(),
+        }
+    }
+
+    pub fn map(&self, ns: &str) -> String {
+        match (self.remaps.get(ns), &self.prefix) {
+            (Some(mapped), _) => mapped.clone(),
+            (None, Some(prefix)) => format!("{prefix}-{ns}"),
+            (None, None) => ns.into(),
+        }
+    }
+
+    // Objects can refer to other namespaces in a handful of places; if we don't fix these up too,
+    // the simulated object will point at the _original_ namespace, which doesn't exist in the
+    // simulation (or worse, does).  Owner references are always in the same namespace as the
+    // object, so there's nothing to do for them.
+    pub fn remap_references(&self, obj: &mut DynamicObject) {
+        if let Some(subjects) = obj.data.get_mut("subjects").and_then(Value::as_array_mut) {
+            for subj in subjects {
+                self.remap_str(subj.get_mut("namespace"));
+            }
+        }
+        self.remap_selectors(&mut obj.data);
+    }
+
+    // Walk the object looking for pod (anti-)affinity terms or network policy peers, which can
+    // list namespaces explicitly or select them by their `kubernetes.io/metadata.name` label
+    fn remap_selectors(&self, val: &mut Value) {
+        match val {
+            Value::Object(map) => {
+                for (field, child) in map.iter_mut() {
+                    match field.as_str() {
+                        "namespaces" => {
+                            for ns in child.as_array_mut().into_iter().flatten() {
+                                self.remap_str(Some(ns));
+                            }
+                        },
+                        "namespaceSelector" => self.remap_namespace_selector(child),
+                        _ => self.remap_selectors(child),
+                    }
+                }
+            },
+            Value::Array(items) => items.iter_mut().for_each(|item| self.remap_selectors(item)),
+            _ => (),
+        }
+    }
+
+    fn remap_namespace_selector(&self, selector: &mut Value) {
+        let labels = selector.get_mut("matchLabels");
+        self.remap_str(labels.and_then(|l| l.get_mut(KUBERNETES_IO_METADATA_NAME_KEY)));
+
+        let exprs = selector.get_mut("matchExpressions").and_then(Value::as_array_mut);
+        for expr in exprs.into_iter().flatten() {
+            if expr.get("key").and_then(Value::as_str) == Some(KUBERNETES_IO_METADATA_NAME_KEY) {
+                for ns in expr.get_mut("values").and_then(Value::as_array_mut).into_iter().flatten() {
+                    self.remap_str(Some(ns));
+                }
+            }
+        }
+    }
+
+    fn remap_str(&self, maybe_val: Option<&mut Value>) {
+        if let Some(val) = maybe_val {
+            if let Some(ns) = val.as_str() {
+                *val = Value::String(self.map(ns));
+            }
+        }
+    }
+}
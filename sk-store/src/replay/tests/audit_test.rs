@@ -0,0 +1,31 @@
+use super::*;
+
+#[rstest]
+fn test_jsonl_audit_sink() {
+    let path = std::env::temp_dir().join(format!("sk-audit-sink-test-{}", std::process::id()));
+    let sink = JsonlAuditSink::create(&path).unwrap();
+    let record = ApplyRecord {
+        ts: 10,
+        trace_ts: 1,
+        gvk: GVK::new("apps", "v1", "Deployment"),
+        name: format!("{TEST_NAMESPACE}/{TEST_DEPLOYMENT}"),
+        result: ApplyResult::Created,
+        error: None,
+    };
+    sink.on_apply(&record);
+    sink.on_apply(&ApplyRecord {
+        result: ApplyResult::Failed,
+        error: Some("oops".into()),
+        ..record.clone()
+    });
+
+    let data = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let lines: Vec<_> = data.lines().collect();
+    assert_eq!(
+        lines[0],
+        r#"{"ts":10,"traceTs":1,"gvk":"apps/v1.Deployment","name":"test-namespace/the-deployment","result":"created"}"#
+    );
+    assert_eq!(serde_json::from_str::<ApplyRecord>(lines[1]).unwrap().error, Some("oops".into()));
+    assert_eq!(lines.len(), 2);
+}
@@ -0,0 +1,39 @@
+use kube::api::DynamicObject;
+
+use super::*;
+use crate::{
+    TraceStore,
+    TracerConfig,
+};
+
+#[rstest]
+fn test_checkpoint_roundtrip() {
+    let path = std::env::temp_dir().join(format!("sk-checkpoint-roundtrip-test-{}", std::process::id()));
+    assert_eq!(ReplayCheckpoint::load(&path).unwrap(), None);
+
+    let checkpoint = ReplayCheckpoint { trace_hash: 5678, next_event: 3, sim_ts: 1234 };
+    checkpoint.save(&path).unwrap();
+    let loaded = ReplayCheckpoint::load(&path).unwrap();
+    assert_eq!(loaded, Some(checkpoint));
+
+    ReplayCheckpoint::remove(&path).unwrap();
+    assert_eq!(ReplayCheckpoint::load(&path).unwrap(), None);
+
+    // Removing a checkpoint that isn't there is fine
+    ReplayCheckpoint::remove(&path).unwrap();
+}
+
+#[rstest]
+fn test_trace_hash(test_deployment: DynamicObject) {
+    let new_store = || {
+        let mut store = TraceStore::new(TracerConfig::default());
+        store.create_or_update_obj(&test_deployment, 1, None);
+        store
+    };
+    let mut store = new_store();
+    let hash = trace_hash(&store).unwrap();
+    assert_eq!(trace_hash(&new_store()).unwrap(), hash);
+
+    store.delete_obj(&test_deployment, 2);
+    assert_ne!(trace_hash(&store).unwrap(), hash);
+}
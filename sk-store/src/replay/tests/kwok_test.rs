@@ -0,0 +1,38 @@
+use kube::api::DynamicObject;
+use serde_json::json;
+
+use super::*;
+
+#[rstest]
+fn test_target_kwok_nodes() {
+    let mut vobj: DynamicObject = serde_json::from_value(json!({
+        "apiVersion": "apps/v1",
+        "kind": "Deployment",
+        "metadata": {"namespace": TEST_NAMESPACE, "name": TEST_DEPLOYMENT},
+        "spec": {"template": {"spec": {"nodeSelector": {"zone": "a", "type": "real"}}}},
+    }))
+    .unwrap();
+
+    // Doing it twice shouldn't add the toleration twice
+    target_kwok_nodes(&mut vobj, "/spec/template").unwrap();
+    target_kwok_nodes(&mut vobj, "/spec/template").unwrap();
+
+    assert_eq!(
+        vobj.data["spec"]["template"]["spec"],
+        json!({
+            "nodeSelector": {"zone": "a", "type": "virtual"},
+            "tolerations": [{"key": VIRTUAL_NODE_TOLERATION_KEY, "operator": "Exists", "effect": "NoSchedule"}],
+        })
+    );
+}
+
+#[rstest]
+fn test_build_kwok_node() {
+    let node = build_kwok_node("the-node", &[(SIMULATION_LABEL_KEY.into(), "my-sim".into())].into());
+
+    assert_eq!(
+        node.metadata.labels.unwrap(),
+        [(SIMULATION_LABEL_KEY.into(), "my-sim".into()), ("type".into(), "virtual".into())].into()
+    );
+    assert_eq!(node.spec.unwrap().taints.unwrap()[0].key, VIRTUAL_NODE_TOLERATION_KEY);
+}
@@ -0,0 +1,14 @@
+mod audit_test;
+mod checkpoint_test;
+mod kwok_test;
+mod mutation_test;
+mod ordering_test;
+mod pacing_test;
+mod remap_test;
+mod replay_test;
+
+use rstest::*;
+use sk_core::k8s::testutils::*;
+use tracing_test::traced_test;
+
+use super::*;
@@ -0,0 +1,63 @@
+use kube::api::DynamicObject;
+use serde_json::json;
+
+use super::*;
+
+fn depl(name: &str) -> DynamicObject {
+    serde_json::from_value(json!({
+        "apiVersion": "apps/v1",
+        "kind": "Deployment",
+        "metadata": {"namespace": TEST_NAMESPACE, "name": name},
+        "spec": {"replicas": 4},
+    }))
+    .unwrap()
+}
+
+fn rules(path: &str) -> Vec<MutationRule> {
+    load_mutation_rules(&format!(
+        "
+- gvk: apps/v1.Deployment
+  name: {TEST_NAMESPACE}/depl-.*
+  patch:
+    - op: replace
+      path: {path}
+      value: 2
+"
+    ))
+    .unwrap()
+}
+
+#[rstest]
+#[case::matching("depl-1", 2)]
+#[case::name_doesnt_match("other-depl-1", 4)]
+fn test_apply_mutations(#[case] name: &str, #[case] expected: i64) {
+    let obj = depl(name);
+    let mut vobj = obj.clone();
+    apply_mutations(&rules("/spec/replicas"), &obj, &mut vobj).unwrap();
+    assert_eq!(vobj.data["spec"]["replicas"], expected);
+}
+
+#[rstest]
+fn test_apply_mutations_wrong_gvk() {
+    let mut obj = depl("depl-1");
+    obj.types.as_mut().unwrap().kind = "StatefulSet".into();
+    let mut vobj = obj.clone();
+    apply_mutations(&rules("/spec/replicas"), &obj, &mut vobj).unwrap();
+    assert_eq!(vobj.data["spec"]["replicas"], 4);
+}
+
+#[rstest]
+fn test_apply_mutations_bad_path() {
+    let obj = depl("depl-1");
+    let mut vobj = obj.clone();
+    let err = apply_mutations(&rules("/spec/foo"), &obj, &mut vobj).unwrap_err();
+    assert!(err.to_string().contains(&format!("{TEST_NAMESPACE}/depl-1")));
+    assert_eq!(vobj, obj);
+}
+
+#[rstest]
+#[case::bad_op("[{gvk: apps/v1.Deployment, patch: [{op: frobnicate, path: /spec}]}]")]
+#[case::bad_regex("[{gvk: apps/v1.Deployment, name: '(', patch: []}]")]
+fn test_load_mutation_rules_invalid(#[case] contents: &str) {
+    assert!(load_mutation_rules(contents).is_err());
+}
@@ -0,0 +1,223 @@
+use kube::api::DynamicObject;
+use kube::ResourceExt;
+use serde_json::json;
+use sk_core::prelude::*;
+
+use super::*;
+use crate::TraceEvent;
+
+fn obj(kind: &str, name: &str, spec: serde_json::Value) -> DynamicObject {
+    serde_json::from_value(json!({
+        "apiVersion": "v1",
+        "kind": kind,
+        "metadata": {"namespace": TEST_NAMESPACE, "name": name},
+        "spec": spec,
+    }))
+    .unwrap()
+}
+
+fn deployment() -> DynamicObject {
+    obj(
+        "Deployment",
+        "the-deployment",
+        json!({"template": {"spec": {
+            "serviceAccountName": "the-sa",
+            "containers": [{
+                "envFrom": [{"configMapRef": {"name": "the-cm"}}],
+            }],
+            "volumes": [{"name": "creds", "secret": {"secretName": "the-secret"}}],
+        }}}),
+    )
+}
+
+fn service_account() -> DynamicObject {
+    obj("ServiceAccount", "the-sa", json!({}))
+}
+
+fn config_map() -> DynamicObject {
+    obj("ConfigMap", "the-cm", json!({}))
+}
+
+// The secret is owned by the configmap, so we get a chain deployment -> configmap -> secret
+fn secret() -> DynamicObject {
+    let mut s = obj("Secret", "the-secret", json!({}));
+    s.metadata.owner_references = Some(vec![metav1::OwnerReference {
+        kind: "ConfigMap".into(),
+        name: "the-cm".into(),
+        ..Default::default()
+    }]);
+    s
+}
+
+fn names(objs: &[DynamicObject]) -> Vec<String> {
+    objs.iter().map(|o| o.name_any()).collect()
+}
+
+fn evt(ts: i64, applied_objs: Vec<DynamicObject>, deleted_objs: Vec<DynamicObject>) -> TraceEvent {
+    TraceEvent { ts, applied_objs, deleted_objs }
+}
+
+#[rstest]
+fn test_find_dependencies() {
+    let deps = find_dependencies(&deployment());
+    assert_eq!(
+        deps,
+        vec![
+            format!("ServiceAccount:{TEST_NAMESPACE}/the-sa"),
+            format!("ConfigMap:{TEST_NAMESPACE}/the-cm"),
+            format!("Secret:{TEST_NAMESPACE}/the-secret"),
+        ]
+    );
+}
+
+#[rstest]
+fn test_find_dependencies_default_service_account() {
+    let o = obj("Deployment", "foo", json!({"template": {"spec": {"serviceAccountName": "default"}}}));
+    assert!(find_dependencies(&o).is_empty());
+}
+
+#[rstest]
+fn test_find_dependencies_cluster_scoped() {
+    let o = obj("Deployment", "foo", json!({"template": {"spec": {"priorityClassName": "high"}}}));
+    assert_eq!(find_dependencies(&o), vec!["PriorityClass:high"]);
+
+    let mut rb = obj("RoleBinding", "foo", json!({}));
+    rb.data = json!({"roleRef": {"kind": "ClusterRole", "name": "view"}});
+    assert_eq!(find_dependencies(&rb), vec!["ClusterRole:view"]);
+}
+
+#[rstest]
+fn test_remove_dependency() {
+    let mut d = deployment();
+    assert!(remove_dependency(&mut d, &format!("ServiceAccount:{TEST_NAMESPACE}/the-sa")));
+    assert!(remove_dependency(&mut d, &format!("Secret:{TEST_NAMESPACE}/the-secret")));
+    // The secret reference is still there, it's just optional now
+    assert_eq!(
+        find_dependencies(&d),
+        vec![format!("Secret:{TEST_NAMESPACE}/the-secret"), format!("ConfigMap:{TEST_NAMESPACE}/the-cm")]
+    );
+    assert_eq!(d.data.pointer("/spec/template/spec/volumes/0/secret/optional"), Some(&json!(true)));
+
+    let mut s = secret();
+    assert!(remove_dependency(&mut s, &format!("ConfigMap:{TEST_NAMESPACE}/the-cm")));
+    assert_eq!(s.metadata.owner_references, None);
+
+    let mut rb = obj("RoleBinding", "foo", json!({}));
+    rb.data = json!({"roleRef": {"kind": "ClusterRole", "name": "view"}});
+    assert!(!remove_dependency(&mut rb, "ClusterRole:view"));
+}
+
+#[rstest]
+fn test_build_apply_plan_same_event() {
+    let events = vec![evt(0, vec![deployment(), secret(), config_map(), service_account()], vec![])];
+    let plan = build_apply_plan(&events);
+    assert_eq!(plan.len(), 1);
+    assert_eq!(names(&plan[0]), vec!["the-cm", "the-secret", "the-sa", "the-deployment"]);
+}
+
+#[rstest]
+#[traced_test]
+fn test_build_apply_plan_hoists_later_dependencies() {
+    let events = vec![
+        evt(0, vec![deployment()], vec![]),
+        evt(5, vec![service_account(), secret()], vec![]),
+        evt(10, vec![config_map()], vec![]),
+    ];
+    let plan = build_apply_plan(&events);
+
+    // Everything gets created in the first event, but the original applies are left in place
+    assert_eq!(names(&plan[0]), vec!["the-sa", "the-cm", "the-secret", "the-deployment"]);
+    assert_eq!(names(&plan[1]), vec!["the-sa", "the-secret"]);
+    assert_eq!(names(&plan[2]), vec!["the-cm"]);
+    assert!(!logs_contain("WARN"));
+}
+
+#[rstest]
+fn test_build_apply_plan_namespace_policies() {
+    let mut other_ns = obj("Deployment", "other-deployment", json!({}));
+    other_ns.metadata.namespace = Some("other".into());
+    let quota = obj("ResourceQuota", "the-quota", json!({"hard": {"pods": "10"}}));
+    let limits = obj("LimitRange", "the-limits", json!({"limits": [{"type": "Container"}]}));
+    let events = vec![
+        evt(0, vec![other_ns, deployment(), service_account(), quota, config_map(), secret()], vec![]),
+        evt(5, vec![obj("LimitRange", "later-limits", json!({}))], vec![]),
+    ];
+    let plan = build_apply_plan(&events);
+
+    // Everything in the quota's namespace waits for it, but objects in other namespaces don't, and
+    // a policy that shows up later doesn't get moved forward
+    assert_eq!(
+        names(&plan[0]),
+        vec!["other-deployment", "the-quota", "the-sa", "the-cm", "the-secret", "the-deployment"]
+    );
+    assert_eq!(names(&plan[1]), vec!["later-limits"]);
+
+    let events = vec![evt(0, vec![deployment(), service_account(), limits], vec![])];
+    let plan = build_apply_plan(&events);
+    assert_eq!(names(&plan[0]), vec!["the-limits", "the-sa", "the-deployment"]);
+}
+
+#[rstest]
+fn test_build_apply_plan_existing_dependencies() {
+    let events =
+        vec![evt(0, vec![service_account(), config_map(), secret()], vec![]), evt(5, vec![deployment()], vec![])];
+    let plan = build_apply_plan(&events);
+    assert_eq!(names(&plan[0]), vec!["the-sa", "the-cm", "the-secret"]);
+    assert_eq!(names(&plan[1]), vec!["the-deployment"]);
+}
+
+#[rstest]
+fn test_build_apply_plan_recreated_after_delete() {
+    let events = vec![
+        evt(0, vec![service_account()], vec![]),
+        evt(5, vec![], vec![service_account()]),
+        evt(10, vec![obj("Deployment", "foo", json!({"serviceAccountName": "the-sa"}))], vec![]),
+        evt(15, vec![service_account()], vec![]),
+    ];
+    let plan = build_apply_plan(&events);
+    assert_eq!(names(&plan[2]), vec!["the-sa", "foo"]);
+}
+
+#[rstest]
+#[traced_test]
+fn test_build_apply_plan_unresolved() {
+    let events = vec![evt(0, vec![deployment(), service_account()], vec![])];
+    let plan = build_apply_plan(&events);
+    assert_eq!(names(&plan[0]), vec!["the-sa", "the-deployment"]);
+    assert!(logs_contain("not in the trace"));
+}
+
+#[rstest]
+#[traced_test]
+fn test_build_apply_plan_cycle() {
+    let mut cm = config_map();
+    cm.metadata.owner_references = Some(vec![metav1::OwnerReference {
+        kind: "Secret".into(),
+        name: "the-secret".into(),
+        ..Default::default()
+    }]);
+    let other = obj("ServiceAccount", "other", json!({}));
+    let events = vec![evt(0, vec![secret(), other, cm], vec![])];
+    let plan = build_apply_plan(&events);
+    assert_eq!(names(&plan[0]), vec!["other", "the-secret", "the-cm"]);
+    assert!(logs_contain("dependency cycle"));
+}
+
+#[rstest]
+fn test_build_apply_plan_long_chain() {
+    // Each ConfigMap is owned by the next one, so the whole event has to be applied in reverse
+    let objs: Vec<_> = (0..1000)
+        .map(|i| {
+            let mut cm = obj("ConfigMap", &format!("cm-{i}"), json!({}));
+            cm.metadata.owner_references = Some(vec![metav1::OwnerReference {
+                kind: "ConfigMap".into(),
+                name: format!("cm-{}", i + 1),
+                ..Default::default()
+            }]);
+            cm
+        })
+        .collect();
+    let events = vec![evt(0, objs, vec![])];
+    let plan = build_apply_plan(&events);
+    assert_eq!(names(&plan[0]), (0..1000).rev().map(|i| format!("cm-{i}")).collect::<Vec<_>>());
+}
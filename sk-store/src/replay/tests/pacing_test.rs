@@ -0,0 +1,63 @@
+use clockabilly::mock::MockUtcClock;
+use clockabilly::Clockable;
+
+use super::*;
+use crate::replay::pacing::Pacer;
+
+const TRACE_TS: [i64; 5] = [100, 110, 130, 3730, 3735];
+
+// Runs through the trace timestamps, pretending that applying each event takes `apply_secs`, and
+// returns the (mock) time at which each event fired, relative to the start of the replay
+fn fire_times(pacing: ReplayPacing, speed: f64, max_sleep: Option<i64>, apply_secs: i64) -> Vec<i64> {
+    let mut clock = MockUtcClock::new(0);
+    let mut pacer = Pacer::new(pacing, speed, max_sleep, Box::new(clock.clone()));
+
+    let mut times = vec![clock.now_ts()];
+    for ts in TRACE_TS.windows(2) {
+        clock.advance(apply_secs);
+        let delay = pacer.delay(ts[0], ts[1]);
+        clock.advance(delay.as_secs() as i64);
+        times.push(clock.now_ts());
+    }
+    times
+}
+
+#[rstest]
+#[case::instant(0, vec![0, 10, 30, 3630, 3635])]
+#[case::slow_apply(3, vec![0, 10, 30, 3630, 3635])]
+// If applying an event takes longer than the gap after it, we catch up as soon as we can
+#[case::very_slow_apply(15, vec![0, 15, 30, 3630, 3645])]
+fn test_recorded_pacing(#[case] apply_secs: i64, #[case] expected: Vec<i64>) {
+    assert_eq!(fire_times(ReplayPacing::Recorded, 1.0, None, apply_secs), expected);
+}
+
+#[rstest]
+fn test_recorded_pacing_ignores_speed() {
+    assert_eq!(fire_times(ReplayPacing::Recorded, 10.0, None, 0), vec![0, 10, 30, 3630, 3635]);
+}
+
+#[rstest]
+fn test_recorded_pacing_max_sleep() {
+    // The hour-long gap is cut down to a minute, and everything after it moves up accordingly
+    assert_eq!(fire_times(ReplayPacing::Recorded, 1.0, Some(60), 2), vec![0, 10, 30, 90, 95]);
+}
+
+#[rstest]
+#[case::no_cap(None, vec![0, 12, 34, 3636, 3643])]
+#[case::cap(Some(60), vec![0, 12, 34, 96, 103])]
+fn test_speed_pacing(#[case] max_sleep: Option<i64>, #[case] expected: Vec<i64>) {
+    // Speed pacing sleeps for the whole (scaled) gap after applying each event, so the apply time
+    // accumulates over the course of the replay
+    assert_eq!(fire_times(ReplayPacing::Speed, 1.0, max_sleep, 2), expected);
+}
+
+#[rstest]
+fn test_recorded_pacing_paused() {
+    let mut clock = MockUtcClock::new(0);
+    let mut pacer = Pacer::new(ReplayPacing::Recorded, 1.0, None, Box::new(clock.clone()));
+
+    // Sitting at a breakpoint for a minute pushes the next event back by a minute
+    clock.advance(60);
+    pacer.paused(60);
+    assert_eq!(pacer.delay(100, 110).as_secs(), 10);
+}
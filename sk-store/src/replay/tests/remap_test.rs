@@ -0,0 +1,109 @@
+use kube::api::DynamicObject;
+use serde_json::json;
+use sk_core::prelude::*;
+
+use super::*;
+
+#[fixture]
+fn ns_map() -> NamespaceMap {
+    NamespaceMap::new(Some(TEST_VIRT_NS_PREFIX), [("prod".to_string(), "sim".to_string())])
+}
+
+#[rstest]
+#[case::valid("prod=sim", Some(("prod", "sim")))]
+#[case::no_equals("prod", None)]
+#[case::empty_from("=sim", None)]
+#[case::empty_to("prod=", None)]
+fn test_parse_remap(#[case] input: &str, #[case] expected: Option<(&str, &str)>) {
+    assert_eq!(parse_remap(input).ok(), expected.map(|(f, t)| (f.to_string(), t.to_string())));
+}
+
+#[rstest]
+fn test_map(ns_map: NamespaceMap) {
+    assert_eq!(ns_map.map("prod"), "sim");
+    assert_eq!(ns_map.map("staging"), format!("{TEST_VIRT_NS_PREFIX}-staging"));
+}
+
+#[rstest]
+fn test_map_no_prefix() {
+    let ns_map = NamespaceMap::new(None, [("prod".to_string(), "sim".to_string())]);
+    assert_eq!(ns_map.map("prod"), "sim");
+    assert_eq!(ns_map.map("staging"), "staging");
+}
+
+#[rstest]
+fn test_remap_role_binding_subjects(ns_map: NamespaceMap) {
+    let mut obj: DynamicObject = serde_json::from_value(json!({
+        "apiVersion": "rbac.authorization.k8s.io/v1",
+        "kind": "ClusterRoleBinding",
+        "metadata": {"name": "the-binding"},
+        "roleRef": {"apiGroup": "rbac.authorization.k8s.io", "kind": "ClusterRole", "name": "the-role"},
+        "subjects": [
+            {"kind": "ServiceAccount", "name": "the-sa", "namespace": "prod"},
+            {"kind": "ServiceAccount", "name": "other-sa", "namespace": "staging"},
+            {"kind": "User", "name": "bob"},
+        ],
+    }))
+    .unwrap();
+    ns_map.remap_references(&mut obj);
+
+    assert_eq!(
+        obj.data["subjects"],
+        json!([
+            {"kind": "ServiceAccount", "name": "the-sa", "namespace": "sim"},
+            {"kind": "ServiceAccount", "name": "other-sa", "namespace": format!("{TEST_VIRT_NS_PREFIX}-staging")},
+            {"kind": "User", "name": "bob"},
+        ])
+    );
+}
+
+#[rstest]
+fn test_remap_selectors(ns_map: NamespaceMap) {
+    let mut obj: DynamicObject = serde_json::from_value(json!({
+        "apiVersion": "apps/v1",
+        "kind": "Deployment",
+        "metadata": {"namespace": "prod", "name": "the-deployment"},
+        "spec": {"template": {"spec": {"affinity": {"podAffinity": {
+            "requiredDuringSchedulingIgnoredDuringExecution": [{
+                "labelSelector": {"matchLabels": {"app": "db"}},
+                "namespaces": ["prod"],
+                "namespaceSelector": {
+                    "matchLabels": {"kubernetes.io/metadata.name": "prod"},
+                    "matchExpressions": [
+                        {"key": "kubernetes.io/metadata.name", "operator": "In", "values": ["prod", "staging"]},
+                        {"key": "team", "operator": "In", "values": ["prod"]},
+                    ],
+                },
+                "topologyKey": "kubernetes.io/hostname",
+            }],
+        }}}}},
+    }))
+    .unwrap();
+    ns_map.remap_references(&mut obj);
+
+    let term = &obj.data["spec"]["template"]["spec"]["affinity"]["podAffinity"]
+        ["requiredDuringSchedulingIgnoredDuringExecution"][0];
+    assert_eq!(term["namespaces"], json!(["sim"]));
+    assert_eq!(
+        term["namespaceSelector"],
+        json!({
+            "matchLabels": {"kubernetes.io/metadata.name": "sim"},
+            "matchExpressions": [
+                {"key": "kubernetes.io/metadata.name", "operator": "In", "values": ["sim", format!("{TEST_VIRT_NS_PREFIX}-staging")]},
+                {"key": "team", "operator": "In", "values": ["prod"]},
+            ],
+        })
+    );
+    assert_eq!(term["labelSelector"], json!({"matchLabels": {"app": "db"}}));
+
+    // The object's own namespace is handled when the virtual object is built, not here
+    assert_eq!(obj.metadata.namespace, Some("prod".into()));
+}
+
+#[rstest]
+fn test_remap_no_references(ns_map: NamespaceMap) {
+    let mut obj = test_deployment(TEST_DEPLOYMENT);
+    let orig = obj.clone();
+    ns_map.remap_references(&mut obj);
+    assert_eq!(obj.data, orig.data);
+}
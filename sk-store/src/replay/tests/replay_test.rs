@@ -0,0 +1,259 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use assertables::*;
+use httpmock::Method::*;
+use kube::api::DynamicObject;
+use serde_json::json;
+use tokio::time::timeout;
+
+use super::*;
+use crate::replay::pacing::Breakpoints;
+use crate::{
+    TraceStore,
+    TracerConfig,
+};
+
+#[derive(Default)]
+struct RecordingObserver {
+    records: Mutex<Vec<ApplyRecord>>,
+    pauses: Mutex<Vec<i64>>,
+}
+
+impl ApplyObserver for RecordingObserver {
+    fn on_apply(&self, record: &ApplyRecord) {
+        self.records.lock().unwrap().push(record.clone());
+    }
+
+    fn on_pause(&self, breakpoint: i64) {
+        self.pauses.lock().unwrap().push(breakpoint);
+    }
+}
+
+struct PassthroughTarget;
+
+impl ReplayTarget for PassthroughTarget {
+    fn namespace_for(&self, ns: &str) -> String {
+        ns.into()
+    }
+
+    fn build_namespace(&self, name: &str) -> corev1::Namespace {
+        corev1::Namespace {
+            metadata: metav1::ObjectMeta { name: Some(name.into()), ..Default::default() },
+            ..Default::default()
+        }
+    }
+
+    fn build_obj(&self, obj: &DynamicObject, _: Option<&str>) -> anyhow::Result<DynamicObject> {
+        Ok(obj.clone())
+    }
+}
+
+fn depl(replicas: i64) -> DynamicObject {
+    serde_json::from_value(json!({
+        "apiVersion": "apps/v1",
+        "kind": "Deployment",
+        "metadata": {"namespace": TEST_NAMESPACE, "name": TEST_DEPLOYMENT},
+        "spec": {"replicas": replicas},
+    }))
+    .unwrap()
+}
+
+fn fake_apiserver(patch_status: u16) -> (MockServerBuilder, kube::Client) {
+    let (mut fake_apiserver, client) = make_fake_apiserver();
+    fake_apiserver
+        .handle(|when, then| {
+            when.path(format!("/api/v1/namespaces/{TEST_NAMESPACE}"));
+            then.json_body(json!({"kind": "Namespace", "metadata": {"name": TEST_NAMESPACE}}));
+        })
+        .handle(|when, then| {
+            when.path("/apis/apps/v1");
+            then.json_body(apps_v1_discovery());
+        })
+        .handle(move |when, then| {
+            when.method(PATCH)
+                .path(format!("/apis/apps/v1/namespaces/{TEST_NAMESPACE}/deployments/{TEST_DEPLOYMENT}"));
+            then.status(patch_status).json_body(status_ok());
+        })
+        .handle(|when, then| {
+            when.method(DELETE)
+                .path(format!("/apis/apps/v1/namespaces/{TEST_NAMESPACE}/deployments/{TEST_DEPLOYMENT}"));
+            then.json_body(status_ok());
+        })
+        .build();
+    (fake_apiserver, client)
+}
+
+#[rstest]
+#[tokio::test]
+async fn itest_replay_trace_observer() {
+    let mut store = TraceStore::new(TracerConfig::default());
+    store.create_or_update_obj(&depl(1), 1, None);
+    store.create_or_update_obj(&depl(2), 2, None);
+    store.delete_obj(&depl(2), 3);
+    store.create_or_update_obj(&depl(3), 4, None);
+
+    let (_fake_apiserver, client) = fake_apiserver(200);
+    let observer = Arc::new(RecordingObserver::default());
+    let opts = ReplayOptions {
+        dry_run: true,
+        observer: observer.clone(),
+        ..Default::default()
+    };
+    replay_trace(client, &store, &PassthroughTarget, &opts).await.unwrap();
+
+    // Re-applying after a delete counts as creating the object again
+    let records = observer.records.lock().unwrap();
+    let summary: Vec<_> = records.iter().map(|rec| (rec.trace_ts, rec.result)).collect();
+    assert_eq!(summary, vec![(1, ApplyResult::Created), (2, ApplyResult::Updated), (4, ApplyResult::Created)]);
+    for rec in records.iter() {
+        assert_eq!(rec.gvk, GVK::new("apps", "v1", "Deployment"));
+        assert_eq!(rec.name, format!("{TEST_NAMESPACE}/{TEST_DEPLOYMENT}"));
+        assert_eq!(rec.error, None);
+    }
+}
+
+#[rstest]
+#[tokio::test]
+async fn itest_replay_trace_observer_failed() {
+    let mut store = TraceStore::new(TracerConfig::default());
+    store.create_or_update_obj(&depl(1), 1, None);
+
+    let (_fake_apiserver, client) = fake_apiserver(500);
+    let observer = Arc::new(RecordingObserver::default());
+    let opts = ReplayOptions {
+        dry_run: true,
+        observer: observer.clone(),
+        ..Default::default()
+    };
+    replay_trace(client, &store, &PassthroughTarget, &opts).await.unwrap_err();
+
+    let records = observer.records.lock().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].result, ApplyResult::Failed);
+    assert!(records[0].error.is_some());
+}
+
+#[rstest]
+#[tokio::test]
+async fn itest_replay_trace_dry_run_missing_namespace() {
+    let mut store = TraceStore::new(TracerConfig::default());
+    store.create_or_update_obj(&depl(1), 1, None);
+    store.create_or_update_obj(&depl(2), 2, None);
+    store.delete_obj(&depl(2), 3);
+
+    // There are no handlers for the deployment, so the replay fails if it tries to apply or delete it
+    let (mut fake_apiserver, client) = make_fake_apiserver();
+    fake_apiserver
+        .handle_not_found(format!("/api/v1/namespaces/{TEST_NAMESPACE}"))
+        .handle(|when, then| {
+            when.method(POST).path("/api/v1/namespaces").query_param("dryRun", "All");
+            then.json_body(json!({"kind": "Namespace", "metadata": {"name": TEST_NAMESPACE}}));
+        })
+        .build();
+    let observer = Arc::new(RecordingObserver::default());
+    let opts = ReplayOptions {
+        dry_run: true,
+        observer: observer.clone(),
+        ..Default::default()
+    };
+    replay_trace(client, &store, &PassthroughTarget, &opts).await.unwrap();
+
+    // The namespace only gets looked up (and created) once
+    fake_apiserver.assert_hits(0, 1);
+    fake_apiserver.assert_hits(1, 1);
+    let records = observer.records.lock().unwrap();
+    let summary: Vec<_> = records.iter().map(|rec| (rec.trace_ts, rec.result)).collect();
+    assert_eq!(summary, vec![(1, ApplyResult::Skipped), (2, ApplyResult::Skipped)]);
+}
+
+#[rstest]
+#[tokio::test]
+async fn itest_replay_trace_checkpoint_other_trace() {
+    let mut store = TraceStore::new(TracerConfig::default());
+    store.create_or_update_obj(&depl(1), 1, None);
+
+    let path = std::env::temp_dir().join(format!("sk-replay-other-trace-test-{}", std::process::id()));
+    ReplayCheckpoint {
+        trace_hash: trace_hash(&store).unwrap() + 1,
+        next_event: 1,
+        sim_ts: 2,
+    }
+    .save(&path)
+    .unwrap();
+
+    // Nothing should get applied, so there aren't any handlers for the deployment
+    let (mut fake_apiserver, client) = make_fake_apiserver();
+    fake_apiserver.build();
+    let opts = ReplayOptions {
+        checkpoint_path: Some(path.clone()),
+        ..Default::default()
+    };
+    let err = replay_trace(client, &store, &PassthroughTarget, &opts).await.unwrap_err();
+
+    // The checkpoint is left alone, in case it's needed to resume the trace it belongs to
+    assert!(ReplayCheckpoint::load(&path).unwrap().is_some());
+    std::fs::remove_file(&path).unwrap();
+    assert_contains!(err.to_string(), "different trace");
+}
+
+// Waits (for a little while) until the replay has applied `count` objects
+async fn wait_for_applies(observer: &RecordingObserver, count: usize) {
+    timeout(Duration::from_secs(5), async {
+        while observer.records.lock().unwrap().len() < count {
+            sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+#[rstest]
+#[tokio::test]
+async fn itest_replay_trace_breakpoints() {
+    let mut store = TraceStore::new(TracerConfig::default());
+    store.create_or_update_obj(&depl(1), 1, None);
+    store.create_or_update_obj(&depl(2), 2, None);
+    store.create_or_update_obj(&depl(3), 4, None);
+
+    let (_fake_apiserver, client) = fake_apiserver(200);
+    let observer = Arc::new(RecordingObserver::default());
+    let resume = Arc::new(Notify::new());
+    let opts = ReplayOptions {
+        dry_run: true,
+        observer: observer.clone(),
+        breakpoints: vec![3, 2],
+        resume: resume.clone(),
+        ..Default::default()
+    };
+    let handle = tokio::spawn(async move { replay_trace(client, &store, &PassthroughTarget, &opts).await });
+
+    // The replay stops before each event that's at or past a breakpoint, and stays stopped until
+    // it's told to resume
+    for (applied, breakpoint) in [(1, 2), (2, 3)] {
+        wait_for_applies(&observer, applied).await;
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(observer.records.lock().unwrap().len(), applied);
+        assert_eq!(*observer.pauses.lock().unwrap().last().unwrap(), breakpoint);
+        assert!(!handle.is_finished());
+        resume.notify_one();
+    }
+
+    handle.await.unwrap().unwrap();
+    let applied_ts: Vec<_> = observer.records.lock().unwrap().iter().map(|rec| rec.trace_ts).collect();
+    assert_eq!(applied_ts, vec![1, 2, 4]);
+    assert_eq!(*observer.pauses.lock().unwrap(), vec![2, 3]);
+}
+
+#[rstest]
+#[case::before_start(vec![0], 1, Some(0))]
+#[case::exact(vec![5], 5, Some(5))]
+#[case::not_yet(vec![5], 4, None)]
+#[case::several_in_one_gap(vec![4, 2, 3, 3], 4, Some(4))]
+fn test_breakpoints_hit(#[case] breakpoints: Vec<i64>, #[case] next_ts: i64, #[case] expected: Option<i64>) {
+    let mut bps = Breakpoints::new(&breakpoints);
+    assert_eq!(bps.hit(next_ts), expected);
+
+    // Each breakpoint only fires once
+    assert_eq!(bps.hit(next_ts), None);
+}
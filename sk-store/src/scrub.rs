@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+use kube::api::DynamicObject;
+use regex::Regex;
+use sk_core::errors::*;
+use sk_core::jsonutils;
+use sk_core::k8s::split_namespaced_name;
+use sk_core::prelude::*;
+
+const SCRUBBED_DATA_KEYS: [&str; 3] = ["binaryData", "data", "stringData"];
+
+// The TraceScrubber replaces identifying information in a trace with stable pseudonyms, so that
+// traces can be shared outside of the organization that collected them.  Namespaces and object
+// names are each mapped to a sequential pseudonym (`namespace-N` and `object-N`) in the order we
+// first see them; because the same mapping is used everywhere (object metadata, the tracked
+// object index, pod lifecycle and usage data) the scrubbed trace still hangs together.
+//
+// Note that we _don't_ touch anything in the object specs: the pod lifecycle data is keyed by the
+// hash of the pod spec, so if we changed the spec the simulated pods wouldn't match up with their
+// lifecycle data anymore.  This also means that labels (and therefore selectors) are unchanged,
+// and that names of other objects referenced from a spec (ConfigMap and Secret volumes, envFrom,
+// serviceAccountName, etc.) keep their original names, even though the objects they point at have
+// been renamed; so those references dangle in the scrubbed trace.
+#[derive(Default)]
+pub struct TraceScrubber {
+    annotation_patterns: Vec<Regex>,
+    namespaces: HashMap<String, String>,
+    names: HashMap<String, String>,
+}
+
+impl TraceScrubber {
+    pub fn new(annotation_patterns: Vec<Regex>) -> TraceScrubber {
+        TraceScrubber { annotation_patterns, ..Default::default() }
+    }
+
+    pub fn namespace(&mut self, ns: &str) -> String {
+        let next = self.namespaces.len();
+        self.namespaces
+            .entry(ns.into())
+            .or_insert_with(|| format!("namespace-{next}"))
+            .clone()
+    }
+
+    pub fn name(&mut self, name: &str) -> String {
+        let next = self.names.len();
+        self.names
+            .entry(name.into())
+            .or_insert_with(|| format!("object-{next}"))
+            .clone()
+    }
+
+    pub fn namespaced_name(&mut self, ns_name: &str) -> String {
+        match split_namespaced_name(ns_name) {
+            (ns, name) if ns.is_empty() => self.name(&name),
+            (ns, name) => format!("{}/{}", self.namespace(&ns), self.name(&name)),
+        }
+    }
+
+    pub fn scrub_obj(&mut self, obj: &mut DynamicObject) -> EmptyResult {
+        let kind = obj.types.as_ref().map(|t| t.kind.as_str()).unwrap_or_default();
+        let is_namespace = kind == "Namespace";
+        let is_secret_or_cm = kind == "Secret" || kind == "ConfigMap";
+
+        // Namespace objects are named after the namespace, so they need to use the namespace
+        // mapping; the apiserver also sets a label with the namespace name that we have to fix up
+        if is_namespace {
+            obj.metadata.name = obj.metadata.name.as_deref().map(|ns| self.namespace(ns));
+            if let Some(labels) = obj.metadata.labels.as_mut() {
+                if labels.contains_key(KUBERNETES_IO_METADATA_NAME_KEY) {
+                    labels
+                        .insert(KUBERNETES_IO_METADATA_NAME_KEY.into(), obj.metadata.name.clone().unwrap_or_default());
+                }
+            }
+        } else {
+            obj.metadata.name = obj.metadata.name.as_deref().map(|name| self.name(name));
+        }
+        obj.metadata.namespace = obj.metadata.namespace.as_deref().map(|ns| self.namespace(ns));
+        obj.metadata.generate_name = None;
+
+        for owner in obj.metadata.owner_references.iter_mut().flatten() {
+            owner.name = self.name(&owner.name);
+        }
+
+        if let Some(annotations) = obj.metadata.annotations.as_mut() {
+            annotations.retain(|k, _| !self.annotation_patterns.iter().any(|re| re.is_match(k)));
+        }
+
+        if is_secret_or_cm && obj.data.is_object() {
+            for key in SCRUBBED_DATA_KEYS {
+                jsonutils::patch_ext::remove("", key, &mut obj.data)?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use kube::ResourceExt;
+use sk_core::k8s::{
+    KubeResourceExt,
+    PodLifecycleData,
+    GVK,
+};
+
+use crate::TraceStore;
+
+// Something about the store that doesn't add up; none of these come up in a trace that was
+// recorded by the tracer and hasn't been messed with, but traces that were edited by hand (or
+// stitched together from different sources) can end up with any of them, and the simulation
+// driver will do something surprising with them if so.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ValidationIssue {
+    // An object has an owner reference to a kind that's tracked, but the owner isn't in the trace;
+    // owners of kinds that we aren't tracking are expected to be missing, so those are ignored
+    UnresolvedOwner {
+        ns_name: String,
+        owner_gvk: GVK,
+        owner_name: String,
+    },
+
+    // There's pod lifecycle data for an owner that isn't in the trace
+    UnknownPodOwner {
+        owner_ns_name: String,
+    },
+
+    // A pod that finished before it started
+    InvalidLifecycle {
+        owner_ns_name: String,
+        pod_hash: u64,
+        seq: usize,
+        lifecycle: PodLifecycleData,
+    },
+
+    // Pods are recorded for an owner whose namespace isn't in the namespace index
+    MissingNamespace {
+        owner_ns_name: String,
+        namespace: String,
+    },
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationIssue::UnresolvedOwner { ns_name, owner_gvk, owner_name } => {
+                write!(f, "{ns_name} is owned by {owner_gvk} {owner_name}, which is not in the trace")
+            },
+            ValidationIssue::UnknownPodOwner { owner_ns_name } => {
+                write!(f, "pod lifecycle data recorded for {owner_ns_name}, which is not in the trace")
+            },
+            ValidationIssue::InvalidLifecycle { owner_ns_name, pod_hash, seq, lifecycle } => {
+                write!(f, "pod {seq} of {owner_ns_name} (hash {pod_hash}) finishes before it starts: {lifecycle:?}")
+            },
+            ValidationIssue::MissingNamespace { owner_ns_name, namespace } => {
+                write!(f, "pods recorded for {owner_ns_name}, but namespace {namespace} is not in the index")
+            },
+        }
+    }
+}
+
+impl TraceStore {
+    // Checks that the different parts of the store agree with each other (see ValidationIssue);
+    // an empty list means everything's fine.  This doesn't look at whether the objects themselves
+    // make sense, that's what `skctl validate` is for.
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        // Objects that have been deleted still count, since their pods (and the things they own)
+        // were around before they were deleted
+        let known: HashSet<_> = self
+            .events
+            .iter()
+            .flat_map(|evt| evt.applied_objs.iter())
+            .map(|obj| obj.namespaced_name())
+            .chain(self.index.keys().cloned())
+            .collect();
+
+        let mut issues = vec![];
+        for obj in self.events.iter().flat_map(|evt| evt.applied_objs.iter()) {
+            for owner in obj.owner_references() {
+                let Ok(owner_gvk) = GVK::from_owner_ref(owner) else {
+                    continue;
+                };
+                if !self.config.tracked_objects.contains_key(&owner_gvk) {
+                    continue;
+                }
+
+                // Owner references can't cross namespaces, so the owner is either in the same
+                // namespace or cluster-scoped
+                let owner_ns_name = match obj.namespace() {
+                    Some(ns) => format!("{ns}/{}", owner.name),
+                    None => owner.name.clone(),
+                };
+                if !known.contains(&owner_ns_name) && !known.contains(&owner.name) {
+                    let issue = ValidationIssue::UnresolvedOwner {
+                        ns_name: obj.namespaced_name(),
+                        owner_gvk,
+                        owner_name: owner.name.clone(),
+                    };
+                    if !issues.contains(&issue) {
+                        issues.push(issue);
+                    }
+                }
+            }
+        }
+
+        let mut owners: Vec<_> = self.pod_owners.lifecycles().iter().collect();
+        owners.sort_by_key(|(owner_ns_name, _)| *owner_ns_name);
+        for (owner_ns_name, lifecycles) in owners {
+            if !known.contains(owner_ns_name) {
+                issues.push(ValidationIssue::UnknownPodOwner { owner_ns_name: owner_ns_name.clone() });
+            }
+
+            if let Some((namespace, _)) = owner_ns_name.split_once('/') {
+                if !self.namespace_counts.contains_key(namespace) {
+                    issues.push(ValidationIssue::MissingNamespace {
+                        owner_ns_name: owner_ns_name.clone(),
+                        namespace: namespace.into(),
+                    });
+                }
+            }
+
+            let mut hashes: Vec<_> = lifecycles.keys().collect();
+            hashes.sort();
+            for pod_hash in hashes {
+                for (seq, lifecycle) in lifecycles[pod_hash].iter().enumerate() {
+                    if let PodLifecycleData::Finished(start_ts, end_ts, _) = lifecycle {
+                        if end_ts < start_ts {
+                            issues.push(ValidationIssue::InvalidLifecycle {
+                                owner_ns_name: owner_ns_name.clone(),
+                                pod_hash: *pod_hash,
+                                seq,
+                                lifecycle: lifecycle.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        issues
+    }
+}
@@ -0,0 +1,100 @@
+use sk_core::errors::SimKubeError;
+
+use super::*;
+use crate::config::DEFAULT_OWNER_LOOKUP_CONCURRENCY;
+
+#[rstest]
+#[case::pods_tracked(true)]
+#[case::pods_not_tracked(false)]
+fn test_validate_tracked_pods(#[case] track_pods: bool) {
+    let mut config_yaml =
+        "trackedObjects:\n  apps/v1.Deployment:\n    podSpecTemplatePath: /spec/template\n".to_string();
+    if track_pods {
+        config_yaml += "  v1.Pod: {}\n";
+    }
+    let config: TracerConfig = serde_yaml::from_str(&config_yaml).unwrap();
+
+    let res = config.validate();
+    if track_pods {
+        let err = res.unwrap_err();
+        assert!(err.to_string().contains("pods are handled by the pod watcher"));
+        assert!(matches!(err.downcast_ref::<SimKubeError>(), Some(SimKubeError::Config(_))));
+    } else {
+        assert!(res.is_ok());
+    }
+}
+
+#[rstest]
+#[case::zero(Some(0), false)]
+#[case::one(Some(1), true)]
+#[case::unset(None, true)]
+fn test_validate_store_channel_capacity(#[case] capacity: Option<usize>, #[case] valid: bool) {
+    let config = TracerConfig {
+        store_channel_capacity: capacity,
+        ..Default::default()
+    };
+    assert_eq!(config.validate().is_ok(), valid);
+}
+
+#[rstest]
+#[case::unbounded(OwnersCachePolicy::Unbounded, None, true)]
+#[case::ttl(OwnersCachePolicy::Ttl, Some(300), true)]
+#[case::ttl_unset(OwnersCachePolicy::Ttl, None, false)]
+#[case::ttl_zero(OwnersCachePolicy::Ttl, Some(0), false)]
+fn test_validate_owners_cache_policy(#[case] policy: OwnersCachePolicy, #[case] ttl: Option<i64>, #[case] valid: bool) {
+    let config = TracerConfig {
+        owners_cache_policy: policy,
+        owners_cache_ttl_seconds: ttl,
+        ..Default::default()
+    };
+    assert_eq!(config.validate().is_ok(), valid);
+}
+
+#[rstest]
+#[case::zero(Some(0), false)]
+#[case::one(Some(1), true)]
+#[case::unset(None, true)]
+fn test_validate_owner_lookup_concurrency(#[case] concurrency: Option<usize>, #[case] valid: bool) {
+    let config = TracerConfig {
+        owner_lookup_concurrency: concurrency,
+        ..Default::default()
+    };
+    assert_eq!(config.validate().is_ok(), valid);
+    if valid {
+        assert_eq!(config.owner_lookup_concurrency(), concurrency.unwrap_or(DEFAULT_OWNER_LOOKUP_CONCURRENCY));
+    }
+}
+
+#[rstest]
+#[case::zero(Some(0), false)]
+#[case::negative(Some(-30), false)]
+#[case::positive(Some(60), true)]
+#[case::unset(None, true)]
+fn test_validate_heartbeat_interval(#[case] interval: Option<i64>, #[case] valid: bool) {
+    let config = TracerConfig {
+        heartbeat_interval_seconds: interval,
+        ..Default::default()
+    };
+    assert_eq!(config.validate().is_ok(), valid);
+}
+
+#[rstest]
+#[case::valid(
+    "{path: /spec/template/spec/containers/*/image, op: rewrite, pattern: ^docker.io/, replacement: ''}",
+    true
+)]
+#[case::strip("{path: /metadata/annotations/example.com~1build-id, op: strip}", true)]
+#[case::relative_path("{path: metadata/annotations/foo, op: strip}", false)]
+#[case::partial_wildcard("{path: /spec/containers/foo*/image, op: strip}", false)]
+#[case::strip_wildcard("{path: /spec/containers/*, op: strip}", false)]
+#[case::bad_pattern("{path: /spec/image, op: rewrite, pattern: '(unclosed', replacement: x}", false)]
+fn test_validate_transforms(#[case] transform: &str, #[case] valid: bool) {
+    let config_yaml = format!("trackedObjects:\n  apps/v1.Deployment:\n    transforms:\n      - {transform}\n");
+    let config: TracerConfig = serde_yaml::from_str(&config_yaml).unwrap();
+
+    let res = config.validate();
+    assert_eq!(res.is_ok(), valid);
+    if let Err(err) = res {
+        assert!(matches!(err.downcast_ref::<SimKubeError>(), Some(SimKubeError::Config(_))));
+    }
+}
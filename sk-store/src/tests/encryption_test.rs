@@ -0,0 +1,61 @@
+use sk_core::errors::SimKubeError;
+
+use super::*;
+use crate::encryption::*;
+
+const TEST_KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+const OTHER_KEY: &str = "ffeeddccbbaa99887766554433221100ffeeddccbbaa99887766554433221100";
+
+#[fixture]
+fn trace_data() -> Vec<u8> {
+    let mut store = TraceStore::new(Default::default());
+    store.create_or_update_obj(&test_deployment("the-deployment"), 0, None);
+    store.export(0, 1, &Default::default()).unwrap()
+}
+
+#[rstest]
+#[case::msgpack(TraceFormat::Msgpack)]
+#[case::json(TraceFormat::Json)]
+fn test_encryption_roundtrip(#[case] format: TraceFormat) {
+    let mut store = TraceStore::new(Default::default());
+    store.create_or_update_obj(&test_deployment("the-deployment"), 0, None);
+    let trace_data = store.export_as(0, 1, &Default::default(), format).unwrap();
+
+    let key = TraceKey::from_hex(TEST_KEY).unwrap();
+    let encrypted = encrypt_trace(&trace_data, &key).unwrap();
+    assert!(is_encrypted(&encrypted));
+    assert_ne!(encrypted, trace_data);
+
+    let decrypted = decrypt_trace(encrypted, Some(&key)).unwrap();
+    assert_eq!(decrypted, trace_data);
+
+    let store = TraceStore::import(decrypted, &None).unwrap();
+    assert!(store.has_obj(&format!("{TEST_NAMESPACE}/the-deployment")));
+}
+
+#[rstest]
+fn test_decrypt_wrong_key(trace_data: Vec<u8>) {
+    let encrypted = encrypt_trace(&trace_data, &TraceKey::from_hex(TEST_KEY).unwrap()).unwrap();
+    let err = decrypt_trace(encrypted, Some(&TraceKey::from_hex(OTHER_KEY).unwrap())).unwrap_err();
+    assert!(err.to_string().contains("key is wrong"));
+}
+
+#[rstest]
+fn test_decrypt_no_key(trace_data: Vec<u8>) {
+    let encrypted = encrypt_trace(&trace_data, &TraceKey::from_hex(TEST_KEY).unwrap()).unwrap();
+    assert!(decrypt_trace(encrypted.clone(), None).is_err());
+    let err = TraceStore::import(encrypted, &None).err().unwrap();
+    assert!(matches!(err.downcast_ref::<SimKubeError>(), Some(SimKubeError::TraceFormat(_))));
+}
+
+#[rstest]
+fn test_decrypt_unencrypted(trace_data: Vec<u8>) {
+    assert_eq!(decrypt_trace(trace_data.clone(), None).unwrap(), trace_data);
+}
+
+#[rstest]
+#[case::too_short("0001")]
+#[case::not_hex("zz0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f")]
+fn test_trace_key_invalid(#[case] hex: &str) {
+    assert!(TraceKey::from_hex(hex).is_err());
+}
@@ -1,3 +1,7 @@
+use std::collections::{
+    HashMap,
+    HashSet,
+};
 use std::sync::{
     Arc,
     Mutex,
@@ -7,28 +11,46 @@ use clockabilly::mock::MockUtcClock;
 use futures::stream;
 use futures::stream::StreamExt;
 use k8s_openapi::apimachinery::pkg::apis::meta::v1 as metav1;
-use kube::api::DynamicObject;
+use kube::api::{
+    DynamicObject,
+    TypeMeta,
+};
 use kube::runtime::watcher::Event;
 use kube::ResourceExt;
 use serde_json::json;
 use sk_api::v1::ExportFilters;
+use sk_core::errors::SimKubeError;
+use sk_core::k8s::{
+    KubeResourceExt,
+    PodReadiness,
+    PodUsage,
+    GVK,
+};
 use sk_core::macros::*;
 
 use super::*;
+use crate::pod_owners_map::PodLifecyclesMap;
+use crate::trace_header::split_header;
 use crate::watchers::{
     DynObjWatcher,
     KubeObjectStream,
 };
-use crate::TraceStore;
+use crate::{
+    TraceEvent,
+    TraceFormat,
+    TraceStorable,
+    TraceStore,
+    TracerConfig,
+};
 
 fn test_pod(idx: i64) -> DynamicObject {
     DynamicObject {
         metadata: metav1::ObjectMeta {
             namespace: Some(TEST_NAMESPACE.into()),
-            name: Some(format!("pod{idx}").into()),
+            name: Some(format!("pod{idx}")),
             ..Default::default()
         },
-        types: None,
+        types: Some(TypeMeta { api_version: "v1".into(), kind: "Pod".into() }),
         data: json!({"spec": {}}),
     }
 }
@@ -54,10 +76,10 @@ fn test_stream(clock: MockUtcClock) -> KubeObjectStream {
             match state {
                 // Initial conditions: we create 10 regular pods and 5 daemonset pods
                 (-1, id) => {
-                    let mut pods: Vec<_> = (0..10).map(|i| test_pod(i)).collect();
-                    let ds_pods: Vec<_> = (0..5).map(|i| test_daemonset_pod(i)).collect();
+                    let mut pods: Vec<_> = (0..10).map(test_pod).collect();
+                    let ds_pods: Vec<_> = (0..5).map(test_daemonset_pod).collect();
                     pods.extend(ds_pods);
-                    return Some((Ok(Event::Restarted(pods)), (0, id)));
+                    Some((Ok(Event::Restarted(pods)), (0, id)))
                 },
 
                 // We recreate one of the pods at time zero just to make sure there's
@@ -65,14 +87,14 @@ fn test_stream(clock: MockUtcClock) -> KubeObjectStream {
                 (0, id) => {
                     let pod = test_pod(id);
                     let new_ts = c.advance(5);
-                    return Some((Ok(Event::Applied(pod)), (new_ts, id)));
+                    Some((Ok(Event::Applied(pod)), (new_ts, id)))
                 },
 
                 // From times 10..20, we delete one of the regular pods
                 (5..=19, id) => {
                     let pod = test_pod(id);
                     let new_ts = c.advance(5);
-                    return Some((Ok(Event::Deleted(pod)), (new_ts, id + 1)));
+                    Some((Ok(Event::Deleted(pod)), (new_ts, id + 1)))
                 },
 
                 // In times 20..25, we test the various filter options:
@@ -84,36 +106,36 @@ fn test_stream(clock: MockUtcClock) -> KubeObjectStream {
                 (20, id) => {
                     let pod = test_daemonset_pod(7);
                     let new_ts = c.advance(1);
-                    return Some((Ok(Event::Applied(pod)), (new_ts, id)));
+                    Some((Ok(Event::Applied(pod)), (new_ts, id)))
                 },
                 (21, id) => {
                     let pod = test_daemonset_pod(8);
                     let new_ts = c.advance(1);
-                    return Some((Ok(Event::Applied(pod)), (new_ts, id)));
+                    Some((Ok(Event::Applied(pod)), (new_ts, id)))
                 },
                 (22, id) => {
                     let mut pod = test_pod(30);
                     pod.metadata.namespace = Some("kube-system".into());
                     let new_ts = c.advance(1);
-                    return Some((Ok(Event::Applied(pod)), (new_ts, id)));
+                    Some((Ok(Event::Applied(pod)), (new_ts, id)))
                 },
                 (23, id) => {
                     let pod = test_daemonset_pod(1);
                     let new_ts = c.advance(1);
-                    return Some((Ok(Event::Deleted(pod)), (new_ts, id)));
+                    Some((Ok(Event::Deleted(pod)), (new_ts, id)))
                 },
                 (24, id) => {
                     let mut pod = test_pod(31);
                     pod.labels_mut().insert("foo".into(), "bar".into());
                     let new_ts = c.advance(1);
-                    return Some((Ok(Event::Applied(pod)), (new_ts, id)));
+                    Some((Ok(Event::Applied(pod)), (new_ts, id)))
                 },
 
                 // Lastly we delete the remaining "regular" pods
                 (25..=55, id) => {
                     let pod = test_pod(id);
                     let new_ts = c.advance(5);
-                    return Some((Ok(Event::Deleted(pod)), (new_ts, id + 1)));
+                    Some((Ok(Event::Deleted(pod)), (new_ts, id + 1)))
                 },
                 _ => None,
             }
@@ -135,7 +157,8 @@ async fn itest_export(#[case] duration: Option<String>) {
     let s = Arc::new(Mutex::new(TraceStore::new(Default::default())));
 
     // First build up the stream of test data and run the watcher (this advances time to the "end")
-    let w = DynObjWatcher::new_from_parts(test_stream(*clock.clone()), s.clone(), clock);
+    let tracked_gvks = HashSet::from([GVK::new("", "v1", "Pod")]);
+    let w = DynObjWatcher::new_from_parts(test_stream(*clock.clone()), s.clone(), tracked_gvks, clock);
     w.start().await;
 
     // Next export the data with the chosen filters
@@ -146,6 +169,7 @@ async fn itest_export(#[case] duration: Option<String>) {
             ..Default::default()
         }],
         exclude_daemonsets: true,
+        ..Default::default()
     };
 
     let store = s.lock().unwrap();
@@ -164,3 +188,234 @@ async fn itest_export(#[case] duration: Option<String>) {
         Err(e) => panic!("failed with error: {}", e),
     };
 }
+
+#[rstest]
+#[traced_test]
+fn test_import_no_pod_usage() {
+    // Traces exported before we recorded pod usage only have four fields in them
+    let events = vec![TraceEvent {
+        ts: 1,
+        applied_objs: vec![test_pod(0)],
+        deleted_objs: vec![],
+    }];
+    let index = HashMap::from([(test_pod(0).namespaced_name(), EMPTY_OBJ_HASH)]);
+    let lifecycle_data: HashMap<String, PodLifecyclesMap> = HashMap::new();
+    let data = rmp_serde::to_vec_named(&(TracerConfig::default(), &events, &index, &lifecycle_data)).unwrap();
+
+    let store = TraceStore::import(data, &None).unwrap();
+    assert_eq!(store.events, events);
+    assert!(store.pod_usage.is_empty());
+}
+
+#[rstest]
+#[traced_test]
+fn test_export_pod_usage() {
+    let owner_ns_name = test_pod(0).namespaced_name();
+    let mut store = TraceStore::new(Default::default());
+    store.create_or_update_obj(&test_pod(0), 0, None);
+    store
+        .pod_owners
+        .store_new_pod_lifecycle("test/pod-a", &owner_ns_name, 1234, &PodLifecycleData::Running(0));
+    store
+        .pod_owners
+        .store_new_pod_lifecycle("test/pod-b", "test/untracked", 1234, &PodLifecycleData::Running(0));
+    store.record_pod_usage("test/pod-a", PodUsage { ts: 0, ..Default::default() });
+    store.record_pod_usage("test/pod-b", PodUsage { ts: 0, ..Default::default() });
+
+    let data = store.export(0, 1, &Default::default()).unwrap();
+    let new_store = TraceStore::import(data, &None).unwrap();
+    assert_eq!(new_store.pod_usage_for(&owner_ns_name, 1234), Some(&vec![PodUsage { ts: 0, ..Default::default() }]));
+    assert_eq!(new_store.pod_usage_for("test/untracked", 1234), None);
+}
+
+#[rstest]
+#[case::open(false, PodLifecycleData::Running(5))]
+#[case::closed(true, PodLifecycleData::Finished(5, 20, None))]
+#[traced_test]
+fn test_export_close_open_intervals(#[case] close_open_intervals: bool, #[case] expected: PodLifecycleData) {
+    let owner_ns_name = test_pod(0).namespaced_name();
+    let mut store = TraceStore::new(Default::default());
+    store.create_or_update_obj(&test_pod(0), 0, None);
+    store
+        .pod_owners
+        .store_new_pod_lifecycle("test/pod-a", &owner_ns_name, 1234, &PodLifecycleData::Running(5));
+    store.pod_owners.store_new_pod_lifecycle(
+        "test/pod-b",
+        &owner_ns_name,
+        1234,
+        &PodLifecycleData::Finished(1, 3, None),
+    );
+
+    let filter = ExportFilters {
+        close_open_intervals: Some(close_open_intervals),
+        ..Default::default()
+    };
+    let data = store.export(0, 20, &filter).unwrap();
+    let new_store = TraceStore::import(data, &None).unwrap();
+
+    // Pods that already finished are left alone either way
+    assert_eq!(
+        new_store.pod_owners.lifecycle_data_for(&owner_ns_name, 1234),
+        Some(&vec![expected, PodLifecycleData::Finished(1, 3, None)])
+    );
+}
+
+#[rstest]
+#[case::msgpack(TraceFormat::Msgpack)]
+#[case::json(TraceFormat::Json)]
+#[traced_test]
+fn test_export_format_round_trip(#[case] format: TraceFormat) {
+    let owner_ns_name = test_pod(0).namespaced_name();
+    let mut store = TraceStore::new(Default::default());
+    store.create_or_update_obj(&test_pod(0), 0, None);
+    store.create_or_update_obj(&test_pod(1), 5, None);
+    store.delete_obj(&test_pod(1), 7);
+    store.pod_owners.store_new_pod_lifecycle(
+        "test/pod-a",
+        &owner_ns_name,
+        1234,
+        &PodLifecycleData::Finished(1, 3, None),
+    );
+
+    let data = store.export_as(0, 10, &Default::default(), format).unwrap();
+    assert_eq!(TraceFormat::detect(&data), format);
+    let (header, _) = split_header(&data).unwrap();
+
+    let imported = TraceStore::import(data, &None).unwrap();
+    assert_eq!(header.unwrap().event_count, imported.iter().count());
+    let expected = TraceStore::import(store.export(0, 10, &Default::default()).unwrap(), &None).unwrap();
+    assert_eq!(imported.iter().collect::<Vec<_>>(), expected.iter().collect::<Vec<_>>());
+    assert_eq!(
+        imported.pod_owners.lifecycle_data_for(&owner_ns_name, 1234),
+        Some(&vec![PodLifecycleData::Finished(1, 3, None)])
+    );
+}
+
+#[rstest]
+#[traced_test]
+fn test_export_header() {
+    let mut depl = test_pod(0);
+    depl.metadata.name = Some("depl".into());
+    depl.types = Some(TypeMeta {
+        api_version: "apps/v1".into(),
+        kind: "Deployment".into(),
+    });
+
+    let mut store = TraceStore::new(Default::default());
+    store.create_or_update_obj(&test_pod(0), 0, None);
+    store.create_or_update_obj(&depl, 2, None);
+    store.create_or_update_obj(&test_pod(1), 5, None);
+    store.delete_obj(&test_pod(0), 7);
+
+    let path = std::env::temp_dir().join(format!("sk-header-test-{}", std::process::id()));
+    std::fs::write(&path, store.export(0, 10, &Default::default()).unwrap()).unwrap();
+    let header = TraceStore::read_header(path.to_str().unwrap()).unwrap().unwrap();
+    let imported = TraceStore::import(std::fs::read(&path).unwrap(), &None).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    // The header should match what's actually in the trace once it's loaded
+    let mut object_counts: HashMap<GVK, HashSet<String>> = HashMap::new();
+    for (evt, _) in imported.iter() {
+        for obj in evt.applied_objs.iter().chain(evt.deleted_objs.iter()) {
+            object_counts
+                .entry(GVK::from_dynamic_obj(obj).unwrap())
+                .or_default()
+                .insert(obj.namespaced_name());
+        }
+    }
+    let object_counts: HashMap<_, _> = object_counts.into_iter().map(|(gvk, objs)| (gvk, objs.len())).collect();
+    assert_eq!(
+        object_counts,
+        HashMap::from([(GVK::new("", "v1", "Pod"), 2), (GVK::new("apps", "v1", "Deployment"), 1)])
+    );
+    assert_eq!(header.object_counts, object_counts);
+    assert_eq!(header.object_count(), imported.object_count());
+    assert_eq!(header.event_count, imported.iter().count());
+    assert_eq!(header.time_bounds, imported.time_bounds());
+
+    // The config digest only depends on the config
+    let other = TraceStore::new(TracerConfig { object_history_depth: 2, ..Default::default() });
+    let (other_header, _) = split_header(&other.export_all().unwrap()).unwrap();
+    let (empty_header, _) = split_header(&TraceStore::new(Default::default()).export_all().unwrap()).unwrap();
+    assert_ne!(other_header.unwrap().config_digest, header.config_digest);
+    assert_eq!(empty_header.unwrap().config_digest, header.config_digest);
+}
+
+#[rstest]
+#[traced_test]
+fn test_read_header_no_header() {
+    // Traces exported before we started writing headers are still readable, they just don't have one
+    let lifecycle_data: HashMap<String, PodLifecyclesMap> = HashMap::new();
+    let data = rmp_serde::to_vec_named(&(
+        TracerConfig::default(),
+        Vec::<TraceEvent>::new(),
+        HashMap::<String, u64>::new(),
+        &lifecycle_data,
+    ))
+    .unwrap();
+
+    let path = std::env::temp_dir().join(format!("sk-no-header-test-{}", std::process::id()));
+    std::fs::write(&path, &data).unwrap();
+    let header = TraceStore::read_header(path.to_str().unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(header, None);
+    assert!(TraceStore::import(data, &None).is_ok());
+}
+
+#[rstest]
+#[case::msgpack(vec![0x81, 0xc1, 0xc1])]
+#[case::json(b"{\"version\": 2}\n[1, 2".to_vec())]
+fn test_import_malformed(#[case] data: Vec<u8>) {
+    let err = TraceStore::import(data, &None).err().unwrap();
+    assert!(matches!(err.downcast_ref::<SimKubeError>(), Some(SimKubeError::Serialization(_))));
+}
+
+#[rstest]
+#[traced_test]
+fn test_checkpoint_roundtrip() {
+    let owner_ns_name = test_pod(0).namespaced_name();
+    let config = TracerConfig {
+        max_objects_per_namespace: Some(1),
+        ..Default::default()
+    };
+    let mut store = TraceStore::new(config);
+    store.create_or_update_obj(&test_pod(0), 0, None);
+    store.create_or_update_obj(&test_pod(1), 1, None); // dropped, since the namespace is full
+    store
+        .pod_owners
+        .store_new_pod_lifecycle("test/pod-a", &owner_ns_name, 1234, &PodLifecycleData::Running(0));
+    store.record_pod_usage("test/pod-a", PodUsage { ts: 0, ..Default::default() });
+    store.record_pod_readiness("test/pod-a", PodReadiness { start_ts: 0, ready_ts: 5 });
+
+    let data = store.checkpoint().unwrap();
+    let mut new_store = TraceStore::from_checkpoint(&data, TracerConfig::default()).unwrap();
+
+    assert_eq!(new_store.events, store.events);
+    assert_eq!(new_store.index, store.index);
+    assert_eq!(new_store.pod_usage_for(&owner_ns_name, 1234), Some(&vec![PodUsage { ts: 0, ..Default::default() }]));
+    assert_eq!(
+        new_store.pod_readiness_for(&owner_ns_name, 1234),
+        Some(&vec![PodReadiness { start_ts: 0, ready_ts: 5 }])
+    );
+    assert_eq!(new_store.namespace_counts, store.namespace_counts);
+    assert_eq!(new_store.capped_namespaces, store.capped_namespaces);
+
+    // The passed-in config takes precedence over the one in the checkpoint
+    assert_eq!(new_store.config.max_objects_per_namespace, None);
+
+    // The pod index is restored too, so updates to pods we were already tracking (e.g., because
+    // the pod watcher re-lists everything on startup) go to the existing entry
+    assert!(new_store.pod_owners.has_pod("test/pod-a"));
+    new_store
+        .record_pod_lifecycle("test/pod-a", None, vec![], &PodLifecycleData::Finished(0, 10, None))
+        .unwrap();
+    assert_eq!(
+        new_store.pod_owners.lifecycle_data_for(&owner_ns_name, 1234),
+        Some(&vec![PodLifecycleData::Finished(0, 10, None)])
+    );
+
+    // Re-recording the same readiness data doesn't duplicate it
+    new_store.record_pod_readiness("test/pod-a", PodReadiness { start_ts: 0, ready_ts: 5 });
+    assert_eq!(new_store.pod_readiness_for(&owner_ns_name, 1234).unwrap().len(), 1);
+}
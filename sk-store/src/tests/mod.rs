@@ -1,5 +1,10 @@
+mod config_test;
+mod encryption_test;
 mod import_export_test;
 mod pod_owners_map_test;
+mod scrub_test;
+mod self_check_test;
+mod trace_schema_test;
 mod trace_store_test;
 
 use rstest::*;
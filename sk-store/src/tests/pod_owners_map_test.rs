@@ -39,12 +39,18 @@ fn test_filter_owners_map() {
     let index = HashMap::from([("test/deployment1".into(), 9876), ("test/deployment2".into(), 5432)]);
     let owners_map = PodOwnersMap::new_from_parts(
         HashMap::from([
-            ("test/deployment1".into(), PodLifecyclesMap::from([(1234, vec![PodLifecycleData::Finished(1, 2)])])),
+            (
+                "test/deployment1".into(),
+                PodLifecyclesMap::from([(1234, vec![PodLifecycleData::Finished(1, 2, None)])]),
+            ),
             (
                 "test/deployment2".into(),
                 PodLifecyclesMap::from([(5678, vec![PodLifecycleData::Running(6), PodLifecycleData::Running(11)])]),
             ),
-            ("test/deployment3".into(), PodLifecyclesMap::from([(9999, vec![PodLifecycleData::Finished(1, 2)])])),
+            (
+                "test/deployment3".into(),
+                PodLifecyclesMap::from([(9999, vec![PodLifecycleData::Finished(1, 2, None)])]),
+            ),
         ]),
         HashMap::new(),
     );
@@ -66,13 +72,13 @@ fn test_filter_lifecycles_map() {
         vec![
             // These overlap
             PodLifecycleData::Running(6),
-            PodLifecycleData::Finished(7, 9),
-            PodLifecycleData::Finished(1, 8),
-            PodLifecycleData::Finished(5, 10),
+            PodLifecycleData::Finished(7, 9, None),
+            PodLifecycleData::Finished(1, 8, None),
+            PodLifecycleData::Finished(5, 10, None),
             // These don't
             PodLifecycleData::Running(10),
             PodLifecycleData::Running(11),
-            PodLifecycleData::Finished(1, 2),
+            PodLifecycleData::Finished(1, 2, None),
         ],
     )]);
     let expected_map = PodLifecyclesMap::from([(1234, lifecycles_map[&1234][0..4].into())]);
@@ -87,7 +93,7 @@ fn test_filter_lifecycles_map_empty() {
         vec![
             // These don't overlap
             PodLifecycleData::Running(11),
-            PodLifecycleData::Finished(1, 2),
+            PodLifecycleData::Finished(1, 2, None),
         ],
     )]);
     let res = filter_lifecycles_map(START_TS, END_TS, &lifecycles_map);
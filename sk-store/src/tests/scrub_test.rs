@@ -0,0 +1,168 @@
+use std::collections::{
+    BTreeMap,
+    HashMap,
+    VecDeque,
+};
+
+use kube::api::{
+    DynamicObject,
+    TypeMeta,
+};
+use kube::ResourceExt;
+use regex::Regex;
+use serde_json::json;
+use sk_core::k8s::{
+    KubeResourceExt,
+    PodEvent,
+    PodLifecycleData,
+    PodUsage,
+};
+use sk_core::macros::*;
+use sk_core::prelude::*;
+
+use super::*;
+use crate::pod_owners_map::PodOwnersMap;
+use crate::{
+    TraceEvent,
+    TraceScrubber,
+    TraceStore,
+};
+
+const PROD_NS: &str = "prod";
+const DEPL_NAME: &str = "frontend";
+const SECRET_NAME: &str = "db-creds";
+const NODE_NAME: &str = "node-1";
+
+fn obj(kind: &str, namespace: Option<&str>, name: &str, data: serde_json::Value) -> DynamicObject {
+    DynamicObject {
+        metadata: metav1::ObjectMeta {
+            namespace: namespace.map(|ns| ns.into()),
+            name: Some(name.into()),
+            ..Default::default()
+        },
+        types: Some(TypeMeta { api_version: "v1".into(), kind: kind.into() }),
+        data,
+    }
+}
+
+#[fixture]
+fn store() -> TraceStore {
+    let mut ns = obj("Namespace", None, PROD_NS, json!({}));
+    ns.metadata.labels = klabel!(KUBERNETES_IO_METADATA_NAME_KEY => PROD_NS);
+
+    let mut depl = obj("Deployment", Some(PROD_NS), DEPL_NAME, json!({"spec": {"replicas": 3}}));
+    depl.metadata.annotations = Some(BTreeMap::from([
+        ("internal.example.com/owner".into(), "alice@example.com".into()),
+        ("simkube.io/keep-me".into(), "true".into()),
+    ]));
+
+    let secret = obj("Secret", Some(PROD_NS), SECRET_NAME, json!({"data": {"password": "aHVudGVyMg=="}}));
+
+    let depl_ns_name = depl.namespaced_name();
+    let pod_ns_name = format!("{PROD_NS}/{DEPL_NAME}-abc123");
+    TraceStore {
+        events: VecDeque::from([
+            TraceEvent {
+                ts: 1,
+                applied_objs: vec![ns, depl.clone(), secret],
+                ..Default::default()
+            },
+            TraceEvent {
+                ts: 2,
+                deleted_objs: vec![depl],
+                ..Default::default()
+            },
+        ]),
+        index: HashMap::from([(PROD_NS.into(), EMPTY_OBJ_HASH), (depl_ns_name.clone(), EMPTY_OBJ_HASH)]),
+        pod_owners: PodOwnersMap::new_from_parts(
+            HashMap::from([(depl_ns_name.clone(), HashMap::from([(1234, vec![PodLifecycleData::Running(1)])]))]),
+            HashMap::from([(pod_ns_name, (depl_ns_name.clone(), 1234, 0))]),
+        ),
+        pod_usage: HashMap::from([(
+            depl_ns_name.clone(),
+            HashMap::from([(1234, vec![PodUsage { ts: 1, ..Default::default() }])]),
+        )]),
+        pod_events: HashMap::from([(
+            depl_ns_name,
+            HashMap::from([(
+                1234,
+                vec![PodEvent {
+                    ts: 1,
+                    event_type: "Normal".into(),
+                    reason: "Scheduled".into(),
+                    message: format!("Successfully assigned {PROD_NS}/{DEPL_NAME}-abc123 to {NODE_NAME}"),
+                    count: 1,
+                }],
+            )]),
+        )]),
+        ..Default::default()
+    }
+}
+
+#[rstest]
+fn test_scrub_referential_integrity(mut store: TraceStore) {
+    let mut scrubber = TraceScrubber::new(vec![Regex::new("^internal\\.example\\.com/").unwrap()]);
+    store.scrub(&mut scrubber).unwrap();
+
+    let ns = &store.events[0].applied_objs[0];
+    let depl = &store.events[0].applied_objs[1];
+    let deleted_depl = &store.events[1].deleted_objs[0];
+    let depl_ns_name = depl.namespaced_name();
+
+    // The namespace object, the namespace label, and the namespace of the objects in it all agree
+    assert_eq!(ns.metadata.name, depl.metadata.namespace);
+    assert_eq!(ns.labels()[KUBERNETES_IO_METADATA_NAME_KEY], ns.name_any());
+
+    // Everything that refers to the namespace or the deployment uses the same pseudonym
+    assert_eq!(deleted_depl.namespaced_name(), depl_ns_name);
+    let mut index_keys: Vec<_> = store.index.keys().collect();
+    index_keys.sort();
+    assert_eq!(index_keys, vec![&ns.name_any(), &depl_ns_name]);
+    assert_eq!(store.pod_owners.lifecycle_data_for(&depl_ns_name, 1234), Some(&vec![PodLifecycleData::Running(1)]));
+    assert_eq!(
+        store
+            .pod_owners
+            .pod_owner_meta(&scrubber.namespaced_name("prod/frontend-abc123"))
+            .unwrap()
+            .0,
+        depl_ns_name
+    );
+    assert!(store.pod_usage_for(&depl_ns_name, 1234).is_some());
+
+    // The scrubber should return the same mapping it used on the store
+    assert_eq!(scrubber.namespaced_name(&format!("{PROD_NS}/{DEPL_NAME}")), depl_ns_name);
+}
+
+#[rstest]
+fn test_scrub_removes_sensitive_data(mut store: TraceStore) {
+    let mut scrubber = TraceScrubber::new(vec![Regex::new("^internal\\.example\\.com/").unwrap()]);
+    store.scrub(&mut scrubber).unwrap();
+
+    let depl = &store.events[0].applied_objs[1];
+    let secret = &store.events[0].applied_objs[2];
+    assert_eq!(depl.annotations().keys().collect::<Vec<_>>(), vec!["simkube.io/keep-me"]);
+    assert_eq!(depl.data, json!({"spec": {"replicas": 3}}));
+    assert_eq!(secret.data, json!({}));
+
+    let depl_ns_name = depl.namespaced_name();
+    let pod_events = &store.pod_events[&depl_ns_name][&1234];
+    assert_eq!(pod_events[0].reason, "Scheduled");
+    assert_eq!(pod_events[0].message, "");
+
+    // None of the original names should show up anywhere in the exported trace
+    let data = store.export_all().unwrap();
+    for name in [PROD_NS, DEPL_NAME, SECRET_NAME, NODE_NAME, "alice"] {
+        assert!(!data.windows(name.len()).any(|w| w == name.as_bytes()), "found {name} in scrubbed trace");
+    }
+}
+
+#[rstest]
+fn test_scrub_export_all_round_trip(mut store: TraceStore) {
+    store.scrub(&mut TraceScrubber::default()).unwrap();
+
+    let new_store = TraceStore::import(store.export_all().unwrap(), &None).unwrap();
+    assert_eq!(new_store.events, store.events);
+    assert_eq!(new_store.index, store.index);
+    assert_eq!(new_store.pod_owners.lifecycles(), store.pod_owners.lifecycles());
+    assert_eq!(new_store.pod_usage, store.pod_usage);
+}
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+use kube::api::DynamicObject;
+use serde_json::json;
+use sk_core::k8s::GVK;
+
+use super::*;
+use crate::pod_owners_map::PodOwnersMap;
+use crate::TrackedObjectConfig;
+
+const POD_HASH: u64 = 1234;
+
+#[fixture]
+fn store() -> TraceStore {
+    let mut store = TraceStore::new(TracerConfig {
+        tracked_objects: HashMap::from([
+            (GVK::new("apps", "v1", "Deployment"), Default::default()),
+            (GVK::new("apps", "v1", "ReplicaSet"), TrackedObjectConfig::default()),
+        ]),
+        ..Default::default()
+    });
+    store.create_or_update_obj(&test_deployment(TEST_DEPLOYMENT), 1, None);
+    store
+}
+
+fn replicaset(name: &str, owner: &str) -> DynamicObject {
+    serde_json::from_value(json!({
+        "apiVersion": "apps/v1",
+        "kind": "ReplicaSet",
+        "metadata": {
+            "namespace": TEST_NAMESPACE,
+            "name": name,
+            "ownerReferences": [
+                {"apiVersion": "apps/v1", "kind": "Deployment", "name": owner, "uid": "1"},
+                {"apiVersion": "example.com/v1", "kind": "Untracked", "name": "whatever", "uid": "2"},
+            ],
+        },
+    }))
+    .unwrap()
+}
+
+#[rstest]
+fn test_validate_consistent(mut store: TraceStore) {
+    let owner_ns_name = format!("{TEST_NAMESPACE}/{TEST_DEPLOYMENT}");
+    store.create_or_update_obj(&replicaset("the-rs", TEST_DEPLOYMENT), 2, None);
+    store.pod_owners = PodOwnersMap::new_from_parts(
+        HashMap::from([(owner_ns_name, HashMap::from([(POD_HASH, vec![PodLifecycleData::Finished(2, 5, None)])]))]),
+        HashMap::new(),
+    );
+
+    // The deployment getting deleted doesn't make its pods (or the replicaset) invalid
+    store.delete_obj(&test_deployment(TEST_DEPLOYMENT), 3);
+    assert_eq!(store.validate(), vec![]);
+}
+
+#[rstest]
+fn test_validate_inconsistent(mut store: TraceStore) {
+    let owner_ns_name = format!("{TEST_NAMESPACE}/{TEST_DEPLOYMENT}");
+    store.create_or_update_obj(&replicaset("the-rs", "missing-depl"), 2, None);
+    store.pod_owners = PodOwnersMap::new_from_parts(
+        HashMap::from([
+            (
+                owner_ns_name.clone(),
+                HashMap::from([(
+                    POD_HASH,
+                    vec![PodLifecycleData::Finished(2, 5, None), PodLifecycleData::Finished(10, 4, None)],
+                )]),
+            ),
+            ("other-ns/ghost".into(), HashMap::from([(POD_HASH, vec![PodLifecycleData::Running(2)])])),
+        ]),
+        HashMap::new(),
+    );
+
+    // Owners are checked in sorted order
+    assert_eq!(
+        store.validate(),
+        vec![
+            ValidationIssue::UnresolvedOwner {
+                ns_name: format!("{TEST_NAMESPACE}/the-rs"),
+                owner_gvk: GVK::new("apps", "v1", "Deployment"),
+                owner_name: "missing-depl".into(),
+            },
+            ValidationIssue::UnknownPodOwner { owner_ns_name: "other-ns/ghost".into() },
+            ValidationIssue::MissingNamespace {
+                owner_ns_name: "other-ns/ghost".into(),
+                namespace: "other-ns".into(),
+            },
+            ValidationIssue::InvalidLifecycle {
+                owner_ns_name,
+                pod_hash: POD_HASH,
+                seq: 1,
+                lifecycle: PodLifecycleData::Finished(10, 4, None),
+            },
+        ]
+    );
+}
@@ -0,0 +1,281 @@
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use kube::api::DynamicObject;
+use regex::Regex;
+use serde_json::{
+    json,
+    Value,
+};
+use sk_core::k8s::{
+    KubeResourceExt,
+    PodEvent,
+    PodOrdinal,
+    PodReadiness,
+    PodScheduling,
+    PodUsage,
+    GVK,
+};
+
+use super::*;
+use crate::trace_header::split_header;
+use crate::{
+    TraceFormat,
+    TraceStorable,
+    TraceStore,
+    TracerConfig,
+};
+
+// Just enough of JSON schema to check the trace schema with; anything the trace schema uses needs
+// to be handled here, otherwise it'd be silently ignored (see test_schema_keywords_are_checked)
+const KNOWN_KEYWORDS: &[&str] = &[
+    "$schema",
+    "$defs",
+    "$ref",
+    "title",
+    "type",
+    "const",
+    "oneOf",
+    "properties",
+    "required",
+    "additionalProperties",
+    "propertyNames",
+    "prefixItems",
+    "items",
+    "minItems",
+    "minimum",
+    "pattern",
+];
+
+fn validate(schema: &Value, root: &Value, instance: &Value, path: &str) -> Result<(), String> {
+    let schema = match schema {
+        Value::Bool(true) => return Ok(()),
+        Value::Bool(false) => return Err(format!("{path}: not allowed")),
+        Value::Object(o) => o,
+        _ => panic!("invalid schema at {path}"),
+    };
+
+    if let Some(Value::String(rf)) = schema.get("$ref") {
+        let target = root.pointer(rf.strip_prefix('#').unwrap()).unwrap();
+        validate(target, root, instance, path)?;
+    }
+
+    if let Some(types) = schema.get("type") {
+        let types: Vec<_> = match types {
+            Value::Array(ts) => ts.iter().map(|t| t.as_str().unwrap()).collect(),
+            t => vec![t.as_str().unwrap()],
+        };
+        let matches = |t: &str| match t {
+            "object" => instance.is_object(),
+            "array" => instance.is_array(),
+            "string" => instance.is_string(),
+            "integer" => instance.is_i64() || instance.is_u64(),
+            "boolean" => instance.is_boolean(),
+            "null" => instance.is_null(),
+            _ => panic!("unknown type {t}"),
+        };
+        if !types.into_iter().any(matches) {
+            return Err(format!("{path}: wrong type for {instance}"));
+        }
+    }
+
+    if let Some(c) = schema.get("const") {
+        if c != instance {
+            return Err(format!("{path}: expected {c}"));
+        }
+    }
+
+    if let Some(Value::Array(options)) = schema.get("oneOf") {
+        let n = options.iter().filter(|s| validate(s, root, instance, path).is_ok()).count();
+        if n != 1 {
+            return Err(format!("{path}: {instance} matched {n} options"));
+        }
+    }
+
+    if let Some(min) = schema.get("minimum") {
+        if instance.as_f64().is_some_and(|v| v < min.as_f64().unwrap()) {
+            return Err(format!("{path}: below minimum"));
+        }
+    }
+
+    if let (Some(Value::String(pattern)), Some(s)) = (schema.get("pattern"), instance.as_str()) {
+        if !Regex::new(pattern).unwrap().is_match(s) {
+            return Err(format!("{path}: {s} does not match {pattern}"));
+        }
+    }
+
+    if let Some(obj) = instance.as_object() {
+        let props = schema.get("properties").and_then(Value::as_object);
+        for key in schema.get("required").and_then(Value::as_array).into_iter().flatten() {
+            if !obj.contains_key(key.as_str().unwrap()) {
+                return Err(format!("{path}: missing {key}"));
+            }
+        }
+        for (key, val) in obj {
+            let key_path = format!("{path}/{key}");
+            if let Some(names) = schema.get("propertyNames") {
+                validate(names, root, &json!(key), &key_path)?;
+            }
+            match props.and_then(|p| p.get(key)) {
+                Some(prop) => validate(prop, root, val, &key_path)?,
+                None => {
+                    if let Some(additional) = schema.get("additionalProperties") {
+                        validate(additional, root, val, &key_path)?;
+                    }
+                },
+            }
+        }
+    }
+
+    if let Some(arr) = instance.as_array() {
+        if let Some(min) = schema.get("minItems") {
+            if (arr.len() as u64) < min.as_u64().unwrap() {
+                return Err(format!("{path}: too few items"));
+            }
+        }
+        let prefix = schema.get("prefixItems").and_then(Value::as_array).map_or(&[][..], |p| &p[..]);
+        for (i, item) in arr.iter().enumerate() {
+            let item_path = format!("{path}/{i}");
+            match prefix.get(i) {
+                Some(s) => validate(s, root, item, &item_path)?,
+                None => {
+                    if let Some(items) = schema.get("items") {
+                        validate(items, root, item, &item_path)?;
+                    }
+                },
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// The header and the trace data as a two-element array, which is what the schema describes
+fn exported_json(store: &TraceStore) -> Value {
+    let data = store.export_as(0, 100, &Default::default(), TraceFormat::Json).unwrap();
+    let (header, rest) = split_header(&data).unwrap();
+    json!([header.unwrap(), serde_json::from_slice::<Value>(rest).unwrap()])
+}
+
+#[fixture]
+fn store() -> TraceStore {
+    let depl: DynamicObject = serde_json::from_value(json!({
+        "apiVersion": "apps/v1",
+        "kind": "Deployment",
+        "metadata": {"namespace": TEST_NAMESPACE, "name": TEST_DEPLOYMENT},
+        "spec": {"replicas": 1},
+    }))
+    .unwrap();
+    let owner = depl.namespaced_name();
+
+    let mut store = TraceStore::new(TracerConfig {
+        tracked_objects: [(
+            GVK::new("apps", "v1", "Deployment"),
+            TrackedObjectConfig {
+                pod_spec_template_path: Some("/spec/template".into()),
+                track_lifecycle: true,
+                ..Default::default()
+            },
+        )]
+        .into(),
+        object_history_depth: 2,
+        ..Default::default()
+    });
+    store.create_or_update_obj(&depl, 1, None);
+    let mut scaled = depl.clone();
+    scaled.data["spec"]["replicas"] = json!(2);
+    store.create_or_update_obj(&scaled, 5, None);
+    store.delete_obj(&scaled, 10);
+
+    for (i, lifecycle) in [
+        PodLifecycleData::Running(2),
+        PodLifecycleData::Finished(2, 8, None),
+        PodLifecycleData::Finished(3, 9, Some("OOMKilled".into())),
+    ]
+    .iter()
+    .enumerate()
+    {
+        store
+            .pod_owners
+            .store_new_pod_lifecycle(&format!("{TEST_NAMESPACE}/pod-{i}"), &owner, 1234, lifecycle);
+    }
+
+    let usage = PodUsage {
+        ts: 3,
+        containers: [("nginx".into(), [("cpu".into(), Quantity("100m".into()))].into())].into(),
+    };
+    store.pod_usage.insert(owner.clone(), [(1234, vec![usage])].into());
+    store
+        .pod_readiness
+        .insert(owner.clone(), [(1234, vec![PodReadiness { start_ts: 2, ready_ts: 4 }])].into());
+    let event = PodEvent {
+        ts: 2,
+        event_type: "Normal".into(),
+        reason: "Pulling".into(),
+        message: "pulling image".into(),
+        count: 1,
+    };
+    store.pod_events.insert(owner.clone(), [(1234, vec![event])].into());
+    store
+        .pod_env
+        .insert(owner.clone(), [(1234, [("nginx".into(), [("FOO".into(), "bar".into())].into())].into())].into());
+    store
+        .pod_ordinals
+        .insert(owner.clone(), [(1234, vec![PodOrdinal { ordinal: 0, lifecycle_idx: 1 }])].into());
+    store
+        .pod_scheduling
+        .insert(owner.clone(), [(1234, PodScheduling::default())].into());
+    store.pod_probes.insert(owner, [(1234, Default::default())].into());
+    store
+}
+
+#[rstest]
+fn test_exported_trace_matches_schema(store: TraceStore) {
+    let schema = TraceFormat::schema();
+    let exported = exported_json(&store);
+    validate(&schema, &schema, &exported, "").unwrap();
+
+    // Make sure we actually exported something in every part of the trace, otherwise the test
+    // above doesn't tell us much
+    for (i, part) in exported[1].as_array().unwrap().iter().enumerate() {
+        assert!(
+            part.as_object()
+                .map_or_else(|| !part.as_array().unwrap().is_empty(), |o| !o.is_empty()),
+            "{i}"
+        );
+    }
+
+    // Importing and re-exporting the trace shouldn't change its shape either
+    let data = store.export_as(0, 100, &Default::default(), TraceFormat::Json).unwrap();
+    let reimported = TraceStore::import(data, &None).unwrap();
+    validate(&schema, &schema, &exported_json(&reimported), "").unwrap();
+}
+
+#[rstest]
+#[case::bad_header(|v: &mut Value| v[0]["configDigest"] = json!("nope"))]
+#[case::bad_event(|v: &mut Value| v[1][1][0]["ts"] = json!("yesterday"))]
+#[case::bad_object(|v: &mut Value| v[1][1][1]["applied_objs"][0]["metadata"] = json!({}))]
+#[case::bad_lifecycle(|v: &mut Value| v[1][3][format!("{TEST_NAMESPACE}/{TEST_DEPLOYMENT}")]["1234"][0] = json!({"Exploded": 5}))]
+#[case::missing_part(|v: &mut Value| { v[1].as_array_mut().unwrap().pop(); })]
+fn test_schema_rejects_malformed_trace(store: TraceStore, #[case] mangle: fn(&mut Value)) {
+    let schema = TraceFormat::schema();
+    let mut exported = exported_json(&store);
+    mangle(&mut exported);
+    validate(&schema, &schema, &exported, "").unwrap_err();
+}
+
+#[rstest]
+fn test_schema_keywords_are_checked() {
+    fn check(v: &Value, in_props: bool) {
+        match v {
+            Value::Object(o) => {
+                for (k, child) in o {
+                    // Property names and $defs entries can be anything
+                    assert!(in_props || KNOWN_KEYWORDS.contains(&k.as_str()), "unknown keyword {k}");
+                    check(child, k == "properties" || k == "$defs");
+                }
+            },
+            Value::Array(a) => a.iter().for_each(|child| check(child, false)),
+            _ => (),
+        }
+    }
+    check(&TraceFormat::schema(), false);
+}
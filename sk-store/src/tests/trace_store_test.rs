@@ -1,11 +1,29 @@
-use std::collections::HashMap;
+use std::collections::{
+    HashMap,
+    HashSet,
+};
 
 use assertables::*;
-use kube::api::DynamicObject;
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use kube::api::{
+    DynamicObject,
+    TypeMeta,
+};
+use kube::ResourceExt;
 use serde_json::json;
-use sk_api::v1::ExportFilters;
+use sk_api::v1::{
+    DanglingRefPolicy,
+    ExportFilters,
+};
+use sk_core::jsonutils;
 use sk_core::k8s::{
+    correlate_pod_events,
     KubeResourceExt,
+    PodEnv,
+    PodExt,
+    PodOrdinal,
+    PodReadiness,
+    PodUsage,
     GVK,
 };
 
@@ -20,8 +38,10 @@ fn tracer() -> TraceStore {
             TrackedObjectConfig {
                 track_lifecycle: true,
                 pod_spec_template_path: Some("/spec/template".into()),
+                ..Default::default()
             },
         )]),
+        ..Default::default()
     })
 }
 
@@ -38,6 +58,12 @@ fn test_obj(#[default("obj")] name: &str) -> DynamicObject {
     }
 }
 
+fn test_obj_in(ns: &str, name: &str) -> DynamicObject {
+    let mut obj = test_obj(name);
+    obj.metadata.namespace = Some(ns.into());
+    obj
+}
+
 #[fixture]
 fn owner_ref() -> metav1::OwnerReference {
     metav1::OwnerReference {
@@ -64,7 +90,7 @@ fn test_lookup_pod_lifecycle_no_hash(mut tracer: TraceStore) {
 #[rstest]
 fn test_lookup_pod_lifecycle(mut tracer: TraceStore) {
     let owner_ns_name = format!("{TEST_NAMESPACE}/{TEST_DEPLOYMENT}");
-    let pod_lifecycle = PodLifecycleData::Finished(1, 2);
+    let pod_lifecycle = PodLifecycleData::Finished(1, 2, None);
 
     tracer.index.insert(owner_ns_name.clone(), 1234);
     tracer.pod_owners = PodOwnersMap::new_from_parts(
@@ -76,6 +102,26 @@ fn test_lookup_pod_lifecycle(mut tracer: TraceStore) {
     assert_eq!(res, pod_lifecycle);
 }
 
+#[rstest]
+#[case::complete(true)]
+#[case::still_running(false)]
+fn test_lookup_pod_lifecycle_completed_job(mut tracer: TraceStore, #[case] complete: bool) {
+    let mut job = test_completed_job();
+    if !complete {
+        job.data["status"]["completionTime"] = serde_json::Value::Null;
+    }
+    let owner_ns_name = job.namespaced_name();
+    tracer.create_or_update_obj(&job, 0, None);
+
+    // 6 completions, 3 at a time, so the 10 minutes the job took is split into two 5-minute waves
+    let start_ts = 1704067200;
+    let expected = match complete {
+        true => PodLifecycleData::Finished(start_ts, start_ts + 300, None),
+        false => PodLifecycleData::Empty,
+    };
+    assert_eq!(tracer.lookup_pod_lifecycle(&owner_ns_name, EMPTY_POD_SPEC_HASH, 0), expected);
+}
+
 #[rstest]
 fn test_collect_events_filtered(mut tracer: TraceStore) {
     tracer.events = [("obj1", 0), ("obj2", 1), ("obj3", 5), ("obj4", 10), ("obj5", 15)]
@@ -102,6 +148,134 @@ fn test_collect_events_filtered(mut tracer: TraceStore) {
     assert!(index.is_empty());
 }
 
+#[rstest]
+fn test_collect_events_max_age(mut tracer: TraceStore) {
+    // obj1 is last updated at ts 9 and obj3 is deleted at 14, so those are both recent enough;
+    // obj2 hasn't changed since ts 2, so it gets dropped
+    tracer.events = [
+        (0, vec!["obj1", "obj2"], vec![]),
+        (2, vec!["obj2"], vec![]),
+        (5, vec!["obj3"], vec![]),
+        (9, vec!["obj1"], vec![]),
+        (14, vec![], vec!["obj3"]),
+    ]
+    .into_iter()
+    .map(|(ts, applied, deleted): (i64, Vec<&str>, Vec<&str>)| TraceEvent {
+        ts,
+        applied_objs: applied.into_iter().map(test_obj).collect(),
+        deleted_objs: deleted.into_iter().map(test_obj).collect(),
+    })
+    .collect();
+
+    let filters = ExportFilters { max_age: Some(10), ..Default::default() };
+    let (events, index) = tracer.collect_events(1, 15, &filters, true);
+
+    let names = |objs: &[DynamicObject]| objs.iter().map(|o| o.name_any()).collect::<Vec<_>>();
+    assert_eq!(names(&events[0].applied_objs), vec!["obj1"]);
+    assert_eq!(
+        events[1..]
+            .iter()
+            .map(|evt| (evt.ts, names(&evt.applied_objs), names(&evt.deleted_objs)))
+            .collect::<Vec<_>>(),
+        vec![
+            (5, vec!["obj3".to_string()], vec![]),
+            (9, vec!["obj1".to_string()], vec![]),
+            (14, vec![], vec!["obj3".to_string()])
+        ]
+    );
+    assert!(!index.contains_key(&format!("{TEST_NAMESPACE}/obj2")));
+}
+
+fn ref_obj(kind: &str, name: &str, owner: Option<(&str, &str)>) -> DynamicObject {
+    let owners: Vec<_> = owner
+        .into_iter()
+        .map(|(kind, name)| json!({"apiVersion": "apps/v1", "kind": kind, "name": name, "uid": "1"}))
+        .collect();
+    serde_json::from_value(json!({
+        "apiVersion": if kind == "ConfigMap" { "v1" } else { "apps/v1" },
+        "kind": kind,
+        "metadata": {
+            "namespace": TEST_NAMESPACE,
+            "name": name,
+            "labels": {"skip": if name == "the-depl" { "yes" } else { "no" }},
+            "ownerReferences": owners,
+        },
+        "spec": {"containers": [{"envFrom": [{"configMapRef": {"name": "the-cm"}}]}]},
+    }))
+    .unwrap()
+}
+
+#[rstest]
+#[case::none(None, vec!["other-depl", "the-pod", "the-rs"])]
+#[case::drop(Some(DanglingRefPolicy::Drop), vec!["other-depl"])]
+#[case::remove_reference(Some(DanglingRefPolicy::RemoveReference), vec!["other-depl", "the-pod", "the-rs"])]
+#[case::include_dependency(
+    Some(DanglingRefPolicy::IncludeDependency),
+    vec!["other-depl", "the-cm", "the-depl", "the-pod", "the-rs"],
+)]
+fn test_collect_events_dangling_refs(
+    mut tracer: TraceStore,
+    #[case] policy: Option<DanglingRefPolicy>,
+    #[case] expected: Vec<&str>,
+) {
+    // the-depl is excluded by label and the-cm is excluded by kind; the-rs refers to both of
+    // them, and the-pod is owned by the-rs (and also refers to the-cm).  other-depl doesn't refer
+    // to anything, so it's always kept.
+    let mut other_depl = ref_obj("Deployment", "other-depl", None);
+    other_depl.data = json!({});
+    tracer.events = vec![
+        TraceEvent {
+            ts: 0,
+            applied_objs: vec![
+                ref_obj("Deployment", "the-depl", None),
+                ref_obj("ConfigMap", "the-cm", None),
+                other_depl,
+            ],
+            ..Default::default()
+        },
+        TraceEvent {
+            ts: 1,
+            applied_objs: vec![ref_obj("ReplicaSet", "the-rs", Some(("Deployment", "the-depl")))],
+            ..Default::default()
+        },
+        TraceEvent {
+            ts: 2,
+            applied_objs: vec![ref_obj("Pod", "the-pod", Some(("ReplicaSet", "the-rs")))],
+            ..Default::default()
+        },
+    ]
+    .into();
+
+    let filters = ExportFilters {
+        excluded_labels: vec![metav1::LabelSelector {
+            match_labels: Some([("skip".into(), "yes".into())].into()),
+            ..Default::default()
+        }],
+        excluded_kinds: Some(vec!["v1.ConfigMap".into()]),
+        dangling_refs: policy,
+        ..Default::default()
+    };
+    let (events, _) = tracer.collect_events(0, 10, &filters, true);
+
+    let objs: Vec<_> = events.iter().flat_map(|evt| evt.applied_objs.iter()).collect();
+    let mut names: Vec<_> = objs.iter().map(|obj| obj.name_any()).collect();
+    names.sort();
+    assert_eq!(names, expected);
+
+    let rs = objs.iter().find(|obj| obj.name_any() == "the-rs");
+    let pod = objs.iter().find(|obj| obj.name_any() == "the-pod");
+    let cm_optional = rs.and_then(|rs| rs.data.pointer("/spec/containers/0/envFrom/0/configMapRef/optional"));
+    if policy == Some(DanglingRefPolicy::RemoveReference) {
+        // The pod's owner is still in the trace, so its reference stays
+        assert_eq!(rs.unwrap().owner_references(), vec![]);
+        assert_eq!(cm_optional, Some(&json!(true)));
+        assert_eq!(pod.unwrap().owner_references().len(), 1);
+    } else if let Some(rs) = rs {
+        assert_eq!(rs.owner_references().len(), 1);
+        assert_none!(cm_optional);
+    }
+}
+
 #[rstest]
 fn test_collect_events(mut tracer: TraceStore) {
     let mut all_events: Vec<_> = [("obj1", 0), ("obj2", 1), ("obj3", 5), ("obj4", 10), ("obj5", 15)]
@@ -158,8 +332,8 @@ fn test_create_or_update_obj(mut tracer: TraceStore, test_obj: DynamicObject) {
 
 #[rstest]
 fn test_create_or_update_objs(mut tracer: TraceStore) {
-    let obj_names = vec!["obj1", "obj2"];
-    let ts = vec![1234, 3445];
+    let obj_names = ["obj1", "obj2"];
+    let ts = [1234, 3445];
     let objs: Vec<_> = obj_names.iter().map(|p| test_obj(p)).collect();
 
     for i in 0..objs.len() {
@@ -173,10 +347,10 @@ fn test_create_or_update_objs(mut tracer: TraceStore) {
     }
     assert_eq!(tracer.events.len(), 2);
 
-    for i in 0..objs.len() {
-        assert_eq!(tracer.events[i].applied_objs.len(), 1);
-        assert_eq!(tracer.events[i].deleted_objs.len(), 0);
-        assert_eq!(tracer.events[i].ts, ts[i]);
+    for (i, evt) in tracer.events.iter().enumerate() {
+        assert_eq!(evt.applied_objs.len(), 1);
+        assert_eq!(evt.deleted_objs.len(), 0);
+        assert_eq!(evt.ts, ts[i]);
     }
 }
 
@@ -198,7 +372,7 @@ fn test_delete_obj(mut tracer: TraceStore, test_obj: DynamicObject) {
 
 #[rstest]
 fn test_recreate_index_all_new(mut tracer: TraceStore) {
-    let obj_names = vec!["obj1", "obj2", "obj3"];
+    let obj_names = ["obj1", "obj2", "obj3"];
     let objs: Vec<_> = obj_names.iter().map(|p| test_obj(p)).collect();
     let ts: i64 = 1234;
 
@@ -219,9 +393,9 @@ fn test_recreate_index_all_new(mut tracer: TraceStore) {
 
 #[rstest]
 fn test_recreate_index_with_created_obj(mut tracer: TraceStore) {
-    let obj_names = vec!["obj1", "obj2", "obj3", "obj4"];
+    let obj_names = ["obj1", "obj2", "obj3", "obj4"];
     let objs: Vec<_> = obj_names.iter().map(|p| test_obj(p)).collect();
-    let ts = vec![1234, 2445];
+    let ts = [1234, 2445];
 
     // Calling it twice shouldn't change the tracked objs
     let mut fewer_objs = objs.clone();
@@ -245,9 +419,9 @@ fn test_recreate_index_with_created_obj(mut tracer: TraceStore) {
 
 #[rstest]
 fn test_recreate_index_with_deleted_obj(mut tracer: TraceStore) {
-    let obj_names = vec!["obj1", "obj2", "obj3"];
+    let obj_names = ["obj1", "obj2", "obj3"];
     let objs: Vec<_> = obj_names.iter().map(|p| test_obj(p)).collect();
-    let ts = vec![1234, 2445];
+    let ts = [1234, 2445];
 
     // Calling it twice shouldn't change the tracked objs
     tracer.update_all_objs(&objs, ts[0]);
@@ -271,15 +445,14 @@ fn test_recreate_index_with_deleted_obj(mut tracer: TraceStore) {
 
 #[rstest]
 fn test_record_pod_lifecycle_already_stored_no_data(mut tracer: TraceStore, owner_ref: metav1::OwnerReference) {
-    assert!(matches!(
-        tracer.record_pod_lifecycle("test/the-pod", None, vec![owner_ref], &PodLifecycleData::Running(1)),
-        Err(_)
-    ));
+    assert!(tracer
+        .record_pod_lifecycle("test/the-pod", None, vec![owner_ref], &PodLifecycleData::Running(1))
+        .is_err());
 }
 
 #[rstest]
 fn test_record_pod_lifecycle_already_stored_no_pod(mut tracer: TraceStore, owner_ref: metav1::OwnerReference) {
-    let new_lifecycle_data = PodLifecycleData::Finished(5, 45);
+    let new_lifecycle_data = PodLifecycleData::Finished(5, 45, None);
     let pod_seq_idx = 2;
     let init_lifecycle_data = vec![
         PodLifecycleData::Running(10),
@@ -314,7 +487,7 @@ fn test_record_pod_lifecycle_with_new_pod_no_tracked_owner(
 ) {
     let ns_name = test_pod.namespaced_name();
     let owner_ns_name = format!("{}/{}", TEST_NAMESPACE, owner_ref.name);
-    let new_lifecycle_data = PodLifecycleData::Finished(5, 45);
+    let new_lifecycle_data = PodLifecycleData::Finished(5, 45, None);
     tracer
         .record_pod_lifecycle(&ns_name, Some(test_pod), vec![owner_ref], &new_lifecycle_data.clone())
         .unwrap();
@@ -334,7 +507,7 @@ fn test_record_pod_lifecycle_with_new_pod_hash(
 ) {
     let ns_name = test_pod.namespaced_name();
     let owner_ns_name = format!("{}/{}", TEST_NAMESPACE, owner_ref.name);
-    let new_lifecycle_data = PodLifecycleData::Finished(5, 45);
+    let new_lifecycle_data = PodLifecycleData::Finished(5, 45, None);
     let gvk = GVK::from_owner_ref(&owner_ref).unwrap();
     tracer.config.tracked_objects.get_mut(&gvk).unwrap().track_lifecycle = track_lifecycle;
     tracer.index.insert(owner_ns_name.clone(), EMPTY_OBJ_HASH);
@@ -356,7 +529,7 @@ fn test_record_pod_lifecycle_with_new_pod_existing_hash(
     test_pod: corev1::Pod,
     owner_ref: metav1::OwnerReference,
 ) {
-    let new_lifecycle_data = PodLifecycleData::Finished(5, 45);
+    let new_lifecycle_data = PodLifecycleData::Finished(5, 45, None);
     let init_lifecycle_data = vec![PodLifecycleData::Running(5)];
     let mut expected_lifecycle_data = init_lifecycle_data.clone();
     expected_lifecycle_data.push(new_lifecycle_data.clone());
@@ -386,7 +559,7 @@ fn test_record_pod_lifecycle_with_existing_pod(
     test_pod: corev1::Pod,
     owner_ref: metav1::OwnerReference,
 ) {
-    let new_lifecycle_data = PodLifecycleData::Finished(5, 45);
+    let new_lifecycle_data = PodLifecycleData::Finished(5, 45, None);
     let init_lifecycle_data = vec![PodLifecycleData::Running(5)];
     let expected_lifecycle_data = vec![new_lifecycle_data.clone()];
 
@@ -408,3 +581,759 @@ fn test_record_pod_lifecycle_with_existing_pod(
         Some(&expected_lifecycle_data)
     );
 }
+
+#[rstest]
+fn test_record_pod_lifecycle_pending_to_running(
+    mut tracer: TraceStore,
+    test_pod: corev1::Pod,
+    owner_ref: metav1::OwnerReference,
+) {
+    let ns_name = test_pod.namespaced_name();
+    let owner_ns_name = format!("{}/{}", TEST_NAMESPACE, owner_ref.name);
+
+    tracer.index.insert(owner_ns_name.clone(), EMPTY_OBJ_HASH);
+    tracer
+        .record_pod_lifecycle(&ns_name, Some(test_pod.clone()), vec![owner_ref.clone()], &PodLifecycleData::Pending(1))
+        .unwrap();
+    assert_eq!(
+        tracer.pod_owners.lifecycle_data_for(&owner_ns_name, EMPTY_POD_SPEC_HASH),
+        Some(&vec![PodLifecycleData::Pending(1)])
+    );
+
+    tracer
+        .record_pod_lifecycle(&ns_name, Some(test_pod), vec![owner_ref], &PodLifecycleData::Running(5))
+        .unwrap();
+    assert_eq!(
+        tracer.pod_owners.lifecycle_data_for(&owner_ns_name, EMPTY_POD_SPEC_HASH),
+        Some(&vec![PodLifecycleData::Running(5)])
+    );
+}
+
+#[rstest]
+fn test_record_pod_usage_untracked_pod(mut tracer: TraceStore) {
+    assert!(!tracer.record_pod_usage("test/the-pod", PodUsage { ts: 5, ..Default::default() }));
+    assert!(tracer.pod_usage.is_empty());
+}
+
+#[rstest]
+fn test_record_pod_usage(mut tracer: TraceStore, test_pod: corev1::Pod, owner_ref: metav1::OwnerReference) {
+    let ns_name = test_pod.namespaced_name();
+    let owner_ns_name = format!("{}/{}", TEST_NAMESPACE, owner_ref.name);
+    let usage = PodUsage {
+        ts: 5,
+        containers: [("nginx".into(), [("cpu".into(), Quantity("10m".into()))].into())].into(),
+    };
+
+    tracer.index.insert(owner_ns_name.clone(), EMPTY_OBJ_HASH);
+    tracer
+        .record_pod_lifecycle(&ns_name, Some(test_pod), vec![owner_ref], &PodLifecycleData::Running(1))
+        .unwrap();
+
+    assert!(tracer.record_pod_usage(&ns_name, usage.clone()));
+    assert_eq!(tracer.pod_usage_for(&owner_ns_name, EMPTY_POD_SPEC_HASH), Some(&vec![usage]));
+}
+
+#[rstest]
+fn test_record_pod_readiness(mut tracer: TraceStore, test_pod: corev1::Pod, owner_ref: metav1::OwnerReference) {
+    let ns_name = test_pod.namespaced_name();
+    let owner_ns_name = format!("{}/{}", TEST_NAMESPACE, owner_ref.name);
+    let readiness = PodReadiness { start_ts: 1, ready_ts: 5 };
+
+    assert!(!tracer.record_pod_readiness(&ns_name, readiness.clone()));
+
+    tracer.index.insert(owner_ns_name.clone(), EMPTY_OBJ_HASH);
+    tracer
+        .record_pod_lifecycle(&ns_name, Some(test_pod), vec![owner_ref], &PodLifecycleData::Running(1))
+        .unwrap();
+
+    assert!(tracer.record_pod_readiness(&ns_name, readiness.clone()));
+    assert_eq!(tracer.pod_readiness_for(&owner_ns_name, EMPTY_POD_SPEC_HASH), Some(&vec![readiness]));
+}
+
+#[rstest]
+fn test_record_pod_env(mut tracer: TraceStore, test_pod: corev1::Pod, owner_ref: metav1::OwnerReference) {
+    let ns_name = test_pod.namespaced_name();
+    let owner_ns_name = format!("{}/{}", TEST_NAMESPACE, owner_ref.name);
+    let env = PodEnv::from([("app".into(), [("LOG_LEVEL".into(), "debug".into())].into())]);
+
+    assert!(!tracer.record_pod_env(&ns_name, env.clone()));
+
+    tracer.index.insert(owner_ns_name.clone(), EMPTY_OBJ_HASH);
+    tracer
+        .record_pod_lifecycle(&ns_name, Some(test_pod), vec![owner_ref], &PodLifecycleData::Running(1))
+        .unwrap();
+
+    // Only the first env we see for a given pod spec is kept
+    assert!(tracer.record_pod_env(&ns_name, env.clone()));
+    assert!(tracer.record_pod_env(&ns_name, PodEnv::new()));
+    assert_eq!(tracer.pod_env_for(&owner_ns_name, EMPTY_POD_SPEC_HASH), Some(&env));
+
+    let imported = TraceStore::import(tracer.export_all().unwrap(), &None).unwrap();
+    assert_eq!(imported.pod_env_for(&owner_ns_name, EMPTY_POD_SPEC_HASH), Some(&env));
+}
+
+#[rstest]
+fn test_record_pod_scheduling(
+    mut tracer: TraceStore,
+    test_pod_with_affinity: corev1::Pod,
+    owner_ref: metav1::OwnerReference,
+) {
+    let ns_name = test_pod_with_affinity.namespaced_name();
+    let owner_ns_name = format!("{}/{}", TEST_NAMESPACE, owner_ref.name);
+    let hash = jsonutils::hash(&serde_json::to_value(test_pod_with_affinity.stable_spec().unwrap()).unwrap());
+    let scheduling = test_pod_with_affinity.scheduling().unwrap();
+
+    assert!(!tracer.record_pod_scheduling(&ns_name, scheduling.clone()));
+
+    tracer.index.insert(owner_ns_name.clone(), EMPTY_OBJ_HASH);
+    tracer
+        .record_pod_lifecycle(&ns_name, Some(test_pod_with_affinity), vec![owner_ref], &PodLifecycleData::Running(1))
+        .unwrap();
+
+    assert!(tracer.record_pod_scheduling(&ns_name, scheduling.clone()));
+    assert_eq!(tracer.pod_scheduling_for(&owner_ns_name, hash), Some(&scheduling));
+
+    let imported = TraceStore::import(tracer.export_all().unwrap(), &None).unwrap();
+    assert_eq!(imported.pod_scheduling_for(&owner_ns_name, hash), Some(&scheduling));
+}
+
+#[rstest]
+fn test_record_pod_probes(
+    mut tracer: TraceStore,
+    test_pod_with_probes: corev1::Pod,
+    owner_ref: metav1::OwnerReference,
+) {
+    let ns_name = test_pod_with_probes.namespaced_name();
+    let owner_ns_name = format!("{}/{}", TEST_NAMESPACE, owner_ref.name);
+    let hash = jsonutils::hash(&serde_json::to_value(test_pod_with_probes.stable_spec().unwrap()).unwrap());
+    let probes = test_pod_with_probes.probes().unwrap();
+
+    assert!(!tracer.record_pod_probes(&ns_name, probes.clone()));
+
+    tracer.index.insert(owner_ns_name.clone(), EMPTY_OBJ_HASH);
+    tracer
+        .record_pod_lifecycle(&ns_name, Some(test_pod_with_probes), vec![owner_ref], &PodLifecycleData::Running(1))
+        .unwrap();
+
+    assert!(tracer.record_pod_probes(&ns_name, probes.clone()));
+    assert_eq!(tracer.pod_probes_for(&owner_ns_name, hash), Some(&probes));
+
+    let imported = TraceStore::import(tracer.export_all().unwrap(), &None).unwrap();
+    assert_eq!(imported.pod_probes_for(&owner_ns_name, hash), Some(&probes));
+}
+
+#[rstest]
+fn test_record_pod_events(mut tracer: TraceStore, test_pod: corev1::Pod, owner_ref: metav1::OwnerReference) {
+    let ns_name = test_pod.namespaced_name();
+    let owner_ns_name = format!("{}/{}", TEST_NAMESPACE, owner_ref.name);
+    let scheduled: corev1::Event = serde_json::from_value(json!({
+        "metadata": {"namespace": TEST_NAMESPACE, "name": "the-event"},
+        "involvedObject": {"kind": "Pod", "namespace": TEST_NAMESPACE, "name": test_pod.metadata.name},
+        "reason": "Scheduled",
+        "message": "Successfully assigned the pod to a node",
+        "type": "Normal",
+        "lastTimestamp": "2024-10-01T00:00:00Z",
+    }))
+    .unwrap();
+
+    tracer.index.insert(owner_ns_name.clone(), EMPTY_OBJ_HASH);
+    tracer
+        .record_pod_lifecycle(&ns_name, Some(test_pod), vec![owner_ref], &PodLifecycleData::Running(1))
+        .unwrap();
+
+    let mut pod_events = correlate_pod_events([scheduled]);
+    let events = pod_events.remove(&ns_name).unwrap();
+
+    // Recording the same events twice shouldn't duplicate them
+    assert!(tracer.record_pod_events(&ns_name, events.clone()));
+    assert!(tracer.record_pod_events(&ns_name, events));
+
+    let recorded = tracer.pod_events_for(&owner_ns_name, EMPTY_POD_SPEC_HASH).unwrap();
+    assert_eq!(recorded.len(), 1);
+    assert_eq!(recorded[0].reason, "Scheduled");
+    assert_eq!(recorded[0].ts, 1727740800);
+
+    assert!(!tracer.record_pod_events("test/some-other-pod", vec![recorded[0].clone()]));
+}
+
+#[rstest]
+fn test_record_pod_lifecycle_statefulset(mut test_pod: corev1::Pod) {
+    let sts_gvk = GVK::new("apps", "v1", "StatefulSet");
+    let mut tracer = TraceStore::new(TracerConfig {
+        tracked_objects: HashMap::from([(
+            sts_gvk,
+            TrackedObjectConfig { track_lifecycle: true, ..Default::default() },
+        )]),
+        ..Default::default()
+    });
+    let owner_ref = metav1::OwnerReference {
+        api_version: "apps/v1".into(),
+        kind: "StatefulSet".into(),
+        name: "web".into(),
+        ..Default::default()
+    };
+    let owner_ns_name = format!("{TEST_NAMESPACE}/web");
+    tracer.index.insert(owner_ns_name.clone(), EMPTY_OBJ_HASH);
+
+    // Pod web-2 comes up first, and then web-0
+    for (i, name) in ["web-2", "web-0"].into_iter().enumerate() {
+        test_pod.metadata.name = Some(name.into());
+        tracer
+            .record_pod_lifecycle(
+                &test_pod.namespaced_name(),
+                Some(test_pod.clone()),
+                vec![owner_ref.clone()],
+                &PodLifecycleData::Running(i as i64),
+            )
+            .unwrap();
+    }
+
+    let expected = vec![PodOrdinal { ordinal: 2, lifecycle_idx: 0 }, PodOrdinal { ordinal: 0, lifecycle_idx: 1 }];
+    assert_eq!(tracer.pod_ordinals_for(&owner_ns_name, EMPTY_POD_SPEC_HASH), Some(&expected));
+
+    let imported = TraceStore::import(tracer.export_all().unwrap(), &None).unwrap();
+    assert_eq!(imported.pod_ordinals_for(&owner_ns_name, EMPTY_POD_SPEC_HASH), Some(&expected));
+}
+
+#[rstest]
+fn test_record_pod_lifecycle_no_ordinals(
+    mut tracer: TraceStore,
+    test_pod: corev1::Pod,
+    owner_ref: metav1::OwnerReference,
+) {
+    let owner_ns_name = format!("{}/{}", TEST_NAMESPACE, owner_ref.name);
+    tracer.index.insert(owner_ns_name.clone(), EMPTY_OBJ_HASH);
+    tracer
+        .record_pod_lifecycle(
+            &test_pod.namespaced_name(),
+            Some(test_pod),
+            vec![owner_ref],
+            &PodLifecycleData::Running(1),
+        )
+        .unwrap();
+    assert_none!(tracer.pod_ordinals_for(&owner_ns_name, EMPTY_POD_SPEC_HASH));
+}
+
+#[rstest]
+fn test_pod_lifecycles(mut tracer: TraceStore, test_pod: corev1::Pod, owner_ref: metav1::OwnerReference) {
+    let ns_name = test_pod.namespaced_name();
+    let owner_ns_name = format!("{}/{}", TEST_NAMESPACE, owner_ref.name);
+    let depl = test_deployment(TEST_DEPLOYMENT);
+
+    tracer.create_or_update_obj(&depl, 1, None);
+    tracer
+        .record_pod_lifecycle(&ns_name, Some(test_pod), vec![owner_ref], &PodLifecycleData::Finished(5, 45, None))
+        .unwrap();
+
+    assert_eq!(
+        tracer.pod_lifecycles(&Default::default()),
+        HashMap::from([(
+            owner_ns_name,
+            HashMap::from([(EMPTY_POD_SPEC_HASH, vec![PodLifecycleData::Finished(5, 45, None)])])
+        )])
+    );
+    assert!(tracer
+        .pod_lifecycles(&ExportFilters::new(vec![TEST_NAMESPACE.into()], vec![], false))
+        .is_empty());
+}
+
+#[rstest]
+#[traced_test]
+fn test_max_objects_per_namespace(mut tracer: TraceStore) {
+    tracer.config.max_objects_per_namespace = Some(2);
+
+    for i in 0..4 {
+        tracer.create_or_update_obj(&test_obj_in("ns1", &format!("obj{i}")), i, None);
+        tracer.create_or_update_obj(&test_obj_in("ns2", &format!("obj{i}")), i, None);
+    }
+    tracer.create_or_update_obj(&test_obj_in("ns3", "obj0"), 5, None);
+
+    assert!(tracer.has_obj("ns1/obj0"));
+    assert!(tracer.has_obj("ns1/obj1"));
+    assert!(!tracer.has_obj("ns1/obj2"));
+    assert!(!tracer.has_obj("ns1/obj3"));
+    assert!(!tracer.has_obj("ns2/obj2"));
+    assert!(tracer.has_obj("ns3/obj0"));
+    assert_eq!(tracer.index.len(), 5);
+    assert_eq!(tracer.capped_namespaces, HashSet::from(["ns1".into(), "ns2".into()]));
+    assert!(logs_contain("namespace ns1 has reached the maximum of 2 objects"));
+
+    // Deleting a dropped object is a no-op, but deleting a recorded one frees up space
+    let num_events = tracer.events.len();
+    tracer.delete_obj(&test_obj_in("ns1", "obj3"), 6);
+    assert_eq!(tracer.events.len(), num_events);
+
+    tracer.delete_obj(&test_obj_in("ns1", "obj0"), 7);
+    tracer.create_or_update_obj(&test_obj_in("ns1", "obj3"), 8, None);
+    assert!(tracer.has_obj("ns1/obj3"));
+}
+
+#[rstest]
+fn test_max_objects_per_namespace_updates_existing(mut tracer: TraceStore) {
+    tracer.config.max_objects_per_namespace = Some(1);
+    tracer.create_or_update_obj(&test_obj_in("ns1", "obj0"), 0, None);
+
+    let mut updated = test_obj_in("ns1", "obj0");
+    updated.data = json!({"spec": {"replicas": 2}});
+    tracer.create_or_update_obj(&updated, 1, None);
+
+    assert_eq!(tracer.events.len(), 2);
+    assert_eq!(tracer.events[1].applied_objs, vec![updated]);
+}
+
+#[rstest]
+fn test_max_objects_per_namespace_update_all(mut tracer: TraceStore) {
+    tracer.config.max_objects_per_namespace = Some(1);
+    let objs = vec![test_obj_in("ns1", "obj0"), test_obj_in("ns1", "obj1")];
+    tracer.update_all_objs(&objs, 0);
+    tracer.update_all_objs(&objs, 1);
+
+    assert_eq!(tracer.index.len(), 1);
+    assert_eq!(tracer.namespace_counts["ns1"], 1);
+    assert_eq!(tracer.events.len(), 1);
+}
+
+#[rstest]
+#[case::latest_only(0, vec![])]
+#[case::partial(2, vec![1, 2])]
+#[case::all(5, vec![0, 1, 2])]
+fn test_object_history(mut tracer: TraceStore, #[case] depth: usize, #[case] expected_ts: Vec<i64>) {
+    tracer.config.object_history_depth = depth;
+
+    let mut obj = test_obj("obj");
+    for i in 0..3 {
+        obj.data = json!({"spec": {"replicas": i}});
+        tracer.create_or_update_obj(&obj, i, None);
+    }
+
+    // An update that doesn't change the spec isn't a new version
+    tracer.create_or_update_obj(&obj, 3, None);
+
+    let ns_name = format!("{TEST_NAMESPACE}/obj");
+    let versions: Vec<_> = tracer.history_for(&ns_name);
+    assert_eq!(versions.iter().map(|v| v.ts).collect::<Vec<_>>(), expected_ts);
+    for v in versions {
+        assert_eq!(v.obj.data, json!({"spec": {"replicas": v.ts}}));
+    }
+
+    // The versions come from the event list, we don't keep another copy of them
+    assert_is_empty!(tracer.history);
+}
+
+#[rstest]
+fn test_object_history_export_import(mut tracer: TraceStore) {
+    tracer.config.object_history_depth = 3;
+
+    let mut obj = test_obj("obj");
+    for i in 0..3 {
+        obj.data = json!({"spec": {"replicas": i}});
+        tracer.create_or_update_obj(&obj, i, None);
+    }
+
+    let ns_name = format!("{TEST_NAMESPACE}/obj");
+    let data = tracer.export(0, 2, &Default::default()).unwrap();
+    let imported = TraceStore::import(data, &None).unwrap();
+    let versions: Vec<_> = imported.history_for(&ns_name).iter().map(|v| v.ts).collect();
+    assert_eq!(versions, vec![0, 1]);
+    assert_eq!(imported.config.object_history_depth, 3);
+}
+
+#[rstest]
+fn test_max_objects_per_namespace_cluster_scoped(mut tracer: TraceStore) {
+    tracer.config.max_objects_per_namespace = Some(1);
+    for i in 0..3 {
+        let mut obj = test_obj(&format!("obj{i}"));
+        obj.metadata.namespace = None;
+        tracer.create_or_update_obj(&obj, i, None);
+    }
+
+    assert!(tracer.has_obj("obj0"));
+    assert!(tracer.has_obj("obj2"));
+    assert!(tracer.capped_namespaces.is_empty());
+}
+
+fn big_obj(name: &str) -> DynamicObject {
+    let mut obj = test_obj(name);
+    obj.data = json!({"spec": {"replicas": 1, "blob": "x".repeat(2000), "small": "foo"}});
+    obj.metadata.annotations = Some([("big-annotation".into(), "y".repeat(1000))].into());
+    obj
+}
+
+#[rstest]
+#[traced_test]
+fn test_max_object_size_truncate(mut tracer: TraceStore) {
+    tracer.config.max_object_size = Some(1500);
+    tracer.create_or_update_obj(&big_obj("obj"), 0, None);
+    tracer.create_or_update_obj(&test_obj("small"), 0, None);
+
+    assert!(tracer.has_obj(&format!("{TEST_NAMESPACE}/obj")));
+    let recorded = &tracer.events[0].applied_objs[0];
+    assert_eq!(recorded.data, json!({"spec": {"replicas": 1, "blob": "<truncated 2000 bytes>", "small": "foo"}}));
+
+    // The annotation is small enough once the blob is truncated, so it's left alone
+    assert_eq!(recorded.metadata.annotations.as_ref().unwrap()["big-annotation"].len(), 1000);
+    assert_eq!(tracer.events[0].applied_objs[1], test_obj("small"));
+    assert!(logs_contain(&format!("object {TEST_NAMESPACE}/obj is")));
+
+    // Seeing the same object again doesn't produce a new event, since the truncated spec is the same
+    tracer.create_or_update_obj(&big_obj("obj"), 1, None);
+    assert_eq!(tracer.events.len(), 1);
+}
+
+#[rstest]
+#[traced_test]
+fn test_max_object_size_skip(mut tracer: TraceStore) {
+    tracer.config.max_object_size = Some(1500);
+    tracer.config.oversized_object_policy = OversizedObjectPolicy::Skip;
+    tracer.create_or_update_obj(&big_obj("obj"), 0, None);
+    tracer.create_or_update_obj(&test_obj("small"), 0, None);
+
+    assert!(!tracer.has_obj(&format!("{TEST_NAMESPACE}/obj")));
+    assert!(tracer.has_obj(&format!("{TEST_NAMESPACE}/small")));
+    assert!(logs_contain(&format!("object {TEST_NAMESPACE}/obj is")));
+    assert!(logs_contain("it will not be recorded"));
+
+    // Deleting a dropped object is a no-op
+    tracer.delete_obj(&big_obj("obj"), 1);
+    assert_eq!(tracer.events.len(), 1);
+}
+
+#[rstest]
+fn test_max_object_size_truncate_not_enough(mut tracer: TraceStore) {
+    tracer.config.max_object_size = Some(100);
+    let mut obj = test_obj("obj");
+    obj.data = json!({"spec": {"items": (0..100).collect::<Vec<_>>()}});
+    tracer.create_or_update_obj(&obj, 0, None);
+
+    assert!(!tracer.has_obj(&format!("{TEST_NAMESPACE}/obj")));
+}
+
+#[rstest]
+fn test_rebase(mut tracer: TraceStore, owner_ref: metav1::OwnerReference) {
+    tracer.config.object_history_depth = 3;
+    let owner_ns_name = format!("{}/{}", TEST_NAMESPACE, owner_ref.name);
+    tracer.index.insert(owner_ns_name.clone(), EMPTY_OBJ_HASH);
+
+    let mut obj = test_obj("obj");
+    for (i, ts) in [1000, 1005, 1030].into_iter().enumerate() {
+        obj.data = json!({"spec": {"replicas": i}});
+        tracer.create_or_update_obj(&obj, ts, None);
+    }
+    tracer.delete_obj(&obj, 1042);
+
+    let (pod1, pod2) = (test_pod("pod1".into()), test_pod("pod2".into()));
+    tracer
+        .record_pod_lifecycle(
+            &pod1.namespaced_name(),
+            Some(pod1.clone()),
+            vec![owner_ref.clone()],
+            &PodLifecycleData::Finished(1001, 1020, None),
+        )
+        .unwrap();
+    tracer
+        .record_pod_lifecycle(&pod2.namespaced_name(), Some(pod2), vec![owner_ref], &PodLifecycleData::Running(1010))
+        .unwrap();
+    tracer.record_pod_usage(&pod1.namespaced_name(), PodUsage { ts: 1015, ..Default::default() });
+
+    tracer.rebase(0);
+
+    assert_eq!(tracer.start_ts(), Some(0));
+    assert_eq!(tracer.events.iter().map(|evt| evt.ts).collect::<Vec<_>>(), vec![0, 5, 30, 42]);
+    assert_eq!(
+        tracer.pod_owners.lifecycle_data_for(&owner_ns_name, EMPTY_POD_SPEC_HASH),
+        Some(&vec![PodLifecycleData::Finished(1, 20, None), PodLifecycleData::Running(10)])
+    );
+    assert_eq!(tracer.pod_usage_for(&owner_ns_name, EMPTY_POD_SPEC_HASH).unwrap()[0].ts, 15);
+
+    let ns_name = format!("{TEST_NAMESPACE}/obj");
+    let versions: Vec<_> = tracer.history_for(&ns_name).iter().map(|v| v.ts).collect();
+    assert_eq!(versions, vec![0, 5, 30]);
+}
+
+#[rstest]
+fn test_rebase_empty(mut tracer: TraceStore) {
+    tracer.rebase(0);
+    assert_eq!(tracer.start_ts(), None);
+}
+
+#[rstest]
+fn test_metadata_accessors(mut tracer: TraceStore, test_pod: corev1::Pod, owner_ref: metav1::OwnerReference) {
+    assert_eq!(tracer.object_count(), 0);
+    assert_eq!(tracer.pod_count(), 0);
+    assert_eq!(tracer.time_bounds(), None);
+    assert!(tracer.namespaces().is_empty());
+
+    let mut cluster_obj = test_obj("cluster-obj");
+    cluster_obj.metadata.namespace = None;
+    tracer.create_or_update_obj(&test_deployment(TEST_DEPLOYMENT), 1, None);
+    tracer.create_or_update_obj(&test_obj_in("ns1", "obj0"), 2, None);
+    tracer.create_or_update_obj(&test_obj_in("ns1", "obj1"), 3, None);
+    tracer.create_or_update_obj(&cluster_obj, 4, None);
+    tracer.delete_obj(&test_obj_in("ns1", "obj1"), 5);
+    tracer
+        .record_pod_lifecycle(
+            &test_pod.namespaced_name(),
+            Some(test_pod),
+            vec![owner_ref],
+            &PodLifecycleData::Finished(5, 45, None),
+        )
+        .unwrap();
+
+    assert_eq!(tracer.object_count(), 3);
+    assert_eq!(tracer.pod_count(), 1);
+    assert_eq!(tracer.time_bounds(), Some((1, 5)));
+    assert_eq!(tracer.namespaces(), HashSet::from([TEST_NAMESPACE.into(), "ns1".into()]));
+
+    // Deleted objects are still in the index of an exported trace, so they show up after importing
+    let imported = TraceStore::import(tracer.export(0, 10, &Default::default()).unwrap(), &None).unwrap();
+    assert_eq!(imported.object_count(), 4);
+    assert_eq!(imported.pod_count(), 1);
+    assert_eq!(imported.time_bounds(), Some((0, 5)));
+    assert_eq!(imported.namespaces(), HashSet::from([TEST_NAMESPACE.into(), "ns1".into()]));
+}
+
+fn pending_obj() -> DynamicObject {
+    let mut obj = test_obj("obj");
+    obj.metadata.name = None;
+    obj.metadata.generate_name = Some("obj-".into());
+    obj.metadata.uid = Some("abcd".into());
+    obj
+}
+
+#[rstest]
+#[case::applied(false)]
+#[case::restarted(true)]
+fn test_create_or_update_pending_name(mut tracer: TraceStore, #[case] restarted: bool) {
+    let pending = pending_obj();
+    let mut named = pending.clone();
+    named.metadata.name = Some("obj-xyz".into());
+    named.data = json!({"spec": {"replicas": 2}});
+
+    tracer.create_or_update_obj(&pending, 0, None);
+    assert!(tracer.has_obj(&format!("{TEST_NAMESPACE}/abcd")));
+
+    if restarted {
+        tracer.update_all_objs(&[named], 1);
+    } else {
+        tracer.create_or_update_obj(&named, 1, None);
+    }
+
+    // The two versions are the same object, so nothing gets deleted, and the earlier version gets
+    // the real name filled in
+    let ns_name = format!("{TEST_NAMESPACE}/obj-xyz");
+    assert!(tracer.has_obj(&ns_name));
+    assert!(!tracer.has_obj(&format!("{TEST_NAMESPACE}/abcd")));
+    assert_eq!(tracer.index.len(), 1);
+    assert_eq!(tracer.namespace_counts[TEST_NAMESPACE], 1);
+    assert!(tracer.events.iter().all(|evt| evt.deleted_objs.is_empty()));
+    assert_eq!(tracer.events[0].applied_objs[0].namespaced_name(), ns_name);
+    assert_eq!(tracer.objs_at(2, &Default::default()), HashSet::from([ns_name]));
+}
+
+#[fixture]
+fn query_tracer(mut tracer: TraceStore) -> TraceStore {
+    let mut depl = test_obj_in("ns1", "depl");
+    depl.types = Some(TypeMeta {
+        api_version: "apps/v1".into(),
+        kind: "Deployment".into(),
+    });
+    let mut svc = test_obj_in("ns1", "svc");
+    svc.types = Some(TypeMeta { api_version: "v1".into(), kind: "Service".into() });
+    let mut other_depl = test_obj_in("ns2", "depl");
+    other_depl.types = depl.types.clone();
+
+    tracer.create_or_update_obj(&depl, 1, None);
+    tracer.create_or_update_obj(&svc, 2, None);
+    tracer.create_or_update_obj(&other_depl, 3, None);
+    tracer.create_or_update_obj(&test_obj_in("ns2", "untyped"), 4, None);
+    tracer.create_or_update_obj(&test_obj_in("ns1", "deleted"), 5, None);
+    tracer.delete_obj(&test_obj_in("ns1", "deleted"), 6);
+
+    // Update one of the objects so we can check that only the latest version is returned
+    depl.data = json!({"spec": {"replicas": 3}});
+    tracer.create_or_update_obj(&depl, 7, None);
+    tracer
+}
+
+fn sorted_names<'a>(objs: impl Iterator<Item = &'a DynamicObject>) -> Vec<String> {
+    let mut names: Vec<_> = objs.map(|obj| obj.namespaced_name()).collect();
+    names.sort();
+    names
+}
+
+#[rstest]
+fn test_objects(query_tracer: TraceStore) {
+    assert_eq!(sorted_names(query_tracer.objects()), vec!["ns1/depl", "ns1/svc", "ns2/depl", "ns2/untyped"]);
+
+    let depl = query_tracer.objects().find(|obj| obj.namespaced_name() == "ns1/depl").unwrap();
+    assert_eq!(depl.data, json!({"spec": {"replicas": 3}}));
+}
+
+#[rstest]
+fn test_objects_same_name_different_kinds(mut query_tracer: TraceStore) {
+    let mut cm = test_obj_in("ns1", "depl");
+    cm.types = Some(TypeMeta { api_version: "v1".into(), kind: "ConfigMap".into() });
+    query_tracer.create_or_update_obj(&cm, 8, None);
+
+    assert_eq!(
+        sorted_names(query_tracer.objects()),
+        vec!["ns1/depl", "ns1/depl", "ns1/svc", "ns2/depl", "ns2/untyped"]
+    );
+}
+
+#[rstest]
+#[case::ns1("ns1", vec!["ns1/depl", "ns1/svc"])]
+#[case::ns2("ns2", vec!["ns2/depl", "ns2/untyped"])]
+#[case::missing("ns3", vec![])]
+fn test_objects_in_namespace(query_tracer: TraceStore, #[case] ns: &str, #[case] expected: Vec<&str>) {
+    assert_eq!(sorted_names(query_tracer.objects_in_namespace(ns)), expected);
+}
+
+#[rstest]
+#[case::deployment(GVK::new("apps", "v1", "Deployment"), vec!["ns1/depl", "ns2/depl"])]
+#[case::service(GVK::new("", "v1", "Service"), vec!["ns1/svc"])]
+#[case::missing(GVK::new("batch", "v1", "Job"), vec![])]
+fn test_objects_of_kind(query_tracer: TraceStore, #[case] gvk: GVK, #[case] expected: Vec<&str>) {
+    assert_eq!(sorted_names(query_tracer.objects_of_kind(&gvk)), expected);
+}
+
+#[rstest]
+fn test_iter_events(mut tracer: TraceStore) {
+    let depl1 = test_deployment("depl1");
+    let depl2 = test_deployment("depl2");
+    tracer.events = [
+        TraceEvent {
+            ts: 0,
+            applied_objs: vec![depl1.clone()],
+            ..Default::default()
+        },
+        TraceEvent {
+            ts: 10,
+            applied_objs: vec![depl2.clone()],
+            ..Default::default()
+        },
+        TraceEvent {
+            ts: 30,
+            deleted_objs: vec![depl1.clone()],
+            ..Default::default()
+        },
+    ]
+    .into();
+
+    let owner1 = format!("{TEST_NAMESPACE}/depl1");
+    let owner2 = format!("{TEST_NAMESPACE}/depl2");
+    tracer.pod_owners = PodOwnersMap::new_from_parts(
+        HashMap::from([
+            (
+                owner1.clone(),
+                HashMap::from([(
+                    EMPTY_POD_SPEC_HASH,
+                    vec![
+                        PodLifecycleData::Finished(5, 30, Some("Completed".into())),
+                        PodLifecycleData::Running(15),
+                        PodLifecycleData::Pending(25),
+                    ],
+                )]),
+            ),
+            (owner2.clone(), HashMap::from([(EMPTY_POD_SPEC_HASH, vec![PodLifecycleData::Running(10)])])),
+        ]),
+        HashMap::new(),
+    );
+
+    let events: Vec<_> = tracer.iter_events().collect();
+    assert_eq!(
+        events,
+        vec![
+            TimelineEvent::ObjectApplied { ts: 0, obj: &depl1 },
+            TimelineEvent::PodStarted {
+                ts: 5,
+                owner_ns_name: &owner1,
+                pod_hash: EMPTY_POD_SPEC_HASH,
+                seq: 0
+            },
+            TimelineEvent::ObjectApplied { ts: 10, obj: &depl2 },
+            TimelineEvent::PodStarted {
+                ts: 10,
+                owner_ns_name: &owner2,
+                pod_hash: EMPTY_POD_SPEC_HASH,
+                seq: 0
+            },
+            TimelineEvent::PodStarted {
+                ts: 15,
+                owner_ns_name: &owner1,
+                pod_hash: EMPTY_POD_SPEC_HASH,
+                seq: 1
+            },
+            TimelineEvent::ObjectDeleted { ts: 30, obj: &depl1 },
+            TimelineEvent::PodFinished {
+                ts: 30,
+                owner_ns_name: &owner1,
+                pod_hash: EMPTY_POD_SPEC_HASH,
+                seq: 0,
+                reason: Some("Completed"),
+            },
+        ]
+    );
+}
+
+#[rstest]
+fn test_iter_events_empty(tracer: TraceStore) {
+    assert_eq!(tracer.iter_events().count(), 0);
+}
+
+#[rstest]
+fn test_compact(mut tracer: TraceStore) {
+    let mut depl = test_deployment("depl1");
+    depl.metadata.managed_fields = Some(vec![Default::default()]);
+    depl.metadata.resource_version = Some("1234".into());
+    let mut updated = depl.clone();
+    updated.data = json!({"spec": {"replicas": 3}});
+
+    tracer.events = [
+        TraceEvent {
+            ts: 0,
+            applied_objs: vec![depl.clone()],
+            ..Default::default()
+        },
+        // Same spec as before, so this doesn't count as a change
+        TraceEvent {
+            ts: 5,
+            applied_objs: vec![depl.clone()],
+            ..Default::default()
+        },
+        TraceEvent {
+            ts: 10,
+            applied_objs: vec![updated.clone()],
+            ..Default::default()
+        },
+        TraceEvent {
+            ts: 15,
+            deleted_objs: vec![updated],
+            ..Default::default()
+        },
+        // After the object is deleted, re-creating it counts as a change again
+        TraceEvent {
+            ts: 20,
+            applied_objs: vec![depl.clone()],
+            ..Default::default()
+        },
+        TraceEvent {
+            ts: 25,
+            applied_objs: vec![depl],
+            ..Default::default()
+        },
+    ]
+    .into();
+
+    assert_eq!(tracer.compact(), 2);
+    assert_eq!(tracer.events.iter().map(|evt| evt.ts).collect::<Vec<_>>(), vec![0, 10, 15, 20, 25]);
+    assert!(tracer.events[4].applied_objs.is_empty());
+    for obj in tracer
+        .events
+        .iter()
+        .flat_map(|evt| evt.applied_objs.iter().chain(evt.deleted_objs.iter()))
+    {
+        assert_none!(obj.metadata.managed_fields);
+        assert_none!(obj.metadata.resource_version);
+    }
+}
@@ -1,24 +1,47 @@
+use std::collections::{
+    HashMap,
+    HashSet,
+};
+
 use kube::api::DynamicObject;
-use sk_api::v1::ExportFilters;
-use sk_core::k8s::KubeResourceExt;
+use sk_api::v1::{
+    DanglingRefPolicy,
+    ExportFilters,
+};
+use sk_core::k8s::{
+    KubeResourceExt,
+    GVK,
+};
+use sk_core::prelude::*;
 
 use super::TraceEvent;
+use crate::replay::{
+    find_dependencies,
+    obj_key,
+    remove_dependency,
+};
 
-pub fn filter_event(evt: &TraceEvent, f: &ExportFilters) -> Option<TraceEvent> {
+// If the filters only include some namespaces, `cluster_deps` holds the keys (see
+// replay/ordering.rs) of the cluster-scoped objects that are referenced by something in those
+// namespaces; all other cluster-scoped objects get filtered out.  Anything in `stale` is filtered
+// out too (see stale_objects), and `repair` fixes up references to objects that were filtered out
+// (see RefRepair).
+pub fn filter_event(
+    evt: &TraceEvent,
+    f: &ExportFilters,
+    cluster_deps: &HashSet<String>,
+    stale: &HashSet<String>,
+    repair: &RefRepair,
+) -> Option<TraceEvent> {
+    let keep = |obj: &&DynamicObject| {
+        let key = obj_key(obj);
+        repair.pulled.contains(&key)
+            || (!obj_matches_filter(obj, f, cluster_deps) && !stale.contains(&key) && !repair.dropped.contains(&key))
+    };
     let new_evt = TraceEvent {
         ts: evt.ts,
-        applied_objs: evt
-            .applied_objs
-            .iter()
-            .filter(|obj| !obj_matches_filter(obj, f))
-            .cloned()
-            .collect(),
-        deleted_objs: evt
-            .deleted_objs
-            .iter()
-            .filter(|obj| !obj_matches_filter(obj, f))
-            .cloned()
-            .collect(),
+        applied_objs: evt.applied_objs.iter().filter(keep).map(|obj| repair.fix(obj)).collect(),
+        deleted_objs: evt.deleted_objs.iter().filter(keep).map(|obj| repair.fix(obj)).collect(),
     };
 
     if new_evt.applied_objs.is_empty() && new_evt.deleted_objs.is_empty() {
@@ -28,11 +51,154 @@ pub fn filter_event(evt: &TraceEvent, f: &ExportFilters) -> Option<TraceEvent> {
     Some(new_evt)
 }
 
-fn obj_matches_filter(obj: &DynamicObject, f: &ExportFilters) -> bool {
+// The keys of all the objects whose last recorded update (or deletion) before end_ts happened more
+// than max_age seconds before end_ts; these have been sitting around unchanged for long enough that
+// they're just noise in a point-in-time simulation.
+pub fn stale_objects<'a>(
+    events: impl IntoIterator<Item = &'a TraceEvent>,
+    end_ts: i64,
+    max_age: i64,
+) -> HashSet<String> {
+    let mut last_updated = HashMap::new();
+    for evt in events.into_iter().take_while(|evt| evt.ts < end_ts) {
+        for obj in evt.applied_objs.iter().chain(evt.deleted_objs.iter()) {
+            last_updated.insert(obj_key(obj), evt.ts);
+        }
+    }
+    last_updated
+        .into_iter()
+        .filter(|(_, ts)| *ts < end_ts - max_age)
+        .map(|(key, _)| key)
+        .collect()
+}
+
+// Filtering out an object doesn't filter out the things that refer to it, so an export can end up
+// with, e.g., a ReplicaSet whose Deployment was excluded, or a Deployment that mounts an excluded
+// ConfigMap; when these get replayed, the ReplicaSet gets garbage-collected and the Deployment's
+// pods never start.  If the filters have a DanglingRefPolicy, we work out ahead of time (over the
+// whole part of the trace being exported) which objects have dangling references, and either drop
+// them (along with anything that refers to _them_), remove the references, or pull the missing
+// objects (and their own dependencies) back into the export.  References to things that weren't in
+// the trace to begin with are left alone, since there's nothing we can do about those here.
+#[derive(Default)]
+pub struct RefRepair {
+    policy: Option<DanglingRefPolicy>,
+    excluded: HashSet<String>,
+    dropped: HashSet<String>,
+    pulled: HashSet<String>,
+}
+
+impl RefRepair {
+    pub fn plan<'a>(
+        events: impl IntoIterator<Item = &'a TraceEvent>,
+        f: &ExportFilters,
+        cluster_deps: &HashSet<String>,
+        stale: &HashSet<String>,
+    ) -> RefRepair {
+        let Some(policy) = f.dangling_refs else {
+            return RefRepair::default();
+        };
+
+        let mut versions: HashMap<String, Vec<&DynamicObject>> = HashMap::new();
+        let mut kept = HashSet::new();
+        for obj in events.into_iter().flat_map(|evt| evt.applied_objs.iter()) {
+            let key = obj_key(obj);
+            if !obj_matches_filter(obj, f, cluster_deps) && !stale.contains(&key) {
+                kept.insert(key.clone());
+            }
+            versions.entry(key).or_default().push(obj);
+        }
+        let excluded: HashSet<_> = versions.keys().filter(|key| !kept.contains(*key)).cloned().collect();
+        let dangling = |key: &str, gone: &HashSet<String>| -> Vec<String> {
+            let mut deps: Vec<_> = versions[key]
+                .iter()
+                .flat_map(|obj| find_dependencies(obj))
+                .filter(|dep| gone.contains(dep))
+                .collect();
+            deps.sort();
+            deps.dedup();
+            deps
+        };
+
+        let mut kept: Vec<_> = kept.into_iter().collect();
+        kept.sort();
+        let mut repair = RefRepair { policy: Some(policy), ..Default::default() };
+        match policy {
+            // Dropping something can leave other things dangling, so keep going until nothing changes
+            DanglingRefPolicy::Drop => {
+                let mut gone = excluded.clone();
+                loop {
+                    let mut changed = false;
+                    for key in &kept {
+                        if repair.dropped.contains(key) {
+                            continue;
+                        }
+                        if let Some(dep) = dangling(key, &gone).first() {
+                            info!("dropping {key} from the export because {dep} was filtered out");
+                            repair.dropped.insert(key.clone());
+                            gone.insert(key.clone());
+                            changed = true;
+                        }
+                    }
+                    if !changed {
+                        break;
+                    }
+                }
+            },
+            DanglingRefPolicy::RemoveReference => {
+                for key in &kept {
+                    for dep in dangling(key, &excluded) {
+                        let removable = versions[key].iter().all(|obj| remove_dependency(&mut (*obj).clone(), &dep));
+                        if removable {
+                            info!("removing reference from {key} to {dep}, which was filtered out");
+                        } else {
+                            warn!(
+                                "{key} can't exist without {dep}, which was filtered out; dropping it from the export"
+                            );
+                            repair.dropped.insert(key.clone());
+                        }
+                    }
+                }
+            },
+            DanglingRefPolicy::IncludeDependency => {
+                let mut queue = kept;
+                while let Some(key) = queue.pop() {
+                    for dep in dangling(&key, &excluded) {
+                        if repair.pulled.insert(dep.clone()) {
+                            info!("including {dep} in the export because {key} depends on it");
+                            queue.push(dep);
+                        }
+                    }
+                }
+            },
+        }
+        repair.excluded = excluded;
+        repair
+    }
+
+    fn fix(&self, obj: &DynamicObject) -> DynamicObject {
+        let mut obj = obj.clone();
+        if self.policy == Some(DanglingRefPolicy::RemoveReference) {
+            for dep in find_dependencies(&obj).iter().filter(|dep| self.excluded.contains(*dep)) {
+                remove_dependency(&mut obj, dep);
+            }
+        }
+        obj
+    }
+}
+
+fn obj_matches_filter(obj: &DynamicObject, f: &ExportFilters, cluster_deps: &HashSet<String>) -> bool {
     obj.metadata
         .namespace
         .as_ref()
         .is_some_and(|ns| f.excluded_namespaces.contains(ns))
+        || f.excluded_kinds.as_ref().is_some_and(|kinds| {
+            GVK::from_dynamic_obj(obj).is_ok_and(|gvk| kinds.contains(&gvk.to_string()))
+        })
+        || f.included_namespaces.as_ref().is_some_and(|included| match &obj.metadata.namespace {
+            Some(ns) => !included.contains(ns),
+            None => !cluster_deps.contains(&obj_key(obj)),
+        })
         || obj
             .metadata
             .owner_references
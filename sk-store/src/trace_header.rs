@@ -0,0 +1,205 @@
+use std::collections::{
+    HashMap,
+    HashSet,
+};
+use std::fs::File;
+use std::io::{
+    BufRead,
+    BufReader,
+};
+
+use ring::digest::{
+    digest,
+    SHA256,
+};
+use serde::de::DeserializeOwned;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::Value;
+use sk_core::errors::*;
+use sk_core::k8s::{
+    KubeResourceExt,
+    GVK,
+};
+
+use crate::encryption::is_encrypted;
+use crate::{
+    TraceEvent,
+    TracerConfig,
+};
+
+// Traces are written in msgpack by default, but they can also be written as JSON, which is bigger
+// but easier to read with other tools.  Either way, the header (see below) and the trace data are
+// written in the same format; JSON traces start with a `{`, which can't be the first byte of a
+// msgpack trace (it would be a positive integer), so we can tell which one we've got just by
+// looking at the first byte.  There's no "binary" format other than msgpack, because the objects
+// in the trace are stored as arbitrary JSON values, which formats like bincode can't deserialize.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TraceFormat {
+    #[default]
+    Msgpack,
+    Json,
+}
+
+impl TraceFormat {
+    pub fn detect(data: &[u8]) -> TraceFormat {
+        match data.first() {
+            Some(b'{') => TraceFormat::Json,
+            _ => TraceFormat::Msgpack,
+        }
+    }
+}
+
+// Exported traces start with a small summary of what's in them, written as its own msgpack object
+// right before the trace data, so we can find out what's in a trace by just decoding the first
+// object in the file.  The summary is a map, whereas the trace itself is an array, which is how we
+// tell traces that were written before we started including the header apart from newer ones.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceHeader {
+    // The number of distinct objects of each kind that show up anywhere in the trace
+    pub object_counts: HashMap<GVK, usize>,
+    pub event_count: usize,
+    pub time_bounds: Option<(i64, i64)>,
+
+    // A SHA-256 digest of the tracer config that was used to record the trace, so that you can
+    // tell whether two traces were captured the same way
+    pub config_digest: String,
+}
+
+impl TraceHeader {
+    pub(crate) fn build<'a>(
+        config: &TracerConfig,
+        events: impl IntoIterator<Item = &'a TraceEvent>,
+    ) -> anyhow::Result<TraceHeader> {
+        let mut objs: HashSet<(GVK, String)> = HashSet::new();
+        let (mut event_count, mut start_ts, mut end_ts) = (0, None, None);
+        for evt in events {
+            event_count += 1;
+            start_ts = start_ts.or(Some(evt.ts));
+            end_ts = Some(evt.ts);
+            for obj in evt.applied_objs.iter().chain(evt.deleted_objs.iter()) {
+                if let Ok(gvk) = GVK::from_dynamic_obj(obj) {
+                    objs.insert((gvk, obj.namespaced_name()));
+                }
+            }
+        }
+
+        let mut object_counts = HashMap::new();
+        for (gvk, _) in objs {
+            *object_counts.entry(gvk).or_default() += 1;
+        }
+
+        Ok(TraceHeader {
+            object_counts,
+            event_count,
+            time_bounds: start_ts.zip(end_ts),
+            config_digest: config_digest(config)?,
+        })
+    }
+
+    pub fn object_count(&self) -> usize {
+        self.object_counts.values().sum()
+    }
+}
+
+// JSON traces have the header on its own line, so that you can look at it with `head -1`
+pub(crate) fn write_with_header<T: Serialize>(
+    header: &TraceHeader,
+    trace: &T,
+    format: TraceFormat,
+) -> anyhow::Result<Vec<u8>> {
+    Ok(match format {
+        TraceFormat::Msgpack => [
+            rmp_serde::to_vec_named(header).map_err(SimKubeError::serialization)?,
+            rmp_serde::to_vec_named(trace).map_err(SimKubeError::serialization)?,
+        ]
+        .concat(),
+        TraceFormat::Json => [
+            serde_json::to_vec(header).map_err(SimKubeError::serialization)?,
+            b"\n".to_vec(),
+            serde_json::to_vec(trace).map_err(SimKubeError::serialization)?,
+        ]
+        .concat(),
+    })
+}
+
+// Reads the trace data (skipping over the header, if there is one), in whichever format it's in
+pub(crate) fn read_trace_data<T: DeserializeOwned>(data: &[u8]) -> anyhow::Result<T> {
+    let (_, rest) = split_header(data)?;
+    Ok(match TraceFormat::detect(data) {
+        TraceFormat::Msgpack => rmp_serde::from_slice(rest).map_err(SimKubeError::serialization)?,
+        TraceFormat::Json => serde_json::from_slice(rest).map_err(SimKubeError::serialization)?,
+    })
+}
+
+// These are the msgpack markers for a fixmap, map16, and map32
+fn starts_with_header(data: &[u8]) -> bool {
+    matches!(data.first(), Some(0x80..=0x8f | 0xde | 0xdf))
+}
+
+// Returns the header (if the trace has one) and the rest of the trace data; all JSON traces have a
+// header, since we added JSON support after headers
+pub(crate) fn split_header(data: &[u8]) -> anyhow::Result<(Option<TraceHeader>, &[u8])> {
+    if TraceFormat::detect(data) == TraceFormat::Json {
+        let mut stream = serde_json::Deserializer::from_slice(data).into_iter();
+        let header = stream.next().ok_or_else(|| SimKubeError::trace_format("JSON trace is empty"))?;
+        let header = header.map_err(SimKubeError::serialization)?;
+        return Ok((Some(header), &data[stream.byte_offset()..]));
+    }
+    if !starts_with_header(data) {
+        return Ok((None, data));
+    }
+
+    let mut rest = data;
+    let header =
+        TraceHeader::deserialize(&mut rmp_serde::Deserializer::new(&mut rest)).map_err(SimKubeError::serialization)?;
+    Ok((Some(header), rest))
+}
+
+// Only reads as much of the file as it needs to; traces that were exported before we started
+// writing headers don't have one, so they just return None.
+pub(crate) fn read_header(path: &str) -> anyhow::Result<Option<TraceHeader>> {
+    let mut file = BufReader::new(File::open(path)?);
+
+    let prefix = file.fill_buf()?;
+    if is_encrypted(prefix) {
+        return Err(SimKubeError::trace_format(
+            "trace is encrypted and must be decrypted before its header can be read",
+        ));
+    }
+    if TraceFormat::detect(prefix) == TraceFormat::Json {
+        let header = serde_json::Deserializer::from_reader(file).into_iter().next();
+        let header = header.ok_or_else(|| SimKubeError::trace_format("JSON trace is empty"))?;
+        return Ok(Some(header.map_err(SimKubeError::serialization)?));
+    }
+    if !starts_with_header(prefix) {
+        return Ok(None);
+    }
+    Ok(Some(rmp_serde::from_read(file).map_err(SimKubeError::serialization)?))
+}
+
+// The config has a bunch of HashMaps in it, so we sort all the keys before computing the digest;
+// otherwise the same config could end up with different digests
+fn config_digest(config: &TracerConfig) -> anyhow::Result<String> {
+    let canonical = serde_json::to_vec(&sort_keys(serde_json::to_value(config)?))?;
+    Ok(digest(&SHA256, &canonical)
+        .as_ref()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect())
+}
+
+fn sort_keys(v: Value) -> Value {
+    match v {
+        Value::Object(o) => {
+            let mut entries: Vec<_> = o.into_iter().collect();
+            entries.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+            Value::Object(entries.into_iter().map(|(k, v)| (k, sort_keys(v))).collect())
+        },
+        Value::Array(a) => Value::Array(a.into_iter().map(sort_keys).collect()),
+        v => v,
+    }
+}
@@ -0,0 +1,298 @@
+use serde_json::{
+    json,
+    Value,
+};
+
+use crate::TraceFormat;
+
+impl TraceFormat {
+    // A JSON schema (draft 2020-12) for the contents of an exported trace, for tools that read
+    // traces without going through TraceStore.  A JSON trace is two JSON values, the header and
+    // then the trace data; the schema describes them as a two-element array, i.e., what you get if
+    // you parse both of them and stick them in a list.  Msgpack traces have the same structure,
+    // except that the pod hash keys (which are strings in JSON) are stored as integers.
+    //
+    // Traces exported by older versions of SimKube are missing the header, and may only have the
+    // first four elements of the trace data; the schema only covers what we export now, which is
+    // why the tests export a trace and check it against this.  Anything in here that describes
+    // something the exporter doesn't write is a bug.
+    pub fn schema() -> Value {
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "SimKube trace",
+            "type": "array",
+            "prefixItems": [{"$ref": "#/$defs/header"}, {"$ref": "#/$defs/trace"}],
+            "minItems": 2,
+            "items": false,
+            "$defs": {
+                "timestamp": {"type": "integer"},
+                "podHash": {"type": "string", "pattern": "^[0-9]+$"},
+                "header": {
+                    "type": "object",
+                    "required": ["objectCounts", "eventCount", "timeBounds", "configDigest"],
+                    "properties": {
+                        "objectCounts": {
+                            "type": "object",
+                            "additionalProperties": {"type": "integer", "minimum": 0},
+                        },
+                        "eventCount": {"type": "integer", "minimum": 0},
+                        "timeBounds": {
+                            "type": ["array", "null"],
+                            "prefixItems": [{"$ref": "#/$defs/timestamp"}, {"$ref": "#/$defs/timestamp"}],
+                            "minItems": 2,
+                            "items": false,
+                        },
+                        "configDigest": {"type": "string", "pattern": "^[0-9a-f]{64}$"},
+                    },
+                    "additionalProperties": false,
+                },
+
+                // The trace data is a tuple; see TraceStore::export_as for what goes where
+                "trace": {
+                    "type": "array",
+                    "prefixItems": [
+                        {"$ref": "#/$defs/config"},
+                        {"type": "array", "items": {"$ref": "#/$defs/event"}},
+                        {"$ref": "#/$defs/index"},
+                        {"$ref": "#/$defs/podLifecycles"},
+                        {"$ref": "#/$defs/podUsage"},
+                        {"$ref": "#/$defs/history"},
+                        {"$ref": "#/$defs/podReadiness"},
+                        {"$ref": "#/$defs/podEvents"},
+                        {"$ref": "#/$defs/podEnv"},
+                        {"$ref": "#/$defs/podOrdinals"},
+                        {"$ref": "#/$defs/podScheduling"},
+                        {"$ref": "#/$defs/podProbes"},
+                    ],
+                    "minItems": 12,
+                    "items": false,
+                },
+                "config": {
+                    "type": "object",
+                    "required": ["trackedObjects"],
+                    "properties": {
+                        "trackedObjects": {
+                            "type": "object",
+                            "additionalProperties": {
+                                "type": "object",
+                                "properties": {
+                                    "podSpecTemplatePath": {"type": ["string", "null"]},
+                                    "trackLifecycle": {"type": "boolean"},
+                                },
+                            },
+                        },
+                    },
+                },
+                "event": {
+                    "type": "object",
+                    "required": ["ts", "applied_objs", "deleted_objs"],
+                    "properties": {
+                        "ts": {"$ref": "#/$defs/timestamp"},
+                        "applied_objs": {"type": "array", "items": {"$ref": "#/$defs/object"}},
+                        "deleted_objs": {"type": "array", "items": {"$ref": "#/$defs/object"}},
+                    },
+                    "additionalProperties": false,
+                },
+
+                // Objects are stored more or less as they came from the apiserver (minus the status,
+                // and some of the metadata; see sanitize_obj), so anything past this is up to the kind
+                "object": {
+                    "type": "object",
+                    "required": ["apiVersion", "kind", "metadata"],
+                    "properties": {
+                        "apiVersion": {"type": "string"},
+                        "kind": {"type": "string"},
+                        "metadata": {
+                            "type": "object",
+                            "required": ["name"],
+                            "properties": {"name": {"type": "string"}, "namespace": {"type": "string"}},
+                        },
+                    },
+                },
+
+                // The hash of each object's spec, keyed by namespaced name
+                "index": {"type": "object", "additionalProperties": {"type": "integer", "minimum": 0}},
+
+                // Everything from here on down is keyed by the namespaced name of the pods' owner and
+                // then by the hash of the pod spec
+                "podLifecycles": {
+                    "type": "object",
+                    "additionalProperties": {
+                        "type": "object",
+                        "propertyNames": {"$ref": "#/$defs/podHash"},
+                        "additionalProperties": {"type": "array", "items": {"$ref": "#/$defs/podLifecycle"}},
+                    },
+                },
+                "podLifecycle": {
+                    "oneOf": [
+                        {"const": "Empty"},
+                        {
+                            "type": "object",
+                            "required": ["Pending"],
+                            "properties": {"Pending": {"$ref": "#/$defs/timestamp"}},
+                            "additionalProperties": false,
+                        },
+                        {
+                            "type": "object",
+                            "required": ["Running"],
+                            "properties": {"Running": {"$ref": "#/$defs/timestamp"}},
+                            "additionalProperties": false,
+                        },
+                        {
+                            "type": "object",
+                            "required": ["Finished"],
+                            "properties": {
+                                // start ts, end ts, and the reason the pod terminated (if we know it)
+                                "Finished": {
+                                    "type": "array",
+                                    "prefixItems": [
+                                        {"$ref": "#/$defs/timestamp"},
+                                        {"$ref": "#/$defs/timestamp"},
+                                        {"type": ["string", "null"]},
+                                    ],
+                                    "minItems": 3,
+                                    "items": false,
+                                },
+                            },
+                            "additionalProperties": false,
+                        },
+                    ],
+                },
+                "podUsage": {
+                    "type": "object",
+                    "additionalProperties": {
+                        "type": "object",
+                        "propertyNames": {"$ref": "#/$defs/podHash"},
+                        "additionalProperties": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "required": ["ts", "containers"],
+                                "properties": {
+                                    "ts": {"$ref": "#/$defs/timestamp"},
+                                    // container name -> resource name -> quantity
+                                    "containers": {
+                                        "type": "object",
+                                        "additionalProperties": {
+                                            "type": "object",
+                                            "additionalProperties": {"type": "string"},
+                                        },
+                                    },
+                                },
+                                "additionalProperties": false,
+                            },
+                        },
+                    },
+                },
+                "history": {
+                    "type": "object",
+                    "additionalProperties": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "required": ["ts", "obj"],
+                            "properties": {"ts": {"$ref": "#/$defs/timestamp"}, "obj": {"$ref": "#/$defs/object"}},
+                            "additionalProperties": false,
+                        },
+                    },
+                },
+                "podReadiness": {
+                    "type": "object",
+                    "additionalProperties": {
+                        "type": "object",
+                        "propertyNames": {"$ref": "#/$defs/podHash"},
+                        "additionalProperties": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "required": ["start_ts", "ready_ts"],
+                                "properties": {
+                                    "start_ts": {"$ref": "#/$defs/timestamp"},
+                                    "ready_ts": {"$ref": "#/$defs/timestamp"},
+                                },
+                                "additionalProperties": false,
+                            },
+                        },
+                    },
+                },
+                "podEvents": {
+                    "type": "object",
+                    "additionalProperties": {
+                        "type": "object",
+                        "propertyNames": {"$ref": "#/$defs/podHash"},
+                        "additionalProperties": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "required": ["ts", "event_type", "reason", "message", "count"],
+                                "properties": {
+                                    "ts": {"$ref": "#/$defs/timestamp"},
+                                    "event_type": {"type": "string"},
+                                    "reason": {"type": "string"},
+                                    "message": {"type": "string"},
+                                    "count": {"type": "integer"},
+                                },
+                                "additionalProperties": false,
+                            },
+                        },
+                    },
+                },
+                "podEnv": {
+                    "type": "object",
+                    "additionalProperties": {
+                        "type": "object",
+                        "propertyNames": {"$ref": "#/$defs/podHash"},
+                        // container name -> variable name -> value
+                        "additionalProperties": {
+                            "type": "object",
+                            "additionalProperties": {"type": "object", "additionalProperties": {"type": "string"}},
+                        },
+                    },
+                },
+                "podOrdinals": {
+                    "type": "object",
+                    "additionalProperties": {
+                        "type": "object",
+                        "propertyNames": {"$ref": "#/$defs/podHash"},
+                        "additionalProperties": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "required": ["ordinal", "lifecycle_idx"],
+                                "properties": {
+                                    "ordinal": {"type": "integer", "minimum": 0},
+                                    "lifecycle_idx": {"type": "integer", "minimum": 0},
+                                },
+                                "additionalProperties": false,
+                            },
+                        },
+                    },
+                },
+
+                // These two are copied out of the pod spec, so they're in the Kubernetes format
+                "podScheduling": {
+                    "type": "object",
+                    "additionalProperties": {
+                        "type": "object",
+                        "propertyNames": {"$ref": "#/$defs/podHash"},
+                        "additionalProperties": {
+                            "type": "object",
+                            "properties": {
+                                "affinity": {"type": ["object", "null"]},
+                                "topologySpreadConstraints": {"type": ["array", "null"]},
+                            },
+                        },
+                    },
+                },
+                "podProbes": {
+                    "type": "object",
+                    "additionalProperties": {
+                        "type": "object",
+                        "propertyNames": {"$ref": "#/$defs/podHash"},
+                        "additionalProperties": {"type": "object"},
+                    },
+                },
+            },
+        })
+    }
+}
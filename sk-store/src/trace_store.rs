@@ -1,5 +1,8 @@
+use std::borrow::Cow;
+use std::cmp::Reverse;
 use std::collections::{
     HashMap,
+    HashSet,
     VecDeque,
 };
 use std::mem::take;
@@ -11,37 +14,125 @@ use clockabilly::{
 };
 use kube::api::DynamicObject;
 use kube::ResourceExt;
+use serde::{
+    Deserialize,
+    Serialize,
+};
 use sk_api::v1::ExportFilters;
+use sk_core::errors::SimKubeError;
 use sk_core::jsonutils;
 use sk_core::k8s::{
     build_deletable,
+    is_statefulset,
+    sanitize_obj,
+    statefulset_pod_ordinal,
+    JobState,
     KubeResourceExt,
+    PodEnv,
+    PodEvent,
     PodExt,
     PodLifecycleData,
+    PodOrdinal,
+    PodProbes,
+    PodReadiness,
+    PodScheduling,
+    PodUsage,
     GVK,
 };
 use sk_core::prelude::*;
 use sk_core::time::duration_to_ts_from;
 
-use crate::config::TracerConfig;
+use crate::config::{
+    OversizedObjectPolicy,
+    TracerConfig,
+};
+use crate::encryption::is_encrypted;
 use crate::pod_owners_map::{
+    close_open_intervals,
+    PodEnvMap,
+    PodEventsMap,
     PodLifecyclesMap,
+    PodOrdinalsMap,
     PodOwnersMap,
+    PodProbesMap,
+    PodReadinessMap,
+    PodSchedulingMap,
+    PodUsageMap,
+};
+use crate::replay::find_dependencies;
+use crate::scrub::TraceScrubber;
+use crate::trace_filter::{
+    filter_event,
+    stale_objects,
+    RefRepair,
+};
+use crate::trace_header::{
+    read_header,
+    read_trace_data,
+    write_with_header,
+    TraceFormat,
+    TraceHeader,
 };
-use crate::trace_filter::filter_event;
 use crate::{
+    ObjectVersion,
+    TimelineEvent,
     TraceAction,
     TraceEvent,
     TraceIterator,
     TraceStorable,
 };
 
-#[derive(Default)]
+// The whole store (unlike an export) can be serialized, so that long-running captures can be
+// checkpointed to disk and picked up again later; see `checkpoint` and `from_checkpoint`.
+#[derive(Default, Deserialize, Serialize)]
 pub struct TraceStore {
     pub(crate) config: TracerConfig,
     pub(crate) events: VecDeque<TraceEvent>,
     pub(crate) pod_owners: PodOwnersMap,
     pub(crate) index: HashMap<String, u64>,
+    pub(crate) pod_usage: HashMap<String, PodUsageMap>,
+
+    // Every version of an object that we record is already in the event list, so we don't keep a
+    // separate copy of them for the object history (see history_until); this is only filled in for
+    // imported traces, whose events start at the beginning of the trace and so can't tell us how
+    // the objects got to be in the state they're in at that point
+    pub(crate) history: HashMap<String, VecDeque<ObjectVersion>>,
+    pub(crate) pod_readiness: HashMap<String, PodReadinessMap>,
+    pub(crate) pod_events: HashMap<String, PodEventsMap>,
+    pub(crate) pod_env: HashMap<String, PodEnvMap>,
+    pub(crate) pod_ordinals: HashMap<String, PodOrdinalsMap>,
+    pub(crate) pod_scheduling: HashMap<String, PodSchedulingMap>,
+    pub(crate) pod_probes: HashMap<String, PodProbesMap>,
+
+    pub(crate) namespace_counts: HashMap<String, usize>,
+    pub(crate) capped_namespaces: HashSet<String>,
+    pub(crate) oversized_objs: HashSet<String>,
+}
+
+// Older traces don't have pod usage data (or object history, readiness data, events, env,
+// StatefulSet ordinals, scheduling constraints, or probes) in them, so we deserialize into this
+// struct (instead of a bare tuple) so that missing trailing fields just get their default values.
+#[derive(Deserialize)]
+struct ExportedTrace(
+    TracerConfig,
+    VecDeque<TraceEvent>,
+    HashMap<String, u64>,
+    HashMap<String, PodLifecyclesMap>,
+    #[serde(default)] HashMap<String, PodUsageMap>,
+    #[serde(default)] HashMap<String, VecDeque<ObjectVersion>>,
+    #[serde(default)] HashMap<String, PodReadinessMap>,
+    #[serde(default)] HashMap<String, PodEventsMap>,
+    #[serde(default)] HashMap<String, PodEnvMap>,
+    #[serde(default)] HashMap<String, PodOrdinalsMap>,
+    #[serde(default)] HashMap<String, PodSchedulingMap>,
+    #[serde(default)] HashMap<String, PodProbesMap>,
+);
+
+// Serializes the same as an ObjectVersion, but borrows the object from the event list
+#[derive(Serialize)]
+struct ObjectVersionRef<'a> {
+    ts: i64,
+    obj: &'a DynamicObject,
 }
 
 // The TraceStore object is an in-memory store of a cluster trace.  It keeps track of all the
@@ -57,6 +148,16 @@ impl TraceStore {
     }
 
     pub fn export(&self, start_ts: i64, end_ts: i64, filter: &ExportFilters) -> anyhow::Result<Vec<u8>> {
+        self.export_as(start_ts, end_ts, filter, TraceFormat::Msgpack)
+    }
+
+    pub fn export_as(
+        &self,
+        start_ts: i64,
+        end_ts: i64,
+        filter: &ExportFilters,
+        format: TraceFormat,
+    ) -> anyhow::Result<Vec<u8>> {
         info!("Exporting objs between {start_ts} and {end_ts} with filters: {filter:?}");
 
         // First, we collect all the events in our trace that match our configured filters.  This
@@ -67,23 +168,107 @@ impl TraceStore {
 
         // Collect all pod lifecycle data that is a) between the start and end times, and b) is
         // owned by some object contained in the trace
-        let lifecycle_data = self.pod_owners.filter(start_ts, end_ts, &index);
-        let data = rmp_serde::to_vec_named(&(&self.config, &events, &index, &lifecycle_data))?;
+        let mut lifecycle_data = self.pod_owners.filter(start_ts, end_ts, &index);
+        if filter.close_open_intervals.unwrap_or(false) {
+            close_open_intervals(&mut lifecycle_data, end_ts);
+        }
+        let pod_usage: HashMap<_, _> = self.pod_usage.iter().filter(|(owner, _)| index.contains_key(*owner)).collect();
+
+        // We keep history from before the start of the trace, since that's how the object got to
+        // the state that it's in at the beginning, but drop anything after the end
+        let history: HashMap<_, _> = self
+            .history_until(end_ts)
+            .into_iter()
+            .filter(|(ns_name, _)| index.contains_key(ns_name))
+            .collect();
+        let pod_readiness: HashMap<_, _> = self
+            .pod_readiness
+            .iter()
+            .filter(|(owner, _)| index.contains_key(*owner))
+            .collect();
+        let pod_events: HashMap<_, _> =
+            self.pod_events.iter().filter(|(owner, _)| index.contains_key(*owner)).collect();
+        let pod_env: HashMap<_, _> = self.pod_env.iter().filter(|(owner, _)| index.contains_key(*owner)).collect();
+        let pod_ordinals: HashMap<_, _> = self
+            .pod_ordinals
+            .iter()
+            .filter(|(owner, _)| index.contains_key(*owner))
+            .collect();
+        let pod_scheduling: HashMap<_, _> = self
+            .pod_scheduling
+            .iter()
+            .filter(|(owner, _)| index.contains_key(*owner))
+            .collect();
+        let pod_probes: HashMap<_, _> =
+            self.pod_probes.iter().filter(|(owner, _)| index.contains_key(*owner)).collect();
+        let header = TraceHeader::build(&self.config, &events)?;
+        let trace = (
+            &self.config,
+            &events,
+            &index,
+            &lifecycle_data,
+            &pod_usage,
+            &history,
+            &pod_readiness,
+            &pod_events,
+            &pod_env,
+            &pod_ordinals,
+            &pod_scheduling,
+            &pod_probes,
+        );
 
         info!("Exported {} events", events.len());
-        Ok(data)
+        write_with_header(&header, &trace, format)
+    }
+
+    // Unlike `export`, this doesn't do any filtering; it just writes out everything that's in the
+    // store (used, e.g., to write out a trace after it's been scrubbed).
+    pub fn export_all(&self) -> anyhow::Result<Vec<u8>> {
+        let header = TraceHeader::build(&self.config, &self.events)?;
+        let trace = (
+            &self.config,
+            &self.events,
+            &self.index,
+            self.pod_owners.lifecycles(),
+            &self.pod_usage,
+            &self.history_until(i64::MAX),
+            &self.pod_readiness,
+            &self.pod_events,
+            &self.pod_env,
+            &self.pod_ordinals,
+            &self.pod_scheduling,
+            &self.pod_probes,
+        );
+
+        info!("Exported {} events", self.events.len());
+        write_with_header(&header, &trace, TraceFormat::Msgpack)
     }
 
     // Note that _importing_ data into a trace store is lossy -- we don't store (or import) all of
     // the metadata necessary to pick up a trace and continue.  Instead, we just re-import enough
     // information to be able to run a simulation off the trace store.
     pub fn import(data: Vec<u8>, maybe_duration: &Option<String>) -> anyhow::Result<TraceStore> {
-        let (config, mut events, index, lifecycle_data): (
-            TracerConfig,
-            VecDeque<TraceEvent>,
-            HashMap<String, u64>,
-            HashMap<String, PodLifecyclesMap>,
-        ) = rmp_serde::from_slice(&data)?;
+        // Otherwise we'd just get an inscrutable msgpack error
+        if is_encrypted(&data) {
+            return Err(SimKubeError::trace_format(
+                "trace is encrypted and must be decrypted before it can be imported",
+            ));
+        }
+
+        let ExportedTrace(
+            config,
+            mut events,
+            index,
+            lifecycle_data,
+            pod_usage,
+            history,
+            pod_readiness,
+            pod_events,
+            pod_env,
+            pod_ordinals,
+            pod_scheduling,
+            pod_probes,
+        ) = read_trace_data(&data)?;
 
         let trace_start_ts = events
             .front()
@@ -105,11 +290,482 @@ impl TraceStore {
         Ok(TraceStore {
             config,
             events,
+            namespace_counts: count_namespaces(&index),
             index,
             pod_owners: PodOwnersMap::new_from_parts(lifecycle_data, HashMap::new()),
+            pod_usage,
+            history,
+            pod_readiness,
+            pod_events,
+            pod_env,
+            pod_ordinals,
+            pod_scheduling,
+            pod_probes,
+            ..Default::default()
         })
     }
 
+    // Reads just the summary at the start of an exported trace file (see trace_header.rs), without
+    // loading the rest of the trace; older traces don't have a header, so this returns None for them.
+    pub fn read_header(path: &str) -> anyhow::Result<Option<TraceHeader>> {
+        read_header(path)
+    }
+
+    // A checkpoint, unlike an export, includes all of the store's internal bookkeeping (e.g., which
+    // pods we're tracking lifecycles for and how many objects are in each namespace), so that a
+    // store loaded from a checkpoint can keep recording exactly where the old one left off.
+    // Checkpoints aren't traces, and can't be used to run simulations.
+    pub fn checkpoint(&self) -> anyhow::Result<Vec<u8>> {
+        rmp_serde::to_vec_named(self).map_err(SimKubeError::serialization)
+    }
+
+    // The config that's passed in replaces the one in the checkpoint, since the caller is the one
+    // deciding what gets watched from here on out
+    pub fn from_checkpoint(data: &[u8], config: TracerConfig) -> anyhow::Result<TraceStore> {
+        let mut store: TraceStore = rmp_serde::from_slice(data)?;
+        store.config = config;
+        info!("Loaded checkpoint with {} events", store.events.len());
+        Ok(store)
+    }
+
+    // Scrub all of the identifying information out of the trace; see scrub.rs for details.  Every
+    // place in the store that references an object by name has to go through the same scrubber so
+    // that everything still lines up afterwards.
+    pub fn scrub(&mut self, scrubber: &mut TraceScrubber) -> EmptyResult {
+        // Namespace objects are keyed by their name like any other cluster-scoped object, but the
+        // name goes through the namespace mapping (see scrub_obj), so the key has to as well
+        let namespace_objs: HashSet<_> = self
+            .events
+            .iter()
+            .flat_map(|evt| evt.applied_objs.iter().chain(evt.deleted_objs.iter()))
+            .filter(|obj| obj.types.as_ref().is_some_and(|t| t.kind == "Namespace"))
+            .filter_map(|obj| obj.metadata.name.clone())
+            .collect();
+        let scrub_key = |scrubber: &mut TraceScrubber, key: &str| match namespace_objs.contains(key) {
+            true => scrubber.namespace(key),
+            false => scrubber.namespaced_name(key),
+        };
+
+        for evt in self.events.iter_mut() {
+            for obj in evt.applied_objs.iter_mut().chain(evt.deleted_objs.iter_mut()) {
+                scrubber.scrub_obj(obj)?;
+            }
+        }
+
+        for version in self.history.values_mut().flatten() {
+            scrubber.scrub_obj(&mut version.obj)?;
+        }
+
+        self.index = take(&mut self.index)
+            .into_iter()
+            .map(|(ns_name, hash)| (scrub_key(scrubber, &ns_name), hash))
+            .collect();
+        self.namespace_counts = count_namespaces(&self.index);
+        self.pod_owners.rename(|ns_name| scrubber.namespaced_name(ns_name));
+        self.pod_usage = take(&mut self.pod_usage)
+            .into_iter()
+            .map(|(ns_name, usage)| (scrubber.namespaced_name(&ns_name), usage))
+            .collect();
+        self.pod_readiness = take(&mut self.pod_readiness)
+            .into_iter()
+            .map(|(ns_name, readiness)| (scrubber.namespaced_name(&ns_name), readiness))
+            .collect();
+        self.pod_events = take(&mut self.pod_events)
+            .into_iter()
+            .map(|(ns_name, events)| (scrubber.namespaced_name(&ns_name), events))
+            .collect();
+
+        // Like env vars, event messages are free-form text that can mention just about anything
+        // (e.g., "Successfully assigned prod/frontend-abc to node-1"), so we blank them out; the
+        // reason and type are enough to tell what happened to the pod
+        for evt in self.pod_events.values_mut().flat_map(|events| events.values_mut()).flatten() {
+            evt.message.clear();
+        }
+        self.pod_ordinals = take(&mut self.pod_ordinals)
+            .into_iter()
+            .map(|(ns_name, ordinals)| (scrubber.namespaced_name(&ns_name), ordinals))
+            .collect();
+        self.pod_scheduling = take(&mut self.pod_scheduling)
+            .into_iter()
+            .map(|(ns_name, scheduling)| (scrubber.namespaced_name(&ns_name), scheduling))
+            .collect();
+        self.pod_probes = take(&mut self.pod_probes)
+            .into_iter()
+            .map(|(ns_name, probes)| (scrubber.namespaced_name(&ns_name), probes))
+            .collect();
+
+        // Env var values can contain just about anything (hostnames, URLs, names of other objects),
+        // and we have no way of knowing what needs to be scrubbed in them, so we just drop them
+        self.pod_env.clear();
+        self.history = take(&mut self.history)
+            .into_iter()
+            .map(|(ns_name, versions)| (scrub_key(scrubber, &ns_name), versions))
+            .collect();
+
+        Ok(())
+    }
+
+    // Re-applies the rules that we use when recording objects to everything in the store, so that
+    // traces that were captured with older versions of SimKube (before we stripped out as much noise
+    // as we do now) can be shrunk down: every object is sanitized, and updates that don't change an
+    // object's spec are dropped, since the store wouldn't have recorded them in the first place.
+    // Events that end up empty are dropped too, except for the last one, which marks the end of the
+    // trace.  Returns the number of updates that were dropped.
+    pub fn compact(&mut self) -> usize {
+        let mut last_hashes: HashMap<String, u64> = HashMap::new();
+        let mut dropped = 0;
+        for evt in self.events.iter_mut() {
+            for obj in evt.applied_objs.iter_mut().chain(evt.deleted_objs.iter_mut()) {
+                sanitize_in_place(obj);
+            }
+
+            let before = evt.applied_objs.len();
+            evt.applied_objs.retain(|obj| {
+                let hash = obj_hash(obj);
+                last_hashes.insert(obj.namespaced_name(), hash) != Some(hash)
+            });
+            dropped += before - evt.applied_objs.len();
+
+            for obj in &evt.deleted_objs {
+                last_hashes.remove(&obj.namespaced_name());
+            }
+        }
+
+        let last_ts = self.events.back().map(|evt| evt.ts);
+        self.events
+            .retain(|evt| Some(evt.ts) == last_ts || !evt.applied_objs.is_empty() || !evt.deleted_objs.is_empty());
+
+        for version in self.history.values_mut().flatten() {
+            sanitize_in_place(&mut version.obj);
+        }
+
+        dropped
+    }
+
+    // Cheap summaries of what's in the store, so that callers don't have to iterate through (or
+    // export) the whole trace to find out how big it is.  These come from the index, so for a store
+    // that's recording, they count the objects that currently exist; for an imported trace, the
+    // index includes every object in the trace, even ones that were deleted before the end.
+    pub fn object_count(&self) -> usize {
+        self.index.len()
+    }
+
+    // Cluster-scoped objects don't count towards any namespace
+    pub fn namespaces(&self) -> HashSet<String> {
+        self.namespace_counts
+            .iter()
+            .filter(|(ns, count)| !ns.is_empty() && **count > 0)
+            .map(|(ns, _)| ns.clone())
+            .collect()
+    }
+
+    // This is the number of pods that we have lifecycle data for; it's linear in the number of pod
+    // owners, rather than the number of pods.
+    pub fn pod_count(&self) -> usize {
+        self.pod_owners.pod_count()
+    }
+
+    pub fn time_bounds(&self) -> Option<(i64, i64)> {
+        self.start_ts().zip(self.end_ts())
+    }
+
+    // Shift every timestamp in the trace so that the first event happens at new_zero; this makes it
+    // easier to compare (or combine) traces that were recorded at different times.  The intervals
+    // between everything in the trace stay the same.
+    pub fn rebase(&mut self, new_zero: i64) {
+        let Some(start_ts) = self.start_ts() else {
+            return;
+        };
+        let delta = new_zero - start_ts;
+
+        for evt in self.events.iter_mut() {
+            evt.ts += delta;
+        }
+        self.pod_owners.shift_timestamps(delta);
+        for usage in self.pod_usage.values_mut().flat_map(|u| u.values_mut()).flatten() {
+            usage.ts += delta;
+        }
+        for readiness in self.pod_readiness.values_mut().flat_map(|r| r.values_mut()).flatten() {
+            readiness.start_ts += delta;
+            readiness.ready_ts += delta;
+        }
+        for evt in self.pod_events.values_mut().flat_map(|e| e.values_mut()).flatten() {
+            evt.ts += delta;
+        }
+        for version in self.history.values_mut().flatten() {
+            version.ts += delta;
+        }
+    }
+
+    // Resource usage samples are stored alongside the lifecycle data for a pod, i.e., keyed by the
+    // owning object and the hash of the pod spec.  If we're not tracking the lifecycle of this pod
+    // then we don't know who owns it, so we just drop the sample on the floor.
+    pub fn record_pod_usage(&mut self, ns_name: &str, usage: PodUsage) -> bool {
+        let Some((owner_ns_name, hash, _)) = self.pod_owners.pod_owner_meta(ns_name) else {
+            debug!("pod {ns_name} is not tracked, ignoring usage data");
+            return false;
+        };
+
+        self.pod_usage
+            .entry(owner_ns_name.clone())
+            .or_default()
+            .entry(*hash)
+            .or_default()
+            .push(usage);
+        true
+    }
+
+    pub fn pod_usage_for(&self, owner_ns_name: &str, pod_hash: u64) -> Option<&Vec<PodUsage>> {
+        self.pod_usage.get(owner_ns_name)?.get(&pod_hash)
+    }
+
+    // Events are joined to pods by name (see sk_core::k8s::correlate_pod_events), and then stored
+    // the same way as usage data; events for untracked pods are dropped.  If we see the same event
+    // twice (e.g., because we resumed from a checkpoint), it's only stored once.
+    pub fn record_pod_events(&mut self, ns_name: &str, events: Vec<PodEvent>) -> bool {
+        let Some((owner_ns_name, hash, _)) = self.pod_owners.pod_owner_meta(ns_name) else {
+            debug!("pod {ns_name} is not tracked, ignoring events");
+            return false;
+        };
+
+        let entries = self
+            .pod_events
+            .entry(owner_ns_name.clone())
+            .or_default()
+            .entry(*hash)
+            .or_default();
+        for evt in events {
+            if !entries.contains(&evt) {
+                entries.push(evt);
+            }
+        }
+        true
+    }
+
+    pub fn pod_events_for(&self, owner_ns_name: &str, pod_hash: u64) -> Option<&Vec<PodEvent>> {
+        self.pod_events.get(owner_ns_name)?.get(&pod_hash)
+    }
+
+    pub fn pod_env_for(&self, owner_ns_name: &str, pod_hash: u64) -> Option<&PodEnv> {
+        self.pod_env.get(owner_ns_name)?.get(&pod_hash)
+    }
+
+    pub fn pod_readiness_for(&self, owner_ns_name: &str, pod_hash: u64) -> Option<&Vec<PodReadiness>> {
+        self.pod_readiness.get(owner_ns_name)?.get(&pod_hash)
+    }
+
+    pub fn pod_scheduling_for(&self, owner_ns_name: &str, pod_hash: u64) -> Option<&PodScheduling> {
+        self.pod_scheduling.get(owner_ns_name)?.get(&pod_hash)
+    }
+
+    pub fn pod_probes_for(&self, owner_ns_name: &str, pod_hash: u64) -> Option<&PodProbes> {
+        self.pod_probes.get(owner_ns_name)?.get(&pod_hash)
+    }
+
+    pub fn pod_ordinals_for(&self, owner_ns_name: &str, pod_hash: u64) -> Option<&Vec<PodOrdinal>> {
+        self.pod_ordinals.get(owner_ns_name)?.get(&pod_hash)
+    }
+
+    // StatefulSet pods get the next available ordinal when they're created, and keep it if they're
+    // re-created later, so the same ordinal can show up more than once for an owner.
+    fn record_pod_ordinal(&mut self, ns_name: &str, pod_name: &str, sts_name: &str) {
+        let Some((owner_ns_name, hash, idx)) = self.pod_owners.pod_owner_meta(ns_name) else {
+            return;
+        };
+        let Some(ordinal) = statefulset_pod_ordinal(pod_name, sts_name) else {
+            warn!("could not determine ordinal for pod {ns_name} owned by StatefulSet {owner_ns_name}");
+            return;
+        };
+
+        self.pod_ordinals
+            .entry(owner_ns_name.clone())
+            .or_default()
+            .entry(*hash)
+            .or_default()
+            .push(PodOrdinal { ordinal, lifecycle_idx: *idx });
+    }
+
+    pub fn pod_lifecycles_for(&self, owner_ns_name: &str) -> Option<&PodLifecyclesMap> {
+        self.pod_owners.lifecycles().get(owner_ns_name)
+    }
+
+    // Returns the lifecycle data for every pod in the trace whose owner matches the given filters;
+    // this is the same data that would end up in an export of the entire trace.
+    pub fn pod_lifecycles(&self, filter: &ExportFilters) -> HashMap<String, PodLifecyclesMap> {
+        let (_, index) = self.collect_events(0, i64::MAX, filter, true);
+        self.pod_owners.filter(0, i64::MAX, &index)
+    }
+
+    // Returns the (latest version of) every object that still exists at the end of the trace and
+    // that matches the given filters, in no particular order.
+    pub fn objs_at_end(&self, filter: &ExportFilters) -> Vec<DynamicObject> {
+        // collect_events flattens everything before the start time into its first event, so if we
+        // start at the very end of time, that's exactly the final set of objects
+        let (mut events, _) = self.collect_events(i64::MAX, i64::MAX, filter, false);
+        take(&mut events[0].applied_objs)
+    }
+
+    // Returns the recorded versions of an object, oldest first; this will be empty unless
+    // object_history_depth is set in the tracer config.
+    pub fn history_for(&self, ns_name: &str) -> Vec<ObjectVersion> {
+        self.history_until(i64::MAX)
+            .remove(ns_name)
+            .into_iter()
+            .flatten()
+            .map(|v| ObjectVersion { ts: v.ts, obj: v.obj.clone() })
+            .collect()
+    }
+
+    // The most recent (up to object_history_depth) versions of each object from before end_ts,
+    // oldest first
+    fn history_until(&self, end_ts: i64) -> HashMap<String, VecDeque<ObjectVersionRef<'_>>> {
+        let depth = self.config.object_history_depth;
+        if depth == 0 {
+            return HashMap::new();
+        }
+
+        if !self.history.is_empty() {
+            return self
+                .history
+                .iter()
+                .map(|(ns_name, versions)| {
+                    let versions = versions
+                        .iter()
+                        .filter(|v| v.ts < end_ts)
+                        .map(|v| ObjectVersionRef { ts: v.ts, obj: &v.obj })
+                        .collect();
+                    (ns_name.clone(), versions)
+                })
+                .collect();
+        }
+
+        let mut history: HashMap<_, VecDeque<_>> = HashMap::new();
+        for evt in self.events.iter().take_while(|evt| evt.ts < end_ts) {
+            for obj in &evt.applied_objs {
+                let versions = history.entry(obj.namespaced_name()).or_default();
+                versions.push_back(ObjectVersionRef { ts: evt.ts, obj });
+                if versions.len() > depth {
+                    versions.pop_front();
+                }
+            }
+        }
+        history
+    }
+
+    // Read-only views of the objects in the store, for callers that want to look at the trace
+    // without exporting it.  These return the latest version of each object that still exists at
+    // the end of the trace, in no particular order.  Objects of different kinds can have the same
+    // name, so these are keyed on both.
+    pub fn objects(&self) -> impl Iterator<Item = &DynamicObject> {
+        let key = |obj: &DynamicObject| (obj.types.as_ref().map(|t| t.kind.clone()), obj.namespaced_name());
+
+        let mut objs = HashMap::new();
+        for evt in &self.events {
+            for obj in &evt.applied_objs {
+                objs.insert(key(obj), obj);
+            }
+            for obj in &evt.deleted_objs {
+                objs.remove(&key(obj));
+            }
+        }
+        objs.into_values()
+    }
+
+    // Everything that happened in the trace, in timestamp order: objects being applied or deleted,
+    // and pods starting or finishing.  Pods that never started (i.e., were still pending when the
+    // trace ended) don't show up at all.  Events with the same timestamp come out with the object
+    // events first, in the order they were recorded, followed by the pod events, ordered by owner,
+    // hash, and launch order.
+    pub fn iter_events(&self) -> impl Iterator<Item = TimelineEvent<'_>> {
+        let mut events = vec![];
+        for evt in &self.events {
+            events.extend(
+                evt.applied_objs
+                    .iter()
+                    .map(|obj| TimelineEvent::ObjectApplied { ts: evt.ts, obj }),
+            );
+            events.extend(
+                evt.deleted_objs
+                    .iter()
+                    .map(|obj| TimelineEvent::ObjectDeleted { ts: evt.ts, obj }),
+            );
+        }
+
+        let mut lifecycles: Vec<_> = self
+            .pod_owners
+            .lifecycles()
+            .iter()
+            .flat_map(|(owner_ns_name, lifecycles_map)| {
+                lifecycles_map
+                    .iter()
+                    .map(move |(pod_hash, lifecycles)| (owner_ns_name.as_str(), *pod_hash, lifecycles))
+            })
+            .collect();
+        lifecycles.sort_by_key(|(owner_ns_name, pod_hash, _)| (*owner_ns_name, *pod_hash));
+
+        for (owner_ns_name, pod_hash, lifecycles) in lifecycles {
+            for (seq, lifecycle_data) in lifecycles.iter().enumerate() {
+                let (start_ts, end) = match lifecycle_data {
+                    PodLifecycleData::Running(start_ts) => (*start_ts, None),
+                    PodLifecycleData::Finished(start_ts, end_ts, reason) => (*start_ts, Some((*end_ts, reason))),
+                    PodLifecycleData::Empty | PodLifecycleData::Pending(_) => continue,
+                };
+                events.push(TimelineEvent::PodStarted { ts: start_ts, owner_ns_name, pod_hash, seq });
+                if let Some((ts, reason)) = end {
+                    let reason = reason.as_deref();
+                    events.push(TimelineEvent::PodFinished { ts, owner_ns_name, pod_hash, seq, reason });
+                }
+            }
+        }
+
+        // sort_by_key is stable, so this keeps everything else in the order described above
+        events.sort_by_key(|evt| evt.ts());
+        events.into_iter()
+    }
+
+    // If we never saw any of a Job's pods (e.g., because they'd already been cleaned up by the time
+    // the trace started), but we know that the Job completed, we make up a lifecycle for its pods so
+    // that the simulated Job finishes in the same amount of time, instead of running forever.  Jobs
+    // with parallelism > 1 run their pods in waves, so see JobState::pod_lifetime for how the time
+    // gets divided up.
+    fn completed_job_lifecycle(&self, owner_ns_name: &str) -> Option<PodLifecycleData> {
+        let job = self
+            .events
+            .iter()
+            .rev()
+            .flat_map(|evt| evt.applied_objs.iter())
+            .find(|obj| obj.namespaced_name() == owner_ns_name)?;
+        let state = match job.annotations().get(JOB_STATE_ANNOTATION_KEY) {
+            Some(state) => serde_json::from_str(state).ok()?,
+            // older traces don't have the annotation, but the status is still there
+            None => JobState::from_dynamic_obj(job).ok()??,
+        };
+
+        let start_ts = state.start_ts?;
+        Some(PodLifecycleData::Finished(start_ts, start_ts + state.pod_lifetime()?, None))
+    }
+
+    pub fn objects_in_namespace<'a>(&'a self, ns: &'a str) -> impl Iterator<Item = &'a DynamicObject> {
+        self.objects().filter(move |obj| obj.metadata.namespace.as_deref() == Some(ns))
+    }
+
+    // Objects without any type information can't match a kind, so they're skipped
+    pub fn objects_of_kind<'a>(&'a self, gvk: &'a GVK) -> impl Iterator<Item = &'a DynamicObject> {
+        self.objects()
+            .filter(move |obj| GVK::from_dynamic_obj(obj).is_ok_and(|obj_gvk| &obj_gvk == gvk))
+    }
+
+    // Everything that any object in the given namespaces depends on, at any point in the trace;
+    // namespaced dependencies are in here too, but we only use this to decide which cluster-scoped
+    // objects to keep, so that doesn't matter.
+    fn cluster_dependencies(&self, namespaces: &[String]) -> HashSet<String> {
+        self.iter()
+            .flat_map(|(evt, _)| evt.applied_objs.iter())
+            .filter(|obj| obj.namespace().is_some_and(|ns| namespaces.contains(&ns)))
+            .flat_map(find_dependencies)
+            .collect()
+    }
+
     pub(crate) fn collect_events(
         &self,
         start_ts: i64,
@@ -126,6 +782,20 @@ impl TraceStore {
         // still present at start_ts -- i.e., it is our starting configuration.
         let mut flattened_objects = HashMap::new();
         let mut index = HashMap::new();
+        let cluster_deps = match &filter.included_namespaces {
+            Some(namespaces) => self.cluster_dependencies(namespaces),
+            None => HashSet::new(),
+        };
+        let stale = match filter.max_age {
+            Some(max_age) => stale_objects(self.iter().map(|(evt, _)| evt), end_ts, max_age),
+            None => HashSet::new(),
+        };
+        let repair = RefRepair::plan(
+            self.iter().map(|(evt, _)| evt).take_while(|evt| evt.ts < end_ts),
+            filter,
+            &cluster_deps,
+            &stale,
+        );
 
         for (evt, _) in self.iter() {
             // trace should be end-exclusive, so we use >= here: anything that is at the
@@ -135,13 +805,13 @@ impl TraceStore {
                 break;
             }
 
-            if let Some(new_evt) = filter_event(evt, filter) {
+            if let Some(new_evt) = filter_event(evt, filter, &cluster_deps, &stale, &repair) {
                 for obj in &new_evt.applied_objs {
                     let ns_name = obj.namespaced_name();
                     if new_evt.ts < start_ts {
                         flattened_objects.insert(ns_name.clone(), obj.clone());
                     }
-                    let hash = jsonutils::hash_option(obj.data.get("spec"));
+                    let hash = obj_hash(obj);
                     index.insert(ns_name, hash);
                 }
 
@@ -167,6 +837,81 @@ impl TraceStore {
         (events, index)
     }
 
+    // A single namespace with a runaway controller can swamp the entire trace, so (if configured)
+    // we stop recording new objects in a namespace once it has too many objects in it.  Objects
+    // that we're already tracking still get updated.
+    fn has_capacity_for(&mut self, obj: &DynamicObject) -> bool {
+        let Some(max_objs) = self.config.max_objects_per_namespace else {
+            return true;
+        };
+
+        // Cluster-scoped objects aren't in any namespace, so they don't count against the limit
+        let Some(ns) = obj.namespace() else {
+            return true;
+        };
+        if self.namespace_counts.get(&ns).copied().unwrap_or_default() < max_objs {
+            return true;
+        }
+
+        if self.capped_namespaces.insert(ns.clone()) {
+            warn!("namespace {ns} has reached the maximum of {max_objs} objects; new objects will not be recorded");
+        }
+        debug!("dropping object {} (namespace is full)", obj.namespaced_name());
+        false
+    }
+
+    // Some objects (CRDs with big embedded data, huge ConfigMaps, etc) can dwarf everything else in
+    // the trace, so (if configured) we either truncate their largest fields or drop them entirely
+    // before they get recorded.  Returns None if the object shouldn't be recorded.  We only warn the
+    // first time we see that an object is too big, since it will probably be too big every time.
+    fn apply_size_cap<'a>(&mut self, obj: &'a DynamicObject) -> Option<Cow<'a, DynamicObject>> {
+        let Some(max_size) = self.config.max_object_size else {
+            return Some(Cow::Borrowed(obj));
+        };
+
+        let size = json_size(obj);
+        if size <= max_size {
+            return Some(Cow::Borrowed(obj));
+        }
+
+        let ns_name = obj.namespaced_name();
+        let first_time = self.oversized_objs.insert(ns_name.clone());
+        if self.config.oversized_object_policy == OversizedObjectPolicy::Truncate {
+            let mut truncated = obj.clone();
+            if truncate_large_fields(&mut truncated, max_size) {
+                if first_time {
+                    warn!("object {ns_name} is {size} bytes (max {max_size}); its largest fields will be truncated");
+                }
+                return Some(Cow::Owned(truncated));
+            }
+        }
+
+        if first_time {
+            warn!("object {ns_name} is {size} bytes (max {max_size}); it will not be recorded");
+        }
+        debug!("dropping object {ns_name} (too big)");
+        None
+    }
+
+    // An object that we first saw without a name (see KubeResourceExt::namespaced_name) now has
+    // one, so we fill the name in everywhere we've recorded the object; that way the trace treats
+    // them as the same object, instead of one that was created and never deleted.
+    fn resolve_pending_name(&mut self, obj: &DynamicObject, pending_ns_name: &str) {
+        info!("{pending_ns_name} is now named {}", obj.namespaced_name());
+
+        let fill_name = |o: &mut DynamicObject| {
+            if o.metadata.name.is_none() && o.metadata.uid == obj.metadata.uid {
+                o.metadata.name.clone_from(&obj.metadata.name);
+            }
+        };
+        for evt in self.events.iter_mut() {
+            evt.applied_objs
+                .iter_mut()
+                .chain(evt.deleted_objs.iter_mut())
+                .for_each(fill_name);
+        }
+    }
+
     fn append_event(&mut self, ts: i64, obj: &DynamicObject, action: TraceAction) {
         info!("{} - {:?} @ {}", obj.namespaced_name(), action, ts);
 
@@ -187,6 +932,28 @@ impl TraceStore {
     }
 }
 
+// We normally only record a new version of an object when its spec changes; in particular, updates
+// that only touch metadata (finalizers, resourceVersion, managedFields, etc.) are ignored, since
+// otherwise controllers churning through them would flood the trace.  However, a Job finishing
+// doesn't touch its spec, and the replay needs to know about it (see completed_job_lifecycle); so
+// for Jobs that we've recorded the state of, the completion time counts as part of the spec.  The
+// same goes for Nodes: a node going NotReady (or getting resized) only shows up in its status.
+fn obj_hash(obj: &DynamicObject) -> u64 {
+    let spec = obj.data.get("spec");
+    if let Some(node_state) = obj.annotations().get(NODE_STATE_ANNOTATION_KEY) {
+        return jsonutils::hash(&serde_json::json!([spec, node_state]));
+    }
+    let completion_time = obj
+        .annotations()
+        .contains_key(JOB_STATE_ANNOTATION_KEY)
+        .then(|| obj.data.pointer("/status/completionTime"))
+        .flatten();
+    match completion_time {
+        Some(completion_time) => jsonutils::hash(&serde_json::json!([spec, completion_time])),
+        None => jsonutils::hash_option(spec),
+    }
+}
+
 impl TraceStorable for TraceStore {
     // We use a swap-and-update operation for the index, which means that if we call
     // create_or_update_obj from a refresh event, the _new_ index won't have the hash data
@@ -195,27 +962,63 @@ impl TraceStorable for TraceStore {
     // `None` and look up the value in the current index (if the object didn't exist in the old
     // index either, we'll do a second lookup in the new index, but that should be pretty fast)..
     fn create_or_update_obj(&mut self, obj: &DynamicObject, ts: i64, maybe_old_hash: Option<u64>) {
+        let Some(obj) = self.apply_size_cap(obj) else {
+            return;
+        };
+        let obj = obj.as_ref();
+
         let ns_name = obj.namespaced_name();
-        let new_hash = jsonutils::hash_option(obj.data.get("spec"));
+        if let Some(pending_ns_name) = pending_ns_name(obj) {
+            if let Some(hash) = self.index.remove(&pending_ns_name) {
+                self.index.insert(ns_name.clone(), hash);
+                self.resolve_pending_name(obj, &pending_ns_name);
+            }
+        }
+        let new_hash = obj_hash(obj);
         let old_hash = maybe_old_hash.or_else(|| self.index.get(&ns_name).cloned());
 
+        if old_hash.is_none() && !self.has_capacity_for(obj) {
+            return;
+        }
+
         if Some(new_hash) != old_hash {
             self.append_event(ts, obj, TraceAction::ObjectApplied);
         }
-        self.index.insert(ns_name, new_hash);
+        if self.index.insert(ns_name, new_hash).is_none() {
+            *self.namespace_counts.entry(obj.namespace().unwrap_or_default()).or_default() += 1;
+        }
     }
 
     fn delete_obj(&mut self, obj: &DynamicObject, ts: i64) {
         let ns_name = obj.namespaced_name();
+        let ns = obj.namespace().unwrap_or_default();
+
+        // If we dropped this object because its namespace was full (or because it was too big), we
+        // never recorded it in the first place, so there's nothing to delete.
+        let oversized = self.oversized_objs.remove(&ns_name);
+        if !self.index.contains_key(&ns_name) && (oversized || self.capped_namespaces.contains(&ns)) {
+            return;
+        }
+
         self.append_event(ts, obj, TraceAction::ObjectDeleted);
-        self.index.remove(&ns_name);
+        if self.index.remove(&ns_name).is_some() {
+            if let Some(count) = self.namespace_counts.get_mut(&ns) {
+                *count = count.saturating_sub(1);
+            }
+        }
     }
 
     fn update_all_objs(&mut self, objs: &[DynamicObject], ts: i64) {
         let mut old_index = take(&mut self.index);
+        self.namespace_counts.clear();
         for obj in objs {
             let ns_name = obj.namespaced_name();
-            let old_hash = old_index.remove(&ns_name);
+            let old_hash = old_index.remove(&ns_name).or_else(|| {
+                let pending_ns_name = pending_ns_name(obj)?;
+                let hash = old_index.remove(&pending_ns_name)?;
+                self.resolve_pending_name(obj, &pending_ns_name);
+                Some(hash)
+            });
             self.create_or_update_obj(obj, ts, old_hash);
         }
 
@@ -228,7 +1031,7 @@ impl TraceStorable for TraceStore {
         let maybe_lifecycle_data = self.pod_owners.lifecycle_data_for(owner_ns_name, pod_hash);
         match maybe_lifecycle_data {
             Some(data) => data[seq % data.len()].clone(),
-            _ => PodLifecycleData::Empty,
+            _ => self.completed_job_lifecycle(owner_ns_name).unwrap_or(PodLifecycleData::Empty),
         }
     }
 
@@ -275,6 +1078,9 @@ impl TraceStorable for TraceStore {
                 let hash = jsonutils::hash(&serde_json::to_value(&pod.stable_spec()?)?);
                 self.pod_owners
                     .store_new_pod_lifecycle(ns_name, &owner_ns_name, hash, lifecycle_data);
+                if is_statefulset(&gvk) {
+                    self.record_pod_ordinal(ns_name, &pod.name_any(), &rf.name);
+                }
                 break;
             }
         } else {
@@ -284,6 +1090,76 @@ impl TraceStorable for TraceStore {
         Ok(())
     }
 
+    // Like resource usage, readiness data is keyed by the owning object and pod spec hash, so we
+    // can only store it for pods whose lifecycle we're already tracking.
+    fn record_pod_readiness(&mut self, ns_name: &str, readiness: PodReadiness) -> bool {
+        let Some((owner_ns_name, hash, _)) = self.pod_owners.pod_owner_meta(ns_name) else {
+            debug!("pod {ns_name} is not tracked, ignoring readiness data");
+            return false;
+        };
+
+        // If we're picking back up from a checkpoint, the pod watcher will see all the existing
+        // ready pods again, so we don't want to store them twice
+        let entries = self
+            .pod_readiness
+            .entry(owner_ns_name.clone())
+            .or_default()
+            .entry(*hash)
+            .or_default();
+        if !entries.contains(&readiness) {
+            info!("pod {ns_name} owned by {owner_ns_name} became ready: {readiness:?}");
+            entries.push(readiness);
+        }
+        true
+    }
+
+    // Env vars are part of the pod spec, so every pod with the same owner and pod spec hash has the
+    // same env; we just keep the first one we see.
+    fn record_pod_env(&mut self, ns_name: &str, env: PodEnv) -> bool {
+        let Some((owner_ns_name, hash, _)) = self.pod_owners.pod_owner_meta(ns_name) else {
+            debug!("pod {ns_name} is not tracked, ignoring env data");
+            return false;
+        };
+
+        self.pod_env
+            .entry(owner_ns_name.clone())
+            .or_default()
+            .entry(*hash)
+            .or_insert(env);
+        true
+    }
+
+    // Same as env vars, the scheduling constraints are part of the pod spec, so the first pod we
+    // see for each owner and pod spec hash is good enough
+    fn record_pod_scheduling(&mut self, ns_name: &str, scheduling: PodScheduling) -> bool {
+        let Some((owner_ns_name, hash, _)) = self.pod_owners.pod_owner_meta(ns_name) else {
+            debug!("pod {ns_name} is not tracked, ignoring scheduling constraints");
+            return false;
+        };
+
+        self.pod_scheduling
+            .entry(owner_ns_name.clone())
+            .or_default()
+            .entry(*hash)
+            .or_insert(scheduling);
+        true
+    }
+
+    // Probes are part of the pod spec too
+    fn record_pod_probes(&mut self, ns_name: &str, probes: PodProbes) -> bool {
+        let Some((owner_ns_name, hash, _)) = self.pod_owners.pod_owner_meta(ns_name) else {
+            debug!("pod {ns_name} is not tracked, ignoring probes");
+            return false;
+        };
+
+        self.pod_probes
+            .entry(owner_ns_name.clone())
+            .or_default()
+            .entry(*hash)
+            .or_insert(probes);
+        true
+    }
+
     fn config(&self) -> &TracerConfig {
         &self.config
     }
@@ -326,6 +1202,83 @@ impl<'a> Iterator for TraceIterator<'a> {
     }
 }
 
+// Objects in the store always have their types set (see `sanitize_obj`), so it's fine to just
+// reuse them here
+fn sanitize_in_place(obj: &mut DynamicObject) {
+    let types = obj.types.clone().unwrap_or_default();
+    sanitize_obj(obj, &types.api_version, &types.kind);
+}
+
+fn count_namespaces(index: &HashMap<String, u64>) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for ns_name in index.keys() {
+        let ns = ns_name.split_once('/').map(|(ns, _)| ns).unwrap_or_default();
+        *counts.entry(ns.into()).or_default() += 1;
+    }
+    counts
+}
+
+fn json_size(obj: &DynamicObject) -> usize {
+    serde_json::to_vec(obj).map(|v| v.len()).unwrap_or_default()
+}
+
+// Replace the biggest string values in the object (anywhere in the object body, plus annotation
+// values) with a placeholder, largest first, until the object fits under max_size.  Returns false
+// if the object is still too big after that (e.g., because it's a giant list of numbers).
+fn truncate_large_fields(obj: &mut DynamicObject, max_size: usize) -> bool {
+    // This is an estimate, since escaped characters take up more room in the serialized object
+    // than they do in the string itself; we check the real size at the end
+    let mut size = json_size(obj);
+
+    let mut fields = vec![];
+    collect_strings(&mut obj.data, &mut fields);
+    if let Some(annotations) = obj.metadata.annotations.as_mut() {
+        fields.extend(annotations.values_mut());
+    }
+    fields.sort_by_key(|s| Reverse(s.len()));
+    for field in fields {
+        if size <= max_size {
+            break;
+        }
+
+        let placeholder = format!("<truncated {} bytes>", field.len());
+        if placeholder.len() >= field.len() {
+            break;
+        }
+        size -= field.len() - placeholder.len();
+        *field = placeholder;
+    }
+    json_size(obj) <= max_size
+}
+
+fn collect_strings<'a>(v: &'a mut serde_json::Value, out: &mut Vec<&'a mut String>) {
+    match v {
+        serde_json::Value::String(s) => out.push(s),
+        serde_json::Value::Array(vals) => {
+            for v in vals {
+                collect_strings(v, out);
+            }
+        },
+        serde_json::Value::Object(map) => {
+            for v in map.values_mut() {
+                collect_strings(v, out);
+            }
+        },
+        _ => (),
+    }
+}
+
+// If an object has both a name and a UID, this is what it would have been keyed as if we'd seen it
+// before it had a name
+fn pending_ns_name(obj: &DynamicObject) -> Option<String> {
+    obj.metadata.name.as_ref()?;
+    let uid = obj.metadata.uid.as_ref()?;
+    Some(match obj.namespace() {
+        Some(ns) => format!("{ns}/{uid}"),
+        None => uid.clone(),
+    })
+}
+
 #[cfg(test)]
 mod test {
     use std::collections::HashSet;
@@ -0,0 +1,133 @@
+use kube::api::DynamicObject;
+use regex::Regex;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::Value;
+use sk_core::errors::*;
+use sk_core::jsonutils;
+use sk_core::k8s::KubeResourceExt;
+use sk_core::prelude::*;
+
+// A rule for normalizing some part of a tracked object before it's stored, so that traces don't
+// pick up things that are specific to the cluster they were recorded in (or that change all the
+// time for no interesting reason).  The path uses the same `*` extension to JSON pointers as
+// podSpecTemplatePath, and is relative to the whole object, so `/metadata/annotations/...` works;
+// remember that a `/` in an annotation or label key has to be written as `~1`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldTransform {
+    pub path: String,
+
+    #[serde(flatten)]
+    pub op: TransformOp,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+pub enum TransformOp {
+    // Remove the field at the path entirely
+    Strip,
+
+    // Replace everything that matches the pattern in the (string) field at the path; the
+    // replacement can refer to capture groups with $1, $name, etc.
+    Rewrite { pattern: String, replacement: String },
+}
+
+enum CompiledOp {
+    Strip,
+    Rewrite(Regex, String),
+}
+
+// The transforms for a single tracked kind, with all of the regexes compiled up front, since these
+// get applied to every object we see
+#[derive(Default)]
+pub(crate) struct ObjectTransformer {
+    rules: Vec<(String, CompiledOp)>,
+}
+
+impl ObjectTransformer {
+    pub(crate) fn new(transforms: &[FieldTransform]) -> anyhow::Result<ObjectTransformer> {
+        let mut rules = vec![];
+        for t in transforms {
+            let path = &t.path;
+            if !path.starts_with('/') {
+                return Err(SimKubeError::config(format!("transform path {path} must start with /")));
+            }
+            if path.split('/').any(|segment| segment.contains('*') && segment != "*") {
+                return Err(SimKubeError::config(format!("transform path {path} has a partial * wildcard")));
+            }
+
+            let op = match &t.op {
+                TransformOp::Strip => {
+                    if path.ends_with('/') || path.ends_with('*') {
+                        return Err(SimKubeError::config(format!(
+                            "cannot strip {path}: path must end in a field name"
+                        )));
+                    }
+                    CompiledOp::Strip
+                },
+                TransformOp::Rewrite { pattern, replacement } => {
+                    let re = Regex::new(pattern).map_err(|err| {
+                        SimKubeError::config(format!("invalid rewrite pattern {pattern} for {path}: {err}"))
+                    })?;
+                    CompiledOp::Rewrite(re, replacement.clone())
+                },
+            };
+            rules.push((path.clone(), op));
+        }
+        Ok(ObjectTransformer { rules })
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    // Rules that don't match anything in the object (e.g., because it doesn't have the annotation
+    // that we're supposed to strip) are skipped
+    pub(crate) fn apply(&self, obj: DynamicObject) -> DynamicObject {
+        if self.is_empty() {
+            return obj;
+        }
+
+        let ns_name = obj.namespaced_name();
+        let mut value = match serde_json::to_value(&obj) {
+            Ok(value) => value,
+            Err(err) => {
+                warn!("could not apply transforms to {ns_name}: {err}");
+                return obj;
+            },
+        };
+
+        for (path, op) in &self.rules {
+            match op {
+                CompiledOp::Strip => {
+                    // We checked that there's a / in the path when we compiled the rule
+                    let (parent, key) = path.rsplit_once('/').unwrap();
+                    let key = key.replace("~1", "/").replace("~0", "~");
+                    for v in jsonutils::patch_ext::matches_mut(parent, &mut value) {
+                        if let Some(map) = v.as_object_mut() {
+                            map.remove(&key);
+                        }
+                    }
+                },
+                CompiledOp::Rewrite(re, replacement) => {
+                    for v in jsonutils::patch_ext::matches_mut(path, &mut value) {
+                        if let Some(s) = v.as_str() {
+                            *v = Value::String(re.replace_all(s, replacement.as_str()).into_owned());
+                        }
+                    }
+                },
+            }
+        }
+
+        match serde_json::from_value(value) {
+            Ok(new_obj) => new_obj,
+            Err(err) => {
+                warn!("transforms left {ns_name} in an invalid state, recording it unchanged: {err}");
+                obj
+            },
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use std::collections::HashMap;
+use std::collections::{
+    HashMap,
+    HashSet,
+};
 use std::pin::Pin;
 use std::sync::mpsc::{
     Receiver,
@@ -25,18 +28,36 @@ use futures::{
 };
 use kube::api::DynamicObject;
 use kube::runtime::watcher::{
+    self,
     watcher,
     Event,
 };
 use kube::runtime::WatchStreamExt;
+use kube::ResourceExt;
+use serde_json::Value;
 use sk_core::errors::*;
 use sk_core::k8s::{
     sanitize_obj,
     ApiSet,
+    CronJobState,
+    HpaState,
+    JobState,
+    KubeResourceExt,
+    NodeState,
     GVK,
 };
 use sk_core::prelude::*;
 
+use super::heartbeat::{
+    heartbeat_tick,
+    Heartbeat,
+};
+use super::network_policy::{
+    network_policy_gvk,
+    pod_gvk,
+    NetworkPolicyResolver,
+};
+use crate::transforms::ObjectTransformer;
 use crate::{
     TraceStorable,
     TraceStore,
@@ -44,6 +65,7 @@ use crate::{
 };
 
 pub type KubeObjectStream = Pin<Box<dyn Stream<Item = anyhow::Result<Event<DynamicObject>>> + Send>>;
+type KubeObjectStreamBuilder = Box<dyn Fn() -> KubeObjectStream + Send>;
 
 // Watch a (customizable) list of objects.  Since we don't know what these object types will be at
 // runtime, we have to use the DynamicObject API, which gives us everything in JSON format that we
@@ -55,29 +77,82 @@ pub struct DynObjWatcher {
     obj_stream: SelectAll<KubeObjectStream>,
     store: Arc<Mutex<dyn TraceStorable + Send>>,
 
+    // We hang on to the builders for each of the streams so we can re-establish them when the
+    // heartbeat interval passes (if there is one)
+    stream_builders: Vec<KubeObjectStreamBuilder>,
+    heartbeat: Option<Heartbeat>,
+
+    // We should only ever get objects back from the apiserver for the kinds that we asked for, but
+    // discovery mismatches can cause us to see other things; these get dropped (and counted)
+    // instead of being recorded in the trace.
+    tracked_gvks: HashSet<GVK>,
+    dropped_counts: HashMap<String, usize>,
+
+    // Only set if NetworkPolicies are tracked; see network_policy.rs
+    network_policies: Option<NetworkPolicyResolver>,
+
+    // Only kinds that actually have transforms configured are in here
+    transformers: HashMap<GVK, ObjectTransformer>,
+
     is_ready: bool,
     ready_tx: Sender<bool>,
 }
 
 impl DynObjWatcher {
+    // If a namespace is given, namespaced objects are only watched in that namespace; cluster-scoped
+    // objects are still watched as usual.
     pub async fn new(
         store: Arc<Mutex<TraceStore>>,
         apiset: &mut ApiSet,
         tracked_objects: &HashMap<GVK, TrackedObjectConfig>,
+        namespace: Option<&str>,
     ) -> anyhow::Result<(DynObjWatcher, Receiver<bool>)> {
-        let mut apis = vec![];
-        for gvk in tracked_objects.keys() {
-            let stream = build_stream_for_tracked_obj(apiset, gvk).await?;
-            apis.push(stream);
+        let mut stream_builders = vec![];
+        for (gvk, config) in tracked_objects {
+            stream_builders.push(build_stream_for_tracked_obj(apiset, gvk, config, namespace).await?);
+        }
+
+        // If we're tracking NetworkPolicies we need to see the pods too, so we can tell which ones
+        // each policy applies to; these don't get recorded unless pods are also tracked.
+        let network_policies = tracked_objects
+            .contains_key(&network_policy_gvk())
+            .then(NetworkPolicyResolver::default);
+        if network_policies.is_some() && !tracked_objects.contains_key(&pod_gvk()) {
+            stream_builders
+                .push(build_stream_for_tracked_obj(apiset, &pod_gvk(), &Default::default(), namespace).await?);
         }
 
+        // Objects are recorded at the version they were read at (see build_stream_for_tracked_obj),
+        // so that's the version we need to look for when deciding what to keep and how to transform it
+        let read_gvk = |gvk: &GVK| {
+            let resolved = apiset.resolved_gvk(gvk).unwrap_or(gvk);
+            if resolved != gvk {
+                warn!("{gvk} is read (and recorded) as {resolved}; list it under that version in trackedObjects");
+            }
+            resolved.clone()
+        };
+        let tracked_gvks = tracked_objects.keys().map(read_gvk).collect();
+        let transformers = build_transformers(tracked_objects)?
+            .into_iter()
+            .map(|(gvk, transformer)| (apiset.resolved_gvk(&gvk).cloned().unwrap_or(gvk), transformer))
+            .collect();
+
         let (tx, rx): (Sender<bool>, Receiver<bool>) = mpsc::channel();
+        let clock = UtcClock::boxed();
+        let heartbeat_interval = store.lock().unwrap().config().heartbeat_interval_seconds;
 
         Ok((
             DynObjWatcher {
-                clock: UtcClock::boxed(),
-                obj_stream: select_all(apis),
+                obj_stream: select_all(stream_builders.iter().map(|build| build())),
                 store,
+                stream_builders,
+                heartbeat: heartbeat_interval.map(|i| Heartbeat::new(i, clock.now_ts())),
+                clock,
+
+                tracked_gvks,
+                dropped_counts: HashMap::new(),
+                network_policies,
+                transformers,
 
                 is_ready: false,
                 ready_tx: tx,
@@ -87,19 +162,49 @@ impl DynObjWatcher {
     }
 
     pub async fn start(mut self) {
-        while let Some(res) = self.obj_stream.next().await {
-            let ts = self.clock.now_ts();
+        loop {
+            let now = self.clock.now_ts();
+            tokio::select! {
+                maybe_res = self.obj_stream.next() => {
+                    let Some(res) = maybe_res else { break };
+                    let ts = self.clock.now_ts();
 
-            match res {
-                Ok(evt) => self.handle_obj_event(evt, ts),
-                Err(err) => {
-                    skerr!(err, "watcher received error on stream");
+                    match res {
+                        Ok(evt) => self.handle_obj_event(evt, ts),
+                        Err(err) => {
+                            skerr!(err, "watcher received error on stream");
+                        },
+                    }
                 },
+                _ = heartbeat_tick(self.heartbeat.as_ref(), now) => (),
             }
+            self.check_heartbeat();
+        }
+    }
+
+    pub(crate) fn check_heartbeat(&mut self) {
+        let now = self.clock.now_ts();
+        if self.heartbeat.as_mut().is_some_and(|hb| hb.due(now)) {
+            info!("heartbeat interval passed, re-establishing object watch streams");
+            self.obj_stream = select_all(self.stream_builders.iter().map(|build| build()));
         }
     }
 
-    fn handle_obj_event(&mut self, evt: Event<DynamicObject>, ts: i64) {
+    pub(crate) fn handle_obj_event(&mut self, evt: Event<DynamicObject>, ts: i64) {
+        let Some(evt) = self.resolve_network_policies(evt, ts) else {
+            return;
+        };
+
+        let evt = match evt {
+            Event::Applied(obj) | Event::Deleted(obj) if !self.is_tracked(&obj) => return,
+            Event::Applied(obj) => Event::Applied(self.prepare(obj)),
+            Event::Restarted(objs) => {
+                let objs: Vec<_> = objs.into_iter().filter(|obj| self.is_tracked(obj)).collect();
+                Event::Restarted(objs.into_iter().map(|obj| self.prepare(obj)).collect())
+            },
+            evt => evt,
+        };
+
         // We don't expect the trace store to panic, but if it does we should panic here too
         let mut store = self.store.lock().unwrap();
         match evt {
@@ -125,22 +230,166 @@ impl DynObjWatcher {
     }
 }
 
-async fn build_stream_for_tracked_obj(apiset: &mut ApiSet, gvk: &GVK) -> anyhow::Result<KubeObjectStream> {
+// An HPA's replica counts and current metric values live in its status, which gets thrown away when
+// the object is replayed; so we stash a copy of the autoscaler's state (as of when we saw it) in an
+// annotation, where it'll survive the replay and can be used to seed the simulated autoscaler.  The
+// same goes for Jobs (how many pods have completed, and how long it took) and CronJobs (the
+// schedule and when it last fired); the driver uses the Job state to make the simulated Job's pods
+// finish when they should.  Nodes get the same treatment (allocatable capacity and conditions), so
+// that we know what the cluster had room for, and when a node went NotReady.
+fn with_status_state(mut obj: DynamicObject) -> DynamicObject {
+    if let Err(err) = record_status_state(&mut obj) {
+        warn!("could not record status for {}: {err}", obj.namespaced_name());
+    }
+    obj
+}
+
+fn record_status_state(obj: &mut DynamicObject) -> EmptyResult {
+    if let Some(state) = HpaState::from_dynamic_obj(obj)? {
+        let state = serde_json::to_string(&state)?;
+        obj.annotations_mut().insert(HPA_STATE_ANNOTATION_KEY.into(), state);
+    } else if let Some(state) = JobState::from_dynamic_obj(obj)? {
+        let state = serde_json::to_string(&state)?;
+        obj.annotations_mut().insert(JOB_STATE_ANNOTATION_KEY.into(), state);
+    } else if let Some(state) = CronJobState::from_dynamic_obj(obj)? {
+        let state = serde_json::to_string(&state)?;
+        obj.annotations_mut().insert(CRONJOB_STATE_ANNOTATION_KEY.into(), state);
+    } else if let Some(state) = NodeState::from_dynamic_obj(obj)? {
+        let state = serde_json::to_string(&state)?;
+        obj.annotations_mut().insert(NODE_STATE_ANNOTATION_KEY.into(), state);
+    }
+    Ok(())
+}
+
+// A ResourceQuota's status has both the hard limits and how much of them is currently in use; the
+// usage is a function of whatever else was running in the namespace, which the simulation is going
+// to recreate anyways, so we throw it away and just keep the hard limits.  (The usage also changes
+// every time a pod comes or goes, but since that doesn't touch the spec it wouldn't get recorded.)
+fn without_quota_usage(mut obj: DynamicObject) -> DynamicObject {
+    if obj.types.as_ref().is_some_and(|t| t.kind == "ResourceQuota") {
+        if let Some(status) = obj.data.get_mut("status").and_then(Value::as_object_mut) {
+            status.remove("used");
+        }
+    }
+    obj
+}
+
+// The config has already been validated by the time we get here, so this shouldn't actually fail
+fn build_transformers(
+    tracked_objects: &HashMap<GVK, TrackedObjectConfig>,
+) -> anyhow::Result<HashMap<GVK, ObjectTransformer>> {
+    let mut transformers = HashMap::new();
+    for (gvk, config) in tracked_objects {
+        let transformer = ObjectTransformer::new(&config.transforms)?;
+        if !transformer.is_empty() {
+            transformers.insert(gvk.clone(), transformer);
+        }
+    }
+    Ok(transformers)
+}
+
+pub(crate) fn build_watcher_config(config: &TrackedObjectConfig) -> watcher::Config {
+    let mut watcher_config = watcher::Config::default();
+    if let Some(labels) = &config.label_selector {
+        watcher_config = watcher_config.labels(labels);
+    }
+    if let Some(fields) = &config.field_selector {
+        watcher_config = watcher_config.fields(fields);
+    }
+    watcher_config
+}
+
+impl DynObjWatcher {
+    // Pod events go to the resolver (and are only passed on if pods are tracked too), and policy
+    // events are held back until we've seen the full list of pods; once that shows up, anything
+    // that was held back gets recorded, with the timestamp it originally came in at.
+    fn resolve_network_policies(&mut self, evt: Event<DynamicObject>, ts: i64) -> Option<Event<DynamicObject>> {
+        let Some(resolver) = self.network_policies.as_mut() else {
+            return Some(evt);
+        };
+        if is_pod_event(&evt) {
+            resolver.handle_pod_event(&evt);
+            for (pending_evt, pending_ts) in resolver.take_ready_events() {
+                self.handle_obj_event(pending_evt, pending_ts);
+            }
+            return self.tracked_gvks.contains(&pod_gvk()).then_some(evt);
+        }
+        resolver.hold_until_listed(evt, ts)
+    }
+
+    fn prepare(&self, obj: DynamicObject) -> DynamicObject {
+        self.with_affected_pods(with_status_state(without_quota_usage(self.transform(obj))))
+    }
+
+    fn with_affected_pods(&self, obj: DynamicObject) -> DynamicObject {
+        match &self.network_policies {
+            Some(resolver) => resolver.with_affected_pods(obj),
+            None => obj,
+        }
+    }
+
+    // This happens before anything else looks at the object, so that (e.g.) the recorded status
+    // state reflects the transformed object
+    fn transform(&self, obj: DynamicObject) -> DynamicObject {
+        match GVK::from_dynamic_obj(&obj).ok().and_then(|gvk| self.transformers.get(&gvk)) {
+            Some(transformer) => transformer.apply(obj),
+            None => obj,
+        }
+    }
+
+    fn is_tracked(&mut self, obj: &DynamicObject) -> bool {
+        let kind = match GVK::from_dynamic_obj(obj) {
+            Ok(gvk) if self.tracked_gvks.contains(&gvk) => return true,
+            Ok(gvk) => gvk.to_string(),
+            Err(_) => "<unknown>".into(),
+        };
+
+        let count = self.dropped_counts.entry(kind.clone()).or_default();
+        *count += 1;
+        debug!("dropping {} of untracked kind {kind} ({count} dropped so far)", obj.namespaced_name());
+        false
+    }
+}
+
+fn is_pod_event(evt: &Event<DynamicObject>) -> bool {
+    let is_pod = |obj: &DynamicObject| GVK::from_dynamic_obj(obj).is_ok_and(|gvk| gvk == pod_gvk());
+    match evt {
+        Event::Applied(obj) | Event::Deleted(obj) => is_pod(obj),
+        Event::Restarted(objs) => objs.first().is_some_and(is_pod),
+    }
+}
+
+async fn build_stream_for_tracked_obj(
+    apiset: &mut ApiSet,
+    gvk: &GVK,
+    config: &TrackedObjectConfig,
+    namespace: Option<&str>,
+) -> anyhow::Result<KubeObjectStreamBuilder> {
     // TODO if this fails (e.g., because some custom resource isn't present in the cluster)
     // it will prevent the tracer from starting up
-    let api_version = gvk.api_version().clone();
-    let kind = gvk.kind.clone();
-
+    //
     // The "unnamespaced" api variant can list/watch in all namespaces
-    let (api, _) = apiset.unnamespaced_api_by_gvk(gvk).await?;
+    let (api, _) = match namespace {
+        Some(ns) => apiset.namespaced_api_by_gvk(gvk, ns).await?,
+        None => apiset.unnamespaced_api_by_gvk(gvk).await?,
+    };
+    let (api, watcher_config) = (api.clone(), build_watcher_config(config));
 
-    Ok(watcher(api.clone(), Default::default())
+    // With version preferences, the api might be for a different version than the one we asked
+    // for, and the objects need to be labelled with the version they were actually read at
+    let read_gvk = apiset.resolved_gvk(gvk).unwrap_or(gvk);
+    let api_version = read_gvk.api_version().clone();
+    let kind = read_gvk.kind.clone();
+    Ok(Box::new(move || {
         // All these objects need to be cloned because they're moved into the stream here
-        .modify(move |obj| {
-            sanitize_obj(obj, &api_version, &kind);
-        })
-        .map_err(|e| e.into())
-        .boxed())
+        let (api_version, kind) = (api_version.clone(), kind.clone());
+        watcher(api.clone(), watcher_config.clone())
+            .modify(move |obj| {
+                sanitize_obj(obj, &api_version, &kind);
+            })
+            .map_err(|e| e.into())
+            .boxed()
+    }))
 }
 
 #[cfg(test)]
@@ -148,15 +397,35 @@ impl DynObjWatcher {
     pub fn new_from_parts(
         objs: KubeObjectStream,
         store: Arc<Mutex<TraceStore>>,
+        tracked_gvks: HashSet<GVK>,
         clock: Box<dyn Clockable + Send>,
     ) -> DynObjWatcher {
         let (tx, _): (Sender<bool>, Receiver<bool>) = mpsc::channel();
+        let network_policies = tracked_gvks
+            .contains(&network_policy_gvk())
+            .then(NetworkPolicyResolver::default);
+        let transformers = build_transformers(&store.lock().unwrap().config().tracked_objects).unwrap();
         DynObjWatcher {
             obj_stream: select_all(vec![objs]),
             store,
+            stream_builders: vec![],
+            heartbeat: None,
             clock,
+            tracked_gvks,
+            dropped_counts: HashMap::new(),
+            network_policies,
+            transformers,
             is_ready: true,
             ready_tx: tx,
         }
     }
+
+    pub(crate) fn set_heartbeat(&mut self, interval: i64, stream_builder: KubeObjectStreamBuilder) {
+        self.heartbeat = Some(Heartbeat::new(interval, self.clock.now_ts()));
+        self.stream_builders = vec![stream_builder];
+    }
+
+    pub(crate) fn dropped_count(&self, kind: &str) -> usize {
+        self.dropped_counts.get(kind).copied().unwrap_or_default()
+    }
 }
@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+use tokio::time::sleep;
+
+// Some proxies (and load balancers) silently drop watch connections that have been idle for too
+// long, and when that happens we just stop getting events without ever finding out about it.  If a
+// heartbeat interval is configured, the watchers tear down and re-establish their watch streams
+// whenever the interval has passed since the last refresh, whether or not anything has come in on
+// them in the meantime.  Re-establishing a watch starts with a fresh list, which shows up as a
+// Restarted event, the same as if the watch had been restarted after an error.
+#[derive(Clone, Debug)]
+pub(crate) struct Heartbeat {
+    interval: i64,
+    last_refresh_ts: i64,
+}
+
+impl Heartbeat {
+    pub(crate) fn new(interval: i64, now: i64) -> Heartbeat {
+        Heartbeat { interval, last_refresh_ts: now }
+    }
+
+    // Returns true (and resets the timer) if it's time to refresh the watch
+    pub(crate) fn due(&mut self, now: i64) -> bool {
+        if now - self.last_refresh_ts < self.interval {
+            return false;
+        }
+        self.last_refresh_ts = now;
+        true
+    }
+}
+
+// Resolves once the heartbeat interval has passed, or never, if there's no heartbeat configured.
+// The watchers still check `due` (using their own clocks) before they actually refresh anything.
+pub(crate) async fn heartbeat_tick(heartbeat: Option<&Heartbeat>, now: i64) {
+    match heartbeat {
+        Some(hb) => sleep(Duration::from_secs((hb.last_refresh_ts + hb.interval - now).max(0) as u64)).await,
+        None => futures::future::pending().await,
+    }
+}
@@ -1,5 +1,8 @@
 mod dyn_obj_watcher;
+mod heartbeat;
+mod network_policy;
 mod pod_watcher;
+mod store_channel;
 
 pub use self::dyn_obj_watcher::{
     DynObjWatcher,
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use kube::api::DynamicObject;
+use kube::runtime::watcher::Event;
+use kube::ResourceExt;
+use sk_core::errors::*;
+use sk_core::k8s::{
+    KubeResourceExt,
+    GVK,
+};
+use sk_core::prelude::*;
+
+pub(crate) fn network_policy_gvk() -> GVK {
+    GVK::new("networking.k8s.io", "v1", "NetworkPolicy")
+}
+
+pub(crate) fn pod_gvk() -> GVK {
+    GVK::new("", "v1", "Pod")
+}
+
+// NetworkPolicies pick the pods they apply to with a label selector, which doesn't tell you much
+// when you're looking at a trace later on; so if NetworkPolicies are tracked, the DynObjWatcher
+// also watches pods, and stashes the list of pods that each policy selected (as of when we saw the
+// policy) in an annotation on the policy.  Policies only ever select pods in their own namespace,
+// and an empty podSelector selects all of them.
+//
+// We can't tell which pods a policy selects until we've listed all the pods, so any policy events
+// that come in before that are held on to (along with the time we got them) until the pod list
+// comes in.  There can be a lot of pods, so we just keep around their names and labels, which is
+// all we need to match them against the policies.
+#[derive(Default)]
+pub(crate) struct NetworkPolicyResolver {
+    pods: HashMap<String, corev1::Pod>,
+    pods_listed: bool,
+    pending_events: Vec<(Event<DynamicObject>, i64)>,
+}
+
+impl NetworkPolicyResolver {
+    pub(crate) fn handle_pod_event(&mut self, evt: &Event<DynamicObject>) {
+        match evt {
+            Event::Applied(pod) => {
+                self.pods.insert(pod.namespaced_name(), pod_labels_only(pod));
+            },
+            Event::Deleted(pod) => {
+                self.pods.remove(&pod.namespaced_name());
+            },
+            Event::Restarted(pods) => {
+                self.pods = pods.iter().map(|pod| (pod.namespaced_name(), pod_labels_only(pod))).collect();
+                self.pods_listed = true;
+            },
+        }
+    }
+
+    // Returns the event back if it's OK to record it now, or None if we're holding on to it until
+    // the pods have been listed
+    pub(crate) fn hold_until_listed(&mut self, evt: Event<DynamicObject>, ts: i64) -> Option<Event<DynamicObject>> {
+        let has_policy = match &evt {
+            Event::Applied(obj) | Event::Deleted(obj) => is_network_policy(obj),
+            Event::Restarted(objs) => objs.iter().any(is_network_policy),
+        };
+        if self.pods_listed || !has_policy {
+            return Some(evt);
+        }
+        self.pending_events.push((evt, ts));
+        None
+    }
+
+    pub(crate) fn take_ready_events(&mut self) -> Vec<(Event<DynamicObject>, i64)> {
+        if !self.pods_listed {
+            return vec![];
+        }
+        std::mem::take(&mut self.pending_events)
+    }
+
+    pub(crate) fn with_affected_pods(&self, mut obj: DynamicObject) -> DynamicObject {
+        if is_network_policy(&obj) {
+            if let Err(err) = self.record_affected_pods(&mut obj) {
+                warn!("could not record affected pods for {}: {err}", obj.namespaced_name());
+            }
+        }
+        obj
+    }
+
+    fn record_affected_pods(&self, obj: &mut DynamicObject) -> EmptyResult {
+        let pods = serde_json::to_string(&self.affected_pods(obj)?)?;
+        obj.annotations_mut().insert(AFFECTED_PODS_ANNOTATION_KEY.into(), pods);
+        Ok(())
+    }
+
+    pub(crate) fn affected_pods(&self, policy: &DynamicObject) -> anyhow::Result<Vec<String>> {
+        let sel: metav1::LabelSelector = match policy.data.get("spec").and_then(|spec| spec.get("podSelector")) {
+            Some(sel) => serde_json::from_value(sel.clone())?,
+            None => Default::default(),
+        };
+        let ns = policy.namespace().unwrap_or_default();
+
+        let mut pods = vec![];
+        for (ns_name, pod) in &self.pods {
+            if pod.namespace().as_ref() == Some(&ns) && pod.matches(&sel)? {
+                pods.push(ns_name.clone());
+            }
+        }
+        pods.sort();
+        Ok(pods)
+    }
+}
+
+fn is_network_policy(obj: &DynamicObject) -> bool {
+    GVK::from_dynamic_obj(obj).is_ok_and(|gvk| gvk == network_policy_gvk())
+}
+
+fn pod_labels_only(pod: &DynamicObject) -> corev1::Pod {
+    corev1::Pod {
+        metadata: metav1::ObjectMeta {
+            namespace: pod.namespace(),
+            name: pod.metadata.name.clone(),
+            labels: pod.metadata.labels.clone(),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
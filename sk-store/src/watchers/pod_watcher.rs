@@ -1,5 +1,8 @@
 use std::borrow::Borrow;
-use std::collections::HashMap;
+use std::collections::{
+    HashMap,
+    HashSet,
+};
 use std::mem::take;
 use std::pin::Pin;
 use std::sync::mpsc::{
@@ -25,21 +28,34 @@ use kube::runtime::watcher::{
     watcher,
     Event,
 };
+use regex::Regex;
 use sk_core::errors::*;
 use sk_core::k8s::{
     ApiSet,
     KubeResourceExt,
+    OwnerChainCache,
     OwnersCache,
+    PodExt,
     PodLifecycleData,
+    PodReadiness,
+    TtlOwnerChainCache,
 };
 use sk_core::prelude::*;
 
+use super::heartbeat::{
+    heartbeat_tick,
+    Heartbeat,
+};
+use super::store_channel::StoreHandle;
 use crate::{
+    OwnersCachePolicy,
     TraceStorable,
     TraceStore,
+    TracerConfig,
 };
 
 pub type PodStream = Pin<Box<dyn Stream<Item = anyhow::Result<Event<corev1::Pod>>> + Send>>;
+type PodStreamBuilder = Box<dyn Fn() -> PodStream + Send>;
 
 // The PodWatcher object monitors incoming pod events and records the relevant ones to the object
 // store; becaues in clusters of any reasonable size, there are a) a lot of pods, and b) a lot of
@@ -49,18 +65,30 @@ pub type PodStream = Pin<Box<dyn Stream<Item = anyhow::Result<Event<corev1::Pod>
 //
 // At a high level: whenever a pod event happens, we check to see whether any properties of its
 // lifecycle data (currently start time and end time) have changed.  If so, we compute the
-// ownership chain for the pod, and forward that info on to the store.
+// ownership chain for the pod, and forward that info on to the store.  Separately, we record the
+// first time that each pod becomes Ready, so that readiness delays can be reproduced later.  If
+// captureContainerEnv is set, we also record each pod's container env vars (with anything sensitive
+// masked out) the first time we store data for it.
 
 pub struct PodWatcher {
     pod_stream: PodStream,
 
+    // Used to re-establish the watch stream when the heartbeat interval passes (if there is one)
+    pod_stream_builder: Option<PodStreamBuilder>,
+    heartbeat: Option<Heartbeat>,
+
     // We store the list of owned pods in memory here, and cache the ownership chain for each pod;
     // This is a simpler data structure than what the object store needs, to allow for easy lookup
     // by pod name.  (The object store needs to store a bunch of extra metadata about sequence
     // number and pod hash and so forth).
     owned_pods: HashMap<String, PodLifecycleData>,
+    ready_pods: HashSet<String>,
     owners_cache: OwnersCache,
-    store: Arc<Mutex<dyn TraceStorable + Send>>,
+    owner_lookup_concurrency: usize,
+    store: StoreHandle,
+
+    // The pattern for sensitive env var names, if we're capturing container env at all
+    env_capture: Option<Regex>,
 
     clock: Box<dyn Clockable + Send>,
     is_ready: bool,
@@ -74,20 +102,52 @@ impl PodWatcher {
     // DynamicObject watcher just needs to construct the relevant api clients once, when it creates
     // the watch streams, so it can yield when it's done.  If at some point in the future this
     // becomes problematic, we can always stick the apiset in an Arc<Mutex<_>>.
-    pub fn new(client: kube::Client, store: Arc<Mutex<TraceStore>>, apiset: ApiSet) -> (PodWatcher, Receiver<bool>) {
-        let pod_api: kube::Api<corev1::Pod> = kube::Api::all(client);
-        let pod_stream = watcher(pod_api, Default::default()).map_err(|e| e.into()).boxed();
+    //
+    // If a namespace is given, we only watch pods in that namespace (and only look up their owners
+    // there), which means we just need namespace-scoped permissions.  If the store's config has a
+    // storeChannelCapacity, this also spawns the task that writes our updates to the store, so it
+    // has to be called from inside a tokio runtime.
+    pub fn new(
+        client: kube::Client,
+        store: Arc<Mutex<TraceStore>>,
+        apiset: ApiSet,
+        namespace: Option<&str>,
+    ) -> (PodWatcher, Receiver<bool>) {
+        let (pod_api, owners_cache): (kube::Api<corev1::Pod>, _) = match namespace {
+            Some(ns) => (kube::Api::namespaced(client, ns), OwnersCache::new_namespaced(apiset, ns)),
+            None => (kube::Api::all(client), OwnersCache::new(apiset)),
+        };
+        let pod_stream_builder: PodStreamBuilder =
+            Box::new(move || watcher(pod_api.clone(), Default::default()).map_err(|e| e.into()).boxed());
         let (tx, rx): (Sender<bool>, Receiver<bool>) = mpsc::channel();
+        let config = store.lock().unwrap().config().clone();
+        let clock = UtcClock::boxed();
+        let owners_cache = owners_cache.with_cache(owner_chain_cache(&config));
+
+        // The config has already been validated by this point, so the pattern should always compile;
+        // if it somehow doesn't, we'd rather not capture anything than risk leaking secrets
+        let env_capture = match config.sensitive_env_regex() {
+            Ok(re) => config.capture_container_env.then_some(re),
+            Err(err) => {
+                skerr!(err, "could not compile sensitive env pattern, not capturing container env");
+                None
+            },
+        };
 
         (
             PodWatcher {
-                pod_stream,
+                pod_stream: pod_stream_builder(),
+                pod_stream_builder: Some(pod_stream_builder),
+                heartbeat: config.heartbeat_interval_seconds.map(|i| Heartbeat::new(i, clock.now_ts())),
 
                 owned_pods: HashMap::new(),
-                owners_cache: OwnersCache::new(apiset),
-                store,
+                ready_pods: HashSet::new(),
+                owners_cache,
+                owner_lookup_concurrency: config.owner_lookup_concurrency(),
+                store: StoreHandle::new(store, &config),
+                env_capture,
 
-                clock: UtcClock::boxed(),
+                clock,
                 is_ready: false,
                 ready_tx: tx,
             },
@@ -97,13 +157,30 @@ impl PodWatcher {
 
     // This is not a reference because it needs to "own" itself when tokio spawns it
     pub async fn start(mut self) {
-        while let Some(res) = self.pod_stream.next().await {
-            match res {
-                Ok(mut evt) => self.handle_pod_event(&mut evt).await,
-                Err(err) => {
-                    skerr!(err, "pod watcher received error on stream");
+        loop {
+            let now = self.clock.now_ts();
+            tokio::select! {
+                maybe_res = self.pod_stream.next() => {
+                    let Some(res) = maybe_res else { break };
+                    match res {
+                        Ok(mut evt) => self.handle_pod_event(&mut evt).await,
+                        Err(err) => {
+                            skerr!(err, "pod watcher received error on stream");
+                        },
+                    }
                 },
+                _ = heartbeat_tick(self.heartbeat.as_ref(), now) => (),
             }
+            self.check_heartbeat();
+        }
+    }
+
+    pub(crate) fn check_heartbeat(&mut self) {
+        let now = self.clock.now_ts();
+        let Some(build) = &self.pod_stream_builder else { return };
+        if self.heartbeat.as_mut().is_some_and(|hb| hb.due(now)) {
+            info!("heartbeat interval passed, re-establishing pod watch stream");
+            self.pod_stream = build();
         }
     }
 
@@ -137,6 +214,7 @@ impl PodWatcher {
                 // left in the old after we're done was deleted in the intervening period.  This
                 // lets us not have to track "object versions" or use a bit vector or something
                 // along those lines.
+                self.prefetch_owner_chains(pods).await;
                 let mut old_owned_pods = take(&mut self.owned_pods);
                 for pod in pods {
                     let ns_name = &pod.namespaced_name();
@@ -162,16 +240,34 @@ impl PodWatcher {
                 // the cluster at startup time.
                 if !self.is_ready {
                     self.is_ready = true;
-
-                    // unlike golang, sending is non-blocking
-                    if let Err(e) = self.ready_tx.send(true) {
-                        error!("failed to update podwatcher ready status: {e:?}")
-                    }
+                    self.store.notify_ready(&self.ready_tx).await;
                 }
             },
         };
     }
 
+    // When the watch (re)starts we get every pod at once, and looking up their owners one at a time
+    // is by far the slowest part of handling that; so we look up the owners for every pod that
+    // we're going to store data for ahead of time, a few at a time, and then the pods get handled
+    // (in order) out of the cache.  If a lookup fails here, it'll be tried again (and the error
+    // reported) when the pod is handled.
+    async fn prefetch_owner_chains(&mut self, pods: &[corev1::Pod]) {
+        let needs_owners: Vec<_> = pods
+            .iter()
+            .filter(|pod| {
+                let ns_name = pod.namespaced_name();
+                PodLifecycleData::new_for(pod).is_ok_and(|data| data > self.owned_pods.get(&ns_name))
+                    && self.owners_cache.lookup(&ns_name).is_none()
+            })
+            .collect();
+        if needs_owners.len() > 1 {
+            debug!("looking up owners for {} pods", needs_owners.len());
+            self.owners_cache
+                .compute_owner_chains(needs_owners, self.owner_lookup_concurrency)
+                .await;
+        }
+    }
+
     async fn handle_pod_applied(&mut self, ns_name: &str, pod: &corev1::Pod) -> EmptyResult {
         let new_lifecycle_data = PodLifecycleData::new_for(pod)?;
         let current_lifecycle_data = self.owned_pods.get(ns_name);
@@ -181,10 +277,20 @@ impl PodWatcher {
         // lifecycle updates if the timestamps match and we've moved from one state to the next.
         //
         // Note that we only store non-empty lifecycle data, which is enforced since
-        // PodLifecycleData::Empty < everything.
+        // PodLifecycleData::Empty < everything.  Pending data _is_ stored, so that pods which never
+        // start running still show up in the trace.
         if new_lifecycle_data > current_lifecycle_data {
+            let first_stored = current_lifecycle_data.is_none();
             self.owned_pods.insert(ns_name.into(), new_lifecycle_data.clone());
             self.store_pod_lifecycle_data(ns_name, Some(pod), &new_lifecycle_data).await?;
+
+            // A pod's env, scheduling constraints, and probes can't change once it's created, so we
+            // only need to record them once
+            if first_stored {
+                self.store_pod_env(ns_name, pod).await?;
+                self.store.record_pod_scheduling(ns_name, pod.scheduling()?).await;
+                self.store.record_pod_probes(ns_name, pod.probes()?).await;
+            }
         } else if !new_lifecycle_data.empty() && new_lifecycle_data != current_lifecycle_data {
             warn!(
                 "new lifecycle data for {} does not match stored data, cowardly refusing to update: {:?} !>= {:?}",
@@ -192,6 +298,36 @@ impl PodWatcher {
             );
         }
 
+        self.handle_pod_ready(ns_name, pod).await;
+        Ok(())
+    }
+
+    // The Ready condition's transition time gets updated every time the pod flips between ready and
+    // not ready, so we only record the first transition that we see.  Pods that never become ready
+    // (or that never start running) just don't have any readiness data stored.
+    async fn handle_pod_ready(&mut self, ns_name: &str, pod: &corev1::Pod) {
+        if self.ready_pods.contains(ns_name) {
+            return;
+        }
+
+        let Some(ready_ts) = pod.ready_ts() else { return };
+        let Some(start_ts) = self.owned_pods.get(ns_name).and_then(|l| l.start_ts()) else {
+            warn!("pod {ns_name} is ready but has no start time, not recording readiness data");
+            return;
+        };
+
+        self.ready_pods.insert(ns_name.into());
+        self.store
+            .record_pod_readiness(ns_name, PodReadiness { start_ts, ready_ts })
+            .await;
+    }
+
+    async fn store_pod_env(&mut self, ns_name: &str, pod: &corev1::Pod) -> EmptyResult {
+        let Some(sensitive_keys) = &self.env_capture else {
+            return Ok(());
+        };
+        let env = pod.container_env(sensitive_keys)?;
+        self.store.record_pod_env(ns_name, env).await;
         Ok(())
     }
 
@@ -206,6 +342,7 @@ impl PodWatcher {
     ) -> EmptyResult {
         // Always remove the pod from our tracker, regardless of what else happens
         self.owned_pods.remove(ns_name);
+        self.ready_pods.remove(ns_name);
 
         // If the current lifecycle data is finished, we know it's already been written to the
         // store so we don't store it a second time.
@@ -216,14 +353,20 @@ impl PodWatcher {
         // TODO: should this logic be somehow combined with the logic in
         // PodLifecycleData::guess_finished_lifecycle?
         let new_lifecycle_data = match maybe_pod {
-            None => {
-                // We never store "empty" data so this unwrap should always succeed
-                let start_ts = current_lifecycle_data.start_ts().unwrap();
-                PodLifecycleData::Finished(start_ts, self.clock.now_ts())
+            None => match current_lifecycle_data.start_ts() {
+                Some(start_ts) => PodLifecycleData::Finished(start_ts, self.clock.now_ts(), None),
+
+                // We never store "empty" data, so if there's no start_ts the pod was still pending
+                // when it was deleted; it never ran, so what we've already stored is final.
+                None => return Ok(()),
             },
             Some(pod) => PodLifecycleData::guess_finished_lifecycle(pod, &current_lifecycle_data, self.clock.borrow())?,
         };
 
+        if new_lifecycle_data == current_lifecycle_data {
+            return Ok(());
+        }
+
         self.store_pod_lifecycle_data(ns_name, maybe_pod, &new_lifecycle_data).await
     }
 
@@ -238,14 +381,25 @@ impl PodWatcher {
         // determined that we want to store the data but _before_ we unlock the object store, since
         // this involves a bunch of API calls, and we don't want to block either thread.
         let owners = match (self.owners_cache.lookup(ns_name), maybe_pod) {
-            (Some(o), _) => o.clone(),
-            (None, Some(pod)) => self.owners_cache.compute_owner_chain(pod).await?,
+            (Some(o), _) => o.all(),
+            (None, Some(pod)) => self.owners_cache.compute_owner_chain(pod).await?.all(),
             _ => bail!("could not determine owner chain for {}", ns_name),
         };
 
-        // We don't expect the trace store to panic, but if it does, we should panic here too
-        let mut store = self.store.lock().unwrap();
-        store.record_pod_lifecycle(ns_name, maybe_pod.cloned(), owners, lifecycle_data)
+        self.store
+            .record_pod_lifecycle(ns_name, maybe_pod.cloned(), owners, lifecycle_data)
+            .await
+    }
+}
+
+// The config has already been validated by this point, so the ttl is always set if the policy needs
+// it
+fn owner_chain_cache(config: &TracerConfig) -> Box<dyn OwnerChainCache> {
+    match config.owners_cache_policy {
+        OwnersCachePolicy::Unbounded => Box::new(HashMap::new()),
+        OwnersCachePolicy::Ttl => {
+            Box::new(TtlOwnerChainCache::new(config.owners_cache_ttl_seconds.unwrap_or_default()))
+        },
     }
 }
 
@@ -262,9 +416,14 @@ impl PodWatcher {
         (
             PodWatcher {
                 pod_stream,
+                pod_stream_builder: None,
+                heartbeat: None,
                 owned_pods,
+                ready_pods: HashSet::new(),
                 owners_cache,
-                store,
+                owner_lookup_concurrency: 1,
+                store: StoreHandle::Direct(store),
+                env_capture: None,
                 clock,
                 is_ready: false,
                 ready_tx: tx,
@@ -273,6 +432,19 @@ impl PodWatcher {
         )
     }
 
+    pub(crate) fn set_heartbeat(&mut self, interval: i64, pod_stream_builder: PodStreamBuilder) {
+        self.heartbeat = Some(Heartbeat::new(interval, self.clock.now_ts()));
+        self.pod_stream_builder = Some(pod_stream_builder);
+    }
+
+    pub(crate) fn set_owner_lookup_concurrency(&mut self, concurrency: usize) {
+        self.owner_lookup_concurrency = concurrency;
+    }
+
+    pub(crate) fn set_env_capture(&mut self, sensitive_keys: Regex) {
+        self.env_capture = Some(sensitive_keys);
+    }
+
     pub(crate) fn get_owned_pod_lifecycle(&self, ns_name: &str) -> Option<&PodLifecycleData> {
         self.owned_pods.get(ns_name)
     }
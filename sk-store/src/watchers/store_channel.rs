@@ -0,0 +1,311 @@
+use std::collections::HashSet;
+use std::sync::{
+    mpsc,
+    Arc,
+    Mutex,
+};
+
+use sk_core::errors::*;
+use sk_core::k8s::{
+    PodEnv,
+    PodLifecycleData,
+    PodProbes,
+    PodReadiness,
+    PodScheduling,
+};
+use sk_core::prelude::*;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::{
+    channel,
+    Receiver,
+    Sender,
+};
+
+use crate::{
+    BackpressurePolicy,
+    TraceStorable,
+    TracerConfig,
+};
+
+// The pod watcher can either write to the store directly, or (if storeChannelCapacity is set)
+// send its updates through a bounded channel to a separate task that does the writing.  The
+// latter means that a slow store can't hold up event processing, but also that the amount of work
+// that can pile up in between is capped; when the channel fills up, the watcher either waits for
+// the store to catch up or drops the update, depending on storeBackpressurePolicy.
+
+#[derive(Debug)]
+pub(crate) enum StoreUpdate {
+    PodLifecycle {
+        ns_name: String,
+        pod: Option<Box<corev1::Pod>>,
+        owners: Vec<metav1::OwnerReference>,
+        lifecycle_data: PodLifecycleData,
+    },
+    PodReadiness {
+        ns_name: String,
+        readiness: PodReadiness,
+    },
+    PodEnv {
+        ns_name: String,
+        env: PodEnv,
+    },
+    PodScheduling {
+        ns_name: String,
+        scheduling: Box<PodScheduling>,
+    },
+    PodProbes {
+        ns_name: String,
+        probes: PodProbes,
+    },
+
+    // The watcher isn't "ready" until everything it's seen so far has actually made it into the
+    // store, so the ready signal goes through the channel too
+    Ready(mpsc::Sender<bool>),
+}
+
+pub(crate) enum StoreHandle {
+    Direct(Arc<Mutex<dyn TraceStorable + Send>>),
+    Channel(StoreSender),
+}
+
+impl StoreHandle {
+    // Must be called from inside a tokio runtime if the config asks for a channel, since the
+    // store writer is spawned as a separate task
+    pub(crate) fn new(store: Arc<Mutex<dyn TraceStorable + Send>>, config: &TracerConfig) -> StoreHandle {
+        match config.store_channel_capacity {
+            Some(capacity) => {
+                let (sender, rx) = StoreSender::new(capacity, config.store_backpressure_policy);
+                tokio::spawn(write_store_updates(rx, store));
+                StoreHandle::Channel(sender)
+            },
+            None => StoreHandle::Direct(store),
+        }
+    }
+
+    pub(crate) async fn record_pod_lifecycle(
+        &mut self,
+        ns_name: &str,
+        pod: Option<corev1::Pod>,
+        owners: Vec<metav1::OwnerReference>,
+        lifecycle_data: &PodLifecycleData,
+    ) -> EmptyResult {
+        match self {
+            // We don't expect the trace store to panic, but if it does, we should panic here too
+            StoreHandle::Direct(store) => {
+                store.lock().unwrap().record_pod_lifecycle(ns_name, pod, owners, lifecycle_data)
+            },
+            StoreHandle::Channel(sender) => {
+                let lifecycle_data = lifecycle_data.clone();
+                sender
+                    .send(StoreUpdate::PodLifecycle {
+                        ns_name: ns_name.into(),
+                        pod: pod.map(Box::new),
+                        owners,
+                        lifecycle_data,
+                    })
+                    .await;
+                Ok(())
+            },
+        }
+    }
+
+    pub(crate) async fn record_pod_readiness(&mut self, ns_name: &str, readiness: PodReadiness) {
+        match self {
+            StoreHandle::Direct(store) => {
+                store.lock().unwrap().record_pod_readiness(ns_name, readiness);
+            },
+            StoreHandle::Channel(sender) => {
+                sender
+                    .send(StoreUpdate::PodReadiness { ns_name: ns_name.into(), readiness })
+                    .await;
+            },
+        }
+    }
+
+    pub(crate) async fn record_pod_env(&mut self, ns_name: &str, env: PodEnv) {
+        match self {
+            StoreHandle::Direct(store) => {
+                store.lock().unwrap().record_pod_env(ns_name, env);
+            },
+            StoreHandle::Channel(sender) => sender.send(StoreUpdate::PodEnv { ns_name: ns_name.into(), env }).await,
+        }
+    }
+
+    pub(crate) async fn record_pod_scheduling(&mut self, ns_name: &str, scheduling: PodScheduling) {
+        match self {
+            StoreHandle::Direct(store) => {
+                store.lock().unwrap().record_pod_scheduling(ns_name, scheduling);
+            },
+            StoreHandle::Channel(sender) => {
+                sender
+                    .send(StoreUpdate::PodScheduling {
+                        ns_name: ns_name.into(),
+                        scheduling: Box::new(scheduling),
+                    })
+                    .await;
+            },
+        }
+    }
+
+    pub(crate) async fn record_pod_probes(&mut self, ns_name: &str, probes: PodProbes) {
+        match self {
+            StoreHandle::Direct(store) => {
+                store.lock().unwrap().record_pod_probes(ns_name, probes);
+            },
+            StoreHandle::Channel(sender) => {
+                sender.send(StoreUpdate::PodProbes { ns_name: ns_name.into(), probes }).await;
+            },
+        }
+    }
+
+    pub(crate) async fn notify_ready(&mut self, ready_tx: &mpsc::Sender<bool>) {
+        match self {
+            StoreHandle::Direct(_) => {
+                // unlike golang, sending is non-blocking
+                if let Err(e) = ready_tx.send(true) {
+                    error!("failed to update podwatcher ready status: {e:?}")
+                }
+            },
+            StoreHandle::Channel(sender) => sender.send(StoreUpdate::Ready(ready_tx.clone())).await,
+        }
+    }
+}
+
+pub(crate) struct StoreSender {
+    tx: Sender<StoreUpdate>,
+    policy: BackpressurePolicy,
+
+    near_full: bool,
+    blocked_count: usize,
+    dropped_count: usize,
+}
+
+impl StoreSender {
+    pub(crate) fn new(capacity: usize, policy: BackpressurePolicy) -> (StoreSender, Receiver<StoreUpdate>) {
+        let (tx, rx) = channel(capacity);
+        (
+            StoreSender {
+                tx,
+                policy,
+                near_full: false,
+                blocked_count: 0,
+                dropped_count: 0,
+            },
+            rx,
+        )
+    }
+
+    pub(crate) async fn send(&mut self, update: StoreUpdate) {
+        let res = match self.tx.try_send(update) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Closed(_)) => Err(()),
+
+            // The ready signal is never dropped, otherwise the watcher might never become ready
+            Err(TrySendError::Full(update @ StoreUpdate::Ready(_))) => self.tx.send(update).await.map_err(|_| ()),
+            Err(TrySendError::Full(update)) => match self.policy {
+                BackpressurePolicy::Block => {
+                    self.blocked_count += 1;
+                    warn!(
+                        "store channel is full, waiting for the store to catch up ({} times so far)",
+                        self.blocked_count
+                    );
+                    self.tx.send(update).await.map_err(|_| ())
+                },
+                BackpressurePolicy::Drop => {
+                    self.dropped_count += 1;
+                    warn!("store channel is full, dropping update ({} dropped so far)", self.dropped_count);
+                    Ok(())
+                },
+            },
+        };
+        if res.is_err() {
+            error!("store writer has exited, could not send update");
+        }
+
+        self.check_fill_level();
+    }
+
+    // Warn when the channel gets to 90% full, and then don't say anything else until it's drained
+    // back down to half full, so we don't spam the logs when it's hovering around the limit
+    fn check_fill_level(&mut self) {
+        let max = self.tx.max_capacity();
+        let used = max - self.tx.capacity();
+        if !self.near_full && used * 10 >= max * 9 {
+            self.near_full = true;
+            warn!("store channel is nearly full ({used}/{max} updates pending)");
+        } else if self.near_full && used * 2 <= max {
+            self.near_full = false;
+            info!("store channel has drained ({used}/{max} updates pending)");
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn blocked_count(&self) -> usize {
+        self.blocked_count
+    }
+
+    #[cfg(test)]
+    pub(crate) fn dropped_count(&self) -> usize {
+        self.dropped_count
+    }
+}
+
+// When the pod watcher writes to the store directly and a pod's lifecycle data can't be stored, it
+// stops handling that pod event, so the pod's env, scheduling constraints, and probes (which are
+// only sent along with its first lifecycle update) never get recorded.  The watcher doesn't find
+// out about failures on this side of the channel, so we keep track of the pods that failed and drop
+// those updates here instead, which keeps the two modes recording the same data.
+pub(crate) async fn write_store_updates(mut rx: Receiver<StoreUpdate>, store: Arc<Mutex<dyn TraceStorable + Send>>) {
+    let mut failed_pods = HashSet::new();
+    let mut failed_count = 0;
+    while let Some(update) = rx.recv().await {
+        match update {
+            StoreUpdate::PodLifecycle { ns_name, pod, owners, lifecycle_data } => {
+                let res =
+                    store
+                        .lock()
+                        .unwrap()
+                        .record_pod_lifecycle(&ns_name, pod.map(|p| *p), owners, &lifecycle_data);
+                match res {
+                    Ok(()) => {
+                        failed_pods.remove(&ns_name);
+                    },
+                    Err(err) => {
+                        failed_count += 1;
+                        skerr!(
+                            err,
+                            "pod {} lifecycle data could not be stored ({} failed so far)",
+                            ns_name,
+                            failed_count
+                        );
+                        failed_pods.insert(ns_name);
+                    },
+                }
+            },
+            StoreUpdate::PodReadiness { ns_name, readiness } => {
+                store.lock().unwrap().record_pod_readiness(&ns_name, readiness);
+            },
+            StoreUpdate::PodEnv { ns_name, .. }
+            | StoreUpdate::PodScheduling { ns_name, .. }
+            | StoreUpdate::PodProbes { ns_name, .. }
+                if failed_pods.contains(&ns_name) =>
+            {
+                warn!("not storing pod {ns_name} data, since its lifecycle data could not be stored");
+            },
+            StoreUpdate::PodEnv { ns_name, env } => {
+                store.lock().unwrap().record_pod_env(&ns_name, env);
+            },
+            StoreUpdate::PodScheduling { ns_name, scheduling } => {
+                store.lock().unwrap().record_pod_scheduling(&ns_name, *scheduling);
+            },
+            StoreUpdate::PodProbes { ns_name, probes } => {
+                store.lock().unwrap().record_pod_probes(&ns_name, probes);
+            },
+            StoreUpdate::Ready(ready_tx) => {
+                if let Err(e) = ready_tx.send(true) {
+                    error!("failed to update podwatcher ready status: {e:?}")
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,629 @@
+use std::collections::{
+    HashMap,
+    HashSet,
+};
+use std::sync::atomic::{
+    AtomicUsize,
+    Ordering,
+};
+use std::sync::{
+    Arc,
+    Mutex,
+};
+
+use clockabilly::mock::MockUtcClock;
+use futures::{
+    stream,
+    StreamExt,
+};
+use httpmock::prelude::*;
+use kube::api::{
+    DynamicObject,
+    TypeMeta,
+};
+use kube::runtime::watcher::Event;
+use kube::ResourceExt;
+use serde_json::json;
+use sk_core::k8s::{
+    ApiSet,
+    CronJobState,
+    HpaState,
+    JobState,
+    KubeResourceExt,
+    NodeState,
+    PodLifecycleData,
+    GVK,
+};
+use sk_core::prelude::*;
+
+use super::*;
+use crate::watchers::dyn_obj_watcher::build_watcher_config;
+use crate::watchers::DynObjWatcher;
+use crate::{
+    TraceStorable,
+    TraceStore,
+    TracerConfig,
+    TrackedObjectConfig,
+};
+
+fn test_dyn_obj(api_version: &str, kind: &str, name: &str) -> DynamicObject {
+    DynamicObject {
+        metadata: metav1::ObjectMeta {
+            namespace: Some(TEST_NAMESPACE.into()),
+            name: Some(name.into()),
+            ..Default::default()
+        },
+        types: Some(TypeMeta { api_version: api_version.into(), kind: kind.into() }),
+        data: json!({"spec": {}}),
+    }
+}
+
+#[rstest]
+fn test_build_watcher_config_default() {
+    let watcher_config = build_watcher_config(&Default::default());
+    assert_eq!(watcher_config.label_selector, None);
+    assert_eq!(watcher_config.field_selector, None);
+}
+
+#[rstest]
+fn test_build_watcher_config_selectors() {
+    let config = TrackedObjectConfig {
+        label_selector: Some("app=foo,tier!=cache".into()),
+        field_selector: Some("metadata.namespace!=kube-system".into()),
+        ..Default::default()
+    };
+    let watcher_config = build_watcher_config(&config);
+    assert_eq!(watcher_config.label_selector, Some("app=foo,tier!=cache".into()));
+    assert_eq!(watcher_config.field_selector, Some("metadata.namespace!=kube-system".into()));
+}
+
+#[rstest]
+#[traced_test]
+fn test_handle_obj_event_drops_untracked_kind() {
+    let store = Arc::new(Mutex::new(TraceStore::new(Default::default())));
+    let tracked_gvks = HashSet::from([GVK::new("apps", "v1", "Deployment")]);
+    let mut w =
+        DynObjWatcher::new_from_parts(stream::empty().boxed(), store.clone(), tracked_gvks, MockUtcClock::boxed(0));
+
+    let depl = test_dyn_obj("apps/v1", "Deployment", "depl");
+    let pod = test_dyn_obj("v1", "Pod", "pod");
+    let mut untyped = test_dyn_obj("v1", "Pod", "untyped");
+    untyped.types = None;
+
+    w.handle_obj_event(Event::Applied(pod.clone()), 1);
+    w.handle_obj_event(Event::Applied(untyped), 2);
+    w.handle_obj_event(Event::Restarted(vec![depl, pod]), 3);
+
+    let store = store.lock().unwrap();
+    assert!(store.has_obj(&format!("{TEST_NAMESPACE}/depl")));
+    assert!(!store.has_obj(&format!("{TEST_NAMESPACE}/pod")));
+    assert!(!store.has_obj(&format!("{TEST_NAMESPACE}/untyped")));
+    assert_eq!(w.dropped_count("v1.Pod"), 2);
+    assert_eq!(w.dropped_count("<unknown>"), 1);
+    assert_eq!(w.dropped_count("apps/v1.Deployment"), 0);
+}
+
+#[rstest]
+fn test_handle_obj_event_ignores_metadata_only_updates() {
+    let store = Arc::new(Mutex::new(TraceStore::new(Default::default())));
+    let mut w = DynObjWatcher::new_from_parts(
+        stream::empty().boxed(),
+        store.clone(),
+        HashSet::from([GVK::new("apps", "v1", "Deployment")]),
+        MockUtcClock::boxed(0),
+    );
+
+    let depl: DynamicObject = serde_json::from_value(json!({
+        "apiVersion": "apps/v1",
+        "kind": "Deployment",
+        "metadata": {"namespace": TEST_NAMESPACE, "name": TEST_DEPLOYMENT},
+        "spec": {"replicas": 1},
+    }))
+    .unwrap();
+    let mut finalized = depl.clone();
+    finalized.metadata.finalizers = Some(vec!["example.com/cleanup".into()]);
+    finalized.metadata.resource_version = Some("1234".into());
+    finalized.metadata.managed_fields = Some(vec![Default::default()]);
+    let mut scaled = finalized.clone();
+    scaled.data["spec"]["replicas"] = json!(42);
+
+    w.handle_obj_event(Event::Applied(depl), 1);
+    w.handle_obj_event(Event::Applied(finalized), 2);
+    w.handle_obj_event(Event::Applied(scaled), 3);
+
+    let store = store.lock().unwrap();
+    let applied_ts: Vec<_> = store
+        .iter()
+        .filter(|(evt, _)| !evt.applied_objs.is_empty())
+        .map(|(evt, _)| evt.ts)
+        .collect();
+    assert_eq!(applied_ts, vec![1, 3]);
+}
+
+#[rstest]
+fn test_handle_obj_event_applies_transforms() {
+    let gvk = GVK::new("apps", "v1", "Deployment");
+    let config: TracerConfig = serde_yaml::from_str(
+        "trackedObjects:
+  apps/v1.Deployment:
+    transforms:
+      - path: /metadata/annotations/example.com~1build-id
+        op: strip
+      - path: /spec/template/spec/containers/*/image
+        op: rewrite
+        pattern: ^registry\\.internal\\.corp/
+        replacement: docker.io/
+",
+    )
+    .unwrap();
+    let store = Arc::new(Mutex::new(TraceStore::new(config)));
+    let mut w = DynObjWatcher::new_from_parts(
+        stream::empty().boxed(),
+        store.clone(),
+        HashSet::from([gvk]),
+        MockUtcClock::boxed(0),
+    );
+
+    let mut depl = test_dyn_obj("apps/v1", "Deployment", "depl");
+    depl.annotations_mut().insert("example.com/build-id".into(), "1234".into());
+    depl.annotations_mut().insert("example.com/owner".into(), "me".into());
+    depl.data = json!({"spec": {"template": {"spec": {"containers": [
+        {"name": "app", "image": "registry.internal.corp/app:v1"},
+        {"name": "sidecar", "image": "quay.io/sidecar:v2"},
+    ]}}}});
+    w.handle_obj_event(Event::Applied(depl), 1);
+
+    let store = store.lock().unwrap();
+    let obj = store.objects().next().unwrap();
+    assert_eq!(obj.annotations().keys().collect::<Vec<_>>(), vec!["example.com/owner"]);
+    assert_eq!(
+        obj.data.pointer("/spec/template/spec/containers").unwrap(),
+        &json!([
+            {"name": "app", "image": "docker.io/app:v1"},
+            {"name": "sidecar", "image": "quay.io/sidecar:v2"},
+        ])
+    );
+}
+
+#[rstest]
+#[case::v1(test_hpa_v1(), GVK::new("autoscaling", "v1", "HorizontalPodAutoscaler"), 5, 50)]
+#[case::v2(test_hpa_v2(), GVK::new("autoscaling", "v2", "HorizontalPodAutoscaler"), 4, 0)]
+fn test_handle_obj_event_records_hpa_state(
+    #[case] hpa: DynamicObject,
+    #[case] gvk: GVK,
+    #[case] desired_replicas: i32,
+    #[case] cpu_target: i32,
+) {
+    let store = Arc::new(Mutex::new(TraceStore::new(Default::default())));
+    let mut w = DynObjWatcher::new_from_parts(
+        stream::empty().boxed(),
+        store.clone(),
+        HashSet::from([gvk]),
+        MockUtcClock::boxed(0),
+    );
+
+    w.handle_obj_event(Event::Restarted(vec![hpa]), 1);
+
+    let store = store.lock().unwrap();
+    let obj = store.objects().next().unwrap();
+    let state: HpaState = serde_json::from_str(&obj.annotations()[HPA_STATE_ANNOTATION_KEY]).unwrap();
+    assert_eq!(state.current_replicas, Some(3));
+    assert_eq!(state.desired_replicas, Some(desired_replicas));
+    assert_eq!(state.metrics.len(), 1);
+    assert_eq!(state.current_metrics.len(), 1);
+    let target = &state.metrics[0].resource.as_ref().unwrap().target;
+    assert_eq!(target.average_utilization.unwrap_or_default(), cpu_target);
+}
+
+#[rstest]
+fn test_handle_obj_event_records_job_state(test_completed_job: DynamicObject, test_cronjob: DynamicObject) {
+    let store = Arc::new(Mutex::new(TraceStore::new(Default::default())));
+    let mut w = DynObjWatcher::new_from_parts(
+        stream::empty().boxed(),
+        store.clone(),
+        HashSet::from([GVK::new("batch", "v1", "Job"), GVK::new("batch", "v1", "CronJob")]),
+        MockUtcClock::boxed(0),
+    );
+
+    w.handle_obj_event(Event::Restarted(vec![test_completed_job, test_cronjob]), 1);
+
+    let store = store.lock().unwrap();
+    let objs: HashMap<_, _> = store.objects().map(|obj| (obj.name_any(), obj)).collect();
+
+    let job = &objs[TEST_JOB];
+    let state: JobState = serde_json::from_str(&job.annotations()[JOB_STATE_ANNOTATION_KEY]).unwrap();
+    assert_eq!(state.succeeded, Some(6));
+    assert_eq!(state.failed, Some(1));
+    assert_eq!(state.parallelism, Some(3));
+    assert!(state.is_complete());
+    assert!(!job.annotations().contains_key(CRONJOB_STATE_ANNOTATION_KEY));
+
+    let cj = &objs[TEST_CRONJOB];
+    let state: CronJobState = serde_json::from_str(&cj.annotations()[CRONJOB_STATE_ANNOTATION_KEY]).unwrap();
+    assert_eq!(state.schedule, "*/5 * * * *");
+    assert_eq!(state.last_schedule_ts, Some(1704067500));
+    assert!(!cj.annotations().contains_key(JOB_STATE_ANNOTATION_KEY));
+}
+
+#[rstest]
+fn test_handle_obj_event_records_job_completion(test_completed_job: DynamicObject) {
+    let store = Arc::new(Mutex::new(TraceStore::new(Default::default())));
+    let mut w = DynObjWatcher::new_from_parts(
+        stream::empty().boxed(),
+        store.clone(),
+        HashSet::from([GVK::new("batch", "v1", "Job")]),
+        MockUtcClock::boxed(0),
+    );
+
+    // Finishing doesn't change the job's spec, but it should still show up as a new version
+    let mut running_job = test_completed_job.clone();
+    running_job.data["status"]["succeeded"] = json!(4);
+    running_job.data["status"]["completionTime"] = serde_json::Value::Null;
+    w.handle_obj_event(Event::Applied(running_job.clone()), 1);
+    w.handle_obj_event(Event::Applied(running_job), 2);
+    w.handle_obj_event(Event::Applied(test_completed_job.clone()), 3);
+
+    let store = store.lock().unwrap();
+    let applied_ts: Vec<_> = store
+        .iter()
+        .filter(|(evt, _)| !evt.applied_objs.is_empty())
+        .map(|(evt, _)| evt.ts)
+        .collect();
+    assert_eq!(applied_ts, vec![1, 3]);
+
+    let lifecycle = store.lookup_pod_lifecycle(&test_completed_job.namespaced_name(), 1234, 0);
+    assert_eq!(lifecycle, PodLifecycleData::Finished(1704067200, 1704067500, None));
+}
+
+#[rstest]
+fn test_handle_obj_event_records_node_state() {
+    let store = Arc::new(Mutex::new(TraceStore::new(Default::default())));
+    let mut w = DynObjWatcher::new_from_parts(
+        stream::empty().boxed(),
+        store.clone(),
+        HashSet::from([GVK::new("", "v1", "Node")]),
+        MockUtcClock::boxed(0),
+    );
+
+    // Heartbeats shouldn't count as a new version, but the node going NotReady should
+    let mut heartbeat = test_node(true);
+    heartbeat.data["status"]["conditions"][1]["lastHeartbeatTime"] = json!("2024-01-01T00:01:00Z");
+    w.handle_obj_event(Event::Restarted(vec![test_node(true)]), 1);
+    w.handle_obj_event(Event::Applied(heartbeat), 2);
+    w.handle_obj_event(Event::Applied(test_node(false)), 3);
+
+    let store = store.lock().unwrap();
+    let applied_ts: Vec<_> = store
+        .iter()
+        .filter(|(evt, _)| !evt.applied_objs.is_empty())
+        .map(|(evt, _)| evt.ts)
+        .collect();
+    assert_eq!(applied_ts, vec![1, 3]);
+
+    let node = store.objects().find(|obj| obj.name_any() == TEST_NODE).unwrap();
+    let state: NodeState = serde_json::from_str(&node.annotations()[NODE_STATE_ANNOTATION_KEY]).unwrap();
+    assert_eq!(state.allocatable["cpu"].0, "3920m");
+    assert_eq!(state.allocatable["memory"].0, "14Gi");
+    assert!(!state.is_ready());
+    assert_eq!(node.data["spec"]["taints"][0]["key"], "dedicated");
+    assert_eq!(node.labels()["topology.kubernetes.io/zone"], "us-west-2a");
+}
+
+#[rstest]
+fn test_handle_obj_event_resource_quota() {
+    let store = Arc::new(Mutex::new(TraceStore::new(Default::default())));
+    let mut w = DynObjWatcher::new_from_parts(
+        stream::empty().boxed(),
+        store.clone(),
+        HashSet::from([GVK::new("", "v1", "ResourceQuota")]),
+        MockUtcClock::boxed(0),
+    );
+
+    let quota = |used: &str| {
+        let mut q = test_dyn_obj("v1", "ResourceQuota", "the-quota");
+        q.data = json!({
+            "spec": {"hard": {"pods": "10", "requests.cpu": "4"}},
+            "status": {"hard": {"pods": "10", "requests.cpu": "4"}, "used": {"pods": used, "requests.cpu": "1"}},
+        });
+        q
+    };
+
+    // Pods coming and going shouldn't show up in the trace
+    w.handle_obj_event(Event::Restarted(vec![quota("3")]), 1);
+    w.handle_obj_event(Event::Applied(quota("5")), 2);
+
+    let store = store.lock().unwrap();
+    assert_eq!(store.iter().filter(|(evt, _)| !evt.applied_objs.is_empty()).count(), 1);
+
+    let q = store.objects().find(|obj| obj.name_any() == "the-quota").unwrap();
+    assert_eq!(q.data["spec"]["hard"]["requests.cpu"], "4");
+    assert_eq!(q.data["status"]["hard"]["pods"], "10");
+    assert!(q.data["status"].get("used").is_none());
+}
+
+fn test_pod_with_labels(ns: &str, name: &str, app: &str) -> DynamicObject {
+    let mut pod = test_dyn_obj("v1", "Pod", name);
+    pod.metadata.namespace = Some(ns.into());
+    pod.metadata.labels = Some([("app".into(), app.into())].into());
+    pod
+}
+
+fn test_network_policy(name: &str, pod_selector: serde_json::Value) -> DynamicObject {
+    let mut policy = test_dyn_obj("networking.k8s.io/v1", "NetworkPolicy", name);
+    policy.data = json!({"spec": {"podSelector": pod_selector, "policyTypes": ["Ingress"]}});
+    policy
+}
+
+#[rstest]
+fn test_handle_obj_event_resolves_network_policies() {
+    let store = Arc::new(Mutex::new(TraceStore::new(Default::default())));
+    let tracked_gvks = HashSet::from([GVK::new("networking.k8s.io", "v1", "NetworkPolicy")]);
+    let mut w =
+        DynObjWatcher::new_from_parts(stream::empty().boxed(), store.clone(), tracked_gvks, MockUtcClock::boxed(0));
+
+    // The policies show up before we've listed the pods, so they have to wait for them
+    w.handle_obj_event(
+        Event::Restarted(vec![
+            test_network_policy("web-only", json!({"matchLabels": {"app": "web"}})),
+            test_network_policy("everything", json!({})),
+        ]),
+        1,
+    );
+    assert!(!store.lock().unwrap().has_obj(&format!("{TEST_NAMESPACE}/web-only")));
+
+    w.handle_obj_event(
+        Event::Restarted(vec![
+            test_pod_with_labels(TEST_NAMESPACE, "web-1", "web"),
+            test_pod_with_labels(TEST_NAMESPACE, "web-2", "web"),
+            test_pod_with_labels(TEST_NAMESPACE, "db-1", "db"),
+            test_pod_with_labels("other-namespace", "web-3", "web"),
+        ]),
+        2,
+    );
+
+    // Pods added after the policy was recorded don't change what's in the trace
+    w.handle_obj_event(Event::Applied(test_pod_with_labels(TEST_NAMESPACE, "web-4", "web")), 3);
+    w.handle_obj_event(
+        Event::Applied(test_network_policy(
+            "db-only",
+            json!({"matchExpressions": [{"key": "app", "operator": "In", "values": ["db"]}]}),
+        )),
+        4,
+    );
+
+    let store = store.lock().unwrap();
+    let affected_pods: HashMap<_, _> = store
+        .objects()
+        .map(|obj| {
+            let pods: Vec<String> = serde_json::from_str(&obj.annotations()[AFFECTED_PODS_ANNOTATION_KEY]).unwrap();
+            (obj.name_any(), pods)
+        })
+        .collect();
+    assert_eq!(
+        affected_pods,
+        HashMap::from([
+            ("web-only".into(), vec![format!("{TEST_NAMESPACE}/web-1"), format!("{TEST_NAMESPACE}/web-2")]),
+            (
+                "everything".into(),
+                vec![
+                    format!("{TEST_NAMESPACE}/db-1"),
+                    format!("{TEST_NAMESPACE}/web-1"),
+                    format!("{TEST_NAMESPACE}/web-2")
+                ]
+            ),
+            ("db-only".into(), vec![format!("{TEST_NAMESPACE}/db-1")]),
+        ])
+    );
+    assert_eq!(store.start_ts(), Some(1));
+    assert!(!store.has_obj(&format!("{TEST_NAMESPACE}/web-1")));
+    assert_eq!(w.dropped_count("v1.Pod"), 0);
+}
+
+#[rstest]
+#[traced_test]
+fn test_dyn_obj_watcher_heartbeat() {
+    let store = Arc::new(Mutex::new(TraceStore::new(Default::default())));
+    let mut clock = MockUtcClock::new(0);
+    let mut w = DynObjWatcher::new_from_parts(stream::empty().boxed(), store, HashSet::new(), Box::new(clock.clone()));
+    let builds = Arc::new(AtomicUsize::new(0));
+    let b = builds.clone();
+    w.set_heartbeat(
+        60,
+        Box::new(move || {
+            b.fetch_add(1, Ordering::SeqCst);
+            stream::empty().boxed()
+        }),
+    );
+
+    // The watch should be re-established every 60 seconds, counting from the last refresh
+    for (advance, expected_builds) in [(30, 0), (30, 1), (59, 1), (1, 2), (150, 3), (0, 3)] {
+        clock.advance(advance);
+        w.check_heartbeat();
+        assert_eq!(builds.load(Ordering::SeqCst), expected_builds);
+    }
+}
+
+fn is_watch(req: &HttpMockRequest) -> bool {
+    req.query_params.as_ref().is_some_and(|qs| qs.iter().any(|(k, _)| k == "watch"))
+}
+
+#[rstest]
+#[tokio::test(flavor = "multi_thread")]
+async fn itest_dyn_obj_watcher_cluster_scoped() {
+    let (mut fake_apiserver, client) = make_fake_apiserver();
+    fake_apiserver
+        .handle(|when, then| {
+            when.method(GET).path("/apis/example.com/v1");
+            then.json_body(json!({
+                "kind": "APIResourceList",
+                "apiVersion": "v1",
+                "groupVersion": "example.com/v1",
+                "resources": [{
+                    "name": "widgets",
+                    "singularName": "widget",
+                    "namespaced": false,
+                    "kind": "Widget",
+                    "verbs": ["get", "list", "watch"],
+                }],
+            }));
+        })
+        .handle(|when, then| {
+            when.method(GET)
+                .path("/apis/example.com/v1/widgets")
+                .matches(|req| !is_watch(req));
+            then.json_body(json!({
+                "kind": "WidgetList",
+                "apiVersion": "example.com/v1",
+                "metadata": {"resourceVersion": "1"},
+                "items": [{
+                    "kind": "Widget",
+                    "apiVersion": "example.com/v1",
+                    "metadata": {"name": "the-widget"},
+                    "spec": {},
+                }],
+            }));
+        });
+    fake_apiserver.build();
+
+    let gvk = GVK::new("example.com", "v1", "Widget");
+    let tracked_objects = HashMap::from([(gvk, TrackedObjectConfig::default())]);
+    let store = Arc::new(Mutex::new(TraceStore::new(Default::default())));
+    let mut apiset = ApiSet::new(client);
+    let (w, ready_rx) = DynObjWatcher::new(store.clone(), &mut apiset, &tracked_objects, None)
+        .await
+        .unwrap();
+
+    let handle = tokio::spawn(w.start());
+    tokio::task::spawn_blocking(move || ready_rx.recv()).await.unwrap().unwrap();
+    handle.abort();
+
+    fake_apiserver.assert();
+    let store = store.lock().unwrap();
+    assert!(store.has_obj("the-widget"));
+    assert!(store.index.keys().all(|k| !k.contains('/')));
+}
+
+#[rstest]
+#[tokio::test(flavor = "multi_thread")]
+async fn itest_dyn_obj_watcher_namespaced() {
+    let (mut fake_apiserver, client) = make_fake_apiserver();
+    fake_apiserver
+        .handle(|when, then| {
+            when.method(GET).path("/apis/apps/v1");
+            then.json_body(apps_v1_discovery());
+        })
+        .handle(|when, then| {
+            when.method(GET)
+                .path(format!("/apis/apps/v1/namespaces/{TEST_NAMESPACE}/deployments"))
+                .matches(|req| !is_watch(req));
+            then.json_body(json!({
+                "kind": "DeploymentList",
+                "apiVersion": "apps/v1",
+                "metadata": {"resourceVersion": "1"},
+                "items": [{
+                    "kind": "Deployment",
+                    "apiVersion": "apps/v1",
+                    "metadata": {"namespace": TEST_NAMESPACE, "name": "the-depl"},
+                    "spec": {},
+                }],
+            }));
+        });
+    fake_apiserver.build();
+
+    let gvk = GVK::new("apps", "v1", "Deployment");
+    let tracked_objects = HashMap::from([(gvk, TrackedObjectConfig::default())]);
+    let store = Arc::new(Mutex::new(TraceStore::new(Default::default())));
+    let mut apiset = ApiSet::new(client);
+    let (w, ready_rx) = DynObjWatcher::new(store.clone(), &mut apiset, &tracked_objects, Some(TEST_NAMESPACE))
+        .await
+        .unwrap();
+
+    let handle = tokio::spawn(w.start());
+    tokio::task::spawn_blocking(move || ready_rx.recv()).await.unwrap().unwrap();
+    handle.abort();
+
+    fake_apiserver.assert();
+    assert!(store.lock().unwrap().has_obj(&format!("{TEST_NAMESPACE}/the-depl")));
+}
+
+#[rstest]
+#[tokio::test(flavor = "multi_thread")]
+async fn itest_dyn_obj_watcher_records_resolved_version() {
+    // example.com serves Widget at both v1beta1 and v1 (and prefers v1); the config asks for
+    // v1beta1, but with version preferences on, the objects are read (and so recorded) at v1
+    let widgets = |version: &str| {
+        json!({
+            "kind": "APIResourceList",
+            "apiVersion": "v1",
+            "groupVersion": format!("example.com/{version}"),
+            "resources": [{
+                "name": "widgets",
+                "singularName": "widget",
+                "namespaced": false,
+                "kind": "Widget",
+                "verbs": ["get", "list", "watch"],
+            }],
+        })
+    };
+    let (mut fake_apiserver, client) = make_fake_apiserver();
+    fake_apiserver
+        .handle(|when, then| {
+            when.method(GET).path("/apis");
+            then.json_body(json!({
+                "kind": "APIGroupList",
+                "apiVersion": "v1",
+                "groups": [{
+                    "name": "example.com",
+                    "versions": [
+                        {"groupVersion": "example.com/v1", "version": "v1"},
+                        {"groupVersion": "example.com/v1beta1", "version": "v1beta1"},
+                    ],
+                    "preferredVersion": {"groupVersion": "example.com/v1", "version": "v1"},
+                }],
+            }));
+        })
+        .handle(move |when, then| {
+            when.method(GET).path("/apis/example.com/v1");
+            then.json_body(widgets("v1"));
+        })
+        .handle(move |when, then| {
+            when.method(GET).path("/apis/example.com/v1beta1");
+            then.json_body(widgets("v1beta1"));
+        })
+        .handle(|when, then| {
+            when.method(GET)
+                .path("/apis/example.com/v1/widgets")
+                .matches(|req| !is_watch(req));
+            then.json_body(json!({
+                "kind": "WidgetList",
+                "apiVersion": "example.com/v1",
+                "metadata": {"resourceVersion": "1"},
+                "items": [{
+                    "kind": "Widget",
+                    "apiVersion": "example.com/v1",
+                    "metadata": {"name": "the-widget"},
+                    "spec": {},
+                }],
+            }));
+        });
+    fake_apiserver.build();
+
+    let gvk = GVK::new("example.com", "v1beta1", "Widget");
+    let tracked_objects = HashMap::from([(gvk, TrackedObjectConfig::default())]);
+    let store = Arc::new(Mutex::new(TraceStore::new(Default::default())));
+    let mut apiset = ApiSet::new(client).with_version_preferences(HashMap::new());
+    let (w, ready_rx) = DynObjWatcher::new(store.clone(), &mut apiset, &tracked_objects, None)
+        .await
+        .unwrap();
+
+    let handle = tokio::spawn(w.start());
+    tokio::task::spawn_blocking(move || ready_rx.recv()).await.unwrap().unwrap();
+    handle.abort();
+
+    let store = store.lock().unwrap();
+    let api_versions: Vec<_> = store
+        .objects()
+        .map(|obj| obj.types.as_ref().unwrap().api_version.clone())
+        .collect();
+    assert_eq!(api_versions, vec!["example.com/v1"]);
+}
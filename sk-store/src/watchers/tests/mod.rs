@@ -1,4 +1,6 @@
+mod dyn_obj_watcher_test;
 mod pod_watcher_test;
+mod store_channel_test;
 
 use rstest::*;
 use sk_core::k8s::testutils::*;
@@ -1,26 +1,43 @@
 use std::collections::HashMap;
+use std::sync::atomic::{
+    AtomicUsize,
+    Ordering,
+};
 use std::sync::{
     Arc,
     Mutex,
 };
 
 use clockabilly::mock::MockUtcClock;
+use clockabilly::DateTime;
 use futures::{
     stream,
     StreamExt,
 };
 use kube::runtime::watcher::Event;
+use kube::ResourceExt;
 use mockall::predicate;
+use serde_json::json;
 use sk_core::k8s::{
     ApiSet,
     KubeResourceExt,
+    OwnerChain,
     OwnersCache,
+    PodEnv,
+    PodExt,
     PodLifecycleData,
+    PodReadiness,
+    PodScheduling,
+    MASKED_ENV_VALUE,
 };
 use sk_core::prelude::*;
 
 use super::*;
 use crate::mock::MockTraceStore;
+use crate::{
+    TraceStore,
+    TracerConfig,
+};
 
 const START_TS: i64 = 1234;
 const END_TS: i64 = 5678;
@@ -30,6 +47,10 @@ fn clock() -> Box<MockUtcClock> {
     MockUtcClock::boxed(START_TS)
 }
 
+fn set_creation_ts(pod: &mut corev1::Pod, ts: i64) {
+    pod.metadata.creation_timestamp = Some(metav1::Time(DateTime::from_timestamp(ts, 0).unwrap()));
+}
+
 fn make_pod_watcher(
     ns_name: &str,
     clock: Box<MockUtcClock>,
@@ -49,6 +70,8 @@ fn make_pod_watcher(
             .returning(|_, _, _, _| Ok(()))
             .once();
     }
+    let _ = store.expect_record_pod_scheduling().returning(|_, _| true);
+    let _ = store.expect_record_pod_probes().returning(|_, _| true);
 
     let stored_pods = if let Some(sd) = stored_data {
         HashMap::from([(ns_name.into(), sd.clone())])
@@ -70,15 +93,35 @@ fn make_pod_watcher(
 #[rstest]
 #[traced_test]
 #[tokio::test]
-async fn test_handle_pod_event_applied_empty(test_pod: corev1::Pod, clock: Box<MockUtcClock>) {
+async fn test_handle_pod_event_applied_empty(mut test_pod: corev1::Pod, clock: Box<MockUtcClock>) {
     let ns_name = test_pod.namespaced_name();
-    let mut pw = make_pod_watcher(&ns_name, clock, None, None);
+    let expected_data = PodLifecycleData::Pending(START_TS);
+    let mut pw = make_pod_watcher(&ns_name, clock, None, Some(&expected_data));
 
+    set_creation_ts(&mut test_pod, START_TS);
     let mut evt = Event::Applied(test_pod.clone());
 
     pw.handle_pod_event(&mut evt).await;
 
-    assert_eq!(pw.get_owned_pod_lifecycle(&ns_name), None);
+    assert_eq!(pw.get_owned_pod_lifecycle(&ns_name).unwrap(), expected_data);
+}
+
+#[rstest]
+#[traced_test]
+#[tokio::test]
+async fn test_handle_pod_event_applied_pending_to_running(mut test_pod: corev1::Pod, clock: Box<MockUtcClock>) {
+    let ns_name = test_pod.namespaced_name();
+    let stored_data = PodLifecycleData::Pending(START_TS - 10);
+    let expected_data = PodLifecycleData::Running(START_TS);
+    let mut pw = make_pod_watcher(&ns_name, clock, Some(&stored_data), Some(&expected_data));
+
+    set_creation_ts(&mut test_pod, START_TS - 10);
+    add_running_container(&mut test_pod, START_TS);
+    let mut evt = Event::Applied(test_pod);
+
+    pw.handle_pod_event(&mut evt).await;
+
+    assert_eq!(pw.get_owned_pod_lifecycle(&ns_name).unwrap(), expected_data);
 }
 
 #[rstest]
@@ -97,6 +140,208 @@ async fn test_handle_pod_event_applied(mut test_pod: corev1::Pod, clock: Box<Moc
     assert_eq!(pw.get_owned_pod_lifecycle(&ns_name).unwrap(), expected_data);
 }
 
+#[rstest]
+#[traced_test]
+#[tokio::test]
+async fn test_handle_pod_event_applied_ready(mut test_pod: corev1::Pod, clock: Box<MockUtcClock>) {
+    let ns_name = test_pod.namespaced_name();
+    let mut store = MockTraceStore::new();
+    let _ = store.expect_record_pod_lifecycle().returning(|_, _, _, _| Ok(()));
+    let _ = store.expect_record_pod_scheduling().returning(|_, _| true);
+    let _ = store.expect_record_pod_probes().returning(|_, _| true);
+    let _ = store
+        .expect_record_pod_readiness()
+        .with(
+            predicate::eq(ns_name.clone()),
+            predicate::eq(PodReadiness { start_ts: START_TS, ready_ts: START_TS + 5 }),
+        )
+        .returning(|_, _| true)
+        .once();
+
+    let (_, client) = make_fake_apiserver();
+    let (mut pw, _) = PodWatcher::new_from_parts(
+        stream::empty().boxed(),
+        HashMap::new(),
+        OwnersCache::new(ApiSet::new(client)),
+        Arc::new(Mutex::new(store)),
+        clock,
+    );
+
+    add_running_container(&mut test_pod, START_TS);
+    add_ready_condition(&mut test_pod, START_TS + 5);
+    pw.handle_pod_event(&mut Event::Applied(test_pod.clone())).await;
+
+    // A later update with a different transition time (e.g., the pod flapped) doesn't get recorded
+    let conditions = test_pod.status.as_mut().unwrap().conditions.as_mut().unwrap();
+    conditions[0].last_transition_time = Some(metav1::Time(DateTime::from_timestamp(START_TS + 50, 0).unwrap()));
+    pw.handle_pod_event(&mut Event::Applied(test_pod)).await;
+}
+
+#[rstest]
+#[traced_test]
+#[tokio::test]
+async fn test_handle_pod_event_applied_env(mut test_pod_with_env: corev1::Pod, clock: Box<MockUtcClock>) {
+    let ns_name = test_pod_with_env.namespaced_name();
+    let expected_env = PodEnv::from([
+        ("init".into(), [("MODE".into(), "setup".into())].into()),
+        (
+            "app".into(),
+            [
+                ("LOG_LEVEL".into(), "debug".into()),
+                ("DB_PASSWORD".into(), MASKED_ENV_VALUE.into()),
+                ("API_CREDS".into(), MASKED_ENV_VALUE.into()),
+                ("CONFIG_PATH".into(), "<configMap cfg/path>".into()),
+                ("POD_NAME".into(), "<field metadata.name>".into()),
+            ]
+            .into(),
+        ),
+    ]);
+
+    let mut store = MockTraceStore::new();
+    let _ = store.expect_record_pod_lifecycle().returning(|_, _, _, _| Ok(()));
+    let _ = store.expect_record_pod_scheduling().returning(|_, _| true);
+    let _ = store.expect_record_pod_probes().returning(|_, _| true);
+    let _ = store
+        .expect_record_pod_env()
+        .with(predicate::eq(ns_name.clone()), predicate::eq(expected_env))
+        .returning(|_, _| true)
+        .once();
+
+    let (_, client) = make_fake_apiserver();
+    let (mut pw, _) = PodWatcher::new_from_parts(
+        stream::empty().boxed(),
+        HashMap::new(),
+        OwnersCache::new(ApiSet::new(client)),
+        Arc::new(Mutex::new(store)),
+        clock,
+    );
+    pw.set_env_capture(TracerConfig::default().sensitive_env_regex().unwrap());
+
+    // The env only gets recorded the first time we store data for the pod
+    set_creation_ts(&mut test_pod_with_env, START_TS - 10);
+    pw.handle_pod_event(&mut Event::Applied(test_pod_with_env.clone())).await;
+    add_running_container(&mut test_pod_with_env, START_TS);
+    pw.handle_pod_event(&mut Event::Applied(test_pod_with_env)).await;
+
+    assert_eq!(pw.get_owned_pod_lifecycle(&ns_name).unwrap(), PodLifecycleData::Running(START_TS));
+}
+
+#[rstest]
+#[case::affinity(test_pod_with_affinity(test_pod("the-pod".into())))]
+#[case::no_affinity(test_pod("the-pod".into()))]
+#[traced_test]
+#[tokio::test]
+async fn test_handle_pod_event_applied_scheduling(#[case] mut pod: corev1::Pod, clock: Box<MockUtcClock>) {
+    let ns_name = pod.namespaced_name();
+    let spec = pod.spec.clone().unwrap();
+    let expected = PodScheduling {
+        affinity: spec.affinity,
+        topology_spread_constraints: spec.topology_spread_constraints,
+    };
+
+    let mut store = MockTraceStore::new();
+    let _ = store.expect_record_pod_lifecycle().returning(|_, _, _, _| Ok(()));
+    let _ = store
+        .expect_record_pod_scheduling()
+        .with(predicate::eq(ns_name.clone()), predicate::eq(expected))
+        .returning(|_, _| true)
+        .once();
+    let _ = store.expect_record_pod_probes().returning(|_, _| true);
+
+    let (_, client) = make_fake_apiserver();
+    let (mut pw, _) = PodWatcher::new_from_parts(
+        stream::empty().boxed(),
+        HashMap::new(),
+        OwnersCache::new(ApiSet::new(client)),
+        Arc::new(Mutex::new(store)),
+        clock,
+    );
+
+    // Like the env, the scheduling constraints only get recorded the first time
+    set_creation_ts(&mut pod, START_TS - 10);
+    pw.handle_pod_event(&mut Event::Applied(pod.clone())).await;
+    add_running_container(&mut pod, START_TS);
+    pw.handle_pod_event(&mut Event::Applied(pod)).await;
+}
+
+#[rstest]
+#[traced_test]
+#[tokio::test]
+async fn test_handle_pod_event_applied_probes(mut test_pod_with_probes: corev1::Pod, clock: Box<MockUtcClock>) {
+    let ns_name = test_pod_with_probes.namespaced_name();
+    let expected = test_pod_with_probes.probes().unwrap();
+    assert_eq!(expected["app"].readiness.as_ref().unwrap().initial_delay_seconds, Some(15));
+
+    let mut store = MockTraceStore::new();
+    let _ = store.expect_record_pod_lifecycle().returning(|_, _, _, _| Ok(()));
+    let _ = store.expect_record_pod_scheduling().returning(|_, _| true);
+    let _ = store
+        .expect_record_pod_probes()
+        .with(predicate::eq(ns_name.clone()), predicate::eq(expected))
+        .returning(|_, _| true)
+        .once();
+
+    let (_, client) = make_fake_apiserver();
+    let (mut pw, _) = PodWatcher::new_from_parts(
+        stream::empty().boxed(),
+        HashMap::new(),
+        OwnersCache::new(ApiSet::new(client)),
+        Arc::new(Mutex::new(store)),
+        clock,
+    );
+
+    set_creation_ts(&mut test_pod_with_probes, START_TS - 10);
+    pw.handle_pod_event(&mut Event::Applied(test_pod_with_probes.clone())).await;
+    add_running_container(&mut test_pod_with_probes, START_TS);
+    pw.handle_pod_event(&mut Event::Applied(test_pod_with_probes)).await;
+}
+
+#[rstest]
+#[traced_test]
+#[tokio::test]
+async fn test_handle_pod_event_applied_env_not_captured(test_pod_with_env: corev1::Pod, clock: Box<MockUtcClock>) {
+    let ns_name = test_pod_with_env.namespaced_name();
+    let mut store = MockTraceStore::new();
+    let _ = store.expect_record_pod_lifecycle().returning(|_, _, _, _| Ok(()));
+    let _ = store.expect_record_pod_scheduling().returning(|_, _| true);
+    let _ = store.expect_record_pod_probes().returning(|_, _| true);
+    let _ = store.expect_record_pod_env().never();
+
+    let (_, client) = make_fake_apiserver();
+    let (mut pw, _) = PodWatcher::new_from_parts(
+        stream::empty().boxed(),
+        HashMap::new(),
+        OwnersCache::new(ApiSet::new(client)),
+        Arc::new(Mutex::new(store)),
+        clock,
+    );
+
+    let mut pod = test_pod_with_env;
+    add_running_container(&mut pod, START_TS);
+    pw.handle_pod_event(&mut Event::Applied(pod)).await;
+
+    assert_eq!(pw.get_owned_pod_lifecycle(&ns_name).unwrap(), PodLifecycleData::Running(START_TS));
+}
+
+#[rstest]
+#[traced_test]
+#[tokio::test]
+async fn test_handle_pod_event_applied_not_ready(mut test_pod: corev1::Pod, clock: Box<MockUtcClock>) {
+    let ns_name = test_pod.namespaced_name();
+    let expected_data = PodLifecycleData::Running(START_TS);
+
+    // The mock store doesn't expect any calls to record_pod_readiness, so this will panic if
+    // readiness data is recorded
+    let mut pw = make_pod_watcher(&ns_name, clock, None, Some(&expected_data));
+
+    add_running_container(&mut test_pod, START_TS);
+    add_ready_condition(&mut test_pod, START_TS + 5);
+    test_pod.status.as_mut().unwrap().conditions.as_mut().unwrap()[0].status = "False".into();
+    pw.handle_pod_event(&mut Event::Applied(test_pod)).await;
+
+    assert_eq!(pw.get_owned_pod_lifecycle(&ns_name).unwrap(), expected_data);
+}
+
 #[rstest]
 #[case::same_ts(START_TS)]
 #[case::diff_ts(5555)]
@@ -125,7 +370,7 @@ async fn test_handle_pod_event_applied_already_stored(
 async fn test_handle_pod_event_applied_running_to_finished(mut test_pod: corev1::Pod, clock: Box<MockUtcClock>) {
     let ns_name = test_pod.namespaced_name();
     let stored_data = PodLifecycleData::Running(START_TS);
-    let expected_data = PodLifecycleData::Finished(START_TS, END_TS);
+    let expected_data = PodLifecycleData::Finished(START_TS, END_TS, None);
     let mut pw = make_pod_watcher(&ns_name, clock, Some(&stored_data), Some(&expected_data));
 
     add_finished_container(&mut test_pod, START_TS, END_TS);
@@ -136,6 +381,23 @@ async fn test_handle_pod_event_applied_running_to_finished(mut test_pod: corev1:
     assert_eq!(pw.get_owned_pod_lifecycle(&ns_name).unwrap(), expected_data);
 }
 
+#[rstest]
+#[traced_test]
+#[tokio::test]
+async fn test_handle_pod_event_applied_running_to_oom_killed(mut test_pod: corev1::Pod, clock: Box<MockUtcClock>) {
+    let ns_name = test_pod.namespaced_name();
+    let stored_data = PodLifecycleData::Running(START_TS);
+    let expected_data = PodLifecycleData::Finished(START_TS, END_TS, Some("OOMKilled".into()));
+    let mut pw = make_pod_watcher(&ns_name, clock, Some(&stored_data), Some(&expected_data));
+
+    add_terminated_container(&mut test_pod, START_TS, END_TS, "OOMKilled");
+    let mut evt = Event::Applied(test_pod);
+
+    pw.handle_pod_event(&mut evt).await;
+
+    assert_eq!(pw.get_owned_pod_lifecycle(&ns_name).unwrap(), expected_data);
+}
+
 #[rstest]
 #[traced_test]
 #[tokio::test]
@@ -157,7 +419,7 @@ async fn test_handle_pod_event_applied_running_to_finished_wrong_start_ts(
 
 #[rstest]
 #[case::no_data(None)]
-#[case::mismatched_data(Some(&PodLifecycleData::Finished(1, 2)))]
+#[case::mismatched_data(Some(&PodLifecycleData::Finished(1, 2, None)))]
 #[traced_test]
 #[tokio::test]
 async fn test_handle_pod_event_deleted_no_update(
@@ -191,7 +453,7 @@ async fn test_handle_pod_event_deleted_finished(
     // If the watcher index says the pod is finished, we've already
     // recorded it in the store, so it really shouldn't matter what the clock says
     let ns_name = test_pod.namespaced_name();
-    let finished = PodLifecycleData::Finished(START_TS, END_TS);
+    let finished = PodLifecycleData::Finished(START_TS, END_TS, None);
     let stored_data = if old_finished { finished.clone() } else { PodLifecycleData::Running(START_TS) };
     let expected_data = if old_finished { None } else { Some(&finished) };
     clock.set(10000);
@@ -214,7 +476,7 @@ async fn test_handle_pod_event_deleted_running(mut test_pod: corev1::Pod, mut cl
     // expect the end_ts in the lifecycle data to match the current time
     let ns_name = test_pod.namespaced_name();
     let stored_data = PodLifecycleData::Running(START_TS);
-    let expected_data = PodLifecycleData::Finished(START_TS, END_TS);
+    let expected_data = PodLifecycleData::Finished(START_TS, END_TS, None);
     clock.set(END_TS);
 
     let mut pw = make_pod_watcher(&ns_name, clock, Some(&stored_data), Some(&expected_data));
@@ -235,7 +497,7 @@ async fn test_handle_pod_event_deleted_no_container_data(test_pod: corev1::Pod,
     // doesn't include any info about its containers, it just has metadata
     let ns_name = test_pod.namespaced_name();
     let stored_data = PodLifecycleData::Running(START_TS);
-    let expected_data = PodLifecycleData::Finished(START_TS, END_TS);
+    let expected_data = PodLifecycleData::Finished(START_TS, END_TS, None);
     clock.set(END_TS);
 
     let mut pw = make_pod_watcher(&ns_name, clock, Some(&stored_data), Some(&expected_data));
@@ -245,6 +507,36 @@ async fn test_handle_pod_event_deleted_no_container_data(test_pod: corev1::Pod,
     assert_eq!(pw.get_owned_pod_lifecycle(&ns_name), None);
 }
 
+#[rstest]
+#[case::with_pod(true)]
+#[case::without_pod(false)]
+#[traced_test]
+#[tokio::test]
+async fn test_handle_pod_event_deleted_pending(
+    mut test_pod: corev1::Pod,
+    mut clock: Box<MockUtcClock>,
+    #[case] with_pod: bool,
+) {
+    // A pod that never started running doesn't get a "finished" state; what we stored when it was
+    // pending is all there is, so nothing new should get written to the store
+    let ns_name = test_pod.namespaced_name();
+    let stored_data = PodLifecycleData::Pending(START_TS);
+    clock.set(END_TS);
+
+    let mut pw = make_pod_watcher(&ns_name, clock, Some(&stored_data), None);
+
+    set_creation_ts(&mut test_pod, START_TS);
+    if with_pod {
+        let mut evt = Event::Deleted(test_pod);
+        pw.handle_pod_event(&mut evt).await;
+    } else {
+        let mut evt = Event::Restarted(vec![]);
+        pw.handle_pod_event(&mut evt).await;
+    }
+
+    assert_eq!(pw.get_owned_pod_lifecycle(&ns_name), None);
+}
+
 #[rstest]
 #[traced_test]
 #[tokio::test]
@@ -273,7 +565,7 @@ async fn test_handle_pod_event_restarted(mut clock: Box<MockUtcClock>) {
             predicate::eq(pod_names[0].clone()),
             predicate::always(),
             predicate::eq(vec![]),
-            predicate::eq(PodLifecycleData::Finished(START_TS, END_TS)),
+            predicate::eq(PodLifecycleData::Finished(START_TS, END_TS, None)),
         )
         .returning(|_, _, _, _| Ok(()))
         .once();
@@ -295,17 +587,17 @@ async fn test_handle_pod_event_restarted(mut clock: Box<MockUtcClock>) {
             predicate::eq(pod_names[3].clone()),
             predicate::eq(None),
             predicate::eq(vec![]),
-            predicate::eq(PodLifecycleData::Finished(START_TS, clock_ts)),
+            predicate::eq(PodLifecycleData::Finished(START_TS, clock_ts, None)),
         )
         .returning(|_, _, _, _| Ok(()))
         .once();
 
     let (_, client) = make_fake_apiserver();
     let owners = HashMap::from([
-        (pod_names[0].clone(), vec![]),
-        (pod_names[1].clone(), vec![]),
+        (pod_names[0].clone(), OwnerChain::default()),
+        (pod_names[1].clone(), OwnerChain::default()),
         // pod2 doesn't belong in the cache so we can induce an error when looking up ownership
-        (pod_names[3].clone(), vec![]),
+        (pod_names[3].clone(), OwnerChain::default()),
     ]);
 
     let cache = OwnersCache::new_from_parts(ApiSet::new(client), owners);
@@ -315,9 +607,136 @@ async fn test_handle_pod_event_restarted(mut clock: Box<MockUtcClock>) {
     let mut evt = Event::Restarted(vec![update_pod0, update_pod1]);
 
     pw.handle_pod_event(&mut evt).await;
-    assert_eq!(pw.get_owned_pod_lifecycle(&pod_names[0]).unwrap(), PodLifecycleData::Finished(START_TS, END_TS));
+    assert_eq!(
+        pw.get_owned_pod_lifecycle(&pod_names[0]).unwrap(),
+        PodLifecycleData::Finished(START_TS, END_TS, None)
+    );
     assert_eq!(pw.get_owned_pod_lifecycle(&pod_names[1]).unwrap(), PodLifecycleData::Running(START_TS));
     assert_eq!(pw.get_owned_pod_lifecycle(&pod_names[2]), None); // pod2 should still be deleted from our index
     assert_eq!(pw.get_owned_pod_lifecycle(&pod_names[3]), None);
     assert!(rx.try_recv().unwrap()); // don't block this recv
 }
+
+#[rstest]
+#[traced_test]
+#[tokio::test]
+async fn test_handle_pod_event_restarted_concurrent_owners(clock: Box<MockUtcClock>) {
+    let (mut fake_apiserver, client) = make_fake_apiserver();
+    fake_apiserver
+        .handle(|when, then| {
+            when.path("/apis/apps/v1");
+            then.json_body(apps_v1_discovery());
+        })
+        .handle(|when, then| {
+            when.path("/apis/apps/v1/replicasets");
+            then.json_body(json!({
+                "metadata": {},
+                "items": [{"metadata": {"namespace": TEST_NAMESPACE, "name": "the-rs"}}],
+            }));
+        })
+        .build();
+
+    // Each new pod has its own owner; "old-pod" is already tracked and hasn't changed, so we don't
+    // need to know who owns it
+    let mut store = MockTraceStore::new();
+    let mut pods = vec![];
+    for i in 0..5 {
+        let rsref = metav1::OwnerReference {
+            api_version: "apps/v1".into(),
+            kind: "ReplicaSet".into(),
+            name: format!("rs-{i}"),
+            ..Default::default()
+        };
+        let mut pod = test_pod(format!("pod-{i}"));
+        pod.owner_references_mut().push(rsref.clone());
+        add_running_container(&mut pod, START_TS);
+        let _ = store
+            .expect_record_pod_lifecycle()
+            .with(
+                predicate::eq(pod.namespaced_name()),
+                predicate::always(),
+                predicate::eq(vec![rsref]),
+                predicate::eq(PodLifecycleData::Running(START_TS)),
+            )
+            .returning(|_, _, _, _| Ok(()))
+            .once();
+        pods.push(pod);
+    }
+    let _ = store.expect_record_pod_scheduling().returning(|_, _| true);
+    let _ = store.expect_record_pod_probes().returning(|_, _| true);
+
+    let mut old_pod = test_pod("old-pod".into());
+    add_running_container(&mut old_pod, START_TS);
+    let owned_pods = HashMap::from([(old_pod.namespaced_name(), PodLifecycleData::Running(START_TS))]);
+    pods.push(old_pod);
+
+    let (mut pw, _) = PodWatcher::new_from_parts(
+        stream::empty().boxed(),
+        owned_pods,
+        OwnersCache::new(ApiSet::new(client)),
+        Arc::new(Mutex::new(store)),
+        clock,
+    );
+    pw.set_owner_lookup_concurrency(3);
+
+    let mut evt = Event::Restarted(pods);
+    pw.handle_pod_event(&mut evt).await;
+
+    // The owners were all looked up ahead of time, so handling the pods didn't look them up again
+    fake_apiserver.assert_hits(1, 5);
+    for i in 0..5 {
+        let ns_name = format!("{TEST_NAMESPACE}/pod-{i}");
+        assert_eq!(pw.get_owned_pod_lifecycle(&ns_name), Some(&PodLifecycleData::Running(START_TS)));
+    }
+}
+
+#[rstest]
+#[tokio::test(flavor = "multi_thread")]
+async fn itest_pod_watcher_namespaced() {
+    let (mut fake_apiserver, client) = make_fake_apiserver();
+    fake_apiserver
+        .handle(|when, then| {
+            when.path(format!("/api/v1/namespaces/{TEST_NAMESPACE}/pods"))
+                .matches(|req| !req.query_params.as_ref().is_some_and(|qs| qs.iter().any(|(k, _)| k == "watch")));
+            then.json_body(json!({
+                "kind": "PodList",
+                "apiVersion": "v1",
+                "metadata": {"resourceVersion": "1"},
+                "items": [],
+            }));
+        })
+        .build();
+
+    let store = Arc::new(Mutex::new(TraceStore::new(Default::default())));
+    let (pw, ready_rx) = PodWatcher::new(client.clone(), store, ApiSet::new(client), Some(TEST_NAMESPACE));
+
+    let handle = tokio::spawn(pw.start());
+    tokio::task::spawn_blocking(move || ready_rx.recv()).await.unwrap().unwrap();
+    handle.abort();
+
+    fake_apiserver.assert();
+}
+
+#[rstest]
+#[traced_test]
+#[tokio::test]
+async fn test_pod_watcher_heartbeat(clock: Box<MockUtcClock>) {
+    let mut pw = make_pod_watcher(&format!("{TEST_NAMESPACE}/the-pod"), clock.clone(), None, None);
+    let builds = Arc::new(AtomicUsize::new(0));
+    let b = builds.clone();
+    pw.set_heartbeat(
+        60,
+        Box::new(move || {
+            b.fetch_add(1, Ordering::SeqCst);
+            stream::empty().boxed()
+        }),
+    );
+
+    // The watch should be re-established every 60 seconds, counting from the last refresh
+    let mut mock_clock = *clock;
+    for (advance, expected_builds) in [(30, 0), (30, 1), (59, 1), (1, 2), (150, 3), (0, 3)] {
+        mock_clock.advance(advance);
+        pw.check_heartbeat();
+        assert_eq!(builds.load(Ordering::SeqCst), expected_builds);
+    }
+}
@@ -0,0 +1,189 @@
+use std::sync::{
+    mpsc,
+    Arc,
+    Mutex,
+};
+
+use futures::poll;
+use mockall::predicate;
+use sk_core::k8s::{
+    PodEnv,
+    PodLifecycleData,
+    PodProbes,
+    PodReadiness,
+};
+use sk_core::prelude::*;
+
+use super::*;
+use crate::mock::MockTraceStore;
+use crate::watchers::store_channel::{
+    write_store_updates,
+    StoreSender,
+    StoreUpdate,
+};
+use crate::BackpressurePolicy;
+
+fn readiness_update(name: &str) -> StoreUpdate {
+    StoreUpdate::PodReadiness {
+        ns_name: format!("{TEST_NAMESPACE}/{name}"),
+        readiness: PodReadiness { start_ts: 1, ready_ts: 2 },
+    }
+}
+
+#[rstest]
+#[traced_test]
+#[tokio::test]
+async fn test_store_sender_drop_when_full() {
+    let (mut sender, mut rx) = StoreSender::new(2, BackpressurePolicy::Drop);
+    for name in ["pod1", "pod2", "pod3"] {
+        sender.send(readiness_update(name)).await;
+    }
+
+    assert_eq!(sender.dropped_count(), 1);
+    assert!(logs_contain("store channel is nearly full"));
+
+    // The first two updates made it through, the last one was dropped
+    let mut received = vec![];
+    while let Ok(StoreUpdate::PodReadiness { ns_name, .. }) = rx.try_recv() {
+        received.push(ns_name);
+    }
+    assert_eq!(received, vec![format!("{TEST_NAMESPACE}/pod1"), format!("{TEST_NAMESPACE}/pod2")]);
+}
+
+#[rstest]
+#[traced_test]
+#[tokio::test]
+async fn test_store_sender_block_when_full() {
+    let (mut sender, mut rx) = StoreSender::new(1, BackpressurePolicy::Block);
+    sender.send(readiness_update("pod1")).await;
+
+    {
+        let send = sender.send(readiness_update("pod2"));
+        tokio::pin!(send);
+        assert!(poll!(&mut send).is_pending());
+
+        // Once the store writer takes something off the channel, the blocked send can go through
+        rx.recv().await.unwrap();
+        send.await;
+    }
+
+    assert_eq!(sender.blocked_count(), 1);
+    assert_eq!(sender.dropped_count(), 0);
+    assert!(matches!(rx.recv().await.unwrap(), StoreUpdate::PodReadiness { ns_name, .. } if ns_name.ends_with("pod2")));
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_store_sender_never_drops_ready() {
+    let (mut sender, mut rx) = StoreSender::new(1, BackpressurePolicy::Drop);
+    let (ready_tx, _) = mpsc::channel();
+    sender.send(readiness_update("pod1")).await;
+
+    let send = sender.send(StoreUpdate::Ready(ready_tx));
+    tokio::pin!(send);
+    assert!(poll!(&mut send).is_pending());
+
+    rx.recv().await.unwrap();
+    send.await;
+    assert!(matches!(rx.recv().await.unwrap(), StoreUpdate::Ready(_)));
+}
+
+#[rstest]
+#[tokio::test]
+async fn test_write_store_updates() {
+    let ns_name = format!("{TEST_NAMESPACE}/pod1");
+    let lifecycle_data = PodLifecycleData::Running(1);
+
+    let mut store = MockTraceStore::new();
+    let _ = store
+        .expect_record_pod_lifecycle()
+        .with(
+            predicate::eq(ns_name.clone()),
+            predicate::always(),
+            predicate::always(),
+            predicate::eq(lifecycle_data.clone()),
+        )
+        .returning(|_, _, _, _| Ok(()))
+        .once();
+    let _ = store
+        .expect_record_pod_readiness()
+        .with(predicate::eq(ns_name.clone()), predicate::always())
+        .returning(|_, _| true)
+        .once();
+
+    let (mut sender, rx) = StoreSender::new(10, BackpressurePolicy::Block);
+    let (ready_tx, ready_rx) = mpsc::channel();
+    sender
+        .send(StoreUpdate::PodLifecycle {
+            ns_name: ns_name.clone(),
+            pod: None,
+            owners: vec![],
+            lifecycle_data,
+        })
+        .await;
+    sender.send(readiness_update("pod1")).await;
+    sender.send(StoreUpdate::Ready(ready_tx)).await;
+    drop(sender);
+
+    // The writer exits once the sender is gone and it's handled everything on the channel, and the
+    // ready signal only comes after the updates before it have been written
+    write_store_updates(rx, Arc::new(Mutex::new(store))).await;
+    assert!(ready_rx.try_recv().unwrap());
+}
+
+#[rstest]
+#[traced_test]
+#[tokio::test]
+async fn test_write_store_updates_lifecycle_failed() {
+    let (ns_name1, ns_name2) = (format!("{TEST_NAMESPACE}/pod1"), format!("{TEST_NAMESPACE}/pod2"));
+
+    let mut store = MockTraceStore::new();
+    let _ = store
+        .expect_record_pod_lifecycle()
+        .with(predicate::eq(ns_name1.clone()), predicate::always(), predicate::always(), predicate::always())
+        .returning(|_, _, _, _| Err(anyhow::anyhow!("fail")))
+        .once();
+    let _ = store
+        .expect_record_pod_lifecycle()
+        .with(predicate::eq(ns_name2.clone()), predicate::always(), predicate::always(), predicate::always())
+        .returning(|_, _, _, _| Ok(()))
+        .once();
+
+    // Same as when the watcher writes to the store directly, the pod whose lifecycle data failed
+    // doesn't get anything else recorded for it
+    let _ = store
+        .expect_record_pod_env()
+        .with(predicate::eq(ns_name2.clone()), predicate::always())
+        .returning(|_, _| true)
+        .once();
+    let _ = store
+        .expect_record_pod_probes()
+        .with(predicate::eq(ns_name2.clone()), predicate::always())
+        .returning(|_, _| true)
+        .once();
+
+    let (mut sender, rx) = StoreSender::new(10, BackpressurePolicy::Block);
+    for ns_name in [&ns_name1, &ns_name2] {
+        sender
+            .send(StoreUpdate::PodLifecycle {
+                ns_name: ns_name.clone(),
+                pod: None,
+                owners: vec![],
+                lifecycle_data: PodLifecycleData::Running(1),
+            })
+            .await;
+        sender
+            .send(StoreUpdate::PodEnv { ns_name: ns_name.clone(), env: PodEnv::new() })
+            .await;
+        sender
+            .send(StoreUpdate::PodProbes {
+                ns_name: ns_name.clone(),
+                probes: PodProbes::default(),
+            })
+            .await;
+    }
+    drop(sender);
+
+    write_store_updates(rx, Arc::new(Mutex::new(store))).await;
+    assert!(logs_contain(&format!("pod {ns_name1} lifecycle data could not be stored (1 failed so far)")));
+}
@@ -89,10 +89,13 @@ async fn run(args: Options) -> EmptyResult {
 
     let client = Client::try_default().await.expect("failed to create kube client");
     let mut apiset = ApiSet::new(client.clone());
+    if let Some(prefs) = &config.preferred_versions {
+        apiset = apiset.with_version_preferences(prefs.clone());
+    }
 
     let store = Arc::new(Mutex::new(TraceStore::new(config.clone())));
-    let (dyn_obj_watcher, _) = DynObjWatcher::new(store.clone(), &mut apiset, &config.tracked_objects).await?;
-    let (pod_watcher, _) = PodWatcher::new(client, store.clone(), apiset);
+    let (dyn_obj_watcher, _) = DynObjWatcher::new(store.clone(), &mut apiset, &config.tracked_objects, None).await?;
+    let (pod_watcher, _) = PodWatcher::new(client, store.clone(), apiset, None);
 
     let rkt_config = rocket::Config { port: args.server_port, ..Default::default() };
     let server = rocket::custom(&rkt_config)
@@ -23,14 +23,14 @@ async fn test_export_helper_cloud(trace_store: Arc<Mutex<TraceStore>>) {
         start_ts: 0,
         end_ts: 1,
         export_path: "s3://foo/bar".into(),
-        filters: Box::new(Default::default()),
+        filters: Box::default(),
     };
     let mut object_store = MockObjectStoreWrapper::new();
     object_store.expect_put().returning(|_| Ok(())).once();
     object_store.expect_scheme().returning(|| ObjectStoreScheme::AmazonS3).once();
 
     let res = export_helper(&req, &trace_store, &object_store).await.unwrap();
-    assert!(res.len() == 0);
+    assert!(res.is_empty());
 }
 
 #[rstest]
@@ -42,10 +42,10 @@ async fn test_export_helper_local(trace_store: Arc<Mutex<TraceStore>>) {
         start_ts: 0,
         end_ts: 1,
         export_path: export_path.into(),
-        filters: Box::new(Default::default()),
+        filters: Box::default(),
     };
     let object_store = SkObjectStore::new(export_path).unwrap();
 
     let res = export_helper(&req, &trace_store, &object_store).await.unwrap();
-    assert!(res.len() > 0);
+    assert!(!res.is_empty());
 }